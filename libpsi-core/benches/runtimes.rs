@@ -0,0 +1,83 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use libpsi_core::{library, QuantumCircuit, Runtime};
+use std::f64::consts::PI;
+
+const SIZES: [usize; 3] = [4, 8, 12];
+
+fn runtimes() -> Vec<(&'static str, Runtime)> {
+    vec![
+        ("basic", Runtime::BasicRT),
+        ("basic-mt", Runtime::BasicRTMT),
+        ("batched", Runtime::BatchedRT),
+        ("batched-mt", Runtime::BatchedRTMT),
+        ("simd", Runtime::SimdRT),
+        ("simd-mt", Runtime::SimdRTMT),
+        ("structure-aware", Runtime::StructureAwareRT),
+        ("structure-aware-mt", Runtime::StructureAwareMT),
+        ("optimal", Runtime::Custom(Runtime::optimal())),
+    ]
+}
+
+fn ghz(n: usize) -> QuantumCircuit {
+    let mut circuit = QuantumCircuit::new(n);
+    circuit.h(0);
+    for q in 0..n - 1 {
+        circuit.cnot(q, q + 1);
+    }
+    circuit
+}
+
+fn clifford_t(n: usize) -> QuantumCircuit {
+    let mut circuit = QuantumCircuit::new(n);
+    for layer in 0..4 {
+        for q in 0..n {
+            match (q + layer) % 4 {
+                0 => circuit.h(q),
+                1 => circuit.s(q),
+                2 => circuit.t(q),
+                _ => circuit.x(q),
+            };
+        }
+        for q in (layer % 2..n - 1).step_by(2) {
+            circuit.cnot(q, q + 1);
+        }
+    }
+    circuit
+}
+
+fn rotation_layers(n: usize) -> QuantumCircuit {
+    let mut circuit = QuantumCircuit::new(n);
+    for layer in 0..4 {
+        let theta = PI / (layer + 2) as f64;
+        for q in 0..n {
+            circuit.rx(q, theta).rz(q, theta / 2.0);
+        }
+        for q in 0..n - 1 {
+            circuit.cz(q, q + 1);
+        }
+    }
+    circuit
+}
+
+fn bench_family(c: &mut Criterion, family: &str, build: fn(usize) -> QuantumCircuit) {
+    let mut group = c.benchmark_group(family);
+    for n in SIZES {
+        let circuit = build(n);
+        for (name, runtime) in runtimes() {
+            group.bench_with_input(BenchmarkId::new(name, n), &circuit, |b, circuit| {
+                b.iter(|| runtime.compute(black_box(n), black_box(circuit.operations())))
+            });
+        }
+    }
+    group.finish();
+}
+
+fn runtimes_benchmark(c: &mut Criterion) {
+    bench_family(c, "ghz", ghz);
+    bench_family(c, "clifford_t", clifford_t);
+    bench_family(c, "rotation_layers", rotation_layers);
+    bench_family(c, "qft", library::qft);
+}
+
+criterion_group!(benches, runtimes_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,93 @@
+use super::circuit::defer_conditionals;
+use super::{GateOp, QuantumState, Runtime};
+
+/// A simulator that circuits can run on, built in or from another crate.
+///
+/// A run calls `init` once, then `apply` or `measure` for every operation in
+/// order (barriers and snapshots are skipped), then `finalize`. Backends are
+/// free to apply operations as they arrive or to buffer them, as the
+/// batching runtimes must.
+pub trait SimulatorBackend {
+    fn name(&self) -> String;
+
+    /// Starts a run on `num_qubits` qubits in |0…0⟩, discarding any previous
+    /// run.
+    fn init(&mut self, num_qubits: usize);
+
+    /// Applies a gate. Never called with measurements, barriers, snapshots
+    /// or conditionals, which `run` defers to controlled gates.
+    fn apply(&mut self, op: &GateOp);
+
+    /// Measures `qubit` into classical bit `clbit`. The built-in runtimes
+    /// defer measurement to sampling, so the default does nothing.
+    fn measure(&mut self, _qubit: usize, _clbit: usize) {}
+
+    /// Ends the run and returns the final state.
+    fn finalize(&mut self) -> QuantumState;
+
+    /// Runs `operations` from |0…0⟩ through the calls above.
+    fn run(&mut self, num_qubits: usize, operations: &[GateOp]) -> QuantumState {
+        self.init(num_qubits);
+        for op in defer_conditionals(operations).iter() {
+            match op {
+                GateOp::Measure(qubit, clbit) => self.measure(*qubit, *clbit),
+                GateOp::Barrier(_, _) | GateOp::Snapshot(_, _) => {}
+                _ => self.apply(op),
+            }
+        }
+        self.finalize()
+    }
+}
+
+/// A built-in `Runtime` as a backend. Operations are buffered and the
+/// runtime computes the state at `finalize`, so batching and fusion see the
+/// whole circuit.
+#[derive(Clone)]
+pub struct RuntimeBackend {
+    runtime: Runtime,
+    num_qubits: usize,
+    operations: Vec<GateOp>,
+}
+
+impl RuntimeBackend {
+    pub fn new(runtime: Runtime) -> Self {
+        RuntimeBackend {
+            runtime,
+            num_qubits: 0,
+            operations: Vec::new(),
+        }
+    }
+
+    pub fn runtime(&self) -> Runtime {
+        self.runtime
+    }
+}
+
+impl From<Runtime> for RuntimeBackend {
+    fn from(runtime: Runtime) -> Self {
+        RuntimeBackend::new(runtime)
+    }
+}
+
+impl SimulatorBackend for RuntimeBackend {
+    fn name(&self) -> String {
+        match self.runtime {
+            Runtime::Custom(config) => config.to_string(),
+            runtime => format!("{:?}", runtime),
+        }
+    }
+
+    fn init(&mut self, num_qubits: usize) {
+        self.num_qubits = num_qubits;
+        self.operations.clear();
+    }
+
+    fn apply(&mut self, op: &GateOp) {
+        self.operations.push(op.clone());
+    }
+
+    fn finalize(&mut self) -> QuantumState {
+        let operations = std::mem::take(&mut self.operations);
+        self.runtime.compute(self.num_qubits, &operations)
+    }
+}
@@ -1,9 +1,57 @@
-use super::{CustomGate, QuantumState, Runtime, RuntimeConfig};
-use crate::{format_amplitude, format_probability, Vector};
+use super::equivalence::{equivalent, unitary};
+use super::runtime::start_state;
+use super::shots;
+use super::{
+    Angle, ComputeJob, ComputeOutput, Counts, CustomGate, CustomGateBuilder, DensityMatrix,
+    GateDurations, NoiseModel, Parameter, QuantumState, Runtime, RuntimeConfig, Schedule,
+    SchedulePolicy, SimulatorBackend, SnapshotKind, SnapshotValue, StateTrace, TrajectorySimulator,
+};
+use crate::gates::SQRT_SWAP;
+use crate::{complex, format_amplitude, format_probability, Complex, Matrix, Vector};
 use core::fmt;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::f64::consts::PI;
 use std::sync::Arc;
 
+/// Builds a `QuantumCircuit` from `;`-separated operations, each a builder
+/// method name followed by its comma-separated arguments, e.g.
+/// `circuit!(3; h 0; cnot 0, 1; rz 2, PI / 4.0; measure 0 -> 0)`. Unknown
+/// gates and wrong argument counts or types fail to compile, as the builder
+/// call would.
+///
+/// A leading `qubits, bits` pair sizes the classical register too. `barrier`
+/// takes its qubits as a list, and `measure q -> c` needs `q` and `c` to be
+/// single tokens, so parenthesise expressions.
+#[macro_export]
+macro_rules! circuit {
+    ($qubits:expr, $bits:expr; $($ops:tt)*) => {{
+        let mut circuit = $crate::QuantumCircuit::with_classical($qubits, $bits);
+        $crate::circuit!(@ops circuit; $($ops)*);
+        circuit
+    }};
+    ($qubits:expr; $($ops:tt)*) => {{
+        let mut circuit = $crate::QuantumCircuit::new($qubits);
+        $crate::circuit!(@ops circuit; $($ops)*);
+        circuit
+    }};
+    (@ops $circuit:ident;) => {};
+    (@ops $circuit:ident; measure $qubit:tt -> $bit:tt $(; $($rest:tt)*)?) => {
+        $circuit.measure($qubit, $bit);
+        $crate::circuit!(@ops $circuit; $($($rest)*)?);
+    };
+    (@ops $circuit:ident; barrier $($qubit:expr),+ $(; $($rest:tt)*)?) => {
+        $circuit.barrier(&[$($qubit),+]);
+        $crate::circuit!(@ops $circuit; $($($rest)*)?);
+    };
+    (@ops $circuit:ident; $gate:ident $($arg:expr),* $(; $($rest:tt)*)?) => {
+        $circuit.$gate($($arg),*);
+        $crate::circuit!(@ops $circuit; $($($rest)*)?);
+    };
+}
+
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum GateOp {
     H(usize),
     X(usize),
@@ -25,14 +73,59 @@ pub enum GateOp {
     CNOT(usize, usize),
     CZ(usize, usize),
     SWAP(usize, usize),
+    ISwap(usize, usize),
+    SqrtSwap(usize, usize),
+    CY(usize, usize),
+    CH(usize, usize),
+    CSX(usize, usize),
+    ECR(usize, usize),
+    DCX(usize, usize),
     CRx(usize, usize, f64),
     CRy(usize, usize, f64),
     CRz(usize, usize, f64),
     CP(usize, usize, f64),
+    Rxx(usize, usize, f64),
+    Ryy(usize, usize, f64),
+    Rzz(usize, usize, f64),
     CCNOT(usize, usize, usize),
     CSWAP(usize, usize, usize),
+    /// X on the last qubit, applied only when every one of the control
+    /// qubits before it is |1⟩.
+    MCX(Vec<usize>, usize),
+    /// The single-qubit `gate` on `target`, applied only when every one of
+    /// `controls` is |1⟩.
+    MCU {
+        gate: Arc<CustomGate>,
+        controls: Vec<usize>,
+        target: usize,
+    },
     Measure(usize, usize),
+    /// Returns the qubit to |0⟩: measures it and flips it on a 1, without
+    /// recording the outcome.
+    Reset(usize),
     Custom(Arc<CustomGate>, Vec<usize>),
+    /// Visual separator across the given qubits, optionally titling the
+    /// section that follows it. Has no effect on the state, but fences the
+    /// kernel optimisations: no gate is fused or reordered across it.
+    Barrier(Vec<usize>, Option<String>),
+    /// Records a quantity under the given label when the runtime reaches
+    /// this point. Has no effect on the state.
+    Snapshot(String, SnapshotKind),
+    /// `op`, applied only if classical bit `classical_bit` reads `value`
+    /// when execution reaches it. See `QuantumCircuit::c_if`.
+    Conditional {
+        op: Box<GateOp>,
+        classical_bit: usize,
+        value: bool,
+    },
+    /// `op` with some of its angles left open, one entry of `symbols` per
+    /// angle of `op`: a `Parameter` read from the bindings, or `None` where
+    /// `op`'s own value stands. Open angles read 0 in `op` until bound; see
+    /// `QuantumCircuit::bind_parameters`.
+    Parameterized {
+        op: Box<GateOp>,
+        symbols: Vec<Option<Parameter>>,
+    },
 }
 
 impl GateOp {
@@ -59,13 +152,28 @@ impl GateOp {
             GateOp::CRy(_, _, _) => "CRy",
             GateOp::CRz(_, _, _) => "CRz",
             GateOp::CP(_, _, _) => "CP",
+            GateOp::Rxx(_, _, _) => "Rxx",
+            GateOp::Ryy(_, _, _) => "Ryy",
+            GateOp::Rzz(_, _, _) => "Rzz",
             GateOp::CNOT(_, _) => "CNOT",
             GateOp::CZ(_, _) => "CZ",
             GateOp::SWAP(_, _) => "SWAP",
+            GateOp::ISwap(_, _) => "iSWAP",
+            GateOp::SqrtSwap(_, _) => "√SWAP",
+            GateOp::CY(_, _) => "CY",
+            GateOp::CH(_, _) => "CH",
+            GateOp::CSX(_, _) => "C√X",
+            GateOp::ECR(_, _) => "ECR",
+            GateOp::DCX(_, _) => "DCX",
             GateOp::CCNOT(_, _, _) => "CCNOT",
             GateOp::CSWAP(_, _, _) => "CSWAP",
+            GateOp::MCX(_, _) => "MCX",
             GateOp::Measure(_, _) => "M",
-            GateOp::Custom(gate, _) => &gate.name,
+            GateOp::Reset(_) => "Reset",
+            GateOp::Custom(gate, _) | GateOp::MCU { gate, .. } => &gate.name,
+            GateOp::Barrier(_, _) => "Barrier",
+            GateOp::Snapshot(_, _) => "Snapshot",
+            GateOp::Conditional { op, .. } | GateOp::Parameterized { op, .. } => op.name(),
         }
     }
 
@@ -91,13 +199,34 @@ impl GateOp {
             GateOp::CNOT(c, t)
             | GateOp::CZ(c, t)
             | GateOp::SWAP(c, t)
+            | GateOp::ISwap(c, t)
+            | GateOp::SqrtSwap(c, t)
+            | GateOp::CY(c, t)
+            | GateOp::CH(c, t)
+            | GateOp::CSX(c, t)
+            | GateOp::ECR(c, t)
+            | GateOp::DCX(c, t)
             | GateOp::CRx(c, t, _)
             | GateOp::CRy(c, t, _)
             | GateOp::CRz(c, t, _)
-            | GateOp::CP(c, t, _) => vec![*c, *t],
+            | GateOp::CP(c, t, _)
+            | GateOp::Rxx(c, t, _)
+            | GateOp::Ryy(c, t, _)
+            | GateOp::Rzz(c, t, _) => vec![*c, *t],
             GateOp::CCNOT(c1, c2, t) | GateOp::CSWAP(c1, c2, t) => vec![*c1, *c2, *t],
-            GateOp::Measure(q, _) => vec![*q],
-            GateOp::Custom(_, targets) => targets.clone(),
+            GateOp::MCX(controls, t) => controls.iter().chain([t]).copied().collect(),
+            GateOp::MCU {
+                controls, target, ..
+            } => controls.iter().chain([target]).copied().collect(),
+            GateOp::Measure(q, _) | GateOp::Reset(q) => vec![*q],
+            GateOp::Custom(_, targets) | GateOp::Barrier(targets, _) => targets.clone(),
+            GateOp::Snapshot(_, SnapshotKind::Expectation(factors)) => {
+                factors.iter().map(|&(qubit, _)| qubit).collect()
+            }
+            GateOp::Snapshot(_, _) => vec![],
+            GateOp::Conditional { op, .. } | GateOp::Parameterized { op, .. } => {
+                op.quantum_targets()
+            }
         }
     }
 
@@ -108,15 +237,150 @@ impl GateOp {
         }
     }
 
+    /// Angle parameters of the operation, in constructor order.
+    pub fn params(&self) -> Vec<f64> {
+        match self {
+            GateOp::Rx(_, theta)
+            | GateOp::Ry(_, theta)
+            | GateOp::Rz(_, theta)
+            | GateOp::P(_, theta)
+            | GateOp::U1(_, theta)
+            | GateOp::CRx(_, _, theta)
+            | GateOp::CRy(_, _, theta)
+            | GateOp::CRz(_, _, theta)
+            | GateOp::CP(_, _, theta)
+            | GateOp::Rxx(_, _, theta)
+            | GateOp::Ryy(_, _, theta)
+            | GateOp::Rzz(_, _, theta) => vec![*theta],
+            GateOp::U2(_, phi, lambda) => vec![*phi, *lambda],
+            GateOp::U3(_, theta, phi, lambda) => vec![*theta, *phi, *lambda],
+            GateOp::Conditional { op, .. } | GateOp::Parameterized { op, .. } => op.params(),
+            _ => vec![],
+        }
+    }
+
     pub fn is_measurement(&self) -> bool {
         matches!(self, GateOp::Measure(_, _))
     }
 
+    pub fn is_reset(&self) -> bool {
+        matches!(self, GateOp::Reset(_))
+    }
+
+    pub fn is_barrier(&self) -> bool {
+        matches!(self, GateOp::Barrier(_, _))
+    }
+
+    pub fn is_snapshot(&self) -> bool {
+        matches!(self, GateOp::Snapshot(_, _))
+    }
+
+    /// Whether the operation is an MCX or MCU, which the statevector
+    /// runtimes apply in place rather than as a kernel.
+    pub fn is_multi_controlled(&self) -> bool {
+        matches!(self, GateOp::MCX(_, _) | GateOp::MCU { .. })
+    }
+
     pub fn is_custom(&self) -> bool {
         matches!(self, GateOp::Custom(_, _))
     }
 
+    pub fn is_conditional(&self) -> bool {
+        matches!(self, GateOp::Conditional { .. })
+    }
+
+    /// The `(classical_bit, value)` a conditional operation waits for.
+    pub fn condition(&self) -> Option<(usize, bool)> {
+        match self {
+            GateOp::Conditional {
+                classical_bit,
+                value,
+                ..
+            } => Some((*classical_bit, *value)),
+            _ => None,
+        }
+    }
+
+    /// Whether some angle is still an unbound `Parameter`.
+    pub fn is_parameterized(&self) -> bool {
+        match self {
+            GateOp::Parameterized { .. } => true,
+            GateOp::Conditional { op, .. } => op.is_parameterized(),
+            _ => false,
+        }
+    }
+
+    /// The open angles, one entry per angle as in `GateOp::Parameterized`,
+    /// or an empty list when every angle is a number.
+    pub fn symbols(&self) -> &[Option<Parameter>] {
+        match self {
+            GateOp::Parameterized { symbols, .. } => symbols,
+            GateOp::Conditional { op, .. } => op.symbols(),
+            _ => &[],
+        }
+    }
+
+    /// The operation with the parameters named in `bindings` replaced by
+    /// their values; a plain gate once none is left open.
+    pub fn bind_parameters(&self, bindings: &[(&str, f64)]) -> GateOp {
+        match self {
+            GateOp::Parameterized { op, symbols } => {
+                let mut params = op.params();
+                let mut open = Vec::with_capacity(symbols.len());
+                for (param, symbol) in params.iter_mut().zip(symbols) {
+                    let value = symbol.as_ref().and_then(|symbol| {
+                        let (_, value) =
+                            bindings.iter().find(|(name, _)| *name == symbol.name())?;
+                        Some(symbol.resolve(*value))
+                    });
+                    match value {
+                        Some(value) => {
+                            *param = value;
+                            open.push(None);
+                        }
+                        None => open.push(symbol.clone()),
+                    }
+                }
+                parameterized(op.with_params(&params), open)
+            }
+            GateOp::Conditional {
+                op,
+                classical_bit,
+                value,
+            } => GateOp::Conditional {
+                op: Box::new(op.bind_parameters(bindings)),
+                classical_bit: *classical_bit,
+                value: *value,
+            },
+            _ => self.clone(),
+        }
+    }
+
+    /// The same gate with its angles replaced by `params`, in `params` order.
+    pub(crate) fn with_params(&self, params: &[f64]) -> GateOp {
+        match *self {
+            GateOp::Rx(t, _) => GateOp::Rx(t, params[0]),
+            GateOp::Ry(t, _) => GateOp::Ry(t, params[0]),
+            GateOp::Rz(t, _) => GateOp::Rz(t, params[0]),
+            GateOp::P(t, _) => GateOp::P(t, params[0]),
+            GateOp::U1(t, _) => GateOp::U1(t, params[0]),
+            GateOp::U2(t, _, _) => GateOp::U2(t, params[0], params[1]),
+            GateOp::U3(t, _, _, _) => GateOp::U3(t, params[0], params[1], params[2]),
+            GateOp::CRx(c, t, _) => GateOp::CRx(c, t, params[0]),
+            GateOp::CRy(c, t, _) => GateOp::CRy(c, t, params[0]),
+            GateOp::CRz(c, t, _) => GateOp::CRz(c, t, params[0]),
+            GateOp::CP(c, t, _) => GateOp::CP(c, t, params[0]),
+            GateOp::Rxx(a, b, _) => GateOp::Rxx(a, b, params[0]),
+            GateOp::Ryy(a, b, _) => GateOp::Ryy(a, b, params[0]),
+            GateOp::Rzz(a, b, _) => GateOp::Rzz(a, b, params[0]),
+            _ => self.clone(),
+        }
+    }
+
     pub fn is_non_clifford(&self) -> bool {
+        if let GateOp::Conditional { op, .. } | GateOp::Parameterized { op, .. } = self {
+            return op.is_non_clifford();
+        }
         matches!(
             self,
             GateOp::T(_)
@@ -134,15 +398,601 @@ impl GateOp {
                 | GateOp::CRy(_, _, _)
                 | GateOp::CRz(_, _, _)
                 | GateOp::CP(_, _, _)
+                | GateOp::SqrtSwap(_, _)
+                | GateOp::CH(_, _)
+                | GateOp::CSX(_, _)
+                | GateOp::Rxx(_, _, _)
+                | GateOp::Ryy(_, _, _)
+                | GateOp::Rzz(_, _, _)
         )
     }
+
+    /// Returns the same operation acting on `map(q)` for every qubit `q`.
+    pub fn map_qubits(&self, map: impl Fn(usize) -> usize) -> GateOp {
+        match self {
+            GateOp::H(t) => GateOp::H(map(*t)),
+            GateOp::X(t) => GateOp::X(map(*t)),
+            GateOp::Y(t) => GateOp::Y(map(*t)),
+            GateOp::Z(t) => GateOp::Z(map(*t)),
+            GateOp::S(t) => GateOp::S(map(*t)),
+            GateOp::T(t) => GateOp::T(map(*t)),
+            GateOp::Sdg(t) => GateOp::Sdg(map(*t)),
+            GateOp::Tdg(t) => GateOp::Tdg(map(*t)),
+            GateOp::Sx(t) => GateOp::Sx(map(*t)),
+            GateOp::Sxdg(t) => GateOp::Sxdg(map(*t)),
+            GateOp::Rx(t, theta) => GateOp::Rx(map(*t), *theta),
+            GateOp::Ry(t, theta) => GateOp::Ry(map(*t), *theta),
+            GateOp::Rz(t, theta) => GateOp::Rz(map(*t), *theta),
+            GateOp::P(t, theta) => GateOp::P(map(*t), *theta),
+            GateOp::U1(t, lambda) => GateOp::U1(map(*t), *lambda),
+            GateOp::U2(t, phi, lambda) => GateOp::U2(map(*t), *phi, *lambda),
+            GateOp::U3(t, theta, phi, lambda) => GateOp::U3(map(*t), *theta, *phi, *lambda),
+            GateOp::CNOT(c, t) => GateOp::CNOT(map(*c), map(*t)),
+            GateOp::CZ(c, t) => GateOp::CZ(map(*c), map(*t)),
+            GateOp::SWAP(a, b) => GateOp::SWAP(map(*a), map(*b)),
+            GateOp::ISwap(a, b) => GateOp::ISwap(map(*a), map(*b)),
+            GateOp::SqrtSwap(a, b) => GateOp::SqrtSwap(map(*a), map(*b)),
+            GateOp::CY(c, t) => GateOp::CY(map(*c), map(*t)),
+            GateOp::CH(c, t) => GateOp::CH(map(*c), map(*t)),
+            GateOp::CSX(c, t) => GateOp::CSX(map(*c), map(*t)),
+            GateOp::ECR(a, b) => GateOp::ECR(map(*a), map(*b)),
+            GateOp::DCX(a, b) => GateOp::DCX(map(*a), map(*b)),
+            GateOp::CRx(c, t, theta) => GateOp::CRx(map(*c), map(*t), *theta),
+            GateOp::CRy(c, t, theta) => GateOp::CRy(map(*c), map(*t), *theta),
+            GateOp::CRz(c, t, theta) => GateOp::CRz(map(*c), map(*t), *theta),
+            GateOp::CP(c, t, theta) => GateOp::CP(map(*c), map(*t), *theta),
+            GateOp::Rxx(a, b, theta) => GateOp::Rxx(map(*a), map(*b), *theta),
+            GateOp::Ryy(a, b, theta) => GateOp::Ryy(map(*a), map(*b), *theta),
+            GateOp::Rzz(a, b, theta) => GateOp::Rzz(map(*a), map(*b), *theta),
+            GateOp::CCNOT(c1, c2, t) => GateOp::CCNOT(map(*c1), map(*c2), map(*t)),
+            GateOp::CSWAP(c, t1, t2) => GateOp::CSWAP(map(*c), map(*t1), map(*t2)),
+            GateOp::MCX(controls, t) => {
+                GateOp::MCX(controls.iter().map(|&c| map(c)).collect(), map(*t))
+            }
+            GateOp::MCU {
+                gate,
+                controls,
+                target,
+            } => GateOp::MCU {
+                gate: Arc::clone(gate),
+                controls: controls.iter().map(|&c| map(c)).collect(),
+                target: map(*target),
+            },
+            GateOp::Measure(q, c) => GateOp::Measure(map(*q), *c),
+            GateOp::Reset(q) => GateOp::Reset(map(*q)),
+            GateOp::Custom(gate, targets) => {
+                GateOp::Custom(Arc::clone(gate), targets.iter().map(|&t| map(t)).collect())
+            }
+            GateOp::Barrier(qubits, title) => {
+                GateOp::Barrier(qubits.iter().map(|&q| map(q)).collect(), title.clone())
+            }
+            GateOp::Snapshot(label, SnapshotKind::Expectation(factors)) => GateOp::Snapshot(
+                label.clone(),
+                SnapshotKind::Expectation(factors.iter().map(|&(q, p)| (map(q), p)).collect()),
+            ),
+            GateOp::Snapshot(label, kind) => GateOp::Snapshot(label.clone(), kind.clone()),
+            GateOp::Conditional {
+                op,
+                classical_bit,
+                value,
+            } => GateOp::Conditional {
+                op: Box::new(op.map_qubits(map)),
+                classical_bit: *classical_bit,
+                value: *value,
+            },
+            GateOp::Parameterized { op, symbols } => GateOp::Parameterized {
+                op: Box::new(op.map_qubits(map)),
+                symbols: symbols.clone(),
+            },
+        }
+    }
+
+    /// The inverse operation, `U†` for a gate `U`. Barriers and snapshots
+    /// are kept as they are; measurements have no inverse and panic.
+    pub fn adjoint(&self) -> GateOp {
+        match self {
+            GateOp::S(t) => GateOp::Sdg(*t),
+            GateOp::Sdg(t) => GateOp::S(*t),
+            GateOp::T(t) => GateOp::Tdg(*t),
+            GateOp::Tdg(t) => GateOp::T(*t),
+            GateOp::Sx(t) => GateOp::Sxdg(*t),
+            GateOp::Sxdg(t) => GateOp::Sx(*t),
+            GateOp::Rx(t, theta) => GateOp::Rx(*t, -theta),
+            GateOp::Ry(t, theta) => GateOp::Ry(*t, -theta),
+            GateOp::Rz(t, theta) => GateOp::Rz(*t, -theta),
+            GateOp::P(t, theta) => GateOp::P(*t, -theta),
+            GateOp::U1(t, lambda) => GateOp::U1(*t, -lambda),
+            GateOp::U2(t, phi, lambda) => GateOp::U2(*t, -lambda - PI, PI - phi),
+            GateOp::U3(t, theta, phi, lambda) => GateOp::U3(*t, -theta, -lambda, -phi),
+            GateOp::CRx(c, t, theta) => GateOp::CRx(*c, *t, -theta),
+            GateOp::CRy(c, t, theta) => GateOp::CRy(*c, *t, -theta),
+            GateOp::CRz(c, t, theta) => GateOp::CRz(*c, *t, -theta),
+            GateOp::CP(c, t, theta) => GateOp::CP(*c, *t, -theta),
+            GateOp::Rxx(a, b, theta) => GateOp::Rxx(*a, *b, -theta),
+            GateOp::Ryy(a, b, theta) => GateOp::Ryy(*a, *b, -theta),
+            GateOp::Rzz(a, b, theta) => GateOp::Rzz(*a, *b, -theta),
+            // Neither has a built-in inverse: iSWAP† as S†⊗S† then CZ then
+            // SWAP, which keeps it Clifford, and √SWAP† as a matrix.
+            GateOp::ISwap(a, b) => {
+                let iswap = CustomGateBuilder::new("iSWAP", 2)
+                    .s(0)
+                    .s(1)
+                    .cz(0, 1)
+                    .swap(0, 1);
+                GateOp::Custom(Arc::new(iswap.build().adjoint()), vec![*a, *b])
+            }
+            GateOp::SqrtSwap(a, b) => {
+                let sqrt_swap = CustomGate::from_matrix("√SWAP", SQRT_SWAP.matrix.clone());
+                GateOp::Custom(Arc::new(sqrt_swap.adjoint()), vec![*a, *b])
+            }
+            GateOp::CSX(c, t) => GateOp::Sxdg(*t).controlled(*c),
+            // The CNOTs run the other way round.
+            GateOp::DCX(a, b) => GateOp::DCX(*b, *a),
+            GateOp::Custom(gate, targets) => {
+                GateOp::Custom(Arc::new(gate.adjoint()), targets.clone())
+            }
+            GateOp::MCU {
+                gate,
+                controls,
+                target,
+            } => GateOp::MCU {
+                gate: Arc::new(gate.adjoint()),
+                controls: controls.clone(),
+                target: *target,
+            },
+            GateOp::Conditional {
+                op,
+                classical_bit,
+                value,
+            } => GateOp::Conditional {
+                op: Box::new(op.adjoint()),
+                classical_bit: *classical_bit,
+                value: *value,
+            },
+            GateOp::Parameterized { op, symbols } => {
+                // Every adjoint angle is minus one original angle, shifted
+                // for U2.
+                let sources: &[(usize, f64)] = match **op {
+                    GateOp::U2(..) => &[(1, -PI), (0, PI)],
+                    GateOp::U3(..) => &[(0, 0.0), (2, 0.0), (1, 0.0)],
+                    _ => &[(0, 0.0)],
+                };
+                let symbols = sources
+                    .iter()
+                    .map(|&(i, shift)| symbols[i].clone().map(|symbol| -symbol + shift))
+                    .collect();
+                GateOp::Parameterized {
+                    op: Box::new(op.adjoint()),
+                    symbols,
+                }
+            }
+            GateOp::Measure(q, c) => {
+                panic!("measurement of qubit {} into bit {} has no inverse", q, c)
+            }
+            GateOp::Reset(q) => panic!("reset of qubit {} has no inverse", q),
+            GateOp::H(_)
+            | GateOp::X(_)
+            | GateOp::Y(_)
+            | GateOp::Z(_)
+            | GateOp::CNOT(_, _)
+            | GateOp::CZ(_, _)
+            | GateOp::SWAP(_, _)
+            | GateOp::CY(_, _)
+            | GateOp::CH(_, _)
+            | GateOp::ECR(_, _)
+            | GateOp::CCNOT(_, _, _)
+            | GateOp::CSWAP(_, _, _)
+            | GateOp::MCX(_, _)
+            | GateOp::Barrier(_, _)
+            | GateOp::Snapshot(_, _) => self.clone(),
+        }
+    }
+
+    /// The operation applied only when qubit `control` is |1⟩. Gates with a
+    /// built-in controlled form map onto it (X to CNOT, Rz to CRz, CNOT to
+    /// CCNOT, CCNOT to MCX, an MCU to one more control, ...); any other
+    /// gate becomes a custom gate with the enlarged block-diagonal matrix.
+    /// Barriers and snapshots are kept as they are, measurements panic.
+    pub fn controlled(&self, control: usize) -> GateOp {
+        assert!(
+            !self.quantum_targets().contains(&control),
+            "{:?} already acts on control qubit {}",
+            self,
+            control
+        );
+        match self {
+            GateOp::Barrier(_, _) | GateOp::Snapshot(_, _) => self.clone(),
+            GateOp::Measure(q, c) => panic!(
+                "measurement of qubit {} into bit {} cannot be controlled",
+                q, c
+            ),
+            GateOp::Reset(q) => panic!("reset of qubit {} cannot be controlled", q),
+            GateOp::Conditional {
+                op,
+                classical_bit,
+                value,
+            } => GateOp::Conditional {
+                op: Box::new(op.controlled(control)),
+                classical_bit: *classical_bit,
+                value: *value,
+            },
+            GateOp::Parameterized { op, symbols } => {
+                let controlled = op.controlled(control);
+                assert!(
+                    controlled.params().len() == symbols.len(),
+                    "{:?} has no controlled form until its parameters are bound",
+                    self
+                );
+                GateOp::Parameterized {
+                    op: Box::new(controlled),
+                    symbols: symbols.clone(),
+                }
+            }
+            _ => controlled_on(control, true, self),
+        }
+    }
+
+    /// Number of qubits and parameters of the gate `from_name` builds for
+    /// `name`: an OpenQASM `qelib1.inc` name or a builder method name.
+    pub fn signature(name: &str) -> Option<(usize, usize)> {
+        let signature = match name {
+            "x" | "y" | "z" | "h" | "s" | "sdg" | "t" | "tdg" | "sx" | "sxdg" => (1, 0),
+            "rx" | "ry" | "rz" | "p" | "u1" => (1, 1),
+            "u2" => (1, 2),
+            "u3" | "u" | "U" => (1, 3),
+            "cx" | "CX" | "cnot" | "cz" | "swap" | "iswap" | "sqrt_swap" => (2, 0),
+            "cy" | "ch" | "csx" | "ecr" | "dcx" => (2, 0),
+            "crx" | "cry" | "crz" | "cp" | "cu1" | "rxx" | "ryy" | "rzz" => (2, 1),
+            "ccx" | "ccnot" | "toffoli" | "cswap" | "fredkin" => (3, 0),
+            "c3x" => (4, 0),
+            "c4x" => (5, 0),
+            _ => return None,
+        };
+        Some(signature)
+    }
+
+    /// The gate called `name` on `qubits` with `params`, for drivers that
+    /// only have the gate as a string. Fails for unknown names and for
+    /// qubit or parameter counts that do not match `signature`.
+    pub fn from_name(name: &str, qubits: &[usize], params: &[f64]) -> Result<GateOp, String> {
+        let (arity, num_params) =
+            Self::signature(name).ok_or_else(|| format!("unknown gate '{}'", name))?;
+        if (qubits.len(), params.len()) != (arity, num_params) {
+            return Err(format!(
+                "'{}' takes {} qubits and {} parameters",
+                name, arity, num_params
+            ));
+        }
+        let q = qubits;
+        let p = params;
+        let op = match name {
+            "x" => GateOp::X(q[0]),
+            "y" => GateOp::Y(q[0]),
+            "z" => GateOp::Z(q[0]),
+            "h" => GateOp::H(q[0]),
+            "s" => GateOp::S(q[0]),
+            "sdg" => GateOp::Sdg(q[0]),
+            "t" => GateOp::T(q[0]),
+            "tdg" => GateOp::Tdg(q[0]),
+            "sx" => GateOp::Sx(q[0]),
+            "sxdg" => GateOp::Sxdg(q[0]),
+            "rx" => GateOp::Rx(q[0], p[0]),
+            "ry" => GateOp::Ry(q[0], p[0]),
+            "rz" => GateOp::Rz(q[0], p[0]),
+            "p" => GateOp::P(q[0], p[0]),
+            "u1" => GateOp::U1(q[0], p[0]),
+            "u2" => GateOp::U2(q[0], p[0], p[1]),
+            "u3" | "u" | "U" => GateOp::U3(q[0], p[0], p[1], p[2]),
+            "cx" | "CX" | "cnot" => GateOp::CNOT(q[0], q[1]),
+            "cz" => GateOp::CZ(q[0], q[1]),
+            "swap" => GateOp::SWAP(q[0], q[1]),
+            "iswap" => GateOp::ISwap(q[0], q[1]),
+            "sqrt_swap" => GateOp::SqrtSwap(q[0], q[1]),
+            "cy" => GateOp::CY(q[0], q[1]),
+            "ch" => GateOp::CH(q[0], q[1]),
+            "csx" => GateOp::CSX(q[0], q[1]),
+            "ecr" => GateOp::ECR(q[0], q[1]),
+            "dcx" => GateOp::DCX(q[0], q[1]),
+            "crx" => GateOp::CRx(q[0], q[1], p[0]),
+            "cry" => GateOp::CRy(q[0], q[1], p[0]),
+            "crz" => GateOp::CRz(q[0], q[1], p[0]),
+            "cp" | "cu1" => GateOp::CP(q[0], q[1], p[0]),
+            "rxx" => GateOp::Rxx(q[0], q[1], p[0]),
+            "ryy" => GateOp::Ryy(q[0], q[1], p[0]),
+            "rzz" => GateOp::Rzz(q[0], q[1], p[0]),
+            "ccx" | "ccnot" | "toffoli" => GateOp::CCNOT(q[0], q[1], q[2]),
+            "cswap" | "fredkin" => GateOp::CSWAP(q[0], q[1], q[2]),
+            "c3x" | "c4x" => GateOp::MCX(q[..q.len() - 1].to_vec(), q[q.len() - 1]),
+            _ => unreachable!("'{}' has a signature but no operation", name),
+        };
+        Ok(op)
+    }
 }
 
+impl fmt::Debug for GateOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{:?}", self.name(), self.quantum_targets())?;
+        let params = self.params();
+        let symbols = self.symbols();
+        if !symbols.is_empty() {
+            let angles: Vec<String> = params
+                .iter()
+                .zip(symbols)
+                .map(|(param, symbol)| match symbol {
+                    Some(symbol) => symbol.to_string(),
+                    None => format!("{:?}", param),
+                })
+                .collect();
+            write!(f, "[{}]", angles.join(", "))?;
+        } else if !params.is_empty() {
+            write!(f, "{:?}", params)?;
+        }
+        let clbits = self.classical_targets();
+        if !clbits.is_empty() {
+            write!(f, " -> {:?}", clbits)?;
+        }
+        if let Some((bit, value)) = self.condition() {
+            write!(f, " if c{} == {}", bit, value as u8)?;
+        }
+        Ok(())
+    }
+}
+
+/// With the `serde` feature, serialises as its qubits, classical bits,
+/// operations, labels, noise model and initial state; computed results are
+/// left out. A custom gate is written out at every use.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "CircuitData"))]
 pub struct QuantumCircuit {
     num_qubits: usize,
     num_classical: usize,
     operations: Vec<GateOp>,
+    #[cfg_attr(feature = "serde", serde(skip))]
     computed_state: Option<QuantumState>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    snapshots: HashMap<String, SnapshotValue>,
+    qubit_labels: BTreeMap<usize, String>,
+    classical_labels: BTreeMap<usize, String>,
+    /// Indices released by `free_qubit`, reused lowest first.
+    free_qubits: BTreeSet<usize>,
+    noise: Option<NoiseModel>,
+    /// Amplitudes to start from instead of |0…0⟩.
+    initial_state: Option<Vec<Complex<f64>>>,
+}
+
+/// The serialised fields of a `QuantumCircuit`, checked before they become
+/// one.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct CircuitData {
+    num_qubits: usize,
+    num_classical: usize,
+    operations: Vec<GateOp>,
+    qubit_labels: BTreeMap<usize, String>,
+    classical_labels: BTreeMap<usize, String>,
+    free_qubits: BTreeSet<usize>,
+    noise: Option<NoiseModel>,
+    initial_state: Option<Vec<Complex<f64>>>,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<CircuitData> for QuantumCircuit {
+    type Error = String;
+
+    fn try_from(data: CircuitData) -> Result<Self, String> {
+        let (n, bits) = (data.num_qubits, data.num_classical);
+        for op in &data.operations {
+            let qubits = op.quantum_targets();
+            if let Some(&q) = qubits.iter().find(|&&q| q >= n) {
+                return Err(format!(
+                    "{:?}: qubit {} out of range for {} qubits",
+                    op, q, n
+                ));
+            }
+            if let GateOp::Custom(gate, targets) = op {
+                if targets.len() != gate.num_qubits {
+                    return Err(format!(
+                        "{:?}: '{}' acts on {} qubits",
+                        op, gate.name, gate.num_qubits
+                    ));
+                }
+            }
+            let condition = op.condition().map(|(bit, _)| bit);
+            if let Some(c) = op
+                .classical_targets()
+                .into_iter()
+                .chain(condition)
+                .find(|&c| c >= bits)
+            {
+                return Err(format!(
+                    "{:?}: classical bit {} out of range for {} bits",
+                    op, c, bits
+                ));
+            }
+        }
+        if let Some(q) = data.free_qubits.iter().find(|&&q| q >= n) {
+            return Err(format!("freed qubit {} out of range for {} qubits", q, n));
+        }
+        if let Some(state) = &data.initial_state {
+            if state.len() != 1 << n {
+                return Err(format!(
+                    "a {}-qubit circuit needs {} amplitudes, not {}",
+                    n,
+                    1usize << n,
+                    state.len()
+                ));
+            }
+        }
+        Ok(QuantumCircuit {
+            num_qubits: n,
+            num_classical: bits,
+            operations: data.operations,
+            computed_state: None,
+            snapshots: HashMap::new(),
+            qubit_labels: data.qubit_labels,
+            classical_labels: data.classical_labels,
+            free_qubits: data.free_qubits,
+            noise: data.noise,
+            initial_state: data.initial_state,
+        })
+    }
+}
+
+/// The qubit a condition on `classical_bit` reads after `before`: the one
+/// last measured into the bit, or `None` if the bit was never written and
+/// still reads 0. Fails if that qubit has been acted on since, as it then
+/// no longer holds the outcome.
+fn condition_source(before: &[GateOp], classical_bit: usize) -> Result<Option<usize>, String> {
+    let Some(index) = before
+        .iter()
+        .rposition(|op| matches!(op, GateOp::Measure(_, c) if *c == classical_bit))
+    else {
+        return Ok(None);
+    };
+    let GateOp::Measure(qubit, _) = before[index] else {
+        unreachable!()
+    };
+    let changed = before[index + 1..].iter().any(|op| {
+        !op.is_measurement()
+            && !op.is_barrier()
+            && !op.is_snapshot()
+            && op.quantum_targets().contains(&qubit)
+    });
+    if changed {
+        return Err(format!(
+            "qubit {} is acted on after being measured into bit {}",
+            qubit, classical_bit
+        ));
+    }
+    Ok(Some(qubit))
+}
+
+/// `op` with the angles in `angles`, wrapped in `GateOp::Parameterized` if
+/// any of them is a `Parameter`.
+fn parameterized(op: GateOp, angles: Vec<Option<Parameter>>) -> GateOp {
+    if angles.iter().all(Option::is_none) {
+        op
+    } else {
+        GateOp::Parameterized {
+            op: Box::new(op),
+            symbols: angles,
+        }
+    }
+}
+
+/// `op` applied only when `control` is `|value⟩`. The common gates map to
+/// their controlled variants; anything else becomes a custom gate with
+/// `control` as its leading qubit.
+fn controlled_on(control: usize, value: bool, op: &GateOp) -> GateOp {
+    if value {
+        match *op {
+            GateOp::X(t) => return GateOp::CNOT(control, t),
+            GateOp::Y(t) => return GateOp::CY(control, t),
+            GateOp::Z(t) => return GateOp::CZ(control, t),
+            GateOp::H(t) => return GateOp::CH(control, t),
+            GateOp::Sx(t) => return GateOp::CSX(control, t),
+            GateOp::Rx(t, theta) => return GateOp::CRx(control, t, theta),
+            GateOp::Ry(t, theta) => return GateOp::CRy(control, t, theta),
+            GateOp::Rz(t, theta) => return GateOp::CRz(control, t, theta),
+            GateOp::P(t, theta) | GateOp::U1(t, theta) => return GateOp::CP(control, t, theta),
+            GateOp::CNOT(c, t) => return GateOp::CCNOT(control, c, t),
+            GateOp::CCNOT(c1, c2, t) => return GateOp::MCX(vec![control, c1, c2], t),
+            GateOp::MCX(ref controls, t) => {
+                let controls = [control].into_iter().chain(controls.iter().copied());
+                return GateOp::MCX(controls.collect(), t);
+            }
+            GateOp::MCU {
+                ref gate,
+                ref controls,
+                target,
+            } => {
+                let controls = [control].into_iter().chain(controls.iter().copied());
+                return GateOp::MCU {
+                    gate: Arc::clone(gate),
+                    controls: controls.collect(),
+                    target,
+                };
+            }
+            GateOp::SWAP(a, b) => return GateOp::CSWAP(control, a, b),
+            _ => {}
+        }
+    }
+    let kernel =
+        Runtime::op_to_kernel(op).unwrap_or_else(|| panic!("{:?} has no unitary to control", op));
+    let dim = kernel.matrix.rows;
+    let offset = if value { dim } else { 0 };
+    let mut matrix = Matrix::new(2 * dim, 2 * dim, vec![complex!(0.0, 0.0); 4 * dim * dim]);
+    for i in 0..2 * dim {
+        matrix.set(i, i, complex!(1.0, 0.0));
+    }
+    for row in 0..dim {
+        for col in 0..dim {
+            matrix.set(offset + row, offset + col, kernel.matrix.get(row, col));
+        }
+    }
+    let name = format!("c{}-{}", if value { "" } else { "0" }, op.name());
+    let mut targets = vec![control];
+    targets.extend(kernel.targets);
+    GateOp::Custom(Arc::new(CustomGate::from_matrix(&name, matrix)), targets)
+}
+
+/// Rewrites every `GateOp::Conditional` by the deferred measurement
+/// principle, for runs that only track the final state. A qubit left alone
+/// after its measurement holds the outcome from then on, so a gate
+/// conditioned on a bit becomes the same gate controlled by the qubit last
+/// measured into it. A bit never measured reads 0. Operations map one to
+/// one, a gate whose condition can never hold becoming an empty barrier, so
+/// indices into the original list stay valid.
+///
+/// Fails when a condition reads a qubit that has been acted on since its
+/// measurement, conditions a gate on that qubit itself, or the circuit
+/// resets a qubit: such circuits only run shot by shot, collapsing at each
+/// measurement.
+pub(crate) fn try_defer_conditionals(operations: &[GateOp]) -> Result<Cow<'_, [GateOp]>, String> {
+    if let Some(GateOp::Reset(qubit)) = operations.iter().find(|op| op.is_reset()) {
+        return Err(format!("qubit {} is reset", qubit));
+    }
+    if !operations.iter().any(GateOp::is_conditional) {
+        return Ok(Cow::Borrowed(operations));
+    }
+    let mut deferred = Vec::with_capacity(operations.len());
+    for (i, op) in operations.iter().enumerate() {
+        let GateOp::Conditional {
+            op: gate,
+            classical_bit,
+            value,
+        } = op
+        else {
+            deferred.push(op.clone());
+            continue;
+        };
+        let deferred_op = match condition_source(&operations[..i], *classical_bit) {
+            Ok(Some(control)) if gate.quantum_targets().contains(&control) => {
+                return Err(format!(
+                    "{:?} acts on qubit {}, whose measurement it is conditioned on",
+                    op, control
+                ))
+            }
+            Ok(Some(control)) => controlled_on(control, *value, gate),
+            Ok(None) if !value => (**gate).clone(),
+            Ok(None) => GateOp::Barrier(Vec::new(), None),
+            Err(err) => return Err(format!("cannot defer {:?}: {}", op, err)),
+        };
+        deferred.push(deferred_op);
+    }
+    Ok(Cow::Owned(deferred))
+}
+
+/// `try_defer_conditionals` for simulators without per-shot execution,
+/// which panic on circuits that need it.
+pub(crate) fn defer_conditionals(operations: &[GateOp]) -> Cow<'_, [GateOp]> {
+    try_defer_conditionals(operations).unwrap_or_else(|err| {
+        panic!(
+            "{}; such circuits run shot by shot, through `QuantumCircuit::sample` or a statevector runtime",
+            err
+        )
+    })
 }
 
 impl QuantumCircuit {
@@ -152,6 +1002,12 @@ impl QuantumCircuit {
             num_classical: 0,
             operations: Vec::new(),
             computed_state: None,
+            snapshots: HashMap::new(),
+            qubit_labels: BTreeMap::new(),
+            classical_labels: BTreeMap::new(),
+            free_qubits: BTreeSet::new(),
+            noise: None,
+            initial_state: None,
         }
     }
 
@@ -161,7 +1017,92 @@ impl QuantumCircuit {
             num_classical,
             operations: Vec::new(),
             computed_state: None,
+            snapshots: HashMap::new(),
+            qubit_labels: BTreeMap::new(),
+            classical_labels: BTreeMap::new(),
+            free_qubits: BTreeSet::new(),
+            noise: None,
+            initial_state: None,
+        }
+    }
+
+    /// The graph state of `edges` on `num_qubits` qubits: H on every qubit,
+    /// then a CZ per edge. `StabilizerTableau::graph_state` describes the
+    /// same state by its stabilisers.
+    pub fn graph_state(num_qubits: usize, edges: &[(usize, usize)]) -> QuantumCircuit {
+        let mut circuit = QuantumCircuit::new(num_qubits);
+        for q in 0..num_qubits {
+            circuit.h(q);
+        }
+        for &(a, b) in edges {
+            assert!(
+                a != b && a < num_qubits && b < num_qubits,
+                "invalid graph state edge ({}, {}) on {} qubits",
+                a,
+                b,
+                num_qubits
+            );
+            circuit.cz(a, b);
+        }
+        circuit
+    }
+
+    /// `(|0…0⟩ + |1…1⟩)/√2` on `num_qubits` qubits: H on qubit 0, then a
+    /// CNOT from it to every other qubit.
+    pub fn ghz(num_qubits: usize) -> QuantumCircuit {
+        assert!(num_qubits >= 1, "GHZ state needs at least one qubit");
+        let mut circuit = QuantumCircuit::new(num_qubits);
+        circuit.h(0);
+        for q in 1..num_qubits {
+            circuit.cnot(0, q);
         }
+        circuit
+    }
+
+    /// The W state, an equal superposition of the `num_qubits` basis states
+    /// with a single 1. Starting from |10…0⟩, each step keeps amplitude
+    /// `1/√n` on qubit `k` with a controlled RY onto qubit `k + 1`, then
+    /// moves the remainder there with a CNOT back.
+    pub fn w_state(num_qubits: usize) -> QuantumCircuit {
+        assert!(num_qubits >= 1, "W state needs at least one qubit");
+        let mut circuit = QuantumCircuit::new(num_qubits);
+        circuit.x(0);
+        for k in 0..num_qubits - 1 {
+            let theta = 2.0 * (1.0 / (num_qubits - k) as f64).sqrt().acos();
+            circuit.cry(k, k + 1, theta).cnot(k + 1, k);
+        }
+        circuit
+    }
+
+    /// Teleports `U3(theta, phi, lambda)|0⟩` from qubit 0 to qubit 2 through
+    /// a Bell pair on qubits 1 and 2, measuring qubits 0 and 1 into bits 0
+    /// and 1. See `teleport`.
+    pub fn teleportation(theta: f64, phi: f64, lambda: f64) -> QuantumCircuit {
+        let mut circuit = QuantumCircuit::with_classical(3, 2);
+        circuit
+            .label_qubit(0, "msg")
+            .label_qubit(1, "alice")
+            .label_qubit(2, "bob");
+        circuit.u3(0, theta, phi, lambda).teleport(0, 1, 2, (0, 1));
+        circuit
+    }
+
+    /// Sends the two classical bits `message` over one qubit of a Bell pair:
+    /// qubit 0 encodes them with `Z^m0 X^m1` and the decoder reads them back
+    /// into bits 0 and 1, so every shot reads `"m0m1"`.
+    pub fn superdense_coding(message: (bool, bool)) -> QuantumCircuit {
+        let mut circuit = QuantumCircuit::with_classical(2, 2);
+        circuit.label_qubit(0, "alice").label_qubit(1, "bob");
+        circuit.bell_pair(0, 1).barrier_all();
+        if message.1 {
+            circuit.x(0);
+        }
+        if message.0 {
+            circuit.z(0);
+        }
+        circuit.barrier_all();
+        circuit.cnot(0, 1).h(0).measure(0, 0).measure(1, 1);
+        circuit
     }
 
     pub fn num_qubits(&self) -> usize {
@@ -172,36 +1113,185 @@ impl QuantumCircuit {
         self.num_classical
     }
 
+    /// A qubit in `|0⟩` for temporary use: the lowest index released by
+    /// `free_qubit`, or a new qubit when none is free. Reusing indices keeps
+    /// `num_qubits` at the peak number of qubits in use at once.
+    pub fn alloc_qubit(&mut self) -> usize {
+        if let Some(qubit) = self.free_qubits.pop_first() {
+            return qubit;
+        }
+        self.num_qubits += 1;
+        self.computed_state = None;
+        if let Some(state) = &mut self.initial_state {
+            // The new qubit is the least significant and starts in |0⟩.
+            *state = state
+                .iter()
+                .flat_map(|&amplitude| [amplitude, complex!(0.0, 0.0)])
+                .collect();
+        }
+        self.num_qubits - 1
+    }
+
+    /// Resets `qubit` and returns it for reuse by `alloc_qubit`, so a later
+    /// allocation starts in `|0⟩`. A qubit still entangled with the rest is
+    /// measured out by the reset; uncompute it first to keep the others
+    /// coherent.
+    pub fn free_qubit(&mut self, qubit: usize) -> &mut Self {
+        assert!(
+            qubit < self.num_qubits,
+            "qubit {} is out of range for {} qubits",
+            qubit,
+            self.num_qubits
+        );
+        assert!(
+            self.free_qubits.insert(qubit),
+            "qubit {} is already free",
+            qubit
+        );
+        self.reset_qubit(qubit)
+    }
+
+    /// Qubits currently allocated: `num_qubits` minus those freed and not
+    /// yet reused.
+    pub fn live_qubits(&self) -> usize {
+        self.num_qubits - self.free_qubits.len()
+    }
+
     pub fn operations(&self) -> &[GateOp] {
         &self.operations
     }
 
+    /// Display name of a qubit wire, `q<n>` unless one was assigned.
+    pub fn qubit_label(&self, qubit: usize) -> String {
+        self.qubit_labels
+            .get(&qubit)
+            .cloned()
+            .unwrap_or_else(|| format!("q{}", qubit))
+    }
+
+    /// Display name of a classical wire, `c<n>` unless one was assigned.
+    pub fn classical_label(&self, bit: usize) -> String {
+        self.classical_labels
+            .get(&bit)
+            .cloned()
+            .unwrap_or_else(|| format!("c{}", bit))
+    }
+
+    pub fn label_qubit(&mut self, qubit: usize, label: &str) -> &mut Self {
+        self.qubit_labels.insert(qubit, label.to_string());
+        self
+    }
+
+    /// Names `qubits` as the register `name[0]`, `name[1]`, ...
+    pub fn label_qubits(&mut self, name: &str, qubits: &[usize]) -> &mut Self {
+        for (i, &qubit) in qubits.iter().enumerate() {
+            self.qubit_labels.insert(qubit, format!("{}[{}]", name, i));
+        }
+        self
+    }
+
+    pub fn label_classical(&mut self, bit: usize, label: &str) -> &mut Self {
+        self.classical_labels.insert(bit, label.to_string());
+        self
+    }
+
+    /// Names `bits` as the classical register `name[0]`, `name[1]`, ...
+    pub fn label_classical_bits(&mut self, name: &str, bits: &[usize]) -> &mut Self {
+        for (i, &bit) in bits.iter().enumerate() {
+            self.classical_labels
+                .insert(bit, format!("{}[{}]", name, i));
+        }
+        self
+    }
+
     pub fn is_computed(&self) -> bool {
         self.computed_state.is_some()
     }
 
+    /// The state from the last `compute`, if it is still up to date.
+    pub fn computed_state(&self) -> Option<&QuantumState> {
+        self.computed_state.as_ref()
+    }
+
     pub fn compute(&mut self) -> &QuantumState {
         self.compute_with(Runtime::default())
     }
 
     pub fn compute_with(&mut self, runtime: Runtime) -> &QuantumState {
-        if self.computed_state.is_some() {
-            return self.computed_state.as_ref().unwrap();
+        if self.computed_state.is_none() {
+            let (state, snapshots) = runtime.compute_with_progress(
+                self.num_qubits,
+                &self.operations,
+                self.initial_state.as_deref(),
+                None,
+            );
+            self.computed_state = Some(state);
+            self.snapshots = snapshots;
         }
-
-        self.computed_state = Some(runtime.compute(self.num_qubits, &self.operations));
         self.computed_state.as_ref().unwrap()
     }
 
     pub fn compute_with_config(&mut self, config: RuntimeConfig) -> &QuantumState {
-        if self.computed_state.is_some() {
-            return self.computed_state.as_ref().unwrap();
+        if self.computed_state.is_none() {
+            let (state, snapshots) = config.compute_with_progress(
+                self.num_qubits,
+                &self.operations,
+                self.initial_state.as_deref(),
+                None,
+            );
+            self.computed_state = Some(state);
+            self.snapshots = snapshots;
         }
+        self.computed_state.as_ref().unwrap()
+    }
 
-        self.computed_state = Some(config.compute(self.num_qubits, &self.operations));
+    /// Computes the state on any `SimulatorBackend`. Snapshots are not
+    /// recorded, since backends need not expose intermediate states, and
+    /// since backends start from |0…0⟩ this panics for circuits with an
+    /// initial state.
+    pub fn compute_with_backend(&mut self, backend: &mut dyn SimulatorBackend) -> &QuantumState {
+        assert!(
+            self.initial_state.is_none(),
+            "{} starts from |0…0⟩ and cannot take an initial state",
+            backend.name()
+        );
+        if self.computed_state.is_none() {
+            self.computed_state = Some(backend.run(self.num_qubits, &self.operations));
+            self.snapshots.clear();
+        }
         self.computed_state.as_ref().unwrap()
     }
 
+    /// Computes the state on a background thread with `runtime` (a `Runtime`
+    /// or a `RuntimeConfig`), leaving the circuit free to change meanwhile.
+    /// The result can be stored back with `set_computed`.
+    pub fn compute_async(&self, runtime: impl Into<Runtime>) -> ComputeJob {
+        ComputeJob::spawn_from(
+            runtime.into(),
+            self.num_qubits,
+            self.operations.clone(),
+            self.initial_state.clone(),
+        )
+    }
+
+    /// Installs the result of `compute_async` as the computed state. The
+    /// caller is responsible for the circuit not having changed since.
+    pub fn set_computed(&mut self, output: ComputeOutput) -> &QuantumState {
+        self.snapshots = output.snapshots;
+        self.computed_state.insert(output.state)
+    }
+
+    /// Quantities recorded by the circuit's snapshots, keyed by label. A label
+    /// used more than once keeps the value from its last snapshot.
+    pub fn snapshots(&mut self) -> &HashMap<String, SnapshotValue> {
+        self.compute();
+        &self.snapshots
+    }
+
+    pub fn snapshot_value(&mut self, label: &str) -> Option<&SnapshotValue> {
+        self.snapshots().get(label)
+    }
+
     pub fn state(&mut self) -> &QuantumState {
         self.compute()
     }
@@ -214,6 +1304,378 @@ impl QuantumCircuit {
         self.compute_with_config(config)
     }
 
+    /// Samples `shots` measurement outcomes from the computed state (see
+    /// `Counts::sample`), computing it with the default runtime if needed.
+    /// Under a noise model each shot is a trajectory instead. Circuits that
+    /// act on a measured qubit again or condition gates on measured bits
+    /// run shot by shot, collapsing the state at those measurements.
+    pub fn sample(&mut self, shots: usize, seed: Option<u64>) -> Counts {
+        if let Some(noise) = &self.noise {
+            return TrajectorySimulator::new(noise.clone()).sample(self, shots, seed);
+        }
+        if shots::needs_shots(&self.operations) {
+            return self.sample_shots(shots, seed);
+        }
+        let measurements = self.measurements();
+        let (num_qubits, num_classical) = (self.num_qubits, self.num_classical);
+        let state = self.compute();
+        Counts::sample(state, num_qubits, &measurements, num_classical, shots, seed)
+    }
+
+    /// Like `sample`, drawing the shots on all threads with independent RNG
+    /// streams (see `Counts::sample_parallel`). Circuits run shot by shot
+    /// split their shots into chunks, each run on its own thread.
+    pub fn run_shots_parallel(&mut self, shots: usize, seed: Option<u64>) -> Counts {
+        if let Some(noise) = &self.noise {
+            return TrajectorySimulator::new(noise.clone())
+                .parallel()
+                .sample(self, shots, seed);
+        }
+        if shots::needs_shots(&self.operations) {
+            return shots::sample_parallel(
+                Runtime::default(),
+                self.num_qubits,
+                self.start_state(),
+                &self.operations,
+                self.num_classical,
+                shots,
+                seed,
+            );
+        }
+        let measurements = self.measurements();
+        let (num_qubits, num_classical) = (self.num_qubits, self.num_classical);
+        let state = self.compute();
+        Counts::sample_parallel(state, num_qubits, &measurements, num_classical, shots, seed)
+    }
+
+    /// `sample` for circuits that depend on mid-circuit outcomes.
+    fn sample_shots(&self, shots: usize, seed: Option<u64>) -> Counts {
+        shots::sample(
+            Runtime::default(),
+            self.num_qubits,
+            self.start_state(),
+            &self.operations,
+            self.num_classical,
+            shots,
+            seed,
+        )
+    }
+
+    /// Attaches `noise` to the circuit: `sample`, `run_shots_parallel` and
+    /// `density_matrix` then include it. The state vector from `compute`
+    /// stays noiseless.
+    pub fn with_noise(&mut self, noise: NoiseModel) -> &mut Self {
+        self.noise = Some(noise);
+        self
+    }
+
+    pub fn noise(&self) -> Option<&NoiseModel> {
+        self.noise.as_ref()
+    }
+
+    pub fn clear_noise(&mut self) -> &mut Self {
+        self.noise = None;
+        self
+    }
+
+    /// Starts every run from `state` instead of |0…0⟩, e.g. from a state
+    /// prepared by another circuit. `state` holds the `2ⁿ` amplitudes with
+    /// qubit 0 most significant, as `compute` returns them, and must be
+    /// normalised.
+    pub fn with_initial_state(&mut self, state: &[Complex<f64>]) -> &mut Self {
+        assert_eq!(
+            state.len(),
+            1 << self.num_qubits,
+            "a {}-qubit circuit needs {} amplitudes",
+            self.num_qubits,
+            1usize << self.num_qubits
+        );
+        let norm: f64 = state.iter().map(Complex::norm2).sum();
+        assert!(
+            (norm - 1.0).abs() < 1e-9,
+            "initial state has norm² {}, not 1",
+            norm
+        );
+        self.initial_state = Some(state.to_vec());
+        self.computed_state = None;
+        self
+    }
+
+    pub fn initial_state(&self) -> Option<&[Complex<f64>]> {
+        self.initial_state.as_deref()
+    }
+
+    pub fn clear_initial_state(&mut self) -> &mut Self {
+        self.initial_state = None;
+        self.computed_state = None;
+        self
+    }
+
+    /// The amplitudes runs start from.
+    pub(crate) fn start_state(&self) -> Vec<Complex<f64>> {
+        start_state(self.num_qubits, self.initial_state.as_deref())
+    }
+
+    /// The final density matrix, with the circuit's noise if it has any.
+    /// Takes `4ⁿ` amplitudes.
+    pub fn density_matrix(&self) -> DensityMatrix {
+        DensityMatrix::from_circuit(self, self.noise.as_ref().unwrap_or(&NoiseModel::new()))
+    }
+
+    /// `(qubit, classical)` pairs of the circuit's measurements.
+    pub(crate) fn measurements(&self) -> Vec<(usize, usize)> {
+        self.operations
+            .iter()
+            .filter_map(|op| match op {
+                GateOp::Measure(q, c) => Some((*q, *c)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The state after every operation, without touching the computed state.
+    pub fn trace(&self, cutoff: f64) -> StateTrace {
+        StateTrace::record_from(
+            self.start_state(),
+            self.num_qubits,
+            &self.operations,
+            cutoff,
+        )
+    }
+
+    /// Start times of the operations under `durations`; see `Schedule`.
+    pub fn schedule(&self, durations: &GateDurations, policy: SchedulePolicy) -> Schedule {
+        Schedule::new(self, durations, policy)
+    }
+
+    /// Appends an operation built elsewhere, e.g. by a parser.
+    pub fn push(&mut self, op: GateOp) -> &mut Self {
+        if let GateOp::Measure(qubit, classical) = op {
+            return self.measure(qubit, classical);
+        }
+        if let GateOp::Conditional {
+            op,
+            classical_bit,
+            value,
+        } = op
+        {
+            return self.push(*op).c_if(classical_bit, value as u8);
+        }
+        self.operations.push(op);
+        self.computed_state = None;
+        self
+    }
+
+    /// Appends the gate called `name`; see `GateOp::from_name`. Also fails
+    /// if a qubit is out of range or used twice, leaving the circuit as it
+    /// was.
+    pub fn append_by_name(
+        &mut self,
+        name: &str,
+        qubits: &[usize],
+        params: &[f64],
+    ) -> Result<&mut Self, String> {
+        let op = GateOp::from_name(name, qubits, params)?;
+        for (i, &q) in qubits.iter().enumerate() {
+            if q >= self.num_qubits {
+                return Err(format!(
+                    "qubit {} out of range for {} qubits",
+                    q, self.num_qubits
+                ));
+            }
+            if qubits[..i].contains(&q) {
+                return Err(format!("'{}' uses qubit {} twice", name, q));
+            }
+        }
+        Ok(self.push(op))
+    }
+
+    /// The circuit undoing this one, for uncomputation or amplitude
+    /// amplification: the operations in reverse order, each replaced by its
+    /// adjoint (see `GateOp::adjoint`). Labels and noise carry over. Panics
+    /// if the circuit measures.
+    pub fn inverse(&self) -> QuantumCircuit {
+        self.with_operations(self.operations.iter().rev().map(GateOp::adjoint).collect())
+    }
+
+    /// This circuit applied only when every qubit in `controls` is |1⟩: each
+    /// operation is replaced by its controlled form (see
+    /// `GateOp::controlled`). The controls must be qubits of this circuit
+    /// that none of its operations touch. Panics if the circuit measures.
+    pub fn controlled(&self, controls: &[usize]) -> QuantumCircuit {
+        for (i, &c) in controls.iter().enumerate() {
+            assert!(
+                c < self.num_qubits,
+                "qubit {} out of range for {} qubits",
+                c,
+                self.num_qubits
+            );
+            assert!(!controls[..i].contains(&c), "control {} listed twice", c);
+        }
+        let operations = self
+            .operations
+            .iter()
+            .map(|op| {
+                controls
+                    .iter()
+                    .fold(op.clone(), |op, &control| op.controlled(control))
+            })
+            .collect();
+        self.with_operations(operations)
+    }
+
+    /// This circuit run `n` times in a row, e.g. a Trotter step or a Grover
+    /// iteration. Zero repetitions give an empty circuit.
+    pub fn repeat(&self, n: usize) -> QuantumCircuit {
+        let operations = self
+            .operations
+            .iter()
+            .cycle()
+            .take(n * self.operations.len());
+        self.with_operations(operations.cloned().collect())
+    }
+
+    /// This circuit raised to the `n`-th power: `repeat(n)`, or the inverse
+    /// repeated `-n` times for negative `n`.
+    pub fn power(&self, n: i64) -> QuantumCircuit {
+        if n < 0 {
+            self.inverse().repeat(n.unsigned_abs() as usize)
+        } else {
+            self.repeat(n as usize)
+        }
+    }
+
+    /// The `2ⁿ × 2ⁿ` unitary of the whole circuit, qubit 0 the most
+    /// significant bit of the row and column indices as in the state vector.
+    /// Measurements, barriers and snapshots are left out, and gates
+    /// conditioned on a measurement become gates controlled by the measured
+    /// qubit, as the runtimes defer them. Costs `2ⁿ` runs of the circuit.
+    pub fn unitary(&self) -> Matrix<Complex<f64>> {
+        unitary(self)
+    }
+
+    /// Whether `other` applies the same unitary as this circuit up to a
+    /// global phase, for checking an optimisation or a hand rewrite. Up to
+    /// 8 qubits the whole unitaries are compared, column by column; wider
+    /// circuits are compared on a few fixed random input states. Every
+    /// output may be `tolerance` away in norm from the other circuit's.
+    /// Circuits of different widths are never equivalent.
+    pub fn equivalent_to(&self, other: &QuantumCircuit, tolerance: f64) -> bool {
+        equivalent(self, other, tolerance)
+    }
+
+    /// A circuit of the same width, labels, noise and initial state as this
+    /// one, running `operations`.
+    fn with_operations(&self, operations: Vec<GateOp>) -> QuantumCircuit {
+        QuantumCircuit {
+            num_qubits: self.num_qubits,
+            num_classical: self.num_classical,
+            operations,
+            computed_state: None,
+            snapshots: HashMap::new(),
+            qubit_labels: self.qubit_labels.clone(),
+            classical_labels: self.classical_labels.clone(),
+            free_qubits: self.free_qubits.clone(),
+            noise: self.noise.clone(),
+            initial_state: self.initial_state.clone(),
+        }
+    }
+
+    /// Appends every operation of `other` with its qubit `i` mapped to
+    /// `qubit_map[i]`, e.g. to place a prebuilt oracle or ansatz on some of
+    /// this circuit's qubits. Classical bits keep their indices, so the two
+    /// circuits share a classical register.
+    pub fn append(&mut self, other: &QuantumCircuit, qubit_map: &[usize]) -> &mut Self {
+        assert_eq!(
+            qubit_map.len(),
+            other.num_qubits,
+            "qubit map has {} entries for a {}-qubit circuit",
+            qubit_map.len(),
+            other.num_qubits
+        );
+        for (i, &q) in qubit_map.iter().enumerate() {
+            assert!(
+                q < self.num_qubits,
+                "qubit {} out of range for {} qubits",
+                q,
+                self.num_qubits
+            );
+            assert!(
+                !qubit_map[..i].contains(&q),
+                "qubit map uses qubit {} twice",
+                q
+            );
+        }
+        self.num_classical = self.num_classical.max(other.num_classical);
+        for op in &other.operations {
+            self.push(op.map_qubits(|q| qubit_map[q]));
+        }
+        self
+    }
+
+    /// Appends `op` with its angles set to `angles`, left open where they
+    /// are parameters.
+    fn push_angles<const N: usize>(&mut self, op: GateOp, angles: [Angle; N]) -> &mut Self {
+        let mut params = [0.0; N];
+        let mut symbols = Vec::with_capacity(N);
+        for (param, angle) in params.iter_mut().zip(angles) {
+            match angle {
+                Angle::Value(value) => {
+                    *param = value;
+                    symbols.push(None);
+                }
+                Angle::Symbol(symbol) => symbols.push(Some(symbol)),
+            }
+        }
+        let op = parameterized(op.with_params(&params), symbols);
+        self.operations.push(op);
+        self.computed_state = None;
+        self
+    }
+
+    /// Names of the parameters still open, sorted.
+    pub fn parameters(&self) -> Vec<String> {
+        let names: BTreeSet<&str> = self
+            .operations
+            .iter()
+            .flat_map(GateOp::symbols)
+            .flatten()
+            .map(Parameter::name)
+            .collect();
+        names.into_iter().map(str::to_string).collect()
+    }
+
+    /// The circuit with each parameter named in `bindings` replaced by its
+    /// value, e.g. `bind_parameters(&[("theta", 0.3)])` on every iteration
+    /// of a variational loop. Parameters left out stay open, and the result
+    /// runs once none is. Panics for a name the circuit has no parameter
+    /// for.
+    pub fn bind_parameters(&self, bindings: &[(&str, f64)]) -> QuantumCircuit {
+        let names = self.parameters();
+        for (name, _) in bindings {
+            assert!(
+                names.iter().any(|known| known == name),
+                "circuit has no parameter named '{}'",
+                name
+            );
+        }
+        let operations = self
+            .operations
+            .iter()
+            .map(|op| op.bind_parameters(bindings))
+            .collect();
+        self.with_operations(operations)
+    }
+
+    /// Removes and returns the last operation.
+    pub fn pop(&mut self) -> Option<GateOp> {
+        let op = self.operations.pop()?;
+        if !op.is_measurement() {
+            self.computed_state = None;
+        }
+        Some(op)
+    }
+
     pub fn h(&mut self, target: usize) -> &mut Self {
         self.operations.push(GateOp::H(target));
         self.computed_state = None;
@@ -274,90 +1736,143 @@ impl QuantumCircuit {
         self
     }
 
-    pub fn rx(&mut self, target: usize, theta: f64) -> &mut Self {
-        self.operations.push(GateOp::Rx(target, theta));
-        self.computed_state = None;
-        self
+    pub fn rx(&mut self, target: usize, theta: impl Into<Angle>) -> &mut Self {
+        self.push_angles(GateOp::Rx(target, 0.0), [theta.into()])
     }
 
-    pub fn ry(&mut self, target: usize, theta: f64) -> &mut Self {
-        self.operations.push(GateOp::Ry(target, theta));
-        self.computed_state = None;
-        self
+    pub fn ry(&mut self, target: usize, theta: impl Into<Angle>) -> &mut Self {
+        self.push_angles(GateOp::Ry(target, 0.0), [theta.into()])
     }
 
-    pub fn rz(&mut self, target: usize, theta: f64) -> &mut Self {
-        self.operations.push(GateOp::Rz(target, theta));
-        self.computed_state = None;
-        self
+    pub fn rz(&mut self, target: usize, theta: impl Into<Angle>) -> &mut Self {
+        self.push_angles(GateOp::Rz(target, 0.0), [theta.into()])
     }
 
-    pub fn p(&mut self, target: usize, theta: f64) -> &mut Self {
-        self.operations.push(GateOp::P(target, theta));
-        self.computed_state = None;
-        self
+    pub fn p(&mut self, target: usize, theta: impl Into<Angle>) -> &mut Self {
+        self.push_angles(GateOp::P(target, 0.0), [theta.into()])
     }
 
-    pub fn u1(&mut self, target: usize, lambda: f64) -> &mut Self {
-        self.operations.push(GateOp::U1(target, lambda));
-        self.computed_state = None;
-        self
+    pub fn u1(&mut self, target: usize, lambda: impl Into<Angle>) -> &mut Self {
+        self.push_angles(GateOp::U1(target, 0.0), [lambda.into()])
+    }
+
+    pub fn u2(
+        &mut self,
+        target: usize,
+        phi: impl Into<Angle>,
+        lambda: impl Into<Angle>,
+    ) -> &mut Self {
+        self.push_angles(GateOp::U2(target, 0.0, 0.0), [phi.into(), lambda.into()])
+    }
+
+    pub fn u3(
+        &mut self,
+        target: usize,
+        theta: impl Into<Angle>,
+        phi: impl Into<Angle>,
+        lambda: impl Into<Angle>,
+    ) -> &mut Self {
+        let angles = [theta.into(), phi.into(), lambda.into()];
+        self.push_angles(GateOp::U3(target, 0.0, 0.0, 0.0), angles)
+    }
+
+    pub fn crx(&mut self, control: usize, target: usize, theta: impl Into<Angle>) -> &mut Self {
+        self.push_angles(GateOp::CRx(control, target, 0.0), [theta.into()])
+    }
+
+    pub fn cry(&mut self, control: usize, target: usize, theta: impl Into<Angle>) -> &mut Self {
+        self.push_angles(GateOp::CRy(control, target, 0.0), [theta.into()])
     }
 
-    pub fn u2(&mut self, target: usize, phi: f64, lambda: f64) -> &mut Self {
-        self.operations.push(GateOp::U2(target, phi, lambda));
+    pub fn crz(&mut self, control: usize, target: usize, theta: impl Into<Angle>) -> &mut Self {
+        self.push_angles(GateOp::CRz(control, target, 0.0), [theta.into()])
+    }
+
+    pub fn cp(&mut self, control: usize, target: usize, theta: impl Into<Angle>) -> &mut Self {
+        self.push_angles(GateOp::CP(control, target, 0.0), [theta.into()])
+    }
+
+    /// `exp(-iθ X⊗X / 2)` on `qubit1` and `qubit2`.
+    pub fn rxx(&mut self, qubit1: usize, qubit2: usize, theta: impl Into<Angle>) -> &mut Self {
+        self.push_angles(GateOp::Rxx(qubit1, qubit2, 0.0), [theta.into()])
+    }
+
+    /// `exp(-iθ Y⊗Y / 2)` on `qubit1` and `qubit2`.
+    pub fn ryy(&mut self, qubit1: usize, qubit2: usize, theta: impl Into<Angle>) -> &mut Self {
+        self.push_angles(GateOp::Ryy(qubit1, qubit2, 0.0), [theta.into()])
+    }
+
+    /// `exp(-iθ Z⊗Z / 2)` on `qubit1` and `qubit2`.
+    pub fn rzz(&mut self, qubit1: usize, qubit2: usize, theta: impl Into<Angle>) -> &mut Self {
+        self.push_angles(GateOp::Rzz(qubit1, qubit2, 0.0), [theta.into()])
+    }
+
+    pub fn cnot(&mut self, control: usize, target: usize) -> &mut Self {
+        self.operations.push(GateOp::CNOT(control, target));
         self.computed_state = None;
         self
     }
 
-    pub fn u3(&mut self, target: usize, theta: f64, phi: f64, lambda: f64) -> &mut Self {
-        self.operations.push(GateOp::U3(target, theta, phi, lambda));
+    pub fn cx(&mut self, control: usize, target: usize) -> &mut Self {
+        self.cnot(control, target)
+    }
+
+    pub fn cz(&mut self, control: usize, target: usize) -> &mut Self {
+        self.operations.push(GateOp::CZ(control, target));
         self.computed_state = None;
         self
     }
 
-    pub fn crx(&mut self, control: usize, target: usize, theta: f64) -> &mut Self {
-        self.operations.push(GateOp::CRx(control, target, theta));
+    pub fn cy(&mut self, control: usize, target: usize) -> &mut Self {
+        self.operations.push(GateOp::CY(control, target));
         self.computed_state = None;
         self
     }
 
-    pub fn cry(&mut self, control: usize, target: usize, theta: f64) -> &mut Self {
-        self.operations.push(GateOp::CRy(control, target, theta));
+    pub fn ch(&mut self, control: usize, target: usize) -> &mut Self {
+        self.operations.push(GateOp::CH(control, target));
         self.computed_state = None;
         self
     }
 
-    pub fn crz(&mut self, control: usize, target: usize, theta: f64) -> &mut Self {
-        self.operations.push(GateOp::CRz(control, target, theta));
+    /// √X on `target` where `control` is |1⟩.
+    pub fn csx(&mut self, control: usize, target: usize) -> &mut Self {
+        self.operations.push(GateOp::CSX(control, target));
         self.computed_state = None;
         self
     }
 
-    pub fn cp(&mut self, control: usize, target: usize, theta: f64) -> &mut Self {
-        self.operations.push(GateOp::CP(control, target, theta));
+    /// Echoed cross-resonance, `(X⊗I − Y⊗X)/√2` with `qubit1` first: the
+    /// native entangling gate of some IBM backends. Its own inverse.
+    pub fn ecr(&mut self, qubit1: usize, qubit2: usize) -> &mut Self {
+        self.operations.push(GateOp::ECR(qubit1, qubit2));
         self.computed_state = None;
         self
     }
 
-    pub fn cnot(&mut self, control: usize, target: usize) -> &mut Self {
-        self.operations.push(GateOp::CNOT(control, target));
+    /// Double CNOT: a CNOT from `qubit1` to `qubit2`, then one back.
+    pub fn dcx(&mut self, qubit1: usize, qubit2: usize) -> &mut Self {
+        self.operations.push(GateOp::DCX(qubit1, qubit2));
         self.computed_state = None;
         self
     }
 
-    pub fn cx(&mut self, control: usize, target: usize) -> &mut Self {
-        self.cnot(control, target)
+    pub fn swap(&mut self, qubit1: usize, qubit2: usize) -> &mut Self {
+        self.operations.push(GateOp::SWAP(qubit1, qubit2));
+        self.computed_state = None;
+        self
     }
 
-    pub fn cz(&mut self, control: usize, target: usize) -> &mut Self {
-        self.operations.push(GateOp::CZ(control, target));
+    /// Swaps the two qubits, multiplying |01⟩ and |10⟩ by `i`.
+    pub fn iswap(&mut self, qubit1: usize, qubit2: usize) -> &mut Self {
+        self.operations.push(GateOp::ISwap(qubit1, qubit2));
         self.computed_state = None;
         self
     }
 
-    pub fn swap(&mut self, qubit1: usize, qubit2: usize) -> &mut Self {
-        self.operations.push(GateOp::SWAP(qubit1, qubit2));
+    /// Half a SWAP: applied twice, it swaps the two qubits.
+    pub fn sqrt_swap(&mut self, qubit1: usize, qubit2: usize) -> &mut Self {
+        self.operations.push(GateOp::SqrtSwap(qubit1, qubit2));
         self.computed_state = None;
         self
     }
@@ -373,6 +1888,47 @@ impl QuantumCircuit {
         self.ccnot(control1, control2, target)
     }
 
+    /// X on `target` where every qubit in `controls` is |1⟩, for any
+    /// number of controls. The statevector runtimes flip the amplitude
+    /// pairs in place rather than build the gate's matrix.
+    pub fn mcx(&mut self, controls: &[usize], target: usize) -> &mut Self {
+        let op = GateOp::MCX(controls.to_vec(), target);
+        let qubits = op.quantum_targets();
+        assert!(
+            (1..qubits.len()).all(|i| !qubits[..i].contains(&qubits[i])),
+            "{:?} uses a qubit twice",
+            op
+        );
+        self.operations.push(op);
+        self.computed_state = None;
+        self
+    }
+
+    /// The single-qubit `gate` on `target` where every qubit in `controls`
+    /// is |1⟩. Like `mcx`, applied to the amplitude pairs in that subspace
+    /// instead of through the enlarged matrix.
+    pub fn mcu(&mut self, gate: &Arc<CustomGate>, controls: &[usize], target: usize) -> &mut Self {
+        assert_eq!(
+            gate.num_qubits, 1,
+            "{} is not a single-qubit gate",
+            gate.name
+        );
+        let op = GateOp::MCU {
+            gate: Arc::clone(gate),
+            controls: controls.to_vec(),
+            target,
+        };
+        let qubits = op.quantum_targets();
+        assert!(
+            (1..qubits.len()).all(|i| !qubits[..i].contains(&qubits[i])),
+            "{:?} uses a qubit twice",
+            op
+        );
+        self.operations.push(op);
+        self.computed_state = None;
+        self
+    }
+
     pub fn cswap(&mut self, control: usize, target1: usize, target2: usize) -> &mut Self {
         self.operations
             .push(GateOp::CSWAP(control, target1, target2));
@@ -384,6 +1940,35 @@ impl QuantumCircuit {
         self.cswap(control, target1, target2)
     }
 
+    /// Entangles `a` and `b`, both in |0⟩, into `(|00⟩ + |11⟩)/√2`.
+    pub fn bell_pair(&mut self, a: usize, b: usize) -> &mut Self {
+        self.h(a).cnot(a, b)
+    }
+
+    /// Teleports the state of `source` onto `target` through `link`, both
+    /// in |0⟩, recording the Bell measurement in `bits` and correcting
+    /// `target` with an X conditioned on `bits.1` and a Z conditioned on
+    /// `bits.0`.
+    pub fn teleport(
+        &mut self,
+        source: usize,
+        link: usize,
+        target: usize,
+        bits: (usize, usize),
+    ) -> &mut Self {
+        self.bell_pair(link, target)
+            .barrier(&[source, link, target])
+            .cnot(source, link)
+            .h(source)
+            .barrier(&[source, link, target])
+            .measure(link, bits.1)
+            .x(target)
+            .c_if(bits.1, 1)
+            .measure(source, bits.0)
+            .z(target)
+            .c_if(bits.0, 1)
+    }
+
     pub fn measure(&mut self, qubit: usize, classical: usize) -> &mut Self {
         if classical >= self.num_classical {
             self.num_classical = classical + 1;
@@ -392,6 +1977,14 @@ impl QuantumCircuit {
         self
     }
 
+    /// Returns `qubit` to `|0⟩` mid-circuit, whatever its state. Circuits
+    /// with resets run shot by shot, like those with conditions.
+    pub fn reset_qubit(&mut self, qubit: usize) -> &mut Self {
+        self.operations.push(GateOp::Reset(qubit));
+        self.computed_state = None;
+        self
+    }
+
     pub fn measure_all(&mut self) -> &mut Self {
         for i in 0..self.num_qubits {
             self.measure(i, i);
@@ -399,6 +1992,69 @@ impl QuantumCircuit {
         self
     }
 
+    /// Makes the last operation conditional on classical bit `classical_bit`
+    /// reading `value` (0 or 1) when execution reaches it, e.g.
+    /// `circuit.measure(0, 0).x(0).c_if(0, 1)` to reset qubit 0. A bit never
+    /// measured reads 0. Sampling evaluates the condition in every shot. The
+    /// final state from `compute` turns it into the gate controlled by the
+    /// measured qubit where that is exact, and is otherwise the state of a
+    /// single shot.
+    pub fn c_if(&mut self, classical_bit: usize, value: u8) -> &mut Self {
+        assert!(value <= 1, "c_if value must be 0 or 1, got {}", value);
+        assert!(
+            classical_bit < self.num_classical,
+            "classical bit {} out of range for {} bits",
+            classical_bit,
+            self.num_classical
+        );
+        let op = self
+            .operations
+            .pop()
+            .expect("c_if needs a gate to condition");
+        assert!(
+            !op.is_measurement()
+                && !op.is_reset()
+                && !op.is_barrier()
+                && !op.is_snapshot()
+                && !op.is_conditional(),
+            "{:?} cannot be classically conditioned",
+            op
+        );
+        self.operations.push(GateOp::Conditional {
+            op: Box::new(op),
+            classical_bit,
+            value: value == 1,
+        });
+        self.computed_state = None;
+        self
+    }
+
+    pub fn barrier(&mut self, qubits: &[usize]) -> &mut Self {
+        self.operations.push(GateOp::Barrier(qubits.to_vec(), None));
+        self
+    }
+
+    pub fn barrier_all(&mut self) -> &mut Self {
+        let qubits: Vec<usize> = (0..self.num_qubits).collect();
+        self.barrier(&qubits)
+    }
+
+    /// Starts a titled section: a barrier across every qubit whose title is
+    /// shown above the following gates by the renderers.
+    pub fn section(&mut self, title: &str) -> &mut Self {
+        let qubits: Vec<usize> = (0..self.num_qubits).collect();
+        self.operations
+            .push(GateOp::Barrier(qubits, Some(title.to_string())));
+        self
+    }
+
+    pub fn snapshot(&mut self, label: &str, kind: SnapshotKind) -> &mut Self {
+        self.operations
+            .push(GateOp::Snapshot(label.to_string(), kind));
+        self.computed_state = None;
+        self
+    }
+
     pub fn custom(&mut self, gate: &Arc<CustomGate>, targets: &[usize]) -> &mut Self {
         self.operations
             .push(GateOp::Custom(Arc::clone(gate), targets.to_vec()));
@@ -456,10 +2112,36 @@ impl fmt::Display for QuantumCircuit {
         writeln!(f, "Operations:")?;
         for (i, op) in self.operations.iter().enumerate() {
             match op {
-                GateOp::Measure(q, c) => writeln!(f, "  {}: {} q{} → c{}", i, op.name(), q, c)?,
+                GateOp::Measure(q, c) => writeln!(
+                    f,
+                    "  {}: {} {} → {}",
+                    i,
+                    op.name(),
+                    self.qubit_label(*q),
+                    self.classical_label(*c)
+                )?,
                 GateOp::Custom(gate, targets) => {
                     writeln!(f, "  {}: [{}] on {:?}", i, gate.name, targets)?
                 }
+                GateOp::Barrier(targets, Some(title)) => {
+                    writeln!(f, "  {}: {} \"{}\" on {:?}", i, op.name(), title, targets)?
+                }
+                GateOp::Snapshot(label, kind) => {
+                    writeln!(f, "  {}: {} \"{}\" of {}", i, op.name(), label, kind)?
+                }
+                GateOp::Conditional {
+                    classical_bit,
+                    value,
+                    ..
+                } => writeln!(
+                    f,
+                    "  {}: {} on {:?} if {} == {}",
+                    i,
+                    op.name(),
+                    op.quantum_targets(),
+                    self.classical_label(*classical_bit),
+                    *value as u8
+                )?,
                 _ => writeln!(f, "  {}: {} on {:?}", i, op.name(), op.quantum_targets())?,
             }
         }
@@ -29,8 +29,8 @@ impl<'a> ClassicalBit<'a> {
 impl<'a> ClassicalRegister<'a> {
     pub fn new(name: &'a str, names: &'a [&'a str]) -> ClassicalRegister<'a> {
         let mut bits: Vec<ClassicalBit<'a>> = Vec::new();
-        for i in 0..names.len() {
-            bits.push(ClassicalBit::new(names[i], false));
+        for name in names {
+            bits.push(ClassicalBit::new(name, false));
         }
         ClassicalRegister { name, bits }
     }
@@ -0,0 +1,228 @@
+use super::QuantumState;
+use crate::maths::parallel::*;
+use crate::Vector;
+use core::fmt;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::hash_map::RandomState;
+use std::collections::BTreeMap;
+use std::hash::{BuildHasher, Hasher};
+
+/// Outcome histogram of repeated measurement of a state.
+///
+/// Outcomes are bitstrings with bit 0 leftmost: classical bits `c0 c1 ...`
+/// when the circuit measures into a classical register, qubits `q0 q1 ...`
+/// otherwise, matching the basis-state order used elsewhere.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Counts {
+    shots: usize,
+    counts: BTreeMap<String, usize>,
+}
+
+impl Counts {
+    /// Draws `shots` samples from `state`. `measurements` are the
+    /// `(qubit, classical)` pairs of the circuit; since the runtimes defer
+    /// measurement to the end, each shot reads every qubit at once and copies
+    /// the measured ones into their classical bits. With no measurements the
+    /// whole register is read out.
+    pub fn sample(
+        state: &QuantumState,
+        num_qubits: usize,
+        measurements: &[(usize, usize)],
+        num_classical: usize,
+        shots: usize,
+        seed: Option<u64>,
+    ) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed.unwrap_or_else(entropy_seed));
+        let by_index = Sampler::new(state).draw(&mut rng, shots);
+        Self::from_indices(by_index, num_qubits, measurements, num_classical, shots)
+    }
+
+    /// Like `sample`, with the shots split into chunks drawn on all threads.
+    /// Chunk `i` uses its own RNG stream derived from the seed and `i`, so a
+    /// seeded result does not depend on the number of threads (it does
+    /// differ from `sample` with the same seed).
+    pub fn sample_parallel(
+        state: &QuantumState,
+        num_qubits: usize,
+        measurements: &[(usize, usize)],
+        num_classical: usize,
+        shots: usize,
+        seed: Option<u64>,
+    ) -> Self {
+        let seed = seed.unwrap_or_else(entropy_seed);
+        let sampler = Sampler::new(state);
+        let chunks: Vec<BTreeMap<usize, usize>> = (0..shots.div_ceil(SHOTS_PER_CHUNK))
+            .into_par_iter()
+            .map(|chunk| {
+                let mut rng = StdRng::seed_from_u64(stream_seed(seed, chunk as u64));
+                let chunk_shots = SHOTS_PER_CHUNK.min(shots - chunk * SHOTS_PER_CHUNK);
+                sampler.draw(&mut rng, chunk_shots)
+            })
+            .collect();
+
+        let mut by_index = BTreeMap::new();
+        for chunk in chunks {
+            for (index, n) in chunk {
+                *by_index.entry(index).or_insert(0) += n;
+            }
+        }
+        Self::from_indices(by_index, num_qubits, measurements, num_classical, shots)
+    }
+
+    pub(crate) fn from_indices(
+        by_index: BTreeMap<usize, usize>,
+        num_qubits: usize,
+        measurements: &[(usize, usize)],
+        num_classical: usize,
+        shots: usize,
+    ) -> Self {
+        let mut counts = BTreeMap::new();
+        for (index, n) in by_index {
+            let key = outcome_key(index, num_qubits, measurements, num_classical);
+            *counts.entry(key).or_insert(0) += n;
+        }
+
+        Counts { shots, counts }
+    }
+
+    /// Counts observed elsewhere, e.g. returned by hardware. Outcomes must
+    /// use the bit order described above; shots is their total.
+    pub fn from_outcomes<S: Into<String>>(outcomes: impl IntoIterator<Item = (S, usize)>) -> Self {
+        let mut counts = BTreeMap::new();
+        for (outcome, n) in outcomes {
+            *counts.entry(outcome.into()).or_insert(0) += n;
+        }
+        Counts {
+            shots: counts.values().sum(),
+            counts,
+        }
+    }
+
+    pub fn shots(&self) -> usize {
+        self.shots
+    }
+
+    pub fn get(&self, outcome: &str) -> usize {
+        self.counts.get(outcome).copied().unwrap_or(0)
+    }
+
+    pub fn probability(&self, outcome: &str) -> f64 {
+        if self.shots == 0 {
+            0.0
+        } else {
+            self.get(outcome) as f64 / self.shots as f64
+        }
+    }
+
+    /// Observed outcomes in lexicographic order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.counts
+            .iter()
+            .map(|(outcome, &n)| (outcome.as_str(), n))
+    }
+
+    pub fn most_frequent(&self) -> Option<(&str, usize)> {
+        self.iter().max_by_key(|&(_, n)| n)
+    }
+
+    /// Number of distinct outcomes observed.
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+}
+
+/// Shots drawn per RNG stream by `Counts::sample_parallel`, and run per
+/// stream by `shots::sample_parallel`.
+pub(crate) const SHOTS_PER_CHUNK: usize = 1 << 14;
+
+/// Inverse-CDF sampling of basis-state indices.
+struct Sampler {
+    cumulative: Vec<f64>,
+    total: f64,
+}
+
+impl Sampler {
+    fn new(state: &QuantumState) -> Self {
+        let mut cumulative = Vec::with_capacity(state.size());
+        let mut total = 0.0;
+        for i in 0..state.size() {
+            total += state.get(i).norm2();
+            cumulative.push(total);
+        }
+        Sampler { cumulative, total }
+    }
+
+    fn draw(&self, rng: &mut StdRng, shots: usize) -> BTreeMap<usize, usize> {
+        let mut by_index = BTreeMap::new();
+        for _ in 0..shots {
+            let r = rng.random::<f64>() * self.total;
+            let index = self
+                .cumulative
+                .partition_point(|&c| c <= r)
+                .min(self.cumulative.len() - 1);
+            *by_index.entry(index).or_insert(0) += 1;
+        }
+        by_index
+    }
+}
+
+/// Seed of the independent RNG stream `stream` under `seed` (SplitMix64).
+pub(crate) fn stream_seed(seed: u64, stream: u64) -> u64 {
+    let mut z = seed.wrapping_add(stream.wrapping_add(1).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// A seed for unseeded sampling. `RandomState` keys come from the OS where
+/// there is an entropy source; on targets without one (wasm32) they are
+/// fixed, so callers there should pass their own seed.
+pub(crate) fn entropy_seed() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+fn outcome_key(
+    index: usize,
+    num_qubits: usize,
+    measurements: &[(usize, usize)],
+    num_classical: usize,
+) -> String {
+    let qubit = |q: usize| (index >> (num_qubits - 1 - q)) & 1 == 1;
+    if measurements.is_empty() {
+        return (0..num_qubits)
+            .map(|q| if qubit(q) { '1' } else { '0' })
+            .collect();
+    }
+
+    let mut bits = vec!['0'; num_classical];
+    for &(q, c) in measurements {
+        bits[c] = if qubit(q) { '1' } else { '0' };
+    }
+    bits.into_iter().collect()
+}
+
+impl fmt::Display for Counts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .counts
+            .values()
+            .max()
+            .map_or(1, |n| n.to_string().len());
+        for (outcome, n) in self.iter() {
+            writeln!(
+                f,
+                "{}: {:>width$} ({:.2}%)",
+                outcome,
+                n,
+                self.probability(outcome) * 100.0,
+                width = width
+            )?;
+        }
+        write!(f, "{} shots", self.shots)
+    }
+}
@@ -1,12 +1,14 @@
 use crate::{Complex, Matrix, QuantumGate};
 
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CustomGateDefinition {
     Matrix(Matrix<Complex<f64>>),
     Composite(Vec<(CompositeOp, Vec<usize>)>),
 }
 
 #[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CompositeOp {
     H,
     X,
@@ -22,6 +24,7 @@ pub enum CompositeOp {
 }
 
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CustomGate {
     pub name: String,
     pub num_qubits: usize,
@@ -58,6 +61,36 @@ impl CustomGate {
         }
     }
 
+    /// The inverse gate, named with a trailing `†`. Composite gates stay
+    /// composite with their steps reversed, S† written as three S gates; a
+    /// composite using T, whose inverse is no composite step, becomes a
+    /// matrix gate.
+    pub fn adjoint(&self) -> CustomGate {
+        let name = format!("{}†", self.name);
+        let matrix = match &self.definition {
+            CustomGateDefinition::Composite(ops)
+                if !ops.iter().any(|(op, _)| matches!(op, CompositeOp::T)) =>
+            {
+                let reversed = ops
+                    .iter()
+                    .rev()
+                    .flat_map(|(op, qubits)| {
+                        let repeats = if matches!(op, CompositeOp::S) { 3 } else { 1 };
+                        std::iter::repeat_n((*op, qubits.clone()), repeats)
+                    })
+                    .collect();
+                return CustomGate::from_composite(&name, self.num_qubits, reversed);
+            }
+            CustomGateDefinition::Composite(ops) => self.compute_composite_matrix(ops),
+            CustomGateDefinition::Matrix(matrix) => matrix.clone(),
+        };
+        let dim = matrix.rows;
+        let data = (0..dim * dim)
+            .map(|i| matrix.get(i % dim, i / dim).get_conjugate())
+            .collect();
+        CustomGate::from_matrix(&name, Matrix::new(dim, dim, data))
+    }
+
     pub fn to_quantum_gate(&self) -> QuantumGate<'static> {
         match &self.definition {
             CustomGateDefinition::Matrix(matrix) => {
@@ -160,7 +193,7 @@ fn matrix_multiply(a: &Matrix<Complex<f64>>, b: &Matrix<Complex<f64>>) -> Matrix
         for j in 0..n {
             let mut sum = Complex::new(0.0, 0.0);
             for k in 0..n {
-                sum = sum + a.data[i * n + k] * b.data[k * n + j];
+                sum += a.data[i * n + k] * b.data[k * n + j];
             }
             result.data[i * n + j] = sum;
         }
@@ -0,0 +1,186 @@
+use super::circuit::defer_conditionals;
+use super::runtime::apply_kernel_in_place;
+use super::{GateOp, Kernel, QuantumCircuit, QuantumState, Runtime};
+use crate::{format_amplitude, Complex, Vector};
+use core::fmt;
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+
+/// Executes a circuit one operation at a time, exposing the intermediate
+/// state after every step.
+///
+/// Each gate is applied as its own unfused kernel, so the state after step
+/// `i` is exactly the state produced by the first `i` operations.
+/// Measurements, barriers and snapshots are no-ops, as in every runtime.
+/// Breakpoints pause `run` before the operation at their index; section
+/// titles (see `QuantumCircuit::section`) can be used in place of indices.
+pub struct CircuitDebugger<'a> {
+    circuit: &'a QuantumCircuit,
+    /// The circuit's operations with conditionals deferred, index for index.
+    deferred: Cow<'a, [GateOp]>,
+    state: Vec<Complex<f64>>,
+    position: usize,
+    breakpoints: BTreeSet<usize>,
+    last_kernel: Option<Kernel>,
+}
+
+impl<'a> CircuitDebugger<'a> {
+    pub fn new(circuit: &'a QuantumCircuit) -> Self {
+        CircuitDebugger {
+            circuit,
+            deferred: defer_conditionals(circuit.operations()),
+            state: circuit.start_state(),
+            position: 0,
+            breakpoints: BTreeSet::new(),
+            last_kernel: None,
+        }
+    }
+
+    /// Index of the next operation to execute.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.position >= self.circuit.operations().len()
+    }
+
+    pub fn next_op(&self) -> Option<&GateOp> {
+        self.circuit.operations().get(self.position)
+    }
+
+    /// Kernel applied by the most recent step, `None` if that step was a
+    /// measurement, barrier or snapshot.
+    pub fn last_kernel(&self) -> Option<&Kernel> {
+        self.last_kernel.as_ref()
+    }
+
+    /// Executes the next operation and returns it, or `None` at the end.
+    pub fn step(&mut self) -> Option<&GateOp> {
+        let op = self.circuit.operations().get(self.position)?;
+        self.last_kernel = Runtime::op_to_kernel(&self.deferred[self.position]);
+        if let Some(kernel) = &self.last_kernel {
+            apply_kernel_in_place(&mut self.state, kernel, self.circuit.num_qubits());
+        }
+        self.position += 1;
+        Some(op)
+    }
+
+    /// Executes operations until `index` is next, rewinding first if it has
+    /// already been passed.
+    pub fn run_to(&mut self, index: usize) -> &mut Self {
+        if index < self.position {
+            self.restart();
+        }
+        while self.position < index && self.step().is_some() {}
+        self
+    }
+
+    /// Runs to the barrier opening the section titled `label`. Returns
+    /// `false`, leaving the debugger untouched, if there is no such section.
+    pub fn run_to_label(&mut self, label: &str) -> bool {
+        match self.label_index(label) {
+            Some(index) => {
+                self.run_to(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Executes operations until the next breakpoint or the end of the
+    /// circuit. Always executes at least one operation.
+    pub fn run(&mut self) -> &mut Self {
+        if self.step().is_some() {
+            while !self.is_finished() && !self.breakpoints.contains(&self.position) {
+                self.step();
+            }
+        }
+        self
+    }
+
+    pub fn restart(&mut self) -> &mut Self {
+        self.state = self.circuit.start_state();
+        self.position = 0;
+        self.last_kernel = None;
+        self
+    }
+
+    pub fn add_breakpoint(&mut self, index: usize) -> &mut Self {
+        self.breakpoints.insert(index);
+        self
+    }
+
+    /// Breaks at the barrier opening the section titled `label`. Returns
+    /// `false` if there is no such section.
+    pub fn add_label_breakpoint(&mut self, label: &str) -> bool {
+        match self.label_index(label) {
+            Some(index) => {
+                self.breakpoints.insert(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn remove_breakpoint(&mut self, index: usize) -> &mut Self {
+        self.breakpoints.remove(&index);
+        self
+    }
+
+    pub fn clear_breakpoints(&mut self) -> &mut Self {
+        self.breakpoints.clear();
+        self
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = usize> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    fn label_index(&self, label: &str) -> Option<usize> {
+        self.circuit
+            .operations()
+            .iter()
+            .position(|op| matches!(op, GateOp::Barrier(_, Some(title)) if title == label))
+    }
+
+    pub fn state(&self) -> QuantumState {
+        QuantumState::new(self.state.clone())
+    }
+
+    pub fn amplitudes(&self) -> &[Complex<f64>] {
+        &self.state
+    }
+
+    pub fn probability(&self, state_index: usize) -> f64 {
+        self.state[state_index].norm2()
+    }
+
+    pub fn probabilities(&self) -> Vec<f64> {
+        self.state.iter().map(|amp| amp.norm2()).collect()
+    }
+}
+
+impl<'a> fmt::Display for CircuitDebugger<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total = self.circuit.operations().len();
+        write!(f, "Step {}/{}", self.position, total)?;
+        match self.next_op() {
+            Some(op) => writeln!(f, ", next: {} on {:?}", op.name(), op.quantum_targets())?,
+            None => writeln!(f, ", finished")?,
+        }
+        if let Some(kernel) = &self.last_kernel {
+            writeln!(f, "Last kernel: {} on {:?}", kernel.name, kernel.targets)?;
+        }
+
+        writeln!(f, "State:")?;
+        let n = self.circuit.num_qubits();
+        for (i, amp) in self.state.iter().enumerate() {
+            if amp.real.abs() > 1e-10 || amp.imaginary.abs() > 1e-10 {
+                let basis: String = format!("{:0width$b}", i, width = n);
+                writeln!(f, "  |{}⟩: {}", basis, format_amplitude(amp))?;
+            }
+        }
+        Ok(())
+    }
+}
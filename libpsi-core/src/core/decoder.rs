@@ -0,0 +1,352 @@
+use super::{Counts, CssCode};
+use std::collections::{HashMap, VecDeque};
+
+/// Turns the syndrome of one check type into the data qubits to flip.
+///
+/// A decoder for bit flips reads the Z checks and returns qubits to apply
+/// `X` to; one for phase flips reads the X checks. Syndrome bit `i` belongs
+/// to check `i` of that type, in the code's order. A decoder that cannot
+/// explain a syndrome returns an error rather than a guess.
+pub trait Decoder {
+    fn decode(&self, syndrome: &[bool]) -> Result<Vec<usize>, String>;
+}
+
+/// Most flagged checks `MatchingDecoder` pairs: its table has `2^k` entries
+/// for `k` of them.
+pub const MAX_DEFECTS: usize = 20;
+
+/// Minimum-weight correction for every syndrome, found by enumerating
+/// errors by increasing weight. The table has an entry per syndrome, so
+/// this suits small codes only.
+#[derive(Clone, Debug)]
+pub struct LookupDecoder {
+    table: HashMap<Vec<bool>, Vec<usize>>,
+}
+
+impl LookupDecoder {
+    pub fn new(checks: &[Vec<usize>], num_data: usize) -> Self {
+        let mut table = HashMap::new();
+        // Independent checks reach every syndrome; stop once they have.
+        let reachable = 1usize
+            .checked_shl(checks.len() as u32)
+            .unwrap_or(usize::MAX);
+        for weight in 0..=num_data {
+            for error in combinations(num_data, weight) {
+                table.entry(syndrome_of(checks, &error)).or_insert(error);
+            }
+            if table.len() >= reachable {
+                break;
+            }
+        }
+        LookupDecoder { table }
+    }
+
+    /// Decodes `X` errors from the code's Z checks.
+    pub fn bit_flips(code: &CssCode) -> Self {
+        Self::new(code.z_stabilizers(), code.num_data_qubits())
+    }
+
+    /// Decodes `Z` errors from the code's X checks.
+    pub fn phase_flips(code: &CssCode) -> Self {
+        Self::new(code.x_stabilizers(), code.num_data_qubits())
+    }
+}
+
+impl Decoder for LookupDecoder {
+    fn decode(&self, syndrome: &[bool]) -> Result<Vec<usize>, String> {
+        self.table
+            .get(syndrome)
+            .cloned()
+            .ok_or_else(|| format!("no error has syndrome {:?}", syndrome))
+    }
+}
+
+/// Minimum-weight perfect matching on the check graph: each data qubit is
+/// an edge between the (at most two) checks it belongs to, or between its
+/// check and the boundary. Flagged checks are paired with each other or the
+/// boundary along shortest paths so the total length is minimal.
+///
+/// The matching is exact, by dynamic programming over subsets of flagged
+/// checks, so its cost grows as `2^k` in their number `k`, and syndromes
+/// with more than `MAX_DEFECTS` are refused. That is fine for the patches
+/// simulated here; larger codes can plug in their own matcher through
+/// `Decoder`.
+#[derive(Clone, Debug)]
+pub struct MatchingDecoder {
+    num_data: usize,
+    num_checks: usize,
+    /// `distance[a][b]` between nodes, with the boundary as node `num_checks`.
+    distance: Vec<Vec<usize>>,
+    /// Qubits along a shortest path between two nodes.
+    path: Vec<Vec<Vec<usize>>>,
+}
+
+impl MatchingDecoder {
+    /// Fails if a qubit sits in more than two checks, which does not make a
+    /// graph (the Steane code, for instance).
+    pub fn new(checks: &[Vec<usize>], num_data: usize) -> Result<Self, String> {
+        let num_checks = checks.len();
+        let boundary = num_checks;
+        let mut edges: Vec<Vec<(usize, usize)>> = vec![Vec::new(); num_checks + 1];
+        for q in 0..num_data {
+            let touched: Vec<usize> = (0..num_checks)
+                .filter(|&c| checks[c].contains(&q))
+                .collect();
+            let (a, b) = match touched[..] {
+                [] => continue,
+                [a] => (a, boundary),
+                [a, b] => (a, b),
+                _ => {
+                    return Err(format!(
+                        "qubit {} is in {} checks; matching needs at most 2",
+                        q,
+                        touched.len()
+                    ))
+                }
+            };
+            edges[a].push((b, q));
+            edges[b].push((a, q));
+        }
+
+        let mut distance = Vec::with_capacity(num_checks + 1);
+        let mut path = Vec::with_capacity(num_checks + 1);
+        for source in 0..=num_checks {
+            let (d, p) = shortest_paths(&edges, source);
+            distance.push(d);
+            path.push(p);
+        }
+        Ok(MatchingDecoder {
+            num_data,
+            num_checks,
+            distance,
+            path,
+        })
+    }
+
+    pub fn bit_flips(code: &CssCode) -> Result<Self, String> {
+        Self::new(code.z_stabilizers(), code.num_data_qubits())
+    }
+
+    pub fn phase_flips(code: &CssCode) -> Result<Self, String> {
+        Self::new(code.x_stabilizers(), code.num_data_qubits())
+    }
+}
+
+impl Decoder for MatchingDecoder {
+    fn decode(&self, syndrome: &[bool]) -> Result<Vec<usize>, String> {
+        if syndrome.len() != self.num_checks {
+            return Err(format!(
+                "syndrome has {} bits for {} checks",
+                syndrome.len(),
+                self.num_checks
+            ));
+        }
+        let defects: Vec<usize> = (0..self.num_checks).filter(|&c| syndrome[c]).collect();
+        let k = defects.len();
+        if k > MAX_DEFECTS {
+            return Err(format!(
+                "{} flagged checks exceed the matching limit of {}",
+                k, MAX_DEFECTS
+            ));
+        }
+        let boundary = self.num_checks;
+
+        // best[mask] = (cost, partner of the lowest defect in mask) for
+        // matching the defects in mask; partner `k` is the boundary.
+        let full = (1usize << k) - 1;
+        let mut best = vec![(usize::MAX, 0); 1 << k];
+        best[0] = (0, 0);
+        for mask in 1..=full {
+            let i = mask.trailing_zeros() as usize;
+            let rest = mask & !(1 << i);
+            let mut choice = (
+                self.distance[defects[i]][boundary].saturating_add(best[rest].0),
+                k,
+            );
+            let mut others = rest;
+            while others != 0 {
+                let j = others.trailing_zeros() as usize;
+                others &= others - 1;
+                let cost =
+                    self.distance[defects[i]][defects[j]].saturating_add(best[rest & !(1 << j)].0);
+                if cost < choice.0 {
+                    choice = (cost, j);
+                }
+            }
+            best[mask] = choice;
+        }
+        // Some defect reaches neither another defect nor the boundary.
+        if best[full].0 == usize::MAX {
+            return Err(format!(
+                "no set of flips explains the flagged checks {:?}",
+                defects
+            ));
+        }
+
+        let mut flips = vec![false; self.num_data];
+        let mut mask = full;
+        while mask != 0 {
+            let i = mask.trailing_zeros() as usize;
+            let partner = best[mask].1;
+            let (to, used) = if partner == k {
+                (boundary, 1 << i)
+            } else {
+                (defects[partner], (1 << i) | (1 << partner))
+            };
+            for &q in &self.path[defects[i]][to] {
+                flips[q] ^= true;
+            }
+            mask &= !used;
+        }
+        Ok((0..flips.len()).filter(|&q| flips[q]).collect())
+    }
+}
+
+/// Breadth-first distances and paths (as qubits) from `source`.
+fn shortest_paths(edges: &[Vec<(usize, usize)>], source: usize) -> (Vec<usize>, Vec<Vec<usize>>) {
+    let mut distance = vec![usize::MAX; edges.len()];
+    let mut path = vec![Vec::new(); edges.len()];
+    let mut queue = VecDeque::from([source]);
+    distance[source] = 0;
+    while let Some(node) = queue.pop_front() {
+        for &(next, qubit) in &edges[node] {
+            if distance[next] == usize::MAX {
+                distance[next] = distance[node] + 1;
+                let mut p = path[node].clone();
+                p.push(qubit);
+                path[next] = p;
+                queue.push_back(next);
+            }
+        }
+    }
+    (distance, path)
+}
+
+fn syndrome_of(checks: &[Vec<usize>], error: &[usize]) -> Vec<bool> {
+    checks
+        .iter()
+        .map(|check| error.iter().filter(|q| check.contains(q)).count() % 2 == 1)
+        .collect()
+}
+
+/// All `k`-subsets of `0..n` in lexicographic order.
+fn combinations(n: usize, k: usize) -> Vec<Vec<usize>> {
+    let mut all = Vec::new();
+    let mut current: Vec<usize> = (0..k).collect();
+    if k > n {
+        return all;
+    }
+    loop {
+        all.push(current.clone());
+        let Some(i) = (0..k).rev().find(|&i| current[i] < n - k + i) else {
+            return all;
+        };
+        current[i] += 1;
+        for j in i + 1..k {
+            current[j] = current[j - 1] + 1;
+        }
+    }
+}
+
+/// Outcome of decoding a memory experiment.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DecodingStats {
+    pub shots: usize,
+    /// Shots where some syndrome round flagged a check.
+    pub flagged: usize,
+    /// Shots whose data read out as logical 1 without correction.
+    pub raw_errors: usize,
+    /// Shots still logical 1 after the decoder's correction.
+    pub logical_errors: usize,
+}
+
+impl DecodingStats {
+    pub fn raw_error_rate(&self) -> f64 {
+        rate(self.raw_errors, self.shots)
+    }
+
+    pub fn logical_error_rate(&self) -> f64 {
+        rate(self.logical_errors, self.shots)
+    }
+}
+
+fn rate(n: usize, shots: usize) -> f64 {
+    if shots == 0 {
+        0.0
+    } else {
+        n as f64 / shots as f64
+    }
+}
+
+impl CssCode {
+    /// Decodes the counts of `memory_circuit(rounds)` (or a circuit with the
+    /// same classical layout) with a bit-flip `decoder`, and compares the
+    /// corrected logical `Z` parity with the encoded |0⟩.
+    ///
+    /// The Z-check syndromes of the rounds, followed by the one recomputed
+    /// from the data readout, are compared round to round: the checks that
+    /// changed (the detection events) locate the flips made since the round
+    /// before, starting from the trivial syndrome of the encoded state. Each
+    /// round's events are decoded on their own and the corrections combined,
+    /// so flips spread over several rounds are corrected even where their
+    /// final syndrome alone would be decoded wrongly. Fails if the decoder
+    /// cannot explain some round's events.
+    pub fn decode_memory(
+        &self,
+        counts: &Counts,
+        rounds: usize,
+        decoder: &dyn Decoder,
+    ) -> Result<DecodingStats, String> {
+        let m = self.num_stabilizers();
+        let n = self.num_data_qubits();
+        let x_count = self.x_stabilizers().len();
+        let mut stats = DecodingStats {
+            shots: counts.shots(),
+            ..DecodingStats::default()
+        };
+        for (outcome, shots) in counts.iter() {
+            let bits: Vec<bool> = outcome.bytes().map(|b| b == b'1').collect();
+            assert_eq!(
+                bits.len(),
+                rounds * m + n,
+                "outcome {} does not match {} rounds of {}",
+                outcome,
+                rounds,
+                self.name()
+            );
+            let mut data = bits[rounds * m..].to_vec();
+            let logical =
+                |data: &[bool]| self.logical_z().iter().filter(|&&q| data[q]).count() % 2 == 1;
+
+            if bits[..rounds * m].iter().any(|&b| b) {
+                stats.flagged += shots;
+            }
+            if logical(&data) {
+                stats.raw_errors += shots;
+            }
+            let readout: Vec<bool> = self
+                .z_stabilizers()
+                .iter()
+                .map(|check| check.iter().filter(|&&q| data[q]).count() % 2 == 1)
+                .collect();
+            let syndromes = (0..rounds)
+                .map(|r| bits[r * m + x_count..(r + 1) * m].to_vec())
+                .chain([readout]);
+            let mut previous = vec![false; self.z_stabilizers().len()];
+            for syndrome in syndromes {
+                let events: Vec<bool> =
+                    syndrome.iter().zip(&previous).map(|(a, b)| a ^ b).collect();
+                if events.iter().any(|&e| e) {
+                    for q in decoder.decode(&events)? {
+                        data[q] ^= true;
+                    }
+                }
+                previous = syndrome;
+            }
+            if logical(&data) {
+                stats.logical_errors += shots;
+            }
+        }
+        Ok(stats)
+    }
+}
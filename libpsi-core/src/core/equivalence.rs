@@ -0,0 +1,138 @@
+use super::circuit::defer_conditionals;
+use super::runtime::PARALLEL_THRESHOLD;
+use super::{Kernel, QuantumCircuit, Runtime, RuntimeConfig};
+use crate::maths::parallel::*;
+use crate::{complex, Complex, Matrix};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Widest circuits compared on every basis state, i.e. as whole unitaries.
+const UNITARY_QUBITS: usize = 8;
+
+/// Random input states tried on wider circuits. Two unitaries that differ
+/// anywhere move almost every random state apart, so a handful suffices.
+const STIMULI: usize = 4;
+
+/// The matrix of `circuit`, column `j` being the state the circuit's
+/// kernels make of `|j⟩`. The columns are independent runs, computed on
+/// all cores.
+pub(crate) fn unitary(circuit: &QuantumCircuit) -> Matrix<Complex<f64>> {
+    let n = circuit.num_qubits();
+    let dim = 1usize << n;
+    let kernels = kernels(circuit);
+    let config = RuntimeConfig::new().simd();
+    let columns: Vec<Vec<Complex<f64>>> = (0..dim)
+        .into_par_iter()
+        .map(|j| run(&config, &kernels, n, basis_state(dim, j), false))
+        .collect();
+    let mut matrix = Matrix::new(dim, dim, vec![complex!(0.0, 0.0); dim * dim]);
+    for (j, column) in columns.iter().enumerate() {
+        for (i, &amplitude) in column.iter().enumerate() {
+            matrix.set(i, j, amplitude);
+        }
+    }
+    matrix
+}
+
+/// Whether `a` and `b` act as the same unitary up to a global phase: the
+/// largest `‖b|ψ⟩ − e^{iφ} a|ψ⟩‖` over the inputs tried stays within
+/// `tolerance`, with one phase `φ` for all of them. Up to `UNITARY_QUBITS`
+/// the inputs are all basis states, so the whole unitaries are compared;
+/// beyond that, `STIMULI` fixed random states. Measurements are deferred
+/// to the end as by the runtimes, so they are not compared.
+pub(crate) fn equivalent(a: &QuantumCircuit, b: &QuantumCircuit, tolerance: f64) -> bool {
+    let n = a.num_qubits();
+    if b.num_qubits() != n {
+        return false;
+    }
+    let dim = 1usize << n;
+    let outputs: Vec<_> = if n <= UNITARY_QUBITS {
+        let (first, second) = (unitary(a), unitary(b));
+        let column = |matrix: &Matrix<Complex<f64>>, j: usize| -> Vec<Complex<f64>> {
+            (0..dim).map(|i| matrix.get(i, j)).collect()
+        };
+        (0..dim)
+            .map(|j| (column(&first, j), column(&second, j)))
+            .collect()
+    } else {
+        let config = RuntimeConfig::optimal();
+        let use_parallel = n >= PARALLEL_THRESHOLD;
+        let (first, second) = (kernels(a), kernels(b));
+        random_states(n)
+            .into_iter()
+            .map(|input| {
+                (
+                    run(&config, &first, n, input.clone(), use_parallel),
+                    run(&config, &second, n, input, use_parallel),
+                )
+            })
+            .collect()
+    };
+    // Σ⟨a ψ|b ψ⟩ is e^{iφ} times the number of inputs when b = e^{iφ} a.
+    let total = outputs
+        .iter()
+        .fold(complex!(0.0, 0.0), |sum, (x, y)| sum + inner(x, y));
+    let phase = if total.abs() > 0.0 {
+        complex!(total.real / total.abs(), total.imaginary / total.abs())
+    } else {
+        complex!(1.0, 0.0)
+    };
+    outputs.iter().all(|(x, y)| {
+        let distance: f64 = x
+            .iter()
+            .zip(y)
+            .map(|(x, y)| (*y - phase * *x).norm2())
+            .sum();
+        distance.sqrt() <= tolerance
+    })
+}
+
+fn kernels(circuit: &QuantumCircuit) -> Vec<Kernel> {
+    let n = circuit.num_qubits();
+    let deferred = defer_conditionals(circuit.operations());
+    let mut batch = Runtime::build_kernel_batch(n, &deferred);
+    batch.optimize();
+    batch.kernels().to_vec()
+}
+
+fn run(
+    config: &RuntimeConfig,
+    kernels: &[Kernel],
+    n: usize,
+    mut state: Vec<Complex<f64>>,
+    use_parallel: bool,
+) -> Vec<Complex<f64>> {
+    config.execute_kernels(&mut state, kernels, n, use_parallel, None, kernels.len());
+    state
+}
+
+fn basis_state(dim: usize, j: usize) -> Vec<Complex<f64>> {
+    let mut state = vec![complex!(0.0, 0.0); dim];
+    state[j] = complex!(1.0, 0.0);
+    state
+}
+
+/// `STIMULI` normalised random states of `n` qubits, the same for every
+/// call.
+fn random_states(n: usize) -> Vec<Vec<Complex<f64>>> {
+    let mut rng = StdRng::seed_from_u64(n as u64);
+    (0..STIMULI)
+        .map(|_| {
+            let state: Vec<Complex<f64>> = (0..1usize << n)
+                .map(|_| complex!(rng.random_range(-1.0..1.0), rng.random_range(-1.0..1.0)))
+                .collect();
+            let norm = state.iter().map(Complex::norm2).sum::<f64>().sqrt();
+            state
+                .into_iter()
+                .map(|amplitude| complex!(amplitude.real / norm, amplitude.imaginary / norm))
+                .collect()
+        })
+        .collect()
+}
+
+/// `⟨a|b⟩`.
+fn inner(a: &[Complex<f64>], b: &[Complex<f64>]) -> Complex<f64> {
+    a.iter().zip(b).fold(complex!(0.0, 0.0), |sum, (x, y)| {
+        sum + x.get_conjugate() * *y
+    })
+}
@@ -0,0 +1,408 @@
+use super::circuit::defer_conditionals;
+use super::runtime::{apply_kernel_in_place, initial_state, PARALLEL_THRESHOLD};
+use super::{GateOp, Pauli, QuantumState, Runtime};
+use crate::maths::parallel::*;
+use crate::{complex, Complex, Matrix, Vector};
+use std::f64::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+/// Time step `Runtime::WFEvolution` integrates each gate with. Gates run for
+/// unit time, so this takes 100 Runge-Kutta steps per gate and keeps the
+/// error per gate around 1e-8.
+pub const DEFAULT_TIME_STEP: f64 = 1e-2;
+
+/// A Hermitian operator on `num_qubits` qubits: a dense matrix, or a real
+/// combination of Pauli products applied without building one.
+#[derive(Clone, Debug)]
+pub struct Hamiltonian {
+    num_qubits: usize,
+    terms: Terms,
+}
+
+#[derive(Clone, Debug)]
+enum Terms {
+    Dense(Matrix<Complex<f64>>),
+    /// `(coefficient, X mask, Z mask, number of Ys)` per Pauli product, the
+    /// masks holding qubit `q` at bit `num_qubits - 1 - q`.
+    Paulis(Vec<(f64, usize, usize, usize)>),
+}
+
+impl Hamiltonian {
+    /// The zero operator, to which `add_term` adds Pauli products.
+    pub fn new(num_qubits: usize) -> Self {
+        Hamiltonian {
+            num_qubits,
+            terms: Terms::Paulis(Vec::new()),
+        }
+    }
+
+    /// Checks that `matrix` is Hermitian and `2ⁿ × 2ⁿ`.
+    pub fn from_matrix(matrix: Matrix<Complex<f64>>) -> Result<Self, String> {
+        let dim = matrix.rows;
+        if matrix.cols != dim || !dim.is_power_of_two() {
+            return Err(format!(
+                "a Hamiltonian needs a 2ⁿ × 2ⁿ matrix, not {} × {}",
+                matrix.rows, matrix.cols
+            ));
+        }
+        for row in 0..dim {
+            for col in row..dim {
+                let difference =
+                    matrix.data[row * dim + col] - matrix.data[col * dim + row].get_conjugate();
+                if difference.norm2() > 1e-20 {
+                    return Err(format!("matrix is not Hermitian at ({}, {})", row, col));
+                }
+            }
+        }
+        Ok(Hamiltonian {
+            num_qubits: dim.trailing_zeros() as usize,
+            terms: Terms::Dense(matrix),
+        })
+    }
+
+    /// Adds `coefficient` times the product of `factors`, given as
+    /// `(qubit, Pauli)` pairs like `SnapshotKind::Expectation`.
+    pub fn add_term(&mut self, coefficient: f64, factors: &[(usize, Pauli)]) -> &mut Self {
+        let n = self.num_qubits;
+        let (mut x, mut z, mut y) = (0, 0, 0);
+        for &(qubit, pauli) in factors {
+            assert!(qubit < n, "qubit {} out of range for {} qubits", qubit, n);
+            let bit = 1 << (n - 1 - qubit);
+            assert!(
+                (x | z) & bit == 0,
+                "qubit {} appears twice in a term",
+                qubit
+            );
+            match pauli {
+                Pauli::I => {}
+                Pauli::X => x |= bit,
+                Pauli::Z => z |= bit,
+                Pauli::Y => {
+                    x |= bit;
+                    z |= bit;
+                    y += 1;
+                }
+            }
+        }
+        match &mut self.terms {
+            Terms::Paulis(terms) => terms.push((coefficient, x, z, y)),
+            Terms::Dense(_) => panic!("cannot add Pauli terms to a dense Hamiltonian"),
+        }
+        self
+    }
+
+    pub fn num_qubits(&self) -> usize {
+        self.num_qubits
+    }
+
+    /// `H|ψ⟩`.
+    pub fn apply(&self, state: &[Complex<f64>], parallel: bool) -> Vec<Complex<f64>> {
+        let dim = 1 << self.num_qubits;
+        assert_eq!(
+            state.len(),
+            dim,
+            "expected a state on {} qubits",
+            self.num_qubits
+        );
+        match &self.terms {
+            Terms::Dense(matrix) => map_indices(dim, parallel, |row| {
+                (0..dim).fold(complex!(0.0, 0.0), |sum, col| {
+                    sum + matrix.data[row * dim + col] * state[col]
+                })
+            }),
+            // P|j⊕x⟩ = i^y (-1)^|(j⊕x) & z| |j⟩ for the product with masks x, z.
+            Terms::Paulis(terms) => map_indices(dim, parallel, |row| {
+                terms
+                    .iter()
+                    .fold(complex!(0.0, 0.0), |sum, &(coefficient, x, z, y)| {
+                        let source = row ^ x;
+                        let sign = if (source & z).count_ones() % 2 == 0 {
+                            1.0
+                        } else {
+                            -1.0
+                        };
+                        let phase = match y % 4 {
+                            0 => complex!(sign, 0.0),
+                            1 => complex!(0.0, sign),
+                            2 => complex!(-sign, 0.0),
+                            _ => complex!(0.0, -sign),
+                        };
+                        sum + phase * complex!(coefficient, 0.0) * state[source]
+                    })
+            }),
+        }
+    }
+}
+
+/// Integrates the Schrödinger equation `d|ψ⟩/dt = -iH|ψ⟩` with the classic
+/// fourth-order Runge-Kutta method at a fixed time step.
+///
+/// `run` simulates a circuit this way, evolving each gate under a generator
+/// `G` with `exp(-iG) = U` for unit time (`θ/2·X` for `Rx(θ)`, `π/2·(I − U)`
+/// for self-inverse gates such as H and CNOT, and so on). Custom gates have
+/// no known generator and are applied as matrices. The result approaches
+/// the gate-based state as the time step shrinks.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimeEvolution {
+    time_step: f64,
+    parallel: bool,
+}
+
+impl TimeEvolution {
+    pub fn new(time_step: f64) -> Self {
+        assert!(time_step > 0.0, "time step must be positive");
+        TimeEvolution {
+            time_step,
+            parallel: false,
+        }
+    }
+
+    /// Computes `H|ψ⟩` and the Runge-Kutta stages on all cores.
+    pub fn parallel(mut self) -> Self {
+        self.parallel = true;
+        self
+    }
+
+    pub fn time_step(&self) -> f64 {
+        self.time_step
+    }
+
+    /// `exp(-iHt)|ψ⟩`, taking the largest step no longer than `time_step`
+    /// that divides `time` evenly.
+    pub fn evolve(
+        &self,
+        hamiltonian: &Hamiltonian,
+        state: &QuantumState,
+        time: f64,
+    ) -> QuantumState {
+        let amplitudes: Vec<Complex<f64>> = (0..state.size()).map(|i| state.get(i)).collect();
+        let evolved = self.integrate(amplitudes, time, |psi| {
+            hamiltonian.apply(psi, self.parallel)
+        });
+        QuantumState::new(evolved)
+    }
+
+    /// The final state of `operations` on `|0…0⟩`, each gate integrated
+    /// from its generator. Measurements, barriers and snapshots are skipped.
+    pub fn run(&self, num_qubits: usize, operations: &[GateOp]) -> QuantumState {
+        self.run_from(initial_state(num_qubits), num_qubits, operations)
+    }
+
+    /// `run` from `state` instead of `|0…0⟩`.
+    pub(crate) fn run_from(
+        &self,
+        mut state: Vec<Complex<f64>>,
+        num_qubits: usize,
+        operations: &[GateOp],
+    ) -> QuantumState {
+        for op in defer_conditionals(operations).iter() {
+            let Some(kernel) = Runtime::op_to_kernel(op) else {
+                continue;
+            };
+            match generators(op, &kernel.matrix) {
+                Some(generators) => {
+                    for generator in generators {
+                        let mut local = kernel.clone();
+                        local.matrix = generator;
+                        state = self.integrate(state, 1.0, |psi| {
+                            let mut h_psi = psi.to_vec();
+                            apply_kernel_in_place(&mut h_psi, &local, num_qubits);
+                            h_psi
+                        });
+                    }
+                }
+                None => apply_kernel_in_place(&mut state, &kernel, num_qubits),
+            }
+        }
+        QuantumState::new(state)
+    }
+
+    fn integrate(
+        &self,
+        mut state: Vec<Complex<f64>>,
+        time: f64,
+        hamiltonian: impl Fn(&[Complex<f64>]) -> Vec<Complex<f64>>,
+    ) -> Vec<Complex<f64>> {
+        let steps = (time.abs() / self.time_step).ceil().max(1.0) as usize;
+        let h = time / steps as f64;
+        let parallel = self.parallel;
+        // -iH|ψ⟩
+        let derivative = |psi: &[Complex<f64>]| {
+            let h_psi = hamiltonian(psi);
+            map_indices(psi.len(), parallel, |i| {
+                complex!(h_psi[i].imaginary, -h_psi[i].real)
+            })
+        };
+        let step = |psi: &[Complex<f64>], k: &[Complex<f64>], scale: f64| {
+            map_indices(psi.len(), parallel, |i| {
+                psi[i] + k[i] * complex!(scale, 0.0)
+            })
+        };
+
+        for _ in 0..steps {
+            let k1 = derivative(&state);
+            let k2 = derivative(&step(&state, &k1, h / 2.0));
+            let k3 = derivative(&step(&state, &k2, h / 2.0));
+            let k4 = derivative(&step(&state, &k3, h));
+            state = map_indices(state.len(), parallel, |i| {
+                state[i]
+                    + (k1[i] + k2[i] * complex!(2.0, 0.0) + k3[i] * complex!(2.0, 0.0) + k4[i])
+                        * complex!(h / 6.0, 0.0)
+            });
+        }
+        state
+    }
+}
+
+impl Default for TimeEvolution {
+    fn default() -> Self {
+        TimeEvolution::new(DEFAULT_TIME_STEP)
+    }
+}
+
+/// Hermitian `G` on the gate's targets with `exp(-iG)` equal to the gate,
+/// or a sequence of them whose evolutions compose to it. `None` for custom
+/// gates.
+fn generators(op: &GateOp, matrix: &Matrix<Complex<f64>>) -> Option<Vec<Matrix<Complex<f64>>>> {
+    let r = |x: f64| complex!(x, 0.0);
+    let zero = r(0.0);
+    let from = |rows: Vec<Vec<Complex<f64>>>| {
+        let dim = rows.len();
+        Matrix::new(dim, dim, rows.into_iter().flatten().collect())
+    };
+    // exp(-iθ/2·σ) for the Pauli σ.
+    let x = |theta: f64| from(vec![vec![zero, r(theta / 2.0)], vec![r(theta / 2.0), zero]]);
+    let y = |theta: f64| {
+        from(vec![
+            vec![zero, complex!(0.0, -theta / 2.0)],
+            vec![complex!(0.0, theta / 2.0), zero],
+        ])
+    };
+    let z = |theta: f64| {
+        from(vec![
+            vec![r(theta / 2.0), zero],
+            vec![zero, r(-theta / 2.0)],
+        ])
+    };
+    // diag(1, e^{iθ})
+    let phase = |theta: f64| from(vec![vec![zero, zero], vec![zero, r(-theta)]]);
+    // |1⟩⟨1| ⊗ g
+    let controlled = |g: Matrix<Complex<f64>>| {
+        let mut data = vec![zero; 16];
+        for row in 0..2 {
+            for col in 0..2 {
+                data[(row + 2) * 4 + col + 2] = g.data[row * 2 + col];
+            }
+        }
+        Matrix::new(4, 4, data)
+    };
+    // π/2·(I − U) for U = U⁻¹, scaled by `scale`.
+    let involution = |scale: f64| {
+        let dim = matrix.rows;
+        let data = (0..dim * dim)
+            .map(|i| {
+                let identity = if i / dim == i % dim { r(1.0) } else { zero };
+                (identity - matrix.data[i]) * r(scale * FRAC_PI_2)
+            })
+            .collect();
+        Matrix::new(dim, dim, data)
+    };
+
+    let sequence = match op {
+        GateOp::H(_)
+        | GateOp::X(_)
+        | GateOp::Y(_)
+        | GateOp::Z(_)
+        | GateOp::CNOT(_, _)
+        | GateOp::CZ(_, _)
+        | GateOp::SWAP(_, _)
+        | GateOp::CY(_, _)
+        | GateOp::CH(_, _)
+        | GateOp::ECR(_, _)
+        | GateOp::CCNOT(_, _, _)
+        | GateOp::CSWAP(_, _, _)
+        | GateOp::MCX(_, _) => vec![involution(1.0)],
+        GateOp::S(_) => vec![phase(FRAC_PI_2)],
+        GateOp::Sdg(_) => vec![phase(-FRAC_PI_2)],
+        GateOp::T(_) => vec![phase(FRAC_PI_4)],
+        GateOp::Tdg(_) => vec![phase(-FRAC_PI_4)],
+        // √X = exp(iπ/4·(I − X)): half the X generator, negated.
+        GateOp::Sx(_) => vec![from(vec![
+            vec![r(-FRAC_PI_4), r(FRAC_PI_4)],
+            vec![r(FRAC_PI_4), r(-FRAC_PI_4)],
+        ])],
+        GateOp::Sxdg(_) => vec![from(vec![
+            vec![r(FRAC_PI_4), r(-FRAC_PI_4)],
+            vec![r(-FRAC_PI_4), r(FRAC_PI_4)],
+        ])],
+        GateOp::Rx(_, theta) => vec![x(*theta)],
+        GateOp::Ry(_, theta) => vec![y(*theta)],
+        GateOp::Rz(_, theta) => vec![z(*theta)],
+        GateOp::P(_, theta) | GateOp::U1(_, theta) => vec![phase(*theta)],
+        // U3(θ, φ, λ) = P(φ)·Ry(θ)·P(λ), applied right to left.
+        GateOp::U2(_, phi, lambda) => vec![phase(*lambda), y(PI / 2.0), phase(*phi)],
+        GateOp::U3(_, theta, phi, lambda) => vec![phase(*lambda), y(*theta), phase(*phi)],
+        GateOp::CRx(_, _, theta) => vec![controlled(x(*theta))],
+        GateOp::CRy(_, _, theta) => vec![controlled(y(*theta))],
+        GateOp::CRz(_, _, theta) => vec![controlled(z(*theta))],
+        GateOp::CP(_, _, theta) => vec![controlled(phase(*theta))],
+        GateOp::CSX(_, _) => vec![controlled(from(vec![
+            vec![r(-FRAC_PI_4), r(FRAC_PI_4)],
+            vec![r(FRAC_PI_4), r(-FRAC_PI_4)],
+        ]))],
+        // π/2·(I − CNOT) one way, then the other.
+        GateOp::DCX(_, _) => vec![
+            from(vec![
+                vec![zero, zero, zero, zero],
+                vec![zero, zero, zero, zero],
+                vec![zero, zero, r(FRAC_PI_2), r(-FRAC_PI_2)],
+                vec![zero, zero, r(-FRAC_PI_2), r(FRAC_PI_2)],
+            ]),
+            from(vec![
+                vec![zero, zero, zero, zero],
+                vec![zero, r(FRAC_PI_2), zero, r(-FRAC_PI_2)],
+                vec![zero, zero, zero, zero],
+                vec![zero, r(-FRAC_PI_2), zero, r(FRAC_PI_2)],
+            ]),
+        ],
+        // iSWAP = exp(iπ/4·(X⊗X + Y⊗Y)).
+        GateOp::ISwap(_, _) => vec![from(vec![
+            vec![zero, zero, zero, zero],
+            vec![zero, zero, r(-FRAC_PI_2), zero],
+            vec![zero, r(-FRAC_PI_2), zero, zero],
+            vec![zero, zero, zero, zero],
+        ])],
+        // √SWAP is i on the singlet and 1 on the triplet: π/4·(SWAP − I).
+        GateOp::SqrtSwap(_, _) => vec![from(vec![
+            vec![zero, zero, zero, zero],
+            vec![zero, r(-FRAC_PI_4), r(FRAC_PI_4), zero],
+            vec![zero, r(FRAC_PI_4), r(-FRAC_PI_4), zero],
+            vec![zero, zero, zero, zero],
+        ])],
+        // θ/2·σ⊗σ
+        GateOp::Rxx(_, _, theta) => vec![x(*theta).kronecker(&x(2.0))],
+        GateOp::Ryy(_, _, theta) => vec![y(*theta).kronecker(&y(2.0))],
+        GateOp::Rzz(_, _, theta) => vec![z(*theta).kronecker(&z(2.0))],
+        GateOp::Custom(_, _)
+        | GateOp::MCU { .. }
+        | GateOp::Measure(_, _)
+        | GateOp::Reset(_)
+        | GateOp::Barrier(_, _)
+        | GateOp::Snapshot(_, _)
+        | GateOp::Conditional { .. }
+        | GateOp::Parameterized { .. } => return None,
+    };
+    Some(sequence)
+}
+
+fn map_indices(
+    dim: usize,
+    parallel: bool,
+    f: impl Fn(usize) -> Complex<f64> + Sync + Send,
+) -> Vec<Complex<f64>> {
+    // Below the runtimes' threshold, threads cost more than they save.
+    if parallel && dim >= 1 << PARALLEL_THRESHOLD {
+        (0..dim).into_par_iter().map(f).collect()
+    } else {
+        (0..dim).map(f).collect()
+    }
+}
@@ -0,0 +1,767 @@
+use super::circuit::try_defer_conditionals;
+use super::counts::{entropy_seed, stream_seed};
+use super::{
+    CompositeOp, Counts, CustomGateDefinition, GateOp, QuantumCircuit, QuantumState,
+    SimulatorBackend,
+};
+use crate::maths::parallel::*;
+use crate::{complex, Complex, Vector};
+use std::collections::BTreeMap;
+use std::f64::consts::{FRAC_1_SQRT_2, FRAC_PI_2, FRAC_PI_4, PI};
+
+const ANGLE_TOLERANCE: f64 = 1e-9;
+
+/// Most non-Clifford phase gates a circuit may contain: each one doubles
+/// the number of stabilizer terms.
+pub const MAX_NON_CLIFFORD: usize = 20;
+
+/// The gates the CH-form updates directly; everything else is decomposed
+/// into these.
+#[derive(Clone, Copy, Debug)]
+enum Elementary {
+    H(usize),
+    S(usize),
+    CZ(usize, usize),
+    CX(usize, usize),
+    /// `diag(1, e^{iθ})` at an angle that is not a multiple of π/2.
+    Phase(usize, f64),
+}
+
+/// Clifford+T simulation by sum over Cliffords: every non-Clifford phase
+/// gate `diag(1, e^{iθ})` is split into `a·I + b·Z`, so a circuit with `t`
+/// of them leaves a sum of `2^t` stabilizer states. Each term is kept in
+/// the CH-form of Bravyi et al. (2019), which updates in `O(n)` to `O(n²)`
+/// per gate and gives any amplitude in `O(n²)`.
+///
+/// Memory and time grow with `2^t` rather than `2ⁿ`, so near-Clifford
+/// circuits on far more qubits than a state vector holds stay in reach.
+/// Rotations at multiples of π/2 are Clifford and do not count towards `t`.
+/// Amplitudes and sampling use bitstrings with qubit 0 first.
+#[derive(Clone)]
+pub struct ExtendedStabilizer {
+    num_qubits: usize,
+    terms: Vec<ChForm>,
+}
+
+impl ExtendedStabilizer {
+    /// `|0…0⟩` on `num_qubits` qubits.
+    pub fn new(num_qubits: usize) -> Self {
+        ExtendedStabilizer {
+            num_qubits,
+            terms: vec![ChForm::new(num_qubits)],
+        }
+    }
+
+    /// The final state of `circuit`. Measurements, barriers and snapshots
+    /// are skipped; gates given by a matrix are not supported.
+    pub fn from_circuit(circuit: &QuantumCircuit) -> Result<Self, String> {
+        let gates = decompose_circuit(circuit)?;
+        let mut state = ExtendedStabilizer::new(circuit.num_qubits());
+        for gate in gates {
+            state.apply_elementary(gate);
+        }
+        Ok(state)
+    }
+
+    /// Applies `op`, failing on gates given by a matrix or when the
+    /// number of terms would pass `2^MAX_NON_CLIFFORD`.
+    pub fn apply(&mut self, op: &GateOp) -> Result<(), String> {
+        let mut gates = Vec::new();
+        decompose(op, &mut gates)?;
+        let added = gates
+            .iter()
+            .filter(|g| matches!(g, Elementary::Phase(..)))
+            .count();
+        if self.terms.len() << added > 1 << MAX_NON_CLIFFORD {
+            return Err(format!(
+                "more than {} non-Clifford phase gates",
+                MAX_NON_CLIFFORD
+            ));
+        }
+        for gate in gates {
+            self.apply_elementary(gate);
+        }
+        Ok(())
+    }
+
+    pub fn num_qubits(&self) -> usize {
+        self.num_qubits
+    }
+
+    /// Number of stabilizer terms in the sum.
+    pub fn rank(&self) -> usize {
+        self.terms.len()
+    }
+
+    /// `⟨bits|ψ⟩`.
+    pub fn amplitude(&self, bits: &[bool]) -> Complex<f64> {
+        assert_eq!(
+            bits.len(),
+            self.num_qubits,
+            "expected {} bits",
+            self.num_qubits
+        );
+        self.amplitude_of(&pack(bits))
+    }
+
+    pub fn probability(&self, bits: &[bool]) -> f64 {
+        self.amplitude(bits).norm2()
+    }
+
+    /// The full state vector, for circuits small enough to hold one.
+    pub fn to_state(&self) -> QuantumState {
+        let n = self.num_qubits;
+        assert!(
+            n < usize::BITS as usize,
+            "{} qubits is too many for a state vector",
+            n
+        );
+        let amplitudes: Vec<Complex<f64>> = (0..1usize << n)
+            .into_par_iter()
+            .map(|index| {
+                let bits: Vec<bool> = (0..n).map(|q| (index >> (n - 1 - q)) & 1 == 1).collect();
+                self.amplitude_of(&pack(&bits))
+            })
+            .collect();
+        QuantumState::new(amplitudes)
+    }
+
+    /// Draws `shots` samples of `circuit` without a state vector, by
+    /// gate-by-gate sampling (Bravyi, Gosset and Liu, 2022): each shot's
+    /// bitstring follows the circuit, and after every Hadamard the touched
+    /// bit is redrawn from its conditional distribution, which takes two
+    /// amplitudes. Outcomes follow the bit order of `Counts`.
+    pub fn sample(
+        circuit: &QuantumCircuit,
+        shots: usize,
+        seed: Option<u64>,
+    ) -> Result<Counts, String> {
+        let gates = decompose_circuit(circuit)?;
+        let n = circuit.num_qubits();
+        let seed = seed.unwrap_or_else(entropy_seed);
+        let mut state = ExtendedStabilizer::new(n);
+        let mut samples = vec![vec![0u64; words(n)]; shots];
+
+        for (step, gate) in gates.into_iter().enumerate() {
+            state.apply_elementary(gate);
+            match gate {
+                Elementary::H(q) => {
+                    samples = samples
+                        .into_par_iter()
+                        .enumerate()
+                        .map(|(shot, mut bits)| {
+                            set(&mut bits, q, false);
+                            let p0 = state.amplitude_of(&bits).norm2();
+                            set(&mut bits, q, true);
+                            let p1 = state.amplitude_of(&bits).norm2();
+                            let u =
+                                uniform(stream_seed(stream_seed(seed, shot as u64), step as u64));
+                            set(&mut bits, q, u * (p0 + p1) >= p0);
+                            bits
+                        })
+                        .collect();
+                }
+                Elementary::CX(c, t) => {
+                    for bits in &mut samples {
+                        if get(bits, c) {
+                            flip(bits, t);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let measurements = circuit.measurements();
+        let mut outcomes: BTreeMap<String, usize> = BTreeMap::new();
+        for bits in &samples {
+            let key: String = if measurements.is_empty() {
+                (0..n)
+                    .map(|q| if get(bits, q) { '1' } else { '0' })
+                    .collect()
+            } else {
+                let mut key = vec!['0'; circuit.num_classical()];
+                for &(q, c) in &measurements {
+                    key[c] = if get(bits, q) { '1' } else { '0' };
+                }
+                key.into_iter().collect()
+            };
+            *outcomes.entry(key).or_insert(0) += 1;
+        }
+        Ok(Counts::from_outcomes(outcomes))
+    }
+
+    fn amplitude_of(&self, bits: &[u64]) -> Complex<f64> {
+        let mut total = complex!(0.0, 0.0);
+        for term in &self.terms {
+            total += term.amplitude(bits);
+        }
+        total
+    }
+
+    fn apply_elementary(&mut self, gate: Elementary) {
+        match gate {
+            Elementary::H(q) => self.terms.iter_mut().for_each(|t| t.h(q)),
+            Elementary::S(q) => self.terms.iter_mut().for_each(|t| t.s(q)),
+            Elementary::CZ(a, b) => self.terms.iter_mut().for_each(|t| t.cz(a, b)),
+            Elementary::CX(c, t) => self.terms.iter_mut().for_each(|term| term.cx(c, t)),
+            Elementary::Phase(q, theta) => {
+                // diag(1, e^{iθ}) = (1 + e^{iθ})/2 · I + (1 − e^{iθ})/2 · Z
+                let turn = complex!(theta.cos(), theta.sin());
+                let a = (complex!(1.0, 0.0) + turn) * complex!(0.5, 0.0);
+                let b = (complex!(1.0, 0.0) - turn) * complex!(0.5, 0.0);
+                let mut flipped = self.terms.clone();
+                for term in &mut self.terms {
+                    term.omega *= a;
+                }
+                for term in &mut flipped {
+                    term.s(q);
+                    term.s(q);
+                    term.omega *= b;
+                }
+                self.terms.append(&mut flipped);
+            }
+        }
+    }
+}
+
+/// Runs circuits through `ExtendedStabilizer` and returns the state vector,
+/// mostly to cross-check it against the other runtimes. Panics on gates it
+/// cannot decompose.
+impl SimulatorBackend for ExtendedStabilizer {
+    fn name(&self) -> String {
+        "ExtendedStabilizer".to_string()
+    }
+
+    fn init(&mut self, num_qubits: usize) {
+        *self = ExtendedStabilizer::new(num_qubits);
+    }
+
+    fn apply(&mut self, op: &GateOp) {
+        if let Err(err) = ExtendedStabilizer::apply(self, op) {
+            panic!("ExtendedStabilizer: {}", err);
+        }
+    }
+
+    fn finalize(&mut self) -> QuantumState {
+        self.to_state()
+    }
+}
+
+fn decompose_circuit(circuit: &QuantumCircuit) -> Result<Vec<Elementary>, String> {
+    let mut gates = Vec::new();
+    for op in try_defer_conditionals(circuit.operations())?.iter() {
+        decompose(op, &mut gates)?;
+    }
+    let count = gates
+        .iter()
+        .filter(|g| matches!(g, Elementary::Phase(..)))
+        .count();
+    if count > MAX_NON_CLIFFORD {
+        return Err(format!(
+            "{} non-Clifford phase gates; at most {} are supported",
+            count, MAX_NON_CLIFFORD
+        ));
+    }
+    Ok(gates)
+}
+
+/// Appends `op` as elementary gates, exact up to a global phase shared by
+/// every term.
+fn decompose(op: &GateOp, out: &mut Vec<Elementary>) -> Result<(), String> {
+    use Elementary::*;
+    match *op {
+        GateOp::H(q) => out.push(H(q)),
+        GateOp::S(q) => out.push(S(q)),
+        GateOp::Sdg(q) => phase(out, q, -FRAC_PI_2),
+        GateOp::Z(q) => phase(out, q, PI),
+        GateOp::X(q) => out.extend([H(q), S(q), S(q), H(q)]),
+        GateOp::Y(q) => out.extend([S(q), S(q), H(q), S(q), S(q), H(q)]),
+        GateOp::T(q) => phase(out, q, FRAC_PI_4),
+        GateOp::Tdg(q) => phase(out, q, -FRAC_PI_4),
+        GateOp::Sx(q) => out.extend([H(q), S(q), H(q)]),
+        GateOp::Sxdg(q) => {
+            out.push(H(q));
+            phase(out, q, -FRAC_PI_2);
+            out.push(H(q));
+        }
+        GateOp::Rz(q, theta) | GateOp::P(q, theta) | GateOp::U1(q, theta) => phase(out, q, theta),
+        GateOp::Rx(q, theta) => rx(out, q, theta),
+        GateOp::Ry(q, theta) => ry(out, q, theta),
+        GateOp::U2(q, phi, lambda) => u3(out, q, FRAC_PI_2, phi, lambda),
+        GateOp::U3(q, theta, phi, lambda) => u3(out, q, theta, phi, lambda),
+        GateOp::CNOT(c, t) => out.push(CX(c, t)),
+        GateOp::CZ(a, b) => out.push(CZ(a, b)),
+        GateOp::SWAP(a, b) => out.extend([CX(a, b), CX(b, a), CX(a, b)]),
+        GateOp::DCX(a, b) => out.extend([CX(a, b), CX(b, a)]),
+        // Y = S·X·S†.
+        GateOp::CY(c, t) => {
+            phase(out, t, -FRAC_PI_2);
+            out.extend([CX(c, t), S(t)]);
+        }
+        // H = V·X·V† for V = Ry(π/4)·H.
+        GateOp::CH(c, t) => {
+            ry(out, t, -FRAC_PI_4);
+            out.extend([H(t), CX(c, t), H(t)]);
+            ry(out, t, FRAC_PI_4);
+        }
+        // √X = H·S·H, and a controlled S is CP(π/2).
+        GateOp::CSX(c, t) => {
+            out.push(H(t));
+            decompose(&GateOp::CP(c, t, FRAC_PI_2), out)?;
+            out.push(H(t));
+        }
+        // ECR = exp(iπ/4·Z⊗X)·(X⊗I).
+        GateOp::ECR(a, b) => {
+            decompose(&GateOp::X(a), out)?;
+            out.push(H(b));
+            decompose(&GateOp::Rzz(a, b, -FRAC_PI_2), out)?;
+            out.push(H(b));
+        }
+        GateOp::CRz(c, t, theta) => {
+            phase(out, t, theta / 2.0);
+            out.push(CX(c, t));
+            phase(out, t, -theta / 2.0);
+            out.push(CX(c, t));
+        }
+        GateOp::CRx(c, t, theta) => {
+            out.push(H(t));
+            decompose(&GateOp::CRz(c, t, theta), out)?;
+            out.push(H(t));
+        }
+        GateOp::CRy(c, t, theta) => {
+            ry(out, t, theta / 2.0);
+            out.push(CX(c, t));
+            ry(out, t, -theta / 2.0);
+            out.push(CX(c, t));
+        }
+        GateOp::CP(c, t, theta) => {
+            phase(out, c, theta / 2.0);
+            out.push(CX(c, t));
+            phase(out, t, -theta / 2.0);
+            out.push(CX(c, t));
+            phase(out, t, theta / 2.0);
+        }
+        GateOp::ISwap(a, b) => {
+            out.extend([S(a), S(b), CZ(a, b)]);
+            decompose(&GateOp::SWAP(a, b), out)?;
+        }
+        // √SWAP = e^{iπ/8}·Rxx(π/4)·Ryy(π/4)·Rzz(π/4), the three commuting.
+        GateOp::SqrtSwap(a, b) => {
+            decompose(&GateOp::Rxx(a, b, FRAC_PI_4), out)?;
+            decompose(&GateOp::Ryy(a, b, FRAC_PI_4), out)?;
+            decompose(&GateOp::Rzz(a, b, FRAC_PI_4), out)?;
+        }
+        GateOp::Rzz(a, b, theta) => {
+            out.push(CX(a, b));
+            phase(out, b, theta);
+            out.push(CX(a, b));
+        }
+        GateOp::Rxx(a, b, theta) => {
+            out.extend([H(a), H(b)]);
+            decompose(&GateOp::Rzz(a, b, theta), out)?;
+            out.extend([H(a), H(b)]);
+        }
+        // Y = S·X·S†, on both qubits.
+        GateOp::Ryy(a, b, theta) => {
+            phase(out, a, -FRAC_PI_2);
+            phase(out, b, -FRAC_PI_2);
+            decompose(&GateOp::Rxx(a, b, theta), out)?;
+            out.extend([S(a), S(b)]);
+        }
+        GateOp::CCNOT(a, b, t) => toffoli(out, a, b, t),
+        GateOp::MCX(ref controls, t) => match controls[..] {
+            [] => decompose(&GateOp::X(t), out)?,
+            [c] => out.push(CX(c, t)),
+            [a, b] => toffoli(out, a, b, t),
+            _ => {
+                return Err(format!(
+                    "{:?}: more than two controls are not decomposed",
+                    op
+                ))
+            }
+        },
+        GateOp::CSWAP(c, a, b) => {
+            out.push(CX(b, a));
+            toffoli(out, c, a, b);
+            out.push(CX(b, a));
+        }
+        GateOp::Custom(ref gate, ref targets) => match &gate.definition {
+            CustomGateDefinition::Composite(ops) => {
+                for (composite, qubits) in ops {
+                    let q = |i: usize| targets[qubits[i]];
+                    let op = match composite {
+                        CompositeOp::H => GateOp::H(q(0)),
+                        CompositeOp::X => GateOp::X(q(0)),
+                        CompositeOp::Y => GateOp::Y(q(0)),
+                        CompositeOp::Z => GateOp::Z(q(0)),
+                        CompositeOp::S => GateOp::S(q(0)),
+                        CompositeOp::T => GateOp::T(q(0)),
+                        CompositeOp::CNOT => GateOp::CNOT(q(0), q(1)),
+                        CompositeOp::CZ => GateOp::CZ(q(0), q(1)),
+                        CompositeOp::SWAP => GateOp::SWAP(q(0), q(1)),
+                        CompositeOp::CCNOT => GateOp::CCNOT(q(0), q(1), q(2)),
+                        CompositeOp::CSWAP => GateOp::CSWAP(q(0), q(1), q(2)),
+                    };
+                    decompose(&op, out)?;
+                }
+            }
+            CustomGateDefinition::Matrix(_) => {
+                return Err(format!("{} is given by a matrix", gate.name))
+            }
+        },
+        GateOp::MCU { ref gate, .. } => {
+            return Err(format!("controlled {} is not decomposed", gate.name))
+        }
+        GateOp::Measure(..) | GateOp::Barrier(..) | GateOp::Snapshot(..) => {}
+        GateOp::Conditional { .. } | GateOp::Reset(_) => {
+            return Err(format!("{:?} must be deferred against its circuit", op))
+        }
+        GateOp::Parameterized { .. } => return Err(format!("{:?} has unbound parameters", op)),
+    }
+    Ok(())
+}
+
+/// `diag(1, e^{iθ})`, as `S` gates when θ is a multiple of π/2.
+fn phase(out: &mut Vec<Elementary>, q: usize, theta: f64) {
+    let quarters = theta / FRAC_PI_2;
+    if (quarters - quarters.round()).abs() < ANGLE_TOLERANCE {
+        for _ in 0..quarters.round().rem_euclid(4.0) as usize {
+            out.push(Elementary::S(q));
+        }
+    } else {
+        out.push(Elementary::Phase(q, theta));
+    }
+}
+
+fn rx(out: &mut Vec<Elementary>, q: usize, theta: f64) {
+    out.push(Elementary::H(q));
+    phase(out, q, theta);
+    out.push(Elementary::H(q));
+}
+
+/// `Ry(θ) = S Rx(θ) S†`.
+fn ry(out: &mut Vec<Elementary>, q: usize, theta: f64) {
+    phase(out, q, -FRAC_PI_2);
+    rx(out, q, theta);
+    out.push(Elementary::S(q));
+}
+
+/// `U3(θ, φ, λ) = Rz(φ) Ry(θ) Rz(λ)` up to phase.
+fn u3(out: &mut Vec<Elementary>, q: usize, theta: f64, phi: f64, lambda: f64) {
+    phase(out, q, lambda);
+    ry(out, q, theta);
+    phase(out, q, phi);
+}
+
+/// The standard seven-T Toffoli.
+fn toffoli(out: &mut Vec<Elementary>, a: usize, b: usize, t: usize) {
+    use Elementary::*;
+    out.extend([H(t), CX(b, t)]);
+    phase(out, t, -FRAC_PI_4);
+    out.push(CX(a, t));
+    phase(out, t, FRAC_PI_4);
+    out.push(CX(b, t));
+    phase(out, t, -FRAC_PI_4);
+    out.push(CX(a, t));
+    phase(out, b, FRAC_PI_4);
+    phase(out, t, FRAC_PI_4);
+    out.extend([H(t), CX(a, b)]);
+    phase(out, a, FRAC_PI_4);
+    phase(out, b, -FRAC_PI_4);
+    out.push(CX(a, b));
+}
+
+/// A stabilizer state `ω U_C U_H |s⟩`: `U_C` is a Clifford built from S, CZ
+/// and CX (so `U_C|0⟩ = |0⟩`), `U_H` a layer of Hadamards on the qubits set
+/// in `v`. `U_C` is stored through its conjugation action:
+/// `U_C† Z_p U_C = Z^{G_p}` and `U_C† X_p U_C = i^{γ_p} X^{F_p} Z^{M_p}`.
+/// Rows are bit-packed.
+#[derive(Clone)]
+struct ChForm {
+    n: usize,
+    f: Vec<Vec<u64>>,
+    g: Vec<Vec<u64>>,
+    m: Vec<Vec<u64>>,
+    gamma: Vec<u8>,
+    v: Vec<u64>,
+    s: Vec<u64>,
+    omega: Complex<f64>,
+}
+
+impl ChForm {
+    fn new(n: usize) -> Self {
+        let identity: Vec<Vec<u64>> = (0..n)
+            .map(|p| {
+                let mut row = vec![0; words(n)];
+                set(&mut row, p, true);
+                row
+            })
+            .collect();
+        ChForm {
+            n,
+            f: identity.clone(),
+            g: identity,
+            m: vec![vec![0; words(n)]; n],
+            gamma: vec![0; n],
+            v: vec![0; words(n)],
+            s: vec![0; words(n)],
+            omega: complex!(1.0, 0.0),
+        }
+    }
+
+    // Gates applied on the left, U_C ← gate · U_C.
+
+    fn s(&mut self, q: usize) {
+        xor_into(&mut self.m[q], &self.g[q]);
+        self.gamma[q] = (self.gamma[q] + 3) % 4;
+    }
+
+    fn cz(&mut self, q: usize, r: usize) {
+        xor_into(&mut self.m[q], &self.g[r]);
+        xor_into(&mut self.m[r], &self.g[q]);
+    }
+
+    fn cx(&mut self, control: usize, target: usize) {
+        let (c, t) = (control, target);
+        let sign = 2 * parity(&self.m[c], &self.f[t]) as u8;
+        self.gamma[c] = (self.gamma[c] + self.gamma[t] + sign) % 4;
+        xor_rows(&mut self.g, t, c);
+        xor_rows(&mut self.f, c, t);
+        xor_rows(&mut self.m, c, t);
+    }
+
+    // Gates applied on the right, U_C ← U_C · gate.
+
+    fn right_s(&mut self, q: usize) {
+        for p in 0..self.n {
+            if get(&self.f[p], q) {
+                flip(&mut self.m[p], q);
+                self.gamma[p] = (self.gamma[p] + 3) % 4;
+            }
+        }
+    }
+
+    fn right_cz(&mut self, q: usize, r: usize) {
+        for p in 0..self.n {
+            let (fq, fr) = (get(&self.f[p], q), get(&self.f[p], r));
+            if fr {
+                flip(&mut self.m[p], q);
+            }
+            if fq {
+                flip(&mut self.m[p], r);
+            }
+            if fq && fr {
+                self.gamma[p] = (self.gamma[p] + 2) % 4;
+            }
+        }
+    }
+
+    fn right_cx(&mut self, control: usize, target: usize) {
+        let (c, t) = (control, target);
+        for p in 0..self.n {
+            if get(&self.f[p], c) {
+                flip(&mut self.f[p], t);
+            }
+            if get(&self.m[p], t) {
+                flip(&mut self.m[p], c);
+            }
+            if get(&self.g[p], t) {
+                flip(&mut self.g[p], c);
+            }
+        }
+    }
+
+    /// `H_q` through `U_C† H_q U_C = (U_C† X_q U_C + U_C† Z_q U_C)/√2`,
+    /// which takes `U_H|s⟩` to a superposition `α|t⟩ + β|u⟩` that is then
+    /// brought back into CH-form.
+    fn h(&mut self, q: usize) {
+        let n_words = words(self.n);
+        let mut t = self.s.clone();
+        let mut u = self.s.clone();
+        let mut alpha_sign = false;
+        let mut beta_sign = false;
+        for w in 0..n_words {
+            let (f, g, m, v, s) = (
+                self.f[q][w],
+                self.g[q][w],
+                self.m[q][w],
+                self.v[w],
+                self.s[w],
+            );
+            t[w] ^= g & v;
+            alpha_sign ^= (g & !v & s).count_ones() % 2 == 1;
+            u[w] ^= (f & !v) ^ (m & v);
+            beta_sign ^= (f & m & v).count_ones() % 2 == 1;
+            beta_sign ^= (((m & !v) ^ (f & v)) & s).count_ones() % 2 == 1;
+        }
+        // β/α = i^δ
+        let delta = (self.gamma[q] + 2 * (alpha_sign ^ beta_sign) as u8) % 4;
+        if alpha_sign {
+            self.omega = -self.omega;
+        }
+
+        if t == u {
+            self.s = t;
+            self.omega *= (complex!(1.0, 0.0) + i_pow(delta)) * complex!(FRAC_1_SQRT_2, 0.0);
+            return;
+        }
+
+        // Reduce to t and u differing on a single qubit `pivot`.
+        let y: Vec<u64> = t.iter().zip(&u).map(|(a, b)| a ^ b).collect();
+        let differs0: Vec<usize> = (0..self.n)
+            .filter(|&j| get(&y, j) && !get(&self.v, j))
+            .collect();
+        let differs1: Vec<usize> = (0..self.n)
+            .filter(|&j| get(&y, j) && get(&self.v, j))
+            .collect();
+        let pivot;
+        if let Some(&first) = differs0.first() {
+            pivot = first;
+            for &j in &differs0[1..] {
+                self.right_cx(pivot, j);
+            }
+            for &j in &differs1 {
+                self.right_cz(pivot, j);
+            }
+        } else {
+            pivot = differs1[0];
+            for &j in &differs1[1..] {
+                self.right_cx(j, pivot);
+            }
+        }
+        for j in (0..self.n).filter(|&j| j != pivot && get(&y, j)) {
+            if get(&t, pivot) {
+                flip(&mut t, j);
+            }
+            if get(&u, pivot) {
+                flip(&mut u, j);
+            }
+        }
+
+        // |t_p⟩ + i^δ|u_p⟩ = √2 · prefactor · S^a H |c⟩ on the pivot.
+        let (delta, prefactor) = if get(&t, pivot) {
+            ((4 - delta) % 4, i_pow(delta))
+        } else {
+            (delta, complex!(1.0, 0.0))
+        };
+        let (a, c) = (delta & 1 == 1, delta >= 2);
+        self.s = t;
+        set(&mut self.s, pivot, c);
+        self.omega *= prefactor;
+        if !get(&self.v, pivot) {
+            set(&mut self.v, pivot, true);
+            if a {
+                self.right_s(pivot);
+            }
+        } else if !a {
+            set(&mut self.v, pivot, false);
+        } else {
+            // H S H = e^{iπ/4} S† H S†
+            for _ in 0..3 {
+                self.right_s(pivot);
+            }
+            let shift = if c { 3 } else { 0 };
+            self.omega *= complex!(FRAC_1_SQRT_2, FRAC_1_SQRT_2) * i_pow(shift);
+        }
+    }
+
+    /// `⟨x|ψ⟩`: `U_C†|x⟩ = i^e |a⟩` from the X images, then
+    /// `⟨a|U_H|s⟩` is a product over qubits.
+    fn amplitude(&self, x: &[u64]) -> Complex<f64> {
+        let n_words = words(self.n);
+        let mut e = 0u8;
+        let mut a = vec![0u64; n_words];
+        let mut b = vec![0u64; n_words];
+        for p in (0..self.n).filter(|&p| get(x, p)) {
+            e = (e + self.gamma[p] + 2 * parity(&b, &self.f[p]) as u8) % 4;
+            xor_into(&mut a, &self.f[p]);
+            xor_into(&mut b, &self.m[p]);
+        }
+        let mut sign = false;
+        let mut hadamards = 0;
+        for ((a, s), v) in a.iter().zip(&self.s).zip(&self.v) {
+            if (a ^ s) & !v != 0 {
+                return complex!(0.0, 0.0);
+            }
+            sign ^= (a & s & v).count_ones() % 2 == 1;
+            hadamards += v.count_ones();
+        }
+        let magnitude = FRAC_1_SQRT_2.powi(hadamards as i32);
+        let amp = self.omega * i_pow((4 - e) % 4) * complex!(magnitude, 0.0);
+        if sign {
+            -amp
+        } else {
+            amp
+        }
+    }
+}
+
+fn i_pow(k: u8) -> Complex<f64> {
+    match k % 4 {
+        0 => complex!(1.0, 0.0),
+        1 => complex!(0.0, 1.0),
+        2 => complex!(-1.0, 0.0),
+        _ => complex!(0.0, -1.0),
+    }
+}
+
+/// A uniform `f64` in `[0, 1)` from the top 53 bits of `bits`.
+fn uniform(bits: u64) -> f64 {
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+fn words(n: usize) -> usize {
+    n.div_ceil(64).max(1)
+}
+
+fn pack(bits: &[bool]) -> Vec<u64> {
+    let mut row = vec![0; words(bits.len())];
+    for (j, &bit) in bits.iter().enumerate() {
+        set(&mut row, j, bit);
+    }
+    row
+}
+
+fn get(row: &[u64], j: usize) -> bool {
+    (row[j / 64] >> (j % 64)) & 1 == 1
+}
+
+fn set(row: &mut [u64], j: usize, bit: bool) {
+    if bit {
+        row[j / 64] |= 1 << (j % 64);
+    } else {
+        row[j / 64] &= !(1 << (j % 64));
+    }
+}
+
+fn flip(row: &mut [u64], j: usize) {
+    row[j / 64] ^= 1 << (j % 64);
+}
+
+fn xor_into(dst: &mut [u64], src: &[u64]) {
+    for (d, s) in dst.iter_mut().zip(src) {
+        *d ^= s;
+    }
+}
+
+/// `rows[dst] ^= rows[src]`.
+fn xor_rows(rows: &mut [Vec<u64>], dst: usize, src: usize) {
+    let (dst, src) = if dst < src {
+        let (low, high) = rows.split_at_mut(src);
+        (&mut low[dst], &high[0])
+    } else {
+        let (low, high) = rows.split_at_mut(dst);
+        (&mut high[0], &low[src])
+    };
+    xor_into(dst, src);
+}
+
+fn parity(a: &[u64], b: &[u64]) -> bool {
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| (x & y).count_ones())
+        .sum::<u32>()
+        % 2
+        == 1
+}
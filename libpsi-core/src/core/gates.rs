@@ -96,6 +96,60 @@ pub fn cp_matrix(theta: f64) -> Matrix<Complex<f64>> {
     )
 }
 
+pub fn rxx_matrix(theta: f64) -> Matrix<Complex<f64>> {
+    let cos = (theta / 2.0).cos();
+    let sin = (theta / 2.0).sin();
+    matrix!(
+        [complex!(cos, 0.0), complex!(0.0, 0.0), complex!(0.0, 0.0), complex!(0.0, -sin)];
+        [complex!(0.0, 0.0), complex!(cos, 0.0), complex!(0.0, -sin), complex!(0.0, 0.0)];
+        [complex!(0.0, 0.0), complex!(0.0, -sin), complex!(cos, 0.0), complex!(0.0, 0.0)];
+        [complex!(0.0, -sin), complex!(0.0, 0.0), complex!(0.0, 0.0), complex!(cos, 0.0)]
+    )
+}
+
+pub fn ryy_matrix(theta: f64) -> Matrix<Complex<f64>> {
+    let cos = (theta / 2.0).cos();
+    let sin = (theta / 2.0).sin();
+    matrix!(
+        [complex!(cos, 0.0), complex!(0.0, 0.0), complex!(0.0, 0.0), complex!(0.0, sin)];
+        [complex!(0.0, 0.0), complex!(cos, 0.0), complex!(0.0, -sin), complex!(0.0, 0.0)];
+        [complex!(0.0, 0.0), complex!(0.0, -sin), complex!(cos, 0.0), complex!(0.0, 0.0)];
+        [complex!(0.0, sin), complex!(0.0, 0.0), complex!(0.0, 0.0), complex!(cos, 0.0)]
+    )
+}
+
+pub fn rzz_matrix(theta: f64) -> Matrix<Complex<f64>> {
+    let half = theta / 2.0;
+    matrix!(
+        [complex!(half.cos(), -half.sin()), complex!(0.0, 0.0), complex!(0.0, 0.0), complex!(0.0, 0.0)];
+        [complex!(0.0, 0.0), complex!(half.cos(), half.sin()), complex!(0.0, 0.0), complex!(0.0, 0.0)];
+        [complex!(0.0, 0.0), complex!(0.0, 0.0), complex!(half.cos(), half.sin()), complex!(0.0, 0.0)];
+        [complex!(0.0, 0.0), complex!(0.0, 0.0), complex!(0.0, 0.0), complex!(half.cos(), -half.sin())]
+    )
+}
+
+/// X on the last of `num_controls + 1` qubits where all the others are
+/// |1⟩: the identity with its last two rows swapped.
+pub fn mcx_matrix(num_controls: usize) -> Matrix<Complex<f64>> {
+    mcu_matrix(&PAULI_X.matrix, num_controls)
+}
+
+/// The single-qubit `gate` on the last of `num_controls + 1` qubits where
+/// all the others are |1⟩: the identity with `gate` as its last block.
+pub fn mcu_matrix(gate: &Matrix<Complex<f64>>, num_controls: usize) -> Matrix<Complex<f64>> {
+    let dim = 1 << (num_controls + 1);
+    let mut matrix = Matrix::new(dim, dim, vec![complex!(0.0, 0.0); dim * dim]);
+    for row in 0..dim - 2 {
+        matrix.set(row, row, complex!(1.0, 0.0));
+    }
+    for row in 0..2 {
+        for col in 0..2 {
+            matrix.set(dim - 2 + row, dim - 2 + col, gate.get(row, col));
+        }
+    }
+    matrix
+}
+
 #[rustfmt::skip]
 lazy_static::lazy_static! {
     pub static ref HADAMARD: QuantumGate<'static> = QuantumGate {
@@ -221,6 +275,53 @@ lazy_static::lazy_static! {
         num_qubits: 2,
     };
 
+    pub static ref CY: QuantumGate<'static> = QuantumGate {
+        name: "CY",
+        matrix: matrix!([complex!(1.0, 0.0), complex!(0.0, 0.0), complex!(0.0, 0.0), complex!(0.0, 0.0)];
+                        [complex!(0.0, 0.0), complex!(1.0, 0.0), complex!(0.0, 0.0), complex!(0.0, 0.0)];
+                        [complex!(0.0, 0.0), complex!(0.0, 0.0), complex!(0.0, 0.0), complex!(0.0, -1.0)];
+                        [complex!(0.0, 0.0), complex!(0.0, 0.0), complex!(0.0, 1.0), complex!(0.0, 0.0)]),
+        num_qubits: 2,
+    };
+
+    pub static ref CH: QuantumGate<'static> = QuantumGate {
+        name: "CH",
+        matrix: matrix!([complex!(1.0, 0.0), complex!(0.0, 0.0), complex!(0.0, 0.0), complex!(0.0, 0.0)];
+                        [complex!(0.0, 0.0), complex!(1.0, 0.0), complex!(0.0, 0.0), complex!(0.0, 0.0)];
+                        [complex!(0.0, 0.0), complex!(0.0, 0.0), complex!(FRAC_1_SQRT_2, 0.0), complex!(FRAC_1_SQRT_2, 0.0)];
+                        [complex!(0.0, 0.0), complex!(0.0, 0.0), complex!(FRAC_1_SQRT_2, 0.0), complex!(-FRAC_1_SQRT_2, 0.0)]),
+        num_qubits: 2,
+    };
+
+    pub static ref CSX: QuantumGate<'static> = QuantumGate {
+        name: "C√X",
+        matrix: matrix!([complex!(1.0, 0.0), complex!(0.0, 0.0), complex!(0.0, 0.0), complex!(0.0, 0.0)];
+                        [complex!(0.0, 0.0), complex!(1.0, 0.0), complex!(0.0, 0.0), complex!(0.0, 0.0)];
+                        [complex!(0.0, 0.0), complex!(0.0, 0.0), complex!(0.5, 0.5), complex!(0.5, -0.5)];
+                        [complex!(0.0, 0.0), complex!(0.0, 0.0), complex!(0.5, -0.5), complex!(0.5, 0.5)]),
+        num_qubits: 2,
+    };
+
+    /// Echoed cross-resonance, (X⊗I − Y⊗X)/√2.
+    pub static ref ECR: QuantumGate<'static> = QuantumGate {
+        name: "ECR",
+        matrix: matrix!([complex!(0.0, 0.0), complex!(0.0, 0.0), complex!(FRAC_1_SQRT_2, 0.0), complex!(0.0, FRAC_1_SQRT_2)];
+                        [complex!(0.0, 0.0), complex!(0.0, 0.0), complex!(0.0, FRAC_1_SQRT_2), complex!(FRAC_1_SQRT_2, 0.0)];
+                        [complex!(FRAC_1_SQRT_2, 0.0), complex!(0.0, -FRAC_1_SQRT_2), complex!(0.0, 0.0), complex!(0.0, 0.0)];
+                        [complex!(0.0, -FRAC_1_SQRT_2), complex!(FRAC_1_SQRT_2, 0.0), complex!(0.0, 0.0), complex!(0.0, 0.0)]),
+        num_qubits: 2,
+    };
+
+    /// CNOT from the first qubit to the second, then back.
+    pub static ref DCX: QuantumGate<'static> = QuantumGate {
+        name: "DCX",
+        matrix: matrix!([complex!(1.0, 0.0), complex!(0.0, 0.0), complex!(0.0, 0.0), complex!(0.0, 0.0)];
+                        [complex!(0.0, 0.0), complex!(0.0, 0.0), complex!(1.0, 0.0), complex!(0.0, 0.0)];
+                        [complex!(0.0, 0.0), complex!(0.0, 0.0), complex!(0.0, 0.0), complex!(1.0, 0.0)];
+                        [complex!(0.0, 0.0), complex!(1.0, 0.0), complex!(0.0, 0.0), complex!(0.0, 0.0)]),
+        num_qubits: 2,
+    };
+
     pub static ref TOFFOLI: QuantumGate<'static> = QuantumGate {
         name: "CCNOT",
         matrix: matrix!(
@@ -0,0 +1,570 @@
+use super::circuit::try_defer_conditionals;
+use super::runtime::start_state_in;
+use super::{GateOp, Kernel, KernelBatch, QuantumCircuit, QuantumState, Runtime, RuntimeConfig};
+use crate::{Complex, Vector};
+use std::sync::OnceLock;
+use wgpu::util::DeviceExt;
+
+/// Invocations per workgroup, as declared in `SHADER`.
+const WORKGROUP_SIZE: u32 = 256;
+/// Bytes between the parameters of consecutive gates in the parameter
+/// buffer: the largest offset alignment a device may ask of a storage
+/// binding.
+const GATE_STRIDE: u64 = 256;
+/// Gates encoded per submission, bounding the parameter buffer.
+const GATES_PER_SUBMIT: usize = 4096;
+/// Matrix entries uploaded per submission, bounding the matrix buffer for
+/// large batches.
+const MATRIX_ENTRIES_PER_SUBMIT: usize = 1 << 20;
+
+/// Single- and two-qubit kernels on a batch of states laid out as
+/// `[instance][amplitude]` in `vec2<f32>`. Each invocation mixes one pair
+/// (or group of four) of amplitudes of one instance that differ only in the
+/// target bits, with that instance's matrix, striding over the grid when
+/// the batch has more groups than the dispatch has invocations.
+const SHADER: &str = r#"
+struct Gate {
+    groups: u32,
+    total: u32,
+    bit0: u32,
+    bit1: u32,
+    qubits: u32,
+    matrix: u32,
+}
+
+@group(0) @binding(0) var<storage, read_write> state: array<vec2<f32>>;
+@group(0) @binding(1) var<storage, read> gate: Gate;
+@group(0) @binding(2) var<storage, read> matrices: array<vec2<f32>>;
+
+fn mul(a: vec2<f32>, b: vec2<f32>) -> vec2<f32> {
+    return vec2<f32>(a.x * b.x - a.y * b.y, a.x * b.y + a.y * b.x);
+}
+
+fn insert_zero(i: u32, bit: u32) -> u32 {
+    let low = i & ((1u << bit) - 1u);
+    return ((i >> bit) << (bit + 1u)) | low;
+}
+
+@compute @workgroup_size(256)
+fn single(@builtin(global_invocation_id) id: vec3<u32>,
+          @builtin(num_workgroups) workgroups: vec3<u32>) {
+    let stride = workgroups.x * 256u;
+    for (var i = id.x; i < gate.total; i += stride) {
+        let instance = i / gate.groups;
+        let m = gate.matrix + instance * 4u;
+        let i0 = (instance << gate.qubits) | insert_zero(i % gate.groups, gate.bit0);
+        let i1 = i0 | (1u << gate.bit0);
+        let a0 = state[i0];
+        let a1 = state[i1];
+        state[i0] = mul(matrices[m], a0) + mul(matrices[m + 1u], a1);
+        state[i1] = mul(matrices[m + 2u], a0) + mul(matrices[m + 3u], a1);
+    }
+}
+
+@compute @workgroup_size(256)
+fn pair(@builtin(global_invocation_id) id: vec3<u32>,
+        @builtin(num_workgroups) workgroups: vec3<u32>) {
+    let stride = workgroups.x * 256u;
+    let lo = min(gate.bit0, gate.bit1);
+    let hi = max(gate.bit0, gate.bit1);
+    for (var i = id.x; i < gate.total; i += stride) {
+        let instance = i / gate.groups;
+        let m = gate.matrix + instance * 16u;
+        let base = (instance << gate.qubits)
+            | insert_zero(insert_zero(i % gate.groups, lo), hi);
+        var index: array<u32, 4>;
+        var amplitude: array<vec2<f32>, 4>;
+        for (var k = 0u; k < 4u; k++) {
+            index[k] = base | select(0u, 1u << gate.bit0, (k & 2u) != 0u)
+                | select(0u, 1u << gate.bit1, (k & 1u) != 0u);
+            amplitude[k] = state[index[k]];
+        }
+        for (var row = 0u; row < 4u; row++) {
+            var sum = vec2<f32>(0.0, 0.0);
+            for (var col = 0u; col < 4u; col++) {
+                sum += mul(matrices[m + row * 4u + col], amplitude[col]);
+            }
+            state[index[row]] = sum;
+        }
+    }
+}
+"#;
+
+/// One gate's entry in the parameter buffer, laid out as `Gate` in
+/// `SHADER`: the groups of amplitudes it mixes per instance and in the
+/// whole batch, the index bits of its targets, and where the first
+/// instance's matrix starts in the matrix buffer.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct GateParams {
+    groups: u32,
+    total: u32,
+    bit0: u32,
+    bit1: u32,
+    qubits: u32,
+    matrix: u32,
+}
+
+/// A state-vector simulator on a wgpu device. States are uploaded once and
+/// stay on the device in single precision; single- and two-qubit kernels
+/// run as compute shaders, and anything wider (multi-controlled gates,
+/// three-qubit and custom gates) is applied on the CPU between them, at the
+/// cost of a round trip.
+///
+/// `run_batch` evolves many instances of a circuit that differ only in
+/// their angles, e.g. the parameter bindings of a variational loop, as one
+/// `[instance][amplitude]` tensor with one dispatch per gate for the whole
+/// batch.
+pub struct GpuSimulator {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    layout: wgpu::BindGroupLayout,
+    single: wgpu::ComputePipeline,
+    pair: wgpu::ComputePipeline,
+    adapter: String,
+}
+
+/// What one instance does with an operation.
+enum Planned<'a> {
+    Gpu(Kernel),
+    Cpu(&'a GateOp),
+}
+
+/// One step of a batch, with an entry per instance.
+enum Step<'a> {
+    Gpu(Vec<Kernel>),
+    Cpu(Vec<&'a GateOp>),
+}
+
+impl GpuSimulator {
+    /// A simulator on the default adapter, or an error when there is none.
+    pub fn new() -> Result<Self, String> {
+        pollster::block_on(Self::request())
+    }
+
+    /// The simulator `Runtime::GPUAccelerated` runs on, created on first
+    /// use; `None` when no adapter is available.
+    pub fn shared() -> Option<&'static GpuSimulator> {
+        static SHARED: OnceLock<Option<GpuSimulator>> = OnceLock::new();
+        SHARED.get_or_init(|| GpuSimulator::new().ok()).as_ref()
+    }
+
+    async fn request() -> Result<Self, String> {
+        let instance = wgpu::Instance::default();
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                ..Default::default()
+            })
+            .await
+            .ok_or("no GPU adapter available")?;
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: Some("psi"),
+                    required_features: wgpu::Features::empty(),
+                    required_limits: adapter.limits(),
+                    memory_hints: wgpu::MemoryHints::Performance,
+                },
+                None,
+            )
+            .await
+            .map_err(|err| format!("cannot open GPU device: {}", err))?;
+
+        let storage = |binding, read_only, dynamic| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: dynamic,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("psi gate"),
+            entries: &[
+                storage(0, false, false),
+                storage(1, true, true),
+                storage(2, true, false),
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("psi gate"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("psi gates"),
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+        let pipeline = |entry_point| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&pipeline_layout),
+                module: &module,
+                entry_point,
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+        let single = pipeline("single");
+        let pair = pipeline("pair");
+
+        Ok(GpuSimulator {
+            adapter: adapter.get_info().name,
+            single,
+            pair,
+            layout,
+            device,
+            queue,
+        })
+    }
+
+    /// The name of the adapter the simulator runs on.
+    pub fn adapter(&self) -> &str {
+        &self.adapter
+    }
+
+    /// The largest register whose state fits in one storage buffer on this
+    /// device.
+    pub fn max_qubits(&self) -> usize {
+        (self.max_bytes() / 8).ilog2() as usize
+    }
+
+    fn max_bytes(&self) -> u64 {
+        let limits = self.device.limits();
+        (limits.max_storage_buffer_binding_size as u64).min(limits.max_buffer_size)
+    }
+
+    /// The state `operations` leave `num_qubits` qubits in, starting from
+    /// `initial` (|0…0⟩ if `None`). Measurements are read out at the end,
+    /// as with the other runtimes; circuits that need shot-by-shot
+    /// execution, and states larger than `max_qubits`, are errors.
+    pub fn run(
+        &self,
+        num_qubits: usize,
+        operations: &[GateOp],
+        initial: Option<&[Complex<f64>]>,
+    ) -> Result<QuantumState, String> {
+        let mut states = self.run_batch(num_qubits, &[operations.to_vec()], initial)?;
+        Ok(states.remove(0))
+    }
+
+    /// `run` for every circuit in `instances`, all from `initial`, in as
+    /// few passes as the device has room for. The instances must apply the
+    /// same gates to the same qubits, and may differ in their angles and
+    /// matrices.
+    pub fn run_batch(
+        &self,
+        num_qubits: usize,
+        instances: &[Vec<GateOp>],
+        initial: Option<&[Complex<f64>]>,
+    ) -> Result<Vec<QuantumState>, String> {
+        if num_qubits > self.max_qubits() {
+            return Err(format!(
+                "{} qubits do not fit on {} (at most {})",
+                num_qubits,
+                self.adapter,
+                self.max_qubits()
+            ));
+        }
+        let instances = instances
+            .iter()
+            .map(|operations| try_defer_conditionals(operations))
+            .collect::<Result<Vec<_>, _>>()?;
+        let per_pass = (self.max_bytes() >> (num_qubits + 3)) as usize;
+        let mut states = Vec::with_capacity(instances.len());
+        for pass in instances.chunks(per_pass) {
+            let plans = pass
+                .iter()
+                .map(|operations| Self::plan(num_qubits, operations));
+            let steps = Self::merge(plans)?;
+            states.extend(self.run_steps(num_qubits, &steps, pass.len(), initial));
+        }
+        Ok(states)
+    }
+
+    /// The states `circuit` ends in under each of `bindings` (see
+    /// `QuantumCircuit::bind_parameters`), computed as one batch.
+    pub fn run_bindings(
+        &self,
+        circuit: &QuantumCircuit,
+        bindings: &[&[(&str, f64)]],
+    ) -> Result<Vec<QuantumState>, String> {
+        let instances: Vec<Vec<GateOp>> = bindings
+            .iter()
+            .map(|binding| circuit.bind_parameters(binding).operations().to_vec())
+            .collect();
+        self.run_batch(circuit.num_qubits(), &instances, circuit.initial_state())
+    }
+
+    /// Kernels for the operations the shaders can apply, fused where
+    /// adjacent, and the operations left to the CPU.
+    fn plan(num_qubits: usize, operations: &[GateOp]) -> Vec<Planned<'_>> {
+        let mut planned = Vec::new();
+        let mut batch = KernelBatch::new(num_qubits);
+        let flush = |batch: &mut KernelBatch, planned: &mut Vec<Planned>| {
+            batch.optimize();
+            planned.extend(batch.kernels().iter().cloned().map(Planned::Gpu));
+            *batch = KernelBatch::new(num_qubits);
+        };
+        for op in operations {
+            if op.is_multi_controlled() {
+                flush(&mut batch, &mut planned);
+                planned.push(Planned::Cpu(op));
+                continue;
+            }
+            match Runtime::op_to_kernel(op) {
+                Some(kernel) if kernel.targets.len() <= 2 => batch.add(kernel),
+                Some(_) => {
+                    flush(&mut batch, &mut planned);
+                    planned.push(Planned::Cpu(op));
+                }
+                None if op.is_barrier() => batch.fence(),
+                None => {}
+            }
+        }
+        flush(&mut batch, &mut planned);
+        planned
+    }
+
+    /// The instances' plans side by side, or an error when they differ in
+    /// more than their matrices.
+    fn merge<'a>(
+        mut plans: impl Iterator<Item = Vec<Planned<'a>>>,
+    ) -> Result<Vec<Step<'a>>, String> {
+        let Some(first) = plans.next() else {
+            return Ok(Vec::new());
+        };
+        let mut steps: Vec<Step> = first
+            .into_iter()
+            .map(|planned| match planned {
+                Planned::Gpu(kernel) => Step::Gpu(vec![kernel]),
+                Planned::Cpu(op) => Step::Cpu(vec![op]),
+            })
+            .collect();
+        let mismatch =
+            || "batched circuits must apply the same gates to the same qubits".to_string();
+        for plan in plans {
+            if plan.len() != steps.len() {
+                return Err(mismatch());
+            }
+            for (step, planned) in steps.iter_mut().zip(plan) {
+                match (step, planned) {
+                    (Step::Gpu(kernels), Planned::Gpu(kernel))
+                        if kernels[0].targets == kernel.targets =>
+                    {
+                        kernels.push(kernel)
+                    }
+                    (Step::Cpu(ops), Planned::Cpu(op)) => ops.push(op),
+                    _ => return Err(mismatch()),
+                }
+            }
+        }
+        Ok(steps)
+    }
+
+    /// Runs `steps` on `instances` copies of `initial` held on the device
+    /// together.
+    fn run_steps(
+        &self,
+        num_qubits: usize,
+        steps: &[Step],
+        instances: usize,
+        initial: Option<&[Complex<f64>]>,
+    ) -> Vec<QuantumState> {
+        let size = 1 << num_qubits;
+        let start = start_state_in::<f32>(num_qubits, initial);
+        let tensor: Vec<Complex<f32>> =
+            (0..instances).flat_map(|_| start.iter().copied()).collect();
+        let state = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("psi state"),
+            size: (tensor.len() * 8) as u64,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        self.upload(&state, &tensor);
+        drop(tensor);
+
+        let per_submit = (MATRIX_ENTRIES_PER_SUBMIT / (16 * instances)).clamp(1, GATES_PER_SUBMIT);
+        let mut steps = steps.iter().peekable();
+        while steps.peek().is_some() {
+            let mut gates = Vec::new();
+            while let Some(Step::Gpu(kernels)) = steps.peek() {
+                gates.push(kernels);
+                steps.next();
+            }
+            for chunk in gates.chunks(per_submit) {
+                self.dispatch(&state, chunk, num_qubits);
+            }
+
+            let mut cpu = Vec::new();
+            while let Some(Step::Cpu(ops)) = steps.peek() {
+                cpu.push(ops);
+                steps.next();
+            }
+            if !cpu.is_empty() {
+                let mut amplitudes = self.download(&state);
+                for (instance, amplitudes) in amplitudes.chunks_exact_mut(size).enumerate() {
+                    let ops: Vec<GateOp> = cpu.iter().map(|ops| ops[instance].clone()).collect();
+                    let mut evolved = amplitudes.to_vec();
+                    RuntimeConfig::optimal().evolve(&mut evolved, num_qubits, &ops, None);
+                    amplitudes.copy_from_slice(&evolved);
+                }
+                self.upload(&state, &amplitudes);
+            }
+        }
+
+        self.download(&state)
+            .chunks_exact(size)
+            .map(|amplitudes| {
+                QuantumState::new(
+                    amplitudes
+                        .iter()
+                        .map(|a| Complex::new(a.real as f64, a.imaginary as f64))
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+
+    /// Applies `gates`, each with a kernel per instance, to `state` in one
+    /// compute pass.
+    fn dispatch(&self, state: &wgpu::Buffer, gates: &[&Vec<Kernel>], num_qubits: usize) {
+        if gates.is_empty() {
+            return;
+        }
+        let instances = gates[0].len() as u32;
+        let mut params = vec![0u8; gates.len() * GATE_STRIDE as usize];
+        let mut matrices: Vec<[f32; 2]> = Vec::new();
+        for (kernels, slot) in gates
+            .iter()
+            .zip(params.chunks_exact_mut(GATE_STRIDE as usize))
+        {
+            let gate = gate_params(&kernels[0], num_qubits, instances, matrices.len());
+            slot[..std::mem::size_of::<GateParams>()].copy_from_slice(bytemuck::bytes_of(&gate));
+            for kernel in kernels.iter() {
+                matrices.extend(
+                    kernel
+                        .matrix
+                        .data
+                        .iter()
+                        .map(|value| [value.real as f32, value.imaginary as f32]),
+                );
+            }
+        }
+        let buffer = |label, contents: &[u8]| {
+            self.device
+                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some(label),
+                    contents,
+                    usage: wgpu::BufferUsages::STORAGE,
+                })
+        };
+        let params = buffer("psi gates", &params);
+        let matrices = buffer("psi matrices", bytemuck::cast_slice(&matrices));
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("psi gate"),
+            layout: &self.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: state.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: &params,
+                        offset: 0,
+                        size: wgpu::BufferSize::new(std::mem::size_of::<GateParams>() as u64),
+                    }),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: matrices.as_entire_binding(),
+                },
+            ],
+        });
+
+        let max_workgroups = self.device.limits().max_compute_workgroups_per_dimension;
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("psi gates"),
+                timestamp_writes: None,
+            });
+            for (i, kernels) in gates.iter().enumerate() {
+                let width = kernels[0].targets.len();
+                pass.set_pipeline(if width == 1 { &self.single } else { &self.pair });
+                pass.set_bind_group(0, &bind_group, &[i as u32 * GATE_STRIDE as u32]);
+                let total = (1u32 << (num_qubits - width)) * instances;
+                pass.dispatch_workgroups(total.div_ceil(WORKGROUP_SIZE).min(max_workgroups), 1, 1);
+            }
+        }
+        self.queue.submit(Some(encoder.finish()));
+    }
+
+    /// The amplitudes in `state`, copied back through a staging buffer.
+    fn download(&self, state: &wgpu::Buffer) -> Vec<Complex<f32>> {
+        let staging = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("psi readback"),
+            size: state.size(),
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        encoder.copy_buffer_to_buffer(state, 0, &staging, 0, state.size());
+        self.queue.submit(Some(encoder.finish()));
+
+        let slice = staging.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+        receiver
+            .recv()
+            .expect("GPU readback was dropped")
+            .expect("cannot map the GPU state for reading");
+        let amplitudes = bytemuck::cast_slice::<u8, [f32; 2]>(&slice.get_mapped_range())
+            .iter()
+            .map(|&[real, imaginary]| Complex::new(real, imaginary))
+            .collect();
+        staging.unmap();
+        amplitudes
+    }
+
+    fn upload(&self, state: &wgpu::Buffer, amplitudes: &[Complex<f32>]) {
+        let data: Vec<[f32; 2]> = amplitudes.iter().map(|a| [a.real, a.imaginary]).collect();
+        self.queue
+            .write_buffer(state, 0, bytemuck::cast_slice(&data));
+    }
+}
+
+/// `kernel`'s place in the batch as the shader reads it. Qubit 0 is the
+/// most significant bit of an index, and `targets[0]` the most significant
+/// bit of a row of the matrix.
+fn gate_params(kernel: &Kernel, num_qubits: usize, instances: u32, matrix: usize) -> GateParams {
+    let bit = |k: usize| {
+        kernel
+            .targets
+            .get(k)
+            .map_or(0, |&t| (num_qubits - 1 - t) as u32)
+    };
+    let groups = 1 << (num_qubits - kernel.targets.len());
+    GateParams {
+        groups,
+        total: groups * instances,
+        bit0: bit(0),
+        bit1: bit(1),
+        qubits: num_qubits as u32,
+        matrix: matrix as u32,
+    }
+}
@@ -0,0 +1,131 @@
+use super::circuit::defer_conditionals;
+use super::{GateOp, Hamiltonian, Kernel, QuantumCircuit, Runtime, RuntimeConfig};
+use crate::{complex, Complex};
+use std::f64::consts::PI;
+
+/// `⟨ψ(θ)|H|ψ(θ)⟩` for a parameterized circuit and its derivative with
+/// respect to every parameter, as computed by
+/// `RuntimeConfig::adjoint_gradients`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gradient {
+    pub expectation: f64,
+    /// `(name, ∂⟨H⟩/∂name)`, in `QuantumCircuit::parameters` order.
+    pub partials: Vec<(String, f64)>,
+}
+
+impl Gradient {
+    /// The derivative with respect to the parameter called `name`.
+    pub fn get(&self, name: &str) -> Option<f64> {
+        self.partials
+            .iter()
+            .find(|(parameter, _)| parameter == name)
+            .map(|&(_, partial)| partial)
+    }
+}
+
+/// The adjoint method: one forward run to `|ψ⟩ = U_N ⋯ U_1|0⟩`, then one
+/// backward sweep carrying `|ψ_i⟩ = U_i† |ψ_{i+1}⟩` and
+/// `|λ_i⟩ = U_i† |λ_{i+1}⟩` from `|λ⟩ = H|ψ⟩`. Each open angle `x` of gate
+/// `i` contributes `2 Re ⟨λ|∂U_i/∂x|ψ_{i-1}⟩`, so all `P` derivatives
+/// cost about three runs of the circuit rather than the `2P` of parameter
+/// shift.
+pub(crate) fn adjoint_gradients(
+    config: &RuntimeConfig,
+    circuit: &QuantumCircuit,
+    observable: &Hamiltonian,
+    bindings: &[(&str, f64)],
+) -> Gradient {
+    let num_qubits = circuit.num_qubits();
+    assert_eq!(
+        observable.num_qubits(),
+        num_qubits,
+        "observable on {} qubits for a {}-qubit circuit",
+        observable.num_qubits(),
+        num_qubits
+    );
+    let bound = circuit.bind_parameters(bindings);
+    let unbound = bound.parameters();
+    assert!(unbound.is_empty(), "no value bound for {:?}", unbound);
+    let deferred = defer_conditionals(bound.operations());
+    let use_parallel = config.parallel && num_qubits >= config.parallel_threshold;
+    let names = circuit.parameters();
+
+    let mut psi = circuit.start_state();
+    config.evolve(&mut psi, num_qubits, &deferred, None);
+    let mut lambda = observable.apply(&psi, use_parallel);
+    let expectation = inner(&psi, &lambda).real;
+
+    let apply = |state: &mut Vec<Complex<f64>>, kernel: Kernel| {
+        config.execute_kernels(state, &[kernel], num_qubits, use_parallel, None, 1)
+    };
+    let mut partials = vec![0.0; names.len()];
+    for (op, original) in deferred.iter().zip(circuit.operations()).rev() {
+        // The runtimes defer measurements to the end, so like barriers and
+        // snapshots they leave the state alone.
+        if op.is_measurement() || op.is_barrier() || op.is_snapshot() {
+            continue;
+        }
+        let inverse = Runtime::op_to_kernel(&op.adjoint()).unwrap();
+        apply(&mut psi, inverse.clone());
+        if !original.symbols().is_empty() {
+            assert!(
+                !original.is_conditional(),
+                "cannot differentiate {:?}, whose deferred form is a controlled gate",
+                original
+            );
+        }
+        for (slot, symbol) in original.symbols().iter().enumerate() {
+            let Some(symbol) = symbol else {
+                continue;
+            };
+            let mut derivative = psi.clone();
+            apply(&mut derivative, derivative_kernel(op, slot));
+            let (scale, _) = symbol.coefficients();
+            let index = names.iter().position(|name| name == symbol.name()).unwrap();
+            partials[index] += 2.0 * scale * inner(&lambda, &derivative).real;
+        }
+        apply(&mut lambda, inverse);
+    }
+
+    Gradient {
+        expectation,
+        partials: names.into_iter().zip(partials).collect(),
+    }
+}
+
+/// `∂U/∂x` for angle `slot` of `op`. Every entry of a gate matrix is a
+/// constant plus a sinusoid of frequency `ω` in each angle (½ for the
+/// rotation angles, 1 for phases), so the exact derivative is
+/// `ω/2 · (U(x + s) − U(x − s))` with `s = π/2ω`.
+fn derivative_kernel(op: &GateOp, slot: usize) -> Kernel {
+    let omega = match op {
+        GateOp::Rx(..)
+        | GateOp::Ry(..)
+        | GateOp::Rz(..)
+        | GateOp::CRx(..)
+        | GateOp::CRy(..)
+        | GateOp::CRz(..)
+        | GateOp::Rxx(..)
+        | GateOp::Ryy(..)
+        | GateOp::Rzz(..) => 0.5,
+        GateOp::U3(..) if slot == 0 => 0.5,
+        _ => 1.0,
+    };
+    let shift = PI / (2.0 * omega);
+    let shifted = |delta: f64| {
+        let mut params = op.params();
+        params[slot] += delta;
+        Runtime::op_to_kernel(&op.with_params(&params)).unwrap()
+    };
+    let (plus, minus) = (shifted(shift), shifted(-shift));
+    let difference = plus.matrix.subtract(&minus.matrix).unwrap();
+    let matrix = difference.scale(complex!(omega / 2.0, 0.0));
+    Kernel::new(&format!("∂{}", op.name()), matrix, plus.targets)
+}
+
+/// `⟨a|b⟩`.
+fn inner(a: &[Complex<f64>], b: &[Complex<f64>]) -> Complex<f64> {
+    a.iter().zip(b).fold(complex!(0.0, 0.0), |sum, (x, y)| {
+        sum + x.get_conjugate() * *y
+    })
+}
@@ -0,0 +1,225 @@
+use super::{GateOp, QuantumState, Runtime, SnapshotValue};
+use crate::Complex;
+use core::fmt;
+use std::collections::HashMap;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+
+/// How far a computation has got, and whether it should stop. Runtimes
+/// check it before every kernel (or gate, for the basic runtimes).
+pub(crate) struct Progress {
+    total: usize,
+    /// Operations applied so far, as `f64` bits; fused kernels count for a
+    /// fraction of an operation each.
+    done: AtomicU64,
+    cancelled: AtomicBool,
+}
+
+impl Progress {
+    fn new(total: usize) -> Self {
+        Progress {
+            total,
+            done: AtomicU64::new(0.0f64.to_bits()),
+            cancelled: AtomicBool::new(false),
+        }
+    }
+
+    fn fraction(&self) -> f64 {
+        if self.total == 0 {
+            return 1.0;
+        }
+        let done = f64::from_bits(self.done.load(Ordering::Relaxed));
+        (done / self.total as f64).min(1.0)
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Credits `operations` to `progress`, if there is one, and says whether
+    /// to carry on.
+    pub(crate) fn step(progress: Option<&Progress>, operations: f64) -> bool {
+        let Some(progress) = progress else {
+            return true;
+        };
+        if progress.is_cancelled() {
+            return false;
+        }
+        // Only the worker thread writes, so a plain load and store suffice.
+        let done = f64::from_bits(progress.done.load(Ordering::Relaxed)) + operations;
+        progress.done.store(done.to_bits(), Ordering::Relaxed);
+        true
+    }
+}
+
+/// What a finished `ComputeJob` produced.
+pub struct ComputeOutput {
+    pub state: QuantumState,
+    pub snapshots: HashMap<String, SnapshotValue>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum JobError {
+    Cancelled,
+    /// The runtime panicked, e.g. because it is not implemented.
+    Failed(String),
+}
+
+impl fmt::Display for JobError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JobError::Cancelled => write!(f, "computation cancelled"),
+            JobError::Failed(msg) => write!(f, "computation failed: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for JobError {}
+
+enum Outcome {
+    Pending,
+    Ready(Result<ComputeOutput, JobError>),
+    Taken,
+}
+
+struct Shared {
+    progress: Progress,
+    outcome: Mutex<Outcome>,
+    finished: Condvar,
+    waker: Mutex<Option<Waker>>,
+}
+
+/// A circuit computing on a background thread, from
+/// `QuantumCircuit::compute_async`.
+///
+/// The job can be polled (`progress`, `try_take`), blocked on (`wait`) or
+/// awaited from any async runtime, since it implements `Future`. Dropping it
+/// cancels the computation.
+pub struct ComputeJob {
+    shared: Arc<Shared>,
+}
+
+impl ComputeJob {
+    pub fn spawn(runtime: Runtime, num_qubits: usize, operations: Vec<GateOp>) -> Self {
+        Self::spawn_from(runtime, num_qubits, operations, None)
+    }
+
+    /// `spawn`, starting from `initial` rather than |0…0⟩ if given.
+    pub(crate) fn spawn_from(
+        runtime: Runtime,
+        num_qubits: usize,
+        operations: Vec<GateOp>,
+        initial: Option<Vec<Complex<f64>>>,
+    ) -> Self {
+        let shared = Arc::new(Shared {
+            progress: Progress::new(operations.len()),
+            outcome: Mutex::new(Outcome::Pending),
+            finished: Condvar::new(),
+            waker: Mutex::new(None),
+        });
+
+        let worker = Arc::clone(&shared);
+        thread::spawn(move || {
+            let progress = &worker.progress;
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                runtime.compute_with_progress(
+                    num_qubits,
+                    &operations,
+                    initial.as_deref(),
+                    Some(progress),
+                )
+            }));
+            let result = match result {
+                _ if progress.is_cancelled() => Err(JobError::Cancelled),
+                Ok((state, snapshots)) => Ok(ComputeOutput { state, snapshots }),
+                Err(payload) => Err(JobError::Failed(panic_message(payload.as_ref()))),
+            };
+
+            *worker.outcome.lock().unwrap() = Outcome::Ready(result);
+            worker.finished.notify_all();
+            if let Some(waker) = worker.waker.lock().unwrap().take() {
+                waker.wake();
+            }
+        });
+
+        ComputeJob { shared }
+    }
+
+    /// Fraction of the operations applied so far, from 0 to 1.
+    pub fn progress(&self) -> f64 {
+        self.shared.progress.fraction()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        !matches!(*self.shared.outcome.lock().unwrap(), Outcome::Pending)
+    }
+
+    /// Asks the computation to stop at the next kernel. The job then
+    /// finishes with `JobError::Cancelled`, unless it had already finished.
+    pub fn cancel(&self) {
+        self.shared
+            .progress
+            .cancelled
+            .store(true, Ordering::Relaxed);
+    }
+
+    /// The result, if the job has finished and it has not been taken yet.
+    pub fn try_take(&mut self) -> Option<Result<ComputeOutput, JobError>> {
+        take_ready(&mut self.shared.outcome.lock().unwrap())
+    }
+
+    /// Blocks until the job finishes.
+    pub fn wait(self) -> Result<ComputeOutput, JobError> {
+        let mut outcome = self.shared.outcome.lock().unwrap();
+        while matches!(*outcome, Outcome::Pending) {
+            outcome = self.shared.finished.wait(outcome).unwrap();
+        }
+        take_ready(&mut outcome).expect("ComputeJob result already taken")
+    }
+}
+
+fn take_ready(outcome: &mut Outcome) -> Option<Result<ComputeOutput, JobError>> {
+    match std::mem::replace(outcome, Outcome::Taken) {
+        Outcome::Ready(result) => Some(result),
+        other => {
+            *outcome = other;
+            None
+        }
+    }
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        msg.to_string()
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        "runtime panicked".to_string()
+    }
+}
+
+impl Future for ComputeJob {
+    type Output = Result<ComputeOutput, JobError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // Register first so a worker finishing in between still wakes us.
+        *self.shared.waker.lock().unwrap() = Some(cx.waker().clone());
+        let mut outcome = self.shared.outcome.lock().unwrap();
+        match &*outcome {
+            Outcome::Pending => Poll::Pending,
+            Outcome::Taken => panic!("ComputeJob polled after completion"),
+            Outcome::Ready(_) => Poll::Ready(take_ready(&mut outcome).unwrap()),
+        }
+    }
+}
+
+impl Drop for ComputeJob {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
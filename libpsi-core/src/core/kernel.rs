@@ -1,64 +1,138 @@
-use crate::maths::simd::{
-    apply_single_qubit_gate_simd, apply_single_qubit_gate_simd_parallel, SimdCapability,
-};
+use super::runtime::RuntimeConfig;
+use crate::maths::simd::SimdCapability;
 use crate::{complex, Complex, Matrix};
-use rayon::prelude::*;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
 
+/// Structure of a kernel's matrix, most specific first: a matrix that is
+/// both diagonal and controlled (CZ, CP) is `Diagonal`, and one that is both
+/// a permutation and controlled (CNOT, CCNOT) is `Permutation`.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum GateType {
+    /// Only diagonal entries are nonzero: each amplitude picks up a phase.
     Diagonal,
-    NonDiagonal,
+    /// Only anti-diagonal entries are nonzero, as for X and Y.
+    AntiDiagonal,
+    /// One nonzero entry per row and column: basis states are permuted and
+    /// given phases, as by SWAP and CNOT.
+    Permutation,
+    /// The identity unless some target qubit is 1, as for CRx and CRy.
     Controlled,
+    NonDiagonal,
 }
 
+/// Entries of magnitude below this count as zero when classifying.
+const STRUCTURE_TOLERANCE: f64 = 1e-10;
+
+/// Largest number of qubits two kernels may act on together for
+/// `Kernel::commutes_with` to compare their matrices.
+pub const MAX_COMMUTATION_QUBITS: usize = 3;
+
+/// Largest number of qubits `StructureAwareKernelBatch::optimise` fuses
+/// gates on different qubits into: an 8×8 kernel.
+pub const MAX_FUSED_QUBITS: usize = 3;
+
+/// A pass over the state vector, in complex multiply-adds per amplitude,
+/// for `Kernel::application_cost`.
+const PASS_COST: f64 = 4.0;
+
+/// `tag` is the title of the circuit section the kernel's gates came from
+/// (see `QuantumCircuit::section`); fusing kernels from different sections
+/// joins their tags with `+`.
 #[derive(Clone)]
 pub struct Kernel {
     pub matrix: Matrix<Complex<f64>>,
     pub targets: Vec<usize>,
     pub name: String,
     pub gate_type: GateType,
+    pub tag: Option<String>,
 }
 
 impl Kernel {
     pub fn new(name: &str, matrix: Matrix<Complex<f64>>, targets: Vec<usize>) -> Self {
-        let gate_type = Self::detect_gate_type(name, &matrix);
+        let gate_type = Self::detect_gate_type(&matrix);
         Self {
             matrix,
             targets,
             name: name.to_string(),
             gate_type,
+            tag: None,
         }
     }
 
-    fn detect_gate_type(name: &str, matrix: &Matrix<Complex<f64>>) -> GateType {
-        let diagonal_gates = [
-            "Z", "S", "T", "Sdg", "Tdg", "Rz", "P", "U1", "CZ", "CP", "CRz",
-        ];
-        if diagonal_gates.iter().any(|&g| name.starts_with(g)) {
-            return GateType::Diagonal;
+    pub fn with_tag(mut self, tag: Option<&str>) -> Self {
+        self.tag = tag.map(str::to_string);
+        self
+    }
+
+    /// The kernel's name prefixed with its tag, e.g. "prep:H+T+S".
+    pub fn label(&self) -> String {
+        match &self.tag {
+            Some(tag) => format!("{}:{}", tag, self.name),
+            None => self.name.clone(),
         }
+    }
 
-        let controlled_gates = [
-            "CNOT", "CZ", "SWAP", "CRx", "CRy", "CRz", "CP", "CCNOT", "CSWAP",
-        ];
-        if controlled_gates.iter().any(|&g| name.starts_with(g)) {
-            return GateType::Controlled;
+    fn detect_gate_type(matrix: &Matrix<Complex<f64>>) -> GateType {
+        let dim = matrix.rows;
+        let nonzero = |row: usize, col: usize| {
+            matrix.data[row * dim + col].norm2() > STRUCTURE_TOLERANCE * STRUCTURE_TOLERANCE
+        };
+        let only = |keep: &dyn Fn(usize, usize) -> bool| {
+            (0..dim).all(|row| (0..dim).all(|col| keep(row, col) || !nonzero(row, col)))
+        };
+
+        if only(&|row, col| row == col) {
+            return GateType::Diagonal;
+        }
+        if only(&|row, col| row + col == dim - 1) {
+            return GateType::AntiDiagonal;
+        }
+        let per_row = |row: usize| (0..dim).filter(|&col| nonzero(row, col)).count();
+        let per_col = |col: usize| (0..dim).filter(|&row| nonzero(row, col)).count();
+        if (0..dim).all(|i| per_row(i) == 1 && per_col(i) == 1) {
+            return GateType::Permutation;
         }
 
-        if matrix.rows == 2 && matrix.cols == 2 {
-            let is_diag = matrix.data[1].real.abs() < 1e-10
-                && matrix.data[1].imaginary.abs() < 1e-10
-                && matrix.data[2].real.abs() < 1e-10
-                && matrix.data[2].imaginary.abs() < 1e-10;
-            if is_diag {
-                return GateType::Diagonal;
-            }
+        if (0..dim.trailing_zeros()).any(|k| controls_on(matrix, dim >> (k + 1))) {
+            return GateType::Controlled;
         }
 
         GateType::NonDiagonal
     }
 
+    /// The targets the kernel is controlled on, and the kernel the other
+    /// targets get when those are all 1; elsewhere the kernel is the
+    /// identity. `None` unless some targets, but not all, are controls.
+    pub fn split_controls(&self) -> Option<(Vec<usize>, Kernel)> {
+        let dim = self.matrix.rows;
+        let g = self.targets.len();
+        let (controls, rest): (Vec<usize>, Vec<usize>) =
+            (0..g).partition(|&k| controls_on(&self.matrix, dim >> (k + 1)));
+        if controls.is_empty() || rest.is_empty() {
+            return None;
+        }
+        let control_bits: usize = controls.iter().map(|&k| dim >> (k + 1)).sum();
+        let index = |j: usize| {
+            rest.iter()
+                .enumerate()
+                .filter(|&(r, _)| (j >> (rest.len() - 1 - r)) & 1 == 1)
+                .fold(control_bits, |i, (_, &k)| i | dim >> (k + 1))
+        };
+        let block_dim = 1 << rest.len();
+        let data = (0..block_dim * block_dim)
+            .map(|e| self.matrix.data[index(e / block_dim) * dim + index(e % block_dim)])
+            .collect();
+        Some((
+            controls.iter().map(|&k| self.targets[k]).collect(),
+            Kernel::new(
+                &self.name,
+                Matrix::new(block_dim, block_dim, data),
+                rest.iter().map(|&k| self.targets[k]).collect(),
+            ),
+        ))
+    }
+
     pub fn num_qubits(&self) -> usize {
         self.targets.len()
     }
@@ -71,18 +145,56 @@ impl Kernel {
         self.targets.iter().any(|t| other.targets.contains(t))
     }
 
+    /// Whether applying the two kernels in either order gives the same
+    /// result. Kernels on disjoint qubits and pairs of diagonal kernels
+    /// always commute; otherwise the commutator of the matrices is computed
+    /// when the kernels act on at most `MAX_COMMUTATION_QUBITS` qubits
+    /// together, and larger pairs are assumed not to commute.
     pub fn commutes_with(&self, other: &Kernel) -> bool {
         if !self.shares_qubits(other) {
             return true;
         }
-
         if self.gate_type == GateType::Diagonal && other.gate_type == GateType::Diagonal {
-            if self.targets == other.targets {
-                return true;
-            }
+            return true;
         }
+        let union = self.union_targets(other);
+        if union.len() > MAX_COMMUTATION_QUBITS {
+            return false;
+        }
+        commutator_vanishes(&self.embed(&union), &other.embed(&union), 1 << union.len())
+    }
 
-        false
+    /// `self.targets` followed by the targets of `other` not among them.
+    fn union_targets(&self, other: &Kernel) -> Vec<usize> {
+        let mut union = self.targets.clone();
+        union.extend(other.targets.iter().filter(|t| !self.targets.contains(t)));
+        union
+    }
+
+    /// The matrix on `union`, a superset of the targets ordered like them
+    /// (`union[0]` most significant), acting as the identity elsewhere.
+    fn embed(&self, union: &[usize]) -> Vec<Complex<f64>> {
+        let width = union.len();
+        let dim = 1 << width;
+        let bits: Vec<usize> = self
+            .targets
+            .iter()
+            .map(|t| 1 << (width - 1 - union.iter().position(|u| u == t).unwrap()))
+            .collect();
+        let mask = bits.iter().fold(0, |m, b| m | b);
+        let local = |index: usize| {
+            bits.iter()
+                .fold(0, |l, &b| (l << 1) | (index & b != 0) as usize)
+        };
+
+        let size = self.matrix.rows;
+        let mut embedded = vec![complex!(0.0, 0.0); dim * dim];
+        for row in 0..dim {
+            for col in (0..dim).filter(|col| col & !mask == row & !mask) {
+                embedded[row * dim + col] = self.matrix.data[local(row) * size + local(col)];
+            }
+        }
+        embedded
     }
 
     pub fn can_fuse_with(&self, other: &Kernel) -> bool {
@@ -97,23 +209,129 @@ impl Kernel {
             return None;
         }
         let fused_matrix = other.matrix.dot(&self.matrix)?;
-        let new_type =
-            if self.gate_type == GateType::Diagonal && other.gate_type == GateType::Diagonal {
-                GateType::Diagonal
-            } else {
-                GateType::NonDiagonal
-            };
         Some(Kernel {
+            gate_type: Self::detect_gate_type(&fused_matrix),
             matrix: fused_matrix,
             targets: self.targets.clone(),
             name: format!("{}+{}", self.name, other.name),
-            gate_type: new_type,
+            tag: merge_tags(&self.tag, &other.tag),
         })
     }
+
+    /// The kernel that applies `self` and then `other`, on the union of
+    /// their targets, or `None` if that is more than `MAX_FUSED_QUBITS`.
+    pub fn fuse_block(&self, other: &Kernel) -> Option<Kernel> {
+        let union = self.union_targets(other);
+        if union.len() > MAX_FUSED_QUBITS {
+            return None;
+        }
+        let dim = 1 << union.len();
+        let first = Matrix::new(dim, dim, self.embed(&union));
+        let second = Matrix::new(dim, dim, other.embed(&union));
+        let fused_matrix = second.dot(&first)?;
+        Some(Kernel {
+            gate_type: Self::detect_gate_type(&fused_matrix),
+            matrix: fused_matrix,
+            targets: union,
+            name: format!("{}+{}", self.name, other.name),
+            tag: merge_tags(&self.tag, &other.tag),
+        })
+    }
+
+    /// Rough cost of applying the kernel, per amplitude of the state: a pass
+    /// over memory plus a multiply-add per nonzero entry of each row that
+    /// changes its amplitude. Rows holding a single one, as in identity
+    /// blocks and pure permutations, only move amplitudes.
+    fn application_cost(&self) -> f64 {
+        let dim = self.matrix.rows;
+        let multiplies: usize = self
+            .matrix
+            .data
+            .chunks(dim)
+            .map(|row| {
+                let mut nonzero = row
+                    .iter()
+                    .filter(|x| x.norm2() > STRUCTURE_TOLERANCE * STRUCTURE_TOLERANCE);
+                let count = nonzero.clone().count();
+                match nonzero.next() {
+                    Some(&x) if count == 1 && x == complex!(1.0, 0.0) => 0,
+                    _ => count,
+                }
+            })
+            .sum();
+        PASS_COST + multiplies as f64 / dim as f64
+    }
+}
+
+/// Whether `AB = BA` for `dim × dim` matrices stored row-major.
+fn commutator_vanishes(a: &[Complex<f64>], b: &[Complex<f64>], dim: usize) -> bool {
+    (0..dim).all(|row| {
+        (0..dim).all(|col| {
+            let mut commutator = complex!(0.0, 0.0);
+            for k in 0..dim {
+                commutator +=
+                    a[row * dim + k] * b[k * dim + col] - b[row * dim + k] * a[k * dim + col];
+            }
+            commutator.norm2() < STRUCTURE_TOLERANCE * STRUCTURE_TOLERANCE
+        })
+    })
+}
+
+fn merge_tags(first: &Option<String>, second: &Option<String>) -> Option<String> {
+    match (first, second) {
+        (Some(a), Some(b)) if !a.split('+').any(|tag| tag == b) => Some(format!("{}+{}", a, b)),
+        (Some(a), _) => Some(a.clone()),
+        (None, b) => b.clone(),
+    }
+}
+
+/// Runs `pass` on each run of kernels between two fences (indices of the
+/// first kernel after each), returning the concatenated result and where
+/// the fences fall in it.
+fn between_fences(
+    kernels: &[Kernel],
+    fences: &[usize],
+    mut pass: impl FnMut(Vec<Kernel>) -> Vec<Kernel>,
+) -> (Vec<Kernel>, Vec<usize>) {
+    let mut out = Vec::with_capacity(kernels.len());
+    let mut moved = Vec::with_capacity(fences.len());
+    let mut start = 0;
+    for &fence in fences {
+        out.extend(pass(kernels[start..fence].to_vec()));
+        moved.push(out.len());
+        start = fence;
+    }
+    out.extend(pass(kernels[start..].to_vec()));
+    (out, moved)
+}
+
+fn fuse_adjacent(kernels: Vec<Kernel>) -> Vec<Kernel> {
+    let mut optimized: Vec<Kernel> = Vec::with_capacity(kernels.len());
+    let mut i = 0;
+
+    while i < kernels.len() {
+        let current = &kernels[i];
+
+        if i + 1 < kernels.len() {
+            let next = &kernels[i + 1];
+            if let Some(fused) = current.fuse(next) {
+                optimized.push(fused);
+                i += 2;
+                continue;
+            }
+        }
+
+        optimized.push(current.clone());
+        i += 1;
+    }
+
+    optimized
 }
 
 pub struct KernelBatch {
     kernels: Vec<Kernel>,
+    /// Indices of the first kernel after each barrier.
+    fences: Vec<usize>,
     num_qubits: usize,
 }
 
@@ -121,6 +339,7 @@ impl KernelBatch {
     pub fn new(num_qubits: usize) -> Self {
         Self {
             kernels: Vec::new(),
+            fences: Vec::new(),
             num_qubits,
         }
     }
@@ -129,6 +348,14 @@ impl KernelBatch {
         self.kernels.push(kernel);
     }
 
+    /// Marks a barrier: `optimize` never fuses a kernel added before it
+    /// with one added after.
+    pub fn fence(&mut self) {
+        if self.fences.last() != Some(&self.kernels.len()) {
+            self.fences.push(self.kernels.len());
+        }
+    }
+
     pub fn len(&self) -> usize {
         self.kernels.len()
     }
@@ -141,73 +368,33 @@ impl KernelBatch {
         &self.kernels
     }
 
+    pub fn num_qubits(&self) -> usize {
+        self.num_qubits
+    }
+
     pub fn optimize(&mut self) {
+        span!(DEBUG, "optimize", kernels = self.kernels.len());
         if self.kernels.len() < 2 {
             return;
         }
 
-        let mut optimized: Vec<Kernel> = Vec::with_capacity(self.kernels.len());
-        let mut i = 0;
-
-        while i < self.kernels.len() {
-            let current = &self.kernels[i];
-
-            if i + 1 < self.kernels.len() {
-                let next = &self.kernels[i + 1];
-                if let Some(fused) = current.fuse(next) {
-                    optimized.push(fused);
-                    i += 2;
-                    continue;
-                }
-            }
-
-            optimized.push(current.clone());
-            i += 1;
-        }
-
-        self.kernels = optimized;
+        (self.kernels, self.fences) = between_fences(&self.kernels, &self.fences, fuse_adjacent);
     }
 
     pub fn execute(&self, state: &mut Vec<Complex<f64>>) {
-        for kernel in &self.kernels {
-            *state = apply_kernel(state, kernel, self.num_qubits);
-        }
+        run_kernels(state, &self.kernels, self.num_qubits, false, false);
     }
 
     pub fn execute_parallel(&self, state: &mut Vec<Complex<f64>>) {
-        for kernel in &self.kernels {
-            *state = apply_kernel_parallel(state, kernel, self.num_qubits);
-        }
+        run_kernels(state, &self.kernels, self.num_qubits, false, true);
     }
 
     pub fn execute_simd(&self, state: &mut Vec<Complex<f64>>) {
-        for kernel in &self.kernels {
-            if kernel.targets.len() == 1 {
-                let gate = matrix_to_2x2(&kernel.matrix);
-                apply_single_qubit_gate_simd(state, &gate, kernel.targets[0], self.num_qubits);
-            } else {
-                *state = apply_kernel(state, kernel, self.num_qubits);
-            }
-        }
+        run_kernels(state, &self.kernels, self.num_qubits, true, false);
     }
 
     pub fn execute_simd_parallel(&self, state: &mut Vec<Complex<f64>>) {
-        for kernel in &self.kernels {
-            if kernel.targets.len() == 1 && self.num_qubits >= 10 {
-                let gate = matrix_to_2x2(&kernel.matrix);
-                apply_single_qubit_gate_simd_parallel(
-                    state,
-                    &gate,
-                    kernel.targets[0],
-                    self.num_qubits,
-                );
-            } else if kernel.targets.len() == 1 {
-                let gate = matrix_to_2x2(&kernel.matrix);
-                apply_single_qubit_gate_simd(state, &gate, kernel.targets[0], self.num_qubits);
-            } else {
-                *state = apply_kernel_parallel(state, kernel, self.num_qubits);
-            }
-        }
+        run_kernels(state, &self.kernels, self.num_qubits, true, true);
     }
 
     pub fn simd_capability(&self) -> SimdCapability {
@@ -215,108 +402,37 @@ impl KernelBatch {
     }
 }
 
-fn matrix_to_2x2(matrix: &Matrix<Complex<f64>>) -> [[Complex<f64>; 2]; 2] {
-    [
-        [matrix.data[0], matrix.data[1]],
-        [matrix.data[2], matrix.data[3]],
-    ]
-}
-
-fn apply_kernel(state: &[Complex<f64>], kernel: &Kernel, num_qubits: usize) -> Vec<Complex<f64>> {
-    let dim = 1 << num_qubits;
-    let g = kernel.targets.len();
-    let gate_dim = 1 << g;
-
-    let target_bits: Vec<usize> = kernel.targets.iter().map(|&t| num_qubits - 1 - t).collect();
-
-    let mut non_target_mask: usize = (1 << num_qubits) - 1;
-    for &pos in &target_bits {
-        non_target_mask &= !(1 << pos);
-    }
-
-    let mut new_state = vec![complex!(0.0, 0.0); dim];
-
-    for i in 0..dim {
-        let mut target_idx = 0usize;
-        for (k, &pos) in target_bits.iter().enumerate() {
-            if (i >> pos) & 1 == 1 {
-                target_idx |= 1 << (g - 1 - k);
-            }
-        }
-
-        let mut sum = complex!(0.0, 0.0);
-
-        for j in 0..gate_dim {
-            let gate_elem = kernel.matrix.data[target_idx * gate_dim + j];
-
-            if gate_elem.real.abs() < 1e-15 && gate_elem.imaginary.abs() < 1e-15 {
-                continue;
-            }
-
-            let mut source_idx = i & non_target_mask;
-            for (k, &pos) in target_bits.iter().enumerate() {
-                if (j >> (g - 1 - k)) & 1 == 1 {
-                    source_idx |= 1 << pos;
-                }
-            }
-
-            sum = sum + gate_elem * state[source_idx];
-        }
-
-        new_state[i] = sum;
-    }
-
-    new_state
+/// Whether `matrix` is controlled on index bit `bit`: every basis state
+/// with that bit clear is left untouched.
+fn controls_on(matrix: &Matrix<Complex<f64>>, bit: usize) -> bool {
+    let dim = matrix.rows;
+    let is_fixed = |i: usize| {
+        (0..dim).all(|j| {
+            let entry = matrix.data[i * dim + j];
+            let transposed = matrix.data[j * dim + i];
+            let expected = if i == j { 1.0 } else { 0.0 };
+            (entry.real - expected).abs() < STRUCTURE_TOLERANCE
+                && entry.imaginary.abs() < STRUCTURE_TOLERANCE
+                && (i == j || transposed.norm2() < STRUCTURE_TOLERANCE * STRUCTURE_TOLERANCE)
+        })
+    };
+    (0..dim).filter(|i| i & bit == 0).all(is_fixed)
 }
 
-fn apply_kernel_parallel(
-    state: &[Complex<f64>],
-    kernel: &Kernel,
+/// Applies `kernels` in order, in place or through the runtime's ping-pong
+/// buffer, as `Runtime` does with the same options.
+fn run_kernels(
+    state: &mut Vec<Complex<f64>>,
+    kernels: &[Kernel],
     num_qubits: usize,
-) -> Vec<Complex<f64>> {
-    let dim = 1 << num_qubits;
-    let g = kernel.targets.len();
-    let gate_dim = 1 << g;
-
-    let target_bits: Vec<usize> = kernel.targets.iter().map(|&t| num_qubits - 1 - t).collect();
-
-    let mut non_target_mask: usize = (1 << num_qubits) - 1;
-    for &pos in &target_bits {
-        non_target_mask &= !(1 << pos);
-    }
-
-    (0..dim)
-        .into_par_iter()
-        .map(|i| {
-            let mut target_idx = 0usize;
-            for (k, &pos) in target_bits.iter().enumerate() {
-                if (i >> pos) & 1 == 1 {
-                    target_idx |= 1 << (g - 1 - k);
-                }
-            }
-
-            let mut sum = complex!(0.0, 0.0);
-
-            for j in 0..gate_dim {
-                let gate_elem = kernel.matrix.data[target_idx * gate_dim + j];
-
-                if gate_elem.real.abs() < 1e-15 && gate_elem.imaginary.abs() < 1e-15 {
-                    continue;
-                }
-
-                let mut source_idx = i & non_target_mask;
-                for (k, &pos) in target_bits.iter().enumerate() {
-                    if (j >> (g - 1 - k)) & 1 == 1 {
-                        source_idx |= 1 << pos;
-                    }
-                }
-
-                sum = sum + gate_elem * state[source_idx];
-            }
-
-            sum
-        })
-        .collect()
+    simd: bool,
+    parallel: bool,
+) {
+    let config = RuntimeConfig {
+        simd,
+        ..RuntimeConfig::new()
+    };
+    config.execute_kernels(state, kernels, num_qubits, parallel, None, kernels.len());
 }
 
 pub struct KernelBuilder {
@@ -367,8 +483,55 @@ impl Default for ExecutionLayer {
     }
 }
 
+/// Results of `Kernel::commutes_with` for pairs that need a matrix
+/// comparison, keyed by both matrices and how their targets overlap, so a
+/// gate pair repeated across the circuit is compared once.
+#[derive(Default)]
+struct CommutationCache {
+    results: HashMap<(MatrixKey, MatrixKey), bool>,
+}
+
+/// Bit patterns of a kernel's matrix entries and positions of its targets
+/// within the pair's union.
+type MatrixKey = (Vec<u64>, Vec<usize>);
+
+impl CommutationCache {
+    fn commutes(&mut self, a: &Kernel, b: &Kernel) -> bool {
+        let cheap = !a.shares_qubits(b)
+            || (a.gate_type == GateType::Diagonal && b.gate_type == GateType::Diagonal)
+            || a.targets.len() + b.targets.len() <= 2;
+        if cheap {
+            return a.commutes_with(b);
+        }
+        let union = a.union_targets(b);
+        if union.len() > MAX_COMMUTATION_QUBITS {
+            return false;
+        }
+        let positions = |k: &Kernel| {
+            k.targets
+                .iter()
+                .map(|t| union.iter().position(|u| u == t).unwrap())
+                .collect()
+        };
+        let bits = |k: &Kernel| {
+            k.matrix
+                .data
+                .iter()
+                .flat_map(|c| [c.real.to_bits(), c.imaginary.to_bits()])
+                .collect()
+        };
+        let key = ((bits(a), positions(a)), (bits(b), positions(b)));
+        *self
+            .results
+            .entry(key)
+            .or_insert_with(|| a.commutes_with(b))
+    }
+}
+
 pub struct StructureAwareKernelBatch {
     kernels: Vec<Kernel>,
+    /// Indices of the first kernel after each barrier.
+    fences: Vec<usize>,
     layers: Vec<ExecutionLayer>,
     num_qubits: usize,
     optimised: bool,
@@ -378,6 +541,7 @@ impl StructureAwareKernelBatch {
     pub fn new(num_qubits: usize) -> Self {
         Self {
             kernels: Vec::new(),
+            fences: Vec::new(),
             layers: Vec::new(),
             num_qubits,
             optimised: false,
@@ -389,6 +553,15 @@ impl StructureAwareKernelBatch {
         self.optimised = false;
     }
 
+    /// Marks a barrier: `optimise` neither fuses nor reorders kernels
+    /// across it, and no execution layer mixes kernels from both sides.
+    pub fn fence(&mut self) {
+        if self.fences.last() != Some(&self.kernels.len()) {
+            self.fences.push(self.kernels.len());
+            self.optimised = false;
+        }
+    }
+
     pub fn len(&self) -> usize {
         self.kernels.len()
     }
@@ -405,22 +578,32 @@ impl StructureAwareKernelBatch {
         &self.layers
     }
 
+    pub fn num_qubits(&self) -> usize {
+        self.num_qubits
+    }
+
     pub fn num_layers(&self) -> usize {
         self.layers.len()
     }
 
     pub fn optimise(&mut self) {
+        span!(DEBUG, "optimise", kernels = self.kernels.len());
         if self.optimised || self.kernels.len() < 2 {
             return;
         }
 
-        self.reorder_commuting_gates();
-        self.multi_pass_fusion();
-        self.build_execution_layers();
+        let mut commutations = CommutationCache::default();
+        (self.kernels, self.fences) = between_fences(&self.kernels, &self.fences, |mut kernels| {
+            Self::reorder_commuting_gates(&mut kernels, &mut commutations);
+            Self::multi_pass_fusion(&mut kernels);
+            Self::fuse_blocks(&mut kernels);
+            kernels
+        });
+        self.build_execution_layers(&mut commutations);
         self.optimised = true;
     }
 
-    fn reorder_commuting_gates(&mut self) {
+    fn reorder_commuting_gates(kernels: &mut Vec<Kernel>, commutations: &mut CommutationCache) {
         let mut changed = true;
         let mut iterations = 0;
         const MAX_ITERATIONS: usize = 100;
@@ -429,29 +612,28 @@ impl StructureAwareKernelBatch {
             changed = false;
             iterations += 1;
 
-            for i in 0..self.kernels.len().saturating_sub(1) {
-                let current = &self.kernels[i];
-                let next = &self.kernels[i + 1];
+            for i in 0..kernels.len().saturating_sub(1) {
+                let current = &kernels[i];
+                let next = &kernels[i + 1];
 
                 if current.targets.len() == 1
                     && next.targets.len() == 1
                     && current.targets[0] != next.targets[0]
-                    && current.commutes_with(next)
+                    && commutations.commutes(current, next)
                 {
-                    for j in (i + 2)..self.kernels.len() {
-                        let candidate = &self.kernels[j];
+                    for j in (i + 2)..kernels.len() {
+                        let candidate = &kernels[j];
 
                         if candidate.targets.len() == 1
                             && candidate.targets[0] == current.targets[0]
                         {
-                            let can_move = (i + 1..j).all(|k| {
-                                let between = &self.kernels[k];
-                                !between.shares_qubits(current) || current.commutes_with(between)
-                            });
+                            // The candidate moves back past everything in between.
+                            let can_move =
+                                (i + 1..j).all(|k| commutations.commutes(candidate, &kernels[k]));
 
                             if can_move && current.can_fuse_with(candidate) {
-                                let kernel_to_move = self.kernels.remove(j);
-                                self.kernels.insert(i + 1, kernel_to_move);
+                                let kernel_to_move = kernels.remove(j);
+                                kernels.insert(i + 1, kernel_to_move);
                                 changed = true;
                                 break;
                             }
@@ -462,7 +644,7 @@ impl StructureAwareKernelBatch {
         }
     }
 
-    fn multi_pass_fusion(&mut self) {
+    fn multi_pass_fusion(kernels: &mut Vec<Kernel>) {
         let mut changed = true;
         let mut iterations = 0;
         const MAX_ITERATIONS: usize = 50;
@@ -471,13 +653,13 @@ impl StructureAwareKernelBatch {
             changed = false;
             iterations += 1;
 
-            let mut new_kernels: Vec<Kernel> = Vec::with_capacity(self.kernels.len());
+            let mut new_kernels: Vec<Kernel> = Vec::with_capacity(kernels.len());
             let mut i = 0;
 
-            while i < self.kernels.len() {
-                if i + 1 < self.kernels.len() {
-                    let current = &self.kernels[i];
-                    let next = &self.kernels[i + 1];
+            while i < kernels.len() {
+                if i + 1 < kernels.len() {
+                    let current = &kernels[i];
+                    let next = &kernels[i + 1];
 
                     if let Some(fused) = current.fuse(next) {
                         new_kernels.push(fused);
@@ -487,92 +669,144 @@ impl StructureAwareKernelBatch {
                     }
                 }
 
-                new_kernels.push(self.kernels[i].clone());
+                new_kernels.push(kernels[i].clone());
                 i += 1;
             }
 
-            self.kernels = new_kernels;
+            *kernels = new_kernels;
         }
     }
 
-    fn build_execution_layers(&mut self) {
-        self.layers.clear();
-
-        for kernel in &self.kernels {
-            let mut placed = false;
-
-            for layer in &mut self.layers {
-                if layer.can_add(kernel) {
-                    layer.add(kernel.clone());
-                    placed = true;
-                    break;
+    /// Merges each kernel into the last one before it on any of its qubits
+    /// when the two act on at most `MAX_FUSED_QUBITS` qubits together and
+    /// the merged kernel is cheaper to apply than both. The kernels in
+    /// between are on other qubits, so the later kernel can move back.
+    fn fuse_blocks(kernels: &mut Vec<Kernel>) {
+        let mut fused: Vec<Kernel> = Vec::with_capacity(kernels.len());
+        // Index in `fused` of the last kernel on each qubit.
+        let mut last_on: HashMap<usize, usize> = HashMap::new();
+        for kernel in kernels.drain(..) {
+            let targets = kernel.targets.clone();
+            let last = targets.iter().filter_map(|t| last_on.get(t)).max().copied();
+            let merged = last.and_then(|j| {
+                fused[j].fuse_block(&kernel).filter(|block| {
+                    block.application_cost()
+                        <= fused[j].application_cost() + kernel.application_cost()
+                })
+            });
+            let index = match (last, merged) {
+                (Some(j), Some(block)) => {
+                    fused[j] = block;
+                    j
+                }
+                _ => {
+                    fused.push(kernel);
+                    fused.len() - 1
                 }
+            };
+            for t in targets {
+                last_on.insert(t, index);
             }
+        }
+        *kernels = fused;
+    }
+
+    /// Places every kernel in the earliest layer after the last one holding
+    /// a kernel it does not commute with, skipping layers that already use
+    /// one of its qubits.
+    fn build_execution_layers(&mut self, commutations: &mut CommutationCache) {
+        self.layers.clear();
+        // Layers before `floor` belong to the other side of a barrier.
+        let mut floor = 0;
+        let mut fences = self.fences.iter().peekable();
 
-            if !placed {
-                let mut new_layer = ExecutionLayer::new();
-                new_layer.add(kernel.clone());
-                self.layers.push(new_layer);
+        for (index, kernel) in self.kernels.iter().enumerate() {
+            while fences.next_if(|&&fence| fence == index).is_some() {
+                floor = self.layers.len();
+            }
+            let earliest = self.layers[floor..]
+                .iter()
+                .rposition(|layer| {
+                    layer
+                        .kernels
+                        .iter()
+                        .any(|k| !commutations.commutes(k, kernel))
+                })
+                .map_or(floor, |blocking| floor + blocking + 1);
+
+            match (earliest..self.layers.len()).find(|&l| self.layers[l].can_add(kernel)) {
+                Some(l) => self.layers[l].add(kernel.clone()),
+                None => {
+                    let mut new_layer = ExecutionLayer::new();
+                    new_layer.add(kernel.clone());
+                    self.layers.push(new_layer);
+                }
             }
         }
     }
 
     pub fn execute(&self, state: &mut Vec<Complex<f64>>) {
-        for kernel in &self.kernels {
-            *state = apply_kernel(state, kernel, self.num_qubits);
-        }
+        run_kernels(state, &self.kernels, self.num_qubits, false, false);
     }
 
     pub fn execute_parallel(&self, state: &mut Vec<Complex<f64>>) {
-        for kernel in &self.kernels {
-            *state = apply_kernel_parallel(state, kernel, self.num_qubits);
-        }
+        run_kernels(state, &self.kernels, self.num_qubits, false, true);
     }
 
     pub fn execute_layered(&self, state: &mut Vec<Complex<f64>>) {
         for layer in &self.layers {
-            for kernel in &layer.kernels {
-                *state = apply_kernel(state, kernel, self.num_qubits);
-            }
+            run_kernels(state, &layer.kernels, self.num_qubits, false, false);
         }
     }
 
     pub fn execute_layered_parallel(&self, state: &mut Vec<Complex<f64>>) {
         for layer in &self.layers {
-            for kernel in &layer.kernels {
-                *state = apply_kernel_parallel(state, kernel, self.num_qubits);
-            }
+            run_kernels(state, &layer.kernels, self.num_qubits, false, true);
         }
     }
 
-    pub fn execute_simd(&self, state: &mut Vec<Complex<f64>>) {
-        for kernel in &self.kernels {
-            if kernel.targets.len() == 1 {
-                let gate = matrix_to_2x2(&kernel.matrix);
-                apply_single_qubit_gate_simd(state, &gate, kernel.targets[0], self.num_qubits);
-            } else {
-                *state = apply_kernel(state, kernel, self.num_qubits);
-            }
+    /// Executes layer by layer like `execute_layered`, timing every kernel.
+    pub fn profile(&self, state: &mut Vec<Complex<f64>>) -> LayerProfile {
+        let kernel_tags = self
+            .layers
+            .iter()
+            .map(|layer| {
+                layer
+                    .kernels
+                    .iter()
+                    .map(|kernel| kernel.tag.clone())
+                    .collect()
+            })
+            .collect();
+        let kernel_times = self
+            .layers
+            .iter()
+            .map(|layer| {
+                layer
+                    .kernels
+                    .iter()
+                    .map(|kernel| {
+                        let start = Instant::now();
+                        let kernel = std::slice::from_ref(kernel);
+                        run_kernels(state, kernel, self.num_qubits, false, false);
+                        start.elapsed()
+                    })
+                    .collect()
+            })
+            .collect();
+
+        LayerProfile {
+            kernel_times,
+            kernel_tags,
         }
     }
 
+    pub fn execute_simd(&self, state: &mut Vec<Complex<f64>>) {
+        run_kernels(state, &self.kernels, self.num_qubits, true, false);
+    }
+
     pub fn execute_simd_parallel(&self, state: &mut Vec<Complex<f64>>) {
-        for kernel in &self.kernels {
-            if kernel.targets.len() == 1 && self.num_qubits >= 10 {
-                let gate = matrix_to_2x2(&kernel.matrix);
-                apply_single_qubit_gate_simd_parallel(
-                    state,
-                    &gate,
-                    kernel.targets[0],
-                    self.num_qubits,
-                );
-            } else if kernel.targets.len() == 1 {
-                let gate = matrix_to_2x2(&kernel.matrix);
-                apply_single_qubit_gate_simd(state, &gate, kernel.targets[0], self.num_qubits);
-            } else {
-                *state = apply_kernel_parallel(state, kernel, self.num_qubits);
-            }
-        }
+        run_kernels(state, &self.kernels, self.num_qubits, true, true);
     }
 
     pub fn stats(&self) -> KernelStats {
@@ -596,6 +830,92 @@ impl StructureAwareKernelBatch {
     }
 }
 
+/// Per-kernel wall-clock times and tags of a layered execution, indexed like
+/// `StructureAwareKernelBatch::layers`.
+#[derive(Debug, Clone)]
+pub struct LayerProfile {
+    pub kernel_times: Vec<Vec<Duration>>,
+    pub kernel_tags: Vec<Vec<Option<String>>>,
+}
+
+impl LayerProfile {
+    /// Time of the slowest kernel in the layer, i.e. the layer's duration if
+    /// its kernels ran concurrently.
+    pub fn layer_time(&self, layer: usize) -> Duration {
+        self.kernel_times[layer]
+            .iter()
+            .max()
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn serial_time(&self) -> Duration {
+        self.kernel_times.iter().flatten().sum()
+    }
+
+    /// Sum of the layer times: the runtime with perfect parallelism within
+    /// each layer.
+    pub fn critical_path(&self) -> Duration {
+        (0..self.kernel_times.len())
+            .map(|l| self.layer_time(l))
+            .sum()
+    }
+
+    /// Serial time spent in each tag, in order of first appearance; untagged
+    /// kernels are counted under `None`.
+    pub fn tag_times(&self) -> Vec<(Option<String>, Duration)> {
+        let mut totals: Vec<(Option<String>, Duration)> = Vec::new();
+        let kernels = self
+            .kernel_times
+            .iter()
+            .flatten()
+            .zip(self.kernel_tags.iter().flatten());
+        for (&time, tag) in kernels {
+            match totals.iter_mut().find(|(t, _)| t == tag) {
+                Some((_, total)) => *total += time,
+                None => totals.push((tag.clone(), time)),
+            }
+        }
+        totals
+    }
+}
+
+impl std::fmt::Display for LayerProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let serial = self.serial_time();
+        let critical = self.critical_path();
+        let speedup = if critical.is_zero() {
+            1.0
+        } else {
+            serial.as_secs_f64() / critical.as_secs_f64()
+        };
+        write!(
+            f,
+            "Serial: {:?}, critical path: {:?} ({:.2}x available parallelism)",
+            serial, critical, speedup
+        )?;
+
+        let tags = self.tag_times();
+        if tags.iter().any(|(tag, _)| tag.is_some()) {
+            for (tag, time) in tags {
+                let share = if serial.is_zero() {
+                    0.0
+                } else {
+                    time.as_secs_f64() / serial.as_secs_f64() * 100.0
+                };
+                write!(
+                    f,
+                    "\n  {}: {:?} ({:.1}%)",
+                    tag.as_deref().unwrap_or("(untagged)"),
+                    time,
+                    share
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct KernelStats {
     pub total_kernels: usize,
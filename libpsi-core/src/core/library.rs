@@ -0,0 +1,582 @@
+//! Standard subroutines as ready-made circuits, to be placed on any qubits
+//! with `QuantumCircuit::append`.
+
+use super::{Counts, CustomGate, GateOp, Parameter, Pauli, QuantumCircuit, SparsePauliOp};
+use crate::{complex, Complex, Matrix};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+/// The quantum Fourier transform on `n` qubits, qubit 0 most significant:
+/// `|j⟩ → Σₖ e^{2πi jk/2ⁿ} |k⟩ / √2ⁿ`. A Hadamard on each qubit followed by
+/// controlled phases `π/2^d` from the qubits `d` places below it, then
+/// swaps reversing the qubit order.
+pub fn qft(n: usize) -> QuantumCircuit {
+    approximate_qft(n, n)
+}
+
+/// The inverse of `qft(n)`.
+pub fn iqft(n: usize) -> QuantumCircuit {
+    qft(n).inverse()
+}
+
+/// `qft(n)` without the controlled phases between qubits more than `degree`
+/// apart, all smaller than `π/2^degree`. This cuts the `n²/2` controlled
+/// phases to about `n · degree`; a `degree` around `log₂ n` already stays
+/// close to the exact transform, and `n - 1` or more is exact.
+pub fn approximate_qft(n: usize, degree: usize) -> QuantumCircuit {
+    assert!(n >= 1, "QFT needs at least one qubit");
+    let mut circuit = QuantumCircuit::new(n);
+    for target in 0..n {
+        circuit.h(target);
+        for control in target + 1..n.min(target + degree + 1) {
+            circuit.cp(control, target, PI / (1u64 << (control - target)) as f64);
+        }
+    }
+    for q in 0..n / 2 {
+        circuit.swap(q, n - 1 - q);
+    }
+    circuit
+}
+
+/// `qft(n)` as a single gate, from its matrix `ω^{jk} / √2ⁿ` with
+/// `ω = e^{2πi/2ⁿ}`.
+pub fn qft_gate(n: usize) -> CustomGate {
+    CustomGate::from_matrix("QFT", fourier_matrix(n, 1.0))
+}
+
+/// `iqft(n)` as a single gate.
+pub fn iqft_gate(n: usize) -> CustomGate {
+    CustomGate::from_matrix("QFT†", fourier_matrix(n, -1.0))
+}
+
+/// The phase oracle flipping the sign of each `marked` basis state of `n`
+/// qubits, read with qubit 0 as the most significant bit: X gates on the
+/// zeros of each state around a Z controlled by all the other qubits.
+pub fn phase_oracle(n: usize, marked: &[usize]) -> QuantumCircuit {
+    assert!(n >= 1, "an oracle needs at least one qubit");
+    let mut circuit = QuantumCircuit::new(n);
+    for (i, &state) in marked.iter().enumerate() {
+        assert!(
+            state < 1 << n,
+            "marked state {} out of range for {} qubits",
+            state,
+            n
+        );
+        assert!(
+            !marked[..i].contains(&state),
+            "state {} marked twice",
+            state
+        );
+        let zeros: Vec<usize> = (0..n).filter(|q| state >> (n - 1 - q) & 1 == 0).collect();
+        for &q in &zeros {
+            circuit.x(q);
+        }
+        all_ones_phase(&mut circuit, n);
+        for &q in &zeros {
+            circuit.x(q);
+        }
+    }
+    circuit
+}
+
+/// The Grover diffusion operator `2|s⟩⟨s| - I` on `n` qubits, `|s⟩` the
+/// uniform superposition, up to a global phase: reflects every amplitude
+/// about their mean.
+pub fn diffusion(n: usize) -> QuantumCircuit {
+    assert!(n >= 1, "diffusion needs at least one qubit");
+    let mut circuit = QuantumCircuit::new(n);
+    for q in 0..n {
+        circuit.h(q).x(q);
+    }
+    all_ones_phase(&mut circuit, n);
+    for q in 0..n {
+        circuit.x(q).h(q);
+    }
+    circuit
+}
+
+/// Grover's search: the uniform superposition, then `iterations` rounds
+/// of `oracle` and `diffusion` on all of its qubits. Measuring afterwards
+/// finds a marked state with high probability when `iterations` is about
+/// `grover_iterations(n, marked)`.
+pub fn grover_search(oracle: &QuantumCircuit, iterations: usize) -> QuantumCircuit {
+    let n = oracle.num_qubits();
+    let qubits: Vec<usize> = (0..n).collect();
+    let round = diffusion(n);
+    let mut circuit = QuantumCircuit::new(n);
+    for q in 0..n {
+        circuit.h(q);
+    }
+    for _ in 0..iterations {
+        circuit.append(oracle, &qubits).append(&round, &qubits);
+    }
+    circuit
+}
+
+/// The number of Grover rounds, `⌊π/4 · √(2ⁿ/marked)⌋`, that brings the
+/// probability of measuring one of `marked` states out of `2ⁿ` closest to
+/// one.
+pub fn grover_iterations(n: usize, marked: usize) -> usize {
+    assert!(
+        marked >= 1 && marked <= 1 << n,
+        "{} marked states out of {}",
+        marked,
+        1u64 << n
+    );
+    let angle = (marked as f64 / (1u64 << n) as f64).sqrt().asin();
+    (PI / (4.0 * angle)).floor() as usize
+}
+
+/// Quantum phase estimation of `unitary` to `precision_qubits` bits. The
+/// circuit has the counting qubits first and the unitary's qubits after
+/// them, where the eigenstate should be prepared before appending it. Each
+/// counting qubit `j` controls `U^(2^(precision_qubits - 1 - j))`, the
+/// inverse QFT follows, and counting qubit `j` is measured into classical
+/// bit `j`, so for an eigenphase `e^{2πiφ}` the outcome read as a binary
+/// number is about `φ · 2^precision_qubits`; see `estimated_phase`.
+pub fn phase_estimation(unitary: &CustomGate, precision_qubits: usize) -> QuantumCircuit {
+    assert!(
+        precision_qubits >= 1,
+        "phase estimation needs at least one precision qubit"
+    );
+    let m = precision_qubits;
+    let targets: Vec<usize> = (m..m + unitary.num_qubits).collect();
+    let mut circuit = QuantumCircuit::new(m + unitary.num_qubits);
+    for q in 0..m {
+        circuit.h(q);
+    }
+    let mut power = unitary.to_quantum_gate().matrix;
+    for k in 0..m {
+        if k > 0 {
+            power = power.dot(&power).unwrap();
+        }
+        let name = format!("{}^{}", unitary.name, 1u64 << k);
+        let gate = Arc::new(CustomGate::from_matrix(&name, power.clone()));
+        circuit.push(GateOp::Custom(gate, targets.clone()).controlled(m - 1 - k));
+    }
+    circuit.append(&iqft(m), &(0..m).collect::<Vec<_>>());
+    for q in 0..m {
+        circuit.measure(q, q);
+    }
+    circuit
+}
+
+/// The phase `φ ∈ [0, 1)` read off the most frequent outcome of a
+/// `phase_estimation` circuit, or `None` for empty counts.
+pub fn estimated_phase(counts: &Counts) -> Option<f64> {
+    let (outcome, _) = counts.most_frequent()?;
+    let value = outcome
+        .chars()
+        .fold(0u64, |value, bit| 2 * value + u64::from(bit == '1'));
+    Some(value as f64 / (1u64 << outcome.len()) as f64)
+}
+
+/// The depth-`p` QAOA ansatz for `cost`: the uniform superposition, then
+/// `p` rounds of the cost layer `exp(-iγₖ C)` and the mixer
+/// `exp(-iβₖ Σ X)`, with `γₖ` and `βₖ` left as the parameters `gamma_k` and
+/// `beta_k` for `bind_parameters` or `RuntimeConfig::adjoint_gradients`.
+/// Each Pauli string of the cost becomes a basis change, a CNOT ladder and
+/// an Rz, so the cost layer is exact when the strings commute, as for
+/// MaxCut; identity terms only add a global phase and are dropped. Panics
+/// on complex coefficients.
+pub fn qaoa(cost: &SparsePauliOp, p: usize) -> QuantumCircuit {
+    let n = cost.num_qubits();
+    assert!(n >= 1, "QAOA needs at least one qubit");
+    for (coefficient, _) in cost.terms() {
+        assert!(
+            coefficient.imaginary == 0.0,
+            "QAOA cost has a complex coefficient {}",
+            coefficient
+        );
+    }
+    let mut circuit = QuantumCircuit::new(n);
+    for q in 0..n {
+        circuit.h(q);
+    }
+    for layer in 0..p {
+        let gamma = Parameter::new(&format!("gamma_{}", layer));
+        for (coefficient, string) in cost.terms() {
+            pauli_rotation(
+                &mut circuit,
+                string,
+                gamma.clone() * (2.0 * coefficient.real),
+            );
+        }
+        let beta = Parameter::new(&format!("beta_{}", layer));
+        for q in 0..n {
+            circuit.rx(q, beta.clone() * 2.0);
+        }
+    }
+    circuit
+}
+
+/// Single-qubit rotation layer of a `two_local` ansatz.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rotation {
+    Rx,
+    Ry,
+    Rz,
+}
+
+/// Two-qubit gate of the entangling layers of a `two_local` ansatz.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Entangler {
+    Cnot,
+    Cz,
+}
+
+/// Which pairs of qubits an entangling layer couples: neighbours `(q, q + 1)`,
+/// neighbours and `(n - 1, 0)`, or every pair `(i, j)` with `i < j`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Entanglement {
+    Linear,
+    Circular,
+    Full,
+}
+
+/// A hardware-efficient variational ansatz on `n` qubits: `reps` rounds of
+/// a layer of each of `rotations` on every qubit followed by an entangling
+/// layer, then a final rotation layer. Every rotation angle is its own
+/// parameter, `theta_0`, `theta_1`, ... in the order applied, zero-padded
+/// so that `QuantumCircuit::parameters` lists them in that order too.
+pub fn two_local(
+    n: usize,
+    rotations: &[Rotation],
+    entangler: Entangler,
+    entanglement: Entanglement,
+    reps: usize,
+) -> QuantumCircuit {
+    assert!(n >= 1, "an ansatz needs at least one qubit");
+    let count = n * rotations.len() * (reps + 1);
+    let width = count.saturating_sub(1).to_string().len();
+    let mut symbols = (0..count).map(|i| Parameter::new(&format!("theta_{:0width$}", i)));
+    let mut pairs: Vec<(usize, usize)> = match entanglement {
+        Entanglement::Full => (0..n)
+            .flat_map(|i| (i + 1..n).map(move |j| (i, j)))
+            .collect(),
+        _ => (1..n).map(|q| (q - 1, q)).collect(),
+    };
+    if entanglement == Entanglement::Circular && n > 2 {
+        pairs.push((n - 1, 0));
+    }
+    let mut circuit = QuantumCircuit::new(n);
+    for rep in 0..=reps {
+        for rotation in rotations {
+            for q in 0..n {
+                let theta = symbols.next().unwrap();
+                match rotation {
+                    Rotation::Rx => circuit.rx(q, theta),
+                    Rotation::Ry => circuit.ry(q, theta),
+                    Rotation::Rz => circuit.rz(q, theta),
+                };
+            }
+        }
+        if rep == reps {
+            break;
+        }
+        for &(a, b) in &pairs {
+            match entangler {
+                Entangler::Cnot => circuit.cnot(a, b),
+                Entangler::Cz => circuit.cz(a, b),
+            };
+        }
+    }
+    circuit
+}
+
+/// `two_local` with Ry rotations and a linear chain of CNOTs, the real
+/// amplitudes ansatz.
+pub fn ry_cnot_linear(n: usize, reps: usize) -> QuantumCircuit {
+    two_local(
+        n,
+        &[Rotation::Ry],
+        Entangler::Cnot,
+        Entanglement::Linear,
+        reps,
+    )
+}
+
+/// `two_local` with Ry and Rz rotations and a linear chain of CNOTs, which
+/// reaches any single-qubit state on each qubit between entangling layers.
+pub fn efficient_su2(n: usize, reps: usize) -> QuantumCircuit {
+    two_local(
+        n,
+        &[Rotation::Ry, Rotation::Rz],
+        Entangler::Cnot,
+        Entanglement::Linear,
+        reps,
+    )
+}
+
+/// Gates `random_circuit` draws from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GateSet {
+    /// H, S, S†, X, Y, Z, √X, CNOT, CZ and SWAP, so the stabilizer runtime
+    /// applies.
+    Clifford,
+    /// The Clifford gates, each qubit of each layer instead getting a T or
+    /// T† with probability `t_fraction`.
+    CliffordT { t_fraction: f64 },
+    /// Fixed gates and rotations by uniformly random angles, with
+    /// controlled rotations among the two-qubit gates.
+    Universal,
+}
+
+/// A random circuit of `depth` layers on `qubits` qubits, the same for the
+/// same `seed`. Each layer acts on every qubit once: the qubits are
+/// shuffled, those drawing a T under `GateSet::CliffordT` get it, and each
+/// of the others is given a single-qubit gate or, half of the time, paired
+/// with the next for a two-qubit gate.
+pub fn random_circuit(qubits: usize, depth: usize, seed: u64, gate_set: GateSet) -> QuantumCircuit {
+    if let GateSet::CliffordT { t_fraction } = gate_set {
+        assert!(
+            (0.0..=1.0).contains(&t_fraction),
+            "T fraction {} is not a probability",
+            t_fraction
+        );
+    }
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut circuit = QuantumCircuit::new(qubits);
+    let mut order: Vec<usize> = (0..qubits).collect();
+    for _ in 0..depth {
+        for i in (1..qubits).rev() {
+            order.swap(i, rng.random_range(0..=i));
+        }
+        let mut rest = Vec::with_capacity(qubits);
+        for &q in &order {
+            match gate_set {
+                GateSet::CliffordT { t_fraction } if rng.random_bool(t_fraction) => {
+                    circuit.push(if rng.random_bool(0.5) {
+                        GateOp::T(q)
+                    } else {
+                        GateOp::Tdg(q)
+                    });
+                }
+                _ => rest.push(q),
+            }
+        }
+        let mut i = 0;
+        while i < rest.len() {
+            if i + 1 < rest.len() && rng.random_bool(0.5) {
+                circuit.push(random_pair_gate(&mut rng, gate_set, rest[i], rest[i + 1]));
+                i += 2;
+            } else {
+                circuit.push(random_single_gate(&mut rng, gate_set, rest[i]));
+                i += 1;
+            }
+        }
+    }
+    circuit
+}
+
+fn random_single_gate(rng: &mut StdRng, gate_set: GateSet, q: usize) -> GateOp {
+    if gate_set == GateSet::Universal {
+        let mut angle = || rng.random_range(-PI..PI);
+        let (x, y, z) = (angle(), angle(), angle());
+        return match rng.random_range(0..8) {
+            0 => GateOp::H(q),
+            1 => GateOp::Sx(q),
+            2 => GateOp::T(q),
+            3 => GateOp::Rx(q, x),
+            4 => GateOp::Ry(q, x),
+            5 => GateOp::Rz(q, x),
+            6 => GateOp::P(q, x),
+            _ => GateOp::U3(q, x, y, z),
+        };
+    }
+    match rng.random_range(0..7) {
+        0 => GateOp::H(q),
+        1 => GateOp::S(q),
+        2 => GateOp::Sdg(q),
+        3 => GateOp::X(q),
+        4 => GateOp::Y(q),
+        5 => GateOp::Z(q),
+        _ => GateOp::Sx(q),
+    }
+}
+
+fn random_pair_gate(rng: &mut StdRng, gate_set: GateSet, a: usize, b: usize) -> GateOp {
+    if gate_set == GateSet::Universal {
+        let theta = rng.random_range(-PI..PI);
+        return match rng.random_range(0..5) {
+            0 => GateOp::CNOT(a, b),
+            1 => GateOp::CZ(a, b),
+            2 => GateOp::CRx(a, b, theta),
+            3 => GateOp::CRz(a, b, theta),
+            _ => GateOp::CP(a, b, theta),
+        };
+    }
+    match rng.random_range(0..3) {
+        0 => GateOp::CNOT(a, b),
+        1 => GateOp::CZ(a, b),
+        _ => GateOp::SWAP(a, b),
+    }
+}
+
+/// A circuit taking `|0…0⟩` to the state with `amplitudes` (qubit 0 most
+/// significant), up to a global phase, by Möttönen's decomposition into
+/// `Ry`, `Rz` and CNOT gates. Qubit by qubit from the first, a rotation
+/// uniformly controlled by the qubits before it splits each prefix's weight
+/// between its two extensions with `Ry`, then sets the phase differences
+/// with `Rz`. The amplitudes are normalised first; each layer costs `2ᵏ`
+/// rotations and as many CNOTs, and layers with no angle are left out, so
+/// real non-negative vectors take no `Rz` at all.
+pub fn prepare_state(amplitudes: &[Complex<f64>]) -> QuantumCircuit {
+    let dim = amplitudes.len();
+    assert!(
+        dim >= 2 && dim.is_power_of_two(),
+        "{} amplitudes do not make a state of one or more qubits",
+        dim
+    );
+    let weights: Vec<f64> = amplitudes.iter().map(Complex::norm2).collect();
+    assert!(
+        weights.iter().sum::<f64>() > 0.0,
+        "cannot prepare the zero vector"
+    );
+    let phases: Vec<f64> = amplitudes
+        .iter()
+        .map(|a| a.imaginary.atan2(a.real))
+        .collect();
+    let n = dim.trailing_zeros() as usize;
+    let mut circuit = QuantumCircuit::new(n);
+    for k in 0..n {
+        let angles: Vec<f64> = (0..1 << k)
+            .map(|prefix| {
+                let (zero, one) = halves(&weights, k, prefix);
+                let (zero, one): (f64, f64) = (zero.iter().sum(), one.iter().sum());
+                2.0 * one.sqrt().atan2(zero.sqrt())
+            })
+            .collect();
+        uniformly_controlled_rotation(&mut circuit, k, &angles, GateOp::Ry);
+    }
+    for k in 0..n {
+        // Rz(β) puts e^{∓iβ/2} on |0⟩ and |1⟩, so β is the difference of
+        // the mean phases under the two halves.
+        let angles: Vec<f64> = (0..1 << k)
+            .map(|prefix| {
+                let (zero, one) = halves(&phases, k, prefix);
+                let mean = |half: &[f64]| half.iter().sum::<f64>() / half.len() as f64;
+                mean(one) - mean(zero)
+            })
+            .collect();
+        uniformly_controlled_rotation(&mut circuit, k, &angles, GateOp::Rz);
+    }
+    circuit
+}
+
+/// The entries of `values`, one per basis state, under the prefix of
+/// length `k` followed by 0 and by 1.
+fn halves(values: &[f64], k: usize, prefix: usize) -> (&[f64], &[f64]) {
+    let block = values.len() >> (k + 1);
+    let start = prefix * 2 * block;
+    (
+        &values[start..start + block],
+        &values[start + block..start + 2 * block],
+    )
+}
+
+/// Appends the rotation of `target` by `angles[c]` when qubits
+/// `0..target` hold `c` (qubit 0 most significant), as `2ᵏ` plain rotations
+/// between CNOTs from the controls in Gray-code order. Each CNOT flips the
+/// sign of the rotations after it for one control, so rotation `i` is felt
+/// as `(−1)^{c · gᵢ} θᵢ` with `gᵢ` the `i`-th Gray code, and `θ` is the
+/// Walsh transform of `angles` that sums back to them.
+fn uniformly_controlled_rotation(
+    circuit: &mut QuantumCircuit,
+    target: usize,
+    angles: &[f64],
+    rotation: fn(usize, f64) -> GateOp,
+) {
+    if angles.iter().all(|angle| angle.abs() < 1e-12) {
+        return;
+    }
+    let count = angles.len();
+    let gray = |i: usize| i ^ (i >> 1);
+    for i in 0..count {
+        let theta = angles
+            .iter()
+            .enumerate()
+            .map(|(c, angle)| {
+                if (c & gray(i)).count_ones() % 2 == 0 {
+                    *angle
+                } else {
+                    -angle
+                }
+            })
+            .sum::<f64>()
+            / count as f64;
+        if theta.abs() >= 1e-12 {
+            circuit.push(rotation(target, theta));
+        }
+        if count > 1 {
+            let flipped = gray(i) ^ gray((i + 1) % count);
+            let control = target - 1 - flipped.trailing_zeros() as usize;
+            circuit.cnot(control, target);
+        }
+    }
+}
+
+/// Appends `exp(-iθ/2 · P)` for the Pauli string `string`: each X or Y
+/// rotated onto Z, the parity of the qubits collected by CNOTs onto the
+/// last of them, an `Rz(θ)` there, and the same steps undone.
+fn pauli_rotation(circuit: &mut QuantumCircuit, string: &[Pauli], theta: Parameter) {
+    let support: Vec<usize> = (0..string.len())
+        .filter(|&q| string[q] != Pauli::I)
+        .collect();
+    let Some(&last) = support.last() else {
+        return;
+    };
+    let change = |circuit: &mut QuantumCircuit, undo: bool| {
+        for &q in &support {
+            match string[q] {
+                Pauli::X => {
+                    circuit.h(q);
+                }
+                Pauli::Y => {
+                    circuit.rx(q, if undo { -PI / 2.0 } else { PI / 2.0 });
+                }
+                _ => {}
+            }
+        }
+    };
+    change(circuit, false);
+    for pair in support.windows(2) {
+        circuit.cnot(pair[0], pair[1]);
+    }
+    circuit.rz(last, theta);
+    for pair in support.windows(2).rev() {
+        circuit.cnot(pair[0], pair[1]);
+    }
+    change(circuit, true);
+}
+
+/// Appends `-1` on `|1…1⟩` of all `n` qubits: Z, CZ, or a Z on the last
+/// qubit controlled by all the others, as an MCX between Hadamards.
+fn all_ones_phase(circuit: &mut QuantumCircuit, n: usize) {
+    match n {
+        1 => circuit.z(0),
+        2 => circuit.cz(0, 1),
+        _ => {
+            let controls: Vec<usize> = (0..n - 1).collect();
+            circuit.h(n - 1).mcx(&controls, n - 1).h(n - 1)
+        }
+    };
+}
+
+/// `ω^{±jk} / √2ⁿ`, the sign of the exponent given by `direction`.
+fn fourier_matrix(n: usize, direction: f64) -> Matrix<Complex<f64>> {
+    assert!(n >= 1, "QFT needs at least one qubit");
+    let dim = 1usize << n;
+    let scale = 1.0 / (dim as f64).sqrt();
+    let data = (0..dim * dim)
+        .map(|i| {
+            // jk mod 2ⁿ keeps the angle exact for large indices.
+            let phase = ((i / dim) * (i % dim)) % dim;
+            let angle = direction * 2.0 * PI * phase as f64 / dim as f64;
+            complex!(scale * angle.cos(), scale * angle.sin())
+        })
+        .collect();
+    Matrix::new(dim, dim, data)
+}
@@ -0,0 +1,294 @@
+use super::counts::entropy_seed;
+use super::{Counts, QuantumCircuit};
+use crate::Matrix;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::BTreeMap;
+
+/// Largest register `ReadoutCalibration` handles: it runs `2ⁿ` circuits and
+/// keeps a `2ⁿ × 2ⁿ` matrix.
+pub const MAX_CALIBRATED_BITS: usize = 12;
+
+/// Classical readout noise, applied to sampled counts: bit `i` of an
+/// outcome reads 1 for a 0 with probability `p0_to_1[i]`, and 0 for a 1
+/// with probability `p1_to_0[i]`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReadoutError {
+    p0_to_1: Vec<f64>,
+    p1_to_0: Vec<f64>,
+}
+
+impl ReadoutError {
+    /// Per-bit `(p0_to_1, p1_to_0)` pairs.
+    pub fn new(flips: &[(f64, f64)]) -> Self {
+        for &(a, b) in flips {
+            assert!(
+                (0.0..=1.0).contains(&a) && (0.0..=1.0).contains(&b),
+                "readout error probabilities must be in [0, 1]"
+            );
+        }
+        ReadoutError {
+            p0_to_1: flips.iter().map(|&(a, _)| a).collect(),
+            p1_to_0: flips.iter().map(|&(_, b)| b).collect(),
+        }
+    }
+
+    /// The same error on every one of `num_bits` bits.
+    pub fn uniform(num_bits: usize, p0_to_1: f64, p1_to_0: f64) -> Self {
+        Self::new(&vec![(p0_to_1, p1_to_0); num_bits])
+    }
+
+    pub fn num_bits(&self) -> usize {
+        self.p0_to_1.len()
+    }
+
+    /// `counts` as a noisy readout would have reported them, flipping the
+    /// bits of each shot independently.
+    pub fn apply(&self, counts: &Counts, seed: Option<u64>) -> Counts {
+        let mut rng = StdRng::seed_from_u64(seed.unwrap_or_else(entropy_seed));
+        let mut noisy: BTreeMap<String, usize> = BTreeMap::new();
+        for (outcome, shots) in counts.iter() {
+            assert_eq!(
+                outcome.len(),
+                self.num_bits(),
+                "outcome {} does not have {} bits",
+                outcome,
+                self.num_bits()
+            );
+            for _ in 0..shots {
+                let read: String = outcome
+                    .bytes()
+                    .enumerate()
+                    .map(|(i, bit)| {
+                        let one = bit == b'1';
+                        let flip = if one {
+                            self.p1_to_0[i]
+                        } else {
+                            self.p0_to_1[i]
+                        };
+                        if one ^ (rng.random::<f64>() < flip) {
+                            '1'
+                        } else {
+                            '0'
+                        }
+                    })
+                    .collect();
+                *noisy.entry(read).or_insert(0) += 1;
+            }
+        }
+        Counts::from_outcomes(noisy)
+    }
+}
+
+/// How `ReadoutCalibration::mitigate` undoes the confusion matrix.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Mitigation {
+    /// Multiplies by the inverse. Unbiased, but the result can have
+    /// negative entries.
+    Inverse,
+    /// The probability distribution `p` minimising `‖A p − q‖²`, found by
+    /// projected gradient descent on the simplex.
+    #[default]
+    LeastSquares,
+}
+
+/// The confusion matrix of a register's readout: entry `(measured,
+/// prepared)` is the probability of reading basis state `measured` after
+/// preparing `prepared`, with indices in the bit order of `Counts`.
+#[derive(Clone)]
+pub struct ReadoutCalibration {
+    num_bits: usize,
+    confusion: Matrix<f64>,
+}
+
+impl ReadoutCalibration {
+    /// One circuit per basis state: `X` on the qubits that are 1, then every
+    /// qubit measured into the classical bit of the same index. Circuit `i`
+    /// prepares the state whose `Counts` outcome has index `i`.
+    pub fn circuits(num_qubits: usize) -> Vec<QuantumCircuit> {
+        assert!(
+            num_qubits <= MAX_CALIBRATED_BITS,
+            "calibrating {} qubits needs {} circuits",
+            num_qubits,
+            1u64 << num_qubits
+        );
+        (0..1usize << num_qubits)
+            .map(|state| {
+                let mut circuit = QuantumCircuit::with_classical(num_qubits, num_qubits);
+                for q in 0..num_qubits {
+                    if bit(state, q, num_qubits) {
+                        circuit.x(q);
+                    }
+                }
+                for q in 0..num_qubits {
+                    circuit.measure(q, q);
+                }
+                circuit
+            })
+            .collect()
+    }
+
+    /// Assembles the confusion matrix from the counts of `circuits`, in
+    /// the same order.
+    pub fn from_counts(num_bits: usize, counts: &[Counts]) -> Self {
+        let dim = 1usize << num_bits;
+        assert_eq!(counts.len(), dim, "expected counts for {} circuits", dim);
+        let mut confusion = Matrix::new(dim, dim, vec![0.0; dim * dim]);
+        for (prepared, counts) in counts.iter().enumerate() {
+            for (outcome, _) in counts.iter() {
+                confusion.set(index(outcome), prepared, counts.probability(outcome));
+            }
+        }
+        ReadoutCalibration {
+            num_bits,
+            confusion,
+        }
+    }
+
+    /// Runs the calibration circuits through `sample`, e.g. an ideal
+    /// simulation followed by `ReadoutError::apply`, or hardware.
+    pub fn measure(num_bits: usize, mut sample: impl FnMut(QuantumCircuit) -> Counts) -> Self {
+        let counts: Vec<Counts> = Self::circuits(num_bits)
+            .into_iter()
+            .map(&mut sample)
+            .collect();
+        Self::from_counts(num_bits, &counts)
+    }
+
+    pub fn num_bits(&self) -> usize {
+        self.num_bits
+    }
+
+    pub fn confusion_matrix(&self) -> &Matrix<f64> {
+        &self.confusion
+    }
+
+    /// Probability of reading what was prepared, averaged over basis states.
+    pub fn assignment_fidelity(&self) -> f64 {
+        let dim = self.confusion.rows;
+        (0..dim).map(|i| self.confusion.get(i, i)).sum::<f64>() / dim as f64
+    }
+
+    /// Estimated noiseless outcome probabilities behind `counts`, keyed like
+    /// `Counts`. Entries below 1e-12 in magnitude are dropped.
+    pub fn mitigate(&self, counts: &Counts, method: Mitigation) -> BTreeMap<String, f64> {
+        let dim = self.confusion.rows;
+        let mut measured = vec![0.0; dim];
+        for (outcome, _) in counts.iter() {
+            assert_eq!(
+                outcome.len(),
+                self.num_bits,
+                "outcome {} does not have {} bits",
+                outcome,
+                self.num_bits
+            );
+            measured[index(outcome)] = counts.probability(outcome);
+        }
+
+        let estimate = match method {
+            Mitigation::Inverse => solve(&self.confusion, &measured)
+                .expect("confusion matrix is singular; use Mitigation::LeastSquares"),
+            Mitigation::LeastSquares => least_squares(&self.confusion, &measured),
+        };
+        estimate
+            .into_iter()
+            .enumerate()
+            .filter(|&(_, p)| p.abs() > 1e-12)
+            .map(|(i, p)| {
+                let key = (0..self.num_bits)
+                    .map(|q| if bit(i, q, self.num_bits) { '1' } else { '0' })
+                    .collect();
+                (key, p)
+            })
+            .collect()
+    }
+}
+
+/// Bit `q` of basis index `state`, bit 0 being the most significant.
+fn bit(state: usize, q: usize, num_bits: usize) -> bool {
+    (state >> (num_bits - 1 - q)) & 1 == 1
+}
+
+fn index(outcome: &str) -> usize {
+    outcome
+        .bytes()
+        .fold(0, |acc, b| (acc << 1) | (b == b'1') as usize)
+}
+
+/// `A x = b` by Gaussian elimination with partial pivoting.
+fn solve(a: &Matrix<f64>, b: &[f64]) -> Option<Vec<f64>> {
+    let n = a.rows;
+    let mut m: Vec<Vec<f64>> = (0..n)
+        .map(|r| {
+            let mut row: Vec<f64> = (0..n).map(|c| a.get(r, c)).collect();
+            row.push(b[r]);
+            row
+        })
+        .collect();
+    for col in 0..n {
+        let pivot = (col..n).max_by(|&x, &y| m[x][col].abs().total_cmp(&m[y][col].abs()))?;
+        if m[pivot][col].abs() < 1e-12 {
+            return None;
+        }
+        m.swap(col, pivot);
+        for r in 0..n {
+            if r != col && m[r][col] != 0.0 {
+                let factor = m[r][col] / m[col][col];
+                let pivot_row = m[col].clone();
+                for (x, p) in m[r].iter_mut().zip(pivot_row).skip(col) {
+                    *x -= factor * p;
+                }
+            }
+        }
+    }
+    Some((0..n).map(|r| m[r][n] / m[r][r]).collect())
+}
+
+/// Minimises `‖A p − q‖²` over probability distributions `p`.
+fn least_squares(a: &Matrix<f64>, q: &[f64]) -> Vec<f64> {
+    let n = a.rows;
+    // ‖A‖² ≤ ‖A‖₁‖A‖∞ bounds the gradient's Lipschitz constant.
+    let norm_1 = (0..n)
+        .map(|c| (0..n).map(|r| a.get(r, c).abs()).sum::<f64>())
+        .fold(0.0, f64::max);
+    let norm_inf = (0..n)
+        .map(|r| (0..n).map(|c| a.get(r, c).abs()).sum::<f64>())
+        .fold(0.0, f64::max);
+    let step = 1.0 / (norm_1 * norm_inf).max(1e-12);
+
+    let mut p = q.to_vec();
+    project_to_simplex(&mut p);
+    for _ in 0..10_000 {
+        let residual: Vec<f64> = (0..n)
+            .map(|r| (0..n).map(|c| a.get(r, c) * p[c]).sum::<f64>() - q[r])
+            .collect();
+        let mut next: Vec<f64> = (0..n)
+            .map(|c| p[c] - step * (0..n).map(|r| a.get(r, c) * residual[r]).sum::<f64>())
+            .collect();
+        project_to_simplex(&mut next);
+        let change: f64 = next.iter().zip(&p).map(|(x, y)| (x - y).abs()).sum();
+        p = next;
+        if change < 1e-12 {
+            break;
+        }
+    }
+    p
+}
+
+/// Euclidean projection onto `{p : p ≥ 0, Σp = 1}` (Duchi et al., 2008).
+fn project_to_simplex(p: &mut [f64]) {
+    let mut sorted = p.to_vec();
+    sorted.sort_by(|x, y| y.total_cmp(x));
+    let mut sum = 0.0;
+    let mut theta = 0.0;
+    for (i, &x) in sorted.iter().enumerate() {
+        sum += x;
+        let candidate = (sum - 1.0) / (i + 1) as f64;
+        if x - candidate > 0.0 {
+            theta = candidate;
+        }
+    }
+    for x in p.iter_mut() {
+        *x = (*x - theta).max(0.0);
+    }
+}
@@ -1,17 +1,64 @@
+pub mod backend;
 pub mod circuit;
 pub mod classical_components;
+pub mod counts;
 pub mod custom_gate;
+pub mod debugger;
+pub mod decoder;
+mod equivalence;
+pub mod evolution;
+pub mod extended_stabilizer;
 pub mod gates;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+pub mod gradient;
+pub mod job;
 pub mod kernel;
+pub mod library;
+pub mod mitigation;
 pub mod noise;
+pub mod operators;
+pub mod parameter;
+pub mod product_state;
+pub mod qec;
 pub mod quantum_components;
 pub mod runtime;
+pub mod schedule;
+mod shots;
+pub mod snapshot;
+mod spectrum;
+pub mod stabilizer;
+mod state_file;
+pub mod trace;
+pub mod trajectory;
+pub mod truncated_state;
 
+pub use backend::*;
 pub use circuit::*;
 pub use classical_components::*;
+pub use counts::*;
 pub use custom_gate::*;
+pub use debugger::*;
+pub use decoder::*;
+pub use evolution::*;
+pub use extended_stabilizer::*;
 pub use gates::*;
+#[cfg(feature = "gpu")]
+pub use gpu::*;
+pub use gradient::*;
+pub use job::*;
 pub use kernel::*;
+pub use mitigation::*;
 pub use noise::*;
+pub use operators::*;
+pub use parameter::*;
+pub use product_state::*;
+pub use qec::*;
 pub use quantum_components::*;
 pub use runtime::*;
+pub use schedule::*;
+pub use snapshot::*;
+pub use stabilizer::*;
+pub use trace::*;
+pub use trajectory::*;
+pub use truncated_state::*;
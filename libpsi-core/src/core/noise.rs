@@ -1,6 +1,11 @@
+use super::circuit::defer_conditionals;
+use super::spectrum::{hermitian_eigenvalues, hermitian_function};
+use super::{GateOp, Kernel, QuantumCircuit, Runtime};
 use crate::{complex, Complex, Matrix};
+use std::collections::BTreeMap;
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct KrausOperator {
     pub matrix: Matrix<Complex<f64>>,
     pub name: String,
@@ -16,6 +21,7 @@ impl KrausOperator {
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NoiseChannel {
     pub name: String,
     pub operators: Vec<KrausOperator>,
@@ -119,10 +125,7 @@ impl NoiseChannel {
 
         Self::new(
             "AmplitudeDamping",
-            vec![
-                KrausOperator::new("K0", k0),
-                KrausOperator::new("K1", k1),
-            ],
+            vec![KrausOperator::new("K0", k0), KrausOperator::new("K1", k1)],
             1,
         )
     }
@@ -155,10 +158,7 @@ impl NoiseChannel {
 
         Self::new(
             "PhaseDamping",
-            vec![
-                KrausOperator::new("K0", k0),
-                KrausOperator::new("K1", k1),
-            ],
+            vec![KrausOperator::new("K0", k0), KrausOperator::new("K1", k1)],
             1,
         )
     }
@@ -334,6 +334,149 @@ impl NoiseChannel {
     }
 }
 
+/// Where noise strikes in a circuit: channels after every gate, after gates
+/// of a given name, after every gate on a given qubit, and on qubits left
+/// idle while others are busy.
+///
+/// Channels after a gate act on each of its qubits when they take one
+/// qubit, and on all of them together when they are as wide as the gate.
+/// Qubit and idle noise take single-qubit channels. Idle time is counted in
+/// layers of the circuit, each gate starting as soon as its qubits are
+/// free; a qubit idles in every layer in which it has no gate, until the
+/// last layer. Measurements, barriers and snapshots are noiseless.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NoiseModel {
+    all_gates: Vec<NoiseChannel>,
+    by_gate: BTreeMap<String, Vec<NoiseChannel>>,
+    by_qubit: BTreeMap<usize, Vec<NoiseChannel>>,
+    idle: Vec<NoiseChannel>,
+}
+
+/// An operation of a circuit under a `NoiseModel`, in the order applied.
+pub(crate) enum NoisyStep<'a> {
+    Gate(Kernel),
+    Channel(&'a NoiseChannel, Vec<usize>),
+}
+
+impl NoiseModel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Follows every gate with `channel`.
+    pub fn add_gate_noise(&mut self, channel: NoiseChannel) -> &mut Self {
+        self.all_gates.push(channel);
+        self
+    }
+
+    /// Follows every gate called `gate`, as reported by `GateOp::name`,
+    /// with `channel`.
+    pub fn add_gate_type_noise(&mut self, gate: &str, channel: NoiseChannel) -> &mut Self {
+        self.by_gate
+            .entry(gate.to_string())
+            .or_default()
+            .push(channel);
+        self
+    }
+
+    /// Applies `channel` to `qubit` after every gate on it.
+    pub fn add_qubit_noise(&mut self, qubit: usize, channel: NoiseChannel) -> &mut Self {
+        assert_eq!(channel.num_qubits, 1, "qubit noise must act on one qubit");
+        self.by_qubit.entry(qubit).or_default().push(channel);
+        self
+    }
+
+    /// Applies `channel` to every qubit once per layer it spends idle.
+    pub fn add_idle_noise(&mut self, channel: NoiseChannel) -> &mut Self {
+        assert_eq!(channel.num_qubits, 1, "idle noise must act on one qubit");
+        self.idle.push(channel);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.all_gates.is_empty()
+            && self.by_gate.is_empty()
+            && self.by_qubit.is_empty()
+            && self.idle.is_empty()
+    }
+
+    /// The gates of `operations` with the model's channels in between.
+    pub(crate) fn steps<'a>(
+        &'a self,
+        num_qubits: usize,
+        operations: &[GateOp],
+    ) -> Vec<NoisyStep<'a>> {
+        let mut steps = Vec::new();
+        // Layers up to which each qubit's idling has been accounted for,
+        // and the first layer in which each qubit is free.
+        let mut accounted = vec![0; num_qubits];
+        let mut free = vec![0; num_qubits];
+        let idle = |steps: &mut Vec<NoisyStep<'a>>, qubit: usize, layers: usize| {
+            for _ in 0..layers {
+                for channel in &self.idle {
+                    steps.push(NoisyStep::Channel(channel, vec![qubit]));
+                }
+            }
+        };
+
+        // A conditional gate runs as its deferred, controlled form, but
+        // picks up the noise of the gate itself.
+        let deferred = defer_conditionals(operations);
+        for (op, gate) in operations.iter().zip(deferred.iter()) {
+            if let GateOp::Barrier(qubits, _) = gate {
+                let fence = qubits.iter().map(|&q| free[q]).max().unwrap_or(0);
+                for &q in qubits {
+                    free[q] = fence;
+                }
+                continue;
+            }
+            let Some(kernel) = Runtime::op_to_kernel(gate) else {
+                continue;
+            };
+            let layer = kernel.targets.iter().map(|&q| free[q]).max().unwrap_or(0);
+            for &q in &kernel.targets {
+                idle(&mut steps, q, layer - accounted[q]);
+                accounted[q] = layer + 1;
+                free[q] = layer + 1;
+            }
+            steps.push(NoisyStep::Gate(kernel));
+
+            let targets = op.quantum_targets();
+
+            let by_gate = self.by_gate.get(op.name()).into_iter().flatten();
+            for channel in self.all_gates.iter().chain(by_gate) {
+                if channel.num_qubits == 1 {
+                    for &q in &targets {
+                        steps.push(NoisyStep::Channel(channel, vec![q]));
+                    }
+                } else {
+                    assert_eq!(
+                        channel.num_qubits,
+                        targets.len(),
+                        "{}-qubit channel {} cannot follow {}",
+                        channel.num_qubits,
+                        channel.name,
+                        op.name()
+                    );
+                    steps.push(NoisyStep::Channel(channel, targets.clone()));
+                }
+            }
+            for &q in &targets {
+                for channel in self.by_qubit.get(&q).into_iter().flatten() {
+                    steps.push(NoisyStep::Channel(channel, vec![q]));
+                }
+            }
+        }
+
+        let depth = free.iter().copied().max().unwrap_or(0);
+        for (q, &done) in accounted.iter().enumerate() {
+            idle(&mut steps, q, depth.saturating_sub(done));
+        }
+        steps
+    }
+}
+
 #[derive(Clone)]
 pub struct DensityMatrix {
     pub data: Vec<Complex<f64>>,
@@ -382,7 +525,7 @@ impl DensityMatrix {
     pub fn trace(&self) -> Complex<f64> {
         let mut sum = complex!(0.0, 0.0);
         for i in 0..self.dim {
-            sum = sum + self.get(i, i);
+            sum += self.get(i, i);
         }
         sum
     }
@@ -393,7 +536,7 @@ impl DensityMatrix {
             for j in 0..self.dim {
                 let rho_ij = self.get(i, j);
                 let rho_ji = self.get(j, i);
-                sum = sum + rho_ij * rho_ji;
+                sum += rho_ij * rho_ji;
             }
         }
         sum.real
@@ -411,10 +554,7 @@ impl DensityMatrix {
         let g = targets.len();
         let gate_dim = 1 << g;
 
-        let target_bits: Vec<usize> = targets
-            .iter()
-            .map(|&t| self.num_qubits - 1 - t)
-            .collect();
+        let target_bits: Vec<usize> = targets.iter().map(|&t| self.num_qubits - 1 - t).collect();
 
         let mut non_target_mask: usize = (1 << self.num_qubits) - 1;
         for &pos in &target_bits {
@@ -456,7 +596,7 @@ impl DensityMatrix {
                         let u_jl_dag = gate.data[tgt_j * gate_dim + l].get_conjugate();
                         let rho_kl = self.get(src_i, src_j);
 
-                        sum = sum + u_ik * rho_kl * u_jl_dag;
+                        sum += u_ik * rho_kl * u_jl_dag;
                     }
                 }
 
@@ -467,6 +607,44 @@ impl DensityMatrix {
         self.data = new_data;
     }
 
+    /// The density matrix of `circuit` from its initial state (`|0…0⟩`
+    /// unless set) with `noise` applied; an empty model gives the pure
+    /// final state.
+    pub fn from_circuit(circuit: &QuantumCircuit, noise: &NoiseModel) -> Self {
+        let mut dm = match circuit.initial_state() {
+            Some(state) => DensityMatrix::from_state_vector(state),
+            None => DensityMatrix::new(circuit.num_qubits()),
+        };
+        for step in noise.steps(circuit.num_qubits(), circuit.operations()) {
+            match step {
+                NoisyStep::Gate(kernel) => dm.apply_unitary(&kernel.matrix, &kernel.targets),
+                NoisyStep::Channel(channel, targets) => dm.apply_channel(channel, &targets),
+            }
+        }
+        dm
+    }
+
+    /// `Σ K ρ K†` over the channel's Kraus operators, on any number of
+    /// `targets` matching the channel's width.
+    pub fn apply_channel(&mut self, channel: &NoiseChannel, targets: &[usize]) {
+        assert_eq!(
+            channel.num_qubits,
+            targets.len(),
+            "channel {} acts on {} qubits",
+            channel.name,
+            channel.num_qubits
+        );
+        let mut data = vec![complex!(0.0, 0.0); self.dim * self.dim];
+        for kraus in &channel.operators {
+            let mut branch = self.clone();
+            branch.apply_unitary(&kraus.matrix, targets);
+            for (total, x) in data.iter_mut().zip(branch.data) {
+                *total += x;
+            }
+        }
+        self.data = data;
+    }
+
     pub fn apply_noise_channel(&mut self, channel: &NoiseChannel, target: usize) {
         if channel.num_qubits != 1 {
             panic!("Only single-qubit noise channels are currently supported");
@@ -492,8 +670,7 @@ impl DensityMatrix {
                             let k_dag_elem = k.data[j_target * 2 + kj].get_conjugate();
                             let rho_elem = self.get(src_i, src_j);
 
-                            new_data[i * self.dim + j] =
-                                new_data[i * self.dim + j] + k_elem * rho_elem * k_dag_elem;
+                            new_data[i * self.dim + j] += k_elem * rho_elem * k_dag_elem;
                         }
                     }
                 }
@@ -521,17 +698,113 @@ impl DensityMatrix {
 
         for i in 0..self.dim {
             for j in 0..self.dim {
-                sum = sum + state[i].get_conjugate() * self.get(i, j) * state[j];
+                sum += state[i].get_conjugate() * self.get(i, j) * state[j];
             }
         }
 
         sum.real
     }
+
+    /// The Uhlmann fidelity `(tr √(√ρ σ √ρ))²` between `self` (`ρ`) and
+    /// `other` (`σ`): 1 for equal states, 0 for orthogonal supports, and
+    /// `fidelity_with_pure_state` when either is pure.
+    pub fn fidelity(&self, other: &DensityMatrix) -> f64 {
+        assert_eq!(
+            self.num_qubits, other.num_qubits,
+            "density matrices on {} and {} qubits",
+            self.num_qubits, other.num_qubits
+        );
+        let dim = self.dim;
+        let root = Matrix::new(
+            dim,
+            dim,
+            hermitian_function(&self.data, dim, |x| x.max(0.0).sqrt()),
+        );
+        let sigma = Matrix::new(dim, dim, other.data.clone());
+        let product = root.dot(&sigma).unwrap().dot(&root).unwrap();
+        let root_trace: f64 = hermitian_eigenvalues(&product.data, dim)
+            .into_iter()
+            .map(|x| x.max(0.0).sqrt())
+            .sum();
+        root_trace * root_trace
+    }
+
+    /// `(⟨X⟩, ⟨Y⟩, ⟨Z⟩)` of `qubit`, the point of its reduced state
+    /// `(I + xX + yY + zZ)/2` in the Bloch ball.
+    pub fn bloch_vector(&self, qubit: usize) -> (f64, f64, f64) {
+        assert!(
+            qubit < self.num_qubits,
+            "qubit {} out of range for {} qubits",
+            qubit,
+            self.num_qubits
+        );
+        let others: Vec<usize> = (0..self.num_qubits).filter(|&q| q != qubit).collect();
+        let rho = self.partial_trace(&others);
+        let coherence = rho.get(0, 1);
+        (
+            2.0 * coherence.real,
+            -2.0 * coherence.imaginary,
+            rho.get(0, 0).real - rho.get(1, 1).real,
+        )
+    }
+
+    /// The state of the remaining qubits, in ascending order, once the
+    /// `traced` ones are summed out: `ρ_A = Σ_e ⟨e|ρ|e⟩` over the basis
+    /// states `e` of the traced qubits.
+    pub fn partial_trace(&self, traced: &[usize]) -> DensityMatrix {
+        let kept: Vec<usize> = (0..self.num_qubits)
+            .filter(|q| !traced.contains(q))
+            .collect();
+        let environment = subsystem_offsets(self.num_qubits, traced);
+        let offsets = subsystem_offsets(self.num_qubits, &kept);
+        let dim = offsets.len();
+        let mut data = vec![complex!(0.0, 0.0); dim * dim];
+        for (i, &row) in offsets.iter().enumerate() {
+            for (j, &col) in offsets.iter().enumerate() {
+                data[i * dim + j] = environment.iter().fold(complex!(0.0, 0.0), |sum, &e| {
+                    sum + self.get(row | e, col | e)
+                });
+            }
+        }
+        DensityMatrix {
+            data,
+            dim,
+            num_qubits: kept.len(),
+        }
+    }
+}
+
+/// For each basis state `k` of `qubits`, the index of the full `num_qubits`
+/// register with those qubits set as in `k` (first qubit most significant)
+/// and every other qubit 0. OR-ing it with the offset of the complementary
+/// qubits gives a full index.
+pub(crate) fn subsystem_offsets(num_qubits: usize, qubits: &[usize]) -> Vec<usize> {
+    for (i, &q) in qubits.iter().enumerate() {
+        assert!(
+            q < num_qubits,
+            "qubit {} out of range for {} qubits",
+            q,
+            num_qubits
+        );
+        assert!(!qubits[..i].contains(&q), "qubit {} listed twice", q);
+    }
+    let k = qubits.len();
+    (0..1usize << k)
+        .map(|value| {
+            qubits.iter().enumerate().fold(0, |index, (idx, &q)| {
+                index | (((value >> (k - 1 - idx)) & 1) << (num_qubits - 1 - q))
+            })
+        })
+        .collect()
 }
 
 impl std::fmt::Display for DensityMatrix {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "DensityMatrix ({} qubits, {}×{}):", self.num_qubits, self.dim, self.dim)?;
+        writeln!(
+            f,
+            "DensityMatrix ({} qubits, {}×{}):",
+            self.num_qubits, self.dim, self.dim
+        )?;
         writeln!(f, "  Trace: {:.6}", self.trace().real)?;
         writeln!(f, "  Purity: {:.6}", self.purity())?;
         writeln!(f, "  Pure: {}", self.is_pure(1e-10))?;
@@ -557,4 +830,3 @@ impl std::fmt::Debug for DensityMatrix {
         Ok(())
     }
 }
-
@@ -0,0 +1,265 @@
+use super::{Hamiltonian, Pauli};
+use crate::{complex, Complex, Matrix};
+use core::fmt;
+use std::collections::HashMap;
+use std::ops::{Add, Mul, Neg, Sub};
+
+/// A weighted sum of Pauli strings, `Σ cₖ Pₖ`, kept as its terms so that
+/// observables on many qubits stay small. Each string holds one `Pauli`
+/// per qubit, qubit 0 first, as in its label: `"XZI"` is X on qubit 0 and
+/// Z on qubit 1.
+///
+/// Sums and tensor products keep every term; `simplify` merges repeated
+/// strings.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SparsePauliOp {
+    num_qubits: usize,
+    terms: Vec<(Complex<f64>, Vec<Pauli>)>,
+}
+
+impl SparsePauliOp {
+    /// The zero operator, to which `add_term` adds Pauli strings.
+    pub fn new(num_qubits: usize) -> Self {
+        SparsePauliOp {
+            num_qubits,
+            terms: Vec::new(),
+        }
+    }
+
+    /// The sum of `coefficient · label` terms, e.g.
+    /// `from_labels(&[("ZZ", 1.0), ("XI", 0.5)])`. Fails for labels of
+    /// different lengths or with letters other than `I`, `X`, `Y` and `Z`.
+    pub fn from_labels(terms: &[(&str, f64)]) -> Result<Self, String> {
+        let num_qubits = terms.first().map_or(0, |(label, _)| label.chars().count());
+        let mut op = SparsePauliOp::new(num_qubits);
+        for &(label, coefficient) in terms {
+            let string = label
+                .chars()
+                .map(|letter| match letter {
+                    'I' => Ok(Pauli::I),
+                    'X' => Ok(Pauli::X),
+                    'Y' => Ok(Pauli::Y),
+                    'Z' => Ok(Pauli::Z),
+                    _ => Err(format!("'{}' in \"{}\" is not a Pauli", letter, label)),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            if string.len() != num_qubits {
+                return Err(format!(
+                    "\"{}\" has {} qubits, not {}",
+                    label,
+                    string.len(),
+                    num_qubits
+                ));
+            }
+            op.terms.push((complex!(coefficient, 0.0), string));
+        }
+        Ok(op)
+    }
+
+    /// Adds `coefficient` times the product of `factors`, given as
+    /// `(qubit, Pauli)` pairs as for `Hamiltonian::add_term`.
+    pub fn add_term(
+        &mut self,
+        coefficient: impl Into<Complex<f64>>,
+        factors: &[(usize, Pauli)],
+    ) -> &mut Self {
+        let n = self.num_qubits;
+        let mut string = vec![Pauli::I; n];
+        for &(qubit, pauli) in factors {
+            assert!(qubit < n, "qubit {} out of range for {} qubits", qubit, n);
+            assert!(
+                string[qubit] == Pauli::I,
+                "qubit {} appears twice in a term",
+                qubit
+            );
+            string[qubit] = pauli;
+        }
+        self.terms.push((coefficient.into(), string));
+        self
+    }
+
+    pub fn num_qubits(&self) -> usize {
+        self.num_qubits
+    }
+
+    /// `(coefficient, string)` pairs, in the order they were added.
+    pub fn terms(&self) -> &[(Complex<f64>, Vec<Pauli>)] {
+        &self.terms
+    }
+
+    /// `self ⊗ other`, on `self`'s qubits followed by `other`'s.
+    pub fn tensor(&self, other: &SparsePauliOp) -> SparsePauliOp {
+        let mut terms = Vec::with_capacity(self.terms.len() * other.terms.len());
+        for (a, left) in &self.terms {
+            for (b, right) in &other.terms {
+                let string = left.iter().chain(right).copied().collect();
+                terms.push((*a * *b, string));
+            }
+        }
+        SparsePauliOp {
+            num_qubits: self.num_qubits + other.num_qubits,
+            terms,
+        }
+    }
+
+    /// Merges terms with the same string, in order of first appearance, and
+    /// drops those whose coefficient is within `tolerance` of zero.
+    pub fn simplify(&self, tolerance: f64) -> SparsePauliOp {
+        let mut index: HashMap<Vec<u8>, usize> = HashMap::new();
+        let mut terms: Vec<(Complex<f64>, Vec<Pauli>)> = Vec::new();
+        for (coefficient, string) in &self.terms {
+            let key = string.iter().map(|&pauli| pauli as u8).collect();
+            match index.get(&key) {
+                Some(&i) => terms[i].0 += *coefficient,
+                None => {
+                    index.insert(key, terms.len());
+                    terms.push((*coefficient, string.clone()));
+                }
+            }
+        }
+        terms.retain(|(coefficient, _)| coefficient.abs() > tolerance);
+        SparsePauliOp {
+            num_qubits: self.num_qubits,
+            terms,
+        }
+    }
+
+    /// The `2ⁿ × 2ⁿ` matrix, for small systems.
+    pub fn to_matrix(&self) -> Matrix<Complex<f64>> {
+        let dim = 1 << self.num_qubits;
+        let mut matrix = Matrix::new(dim, dim, vec![complex!(0.0, 0.0); dim * dim]);
+        for (coefficient, string) in &self.terms {
+            let (x, z, y) = masks(string);
+            // P|j⟩ = i^y (-1)^|j & z| |j ⊕ x⟩.
+            let phase = *coefficient
+                * match y % 4 {
+                    0 => complex!(1.0, 0.0),
+                    1 => complex!(0.0, 1.0),
+                    2 => complex!(-1.0, 0.0),
+                    _ => complex!(0.0, -1.0),
+                };
+            for col in 0..dim {
+                let term = if (col & z).count_ones() % 2 == 0 {
+                    phase
+                } else {
+                    -phase
+                };
+                matrix.set(col ^ x, col, matrix.get(col ^ x, col) + term);
+            }
+        }
+        matrix
+    }
+
+    /// The same operator as a `Hamiltonian`, for time evolution and
+    /// gradients. Fails unless every coefficient is real, i.e. unless the
+    /// operator is Hermitian term by term.
+    pub fn to_hamiltonian(&self) -> Result<Hamiltonian, String> {
+        let mut hamiltonian = Hamiltonian::new(self.num_qubits);
+        for (coefficient, string) in &self.terms {
+            if coefficient.imaginary != 0.0 {
+                return Err(format!(
+                    "{} has a complex coefficient {}",
+                    label(string),
+                    coefficient
+                ));
+            }
+            let factors: Vec<(usize, Pauli)> = string
+                .iter()
+                .enumerate()
+                .filter(|&(_, &pauli)| pauli != Pauli::I)
+                .map(|(qubit, &pauli)| (qubit, pauli))
+                .collect();
+            hamiltonian.add_term(coefficient.real, &factors);
+        }
+        Ok(hamiltonian)
+    }
+}
+
+/// `(X mask, Z mask, number of Ys)` of a Pauli string, qubit `q` at bit
+/// `n - 1 - q`, as in `Hamiltonian`.
+fn masks(string: &[Pauli]) -> (usize, usize, usize) {
+    let n = string.len();
+    let (mut x, mut z, mut y) = (0, 0, 0);
+    for (qubit, pauli) in string.iter().enumerate() {
+        let bit = 1 << (n - 1 - qubit);
+        match pauli {
+            Pauli::I => {}
+            Pauli::X => x |= bit,
+            Pauli::Z => z |= bit,
+            Pauli::Y => {
+                x |= bit;
+                z |= bit;
+                y += 1;
+            }
+        }
+    }
+    (x, z, y)
+}
+
+fn label(string: &[Pauli]) -> String {
+    string.iter().map(|pauli| format!("{:?}", pauli)).collect()
+}
+
+impl Add for SparsePauliOp {
+    type Output = SparsePauliOp;
+
+    fn add(mut self, other: SparsePauliOp) -> SparsePauliOp {
+        assert_eq!(
+            self.num_qubits, other.num_qubits,
+            "cannot add operators on {} and {} qubits",
+            self.num_qubits, other.num_qubits
+        );
+        self.terms.extend(other.terms);
+        self
+    }
+}
+
+impl Sub for SparsePauliOp {
+    type Output = SparsePauliOp;
+
+    fn sub(self, other: SparsePauliOp) -> SparsePauliOp {
+        self + -other
+    }
+}
+
+impl Neg for SparsePauliOp {
+    type Output = SparsePauliOp;
+
+    fn neg(self) -> SparsePauliOp {
+        self * -1.0
+    }
+}
+
+impl Mul<Complex<f64>> for SparsePauliOp {
+    type Output = SparsePauliOp;
+
+    fn mul(mut self, factor: Complex<f64>) -> SparsePauliOp {
+        for (coefficient, _) in &mut self.terms {
+            *coefficient *= factor;
+        }
+        self
+    }
+}
+
+impl Mul<f64> for SparsePauliOp {
+    type Output = SparsePauliOp;
+
+    fn mul(self, factor: f64) -> SparsePauliOp {
+        self * complex!(factor, 0.0)
+    }
+}
+
+impl fmt::Display for SparsePauliOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.terms.is_empty() {
+            return write!(f, "0");
+        }
+        for (i, (coefficient, string)) in self.terms.iter().enumerate() {
+            if i > 0 {
+                write!(f, " + ")?;
+            }
+            write!(f, "({})·{}", coefficient, label(string))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,122 @@
+use core::fmt;
+use std::ops::{Add, Mul, Neg, Sub};
+
+/// A named angle left open when the circuit is built and given a value by
+/// `QuantumCircuit::bind_parameters`, so a variational circuit is built once
+/// and rebound every iteration. Stands for `scale · name + offset`, which
+/// covers the scaled and shifted uses of one angle within a circuit, e.g.
+/// `gamma * 2.0` in a cost layer.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Parameter {
+    name: String,
+    scale: f64,
+    offset: f64,
+}
+
+impl Parameter {
+    pub fn new(name: &str) -> Self {
+        Parameter {
+            name: name.to_string(),
+            scale: 1.0,
+            offset: 0.0,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// `(scale, offset)`: the parameter stands for `scale · value + offset`.
+    pub fn coefficients(&self) -> (f64, f64) {
+        (self.scale, self.offset)
+    }
+
+    /// The angle once the parameter's name is bound to `value`.
+    pub fn resolve(&self, value: f64) -> f64 {
+        self.scale * value + self.offset
+    }
+}
+
+impl Neg for Parameter {
+    type Output = Parameter;
+
+    fn neg(self) -> Parameter {
+        self * -1.0
+    }
+}
+
+impl Mul<f64> for Parameter {
+    type Output = Parameter;
+
+    fn mul(self, factor: f64) -> Parameter {
+        Parameter {
+            scale: self.scale * factor,
+            offset: self.offset * factor,
+            ..self
+        }
+    }
+}
+
+impl Add<f64> for Parameter {
+    type Output = Parameter;
+
+    fn add(self, shift: f64) -> Parameter {
+        Parameter {
+            offset: self.offset + shift,
+            ..self
+        }
+    }
+}
+
+impl Sub<f64> for Parameter {
+    type Output = Parameter;
+
+    fn sub(self, shift: f64) -> Parameter {
+        self + -shift
+    }
+}
+
+impl fmt::Display for Parameter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.scale == 1.0 {
+            write!(f, "{}", self.name)?;
+        } else if self.scale == -1.0 {
+            write!(f, "-{}", self.name)?;
+        } else {
+            write!(f, "{}·{}", self.scale, self.name)?;
+        }
+        if self.offset > 0.0 {
+            write!(f, " + {}", self.offset)?;
+        } else if self.offset < 0.0 {
+            write!(f, " - {}", -self.offset)?;
+        }
+        Ok(())
+    }
+}
+
+/// An angle argument of a circuit builder method: a number, or a
+/// `Parameter` bound later.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Angle {
+    Value(f64),
+    Symbol(Parameter),
+}
+
+impl From<f64> for Angle {
+    fn from(value: f64) -> Self {
+        Angle::Value(value)
+    }
+}
+
+impl From<Parameter> for Angle {
+    fn from(parameter: Parameter) -> Self {
+        Angle::Symbol(parameter)
+    }
+}
+
+impl From<&Parameter> for Angle {
+    fn from(parameter: &Parameter) -> Self {
+        Angle::Symbol(parameter.clone())
+    }
+}
@@ -0,0 +1,323 @@
+use super::circuit::defer_conditionals;
+use super::counts::entropy_seed;
+use super::runtime::apply_kernel_in_place;
+use super::{Counts, GateOp, QuantumCircuit, QuantumState, Runtime, SimulatorBackend};
+use crate::maths::parallel::*;
+use crate::{complex, Complex, Vector};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::BTreeMap;
+
+/// A qubit counts as unentangled from the rest of its group when the
+/// determinant of its reduced density matrix is below this.
+const SEPARABLE_TOLERANCE: f64 = 1e-12;
+
+/// Qubits sharing a state vector, with qubit `qubits[0]` as its most
+/// significant bit.
+#[derive(Clone, Debug)]
+struct Group {
+    qubits: Vec<usize>,
+    amplitudes: Vec<Complex<f64>>,
+}
+
+impl Group {
+    fn zero(qubit: usize) -> Self {
+        Group {
+            qubits: vec![qubit],
+            amplitudes: vec![complex!(1.0, 0.0), complex!(0.0, 0.0)],
+        }
+    }
+
+    /// Bit of `qubit` in this group's indices.
+    fn mask(&self, qubit: usize) -> usize {
+        let position = self.qubits.iter().position(|&q| q == qubit).unwrap();
+        1 << (self.qubits.len() - 1 - position)
+    }
+
+    fn tensor(self, other: Group) -> Group {
+        let mut amplitudes = Vec::with_capacity(self.amplitudes.len() * other.amplitudes.len());
+        for &a in &self.amplitudes {
+            for &b in &other.amplitudes {
+                amplitudes.push(a * b);
+            }
+        }
+        let mut qubits = self.qubits;
+        qubits.extend(other.qubits);
+        Group { qubits, amplitudes }
+    }
+
+    /// Factors `qubit` out as a group of its own if it is in a product
+    /// state with the rest.
+    fn split_off(&mut self, qubit: usize) -> Option<Group> {
+        if self.qubits.len() == 1 {
+            return None;
+        }
+        let bit = self.mask(qubit);
+        let pairs = || {
+            (0..self.amplitudes.len())
+                .filter(move |i| i & bit == 0)
+                .map(|i| (self.amplitudes[i], self.amplitudes[i | bit]))
+        };
+
+        // Reduced density matrix [[p0, c], [c*, p1]]: pure iff p0·p1 = |c|².
+        let (mut p0, mut p1, mut c) = (0.0, 0.0, complex!(0.0, 0.0));
+        for (x0, x1) in pairs() {
+            p0 += x0.norm2();
+            p1 += x1.norm2();
+            c += x0 * x1.get_conjugate();
+        }
+        if p0 * p1 - c.norm2() > SEPARABLE_TOLERANCE {
+            return None;
+        }
+
+        // ψ = φ ⊗ χ: take χ from the larger half, then φ = ⟨χ|ψ⟩ per value.
+        let use_zero = p0 >= p1;
+        let norm = complex!(1.0 / p0.max(p1).sqrt(), 0.0);
+        let rest: Vec<Complex<f64>> = pairs()
+            .map(|(x0, x1)| (if use_zero { x0 } else { x1 }) * norm)
+            .collect();
+        let overlap = |pick_one: bool| {
+            pairs()
+                .zip(&rest)
+                .map(|((x0, x1), r)| (if pick_one { x1 } else { x0 }) * r.get_conjugate())
+                .fold(complex!(0.0, 0.0), |sum, x| sum + x)
+        };
+        let single = Group {
+            qubits: vec![qubit],
+            amplitudes: vec![overlap(false), overlap(true)],
+        };
+        self.qubits.retain(|&q| q != qubit);
+        self.amplitudes = rest;
+        Some(single)
+    }
+
+    /// Index into this group's amplitudes of the global basis state `bits`.
+    fn local_index(&self, bits: impl Fn(usize) -> bool) -> usize {
+        self.qubits
+            .iter()
+            .fold(0, |index, &q| (index << 1) | bits(q) as usize)
+    }
+}
+
+/// State-vector simulation that keeps unentangled qubits apart: every qubit
+/// starts in a group of its own, and a gate only merges the groups of the
+/// qubits it acts on. After a multi-qubit gate each target is checked and
+/// split back out if it ended up in a product state.
+///
+/// Memory and time follow the largest group rather than the register, so
+/// circuits whose entanglement stays local or comes late run in a fraction
+/// of a full state vector. Amplitudes and sampling never build the full
+/// state; `to_state` does. Bitstrings have qubit 0 first.
+#[derive(Clone, Debug)]
+pub struct ProductState {
+    groups: Vec<Group>,
+    /// Index into `groups` of each qubit's group.
+    owner: Vec<usize>,
+}
+
+impl ProductState {
+    /// `|0…0⟩` on `num_qubits` qubits.
+    pub fn new(num_qubits: usize) -> Self {
+        ProductState {
+            groups: (0..num_qubits).map(Group::zero).collect(),
+            owner: (0..num_qubits).collect(),
+        }
+    }
+
+    /// The final state of `circuit`. Measurements, barriers and snapshots
+    /// are skipped.
+    pub fn from_circuit(circuit: &QuantumCircuit) -> Self {
+        let mut state = ProductState::new(circuit.num_qubits());
+        for op in defer_conditionals(circuit.operations()).iter() {
+            state.apply(op);
+        }
+        state
+    }
+
+    /// Applies `op`, merging the groups of its qubits first. Measurements,
+    /// barriers and snapshots are skipped.
+    pub fn apply(&mut self, op: &GateOp) {
+        let Some(kernel) = Runtime::op_to_kernel(op) else {
+            return;
+        };
+        let mut owners: Vec<usize> = kernel.targets.iter().map(|&q| self.owner[q]).collect();
+        owners.sort_unstable();
+        owners.dedup();
+        // Removing the highest index first keeps the others valid.
+        let mut group = owners
+            .into_iter()
+            .rev()
+            .map(|g| self.take_group(g))
+            .reduce(|merged, next| next.tensor(merged))
+            .unwrap();
+
+        let mut local = kernel.clone();
+        let width = group.qubits.len();
+        local.targets = kernel
+            .targets
+            .iter()
+            .map(|&q| width - 1 - group.mask(q).trailing_zeros() as usize)
+            .collect();
+        apply_kernel_in_place(&mut group.amplitudes, &local, width);
+
+        if kernel.targets.len() > 1 {
+            for &q in &kernel.targets {
+                if let Some(single) = group.split_off(q) {
+                    self.push_group(single);
+                }
+            }
+        }
+        self.push_group(group);
+    }
+
+    pub fn num_qubits(&self) -> usize {
+        self.owner.len()
+    }
+
+    /// The qubits of each group of mutually entangled qubits, in no
+    /// particular order.
+    pub fn groups(&self) -> Vec<Vec<usize>> {
+        self.groups
+            .iter()
+            .map(|g| {
+                let mut qubits = g.qubits.clone();
+                qubits.sort_unstable();
+                qubits
+            })
+            .collect()
+    }
+
+    /// Qubits in the largest group, which sets the memory in use.
+    pub fn largest_group(&self) -> usize {
+        self.groups
+            .iter()
+            .map(|g| g.qubits.len())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// `⟨bits|ψ⟩`, as a product over the groups.
+    pub fn amplitude(&self, bits: &[bool]) -> Complex<f64> {
+        assert_eq!(
+            bits.len(),
+            self.num_qubits(),
+            "expected {} bits",
+            self.num_qubits()
+        );
+        self.groups
+            .iter()
+            .map(|g| g.amplitudes[g.local_index(|q| bits[q])])
+            .fold(complex!(1.0, 0.0), |product, a| product * a)
+    }
+
+    pub fn probability(&self, bits: &[bool]) -> f64 {
+        self.amplitude(bits).norm2()
+    }
+
+    /// The full state vector, for registers small enough to hold one.
+    pub fn to_state(&self) -> QuantumState {
+        let n = self.num_qubits();
+        assert!(
+            n < usize::BITS as usize,
+            "{} qubits is too many for a state vector",
+            n
+        );
+        let amplitudes: Vec<Complex<f64>> = (0..1usize << n)
+            .into_par_iter()
+            .map(|index| {
+                let bit = |q: usize| (index >> (n - 1 - q)) & 1 == 1;
+                self.groups
+                    .iter()
+                    .map(|g| g.amplitudes[g.local_index(bit)])
+                    .fold(complex!(1.0, 0.0), |product, a| product * a)
+            })
+            .collect();
+        QuantumState::new(amplitudes)
+    }
+
+    /// Draws `shots` samples of `circuit`, each group independently of the
+    /// others, so no full state vector is built. Outcomes follow the bit
+    /// order of `Counts`.
+    pub fn sample(circuit: &QuantumCircuit, shots: usize, seed: Option<u64>) -> Counts {
+        let state = ProductState::from_circuit(circuit);
+        let n = state.num_qubits();
+        let mut rng = StdRng::seed_from_u64(seed.unwrap_or_else(entropy_seed));
+        let cumulative: Vec<Vec<f64>> = state
+            .groups
+            .iter()
+            .map(|g| {
+                g.amplitudes
+                    .iter()
+                    .scan(0.0, |total, a| {
+                        *total += a.norm2();
+                        Some(*total)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let measurements = circuit.measurements();
+        let mut outcomes: BTreeMap<String, usize> = BTreeMap::new();
+        let mut bits = vec![false; n];
+        for _ in 0..shots {
+            for (group, cumulative) in state.groups.iter().zip(&cumulative) {
+                let total = cumulative[cumulative.len() - 1];
+                let r = rng.random::<f64>() * total;
+                let index = cumulative
+                    .partition_point(|&c| c <= r)
+                    .min(cumulative.len() - 1);
+                let width = group.qubits.len();
+                for (position, &q) in group.qubits.iter().enumerate() {
+                    bits[q] = (index >> (width - 1 - position)) & 1 == 1;
+                }
+            }
+            let key: String = if measurements.is_empty() {
+                bits.iter().map(|&b| if b { '1' } else { '0' }).collect()
+            } else {
+                let mut key = vec!['0'; circuit.num_classical()];
+                for &(q, c) in &measurements {
+                    key[c] = if bits[q] { '1' } else { '0' };
+                }
+                key.into_iter().collect()
+            };
+            *outcomes.entry(key).or_insert(0) += 1;
+        }
+        Counts::from_outcomes(outcomes)
+    }
+
+    /// Removes group `index`, moving the last group into its slot.
+    fn take_group(&mut self, index: usize) -> Group {
+        let group = self.groups.swap_remove(index);
+        if let Some(moved) = self.groups.get(index) {
+            for &q in &moved.qubits {
+                self.owner[q] = index;
+            }
+        }
+        group
+    }
+
+    fn push_group(&mut self, group: Group) {
+        for &q in &group.qubits {
+            self.owner[q] = self.groups.len();
+        }
+        self.groups.push(group);
+    }
+}
+
+impl SimulatorBackend for ProductState {
+    fn name(&self) -> String {
+        "ProductState".to_string()
+    }
+
+    fn init(&mut self, num_qubits: usize) {
+        *self = ProductState::new(num_qubits);
+    }
+
+    fn apply(&mut self, op: &GateOp) {
+        ProductState::apply(self, op);
+    }
+
+    fn finalize(&mut self) -> QuantumState {
+        self.to_state()
+    }
+}
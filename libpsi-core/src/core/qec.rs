@@ -0,0 +1,318 @@
+use super::{Pauli, QuantumCircuit};
+
+/// A CSS stabilizer code encoding one logical qubit: its X- and Z-type
+/// stabilizer generators and logical operators, each given as the data
+/// qubits it acts on.
+///
+/// The builders append to a circuit whose data qubits are passed as a slice,
+/// so a code can sit anywhere in a larger register. Since the runtimes defer
+/// measurement to the end, every syndrome round needs fresh ancillas.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CssCode {
+    name: String,
+    num_data: usize,
+    x_stabilizers: Vec<Vec<usize>>,
+    z_stabilizers: Vec<Vec<usize>>,
+    logical_x: Vec<usize>,
+    logical_z: Vec<usize>,
+}
+
+impl CssCode {
+    /// The bit-flip repetition code: `Z_i Z_{i+1}` stabilizers, logical
+    /// `X` on every qubit and logical `Z` on qubit 0. `distance` 3 is the
+    /// usual 3-qubit code.
+    pub fn repetition(distance: usize) -> Self {
+        assert!(distance >= 2, "repetition code needs at least 2 qubits");
+        CssCode {
+            name: format!("repetition-{}", distance),
+            num_data: distance,
+            x_stabilizers: Vec::new(),
+            z_stabilizers: (0..distance - 1).map(|i| vec![i, i + 1]).collect(),
+            logical_x: (0..distance).collect(),
+            logical_z: vec![0],
+        }
+    }
+
+    /// The [[7,1,3]] Steane code. Both stabilizer types come from the
+    /// Hamming code's parity checks: qubit `i` is in check `b` when bit `b`
+    /// of `i + 1` is set.
+    pub fn steane() -> Self {
+        let checks: Vec<Vec<usize>> = (0..3)
+            .rev()
+            .map(|b| (0..7).filter(|i| ((i + 1) >> b) & 1 == 1).collect())
+            .collect();
+        CssCode {
+            name: "steane".to_string(),
+            num_data: 7,
+            x_stabilizers: checks.clone(),
+            z_stabilizers: checks,
+            logical_x: vec![0, 1, 2],
+            logical_z: vec![0, 1, 2],
+        }
+    }
+
+    /// The rotated surface code on a `distance × distance` patch, data qubit
+    /// `(row, col)` at index `row * distance + col`. Weight-4 plaquettes
+    /// alternate X and Z in a checkerboard, with weight-2 X checks on the
+    /// top and bottom edges and Z checks on the left and right. Logical `X`
+    /// runs down the first column, logical `Z` along the first row.
+    pub fn surface(distance: usize) -> Self {
+        assert!(
+            distance >= 3 && distance % 2 == 1,
+            "surface code distance must be odd and at least 3"
+        );
+        let d = distance as isize;
+        let mut x_stabilizers = Vec::new();
+        let mut z_stabilizers = Vec::new();
+        // Face (r, c) touches data qubits (r..=r+1, c..=c+1) inside the patch.
+        for r in -1..d {
+            for c in -1..d {
+                let is_x = (r + c).rem_euclid(2) == 0;
+                let on_row_edge = r == -1 || r == d - 1;
+                let on_col_edge = c == -1 || c == d - 1;
+                let keep = match (on_row_edge, on_col_edge) {
+                    (false, false) => true,
+                    (true, false) => is_x,
+                    (false, true) => !is_x,
+                    (true, true) => false,
+                };
+                if !keep {
+                    continue;
+                }
+                let support: Vec<usize> = [(r, c), (r, c + 1), (r + 1, c), (r + 1, c + 1)]
+                    .into_iter()
+                    .filter(|&(i, j)| (0..d).contains(&i) && (0..d).contains(&j))
+                    .map(|(i, j)| (i * d + j) as usize)
+                    .collect();
+                if is_x {
+                    x_stabilizers.push(support);
+                } else {
+                    z_stabilizers.push(support);
+                }
+            }
+        }
+        CssCode {
+            name: format!("surface-{}", distance),
+            num_data: distance * distance,
+            x_stabilizers,
+            z_stabilizers,
+            logical_x: (0..distance).map(|r| r * distance).collect(),
+            logical_z: (0..distance).collect(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn num_data_qubits(&self) -> usize {
+        self.num_data
+    }
+
+    /// Ancillas (and classical bits) used by one syndrome round.
+    pub fn num_stabilizers(&self) -> usize {
+        self.x_stabilizers.len() + self.z_stabilizers.len()
+    }
+
+    pub fn x_stabilizers(&self) -> &[Vec<usize>] {
+        &self.x_stabilizers
+    }
+
+    pub fn z_stabilizers(&self) -> &[Vec<usize>] {
+        &self.z_stabilizers
+    }
+
+    pub fn logical_x(&self) -> &[usize] {
+        &self.logical_x
+    }
+
+    pub fn logical_z(&self) -> &[usize] {
+        &self.logical_z
+    }
+
+    /// The data qubit whose state `encode` spreads over the code.
+    pub fn input_qubit(&self) -> usize {
+        self.logical_x[0]
+    }
+
+    /// Every stabilizer generator as Pauli factors on `data`, X-type first,
+    /// in the order `measure_syndrome` assigns ancillas. Ready for
+    /// `SnapshotKind::Expectation`.
+    pub fn stabilizers(&self, data: &[usize]) -> Vec<Vec<(usize, Pauli)>> {
+        let x = self.x_stabilizers.iter().map(|s| paulis(s, data, Pauli::X));
+        let z = self.z_stabilizers.iter().map(|s| paulis(s, data, Pauli::Z));
+        x.chain(z).collect()
+    }
+
+    pub fn logical_x_paulis(&self, data: &[usize]) -> Vec<(usize, Pauli)> {
+        paulis(&self.logical_x, data, Pauli::X)
+    }
+
+    pub fn logical_z_paulis(&self, data: &[usize]) -> Vec<(usize, Pauli)> {
+        paulis(&self.logical_z, data, Pauli::Z)
+    }
+
+    /// Appends the encoder: `data[input_qubit()]` holds the state to encode
+    /// and the other data qubits start in |0⟩.
+    ///
+    /// The input is copied onto the logical-X support, then each X-type
+    /// generator is prepared from its own pivot qubit in |+⟩. Pivots come
+    /// from row-reducing the X generators over the qubits outside the
+    /// logical-X support, so no fan-out disturbs another generator.
+    pub fn encode(&self, circuit: &mut QuantumCircuit, data: &[usize]) {
+        self.check_data(data);
+        let input = self.input_qubit();
+        for &q in &self.logical_x[1..] {
+            circuit.cnot(data[input], data[q]);
+        }
+
+        let generators = self.x_pivots();
+        for &(pivot, _) in &generators {
+            circuit.h(data[pivot]);
+        }
+        for (pivot, support) in &generators {
+            for &q in support.iter().filter(|&&q| q != *pivot) {
+                circuit.cnot(data[*pivot], data[q]);
+            }
+        }
+    }
+
+    /// Appends one round of stabilizer measurements: generator `i` (in
+    /// `stabilizers` order) is read out through `ancillas[i]`, which must
+    /// be in |0⟩, into `classical[i]`.
+    pub fn measure_syndrome(
+        &self,
+        circuit: &mut QuantumCircuit,
+        data: &[usize],
+        ancillas: &[usize],
+        classical: &[usize],
+    ) {
+        self.check_data(data);
+        let m = self.num_stabilizers();
+        assert!(
+            ancillas.len() == m && classical.len() == m,
+            "{} needs {} ancillas and classical bits per round",
+            self.name,
+            m
+        );
+        let x_count = self.x_stabilizers.len();
+        for (i, support) in self.x_stabilizers.iter().enumerate() {
+            circuit.h(ancillas[i]);
+            for &q in support {
+                circuit.cnot(ancillas[i], data[q]);
+            }
+            circuit.h(ancillas[i]);
+        }
+        for (i, support) in self.z_stabilizers.iter().enumerate() {
+            for &q in support {
+                circuit.cnot(data[q], ancillas[x_count + i]);
+            }
+        }
+        for i in 0..m {
+            circuit.measure(ancillas[i], classical[i]);
+        }
+    }
+
+    pub fn apply_logical_x(&self, circuit: &mut QuantumCircuit, data: &[usize]) {
+        self.check_data(data);
+        for &q in &self.logical_x {
+            circuit.x(data[q]);
+        }
+    }
+
+    pub fn apply_logical_z(&self, circuit: &mut QuantumCircuit, data: &[usize]) {
+        self.check_data(data);
+        for &q in &self.logical_z {
+            circuit.z(data[q]);
+        }
+    }
+
+    /// A memory experiment: encode |0⟩ on data qubits `0..n`, run `rounds`
+    /// syndrome rounds on fresh ancillas, then measure the data. Round `r`
+    /// writes classical bits `r * m..(r + 1) * m` for `m` stabilizers; the
+    /// data lands in the last `n` bits.
+    pub fn memory_circuit(&self, rounds: usize) -> QuantumCircuit {
+        self.memory_circuit_with(rounds, |_, _, _| {})
+    }
+
+    /// Like `memory_circuit`, calling `before_round(circuit, data, round)`
+    /// ahead of each syndrome round and once more (with `round == rounds`)
+    /// before the data readout, e.g. to insert errors.
+    pub fn memory_circuit_with(
+        &self,
+        rounds: usize,
+        mut before_round: impl FnMut(&mut QuantumCircuit, &[usize], usize),
+    ) -> QuantumCircuit {
+        let n = self.num_data;
+        let m = self.num_stabilizers();
+        let mut circuit = QuantumCircuit::with_classical(n + rounds * m, rounds * m + n);
+        let data: Vec<usize> = (0..n).collect();
+        circuit.label_qubits("d", &data);
+        self.encode(&mut circuit, &data);
+        for round in 0..rounds {
+            let ancillas: Vec<usize> = (n + round * m..n + (round + 1) * m).collect();
+            let classical: Vec<usize> = (round * m..(round + 1) * m).collect();
+            circuit.barrier_all();
+            before_round(&mut circuit, &data, round);
+            self.measure_syndrome(&mut circuit, &data, &ancillas, &classical);
+        }
+        before_round(&mut circuit, &data, rounds);
+        for q in 0..n {
+            circuit.measure(q, rounds * m + q);
+        }
+        circuit
+    }
+
+    fn check_data(&self, data: &[usize]) {
+        assert_eq!(
+            data.len(),
+            self.num_data,
+            "{} acts on {} data qubits",
+            self.name,
+            self.num_data
+        );
+    }
+
+    /// The X generators in reduced row echelon form over the qubits outside
+    /// the logical-X support, as `(pivot, support)`: each pivot lies in its
+    /// own generator only.
+    fn x_pivots(&self) -> Vec<(usize, Vec<usize>)> {
+        let mut rows: Vec<Vec<bool>> = self
+            .x_stabilizers
+            .iter()
+            .map(|s| (0..self.num_data).map(|q| s.contains(&q)).collect())
+            .collect();
+        let mut pivots = Vec::new();
+        for col in (0..self.num_data).filter(|q| !self.logical_x.contains(q)) {
+            let rank = pivots.len();
+            let Some(found) = (rank..rows.len()).find(|&r| rows[r][col]) else {
+                continue;
+            };
+            rows.swap(rank, found);
+            for r in 0..rows.len() {
+                if r != rank && rows[r][col] {
+                    let pivot_row = rows[rank].clone();
+                    for (bit, p) in rows[r].iter_mut().zip(pivot_row) {
+                        *bit ^= p;
+                    }
+                }
+            }
+            pivots.push(col);
+        }
+        assert_eq!(
+            pivots.len(),
+            rows.len(),
+            "X generators of {} are dependent on the logical-X support",
+            self.name
+        );
+        pivots
+            .into_iter()
+            .zip(rows)
+            .map(|(pivot, row)| (pivot, (0..self.num_data).filter(|&q| row[q]).collect()))
+            .collect()
+    }
+}
+
+fn paulis(support: &[usize], data: &[usize], pauli: Pauli) -> Vec<(usize, Pauli)> {
+    support.iter().map(|&q| (data[q], pauli)).collect()
+}
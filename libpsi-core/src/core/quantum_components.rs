@@ -1,3 +1,6 @@
+use super::noise::subsystem_offsets;
+use super::spectrum::hermitian_eigenvalues;
+use super::DensityMatrix;
 use crate::{column_vector, complex, ColumnVector, Complex, Float, Matrix, Vector, VectorMatrix};
 use core::{fmt, ops};
 
@@ -40,6 +43,73 @@ impl QuantumState {
     pub fn state_1() -> QuantumState {
         column_vector![complex!(0.0, 0.0), complex!(1.0, 0.0)]
     }
+
+    /// The density matrix of the `kept` qubits, in the order given, with
+    /// the rest traced out. Built straight from the amplitudes as
+    /// `ρ_ij = Σ_e ψ_{ie} ψ*_{je}`, so the full `4ⁿ` density matrix never
+    /// exists.
+    pub fn reduced_density_matrix(&self, kept: &[usize]) -> DensityMatrix {
+        let n = (self.size() as f64).log2() as usize;
+        let traced: Vec<usize> = (0..n).filter(|q| !kept.contains(q)).collect();
+        let offsets = subsystem_offsets(n, kept);
+        let environment = subsystem_offsets(n, &traced);
+        let dim = offsets.len();
+        let mut data = vec![complex!(0.0, 0.0); dim * dim];
+        for (i, &row) in offsets.iter().enumerate() {
+            for (j, &col) in offsets.iter().enumerate() {
+                data[i * dim + j] = environment.iter().fold(complex!(0.0, 0.0), |sum, &e| {
+                    sum + self.get(row | e) * self.get(col | e).get_conjugate()
+                });
+            }
+        }
+        DensityMatrix {
+            data,
+            dim,
+            num_qubits: kept.len(),
+        }
+    }
+
+    /// The von Neumann entropy, in bits, of the `partition` qubits with the
+    /// rest traced out: `−Σ λ log₂ λ` over the squared Schmidt values `λ` of
+    /// the split. Both sides share their Schmidt values, so the spectrum is
+    /// taken from whichever reduced state is smaller.
+    pub fn entanglement_entropy(&self, partition: &[usize]) -> f64 {
+        let n = (self.size() as f64).log2() as usize;
+        let complement: Vec<usize> = (0..n).filter(|q| !partition.contains(q)).collect();
+        let side = if partition.len() <= complement.len() {
+            partition
+        } else {
+            &complement
+        };
+        let reduced = self.reduced_density_matrix(side);
+        hermitian_eigenvalues(&reduced.data, reduced.dim)
+            .into_iter()
+            .filter(|&lambda| lambda > 0.0)
+            .map(|lambda| -lambda * lambda.log2())
+            .sum()
+    }
+
+    /// `(⟨X⟩, ⟨Y⟩, ⟨Z⟩)` of `qubit`, as `DensityMatrix::bloch_vector`. Its
+    /// length is 1 unless the qubit is entangled with the others.
+    pub fn bloch_vector(&self, qubit: usize) -> (f64, f64, f64) {
+        self.reduced_density_matrix(&[qubit]).bloch_vector(0)
+    }
+}
+
+/// `|⟨a|b⟩|²`, the fidelity of two pure states; global phases drop out.
+pub fn fidelity(a: &QuantumState, b: &QuantumState) -> f64 {
+    assert_eq!(
+        a.size(),
+        b.size(),
+        "states of {} and {} amplitudes",
+        a.size(),
+        b.size()
+    );
+    (0..a.size())
+        .fold(complex!(0.0, 0.0), |sum, i| {
+            sum + a.get(i).get_conjugate() * b.get(i)
+        })
+        .norm2()
 }
 
 fn identity_matrix<T: Float>(size: usize) -> Matrix<T> {
@@ -121,8 +191,8 @@ impl<'a> QuantumBit<'a> {
 impl<'a> QuantumRegister<'a> {
     pub fn new(name: &'a str, names: &[&'a str]) -> QuantumRegister<'a> {
         let mut bits: Vec<QuantumBit<'a>> = Vec::new();
-        for i in 0..names.len() {
-            bits.push(QuantumBit::new(names[i], QuantumState::state_0()))
+        for name in names {
+            bits.push(QuantumBit::new(name, QuantumState::state_0()))
         }
 
         QuantumRegister::from(name, &mut bits)
@@ -161,6 +231,12 @@ impl<'a> QuantumRegister<'a> {
         self.state_vector.clone()
     }
 
+    /// Replaces the register's state, which may entangle its qubits; the
+    /// qubits keep their names but no longer describe it.
+    pub(crate) fn set_state(&mut self, state: QuantumState) {
+        self.state_vector = state;
+    }
+
     pub fn get_name(&self) -> &'a str {
         self.name
     }
@@ -204,6 +280,38 @@ impl<'a> QuantumRegister<'a> {
             .expect("Matrix multiplication failed during gate application");
     }
 
+    /// Applies the single-qubit `gate` to `target` where every qubit in
+    /// `controls` is |1⟩, one amplitude pair at a time within that subspace
+    /// rather than through the full operator.
+    pub fn apply_controlled_gate(&mut self, gate: &QuantumGate, controls: &[usize], target: usize) {
+        let n = self.num_qubits();
+        assert_eq!(gate.num_qubits, 1, "Controlled gate must act on one qubit");
+        for &q in controls.iter().chain([&target]) {
+            assert!(
+                q < n,
+                "Qubit index {} out of range for {}-qubit register",
+                q,
+                n
+            );
+        }
+        assert!(
+            !controls.contains(&target),
+            "Target qubit {} is also a control",
+            target
+        );
+
+        let bit = 1 << (n - 1 - target);
+        let set = controls.iter().fold(0, |set, &c| set | 1 << (n - 1 - c));
+        let m = &gate.matrix;
+        for i in 0..1usize << n {
+            if i & bit == 0 && i & set == set {
+                let (a0, a1) = (self.state_vector[i], self.state_vector[i | bit]);
+                self.state_vector[i] = m.get(0, 0) * a0 + m.get(0, 1) * a1;
+                self.state_vector[i | bit] = m.get(1, 0) * a0 + m.get(1, 1) * a1;
+            }
+        }
+    }
+
     fn build_full_operator(&self, gate: &QuantumGate, targets: &[usize]) -> Matrix<Complex<f64>> {
         let n = self.num_qubits();
         let g = gate.num_qubits;
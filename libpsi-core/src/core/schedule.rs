@@ -0,0 +1,221 @@
+use super::{GateOp, QuantumCircuit};
+use std::collections::HashMap;
+
+/// How long each operation takes, in whatever unit the caller chooses
+/// (nanoseconds for hardware figures). Durations set by gate name, as
+/// reported by `GateOp::name`, override the defaults for the gate's number
+/// of qubits. Barriers and snapshots take no time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GateDurations {
+    single_qubit: f64,
+    two_qubit: f64,
+    multi_qubit: f64,
+    measurement: f64,
+    by_name: HashMap<String, f64>,
+}
+
+impl GateDurations {
+    pub fn new(single_qubit: f64, two_qubit: f64, multi_qubit: f64, measurement: f64) -> Self {
+        for duration in [single_qubit, two_qubit, multi_qubit, measurement] {
+            assert!(duration >= 0.0, "gate durations must be non-negative");
+        }
+        GateDurations {
+            single_qubit,
+            two_qubit,
+            multi_qubit,
+            measurement,
+            by_name: HashMap::new(),
+        }
+    }
+
+    /// Every gate takes one unit and measurements nothing, so the total
+    /// time of an ASAP schedule is the circuit depth.
+    pub fn unit() -> Self {
+        Self::new(1.0, 1.0, 1.0, 0.0)
+    }
+
+    /// Sets the duration of every gate called `name`.
+    pub fn set(&mut self, name: &str, duration: f64) -> &mut Self {
+        assert!(duration >= 0.0, "gate durations must be non-negative");
+        self.by_name.insert(name.to_string(), duration);
+        self
+    }
+
+    pub fn duration(&self, op: &GateOp) -> f64 {
+        if op.is_barrier() || op.is_snapshot() {
+            return 0.0;
+        }
+        if let Some(&duration) = self.by_name.get(op.name()) {
+            return duration;
+        }
+        if op.is_measurement() || op.is_reset() {
+            return self.measurement;
+        }
+        match op.quantum_targets().len() {
+            0 | 1 => self.single_qubit,
+            2 => self.two_qubit,
+            _ => self.multi_qubit,
+        }
+    }
+}
+
+impl Default for GateDurations {
+    fn default() -> Self {
+        Self::unit()
+    }
+}
+
+/// When operations start within the slack the circuit leaves them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SchedulePolicy {
+    /// Every operation starts as soon as its qubits are free.
+    #[default]
+    Asap,
+    /// Every operation starts as late as it can without delaying the
+    /// ones after it, which keeps freshly prepared qubits from idling.
+    Alap,
+}
+
+/// Start times for the operations of a circuit. Operations on a common
+/// qubit or classical bit keep their circuit order; a barrier makes all of
+/// its qubits wait for the last of them, and snapshots are left out.
+#[derive(Clone, Debug)]
+pub struct Schedule {
+    num_qubits: usize,
+    operations: Vec<GateOp>,
+    start: Vec<f64>,
+    duration: Vec<f64>,
+    total: f64,
+}
+
+impl Schedule {
+    pub fn new(
+        circuit: &QuantumCircuit,
+        durations: &GateDurations,
+        policy: SchedulePolicy,
+    ) -> Self {
+        let operations: Vec<GateOp> = circuit
+            .operations()
+            .iter()
+            .filter(|op| !op.is_snapshot())
+            .cloned()
+            .collect();
+        let duration: Vec<f64> = operations.iter().map(|op| durations.duration(op)).collect();
+        let num_qubits = circuit.num_qubits();
+        let num_classical = circuit.num_classical();
+
+        let (start, total) = match policy {
+            SchedulePolicy::Asap => {
+                asap(operations.iter().zip(&duration), num_qubits, num_classical)
+            }
+            SchedulePolicy::Alap => {
+                // ALAP is ASAP on the reversed circuit, read backwards.
+                let (mut start, total) = asap(
+                    operations.iter().zip(&duration).rev(),
+                    num_qubits,
+                    num_classical,
+                );
+                start.reverse();
+                for (s, d) in start.iter_mut().zip(&duration) {
+                    *s = total - *s - d;
+                }
+                (start, total)
+            }
+        };
+
+        Schedule {
+            num_qubits,
+            operations,
+            start,
+            duration,
+            total,
+        }
+    }
+
+    /// The scheduled operations, which are the circuit's without snapshots.
+    pub fn operations(&self) -> &[GateOp] {
+        &self.operations
+    }
+
+    /// Start time of each operation in `operations`.
+    pub fn start_times(&self) -> &[f64] {
+        &self.start
+    }
+
+    pub fn durations(&self) -> &[f64] {
+        &self.duration
+    }
+
+    /// Wall time of the circuit: the schedule starts at 0 and ends when the
+    /// last operation finishes.
+    pub fn total_time(&self) -> f64 {
+        self.total
+    }
+
+    /// `(start, end)` of each stretch in which `qubit` waits between the
+    /// start and end of the circuit.
+    pub fn idle_periods(&self, qubit: usize) -> Vec<(f64, f64)> {
+        let mut busy: Vec<(f64, f64)> = self
+            .operations
+            .iter()
+            .enumerate()
+            .filter(|(i, op)| self.duration[*i] > 0.0 && op.quantum_targets().contains(&qubit))
+            .map(|(i, _)| (self.start[i], self.start[i] + self.duration[i]))
+            .collect();
+        busy.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut idle = Vec::new();
+        let mut free_from = 0.0;
+        for (start, end) in busy.into_iter().chain([(self.total, self.total)]) {
+            if start > free_from {
+                idle.push((free_from, start));
+            }
+            free_from = f64::max(free_from, end);
+        }
+        idle
+    }
+
+    /// Total idle time of every qubit.
+    pub fn idle_times(&self) -> Vec<f64> {
+        (0..self.num_qubits)
+            .map(|q| {
+                self.idle_periods(q)
+                    .iter()
+                    .fold(0.0, |total, (start, end)| total + end - start)
+            })
+            .collect()
+    }
+}
+
+/// Start times and total time with every operation placed as early as its
+/// qubits and classical bits allow.
+fn asap<'a>(
+    operations: impl Iterator<Item = (&'a GateOp, &'a f64)>,
+    num_qubits: usize,
+    num_classical: usize,
+) -> (Vec<f64>, f64) {
+    let mut qubit_free = vec![0.0; num_qubits];
+    let mut bit_free = vec![0.0; num_classical];
+    let mut total: f64 = 0.0;
+    let start = operations
+        .map(|(op, &duration)| {
+            let qubits = op.quantum_targets();
+            let bits = op.classical_targets();
+            let start = qubits
+                .iter()
+                .map(|&q| qubit_free[q])
+                .chain(bits.iter().map(|&c| bit_free[c]))
+                .fold(0.0, f64::max);
+            let end = start + duration;
+            for &q in &qubits {
+                qubit_free[q] = end;
+            }
+            for &c in &bits {
+                bit_free[c] = end;
+            }
+            total = total.max(end);
+            start
+        })
+        .collect();
+    (start, total)
+}
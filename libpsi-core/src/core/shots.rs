@@ -0,0 +1,393 @@
+use super::counts::{entropy_seed, stream_seed, SHOTS_PER_CHUNK};
+use super::{Counts, GateOp, QuantumState, Runtime, SnapshotValue, StateTrace, TraceStep};
+use crate::maths::parallel::*;
+use crate::{complex, Complex, Vector};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::{BTreeMap, HashMap};
+
+/// Whether `operations` must run shot by shot, collapsing the state at
+/// measurements: some operation depends on a measured bit, a measured
+/// qubit is acted on again, or a qubit is reset, so reading everything out
+/// at the end would see the wrong outcome.
+pub(crate) fn needs_shots(operations: &[GateOp]) -> bool {
+    operations.iter().enumerate().any(|(i, op)| match op {
+        GateOp::Conditional { .. } | GateOp::Reset(_) => true,
+        GateOp::Measure(qubit, _) => acted_on(&operations[i + 1..], *qubit),
+        _ => false,
+    })
+}
+
+/// Samples `shots` outcomes of `operations`, evaluating every classical
+/// condition against the bits measured earlier in the same shot. Shots
+/// share their history until a measurement splits them by outcome, so the
+/// state is evolved once per distinct branch rather than once per shot.
+/// Outcomes follow the bit order of `Counts`.
+pub(crate) fn sample(
+    runtime: Runtime,
+    num_qubits: usize,
+    start: Vec<Complex<f64>>,
+    operations: &[GateOp],
+    num_classical: usize,
+    shots: usize,
+    seed: Option<u64>,
+) -> Counts {
+    let mut rng = StdRng::seed_from_u64(seed.unwrap_or_else(entropy_seed));
+    let executor = Executor::new(runtime, num_qubits, operations);
+    let root = Branch::new(start, num_classical, shots);
+    Counts::from_outcomes(executor.sample(root, &mut rng))
+}
+
+/// `sample` split into chunks of `SHOTS_PER_CHUNK` shots run on all
+/// threads. Chunk `i` uses its own RNG stream derived from the seed and `i`,
+/// as in `Counts::sample_parallel`, so a seeded result does not depend on
+/// the number of threads.
+pub(crate) fn sample_parallel(
+    runtime: Runtime,
+    num_qubits: usize,
+    start: Vec<Complex<f64>>,
+    operations: &[GateOp],
+    num_classical: usize,
+    shots: usize,
+    seed: Option<u64>,
+) -> Counts {
+    let seed = seed.unwrap_or_else(entropy_seed);
+    let chunks: Vec<BTreeMap<String, usize>> = (0..shots.div_ceil(SHOTS_PER_CHUNK))
+        .into_par_iter()
+        .map(|chunk| {
+            let mut rng = StdRng::seed_from_u64(stream_seed(seed, chunk as u64));
+            let chunk_shots = SHOTS_PER_CHUNK.min(shots - chunk * SHOTS_PER_CHUNK);
+            let executor = Executor::new(runtime, num_qubits, operations);
+            let root = Branch::new(start.clone(), num_classical, chunk_shots);
+            executor.sample(root, &mut rng)
+        })
+        .collect();
+    Counts::from_outcomes(chunks.into_iter().flatten())
+}
+
+/// One shot of a circuit: its final state, the snapshots taken along the
+/// way, and the state after every operation when it was traced.
+pub(crate) struct Shot {
+    pub state: QuantumState,
+    pub snapshots: HashMap<String, SnapshotValue>,
+    pub trace: Option<StateTrace>,
+}
+
+/// One shot of `operations` from `start`, collapsed onto the outcome drawn
+/// at each measurement that later operations depend on. With a `trace`
+/// cutoff, the state after every operation is recorded as well.
+pub(crate) fn run_shot(
+    runtime: Runtime,
+    num_qubits: usize,
+    start: Vec<Complex<f64>>,
+    operations: &[GateOp],
+    seed: u64,
+    trace: Option<f64>,
+) -> Shot {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let num_classical = operations
+        .iter()
+        .flat_map(|op| {
+            op.condition()
+                .map(|(c, _)| c)
+                .into_iter()
+                .chain(op.classical_targets())
+        })
+        .max()
+        .map_or(0, |c| c + 1);
+    let root = Branch::new(start.clone(), num_classical, 1);
+    let mut executor = Executor::new(runtime, num_qubits, operations);
+    executor.record = true;
+    executor.trace = trace.is_some();
+    let mut last = None;
+    executor.run(root, &mut rng, |branch, _| {
+        last = Some(std::mem::take(branch));
+    });
+    let last = last.expect("a shot always reaches the end");
+    Shot {
+        state: QuantumState::new(last.state),
+        snapshots: last.snapshots,
+        trace: trace.map(|cutoff| StateTrace::from_steps(start, num_qubits, cutoff, last.steps)),
+    }
+}
+
+/// Shots that agree on every outcome so far: their common state, bits,
+/// and the index of the next operation to run. Snapshots and trace steps
+/// are only kept when the executor records them.
+#[derive(Default)]
+struct Branch {
+    state: Vec<Complex<f64>>,
+    bits: Vec<bool>,
+    next: usize,
+    shots: usize,
+    snapshots: HashMap<String, SnapshotValue>,
+    steps: Vec<TraceStep>,
+}
+
+impl Branch {
+    fn new(state: Vec<Complex<f64>>, num_classical: usize, shots: usize) -> Self {
+        Branch {
+            state,
+            bits: vec![false; num_classical],
+            shots,
+            ..Branch::default()
+        }
+    }
+}
+
+struct Executor<'a> {
+    runtime: Runtime,
+    num_qubits: usize,
+    operations: &'a [GateOp],
+    /// Per operation, whether it is a reset or a measurement the rest of
+    /// the circuit depends on, which splits the shots by its outcome.
+    splits: Vec<bool>,
+    /// Whether branches capture their snapshots.
+    record: bool,
+    /// Whether branches keep the state after every operation.
+    trace: bool,
+}
+
+impl<'a> Executor<'a> {
+    fn new(runtime: Runtime, num_qubits: usize, operations: &'a [GateOp]) -> Self {
+        assert!(
+            !matches!(runtime, Runtime::Stabilizer),
+            "the Stabilizer runtime runs shots through StabilizerState::sample"
+        );
+        let splits = operations
+            .iter()
+            .enumerate()
+            .map(|(i, op)| {
+                let (qubit, bit) = match *op {
+                    GateOp::Measure(qubit, bit) => (qubit, bit),
+                    GateOp::Reset(_) => return true,
+                    _ => return false,
+                };
+                let rest = &operations[i + 1..];
+                acted_on(rest, qubit)
+                    || rest
+                        .iter()
+                        .any(|op| matches!(op.condition(), Some((c, _)) if c == bit))
+            })
+            .collect();
+        Executor {
+            runtime,
+            num_qubits,
+            operations,
+            splits,
+            record: false,
+            trace: false,
+        }
+    }
+
+    /// Runs `root` and draws an outcome for each of its shots from the
+    /// branch it ends in.
+    fn sample(&self, root: Branch, rng: &mut StdRng) -> BTreeMap<String, usize> {
+        let num_qubits = self.num_qubits;
+        let readout = self.readout();
+        let mut outcomes: BTreeMap<String, usize> = BTreeMap::new();
+        self.run(root, rng, |branch, rng| {
+            let mut cumulative = Vec::with_capacity(branch.state.len());
+            let mut total = 0.0;
+            for amplitude in &branch.state {
+                total += amplitude.norm2();
+                cumulative.push(total);
+            }
+            for _ in 0..branch.shots {
+                let r = rng.random::<f64>() * total;
+                let index = cumulative
+                    .partition_point(|&c| c <= r)
+                    .min(cumulative.len() - 1);
+                let qubit = |q: usize| (index >> (num_qubits - 1 - q)) & 1 == 1;
+                let key: String = match &readout {
+                    None => (0..num_qubits).map(|q| bit_char(qubit(q))).collect(),
+                    Some(terminal) => {
+                        let mut bits = branch.bits.clone();
+                        for &(q, c) in terminal {
+                            bits[c] = qubit(q);
+                        }
+                        bits.into_iter().map(bit_char).collect()
+                    }
+                };
+                *outcomes.entry(key).or_insert(0) += 1;
+            }
+        });
+        outcomes
+    }
+
+    /// The `(qubit, classical)` measurements read from the final state of
+    /// each branch: those that do not split the shots and whose bit is not
+    /// overwritten later. `None` when the circuit measures nothing and the
+    /// whole register is read out.
+    fn readout(&self) -> Option<Vec<(usize, usize)>> {
+        let measurements: Vec<(usize, usize, usize)> = self
+            .operations
+            .iter()
+            .enumerate()
+            .filter_map(|(i, op)| match *op {
+                GateOp::Measure(q, c) => Some((i, q, c)),
+                _ => None,
+            })
+            .collect();
+        if measurements.is_empty() {
+            return None;
+        }
+        let terminal = measurements
+            .iter()
+            .filter(|&&(i, _, c)| {
+                !self.splits[i] && !measurements.iter().any(|&(j, _, d)| j > i && d == c)
+            })
+            .map(|&(_, q, c)| (q, c))
+            .collect();
+        Some(terminal)
+    }
+
+    /// Runs `root` depth first, calling `leaf` with every branch that
+    /// reaches the end of the circuit.
+    fn run(&self, root: Branch, rng: &mut StdRng, mut leaf: impl FnMut(&mut Branch, &mut StdRng)) {
+        let mut pending = vec![root];
+        while let Some(mut branch) = pending.pop() {
+            let mut segment = Vec::new();
+            while branch.next < self.operations.len() && !self.splits[branch.next] {
+                match &self.operations[branch.next] {
+                    GateOp::Conditional {
+                        op,
+                        classical_bit,
+                        value,
+                    } => {
+                        if branch.bits[*classical_bit] == *value {
+                            segment.push((**op).clone());
+                        }
+                    }
+                    GateOp::Snapshot(label, kind) => {
+                        if self.record {
+                            self.flush(&mut branch, &mut segment);
+                            let value = kind.capture(&branch.state, self.num_qubits);
+                            branch.snapshots.insert(label.clone(), value);
+                        }
+                    }
+                    op => segment.push(op.clone()),
+                }
+                if self.trace {
+                    self.flush(&mut branch, &mut segment);
+                    let index = branch.next;
+                    self.step(&mut branch, index);
+                }
+                branch.next += 1;
+            }
+            self.flush(&mut branch, &mut segment);
+            if branch.next == self.operations.len() {
+                leaf(&mut branch, rng);
+                continue;
+            }
+
+            let (qubit, bit) = match self.operations[branch.next] {
+                GateOp::Measure(qubit, bit) => (qubit, Some(bit)),
+                GateOp::Reset(qubit) => (qubit, None),
+                _ => unreachable!("only measurements and resets split shots"),
+            };
+            branch.next += 1;
+            let mask = 1 << (self.num_qubits - 1 - qubit);
+            let p1: f64 = branch
+                .state
+                .iter()
+                .enumerate()
+                .filter(|&(i, _)| i & mask != 0)
+                .map(|(_, a)| a.norm2())
+                .sum();
+            let ones = (0..branch.shots)
+                .filter(|_| rng.random::<f64>() < p1)
+                .count();
+            for (outcome, shots) in [(true, ones), (false, branch.shots - ones)] {
+                if shots == 0 {
+                    continue;
+                }
+                let mut bits = branch.bits.clone();
+                let mut state = collapse(&branch.state, mask, outcome);
+                match bit {
+                    Some(bit) => bits[bit] = outcome,
+                    // A reset flips the qubit back to |0⟩.
+                    None if outcome => {
+                        for i in (0..state.len()).filter(|i| i & mask == 0) {
+                            state.swap(i, i | mask);
+                        }
+                    }
+                    None => {}
+                }
+                let mut split = Branch {
+                    state,
+                    bits,
+                    next: branch.next,
+                    shots,
+                    snapshots: branch.snapshots.clone(),
+                    steps: branch.steps.clone(),
+                };
+                if self.trace {
+                    self.step(&mut split, branch.next - 1);
+                }
+                pending.push(split);
+            }
+        }
+    }
+
+    /// Applies the gates collected in `segment` to `branch`.
+    fn flush(&self, branch: &mut Branch, segment: &mut Vec<GateOp>) {
+        if segment.is_empty() {
+            return;
+        }
+        let state = self
+            .runtime
+            .compute_from(self.num_qubits, segment, Some(&branch.state));
+        branch.state = (0..state.size()).map(|i| state.get(i)).collect();
+        segment.clear();
+    }
+
+    fn step(&self, branch: &mut Branch, index: usize) {
+        branch.steps.push(TraceStep {
+            index,
+            op: self.operations[index].clone(),
+            amplitudes: branch.state.clone(),
+        });
+    }
+}
+
+/// Whether an operation in `operations` changes `qubit`.
+fn acted_on(operations: &[GateOp], qubit: usize) -> bool {
+    operations.iter().any(|op| {
+        !op.is_measurement()
+            && !op.is_barrier()
+            && !op.is_snapshot()
+            && op.quantum_targets().contains(&qubit)
+    })
+}
+
+/// `state` projected onto the qubit under `mask` reading `outcome`, and
+/// renormalised.
+fn collapse(state: &[Complex<f64>], mask: usize, outcome: bool) -> Vec<Complex<f64>> {
+    let norm: f64 = state
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| (i & mask != 0) == outcome)
+        .map(|(_, a)| a.norm2())
+        .sum();
+    let scale = complex!(1.0 / norm.sqrt(), 0.0);
+    state
+        .iter()
+        .enumerate()
+        .map(|(i, &a)| {
+            if (i & mask != 0) == outcome {
+                a * scale
+            } else {
+                complex!(0.0, 0.0)
+            }
+        })
+        .collect()
+}
+
+fn bit_char(bit: bool) -> char {
+    if bit {
+        '1'
+    } else {
+        '0'
+    }
+}
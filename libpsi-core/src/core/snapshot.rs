@@ -0,0 +1,117 @@
+use super::QuantumState;
+use crate::{complex, format_amplitude, format_probability, Complex, Vector};
+use core::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Pauli {
+    I,
+    X,
+    Y,
+    Z,
+}
+
+/// What a `GateOp::Snapshot` records.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SnapshotKind {
+    State,
+    Probabilities,
+    /// Expectation value of a Pauli product, given as `(qubit, Pauli)` factors.
+    /// Qubits without a factor are acted on by the identity.
+    Expectation(Vec<(usize, Pauli)>),
+}
+
+/// A quantity captured by the runtime when it reaches a snapshot.
+#[derive(Clone)]
+pub enum SnapshotValue {
+    State(QuantumState),
+    Probabilities(Vec<f64>),
+    Expectation(f64),
+}
+
+impl SnapshotKind {
+    pub fn capture(&self, state: &[Complex<f64>], num_qubits: usize) -> SnapshotValue {
+        match self {
+            SnapshotKind::State => SnapshotValue::State(QuantumState::new(state.to_vec())),
+            SnapshotKind::Probabilities => {
+                SnapshotValue::Probabilities(state.iter().map(|amp| amp.norm2()).collect())
+            }
+            SnapshotKind::Expectation(factors) => {
+                SnapshotValue::Expectation(pauli_expectation(state, factors, num_qubits))
+            }
+        }
+    }
+}
+
+/// ⟨ψ|P|ψ⟩ for a Pauli product P. Every factor maps |i⟩ to a phase times
+/// |i ⊕ flip⟩, so the sum runs over basis states without building P.
+fn pauli_expectation(state: &[Complex<f64>], factors: &[(usize, Pauli)], num_qubits: usize) -> f64 {
+    let mut flip = 0usize;
+    for &(qubit, pauli) in factors {
+        if matches!(pauli, Pauli::X | Pauli::Y) {
+            flip ^= 1 << (num_qubits - 1 - qubit);
+        }
+    }
+
+    let mut total = complex!(0.0, 0.0);
+    for (i, &amp) in state.iter().enumerate() {
+        let mut phase = complex!(1.0, 0.0);
+        for &(qubit, pauli) in factors {
+            let bit = (i >> (num_qubits - 1 - qubit)) & 1;
+            phase = match (pauli, bit) {
+                (Pauli::Y, 0) => phase * complex!(0.0, 1.0),
+                (Pauli::Y, _) => phase * complex!(0.0, -1.0),
+                (Pauli::Z, 1) => -phase,
+                _ => phase,
+            };
+        }
+        total += state[i ^ flip].get_conjugate() * phase * amp;
+    }
+    total.real
+}
+
+impl fmt::Display for SnapshotKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotKind::State => write!(f, "state"),
+            SnapshotKind::Probabilities => write!(f, "probabilities"),
+            SnapshotKind::Expectation(factors) => {
+                let factors: Vec<String> = factors
+                    .iter()
+                    .map(|(qubit, pauli)| format!("{:?}{}", pauli, qubit))
+                    .collect();
+                write!(f, "⟨{}⟩", factors.join(" "))
+            }
+        }
+    }
+}
+
+impl fmt::Display for SnapshotValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotValue::State(state) => {
+                let n = state.size().max(1).trailing_zeros() as usize;
+                for i in 0..state.size() {
+                    let amp = state.get(i);
+                    if amp.real.abs() > 1e-10 || amp.imaginary.abs() > 1e-10 {
+                        let basis: String = format!("{:0width$b}", i, width = n);
+                        writeln!(f, "  |{}⟩: {}", basis, format_amplitude(&amp))?;
+                    }
+                }
+                Ok(())
+            }
+            SnapshotValue::Probabilities(probs) => {
+                let n = probs.len().max(1).trailing_zeros() as usize;
+                for (i, p) in probs.iter().enumerate() {
+                    if *p > 1e-10 {
+                        let basis: String = format!("{:0width$b}", i, width = n);
+                        writeln!(f, "  |{}⟩: {}", basis, format_probability(*p))?;
+                    }
+                }
+                Ok(())
+            }
+            SnapshotValue::Expectation(value) => writeln!(f, "  {:.6}", value),
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use crate::{complex, Complex};
+
+/// Sweeps of the Jacobi method before giving up; each roughly squares the
+/// off-diagonal norm once it is small, so a dozen is plenty in practice.
+const MAX_SWEEPS: usize = 64;
+
+/// The eigenvalues of the Hermitian `dim × dim` matrix `data` (row-major),
+/// in descending order.
+///
+/// `A = R + iI` is Hermitian exactly when `[[R, −I], [I, R]]` is real
+/// symmetric, and the latter has the eigenvalues of `A`, each twice, so
+/// the real Jacobi method does the work.
+pub(crate) fn hermitian_eigenvalues(data: &[Complex<f64>], dim: usize) -> Vec<f64> {
+    let (mut values, _) = symmetric_eigen(embed(data, dim), 2 * dim);
+    values.sort_by(|a, b| b.total_cmp(a));
+    values
+        .chunks(2)
+        .map(|pair| (pair[0] + pair[1]) / 2.0)
+        .collect()
+}
+
+/// `f(A)` for the Hermitian `dim × dim` matrix `data`, applying `f` to its
+/// eigenvalues. Taken on the real embedding `M` of `A`, `f(M)` is the
+/// embedding of `f(A)` whatever basis the doubled eigenvalues get.
+pub(crate) fn hermitian_function(
+    data: &[Complex<f64>],
+    dim: usize,
+    f: impl Fn(f64) -> f64,
+) -> Vec<Complex<f64>> {
+    let size = 2 * dim;
+    let (values, vectors) = symmetric_eigen(embed(data, dim), size);
+    let values: Vec<f64> = values.into_iter().map(f).collect();
+    let entry = |i: usize, j: usize| -> f64 {
+        (0..size)
+            .map(|k| vectors[i * size + k] * values[k] * vectors[j * size + k])
+            .sum()
+    };
+    let mut result = Vec::with_capacity(dim * dim);
+    for i in 0..dim {
+        for j in 0..dim {
+            result.push(complex!(entry(i, j), entry(i + dim, j)));
+        }
+    }
+    result
+}
+
+/// The real symmetric `[[R, −I], [I, R]]` of `A = R + iI`.
+fn embed(data: &[Complex<f64>], dim: usize) -> Vec<f64> {
+    let size = 2 * dim;
+    let mut real = vec![0.0; size * size];
+    for i in 0..dim {
+        for j in 0..dim {
+            let x = data[i * dim + j];
+            real[i * size + j] = x.real;
+            real[(i + dim) * size + j + dim] = x.real;
+            real[(i + dim) * size + j] = x.imaginary;
+            real[i * size + j + dim] = -x.imaginary;
+        }
+    }
+    real
+}
+
+/// Cyclic Jacobi: rotate away each off-diagonal entry in turn until the
+/// matrix is diagonal to working precision. Returns the eigenvalues and
+/// the row-major matrix whose columns are the matching eigenvectors.
+fn symmetric_eigen(mut a: Vec<f64>, n: usize) -> (Vec<f64>, Vec<f64>) {
+    let mut v = vec![0.0; n * n];
+    for i in 0..n {
+        v[i * n + i] = 1.0;
+    }
+    let scale: f64 = a.iter().map(|x| x * x).sum();
+    for _ in 0..MAX_SWEEPS {
+        let off: f64 = (0..n)
+            .flat_map(|p| (p + 1..n).map(move |q| (p, q)))
+            .map(|(p, q)| a[p * n + q] * a[p * n + q])
+            .sum();
+        if off <= f64::EPSILON * f64::EPSILON * scale {
+            break;
+        }
+        for p in 0..n {
+            for q in p + 1..n {
+                let apq = a[p * n + q];
+                if apq == 0.0 {
+                    continue;
+                }
+                let theta = (a[q * n + q] - a[p * n + p]) / (2.0 * apq);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+                for k in 0..n {
+                    let (akp, akq) = (a[k * n + p], a[k * n + q]);
+                    a[k * n + p] = c * akp - s * akq;
+                    a[k * n + q] = s * akp + c * akq;
+                    let (vkp, vkq) = (v[k * n + p], v[k * n + q]);
+                    v[k * n + p] = c * vkp - s * vkq;
+                    v[k * n + q] = s * vkp + c * vkq;
+                }
+                for k in 0..n {
+                    let (apk, aqk) = (a[p * n + k], a[q * n + k]);
+                    a[p * n + k] = c * apk - s * aqk;
+                    a[q * n + k] = s * apk + c * aqk;
+                }
+            }
+        }
+    }
+    ((0..n).map(|i| a[i * n + i]).collect(), v)
+}
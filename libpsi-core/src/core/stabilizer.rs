@@ -0,0 +1,820 @@
+use super::circuit::try_defer_conditionals;
+use super::counts::{entropy_seed, stream_seed};
+use super::shots;
+use super::{
+    CompositeOp, Counts, CustomGateDefinition, GateOp, QuantumCircuit, QuantumState,
+    SimulatorBackend,
+};
+use crate::maths::parallel::*;
+use crate::{complex, Complex, Vector};
+use core::fmt;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::BTreeMap;
+use std::f64::consts::FRAC_PI_2;
+
+const ANGLE_TOLERANCE: f64 = 1e-9;
+
+/// A Clifford unitary `U` as its Aaronson–Gottesman tableau: the images
+/// `U X_i U†` (destabilisers) and `U Z_i U†` (stabilisers) of the Pauli
+/// generators, each a signed Pauli string.
+///
+/// Two Clifford circuits implement the same unitary up to global phase
+/// exactly when their tableaus are equal, which takes `O(n²)` memory
+/// instead of a `2ⁿ` state vector.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StabilizerTableau {
+    num_qubits: usize,
+    /// Rows `0..n` are destabilisers, `n..2n` stabilisers. A row is
+    /// `(-1)^r` times the product over qubits of `X` (x set), `Z` (z set) or
+    /// `Y` (both).
+    x: Vec<Vec<bool>>,
+    z: Vec<Vec<bool>>,
+    r: Vec<bool>,
+}
+
+impl StabilizerTableau {
+    /// The tableau of the identity.
+    pub fn new(num_qubits: usize) -> Self {
+        let rows = 2 * num_qubits;
+        let mut x = vec![vec![false; num_qubits]; rows];
+        let mut z = vec![vec![false; num_qubits]; rows];
+        for q in 0..num_qubits {
+            x[q][q] = true;
+            z[num_qubits + q][q] = true;
+        }
+        StabilizerTableau {
+            num_qubits,
+            x,
+            z,
+            r: vec![false; rows],
+        }
+    }
+
+    /// The tableau of `QuantumCircuit::graph_state`, built directly: the
+    /// stabiliser of qubit `a` is `X_a` times `Z_b` for every neighbour `b`,
+    /// its destabiliser `Z_a`.
+    pub fn graph_state(num_qubits: usize, edges: &[(usize, usize)]) -> Self {
+        let n = num_qubits;
+        let mut tableau = StabilizerTableau::new(n);
+        for q in 0..n {
+            tableau.x[q][q] = false;
+            tableau.z[q][q] = true;
+            tableau.x[n + q][q] = true;
+            tableau.z[n + q][q] = false;
+        }
+        for &(a, b) in edges {
+            assert!(
+                a != b && a < n && b < n,
+                "invalid graph state edge ({}, {}) on {} qubits",
+                a,
+                b,
+                n
+            );
+            tableau.z[n + a][b] ^= true;
+            tableau.z[n + b][a] ^= true;
+        }
+        tableau
+    }
+
+    /// The tableau of `circuit`, which must consist of Clifford gates.
+    /// Rotations are accepted at multiples of π/2; barriers and snapshots
+    /// are skipped.
+    pub fn from_circuit(circuit: &QuantumCircuit) -> Result<Self, String> {
+        let mut tableau = StabilizerTableau::new(circuit.num_qubits());
+        for op in try_defer_conditionals(circuit.operations())?.iter() {
+            tableau.apply(op)?;
+        }
+        Ok(tableau)
+    }
+
+    pub fn num_qubits(&self) -> usize {
+        self.num_qubits
+    }
+
+    /// Follows the tableau with `op`, or fails if `op` is not Clifford.
+    pub fn apply(&mut self, op: &GateOp) -> Result<(), String> {
+        match *op {
+            GateOp::H(t) => self.h(t),
+            GateOp::S(t) => self.s(t),
+            GateOp::Sdg(t) => self.sdg(t),
+            GateOp::X(t) => self.x(t),
+            GateOp::Y(t) => self.y(t),
+            GateOp::Z(t) => self.z(t),
+            GateOp::Sx(t) => {
+                self.h(t);
+                self.s(t);
+                self.h(t);
+            }
+            GateOp::Sxdg(t) => {
+                self.h(t);
+                self.sdg(t);
+                self.h(t);
+            }
+            GateOp::CNOT(c, t) => self.cnot(c, t),
+            GateOp::MCX(ref controls, t) => match controls[..] {
+                [] => self.x(t),
+                [c] => self.cnot(c, t),
+                _ => return Err(format!("{} is not a Clifford gate", op.name())),
+            },
+            GateOp::CZ(a, b) => self.cz(a, b),
+            GateOp::SWAP(a, b) => self.swap(a, b),
+            GateOp::CY(c, t) => {
+                self.sdg(t);
+                self.cnot(c, t);
+                self.s(t);
+            }
+            GateOp::ECR(a, b) => {
+                self.x(a);
+                self.h(b);
+                self.cnot(a, b);
+                self.sdg(b);
+                self.cnot(a, b);
+                self.h(b);
+            }
+            GateOp::DCX(a, b) => {
+                self.cnot(a, b);
+                self.cnot(b, a);
+            }
+            GateOp::ISwap(a, b) => {
+                self.s(a);
+                self.s(b);
+                self.cz(a, b);
+                self.swap(a, b);
+            }
+            GateOp::Rz(t, theta) | GateOp::P(t, theta) | GateOp::U1(t, theta) => {
+                let quarters = quarter_turns(op, theta)?;
+                self.s_power(t, quarters);
+            }
+            GateOp::Rx(t, theta) => {
+                let quarters = quarter_turns(op, theta)?;
+                self.h(t);
+                self.s_power(t, quarters);
+                self.h(t);
+            }
+            // Ry(θ) = S Rx(θ) S†.
+            GateOp::Ry(t, theta) => {
+                let quarters = quarter_turns(op, theta)?;
+                self.sdg(t);
+                self.h(t);
+                self.s_power(t, quarters);
+                self.h(t);
+                self.s(t);
+            }
+            GateOp::Barrier(_, _) | GateOp::Snapshot(_, _) => {}
+            GateOp::Custom(ref gate, ref targets) => match &gate.definition {
+                CustomGateDefinition::Composite(ops) => {
+                    for (composite, qubits) in ops {
+                        let q = |i: usize| targets[qubits[i]];
+                        match composite {
+                            CompositeOp::H => self.h(q(0)),
+                            CompositeOp::X => self.x(q(0)),
+                            CompositeOp::Y => self.y(q(0)),
+                            CompositeOp::Z => self.z(q(0)),
+                            CompositeOp::S => self.s(q(0)),
+                            CompositeOp::CNOT => self.cnot(q(0), q(1)),
+                            CompositeOp::CZ => self.cz(q(0), q(1)),
+                            CompositeOp::SWAP => self.swap(q(0), q(1)),
+                            CompositeOp::T | CompositeOp::CCNOT | CompositeOp::CSWAP => {
+                                return Err(format!("{} contains a non-Clifford gate", gate.name))
+                            }
+                        }
+                    }
+                }
+                CustomGateDefinition::Matrix(_) => {
+                    return Err(format!("{} is given by a matrix", gate.name))
+                }
+            },
+            GateOp::Conditional { .. } => {
+                return Err(format!("{:?} must be deferred against its circuit", op))
+            }
+            GateOp::Parameterized { .. } => return Err(format!("{:?} has unbound parameters", op)),
+            _ => return Err(format!("{} is not a Clifford gate", op.name())),
+        }
+        Ok(())
+    }
+
+    /// The tableau of `self` followed by `other`.
+    pub fn compose(&self, other: &StabilizerTableau) -> StabilizerTableau {
+        assert_eq!(
+            self.num_qubits, other.num_qubits,
+            "tableaus act on different numbers of qubits"
+        );
+        let n = self.num_qubits;
+        let mut result = StabilizerTableau::new(n);
+        for row in 0..2 * n {
+            // Write the row as i^k X^x Z^z and substitute other's images of
+            // the generators, multiplying out the phases.
+            let mut phase = 2 * self.r[row] as u32 + self.y_count(row);
+            let mut x = vec![false; n];
+            let mut z = vec![false; n];
+            for q in 0..n {
+                for (present, image) in [(self.x[row][q], q), (self.z[row][q], n + q)] {
+                    if present {
+                        let image_phase = 2 * other.r[image] as u32 + other.y_count(image);
+                        let swaps = (0..n).filter(|&j| z[j] && other.x[image][j]).count() as u32;
+                        phase += image_phase + 2 * swaps;
+                        for j in 0..n {
+                            x[j] ^= other.x[image][j];
+                            z[j] ^= other.z[image][j];
+                        }
+                    }
+                }
+            }
+            let y_count = (0..n).filter(|&j| x[j] && z[j]).count() as u32;
+            result.r[row] = (phase + 4 - y_count % 4) % 4 == 2;
+            result.x[row] = x;
+            result.z[row] = z;
+        }
+        result
+    }
+
+    /// Inverts the tableau by synthesis; see `to_circuit`.
+    pub fn inverse(&self) -> StabilizerTableau {
+        StabilizerTableau::from_circuit(&self.synthesise_inverse())
+            .expect("synthesised circuits are Clifford")
+    }
+
+    /// An H/S/CNOT circuit implementing the tableau (up to global phase),
+    /// with S† for inverted phase gates, after a layer of X and Z that fixes
+    /// signs. Equivalent Clifford circuits synthesise to the same circuit,
+    /// so this also canonicalises them.
+    pub fn to_circuit(&self) -> QuantumCircuit {
+        let reduction = self.synthesise_inverse();
+        let mut circuit = QuantumCircuit::new(self.num_qubits);
+        for op in reduction.operations().iter().rev() {
+            circuit.push(match *op {
+                GateOp::S(t) => GateOp::Sdg(t),
+                GateOp::Sdg(t) => GateOp::S(t),
+                ref op => op.clone(),
+            });
+        }
+        circuit
+    }
+
+    /// Reduces a copy of the tableau to the identity (Aaronson–Gottesman,
+    /// as in Qiskit's `synth_clifford_ag`), returning the gates applied;
+    /// they implement the inverse of the tableau.
+    fn synthesise_inverse(&self) -> QuantumCircuit {
+        let n = self.num_qubits;
+        let mut tableau = self.clone();
+        let mut circuit = QuantumCircuit::new(n);
+        let mut apply = |tableau: &mut StabilizerTableau, op: GateOp| {
+            tableau.apply(&op).expect("reduction gates are Clifford");
+            match op {
+                GateOp::SWAP(a, b) => {
+                    circuit.cnot(a, b).cnot(b, a).cnot(a, b);
+                }
+                op => {
+                    circuit.push(op);
+                }
+            }
+        };
+
+        for q in 0..n {
+            // Bring an X onto qubit q of destabiliser q.
+            if !tableau.x[q][q] {
+                if let Some(i) = (q + 1..n).find(|&i| tableau.x[q][i]) {
+                    apply(&mut tableau, GateOp::SWAP(q, i));
+                } else if let Some(i) = (q..n).find(|&i| tableau.z[q][i]) {
+                    apply(&mut tableau, GateOp::H(i));
+                    if i != q {
+                        apply(&mut tableau, GateOp::SWAP(q, i));
+                    }
+                }
+            }
+
+            // Clear the rest of destabiliser q, leaving X_q.
+            for i in q + 1..n {
+                if tableau.x[q][i] {
+                    apply(&mut tableau, GateOp::CNOT(q, i));
+                }
+            }
+            if (q..n).any(|i| tableau.z[q][i]) {
+                if !tableau.z[q][q] {
+                    apply(&mut tableau, GateOp::S(q));
+                }
+                for i in q + 1..n {
+                    if tableau.z[q][i] {
+                        apply(&mut tableau, GateOp::CNOT(i, q));
+                    }
+                }
+                apply(&mut tableau, GateOp::S(q));
+            }
+
+            // Clear stabiliser q, leaving Z_q.
+            let s = n + q;
+            for i in q + 1..n {
+                if tableau.z[s][i] {
+                    apply(&mut tableau, GateOp::CNOT(i, q));
+                }
+            }
+            if (q..n).any(|i| tableau.x[s][i]) {
+                apply(&mut tableau, GateOp::H(q));
+                for i in q + 1..n {
+                    if tableau.x[s][i] {
+                        apply(&mut tableau, GateOp::CNOT(q, i));
+                    }
+                }
+                if tableau.z[s][q] {
+                    apply(&mut tableau, GateOp::S(q));
+                }
+                apply(&mut tableau, GateOp::H(q));
+            }
+        }
+
+        for q in 0..n {
+            if tableau.r[q] {
+                apply(&mut tableau, GateOp::Z(q));
+            }
+            if tableau.r[n + q] {
+                apply(&mut tableau, GateOp::X(q));
+            }
+        }
+        circuit
+    }
+
+    /// Destabiliser `qubit`, the image of `X_qubit`, e.g. `-XZI`.
+    pub fn destabiliser(&self, qubit: usize) -> String {
+        self.pauli_string(qubit)
+    }
+
+    /// Stabiliser `qubit`, the image of `Z_qubit`.
+    pub fn stabiliser(&self, qubit: usize) -> String {
+        self.pauli_string(self.num_qubits + qubit)
+    }
+
+    fn pauli_string(&self, row: usize) -> String {
+        let sign = if self.r[row] { '-' } else { '+' };
+        let paulis = (0..self.num_qubits).map(|q| match (self.x[row][q], self.z[row][q]) {
+            (false, false) => 'I',
+            (true, false) => 'X',
+            (false, true) => 'Z',
+            (true, true) => 'Y',
+        });
+        std::iter::once(sign).chain(paulis).collect()
+    }
+
+    /// Row `row` times row `other`, in place.
+    fn multiply_row(&mut self, row: usize, other: usize) {
+        let (x2, z2, r2) = (self.x[other].clone(), self.z[other].clone(), self.r[other]);
+        self.r[row] = pauli_product(
+            (&mut self.x[row], &mut self.z[row], self.r[row]),
+            (&x2, &z2, r2),
+        );
+    }
+
+    /// Row `row` as `(x, z, phase)` with the operator acting as
+    /// `P|j⟩ = phase·(-1)^|j & z| |j ⊕ x⟩`, qubit `q` at bit `n - 1 - q`.
+    fn masks(&self, row: usize) -> (usize, usize, Complex<f64>) {
+        let bits = |v: &[bool]| v.iter().fold(0, |m, &b| (m << 1) | b as usize);
+        // Y = iXZ, so each Y contributes a factor i.
+        let phase = match (2 * self.r[row] as u32 + self.y_count(row)) % 4 {
+            0 => complex!(1.0, 0.0),
+            1 => complex!(0.0, 1.0),
+            2 => complex!(-1.0, 0.0),
+            _ => complex!(0.0, -1.0),
+        };
+        (bits(&self.x[row]), bits(&self.z[row]), phase)
+    }
+
+    fn y_count(&self, row: usize) -> u32 {
+        (0..self.num_qubits)
+            .filter(|&q| self.x[row][q] && self.z[row][q])
+            .count() as u32
+    }
+
+    fn h(&mut self, t: usize) {
+        for row in 0..2 * self.num_qubits {
+            self.r[row] ^= self.x[row][t] && self.z[row][t];
+            std::mem::swap(&mut self.x[row][t], &mut self.z[row][t]);
+        }
+    }
+
+    fn s(&mut self, t: usize) {
+        for row in 0..2 * self.num_qubits {
+            self.r[row] ^= self.x[row][t] && self.z[row][t];
+            self.z[row][t] ^= self.x[row][t];
+        }
+    }
+
+    fn sdg(&mut self, t: usize) {
+        self.s_power(t, 3);
+    }
+
+    fn s_power(&mut self, t: usize, power: usize) {
+        for _ in 0..power % 4 {
+            self.s(t);
+        }
+    }
+
+    fn x(&mut self, t: usize) {
+        for row in 0..2 * self.num_qubits {
+            self.r[row] ^= self.z[row][t];
+        }
+    }
+
+    fn y(&mut self, t: usize) {
+        for row in 0..2 * self.num_qubits {
+            self.r[row] ^= self.x[row][t] ^ self.z[row][t];
+        }
+    }
+
+    fn z(&mut self, t: usize) {
+        for row in 0..2 * self.num_qubits {
+            self.r[row] ^= self.x[row][t];
+        }
+    }
+
+    fn cnot(&mut self, c: usize, t: usize) {
+        for row in 0..2 * self.num_qubits {
+            let (xc, zc, xt, zt) = (
+                self.x[row][c],
+                self.z[row][c],
+                self.x[row][t],
+                self.z[row][t],
+            );
+            self.r[row] ^= xc && zt && (xt == zc);
+            self.x[row][t] = xt ^ xc;
+            self.z[row][c] = zc ^ zt;
+        }
+    }
+
+    fn cz(&mut self, a: usize, b: usize) {
+        self.h(b);
+        self.cnot(a, b);
+        self.h(b);
+    }
+
+    fn swap(&mut self, a: usize, b: usize) {
+        for row in 0..2 * self.num_qubits {
+            self.x[row].swap(a, b);
+            self.z[row].swap(a, b);
+        }
+    }
+}
+
+/// Clifford simulation of a state in the style of Aaronson and Gottesman's
+/// CHP: `|0…0⟩` after a Clifford circuit is the tableau's stabilisers'
+/// joint +1 eigenstate, and measuring a qubit updates the tableau in
+/// `O(n²)`. Circuits of H, S, CNOT, CZ, the Paulis and rotations at
+/// multiples of π/2 run in polynomial time on hundreds of qubits; any other
+/// gate is an error.
+///
+/// The tableau does not track the global phase, so `to_state` fixes it by
+/// convention. Bitstrings have qubit 0 first.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StabilizerState {
+    tableau: StabilizerTableau,
+}
+
+impl StabilizerState {
+    /// `|0…0⟩` on `num_qubits` qubits.
+    pub fn new(num_qubits: usize) -> Self {
+        StabilizerState {
+            tableau: StabilizerTableau::new(num_qubits),
+        }
+    }
+
+    /// The final state of `circuit`. Measurements, barriers and snapshots
+    /// are skipped.
+    pub fn from_circuit(circuit: &QuantumCircuit) -> Result<Self, String> {
+        let mut state = StabilizerState::new(circuit.num_qubits());
+        for op in try_defer_conditionals(circuit.operations())?.iter() {
+            state.apply(op)?;
+        }
+        Ok(state)
+    }
+
+    /// Applies `op`, or fails if it is not Clifford. Measurements are
+    /// skipped; use `measure` to collapse the state.
+    pub fn apply(&mut self, op: &GateOp) -> Result<(), String> {
+        if op.is_measurement() {
+            return Ok(());
+        }
+        self.tableau.apply(op)
+    }
+
+    pub fn num_qubits(&self) -> usize {
+        self.tableau.num_qubits
+    }
+
+    /// The tableau of the Clifford that prepared the state from `|0…0⟩`,
+    /// as far as measurements have left it.
+    pub fn tableau(&self) -> &StabilizerTableau {
+        &self.tableau
+    }
+
+    /// Measures `qubit` in the Z basis and collapses the state onto the
+    /// outcome, drawn from `rng` when it is not certain.
+    pub fn measure(&mut self, qubit: usize, rng: &mut impl Rng) -> bool {
+        self.measure_with(qubit, || rng.random::<bool>()).0
+    }
+
+    /// Runs `operations` as one shot: every measurement collapses the state
+    /// onto an outcome drawn from `rng` and stores it in its classical bit,
+    /// every conditional gate reads the bits stored so far, and every reset
+    /// measures its qubit and flips a 1 back. Returns the classical bits,
+    /// or fails on a non-Clifford gate.
+    pub(crate) fn run_shot(
+        &mut self,
+        operations: &[GateOp],
+        rng: &mut impl Rng,
+    ) -> Result<Vec<bool>, String> {
+        let mut bits = Vec::new();
+        for op in operations {
+            match op {
+                GateOp::Measure(qubit, bit) => {
+                    if *bit >= bits.len() {
+                        bits.resize(bit + 1, false);
+                    }
+                    bits[*bit] = self.measure(*qubit, rng);
+                }
+                GateOp::Reset(qubit) => {
+                    if self.measure(*qubit, rng) {
+                        self.apply(&GateOp::X(*qubit))?;
+                    }
+                }
+                GateOp::Conditional {
+                    op,
+                    classical_bit,
+                    value,
+                } => {
+                    if bits.get(*classical_bit).copied().unwrap_or(false) == *value {
+                        self.apply(op)?;
+                    }
+                }
+                _ => self.apply(op)?,
+            }
+        }
+        Ok(bits)
+    }
+
+    /// The outcome of measuring `qubit`, if it is certain.
+    pub fn certain_outcome(&self, qubit: usize) -> Option<bool> {
+        let n = self.num_qubits();
+        assert!(qubit < n, "qubit {} out of range for {} qubits", qubit, n);
+        if (n..2 * n).any(|row| self.tableau.x[row][qubit]) {
+            return None;
+        }
+        Some(self.deterministic_outcome(qubit))
+    }
+
+    /// `|⟨bits|ψ⟩|²`: zero or `2^-k`, where `k` of the measurements it
+    /// takes to reach `bits` are random.
+    pub fn probability(&self, bits: &[bool]) -> f64 {
+        let n = self.num_qubits();
+        assert_eq!(bits.len(), n, "expected {} bits", n);
+        let mut state = self.clone();
+        let mut probability = 1.0;
+        for (q, &bit) in bits.iter().enumerate() {
+            let (outcome, random) = state.measure_with(q, || bit);
+            if random {
+                probability *= 0.5;
+            } else if outcome != bit {
+                return 0.0;
+            }
+        }
+        probability
+    }
+
+    /// The full state vector, for registers small enough to hold one: the
+    /// projection of a basis state in the support onto the stabilisers'
+    /// +1 eigenspace, phased so the lowest such basis state has a positive
+    /// real amplitude.
+    pub fn to_state(&self) -> QuantumState {
+        let n = self.num_qubits();
+        assert!(
+            n < usize::BITS as usize,
+            "{} qubits is too many for a state vector",
+            n
+        );
+        let mut support = self.clone();
+        let basis = (0..n).fold(0usize, |index, q| {
+            (index << 1) | support.measure_with(q, || false).0 as usize
+        });
+
+        let mut amplitudes = vec![complex!(0.0, 0.0); 1 << n];
+        amplitudes[basis] = complex!(1.0, 0.0);
+        for row in n..2 * n {
+            let (x, z, phase) = self.tableau.masks(row);
+            let next: Vec<Complex<f64>> = (0..amplitudes.len())
+                .into_par_iter()
+                .map(|index| {
+                    // (I + P)/2, with P|j⟩ = phase·(-1)^|j & z| |j ⊕ x⟩.
+                    let source = index ^ x;
+                    let sign = if (source & z).count_ones() % 2 == 0 {
+                        phase
+                    } else {
+                        phase * complex!(-1.0, 0.0)
+                    };
+                    (amplitudes[index] + sign * amplitudes[source]) * complex!(0.5, 0.0)
+                })
+                .collect();
+            amplitudes = next;
+        }
+
+        let reference = amplitudes[basis];
+        let scale = reference.get_conjugate()
+            * complex!(1.0 / (reference.norm2().sqrt() * norm(&amplitudes)), 0.0);
+        QuantumState::new(amplitudes.into_iter().map(|a| a * scale).collect())
+    }
+
+    /// Draws `shots` samples of `circuit` by measuring a copy of its final
+    /// tableau for each shot, in `O(n³)` per shot and without a state
+    /// vector. Outcomes follow the bit order of `Counts`.
+    pub fn sample(
+        circuit: &QuantumCircuit,
+        shots: usize,
+        seed: Option<u64>,
+    ) -> Result<Counts, String> {
+        let n = circuit.num_qubits();
+        let seed = seed.unwrap_or_else(entropy_seed);
+        if shots::needs_shots(circuit.operations()) {
+            return Self::sample_shots(circuit, shots, seed);
+        }
+        let state = StabilizerState::from_circuit(circuit)?;
+        let measurements = circuit.measurements();
+        let mut measured: Vec<usize> = if measurements.is_empty() {
+            (0..n).collect()
+        } else {
+            measurements.iter().map(|&(q, _)| q).collect()
+        };
+        measured.sort_unstable();
+        measured.dedup();
+
+        let samples: Vec<Vec<bool>> = (0..shots)
+            .into_par_iter()
+            .map(|shot| {
+                let mut rng = StdRng::seed_from_u64(stream_seed(seed, shot as u64));
+                let mut state = state.clone();
+                let mut bits = vec![false; n];
+                for &q in &measured {
+                    bits[q] = state.measure(q, &mut rng);
+                }
+                bits
+            })
+            .collect();
+
+        let mut outcomes: BTreeMap<String, usize> = BTreeMap::new();
+        for bits in &samples {
+            let key: String = if measurements.is_empty() {
+                bits.iter().map(|&b| if b { '1' } else { '0' }).collect()
+            } else {
+                let mut key = vec!['0'; circuit.num_classical()];
+                for &(q, c) in &measurements {
+                    key[c] = if bits[q] { '1' } else { '0' };
+                }
+                key.into_iter().collect()
+            };
+            *outcomes.entry(key).or_insert(0) += 1;
+        }
+        Ok(Counts::from_outcomes(outcomes))
+    }
+
+    /// `sample` for circuits that depend on mid-circuit outcomes: each shot
+    /// runs the whole circuit through `run_shot`.
+    fn sample_shots(circuit: &QuantumCircuit, shots: usize, seed: u64) -> Result<Counts, String> {
+        let n = circuit.num_qubits();
+        let measures = !circuit.measurements().is_empty();
+        let samples: Vec<String> = (0..shots)
+            .into_par_iter()
+            .map(|shot| {
+                let mut rng = StdRng::seed_from_u64(stream_seed(seed, shot as u64));
+                let mut state = StabilizerState::new(n);
+                let mut bits = state.run_shot(circuit.operations(), &mut rng)?;
+                if measures {
+                    bits.resize(circuit.num_classical(), false);
+                } else {
+                    bits = (0..n).map(|q| state.measure(q, &mut rng)).collect();
+                }
+                Ok(bits.iter().map(|&b| if b { '1' } else { '0' }).collect())
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Counts::from_outcomes(
+            samples.into_iter().map(|key| (key, 1)),
+        ))
+    }
+
+    /// Measures `qubit`, taking the outcome from `choose` if it is random.
+    /// Returns the outcome and whether it was random.
+    fn measure_with(&mut self, qubit: usize, choose: impl FnOnce() -> bool) -> (bool, bool) {
+        let n = self.num_qubits();
+        assert!(qubit < n, "qubit {} out of range for {} qubits", qubit, n);
+        let tableau = &mut self.tableau;
+        let Some(p) = (n..2 * n).find(|&row| tableau.x[row][qubit]) else {
+            return (self.deterministic_outcome(qubit), false);
+        };
+
+        // Every other row anticommuting with Z_qubit absorbs row p, the
+        // destabiliser paired with p becomes p, and p becomes ±Z_qubit.
+        for row in 0..2 * n {
+            if row != p && tableau.x[row][qubit] {
+                tableau.multiply_row(row, p);
+            }
+        }
+        let outcome = choose();
+        tableau.x[p - n] = tableau.x[p].clone();
+        tableau.z[p - n] = tableau.z[p].clone();
+        tableau.r[p - n] = tableau.r[p];
+        tableau.x[p] = vec![false; n];
+        tableau.z[p] = vec![false; n];
+        tableau.z[p][qubit] = true;
+        tableau.r[p] = outcome;
+        (outcome, true)
+    }
+
+    /// The outcome of measuring `qubit` when no stabiliser anticommutes
+    /// with `Z_qubit`: the sign of `Z_qubit` as a product of stabilisers,
+    /// picked out by the destabilisers that anticommute with it.
+    fn deterministic_outcome(&self, qubit: usize) -> bool {
+        let n = self.num_qubits();
+        let tableau = &self.tableau;
+        let mut x = vec![false; n];
+        let mut z = vec![false; n];
+        let mut r = false;
+        for row in 0..n {
+            if tableau.x[row][qubit] {
+                let stabiliser = n + row;
+                r = pauli_product(
+                    (&mut x, &mut z, r),
+                    (
+                        &tableau.x[stabiliser],
+                        &tableau.z[stabiliser],
+                        tableau.r[stabiliser],
+                    ),
+                );
+            }
+        }
+        r
+    }
+}
+
+/// Runs Clifford circuits through `StabilizerState` and returns the state
+/// vector up to global phase. Panics on non-Clifford gates.
+impl SimulatorBackend for StabilizerState {
+    fn name(&self) -> String {
+        "StabilizerState".to_string()
+    }
+
+    fn init(&mut self, num_qubits: usize) {
+        *self = StabilizerState::new(num_qubits);
+    }
+
+    fn apply(&mut self, op: &GateOp) {
+        if let Err(err) = StabilizerState::apply(self, op) {
+            panic!("StabilizerState: {}", err);
+        }
+    }
+
+    fn finalize(&mut self) -> QuantumState {
+        self.to_state()
+    }
+}
+
+/// Multiplies the Pauli string `(x, z, r)` by `(x2, z2, r2)` and returns
+/// the sign of the product (`rowsum` in Aaronson and Gottesman). The sign
+/// is only meaningful when the two commute.
+fn pauli_product(
+    (x, z, r): (&mut [bool], &mut [bool], bool),
+    (x2, z2, r2): (&[bool], &[bool], bool),
+) -> bool {
+    // Exponent of i picked up by each single-qubit product.
+    let mut exponent = 2 * r as i32 + 2 * r2 as i32;
+    for q in 0..x.len() {
+        exponent += match (x2[q], z2[q]) {
+            (false, false) => 0,
+            (true, true) => z[q] as i32 - x[q] as i32,
+            (true, false) => z[q] as i32 * (2 * x[q] as i32 - 1),
+            (false, true) => x[q] as i32 * (1 - 2 * z[q] as i32),
+        };
+        x[q] ^= x2[q];
+        z[q] ^= z2[q];
+    }
+    exponent.rem_euclid(4) == 2
+}
+
+fn norm(amplitudes: &[Complex<f64>]) -> f64 {
+    amplitudes.iter().map(|a| a.norm2()).sum::<f64>().sqrt()
+}
+
+/// `theta` as a whole number of quarter turns, or an error for `op`.
+fn quarter_turns(op: &GateOp, theta: f64) -> Result<usize, String> {
+    let quarters = theta / FRAC_PI_2;
+    if (quarters - quarters.round()).abs() > ANGLE_TOLERANCE {
+        return Err(format!("{}({}) is not a Clifford gate", op.name(), theta));
+    }
+    Ok(quarters.round().rem_euclid(4.0) as usize)
+}
+
+impl fmt::Display for StabilizerTableau {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for q in 0..self.num_qubits {
+            writeln!(f, "X{} → {}", q, self.destabiliser(q))?;
+        }
+        for q in 0..self.num_qubits {
+            writeln!(f, "Z{} → {}", q, self.stabiliser(q))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,123 @@
+use crate::{complex, QuantumState, Vector};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// A state file is `MAGIC`, `VERSION`, a flags byte and the number of
+/// qubits as a little-endian `u32`, then the 2ⁿ amplitudes as little-endian
+/// `f64` pairs, real part first, zlib-compressed if the flags say so.
+const MAGIC: &[u8; 4] = b"PSIS";
+const VERSION: u8 = 1;
+const COMPRESSED: u8 = 1;
+
+/// Widest state a file may claim, so that a corrupt header fails instead
+/// of asking for an absurd allocation.
+const MAX_QUBITS: u32 = 40;
+
+impl QuantumState {
+    /// Writes the state to `path`, 16 bytes per amplitude, for `load` to
+    /// read back bit for bit.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write_header(&mut out, 0)?;
+        self.write_amplitudes(&mut out)?;
+        out.flush()
+    }
+
+    /// As `save`, with the amplitudes zlib-compressed: far smaller for
+    /// states with many zero amplitudes, slower to write for dense ones.
+    #[cfg(feature = "compression")]
+    pub fn save_compressed(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        use flate2::write::ZlibEncoder;
+        use flate2::Compression;
+
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write_header(&mut out, COMPRESSED)?;
+        let mut encoder = ZlibEncoder::new(out, Compression::default());
+        self.write_amplitudes(&mut encoder)?;
+        encoder.finish()?.flush()
+    }
+
+    /// Reads a state written by `save` or `save_compressed`. Fails with
+    /// `InvalidData` for any other file, and with `Unsupported` for a
+    /// compressed one without the `compression` feature.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<QuantumState> {
+        let mut input = BufReader::new(File::open(path)?);
+        let mut header = [0u8; 10];
+        input
+            .read_exact(&mut header)
+            .map_err(|_| invalid("truncated header"))?;
+        if &header[..4] != MAGIC {
+            return Err(invalid("not a psi state file"));
+        }
+        if header[4] != VERSION {
+            return Err(invalid(&format!("unknown version {}", header[4])));
+        }
+        let flags = header[5];
+        if flags & !COMPRESSED != 0 {
+            return Err(invalid(&format!("unknown flags {:#04x}", flags)));
+        }
+        let num_qubits = u32::from_le_bytes(header[6..].try_into().unwrap());
+        if num_qubits > MAX_QUBITS {
+            return Err(invalid(&format!("{} qubits", num_qubits)));
+        }
+        let dim = 1usize << num_qubits;
+        if flags & COMPRESSED == 0 {
+            return read_amplitudes(input, dim);
+        }
+        #[cfg(feature = "compression")]
+        {
+            read_amplitudes(flate2::read::ZlibDecoder::new(input), dim)
+        }
+        #[cfg(not(feature = "compression"))]
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "compressed state file; enable the `compression` feature of libpsi-core",
+        ))
+    }
+
+    fn write_header(&self, out: &mut impl Write, flags: u8) -> io::Result<()> {
+        let dim = self.size();
+        if !dim.is_power_of_two() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} amplitudes is not a number of qubits", dim),
+            ));
+        }
+        out.write_all(MAGIC)?;
+        out.write_all(&[VERSION, flags])?;
+        out.write_all(&dim.trailing_zeros().to_le_bytes())
+    }
+
+    fn write_amplitudes(&self, out: &mut impl Write) -> io::Result<()> {
+        for i in 0..self.size() {
+            let amplitude = self.get(i);
+            out.write_all(&amplitude.real.to_le_bytes())?;
+            out.write_all(&amplitude.imaginary.to_le_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+fn read_amplitudes(mut input: impl Read, dim: usize) -> io::Result<QuantumState> {
+    // Grown as read, so a corrupt header runs into the end of the file
+    // before its size is ever allocated.
+    let mut amplitudes = Vec::with_capacity(dim.min(1 << 20));
+    let mut bytes = [0u8; 16];
+    for _ in 0..dim {
+        input
+            .read_exact(&mut bytes)
+            .map_err(|_| invalid(&format!("fewer than {} amplitudes", dim)))?;
+        let real = f64::from_le_bytes(bytes[..8].try_into().unwrap());
+        let imaginary = f64::from_le_bytes(bytes[8..].try_into().unwrap());
+        amplitudes.push(complex!(real, imaginary));
+    }
+    if input.read(&mut bytes)? != 0 {
+        return Err(invalid(&format!("more than {} amplitudes", dim)));
+    }
+    Ok(QuantumState::new(amplitudes))
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
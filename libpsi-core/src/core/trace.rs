@@ -0,0 +1,141 @@
+use super::circuit::defer_conditionals;
+use super::runtime::{apply_kernel_in_place, initial_state};
+use super::{GateOp, QuantumState, Runtime};
+use crate::{format_dirac, Complex, Vector};
+use core::fmt;
+
+/// The state right after one operation of a traced run.
+#[derive(Clone)]
+pub struct TraceStep {
+    pub index: usize,
+    pub op: GateOp,
+    pub amplitudes: Vec<Complex<f64>>,
+}
+
+impl TraceStep {
+    pub fn state(&self) -> QuantumState {
+        QuantumState::new(self.amplitudes.clone())
+    }
+}
+
+/// The state after every operation of a circuit. Displays one line per
+/// operation in Dirac notation, leaving out basis states whose probability
+/// is below `cutoff`.
+///
+/// Operations are applied one at a time without fusion, so every step
+/// matches the circuit as written.
+pub struct StateTrace {
+    num_qubits: usize,
+    start: Vec<Complex<f64>>,
+    cutoff: f64,
+    steps: Vec<TraceStep>,
+}
+
+impl StateTrace {
+    pub fn record(num_qubits: usize, operations: &[GateOp], cutoff: f64) -> Self {
+        Self::record_from(initial_state(num_qubits), num_qubits, operations, cutoff)
+    }
+
+    /// `record` from `start` instead of `|0…0⟩`.
+    pub(crate) fn record_from(
+        start: Vec<Complex<f64>>,
+        num_qubits: usize,
+        operations: &[GateOp],
+        cutoff: f64,
+    ) -> Self {
+        let mut state = start.clone();
+        let deferred = defer_conditionals(operations);
+        let steps = operations
+            .iter()
+            .zip(deferred.iter())
+            .enumerate()
+            .map(|(index, (op, gate))| {
+                if let Some(kernel) = Runtime::op_to_kernel(gate) {
+                    apply_kernel_in_place(&mut state, &kernel, num_qubits);
+                }
+                TraceStep {
+                    index,
+                    op: op.clone(),
+                    amplitudes: state.clone(),
+                }
+            })
+            .collect();
+
+        StateTrace {
+            num_qubits,
+            start,
+            cutoff,
+            steps,
+        }
+    }
+
+    /// A trace of steps recorded elsewhere, such as along one shot.
+    pub(crate) fn from_steps(
+        start: Vec<Complex<f64>>,
+        num_qubits: usize,
+        cutoff: f64,
+        steps: Vec<TraceStep>,
+    ) -> Self {
+        StateTrace {
+            num_qubits,
+            start,
+            cutoff,
+            steps,
+        }
+    }
+
+    pub fn steps(&self) -> &[TraceStep] {
+        &self.steps
+    }
+
+    pub fn num_qubits(&self) -> usize {
+        self.num_qubits
+    }
+
+    pub fn cutoff(&self) -> f64 {
+        self.cutoff
+    }
+
+    pub fn final_state(&self) -> QuantumState {
+        match self.steps.last() {
+            Some(step) => step.state(),
+            None => QuantumState::new(self.start.clone()),
+        }
+    }
+}
+
+impl fmt::Display for StateTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let labels: Vec<String> = self
+            .steps
+            .iter()
+            .map(|step| {
+                format!(
+                    "{}: {} on {:?}",
+                    step.index,
+                    step.op.name(),
+                    step.op.quantum_targets()
+                )
+            })
+            .collect();
+        let width = labels.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+
+        writeln!(
+            f,
+            "  {:<width$}  {}",
+            "start",
+            format_dirac(&self.start, self.num_qubits, self.cutoff),
+            width = width
+        )?;
+        for (label, step) in labels.iter().zip(&self.steps) {
+            writeln!(
+                f,
+                "  {:<width$}  {}",
+                label,
+                format_dirac(&step.amplitudes, self.num_qubits, self.cutoff),
+                width = width
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,278 @@
+use super::circuit::defer_conditionals;
+use super::counts::entropy_seed;
+use super::{Counts, GateOp, Kernel, QuantumCircuit, QuantumState, Runtime, SimulatorBackend};
+use crate::{complex, Complex, Vector};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::{BTreeMap, HashMap};
+
+/// Amplitudes smaller than this after a gate are exact zeros up to
+/// rounding and are never stored.
+const ZERO: f64 = 1e-30;
+
+/// Approximate simulation that keeps only the significant amplitudes: the
+/// state is stored sparsely, and after every layer (a run of gates on
+/// disjoint qubits) each basis state with probability below `threshold`
+/// is dropped and the rest renormalised.
+///
+/// Dropping probability `ε` leaves a state with fidelity `1 − ε` to the one
+/// before, and `fidelity` multiplies these up as an estimate of the overlap
+/// with the exact result. A threshold of 0 keeps everything, so memory
+/// follows the number of nonzero amplitudes; larger thresholds trade
+/// accuracy for memory on states that concentrate on few basis states.
+/// Pruning happens only between layers, so a layer of `k` superposing gates
+/// can still multiply the support by `2^k` before it is cut back. Bitstrings
+/// have qubit 0 first.
+#[derive(Clone, Debug)]
+pub struct TruncatedState {
+    num_qubits: usize,
+    threshold: f64,
+    amplitudes: HashMap<usize, Complex<f64>>,
+    fidelity: f64,
+    /// Qubits touched since the last truncation.
+    layer: Vec<bool>,
+}
+
+impl TruncatedState {
+    /// `|0…0⟩` on `num_qubits` qubits, pruning basis states with
+    /// probability below `threshold`.
+    pub fn new(num_qubits: usize, threshold: f64) -> Self {
+        assert!(
+            num_qubits < usize::BITS as usize,
+            "{} qubits is too many to index",
+            num_qubits
+        );
+        assert!(
+            (0.0..1.0).contains(&threshold),
+            "truncation threshold must be in [0, 1)"
+        );
+        TruncatedState {
+            num_qubits,
+            threshold,
+            amplitudes: HashMap::from([(0, complex!(1.0, 0.0))]),
+            fidelity: 1.0,
+            layer: vec![false; num_qubits],
+        }
+    }
+
+    /// The final state of `circuit`, truncated after every layer.
+    pub fn from_circuit(circuit: &QuantumCircuit, threshold: f64) -> Self {
+        let mut state = TruncatedState::new(circuit.num_qubits(), threshold);
+        for op in defer_conditionals(circuit.operations()).iter() {
+            state.apply(op);
+        }
+        state.truncate();
+        state
+    }
+
+    /// Applies `op`, first truncating if it starts a new layer. Measurements,
+    /// barriers and snapshots are skipped.
+    pub fn apply(&mut self, op: &GateOp) {
+        let Some(kernel) = Runtime::op_to_kernel(op) else {
+            return;
+        };
+        if kernel.targets.iter().any(|&q| self.layer[q]) {
+            self.truncate();
+        }
+        for &q in &kernel.targets {
+            self.layer[q] = true;
+        }
+        self.apply_kernel(&kernel);
+    }
+
+    /// Drops the basis states below the threshold and renormalises,
+    /// returning the probability removed.
+    pub fn truncate(&mut self) -> f64 {
+        self.layer.fill(false);
+        if self.threshold == 0.0 {
+            return 0.0;
+        }
+        let total: f64 = self.amplitudes.values().map(|a| a.norm2()).sum();
+        let threshold = self.threshold * total;
+        self.amplitudes.retain(|_, a| a.norm2() >= threshold);
+        let kept: f64 = self.amplitudes.values().map(|a| a.norm2()).sum();
+        assert!(
+            kept > 0.0,
+            "truncation threshold {} removed the whole state",
+            self.threshold
+        );
+        let scale = complex!((total / kept).sqrt(), 0.0);
+        for a in self.amplitudes.values_mut() {
+            *a *= scale;
+        }
+        let lost = 1.0 - kept / total;
+        self.fidelity *= 1.0 - lost;
+        lost
+    }
+
+    pub fn num_qubits(&self) -> usize {
+        self.num_qubits
+    }
+
+    pub fn threshold(&self) -> f64 {
+        self.threshold
+    }
+
+    /// Estimated fidelity with the exact state: the product of `1 − ε` over
+    /// every truncation that removed probability `ε`.
+    pub fn fidelity(&self) -> f64 {
+        self.fidelity
+    }
+
+    /// Basis states currently stored.
+    pub fn num_amplitudes(&self) -> usize {
+        self.amplitudes.len()
+    }
+
+    /// `⟨bits|ψ⟩`, zero for pruned basis states.
+    pub fn amplitude(&self, bits: &[bool]) -> Complex<f64> {
+        assert_eq!(
+            bits.len(),
+            self.num_qubits,
+            "expected {} bits",
+            self.num_qubits
+        );
+        let index = bits.iter().fold(0, |index, &b| (index << 1) | b as usize);
+        self.amplitudes
+            .get(&index)
+            .copied()
+            .unwrap_or(complex!(0.0, 0.0))
+    }
+
+    pub fn probability(&self, bits: &[bool]) -> f64 {
+        self.amplitude(bits).norm2()
+    }
+
+    /// The full state vector, for registers small enough to hold one.
+    pub fn to_state(&self) -> QuantumState {
+        let mut amplitudes = vec![complex!(0.0, 0.0); 1 << self.num_qubits];
+        for (&index, &a) in &self.amplitudes {
+            amplitudes[index] = a;
+        }
+        QuantumState::new(amplitudes)
+    }
+
+    /// Draws `shots` samples of `circuit` from its truncated state, without
+    /// a dense state vector. Outcomes follow the bit order of `Counts`.
+    pub fn sample(
+        circuit: &QuantumCircuit,
+        threshold: f64,
+        shots: usize,
+        seed: Option<u64>,
+    ) -> Counts {
+        let state = TruncatedState::from_circuit(circuit, threshold);
+        let n = state.num_qubits;
+        let mut entries: Vec<(usize, f64)> = state
+            .amplitudes
+            .iter()
+            .map(|(&index, a)| (index, a.norm2()))
+            .collect();
+        entries.sort_unstable_by_key(|&(index, _)| index);
+        let cumulative: Vec<f64> = entries
+            .iter()
+            .scan(0.0, |total, &(_, p)| {
+                *total += p;
+                Some(*total)
+            })
+            .collect();
+        let total = cumulative.last().copied().unwrap_or(0.0);
+
+        let mut rng = StdRng::seed_from_u64(seed.unwrap_or_else(entropy_seed));
+        let mut by_index: BTreeMap<usize, usize> = BTreeMap::new();
+        for _ in 0..shots {
+            let r = rng.random::<f64>() * total;
+            let i = cumulative
+                .partition_point(|&c| c <= r)
+                .min(cumulative.len() - 1);
+            *by_index.entry(entries[i].0).or_insert(0) += 1;
+        }
+
+        let measurements = circuit.measurements();
+        let mut outcomes: BTreeMap<String, usize> = BTreeMap::new();
+        for (index, count) in by_index {
+            let bit = |q: usize| {
+                if (index >> (n - 1 - q)) & 1 == 1 {
+                    '1'
+                } else {
+                    '0'
+                }
+            };
+            let key: String = if measurements.is_empty() {
+                (0..n).map(bit).collect()
+            } else {
+                let mut key = vec!['0'; circuit.num_classical()];
+                for &(q, c) in &measurements {
+                    key[c] = bit(q);
+                }
+                key.into_iter().collect()
+            };
+            *outcomes.entry(key).or_insert(0) += count;
+        }
+        Counts::from_outcomes(outcomes)
+    }
+
+    /// Applies `kernel` to the stored amplitudes only: they are grouped by
+    /// their bits outside the targets, and each group is multiplied by the
+    /// gate matrix.
+    fn apply_kernel(&mut self, kernel: &Kernel) {
+        let n = self.num_qubits;
+        let width = kernel.targets.len();
+        let bits: Vec<usize> = kernel.targets.iter().map(|&t| 1 << (n - 1 - t)).collect();
+        let mask: usize = bits.iter().fold(0, |m, b| m | b);
+        // Index of `bits[k]` in the gate's basis, target 0 most significant.
+        let spread = |local: usize| {
+            bits.iter()
+                .enumerate()
+                .filter(|&(k, _)| (local >> (width - 1 - k)) & 1 == 1)
+                .fold(0, |index, (_, b)| index | b)
+        };
+        let local = |index: usize| {
+            bits.iter()
+                .fold(0, |l, &b| (l << 1) | (index & b != 0) as usize)
+        };
+
+        let dim = 1 << width;
+        let mut blocks: HashMap<usize, Vec<Complex<f64>>> = HashMap::new();
+        for (&index, &a) in &self.amplitudes {
+            blocks
+                .entry(index & !mask)
+                .or_insert_with(|| vec![complex!(0.0, 0.0); dim])[local(index)] = a;
+        }
+
+        let matrix = &kernel.matrix.data;
+        let mut next = HashMap::with_capacity(self.amplitudes.len());
+        for (base, input) in blocks {
+            for row in 0..dim {
+                let mut sum = complex!(0.0, 0.0);
+                for (col, &x) in input.iter().enumerate() {
+                    sum += matrix[row * dim + col] * x;
+                }
+                if sum.norm2() > ZERO {
+                    next.insert(base | spread(row), sum);
+                }
+            }
+        }
+        self.amplitudes = next;
+    }
+}
+
+/// Runs circuits through `TruncatedState` and returns the (renormalised)
+/// truncated state.
+impl SimulatorBackend for TruncatedState {
+    fn name(&self) -> String {
+        format!("TruncatedState(threshold={})", self.threshold)
+    }
+
+    fn init(&mut self, num_qubits: usize) {
+        *self = TruncatedState::new(num_qubits, self.threshold);
+    }
+
+    fn apply(&mut self, op: &GateOp) {
+        TruncatedState::apply(self, op);
+    }
+
+    fn finalize(&mut self) -> QuantumState {
+        self.truncate();
+        self.to_state()
+    }
+}
@@ -1,3 +1,12 @@
+/// Enters a `tracing` span for the rest of the enclosing block when the
+/// `tracing` feature is enabled, and expands to nothing otherwise.
+macro_rules! span {
+    ($level:ident, $($args:tt)*) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::span!(tracing::Level::$level, $($args)*).entered();
+    };
+}
+
 pub mod core;
 pub mod maths;
 
@@ -8,11 +17,33 @@ pub use maths::numeric::*;
 pub use maths::simd::*;
 pub use maths::vector::*;
 
+pub use core::backend::*;
 pub use core::circuit::*;
 pub use core::classical_components::*;
+pub use core::counts::*;
 pub use core::custom_gate::*;
+pub use core::debugger::*;
+pub use core::decoder::*;
+pub use core::evolution::*;
+pub use core::extended_stabilizer::*;
 pub use core::gates;
+#[cfg(feature = "gpu")]
+pub use core::gpu::*;
+pub use core::gradient::*;
+pub use core::job::*;
 pub use core::kernel::*;
+pub use core::library;
+pub use core::mitigation::*;
 pub use core::noise::*;
+pub use core::operators::*;
+pub use core::parameter::*;
+pub use core::product_state::*;
+pub use core::qec::*;
 pub use core::quantum_components::*;
 pub use core::runtime::*;
+pub use core::schedule::*;
+pub use core::snapshot::*;
+pub use core::stabilizer::*;
+pub use core::trace::*;
+pub use core::trajectory::*;
+pub use core::truncated_state::*;
@@ -53,7 +53,11 @@ macro_rules! impl_ops {
     };
 }
 
+/// Laid out as `[real, imaginary]`, so that the SIMD kernels can load a
+/// run of amplitudes as interleaved floats.
 #[derive(Copy, Clone, PartialOrd, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
 pub struct Complex<T: Float> {
     pub real: T,
     pub imaginary: T,
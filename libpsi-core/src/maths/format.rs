@@ -1,7 +1,7 @@
 use crate::Complex;
+use std::f64::consts::SQRT_2;
 
 const EPSILON: f64 = 1e-10;
-const SQRT_2: f64 = 1.4142135623730951;
 const INV_SQRT_2: f64 = 0.7071067811865475;
 const INV_SQRT_8: f64 = 0.3535533905932738;
 const INV_SQRT_32: f64 = 0.1767766952966369;
@@ -139,3 +139,45 @@ pub fn format_probability(p: f64) -> String {
 
     format!("{:.4}", p)
 }
+
+/// Writes a state vector as a sum of kets, e.g. `¹⁄√2|00⟩ + ¹⁄√2|11⟩`,
+/// leaving out basis states whose probability is below `cutoff`.
+pub fn format_dirac(amplitudes: &[Complex<f64>], num_qubits: usize, cutoff: f64) -> String {
+    let mut out = String::new();
+
+    for (i, amp) in amplitudes.iter().enumerate() {
+        if amp.norm2() < cutoff.max(EPSILON) {
+            continue;
+        }
+
+        let mut coeff = format_amplitude(amp);
+        let negative = coeff.starts_with('-');
+        if !approx_eq(amp.real, 0.0) && !approx_eq(amp.imaginary, 0.0) {
+            coeff = format!("({})", coeff);
+        } else if negative {
+            coeff.remove(0);
+        }
+        if coeff == "1" {
+            coeff.clear();
+        }
+
+        let sign = match (out.is_empty(), negative && !coeff.starts_with('(')) {
+            (true, true) => "-",
+            (true, false) => "",
+            (false, true) => " - ",
+            (false, false) => " + ",
+        };
+        out.push_str(&format!(
+            "{}{}|{:0width$b}⟩",
+            sign,
+            coeff,
+            i,
+            width = num_qubits
+        ));
+    }
+
+    if out.is_empty() {
+        out.push('0');
+    }
+    out
+}
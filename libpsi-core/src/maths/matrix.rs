@@ -46,6 +46,7 @@ macro_rules! impl_matrix_ops {
 }
 
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Matrix<T: Float> {
     pub data: Vec<T>,
     pub rows: usize,
@@ -79,7 +80,7 @@ impl<T: Float> Matrix<T> {
             for j in 0..other.cols {
                 let mut sum = T::zero();
                 for k in 0..self.cols {
-                    sum = sum + (self.get(i, k) * other.get(k, j));
+                    sum += self.get(i, k) * other.get(k, j);
                 }
                 result.set(i, j, sum);
             }
@@ -100,7 +101,7 @@ impl<T: Float> Matrix<T> {
                     for l in 0..other.cols {
                         let result_row = i * other.rows + k;
                         let result_col = j * other.cols + l;
-                        result.set(result_row, result_col, self_val.clone() * other.get(k, l));
+                        result.set(result_row, result_col, self_val * other.get(k, l));
                     }
                 }
             }
@@ -301,7 +302,7 @@ impl<T: Float + fmt::Display> fmt::Display for Matrix<T> {
             }
 
             if i != self.rows - 1 {
-                write!(f, "\n")?;
+                writeln!(f)?;
             }
         }
 
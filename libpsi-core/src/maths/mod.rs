@@ -2,6 +2,7 @@ pub mod complex;
 pub mod format;
 pub mod matrix;
 pub mod numeric;
+pub(crate) mod parallel;
 pub mod simd;
 pub mod vector;
 pub mod vector_ops;
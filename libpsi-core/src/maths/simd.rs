@@ -1,4 +1,5 @@
-use crate::{complex, Complex};
+use crate::{complex, Complex, Float};
+use std::borrow::Cow;
 
 #[cfg(target_arch = "x86_64")]
 use std::arch::x86_64::*;
@@ -454,7 +455,7 @@ pub fn apply_single_qubit_gate_simd_parallel(
     target: usize,
     num_qubits: usize,
 ) {
-    use rayon::prelude::*;
+    use super::parallel::*;
 
     let target_bit = num_qubits - 1 - target;
     let step = 1 << target_bit;
@@ -504,6 +505,594 @@ pub fn apply_single_qubit_gate_simd_parallel(
     }
 }
 
+/// A real type the runtimes hold amplitudes in: `f64`, or `f32` under
+/// `Precision::F32`. Gate matrices stay `f64` and are rounded as applied.
+pub(crate) trait StateFloat: Float + Send + Sync {
+    fn from_f64(value: f64) -> Self;
+
+    fn to_f64(self) -> f64;
+
+    fn apply_single_qubit_gate(
+        state: &mut [Complex<Self>],
+        gate: &[[Complex<f64>; 2]; 2],
+        target: usize,
+        num_qubits: usize,
+        parallel: bool,
+    );
+
+    /// The state as `f64` amplitudes, borrowed when it already is.
+    fn widen(state: &[Complex<Self>]) -> Cow<'_, [Complex<f64>]>;
+
+    fn into_widened(state: Vec<Complex<Self>>) -> Vec<Complex<f64>>;
+
+    fn apply_two_qubit_gate(
+        state: &mut [Complex<Self>],
+        gate: &[[Complex<f64>; 4]; 4],
+        targets: [usize; 2],
+        num_qubits: usize,
+        parallel: bool,
+    ) {
+        apply_two_qubit(state, gate, targets, num_qubits, parallel);
+    }
+
+    /// The two-qubit kernel on one group of runs, see `for_each_quad`.
+    fn mix_quads(quad: [&mut [Complex<Self>]; 4], gate: &[[Complex<Self>; 4]; 4]);
+
+    fn scale_run(run: &mut [Complex<Self>], phase: Complex<Self>);
+
+    fn from_complex(value: Complex<f64>) -> Complex<Self> {
+        Complex::new(Self::from_f64(value.real), Self::from_f64(value.imaginary))
+    }
+
+    fn to_complex(value: Complex<Self>) -> Complex<f64> {
+        complex!(value.real.to_f64(), value.imaginary.to_f64())
+    }
+}
+
+impl StateFloat for f64 {
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+
+    fn to_f64(self) -> f64 {
+        self
+    }
+
+    fn widen(state: &[Complex<f64>]) -> Cow<'_, [Complex<f64>]> {
+        Cow::Borrowed(state)
+    }
+
+    fn into_widened(state: Vec<Complex<f64>>) -> Vec<Complex<f64>> {
+        state
+    }
+
+    fn apply_single_qubit_gate(
+        state: &mut [Complex<f64>],
+        gate: &[[Complex<f64>; 2]; 2],
+        target: usize,
+        num_qubits: usize,
+        parallel: bool,
+    ) {
+        if parallel {
+            apply_single_qubit_gate_simd_parallel(state, gate, target, num_qubits);
+        } else {
+            apply_single_qubit_gate_simd(state, gate, target, num_qubits);
+        }
+    }
+
+    fn mix_quads(quad: [&mut [Complex<f64>]; 4], gate: &[[Complex<f64>; 4]; 4]) {
+        mix_quads_f64(quad, gate);
+    }
+
+    fn scale_run(run: &mut [Complex<f64>], phase: Complex<f64>) {
+        scale_run_f64(run, phase);
+    }
+}
+
+impl StateFloat for f32 {
+    fn from_f64(value: f64) -> Self {
+        value as f32
+    }
+
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn widen(state: &[Complex<f32>]) -> Cow<'_, [Complex<f64>]> {
+        Cow::Owned(state.iter().copied().map(f32::to_complex).collect())
+    }
+
+    fn into_widened(state: Vec<Complex<f32>>) -> Vec<Complex<f64>> {
+        f32::widen(&state).into_owned()
+    }
+
+    fn apply_single_qubit_gate(
+        state: &mut [Complex<f32>],
+        gate: &[[Complex<f64>; 2]; 2],
+        target: usize,
+        num_qubits: usize,
+        parallel: bool,
+    ) {
+        let gate = gate.map(|row| row.map(f32::from_complex));
+        if parallel {
+            apply_single_qubit_gate_simd_f32_parallel(state, &gate, target, num_qubits);
+        } else {
+            apply_single_qubit_gate_simd_f32(state, &gate, target, num_qubits);
+        }
+    }
+
+    fn mix_quads(quad: [&mut [Complex<f32>]; 4], gate: &[[Complex<f32>; 4]; 4]) {
+        mix_quads_f32(quad, gate);
+    }
+
+    fn scale_run(run: &mut [Complex<f32>], phase: Complex<f32>) {
+        scale_run_f32(run, phase);
+    }
+}
+
+/// Amplitudes per parallel task in the single-precision kernels.
+const F32_CHUNK: usize = 1 << 14;
+
+/// `gate` on `target` of a single-precision state. Contiguous runs of
+/// amplitudes are rotated eight floats at a time with AVX2, twice the
+/// amplitudes per instruction of the `f64` kernels.
+pub fn apply_single_qubit_gate_simd_f32(
+    state: &mut [Complex<f32>],
+    gate: &[[Complex<f32>; 2]; 2],
+    target: usize,
+    num_qubits: usize,
+) {
+    let step = 1 << (num_qubits - 1 - target);
+    for block in state.chunks_mut(2 * step) {
+        let (low, high) = block.split_at_mut(step);
+        rotate_pairs_f32(low, high, gate);
+    }
+}
+
+pub fn apply_single_qubit_gate_simd_f32_parallel(
+    state: &mut [Complex<f32>],
+    gate: &[[Complex<f32>; 2]; 2],
+    target: usize,
+    num_qubits: usize,
+) {
+    use super::parallel::*;
+
+    let step = 1 << (num_qubits - 1 - target);
+    if 2 * step >= F32_CHUNK {
+        // Few blocks, each large: split the pairs within a block.
+        for block in state.chunks_mut(2 * step) {
+            let (low, high) = block.split_at_mut(step);
+            low.par_chunks_mut(F32_CHUNK / 2)
+                .zip(high.par_chunks_mut(F32_CHUNK / 2))
+                .for_each(|(low, high)| rotate_pairs_f32(low, high, gate));
+        }
+    } else {
+        state.par_chunks_mut(F32_CHUNK).for_each(|chunk| {
+            for block in chunk.chunks_mut(2 * step) {
+                let (low, high) = block.split_at_mut(step);
+                rotate_pairs_f32(low, high, gate);
+            }
+        });
+    }
+}
+
+/// `(low[k], high[k]) ← gate · (low[k], high[k])` for every `k`.
+fn rotate_pairs_f32(
+    low: &mut [Complex<f32>],
+    high: &mut [Complex<f32>],
+    gate: &[[Complex<f32>; 2]; 2],
+) {
+    #[cfg(target_arch = "x86_64")]
+    if low.len().is_multiple_of(4) && SimdCapability::detect() != SimdCapability::None {
+        unsafe { rotate_pairs_f32_avx2(low, high, gate) };
+        return;
+    }
+    for (a, b) in low.iter_mut().zip(high) {
+        let (s0, s1) = (*a, *b);
+        *a = gate[0][0] * s0 + gate[0][1] * s1;
+        *b = gate[1][0] * s0 + gate[1][1] * s1;
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2", enable = "fma")]
+unsafe fn rotate_pairs_f32_avx2(
+    low: &mut [Complex<f32>],
+    high: &mut [Complex<f32>],
+    gate: &[[Complex<f32>; 2]; 2],
+) {
+    let broadcast = |g: Complex<f32>| (_mm256_set1_ps(g.real), _mm256_set1_ps(g.imaginary));
+    let (g00, g01) = (broadcast(gate[0][0]), broadcast(gate[0][1]));
+    let (g10, g11) = (broadcast(gate[1][0]), broadcast(gate[1][1]));
+    // `Complex` is `repr(C)`, so four amplitudes are eight interleaved floats.
+    let floats = 2 * low.len();
+    let (low, high) = (low.as_mut_ptr() as *mut f32, high.as_mut_ptr() as *mut f32);
+    for k in (0..floats).step_by(8) {
+        let a = _mm256_loadu_ps(low.add(k));
+        let b = _mm256_loadu_ps(high.add(k));
+        let new_a = _mm256_add_ps(complex_mul_ps(a, g00), complex_mul_ps(b, g01));
+        let new_b = _mm256_add_ps(complex_mul_ps(a, g10), complex_mul_ps(b, g11));
+        _mm256_storeu_ps(low.add(k), new_a);
+        _mm256_storeu_ps(high.add(k), new_b);
+    }
+}
+
+/// Four interleaved complex products `s · (re + i·im)`.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2", enable = "fma")]
+unsafe fn complex_mul_ps(s: __m256, (re, im): (__m256, __m256)) -> __m256 {
+    // [s.re·re − s.im·im, s.im·re + s.re·im] pairwise.
+    let swapped = _mm256_permute_ps(s, 0b1011_0001);
+    _mm256_fmaddsub_ps(s, re, _mm256_mul_ps(swapped, im))
+}
+
+/// Amplitudes per parallel task in the two-qubit kernels.
+const QUAD_CHUNK: usize = 1 << 12;
+
+/// `gate` on `targets` of the state, the first target the high bit of the
+/// gate's basis as for kernels. The four amplitudes of each group sit in
+/// four runs of equal length, which are mixed a vector at a time: two
+/// amplitudes per AVX2 instruction, four per AVX-512 one. A controlled
+/// phase only scales the run where both qubits are set.
+pub fn apply_two_qubit_gate_simd(
+    state: &mut [Complex<f64>],
+    gate: &[[Complex<f64>; 4]; 4],
+    targets: [usize; 2],
+    num_qubits: usize,
+) {
+    apply_two_qubit(state, gate, targets, num_qubits, false);
+}
+
+pub fn apply_two_qubit_gate_simd_parallel(
+    state: &mut [Complex<f64>],
+    gate: &[[Complex<f64>; 4]; 4],
+    targets: [usize; 2],
+    num_qubits: usize,
+) {
+    apply_two_qubit(state, gate, targets, num_qubits, true);
+}
+
+fn apply_two_qubit<T: StateFloat>(
+    state: &mut [Complex<T>],
+    gate: &[[Complex<f64>; 4]; 4],
+    targets: [usize; 2],
+    num_qubits: usize,
+    parallel: bool,
+) {
+    let [high, low] = targets.map(|t| num_qubits - 1 - t);
+    let mut gate = *gate;
+    if high < low {
+        // Reorder the basis so the gate's high bit is the state's.
+        gate.swap(1, 2);
+        for row in &mut gate {
+            row.swap(1, 2);
+        }
+    }
+    let (low, high) = (low.min(high), low.max(high));
+    if let Some(phase) = controlled_phase(&gate) {
+        let phase = T::from_complex(phase);
+        for_each_quad(state, low, high, parallel, |[_, _, _, both]| {
+            T::scale_run(both, phase)
+        });
+    } else {
+        let gate = gate.map(|row| row.map(T::from_complex));
+        for_each_quad(state, low, high, parallel, |quad| T::mix_quads(quad, &gate));
+    }
+}
+
+/// The `e^{iφ}` of `diag(1, 1, 1, e^{iφ})`, if `gate` is one.
+fn controlled_phase(gate: &[[Complex<f64>; 4]; 4]) -> Option<Complex<f64>> {
+    let one = complex!(1.0, 0.0);
+    let is_phase = (0..4).all(|r| {
+        (0..4).all(|c| {
+            let expected = match (r, c) {
+                (3, 3) => gate[3][3],
+                _ if r == c => one,
+                _ => complex!(0.0, 0.0),
+            };
+            (gate[r][c] - expected).abs() < 1e-15
+        })
+    });
+    is_phase.then_some(gate[3][3])
+}
+
+/// Calls `kernel` on the runs of amplitudes with bits `low` and `high`
+/// clear, `low` set, `high` set and both set, in that order; parallel
+/// tasks each take at most `QUAD_CHUNK` amplitudes.
+fn for_each_quad<T: Send>(
+    state: &mut [T],
+    low: usize,
+    high: usize,
+    parallel: bool,
+    kernel: impl Fn([&mut [T]; 4]) + Sync,
+) {
+    use super::parallel::*;
+
+    let run = 1 << low;
+    let quads = |block: &mut [T]| {
+        let (clear, set) = block.split_at_mut(1 << high);
+        for (c0, c1) in clear.chunks_mut(2 * run).zip(set.chunks_mut(2 * run)) {
+            let (a00, a01) = c0.split_at_mut(run);
+            let (a10, a11) = c1.split_at_mut(run);
+            kernel([a00, a01, a10, a11]);
+        }
+    };
+    if !parallel {
+        state.chunks_mut(2 << high).for_each(quads);
+    } else if 2 << high <= QUAD_CHUNK {
+        state.par_chunks_mut(QUAD_CHUNK).for_each(|chunk| {
+            chunk.chunks_mut(2 << high).for_each(quads);
+        });
+    } else if 4 * run <= QUAD_CHUNK {
+        // Few blocks: split each between tasks, whole quads at a time.
+        for block in state.chunks_mut(2 << high) {
+            let (clear, set) = block.split_at_mut(1 << high);
+            clear
+                .par_chunks_mut(QUAD_CHUNK / 2)
+                .zip(set.par_chunks_mut(QUAD_CHUNK / 2))
+                .for_each(|(clear, set)| {
+                    for (c0, c1) in clear.chunks_mut(2 * run).zip(set.chunks_mut(2 * run)) {
+                        let (a00, a01) = c0.split_at_mut(run);
+                        let (a10, a11) = c1.split_at_mut(run);
+                        kernel([a00, a01, a10, a11]);
+                    }
+                });
+        }
+    } else {
+        // Long runs: split the runs themselves.
+        for block in state.chunks_mut(2 << high) {
+            let (clear, set) = block.split_at_mut(1 << high);
+            for (c0, c1) in clear.chunks_mut(2 * run).zip(set.chunks_mut(2 * run)) {
+                let (a00, a01) = c0.split_at_mut(run);
+                let (a10, a11) = c1.split_at_mut(run);
+                let piece = QUAD_CHUNK / 4;
+                a00.par_chunks_mut(piece)
+                    .zip(a01.par_chunks_mut(piece))
+                    .zip(a10.par_chunks_mut(piece).zip(a11.par_chunks_mut(piece)))
+                    .for_each(|((a00, a01), (a10, a11))| kernel([a00, a01, a10, a11]));
+            }
+        }
+    }
+}
+
+/// `(a00, a01, a10, a11)[k] ← gate · (a00, a01, a10, a11)[k]` for every `k`.
+fn mix_quads_f64(quad: [&mut [Complex<f64>]; 4], gate: &[[Complex<f64>; 4]; 4]) {
+    let len = quad[0].len();
+    #[cfg(target_arch = "x86_64")]
+    {
+        let cap = SimdCapability::detect();
+        if cap == SimdCapability::Avx512 && len.is_multiple_of(4) {
+            unsafe { mix_quads_avx512(quad, gate) };
+            return;
+        }
+        if cap != SimdCapability::None && len.is_multiple_of(2) {
+            unsafe { mix_quads_avx2(quad, gate) };
+            return;
+        }
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        unsafe { mix_quads_neon(quad, gate) };
+        return;
+    }
+    #[allow(unreachable_code)]
+    mix_quads_scalar(quad, gate, len)
+}
+
+fn mix_quads_scalar<T: Float>(
+    [a00, a01, a10, a11]: [&mut [Complex<T>]; 4],
+    gate: &[[Complex<T>; 4]; 4],
+    len: usize,
+) {
+    for k in 0..len {
+        let s = [a00[k], a01[k], a10[k], a11[k]];
+        let row = |r: usize| {
+            gate[r][0] * s[0] + gate[r][1] * s[1] + gate[r][2] * s[2] + gate[r][3] * s[3]
+        };
+        (a00[k], a01[k], a10[k], a11[k]) = (row(0), row(1), row(2), row(3));
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2", enable = "fma")]
+unsafe fn mix_quads_avx2(quad: [&mut [Complex<f64>]; 4], gate: &[[Complex<f64>; 4]; 4]) {
+    let mut g = [[(_mm256_setzero_pd(), _mm256_setzero_pd()); 4]; 4];
+    for r in 0..4 {
+        for c in 0..4 {
+            g[r][c] = (
+                _mm256_set1_pd(gate[r][c].real),
+                _mm256_set1_pd(gate[r][c].imaginary),
+            );
+        }
+    }
+    let floats = 2 * quad[0].len();
+    let runs = quad.map(|run| run.as_mut_ptr() as *mut f64);
+    for k in (0..floats).step_by(4) {
+        let s = runs.map(|run| _mm256_loadu_pd(run.add(k)));
+        for r in 0..4 {
+            let mut sum = complex_mul_pd(s[0], g[r][0]);
+            for c in 1..4 {
+                sum = _mm256_add_pd(sum, complex_mul_pd(s[c], g[r][c]));
+            }
+            _mm256_storeu_pd(runs[r].add(k), sum);
+        }
+    }
+}
+
+/// Two interleaved complex products `s · (re + i·im)`.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2", enable = "fma")]
+unsafe fn complex_mul_pd(s: __m256d, (re, im): (__m256d, __m256d)) -> __m256d {
+    let swapped = _mm256_permute_pd(s, 0b0101);
+    _mm256_fmaddsub_pd(s, re, _mm256_mul_pd(swapped, im))
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f")]
+unsafe fn mix_quads_avx512(quad: [&mut [Complex<f64>]; 4], gate: &[[Complex<f64>; 4]; 4]) {
+    let mut g = [[(_mm512_setzero_pd(), _mm512_setzero_pd()); 4]; 4];
+    for r in 0..4 {
+        for c in 0..4 {
+            g[r][c] = (
+                _mm512_set1_pd(gate[r][c].real),
+                _mm512_set1_pd(gate[r][c].imaginary),
+            );
+        }
+    }
+    let floats = 2 * quad[0].len();
+    let runs = quad.map(|run| run.as_mut_ptr() as *mut f64);
+    for k in (0..floats).step_by(8) {
+        let s = runs.map(|run| _mm512_loadu_pd(run.add(k)));
+        for r in 0..4 {
+            let mut sum = complex_mul_pd512(s[0], g[r][0]);
+            for c in 1..4 {
+                sum = _mm512_add_pd(sum, complex_mul_pd512(s[c], g[r][c]));
+            }
+            _mm512_storeu_pd(runs[r].add(k), sum);
+        }
+    }
+}
+
+/// Four interleaved complex products `s · (re + i·im)`.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f")]
+unsafe fn complex_mul_pd512(s: __m512d, (re, im): (__m512d, __m512d)) -> __m512d {
+    let swapped = _mm512_permute_pd(s, 0b0101_0101);
+    _mm512_fmaddsub_pd(s, re, _mm512_mul_pd(swapped, im))
+}
+
+#[cfg(target_arch = "aarch64")]
+unsafe fn mix_quads_neon(quad: [&mut [Complex<f64>]; 4], gate: &[[Complex<f64>; 4]; 4]) {
+    // One amplitude per register: s·g = s·re + swap(s)·(−im, im).
+    let mut g = [[(vdupq_n_f64(0.0), vdupq_n_f64(0.0)); 4]; 4];
+    for r in 0..4 {
+        for c in 0..4 {
+            let x = gate[r][c];
+            g[r][c] = (
+                vdupq_n_f64(x.real),
+                vld1q_f64([-x.imaginary, x.imaginary].as_ptr()),
+            );
+        }
+    }
+    let floats = 2 * quad[0].len();
+    let runs = quad.map(|run| run.as_mut_ptr() as *mut f64);
+    for k in (0..floats).step_by(2) {
+        let s = runs.map(|run| vld1q_f64(run.add(k)));
+        let swapped = s.map(|s| vextq_f64::<1>(s, s));
+        for r in 0..4 {
+            let mut sum = vdupq_n_f64(0.0);
+            for c in 0..4 {
+                sum = vfmaq_f64(sum, s[c], g[r][c].0);
+                sum = vfmaq_f64(sum, swapped[c], g[r][c].1);
+            }
+            vst1q_f64(runs[r].add(k), sum);
+        }
+    }
+}
+
+fn mix_quads_f32(quad: [&mut [Complex<f32>]; 4], gate: &[[Complex<f32>; 4]; 4]) {
+    let len = quad[0].len();
+    #[cfg(target_arch = "x86_64")]
+    if len.is_multiple_of(4) && SimdCapability::detect() != SimdCapability::None {
+        unsafe { mix_quads_f32_avx2(quad, gate) };
+        return;
+    }
+    mix_quads_scalar(quad, gate, len)
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2", enable = "fma")]
+unsafe fn mix_quads_f32_avx2(quad: [&mut [Complex<f32>]; 4], gate: &[[Complex<f32>; 4]; 4]) {
+    let mut g = [[(_mm256_setzero_ps(), _mm256_setzero_ps()); 4]; 4];
+    for r in 0..4 {
+        for c in 0..4 {
+            g[r][c] = (
+                _mm256_set1_ps(gate[r][c].real),
+                _mm256_set1_ps(gate[r][c].imaginary),
+            );
+        }
+    }
+    let floats = 2 * quad[0].len();
+    let runs = quad.map(|run| run.as_mut_ptr() as *mut f32);
+    for k in (0..floats).step_by(8) {
+        let s = runs.map(|run| _mm256_loadu_ps(run.add(k)));
+        for r in 0..4 {
+            let mut sum = complex_mul_ps(s[0], g[r][0]);
+            for c in 1..4 {
+                sum = _mm256_add_ps(sum, complex_mul_ps(s[c], g[r][c]));
+            }
+            _mm256_storeu_ps(runs[r].add(k), sum);
+        }
+    }
+}
+
+/// `run[k] ← phase · run[k]`, the part of a controlled phase that moves
+/// anything.
+fn scale_run_f64(run: &mut [Complex<f64>], phase: Complex<f64>) {
+    #[cfg(target_arch = "x86_64")]
+    if run.len().is_multiple_of(2) && SimdCapability::detect() != SimdCapability::None {
+        unsafe { scale_run_avx2(run, phase) };
+        return;
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        unsafe { scale_run_neon(run, phase) };
+        return;
+    }
+    #[allow(unreachable_code)]
+    for x in run {
+        *x = phase * *x;
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2", enable = "fma")]
+unsafe fn scale_run_avx2(run: &mut [Complex<f64>], phase: Complex<f64>) {
+    let g = (_mm256_set1_pd(phase.real), _mm256_set1_pd(phase.imaginary));
+    let floats = 2 * run.len();
+    let run = run.as_mut_ptr() as *mut f64;
+    for k in (0..floats).step_by(4) {
+        let s = _mm256_loadu_pd(run.add(k));
+        _mm256_storeu_pd(run.add(k), complex_mul_pd(s, g));
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+unsafe fn scale_run_neon(run: &mut [Complex<f64>], phase: Complex<f64>) {
+    let re = vdupq_n_f64(phase.real);
+    let im = vld1q_f64([-phase.imaginary, phase.imaginary].as_ptr());
+    let floats = 2 * run.len();
+    let run = run.as_mut_ptr() as *mut f64;
+    for k in (0..floats).step_by(2) {
+        let s = vld1q_f64(run.add(k));
+        let product = vfmaq_f64(vmulq_f64(s, re), vextq_f64::<1>(s, s), im);
+        vst1q_f64(run.add(k), product);
+    }
+}
+
+fn scale_run_f32(run: &mut [Complex<f32>], phase: Complex<f32>) {
+    #[cfg(target_arch = "x86_64")]
+    if run.len().is_multiple_of(4) && SimdCapability::detect() != SimdCapability::None {
+        unsafe { scale_run_f32_avx2(run, phase) };
+        return;
+    }
+    for x in run {
+        *x = phase * *x;
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2", enable = "fma")]
+unsafe fn scale_run_f32_avx2(run: &mut [Complex<f32>], phase: Complex<f32>) {
+    let g = (_mm256_set1_ps(phase.real), _mm256_set1_ps(phase.imaginary));
+    let floats = 2 * run.len();
+    let run = run.as_mut_ptr() as *mut f32;
+    for k in (0..floats).step_by(8) {
+        let s = _mm256_loadu_ps(run.add(k));
+        _mm256_storeu_ps(run.add(k), complex_mul_ps(s, g));
+    }
+}
+
 pub fn get_simd_info() -> String {
     let cap = SimdCapability::detect();
     format!("SIMD: {}", cap.name())
@@ -57,7 +57,7 @@ impl<T: Float> ColumnVector<T> {
         for i in 0..matrix.rows {
             let mut sum = T::zero();
             for j in 0..matrix.cols {
-                sum = sum + (matrix.get(i, j) * self.get(j));
+                sum += matrix.get(i, j) * self.get(j);
             }
             result.set(i, sum);
         }
@@ -81,7 +81,7 @@ impl<T: Float> RowVector<T> {
         for j in 0..matrix.cols {
             let mut sum = T::zero();
             for i in 0..matrix.rows {
-                sum = sum + (self.get(i) * matrix.get(i, j));
+                sum += self.get(i) * matrix.get(i, j);
             }
             result.set(j, sum);
         }
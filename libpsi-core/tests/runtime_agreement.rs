@@ -0,0 +1,185 @@
+//! Random circuits must give the same state under every runtime, and every
+//! runtime must keep the state normalised.
+
+use libpsi_core::{
+    CustomGateBuilder, GateOp, QuantumState, Runtime, RuntimeConfig, StateTrace, Vector,
+};
+use proptest::prelude::*;
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+const TOLERANCE: f64 = 1e-9;
+
+fn runtimes() -> Vec<(&'static str, Runtime)> {
+    vec![
+        ("BasicRTMT", Runtime::BasicRTMT),
+        ("BatchedRT", Runtime::BatchedRT),
+        ("BatchedRTMT", Runtime::BatchedRTMT),
+        ("SimdRT", Runtime::SimdRT),
+        ("SimdRTMT", Runtime::SimdRTMT),
+        ("StructureAwareRT", Runtime::StructureAwareRT),
+        ("StructureAwareMT", Runtime::StructureAwareMT),
+        ("Optimal", Runtime::Custom(Runtime::optimal())),
+        // The strategy stays below the parallel threshold, so these force
+        // the parallel paths the runtimes above fall back from.
+        (
+            "BatchedMT, no threshold",
+            parallel(RuntimeConfig::new().batched()),
+        ),
+        (
+            "SimdMT, no threshold",
+            parallel(RuntimeConfig::new().simd()),
+        ),
+        (
+            "StructureAwareMT, no threshold",
+            parallel(RuntimeConfig::new().structure_aware()),
+        ),
+        ("Optimal, no threshold", parallel(Runtime::optimal())),
+    ]
+}
+
+fn parallel(config: RuntimeConfig) -> Runtime {
+    Runtime::Custom(config.parallel().with_threshold(0))
+}
+
+fn two_qubits(n: usize) -> impl Strategy<Value = (usize, usize)> {
+    (0..n, 0..n).prop_filter("distinct qubits", |(a, b)| a != b)
+}
+
+fn three_qubits(n: usize) -> impl Strategy<Value = (usize, usize, usize)> {
+    (0..n, 0..n, 0..n).prop_filter("distinct qubits", |(a, b, c)| a != b && b != c && a != c)
+}
+
+fn gate(n: usize) -> impl Strategy<Value = GateOp> {
+    let angle = -2.0 * PI..2.0 * PI;
+    let bell = Arc::new(
+        CustomGateBuilder::new("BELL", 2)
+            .h(0)
+            .cnot(0, 1)
+            .t(1)
+            .build(),
+    );
+    let ht = Arc::new(CustomGateBuilder::new("HT", 1).h(0).t(0).build());
+    prop_oneof![
+        (0..n).prop_map(GateOp::H),
+        (0..n).prop_map(GateOp::X),
+        (0..n).prop_map(GateOp::Y),
+        (0..n).prop_map(GateOp::Z),
+        (0..n).prop_map(GateOp::S),
+        (0..n).prop_map(GateOp::T),
+        (0..n).prop_map(GateOp::Sdg),
+        (0..n).prop_map(GateOp::Tdg),
+        (0..n).prop_map(GateOp::Sx),
+        (0..n).prop_map(GateOp::Sxdg),
+        (0..n, angle.clone()).prop_map(|(q, a)| GateOp::Rx(q, a)),
+        (0..n, angle.clone()).prop_map(|(q, a)| GateOp::Ry(q, a)),
+        (0..n, angle.clone()).prop_map(|(q, a)| GateOp::Rz(q, a)),
+        (0..n, angle.clone()).prop_map(|(q, a)| GateOp::P(q, a)),
+        (0..n, angle.clone()).prop_map(|(q, a)| GateOp::U1(q, a)),
+        (0..n, angle.clone(), angle.clone()).prop_map(|(q, a, b)| GateOp::U2(q, a, b)),
+        (0..n, angle.clone(), angle.clone(), angle.clone())
+            .prop_map(|(q, a, b, c)| GateOp::U3(q, a, b, c)),
+        two_qubits(n).prop_map(|(c, t)| GateOp::CNOT(c, t)),
+        two_qubits(n).prop_map(|(c, t)| GateOp::CZ(c, t)),
+        two_qubits(n).prop_map(|(a, b)| GateOp::SWAP(a, b)),
+        two_qubits(n).prop_map(|(a, b)| GateOp::ISwap(a, b)),
+        two_qubits(n).prop_map(|(a, b)| GateOp::SqrtSwap(a, b)),
+        two_qubits(n).prop_map(|(c, t)| GateOp::CY(c, t)),
+        two_qubits(n).prop_map(|(c, t)| GateOp::CH(c, t)),
+        two_qubits(n).prop_map(|(c, t)| GateOp::CSX(c, t)),
+        two_qubits(n).prop_map(|(a, b)| GateOp::ECR(a, b)),
+        two_qubits(n).prop_map(|(a, b)| GateOp::DCX(a, b)),
+        (two_qubits(n), angle.clone()).prop_map(|((c, t), a)| GateOp::CRx(c, t, a)),
+        (two_qubits(n), angle.clone()).prop_map(|((c, t), a)| GateOp::CRy(c, t, a)),
+        (two_qubits(n), angle.clone()).prop_map(|((c, t), a)| GateOp::CRz(c, t, a)),
+        (two_qubits(n), angle.clone()).prop_map(|((c, t), a)| GateOp::CP(c, t, a)),
+        (two_qubits(n), angle.clone()).prop_map(|((a, b), x)| GateOp::Rxx(a, b, x)),
+        (two_qubits(n), angle.clone()).prop_map(|((a, b), x)| GateOp::Ryy(a, b, x)),
+        (two_qubits(n), angle).prop_map(|((a, b), x)| GateOp::Rzz(a, b, x)),
+        three_qubits(n).prop_map(|(a, b, c)| GateOp::CCNOT(a, b, c)),
+        three_qubits(n).prop_map(|(a, b, c)| GateOp::CSWAP(a, b, c)),
+        (Just((0..n).collect::<Vec<_>>()).prop_shuffle(), 0..n)
+            .prop_map(|(q, k)| GateOp::MCX(q[1..=k].to_vec(), q[0])),
+        (Just((0..n).collect::<Vec<_>>()).prop_shuffle(), 0..n).prop_map(move |(q, k)| {
+            GateOp::MCU {
+                gate: Arc::clone(&ht),
+                controls: q[1..=k].to_vec(),
+                target: q[0],
+            }
+        }),
+        two_qubits(n).prop_map(move |(a, b)| GateOp::Custom(Arc::clone(&bell), vec![a, b])),
+        (0..n).prop_map(|q| GateOp::Measure(q, q)),
+        (0..n).prop_map(|q| GateOp::Barrier(vec![q], None)),
+    ]
+}
+
+fn circuit() -> impl Strategy<Value = (usize, Vec<GateOp>)> {
+    (3usize..=6).prop_flat_map(|n| (Just(n), prop::collection::vec(gate(n), 0..40)))
+}
+
+fn norm(state: &QuantumState) -> f64 {
+    (0..state.size()).map(|i| state.get(i).norm2()).sum()
+}
+
+fn max_difference(a: &QuantumState, b: &QuantumState) -> f64 {
+    (0..a.size())
+        .map(|i| {
+            let (x, y) = (a.get(i), b.get(i));
+            (x.real - y.real)
+                .abs()
+                .max((x.imaginary - y.imaginary).abs())
+        })
+        .fold(0.0, f64::max)
+}
+
+proptest! {
+    #[test]
+    fn runtimes_agree_with_basic((num_qubits, ops) in circuit()) {
+        let reference = Runtime::BasicRT.compute(num_qubits, &ops);
+        prop_assert!((norm(&reference) - 1.0).abs() < TOLERANCE, "BasicRT norm {}", norm(&reference));
+
+        for (name, runtime) in runtimes() {
+            let state = runtime.compute(num_qubits, &ops);
+            prop_assert_eq!(state.size(), reference.size());
+            let difference = max_difference(&state, &reference);
+            prop_assert!(difference < TOLERANCE, "{} differs from BasicRT by {}", name, difference);
+            prop_assert!((norm(&state) - 1.0).abs() < TOLERANCE, "{} norm {}", name, norm(&state));
+        }
+    }
+}
+
+/// Above the 14-qubit cache block, so runs of gates go through blocking, on
+/// the low qubits and the high ones. The Basic runtimes build full
+/// operators, too large here; a trace applies the gates one at a time
+/// instead.
+#[test]
+fn runtimes_agree_above_cache_block() {
+    let n = 16;
+    let mut ops = Vec::new();
+    for layer in 0..2 {
+        for q in 0..n {
+            ops.push(GateOp::H(q));
+            ops.push(GateOp::Rz(q, 0.3 * (q + layer) as f64));
+        }
+        for q in 0..n - 1 {
+            ops.push(GateOp::CNOT(q, q + 1));
+        }
+        ops.push(GateOp::U3(layer, 0.7, -0.2, 1.3));
+        ops.push(GateOp::Rxx(0, n - 1, 0.9));
+        ops.push(GateOp::CCNOT(n - 1, 1, n / 2));
+    }
+
+    let reference = StateTrace::record(n, &ops, 0.0).final_state();
+    for (name, runtime) in runtimes() {
+        if name.starts_with("Basic") {
+            continue;
+        }
+        let difference = max_difference(&runtime.compute(n, &ops), &reference);
+        assert!(
+            difference < TOLERANCE,
+            "{} differs from the trace by {}",
+            name,
+            difference
+        );
+    }
+}
@@ -0,0 +1,25 @@
+use core::fmt;
+
+/// A problem in an OpenQASM source, with the 1-based line it was found on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QasmError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl QasmError {
+    pub fn new(line: usize, message: impl Into<String>) -> Self {
+        QasmError {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for QasmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for QasmError {}
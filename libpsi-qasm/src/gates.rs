@@ -0,0 +1,30 @@
+use libpsi_core::GateOp;
+
+/// Number of qubits and parameters taken by each supported `qelib1.inc`
+/// gate, by its OpenQASM name.
+pub fn gate_signature(name: &str) -> Option<(usize, usize)> {
+    let signature = match name {
+        "id" | "x" | "y" | "z" | "h" | "s" | "sdg" | "t" | "tdg" | "sx" | "sxdg" => (1, 0),
+        "rx" | "ry" | "rz" | "p" | "u1" => (1, 1),
+        "u2" => (1, 2),
+        "u3" | "u" | "U" => (1, 3),
+        "cx" | "CX" | "cz" | "swap" | "iswap" => (2, 0),
+        "cy" | "ch" | "csx" | "ecr" | "dcx" => (2, 0),
+        "crx" | "cry" | "crz" | "cp" | "cu1" | "rxx" | "ryy" | "rzz" => (2, 1),
+        "ccx" | "cswap" => (3, 0),
+        "c3x" => (4, 0),
+        "c4x" => (5, 0),
+        _ => return None,
+    };
+    Some(signature)
+}
+
+/// Builds the operation for an OpenQASM gate application. Returns `None` for
+/// unknown gates, for a qubit or parameter count that does not match
+/// `gate_signature`, and for `id`, which has no operation of its own.
+pub fn gate_op(name: &str, qubits: &[usize], params: &[f64]) -> Option<GateOp> {
+    if name == "id" || gate_signature(name)? != (qubits.len(), params.len()) {
+        return None;
+    }
+    GateOp::from_name(name, qubits, params).ok()
+}
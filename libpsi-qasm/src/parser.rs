@@ -0,0 +1,351 @@
+use crate::error::QasmError;
+use crate::expr;
+use crate::gates::{gate_op, gate_signature};
+use libpsi_core::{GateOp, QuantumCircuit};
+
+/// Parses an OpenQASM 2.0 program into a circuit.
+///
+/// Registers are laid out in declaration order and the wires are labelled
+/// `name[i]` after them. Gates from `qelib1.inc` (see `gate_signature`),
+/// `measure`, `reset` and `barrier` are supported, including broadcasting
+/// over whole registers; `if` and custom `gate` definitions are reported as
+/// errors.
+pub fn parse(source: &str) -> Result<QuantumCircuit, QasmError> {
+    let mut program = Program::default();
+    for (line, statement) in statements(source)? {
+        program
+            .statement(&statement)
+            .map_err(|message| QasmError::new(line, message))?;
+    }
+    Ok(program.into_circuit())
+}
+
+/// Splits the source into `;`-terminated statements, dropping `//` comments,
+/// each paired with the line it starts on.
+fn statements(source: &str) -> Result<Vec<(usize, String)>, QasmError> {
+    let mut statements = Vec::new();
+    let mut current = String::new();
+    let mut start = 0;
+
+    for (n, line) in source.lines().enumerate() {
+        let code = line.split("//").next().unwrap_or("");
+        for ch in code.chars() {
+            if current.trim().is_empty() {
+                start = n + 1;
+            }
+            if ch == ';' {
+                statements.push((start, current.trim().to_string()));
+                current.clear();
+            } else if ch == '{' {
+                return Err(QasmError::new(n + 1, "gate definitions are not supported"));
+            } else {
+                current.push(ch);
+            }
+        }
+        current.push('\n');
+    }
+
+    if !current.trim().is_empty() {
+        return Err(QasmError::new(start, "missing ';' at end of statement"));
+    }
+    Ok(statements)
+}
+
+struct Register {
+    name: String,
+    offset: usize,
+    size: usize,
+}
+
+#[derive(Default)]
+struct Program {
+    qregs: Vec<Register>,
+    cregs: Vec<Register>,
+    operations: Vec<GateOp>,
+}
+
+impl Program {
+    fn num_qubits(&self) -> usize {
+        self.qregs.iter().map(|reg| reg.size).sum()
+    }
+
+    fn num_classical(&self) -> usize {
+        self.cregs.iter().map(|reg| reg.size).sum()
+    }
+
+    fn into_circuit(self) -> QuantumCircuit {
+        let mut circuit = QuantumCircuit::with_classical(self.num_qubits(), self.num_classical());
+        for reg in &self.qregs {
+            let wires: Vec<usize> = (reg.offset..reg.offset + reg.size).collect();
+            circuit.label_qubits(&reg.name, &wires);
+        }
+        for reg in &self.cregs {
+            let bits: Vec<usize> = (reg.offset..reg.offset + reg.size).collect();
+            circuit.label_classical_bits(&reg.name, &bits);
+        }
+        for op in self.operations {
+            circuit.push(op);
+        }
+        circuit
+    }
+
+    fn statement(&mut self, statement: &str) -> Result<(), String> {
+        if statement.is_empty() {
+            return Ok(());
+        }
+        let (keyword, rest) = split_keyword(statement);
+        match keyword {
+            "OPENQASM" => {
+                if rest.trim().starts_with('2') {
+                    Ok(())
+                } else {
+                    Err(format!("unsupported OpenQASM version '{}'", rest.trim()))
+                }
+            }
+            "include" => {
+                if rest.trim() == "\"qelib1.inc\"" {
+                    Ok(())
+                } else {
+                    Err(format!("cannot include {}", rest.trim()))
+                }
+            }
+            "qreg" => {
+                let (name, size) = parse_declaration(rest)?;
+                let offset = self.num_qubits();
+                self.declare(true, name, offset, size)
+            }
+            "creg" => {
+                let (name, size) = parse_declaration(rest)?;
+                let offset = self.num_classical();
+                self.declare(false, name, offset, size)
+            }
+            "measure" => self.measure(rest),
+            "barrier" => {
+                let mut qubits: Vec<usize> = Vec::new();
+                for arg in split_args(rest) {
+                    qubits.extend(self.resolve(&self.qregs, arg)?.0);
+                }
+                qubits.sort_unstable();
+                qubits.dedup();
+                self.operations.push(GateOp::Barrier(qubits, None));
+                Ok(())
+            }
+            "reset" => {
+                let (qubits, _) = self.resolve(&self.qregs, rest)?;
+                self.operations
+                    .extend(qubits.into_iter().map(GateOp::Reset));
+                Ok(())
+            }
+            "if" => Err("classically controlled gates are not supported".to_string()),
+            "gate" | "opaque" => Err("gate definitions are not supported".to_string()),
+            _ => self.gate(statement),
+        }
+    }
+
+    fn declare(
+        &mut self,
+        quantum: bool,
+        name: &str,
+        offset: usize,
+        size: usize,
+    ) -> Result<(), String> {
+        if self
+            .qregs
+            .iter()
+            .chain(&self.cregs)
+            .any(|reg| reg.name == name)
+        {
+            return Err(format!("register '{}' is already declared", name));
+        }
+        let register = Register {
+            name: name.to_string(),
+            offset,
+            size,
+        };
+        if quantum {
+            self.qregs.push(register);
+        } else {
+            self.cregs.push(register);
+        }
+        Ok(())
+    }
+
+    /// Wires named by `arg`: one for `name[i]`, the whole register for `name`.
+    /// The flag is true for a whole register.
+    fn resolve(&self, registers: &[Register], arg: &str) -> Result<(Vec<usize>, bool), String> {
+        let arg = arg.trim();
+        let (name, index) = match arg.split_once('[') {
+            Some((name, index)) => {
+                let index = index
+                    .strip_suffix(']')
+                    .and_then(|index| index.trim().parse::<usize>().ok())
+                    .ok_or_else(|| format!("invalid index in '{}'", arg))?;
+                (name.trim(), Some(index))
+            }
+            None => (arg, None),
+        };
+        let register = registers
+            .iter()
+            .find(|reg| reg.name == name)
+            .ok_or_else(|| format!("unknown register '{}'", name))?;
+        match index {
+            Some(i) if i >= register.size => Err(format!(
+                "index {} out of range for register '{}' of size {}",
+                i, name, register.size
+            )),
+            Some(i) => Ok((vec![register.offset + i], false)),
+            None => Ok((
+                (register.offset..register.offset + register.size).collect(),
+                true,
+            )),
+        }
+    }
+
+    fn measure(&mut self, rest: &str) -> Result<(), String> {
+        let (qubit_arg, bit_arg) = rest
+            .split_once("->")
+            .ok_or_else(|| "expected 'measure qubit -> bit'".to_string())?;
+        let (qubits, _) = self.resolve(&self.qregs, qubit_arg)?;
+        let (bits, _) = self.resolve(&self.cregs, bit_arg)?;
+        if qubits.len() != bits.len() {
+            return Err(format!(
+                "cannot measure {} qubits into {} bits",
+                qubits.len(),
+                bits.len()
+            ));
+        }
+        for (q, c) in qubits.into_iter().zip(bits) {
+            self.operations.push(GateOp::Measure(q, c));
+        }
+        Ok(())
+    }
+
+    fn gate(&mut self, statement: &str) -> Result<(), String> {
+        let name_end = statement
+            .find(|ch: char| !(ch.is_ascii_alphanumeric() || ch == '_'))
+            .unwrap_or(statement.len());
+        let name = &statement[..name_end];
+        let mut rest = statement[name_end..].trim_start();
+
+        let mut params = Vec::new();
+        if rest.starts_with('(') {
+            let close =
+                matching_paren(rest).ok_or_else(|| format!("unclosed '(' after '{}'", name))?;
+            for param in split_args(&rest[1..close]) {
+                params.push(expr::evaluate(param)?);
+            }
+            rest = &rest[close + 1..];
+        }
+
+        let (num_qubits, num_params) =
+            gate_signature(name).ok_or_else(|| format!("unknown gate '{}'", name))?;
+        if params.len() != num_params {
+            return Err(format!(
+                "gate '{}' takes {} parameters, got {}",
+                name,
+                num_params,
+                params.len()
+            ));
+        }
+        let args = split_args(rest);
+        if args.len() != num_qubits {
+            return Err(format!(
+                "gate '{}' acts on {} qubits, got {}",
+                name,
+                num_qubits,
+                args.len()
+            ));
+        }
+
+        let mut resolved = Vec::with_capacity(args.len());
+        for arg in args {
+            resolved.push(self.resolve(&self.qregs, arg)?);
+        }
+        let width = resolved
+            .iter()
+            .filter(|(_, whole)| *whole)
+            .map(|(wires, _)| wires.len())
+            .max()
+            .unwrap_or(1);
+        if resolved
+            .iter()
+            .any(|(wires, whole)| *whole && wires.len() != width)
+        {
+            return Err(format!("registers passed to '{}' differ in size", name));
+        }
+
+        for i in 0..width {
+            let qubits: Vec<usize> = resolved
+                .iter()
+                .map(|(wires, whole)| if *whole { wires[i] } else { wires[0] })
+                .collect();
+            let mut distinct = qubits.clone();
+            distinct.sort_unstable();
+            distinct.dedup();
+            if distinct.len() != qubits.len() {
+                return Err(format!("gate '{}' applied to the same qubit twice", name));
+            }
+            if let Some(op) = gate_op(name, &qubits, &params) {
+                self.operations.push(op);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn split_keyword(statement: &str) -> (&str, &str) {
+    match statement.find(|ch: char| ch.is_whitespace() || ch == '(') {
+        Some(end) => (&statement[..end], &statement[end..]),
+        None => (statement, ""),
+    }
+}
+
+fn parse_declaration(rest: &str) -> Result<(&str, usize), String> {
+    let rest = rest.trim();
+    let (name, size) = rest
+        .split_once('[')
+        .ok_or_else(|| format!("expected 'name[size]', got '{}'", rest))?;
+    let size = size
+        .strip_suffix(']')
+        .and_then(|size| size.trim().parse::<usize>().ok())
+        .ok_or_else(|| format!("invalid register size in '{}'", rest))?;
+    Ok((name.trim(), size))
+}
+
+/// Splits on commas outside parentheses, dropping empty pieces.
+fn split_args(text: &str) -> Vec<&str> {
+    let mut args = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, ch) in text.char_indices() {
+        match ch {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                args.push(text[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    args.push(text[start..].trim());
+    args.retain(|arg| !arg.is_empty());
+    args
+}
+
+fn matching_paren(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, ch) in text.char_indices() {
+        match ch {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
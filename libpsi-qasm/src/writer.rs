@@ -0,0 +1,143 @@
+use libpsi_core::{CompositeOp, CustomGateDefinition, GateOp, QuantumCircuit};
+use std::fmt::Write;
+
+/// OpenQASM name of a built-in operation, or `None` for operations that
+/// have no single `qelib1.inc` gate.
+fn qasm_name(op: &GateOp) -> Option<&'static str> {
+    let name = match op {
+        GateOp::H(_) => "h",
+        GateOp::X(_) => "x",
+        GateOp::Y(_) => "y",
+        GateOp::Z(_) => "z",
+        GateOp::S(_) => "s",
+        GateOp::T(_) => "t",
+        GateOp::Sdg(_) => "sdg",
+        GateOp::Tdg(_) => "tdg",
+        GateOp::Sx(_) => "sx",
+        GateOp::Sxdg(_) => "sxdg",
+        GateOp::Rx(_, _) => "rx",
+        GateOp::Ry(_, _) => "ry",
+        GateOp::Rz(_, _) => "rz",
+        GateOp::P(_, _) => "p",
+        GateOp::U1(_, _) => "u1",
+        GateOp::U2(_, _, _) => "u2",
+        GateOp::U3(_, _, _, _) => "u3",
+        GateOp::CNOT(_, _) => "cx",
+        GateOp::CZ(_, _) => "cz",
+        GateOp::SWAP(_, _) => "swap",
+        GateOp::ISwap(_, _) => "iswap",
+        GateOp::CY(_, _) => "cy",
+        GateOp::CH(_, _) => "ch",
+        GateOp::CSX(_, _) => "csx",
+        GateOp::ECR(_, _) => "ecr",
+        GateOp::DCX(_, _) => "dcx",
+        GateOp::CRx(_, _, _) => "crx",
+        GateOp::CRy(_, _, _) => "cry",
+        GateOp::CRz(_, _, _) => "crz",
+        GateOp::CP(_, _, _) => "cp",
+        GateOp::Rxx(_, _, _) => "rxx",
+        GateOp::Ryy(_, _, _) => "ryy",
+        GateOp::Rzz(_, _, _) => "rzz",
+        GateOp::CCNOT(_, _, _) => "ccx",
+        GateOp::CSWAP(_, _, _) => "cswap",
+        GateOp::MCX(controls, _) => match controls.len() {
+            0 => "x",
+            1 => "cx",
+            2 => "ccx",
+            3 => "c3x",
+            4 => "c4x",
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(name)
+}
+
+fn composite_name(op: CompositeOp) -> &'static str {
+    match op {
+        CompositeOp::H => "h",
+        CompositeOp::X => "x",
+        CompositeOp::Y => "y",
+        CompositeOp::Z => "z",
+        CompositeOp::S => "s",
+        CompositeOp::T => "t",
+        CompositeOp::CNOT => "cx",
+        CompositeOp::CZ => "cz",
+        CompositeOp::SWAP => "swap",
+        CompositeOp::CCNOT => "ccx",
+        CompositeOp::CSWAP => "cswap",
+    }
+}
+
+fn write_gate(out: &mut String, name: &str, params: &[f64], qubits: &[usize]) {
+    out.push_str(name);
+    if !params.is_empty() {
+        let params: Vec<String> = params.iter().map(|p| format!("{:?}", p)).collect();
+        let _ = write!(out, "({})", params.join(","));
+    }
+    let qubits: Vec<String> = qubits.iter().map(|q| format!("q[{}]", q)).collect();
+    let _ = writeln!(out, " {};", qubits.join(","));
+}
+
+/// Writes a circuit as an OpenQASM 2.0 program over one `q` register (and a
+/// `c` register when it has classical bits), so that `parse` reads it back.
+///
+/// Composite custom gates are written out gate by gate and snapshots are
+/// dropped; matrix-defined custom gates, gates conditioned on a single
+/// classical bit and unbound parameters cannot be expressed and are reported
+/// as errors.
+pub fn to_qasm(circuit: &QuantumCircuit) -> Result<String, String> {
+    let mut out = String::from("OPENQASM 2.0;\ninclude \"qelib1.inc\";\n");
+    let _ = writeln!(out, "qreg q[{}];", circuit.num_qubits());
+    if circuit.num_classical() > 0 {
+        let _ = writeln!(out, "creg c[{}];", circuit.num_classical());
+    }
+
+    for op in circuit.operations() {
+        match op {
+            GateOp::Measure(q, c) => {
+                let _ = writeln!(out, "measure q[{}] -> c[{}];", q, c);
+            }
+            GateOp::Reset(q) => {
+                let _ = writeln!(out, "reset q[{}];", q);
+            }
+            GateOp::Barrier(qubits, title) => {
+                if let Some(title) = title {
+                    let _ = writeln!(out, "// {}", title);
+                }
+                write_gate(&mut out, "barrier", &[], qubits);
+            }
+            GateOp::Snapshot(_, _) => {}
+            GateOp::Conditional { classical_bit, .. } => {
+                return Err(format!(
+                    "{} conditioned on c[{}] has no OpenQASM 2.0 form, whose `if` tests a whole register",
+                    op.name(),
+                    classical_bit
+                ));
+            }
+            GateOp::Parameterized { .. } => {
+                return Err(format!("{:?} has unbound parameters", op));
+            }
+            GateOp::Custom(gate, targets) => match &gate.definition {
+                CustomGateDefinition::Composite(ops) => {
+                    for (op, local) in ops {
+                        let qubits: Vec<usize> = local.iter().map(|&i| targets[i]).collect();
+                        write_gate(&mut out, composite_name(*op), &[], &qubits);
+                    }
+                }
+                CustomGateDefinition::Matrix(_) => {
+                    return Err(format!(
+                        "custom gate '{}' is defined by a matrix and has no OpenQASM form",
+                        gate.name
+                    ));
+                }
+            },
+            _ => {
+                let name =
+                    qasm_name(op).ok_or_else(|| format!("no OpenQASM form for {}", op.name()))?;
+                write_gate(&mut out, name, &op.params(), &op.quantum_targets());
+            }
+        }
+    }
+    Ok(out)
+}
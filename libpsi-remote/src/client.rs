@@ -0,0 +1,98 @@
+use crate::{JobRequest, JobStatus, Provider, RemoteError};
+use libpsi_core::{Counts, GateOp, QuantumCircuit};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Runs circuits on a `Provider` and waits for their counts.
+///
+/// ```no_run
+/// use libpsi_core::QuantumCircuit;
+/// use libpsi_remote::{HttpProvider, RemoteClient};
+///
+/// let mut circuit = QuantumCircuit::new(2);
+/// circuit.h(0).cnot(0, 1);
+///
+/// let client = RemoteClient::new(HttpProvider::new("https://qpu.example.com/v1").with_token("..."));
+/// let hardware = client.run(&circuit, 1000).unwrap();
+/// let predicted = circuit.sample(1000, None);
+/// ```
+pub struct RemoteClient<P: Provider> {
+    provider: P,
+    poll_interval: Duration,
+    timeout: Option<Duration>,
+}
+
+impl<P: Provider> RemoteClient<P> {
+    pub fn new(provider: P) -> Self {
+        RemoteClient {
+            provider,
+            poll_interval: Duration::from_secs(2),
+            timeout: None,
+        }
+    }
+
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Gives up on jobs that have not finished after `timeout`. Without one
+    /// the client waits indefinitely, as queues on real hardware can be long.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn provider(&self) -> &P {
+        &self.provider
+    }
+
+    /// Submits `circuit` without waiting; see `wait`.
+    pub fn submit(&self, circuit: &QuantumCircuit, shots: usize) -> Result<String, RemoteError> {
+        self.provider.submit(&job_request(circuit, shots)?)
+    }
+
+    /// Polls `job_id` until it finishes.
+    pub fn wait(&self, job_id: &str) -> Result<Counts, RemoteError> {
+        let start = Instant::now();
+        loop {
+            match self.provider.status(job_id)? {
+                JobStatus::Done(counts) => return Ok(counts),
+                JobStatus::Failed(msg) => return Err(RemoteError::JobFailed(msg)),
+                JobStatus::Queued | JobStatus::Running => {}
+            }
+            if self.timeout.is_some_and(|t| start.elapsed() >= t) {
+                return Err(RemoteError::Timeout(job_id.to_string()));
+            }
+            thread::sleep(self.poll_interval);
+        }
+    }
+
+    /// Runs `circuit` for `shots` shots and returns counts with the same
+    /// outcome keys as `circuit.sample(shots, _)`.
+    pub fn run(&self, circuit: &QuantumCircuit, shots: usize) -> Result<Counts, RemoteError> {
+        let job_id = self.submit(circuit, shots)?;
+        self.wait(&job_id)
+    }
+}
+
+/// Serialises `circuit` for submission. Hardware only reports what is
+/// measured, so a circuit without measurements, which the local sampler
+/// reads out whole, gets `measure q[i] -> c[i]` on every qubit.
+pub fn job_request(circuit: &QuantumCircuit, shots: usize) -> Result<JobRequest, RemoteError> {
+    let measured = circuit
+        .operations()
+        .iter()
+        .any(|op| matches!(op, GateOp::Measure(_, _)));
+    let qasm = if measured {
+        libpsi_qasm::to_qasm(circuit)
+    } else {
+        let mut circuit = circuit.clone();
+        for q in 0..circuit.num_qubits() {
+            circuit.measure(q, q);
+        }
+        libpsi_qasm::to_qasm(&circuit)
+    }
+    .map_err(RemoteError::Serialize)?;
+    Ok(JobRequest { qasm, shots })
+}
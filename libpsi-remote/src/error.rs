@@ -0,0 +1,29 @@
+use core::fmt;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RemoteError {
+    /// The circuit has no OpenQASM form (e.g. a matrix custom gate).
+    Serialize(String),
+    /// The provider could not be reached or answered with an error.
+    Transport(String),
+    /// The provider answered with something that could not be understood.
+    Response(String),
+    /// The job ran and failed on the provider's side.
+    JobFailed(String),
+    /// The job did not finish within the client's timeout.
+    Timeout(String),
+}
+
+impl fmt::Display for RemoteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RemoteError::Serialize(msg) => write!(f, "cannot serialise circuit: {}", msg),
+            RemoteError::Transport(msg) => write!(f, "request failed: {}", msg),
+            RemoteError::Response(msg) => write!(f, "unexpected response: {}", msg),
+            RemoteError::JobFailed(msg) => write!(f, "job failed: {}", msg),
+            RemoteError::Timeout(job) => write!(f, "job {} timed out", job),
+        }
+    }
+}
+
+impl std::error::Error for RemoteError {}
@@ -0,0 +1,114 @@
+use crate::{JobRequest, JobStatus, Provider, RemoteError};
+use libpsi_core::Counts;
+use serde_json::{json, Value};
+
+/// A provider speaking a small JSON job API, for services that expose one
+/// directly or through a thin proxy:
+///
+/// - `POST {base}/jobs` with `{"qasm": "...", "shots": n}` answers `{"id": "..."}`
+/// - `GET {base}/jobs/{id}` answers `{"status": "queued" | "running" | "done" |
+///   "failed", "counts": {"01": 480, ...}, "error": "..."}`
+pub struct HttpProvider {
+    base_url: String,
+    headers: Vec<(String, String)>,
+    reversed_bits: bool,
+}
+
+impl HttpProvider {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        HttpProvider {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            headers: Vec::new(),
+            reversed_bits: false,
+        }
+    }
+
+    /// Sends `Authorization: Bearer {token}` with every request.
+    pub fn with_token(self, token: &str) -> Self {
+        self.with_header("Authorization", &format!("Bearer {}", token))
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// For services that write bitstrings with classical bit 0 rightmost, as
+    /// Qiskit does.
+    pub fn with_reversed_bits(mut self) -> Self {
+        self.reversed_bits = true;
+        self
+    }
+
+    fn parse_counts(&self, counts: &Value) -> Result<Counts, RemoteError> {
+        let counts = counts
+            .as_object()
+            .ok_or_else(|| RemoteError::Response("counts is not an object".to_string()))?;
+        let mut outcomes = Vec::with_capacity(counts.len());
+        for (bits, n) in counts {
+            let n = n.as_u64().ok_or_else(|| {
+                RemoteError::Response(format!("count for '{}' is not a number", bits))
+            })?;
+            let bits = if self.reversed_bits {
+                bits.chars().rev().collect()
+            } else {
+                bits.clone()
+            };
+            outcomes.push((bits, n as usize));
+        }
+        Ok(Counts::from_outcomes(outcomes))
+    }
+}
+
+fn read_json(
+    response: Result<ureq::http::Response<ureq::Body>, ureq::Error>,
+) -> Result<Value, RemoteError> {
+    let mut response = response.map_err(|err| RemoteError::Transport(err.to_string()))?;
+    let body = response
+        .body_mut()
+        .read_to_string()
+        .map_err(|err| RemoteError::Transport(err.to_string()))?;
+    serde_json::from_str(&body).map_err(|err| RemoteError::Response(err.to_string()))
+}
+
+impl Provider for HttpProvider {
+    fn name(&self) -> &str {
+        &self.base_url
+    }
+
+    fn submit(&self, job: &JobRequest) -> Result<String, RemoteError> {
+        let mut request = ureq::post(format!("{}/jobs", self.base_url))
+            .header("Content-Type", "application/json");
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        let body = json!({ "qasm": job.qasm, "shots": job.shots }).to_string();
+        let response = read_json(request.send(&body))?;
+        match &response["id"] {
+            Value::String(id) => Ok(id.clone()),
+            Value::Number(id) => Ok(id.to_string()),
+            _ => Err(RemoteError::Response("no job id".to_string())),
+        }
+    }
+
+    fn status(&self, job_id: &str) -> Result<JobStatus, RemoteError> {
+        let mut request = ureq::get(format!("{}/jobs/{}", self.base_url, job_id));
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        let response = read_json(request.call())?;
+        match response["status"].as_str() {
+            Some("queued") => Ok(JobStatus::Queued),
+            Some("running") => Ok(JobStatus::Running),
+            Some("done") => self.parse_counts(&response["counts"]).map(JobStatus::Done),
+            Some("failed") => Ok(JobStatus::Failed(
+                response["error"]
+                    .as_str()
+                    .unwrap_or("no reason given")
+                    .to_string(),
+            )),
+            Some(other) => Err(RemoteError::Response(format!("unknown status '{}'", other))),
+            None => Err(RemoteError::Response("no status".to_string())),
+        }
+    }
+}
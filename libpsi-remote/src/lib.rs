@@ -0,0 +1,11 @@
+pub mod client;
+pub mod error;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod provider;
+
+pub use client::*;
+pub use error::*;
+#[cfg(feature = "http")]
+pub use http::*;
+pub use provider::*;
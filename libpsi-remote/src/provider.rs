@@ -0,0 +1,32 @@
+use crate::RemoteError;
+use libpsi_core::Counts;
+
+/// A job as sent to a provider.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JobRequest {
+    /// OpenQASM 2.0 source; every qubit read out is measured explicitly.
+    pub qasm: String,
+    pub shots: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Done(Counts),
+    Failed(String),
+}
+
+/// A service that runs OpenQASM jobs, usually a cloud provider's REST API.
+///
+/// Providers translate results into psi's bit order (classical bit 0
+/// leftmost) before returning them, so counts compare directly with
+/// `QuantumCircuit::sample`.
+pub trait Provider {
+    fn name(&self) -> &str;
+
+    /// Submits a job and returns the provider's id for it.
+    fn submit(&self, job: &JobRequest) -> Result<String, RemoteError>;
+
+    fn status(&self, job_id: &str) -> Result<JobStatus, RemoteError>;
+}
@@ -0,0 +1,245 @@
+use super::charset::Charset;
+use super::terminal::Terminal;
+use super::visualizer::Visualizer;
+use core::fmt;
+use libpsi_core::{format_amplitude, format_dirac, Complex, QuantumState, Vector};
+use std::f64::consts::PI;
+
+const DEFAULT_BAR_WIDTH: usize = 32;
+const DEFAULT_MAX_QUBITS: usize = 6;
+const EPSILON: f64 = 1e-10;
+
+/// Renders every basis state of a state vector as a magnitude bar followed by
+/// a phase arrow and the phase as a multiple of π.
+///
+/// Above `max_qubits` only non-zero amplitudes are listed.
+pub struct AmplitudeRenderer<'a> {
+    state: &'a QuantumState,
+    num_qubits: usize,
+    bar_width: usize,
+    max_qubits: usize,
+    charset: Charset,
+}
+
+impl<'a> AmplitudeRenderer<'a> {
+    pub fn new(state: &'a QuantumState) -> Self {
+        let num_qubits = state.size().max(1).trailing_zeros() as usize;
+        AmplitudeRenderer {
+            state,
+            num_qubits,
+            bar_width: DEFAULT_BAR_WIDTH,
+            max_qubits: DEFAULT_MAX_QUBITS,
+            charset: Charset::Unicode,
+        }
+    }
+
+    pub fn with_bar_width(mut self, bar_width: usize) -> Self {
+        self.bar_width = bar_width.max(1);
+        self
+    }
+
+    pub fn with_max_qubits(mut self, max_qubits: usize) -> Self {
+        self.max_qubits = max_qubits;
+        self
+    }
+
+    /// Takes the charset from `terminal`.
+    pub fn with_terminal(mut self, terminal: Terminal) -> Self {
+        self.charset = terminal.charset();
+        self
+    }
+
+    pub fn with_charset(mut self, charset: Charset) -> Self {
+        self.charset = charset;
+        self
+    }
+
+    fn bar(&self, magnitude: f64) -> String {
+        if self.charset == Charset::Ascii {
+            let full = (magnitude.clamp(0.0, 1.0) * self.bar_width as f64).round() as usize;
+            return format!("{}{}", "#".repeat(full), " ".repeat(self.bar_width - full));
+        }
+
+        let eighths = (magnitude.clamp(0.0, 1.0) * (self.bar_width * 8) as f64).round() as usize;
+        let full = eighths / 8;
+        let partial = eighths % 8;
+
+        let mut bar = "█".repeat(full);
+        if partial > 0 {
+            bar.push(['▏', '▎', '▍', '▌', '▋', '▊', '▉'][partial - 1]);
+        }
+        let used = full + usize::from(partial > 0);
+        bar.push_str(&" ".repeat(self.bar_width - used));
+        bar
+    }
+}
+
+fn phase_arrow(phase: f64) -> char {
+    let sector = ((phase.rem_euclid(2.0 * PI) / (PI / 4.0)).round() as usize) % 8;
+    ['→', '↗', '↑', '↖', '←', '↙', '↓', '↘'][sector]
+}
+
+fn format_phase(phase: f64, charset: Charset) -> String {
+    let pi = match charset {
+        Charset::Unicode => "π",
+        Charset::Ascii => "pi",
+    };
+    format_turns(phase, pi)
+}
+
+fn format_turns(phase: f64, pi: &str) -> String {
+    let turns = phase / PI;
+    for denominator in [1i64, 2, 3, 4, 6, 8] {
+        let numerator = (turns * denominator as f64).round();
+        if (turns * denominator as f64 - numerator).abs() < 1e-8 {
+            let numerator = numerator as i64;
+            return match (numerator, denominator) {
+                (0, _) => "0".to_string(),
+                (1, 1) => pi.to_string(),
+                (-1, 1) => format!("-{}", pi),
+                (n, 1) => format!("{}{}", n, pi),
+                (1, d) => format!("{}/{}", pi, d),
+                (-1, d) => format!("-{}/{}", pi, d),
+                (n, d) => format!("{}{}/{}", n, pi, d),
+            };
+        }
+    }
+    format!("{:.4}", phase)
+}
+
+fn ascii_amplitude(amp: &Complex<f64>) -> String {
+    if amp.imaginary.abs() < EPSILON {
+        format!("{:.4}", amp.real)
+    } else if amp.real.abs() < EPSILON {
+        format!("{:.4}i", amp.imaginary)
+    } else {
+        format!("{:.4}{:+.4}i", amp.real, amp.imaginary)
+    }
+}
+
+/// One-line Dirac form of a state, leaving out basis states with
+/// probability below `cutoff`.
+pub fn format_state(
+    state: &QuantumState,
+    num_qubits: usize,
+    cutoff: f64,
+    charset: Charset,
+) -> String {
+    let amplitudes: Vec<Complex<f64>> = (0..state.size()).map(|i| state.get(i)).collect();
+    if charset == Charset::Unicode {
+        return format_dirac(&amplitudes, num_qubits, cutoff);
+    }
+
+    let mut out = String::new();
+    for (i, amp) in amplitudes.iter().enumerate() {
+        if amp.norm2() < cutoff.max(EPSILON) {
+            continue;
+        }
+        let mut coeff = ascii_amplitude(amp);
+        let complex = amp.real.abs() >= EPSILON && amp.imaginary.abs() >= EPSILON;
+        let negative = !complex && coeff.starts_with('-');
+        if complex {
+            coeff = format!("({})", coeff);
+        } else if negative {
+            coeff.remove(0);
+        }
+        let sign = match (out.is_empty(), negative) {
+            (true, true) => "-",
+            (true, false) => "",
+            (false, true) => " - ",
+            (false, false) => " + ",
+        };
+        out.push_str(&format!(
+            "{}{}|{:0width$b}>",
+            sign,
+            coeff,
+            i,
+            width = num_qubits
+        ));
+    }
+    if out.is_empty() {
+        "0".to_string()
+    } else {
+        out
+    }
+}
+
+impl<'a> Visualizer for AmplitudeRenderer<'a> {
+    fn export(&self) -> String {
+        format!("{}", self)
+    }
+}
+
+impl<'a> fmt::Display for AmplitudeRenderer<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let n = self.num_qubits;
+        let show_all = n <= self.max_qubits;
+
+        let rows: Vec<(String, String, String, String)> = (0..self.state.size())
+            .filter_map(|i| {
+                let amp = self.state.get(i);
+                let magnitude = amp.abs();
+                if !show_all && magnitude < EPSILON {
+                    return None;
+                }
+
+                let (basis, phase, amplitude) = match self.charset {
+                    Charset::Unicode => (
+                        format!("|{:0width$b}⟩", i, width = n),
+                        format!(
+                            "{} {}",
+                            phase_arrow(amp.phase()),
+                            format_phase(amp.phase(), self.charset)
+                        ),
+                        format_amplitude(&amp),
+                    ),
+                    Charset::Ascii => (
+                        format!("|{:0width$b}>", i, width = n),
+                        format_phase(amp.phase(), self.charset),
+                        ascii_amplitude(&amp),
+                    ),
+                };
+                let phase = if magnitude < EPSILON {
+                    String::new()
+                } else {
+                    phase
+                };
+                Some((basis, self.bar(magnitude), amplitude, phase))
+            })
+            .collect();
+
+        let amp_width = rows
+            .iter()
+            .map(|(_, _, amp, _)| amp.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        let (top, bottom, side) = match self.charset {
+            Charset::Unicode => (('┌', '┐'), ('└', '┘'), '│'),
+            Charset::Ascii => (('+', '+'), ('+', '+'), '|'),
+        };
+        let rule = self
+            .charset
+            .quantum_wire()
+            .to_string()
+            .repeat(self.bar_width);
+
+        writeln!(f, "{} {}{}{}", " ".repeat(n + 2), top.0, rule, top.1)?;
+        for (basis, bar, amp, phase) in &rows {
+            let line = format!(
+                "{} {}{}{} {:<width$}  {}",
+                basis,
+                side,
+                bar,
+                side,
+                amp,
+                phase,
+                width = amp_width
+            );
+            writeln!(f, "{}", line.trim_end())?;
+        }
+        writeln!(f, "{} {}{}{}", " ".repeat(n + 2), bottom.0, rule, bottom.1)?;
+
+        Ok(())
+    }
+}
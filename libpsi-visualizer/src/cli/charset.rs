@@ -0,0 +1,181 @@
+/// Glyph set used by the circuit renderers. `Ascii` avoids box-drawing and
+/// other non-ASCII characters for terminals, logs and CI artifacts that
+/// cannot display them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Charset {
+    #[default]
+    Unicode,
+    Ascii,
+}
+
+impl Charset {
+    pub(crate) fn quantum_wire(self) -> char {
+        match self {
+            Charset::Unicode => '─',
+            Charset::Ascii => '-',
+        }
+    }
+
+    pub(crate) fn classical_wire(self) -> char {
+        match self {
+            Charset::Unicode => '═',
+            Charset::Ascii => '=',
+        }
+    }
+
+    pub(crate) fn quantum_vertical(self) -> char {
+        match self {
+            Charset::Unicode => '│',
+            Charset::Ascii => '|',
+        }
+    }
+
+    pub(crate) fn classical_vertical(self) -> char {
+        match self {
+            Charset::Unicode => '║',
+            Charset::Ascii => ':',
+        }
+    }
+
+    pub(crate) fn control(self) -> char {
+        match self {
+            Charset::Unicode => '●',
+            Charset::Ascii => '@',
+        }
+    }
+
+    pub(crate) fn target(self) -> char {
+        match self {
+            Charset::Unicode => '⊕',
+            Charset::Ascii => '+',
+        }
+    }
+
+    pub(crate) fn swap(self) -> char {
+        match self {
+            Charset::Unicode => '╳',
+            Charset::Ascii => 'x',
+        }
+    }
+
+    /// Where a measurement coming down from a qubit meets its classical wire.
+    pub(crate) fn measure_down(self) -> char {
+        match self {
+            Charset::Unicode => '╩',
+            Charset::Ascii => 'v',
+        }
+    }
+
+    /// Where a measurement coming across from a qubit meets its classical wire.
+    pub(crate) fn measure_across(self) -> char {
+        match self {
+            Charset::Unicode => '╣',
+            Charset::Ascii => '>',
+        }
+    }
+
+    /// Barrier drawn across a horizontal wire.
+    pub(crate) fn barrier(self) -> char {
+        match self {
+            Charset::Unicode => '┆',
+            Charset::Ascii => '.',
+        }
+    }
+
+    /// Barrier drawn across a vertical wire.
+    pub(crate) fn barrier_across(self) -> char {
+        match self {
+            Charset::Unicode => '┄',
+            Charset::Ascii => '.',
+        }
+    }
+
+    /// Label of a row standing in for elided horizontal wires.
+    pub(crate) fn ellipsis_down(self) -> &'static str {
+        match self {
+            Charset::Unicode => "⋮",
+            Charset::Ascii => ":",
+        }
+    }
+
+    /// Header of a column standing in for elided vertical wires.
+    pub(crate) fn ellipsis_across(self) -> &'static str {
+        match self {
+            Charset::Unicode => "⋯",
+            Charset::Ascii => "...",
+        }
+    }
+
+    pub(crate) fn end(self) -> char {
+        match self {
+            Charset::Unicode => '░',
+            Charset::Ascii => '#',
+        }
+    }
+
+    pub(crate) fn continues(self) -> char {
+        match self {
+            Charset::Unicode => '»',
+            Charset::Ascii => '>',
+        }
+    }
+
+    pub(crate) fn continued(self) -> char {
+        match self {
+            Charset::Unicode => '«',
+            Charset::Ascii => '<',
+        }
+    }
+
+    /// Fill of a busy stretch in the layer chart.
+    pub(crate) fn bar(self) -> char {
+        match self {
+            Charset::Unicode => '█',
+            Charset::Ascii => '#',
+        }
+    }
+
+    /// Fill of an idle stretch in the layer chart.
+    pub(crate) fn idle(self) -> char {
+        match self {
+            Charset::Unicode => '·',
+            Charset::Ascii => '.',
+        }
+    }
+
+    pub(crate) fn arrow(self) -> &'static str {
+        match self {
+            Charset::Unicode => "→",
+            Charset::Ascii => "->",
+        }
+    }
+
+    pub(crate) fn brackets(self) -> (char, char) {
+        match self {
+            Charset::Unicode => ('[', ']'),
+            Charset::Ascii => ('|', '|'),
+        }
+    }
+
+    pub(crate) fn boxed(self, name: &str) -> String {
+        let (open, close) = self.brackets();
+        format!("{}{}{}", open, self.gate_name(name), close)
+    }
+
+    pub(crate) fn gate_name(self, name: &str) -> String {
+        if self == Charset::Unicode {
+            return name.to_string();
+        }
+        match name {
+            "S†" => "sdg".to_string(),
+            "T†" => "tdg".to_string(),
+            "√X" => "sx".to_string(),
+            "√X†" => "sxdg".to_string(),
+            "|0⟩" => "|0>".to_string(),
+            _ => name
+                .chars()
+                .map(|ch| if ch.is_ascii() { ch } else { '?' })
+                .collect(),
+        }
+    }
+}
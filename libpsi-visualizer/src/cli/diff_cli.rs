@@ -0,0 +1,325 @@
+use super::charset::Charset;
+use super::layout;
+use super::terminal::Terminal;
+use super::theme::Theme;
+use super::visualizer::Visualizer;
+use core::fmt;
+use libpsi_core::{GateOp, QuantumCircuit};
+
+/// One aligned operation of a `CircuitDiff`. Indices refer to positions in
+/// the `before` and `after` operation lists.
+#[derive(Clone)]
+pub enum DiffEntry {
+    Unchanged {
+        before: usize,
+        after: usize,
+        op: GateOp,
+    },
+    Removed {
+        before: usize,
+        op: GateOp,
+    },
+    Inserted {
+        after: usize,
+        op: GateOp,
+    },
+    /// The same gate on the same wires, with different parameters or title.
+    Changed {
+        before: usize,
+        after: usize,
+        old: GateOp,
+        new: GateOp,
+    },
+}
+
+/// Alignment of two circuits, e.g. before and after an optimisation pass.
+///
+/// Operations are matched along the longest common subsequence of gates
+/// acting on the same wires; matched gates whose parameters differ are
+/// reported as changed rather than as a removal and an insertion.
+pub struct CircuitDiff {
+    entries: Vec<DiffEntry>,
+    qubit_names: Vec<String>,
+    classical_names: Vec<String>,
+}
+
+fn same_slot(a: &GateOp, b: &GateOp) -> bool {
+    a.name() == b.name()
+        && a.quantum_targets() == b.quantum_targets()
+        && a.classical_targets() == b.classical_targets()
+}
+
+fn same_op(a: &GateOp, b: &GateOp) -> bool {
+    let same_label = match (a, b) {
+        (GateOp::Barrier(_, x), GateOp::Barrier(_, y)) => x == y,
+        (GateOp::Snapshot(x, k), GateOp::Snapshot(y, l)) => x == y && k == l,
+        _ => true,
+    };
+    same_slot(a, b)
+        && a.params() == b.params()
+        && a.symbols() == b.symbols()
+        && a.condition() == b.condition()
+        && same_label
+}
+
+impl CircuitDiff {
+    pub fn new(before: &QuantumCircuit, after: &QuantumCircuit) -> Self {
+        let old = before.operations();
+        let new = after.operations();
+        let (n, m) = (old.len(), new.len());
+
+        // lcs[i][j] is the length of the alignment of old[i..] with new[j..].
+        let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lcs[i][j] = if same_slot(&old[i], &new[j]) {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+
+        let mut entries = Vec::with_capacity(n.max(m));
+        let (mut i, mut j) = (0, 0);
+        while i < n || j < m {
+            if i < n && j < m && same_slot(&old[i], &new[j]) && lcs[i][j] == lcs[i + 1][j + 1] + 1 {
+                entries.push(if same_op(&old[i], &new[j]) {
+                    DiffEntry::Unchanged {
+                        before: i,
+                        after: j,
+                        op: new[j].clone(),
+                    }
+                } else {
+                    DiffEntry::Changed {
+                        before: i,
+                        after: j,
+                        old: old[i].clone(),
+                        new: new[j].clone(),
+                    }
+                });
+                i += 1;
+                j += 1;
+            } else if j == m || (i < n && lcs[i + 1][j] >= lcs[i][j + 1]) {
+                entries.push(DiffEntry::Removed {
+                    before: i,
+                    op: old[i].clone(),
+                });
+                i += 1;
+            } else {
+                entries.push(DiffEntry::Inserted {
+                    after: j,
+                    op: new[j].clone(),
+                });
+                j += 1;
+            }
+        }
+
+        let label = |q: usize| {
+            if q < after.num_qubits() {
+                after.qubit_label(q)
+            } else {
+                before.qubit_label(q)
+            }
+        };
+        let bit_label = |c: usize| {
+            if c < after.num_classical() {
+                after.classical_label(c)
+            } else {
+                before.classical_label(c)
+            }
+        };
+
+        CircuitDiff {
+            entries,
+            qubit_names: (0..before.num_qubits().max(after.num_qubits()))
+                .map(label)
+                .collect(),
+            classical_names: (0..before.num_classical().max(after.num_classical()))
+                .map(bit_label)
+                .collect(),
+        }
+    }
+
+    pub fn entries(&self) -> &[DiffEntry] {
+        &self.entries
+    }
+
+    pub fn inserted(&self) -> usize {
+        self.count(|entry| matches!(entry, DiffEntry::Inserted { .. }))
+    }
+
+    pub fn removed(&self) -> usize {
+        self.count(|entry| matches!(entry, DiffEntry::Removed { .. }))
+    }
+
+    pub fn changed(&self) -> usize {
+        self.count(|entry| matches!(entry, DiffEntry::Changed { .. }))
+    }
+
+    pub fn is_identical(&self) -> bool {
+        self.entries
+            .iter()
+            .all(|entry| matches!(entry, DiffEntry::Unchanged { .. }))
+    }
+
+    fn count(&self, pred: impl Fn(&DiffEntry) -> bool) -> usize {
+        self.entries.iter().filter(|entry| pred(entry)).count()
+    }
+}
+
+/// Prints a `CircuitDiff` one operation per line, unified-diff style: `-`
+/// for removed, `+` for inserted and `~` for changed operations, preceded by
+/// their indices in the two circuits.
+pub struct DiffRenderer<'a> {
+    diff: &'a CircuitDiff,
+    theme: Theme,
+    charset: Charset,
+}
+
+impl<'a> DiffRenderer<'a> {
+    pub fn new(diff: &'a CircuitDiff) -> Self {
+        DiffRenderer {
+            diff,
+            theme: Theme::plain(),
+            charset: Charset::Unicode,
+        }
+    }
+
+    /// Takes the charset and theme from `terminal`.
+    pub fn with_terminal(mut self, terminal: Terminal) -> Self {
+        self.charset = terminal.charset();
+        self.theme = terminal.theme();
+        self
+    }
+
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    pub fn with_charset(mut self, charset: Charset) -> Self {
+        self.charset = charset;
+        self
+    }
+
+    fn gate_text(&self, op: &GateOp) -> String {
+        let name = self.charset.gate_name(op.name());
+        let params = op.params();
+        if let GateOp::Barrier(_, Some(title)) = op {
+            format!("{}(\"{}\")", name, title)
+        } else if let GateOp::Snapshot(label, kind) = op {
+            format!("{}(\"{}\", {})", name, label, kind)
+        } else if params.is_empty() {
+            name
+        } else {
+            let params: Vec<String> = params.iter().map(|p| format!("{:.2}", p)).collect();
+            format!("{}({})", name, params.join(", "))
+        }
+    }
+
+    fn wires_text(&self, op: &GateOp) -> String {
+        let qubits: Vec<&str> = op
+            .quantum_targets()
+            .iter()
+            .map(|&q| self.diff.qubit_names[q].as_str())
+            .collect();
+        let mut text = qubits.join(", ");
+        for c in op.classical_targets() {
+            text.push_str(&format!(
+                " {} {}",
+                self.charset.arrow(),
+                self.diff.classical_names[c]
+            ));
+        }
+        if let Some((bit, value)) = op.condition() {
+            text.push_str(&format!(" if {}", layout::condition_text(bit, value)));
+        }
+        text
+    }
+}
+
+impl<'a> Visualizer for DiffRenderer<'a> {
+    fn export(&self) -> String {
+        format!("{}", self)
+    }
+}
+
+impl<'a> fmt::Display for DiffRenderer<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries = self.diff.entries();
+        let last = entries
+            .iter()
+            .map(|entry| match entry {
+                DiffEntry::Unchanged { before, after, .. }
+                | DiffEntry::Changed { before, after, .. } => (*before).max(*after),
+                DiffEntry::Removed { before, .. } => *before,
+                DiffEntry::Inserted { after, .. } => *after,
+            })
+            .max()
+            .unwrap_or(0);
+        let width = last.to_string().len();
+        let index = |i: Option<usize>| match i {
+            Some(i) => format!("{:>width$}", i, width = width),
+            None => " ".repeat(width),
+        };
+
+        for entry in entries {
+            let (before, after, mark, style, text) = match entry {
+                DiffEntry::Unchanged { before, after, op } => (
+                    Some(*before),
+                    Some(*after),
+                    ' ',
+                    "",
+                    format!("{} {}", self.gate_text(op), self.wires_text(op)),
+                ),
+                DiffEntry::Removed { before, op } => (
+                    Some(*before),
+                    None,
+                    '-',
+                    self.theme.removed,
+                    format!("{} {}", self.gate_text(op), self.wires_text(op)),
+                ),
+                DiffEntry::Inserted { after, op } => (
+                    None,
+                    Some(*after),
+                    '+',
+                    self.theme.inserted,
+                    format!("{} {}", self.gate_text(op), self.wires_text(op)),
+                ),
+                DiffEntry::Changed {
+                    before,
+                    after,
+                    old,
+                    new,
+                } => (
+                    Some(*before),
+                    Some(*after),
+                    '~',
+                    self.theme.changed,
+                    format!(
+                        "{} {} {} {}",
+                        self.gate_text(old),
+                        self.charset.arrow(),
+                        self.gate_text(new),
+                        self.wires_text(new)
+                    ),
+                ),
+            };
+            let line = format!("{} {} {} {}", index(before), index(after), mark, text);
+            writeln!(f, "{}", self.theme.paint(style, line.trim_end()))?;
+        }
+
+        if self.diff.is_identical() {
+            writeln!(f, "no differences")
+        } else {
+            writeln!(
+                f,
+                "{} inserted, {} removed, {} changed",
+                self.diff.inserted(),
+                self.diff.removed(),
+                self.diff.changed()
+            )
+        }
+    }
+}
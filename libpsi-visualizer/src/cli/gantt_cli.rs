@@ -0,0 +1,212 @@
+use super::charset::Charset;
+use super::terminal::Terminal;
+use super::theme::Theme;
+use super::visualizer::Visualizer;
+use core::fmt;
+use libpsi_core::{LayerProfile, StructureAwareKernelBatch};
+
+const DEFAULT_LAYER_WIDTH: usize = 6;
+const DEFAULT_CHART_WIDTH: usize = 60;
+
+/// Gantt-style chart of the execution layers of a `StructureAwareKernelBatch`:
+/// layers run left to right, one row per qubit, and each kernel is a bar on
+/// the qubits it acts on. Idle stretches show where a layer leaves qubits
+/// free, and the utilisation column how busy each qubit is overall.
+///
+/// With a `LayerProfile` every layer is as wide as its slowest kernel and
+/// every bar as long as its kernel took, scaled to `chart_width` columns;
+/// otherwise all layers and kernels are drawn the same width.
+pub struct GanttRenderer<'a> {
+    batch: &'a StructureAwareKernelBatch,
+    profile: Option<&'a LayerProfile>,
+    chart_width: usize,
+    theme: Theme,
+    charset: Charset,
+}
+
+impl<'a> GanttRenderer<'a> {
+    pub fn new(batch: &'a StructureAwareKernelBatch) -> Self {
+        GanttRenderer {
+            batch,
+            profile: None,
+            chart_width: DEFAULT_CHART_WIDTH,
+            theme: Theme::plain(),
+            charset: Charset::Unicode,
+        }
+    }
+
+    pub fn with_profile(mut self, profile: &'a LayerProfile) -> Self {
+        self.profile = Some(profile);
+        self
+    }
+
+    pub fn with_chart_width(mut self, chart_width: usize) -> Self {
+        self.chart_width = chart_width.max(1);
+        self
+    }
+
+    /// Takes the charset and theme from `terminal`.
+    pub fn with_terminal(mut self, terminal: Terminal) -> Self {
+        self.charset = terminal.charset();
+        self.theme = terminal.theme();
+        self
+    }
+
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    pub fn with_charset(mut self, charset: Charset) -> Self {
+        self.charset = charset;
+        self
+    }
+
+    /// Kernel durations in seconds, one row per layer; every kernel counts as
+    /// one unit without a profile.
+    fn durations(&self) -> Vec<Vec<f64>> {
+        self.batch
+            .layers()
+            .iter()
+            .enumerate()
+            .map(|(l, layer)| {
+                (0..layer.kernels.len())
+                    .map(|k| match self.profile {
+                        Some(profile) => profile.kernel_times[l][k].as_secs_f64(),
+                        None => 1.0,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn layer_widths(&self, layer_times: &[f64]) -> Vec<usize> {
+        if self.profile.is_none() {
+            return vec![DEFAULT_LAYER_WIDTH; layer_times.len()];
+        }
+        let total: f64 = layer_times.iter().sum();
+        layer_times
+            .iter()
+            .map(|&t| {
+                if total > 0.0 {
+                    ((t / total) * self.chart_width as f64).round().max(1.0) as usize
+                } else {
+                    1
+                }
+            })
+            .collect()
+    }
+}
+
+impl<'a> Visualizer for GanttRenderer<'a> {
+    fn export(&self) -> String {
+        format!("{}", self)
+    }
+}
+
+impl<'a> fmt::Display for GanttRenderer<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let nq = self.batch.num_qubits();
+        let layers = self.batch.layers();
+        let durations = self.durations();
+        let layer_times: Vec<f64> = durations
+            .iter()
+            .map(|times| times.iter().copied().fold(0.0, f64::max))
+            .collect();
+        let widths = self.layer_widths(&layer_times);
+        let total_time: f64 = layer_times.iter().sum();
+
+        let labels: Vec<String> = (0..nq).map(|q| format!("q{}: ", q)).collect();
+        let label_width = labels.iter().map(|l| l.len()).max().unwrap_or(0);
+
+        let mut header = " ".repeat(label_width);
+        let mut next_free = 0;
+        let mut offset = 0;
+        for (l, &width) in widths.iter().enumerate() {
+            let index = format!("L{}", l);
+            if offset >= next_free {
+                header.push_str(&" ".repeat(offset - next_free));
+                header.push_str(&index);
+                next_free = offset + index.len();
+            }
+            offset += width + 1;
+        }
+        writeln!(f, "{}", header.trim_end())?;
+
+        let mut busy = vec![0.0; nq];
+        let mut rows: Vec<String> = labels
+            .iter()
+            .map(|label| format!("{:>width$}", label, width = label_width))
+            .collect();
+        for (l, layer) in layers.iter().enumerate() {
+            let width = widths[l];
+            let mut cells: Vec<Option<(usize, &str)>> = vec![None; nq];
+            for (k, kernel) in layer.kernels.iter().enumerate() {
+                let t = durations[l][k];
+                let len = if layer_times[l] > 0.0 {
+                    ((t / layer_times[l]) * width as f64)
+                        .round()
+                        .clamp(1.0, width as f64) as usize
+                } else {
+                    width
+                };
+                let style = if kernel.name.contains('+') {
+                    self.theme.custom
+                } else {
+                    self.theme.clifford
+                };
+                for &q in &kernel.targets {
+                    cells[q] = Some((len, style));
+                    busy[q] += t;
+                }
+            }
+
+            for (row, cell) in rows.iter_mut().zip(&cells) {
+                let (len, style) = cell.unwrap_or((0, ""));
+                row.push_str(
+                    &self
+                        .theme
+                        .paint(style, &self.charset.bar().to_string().repeat(len)),
+                );
+                row.push_str(&self.theme.paint(
+                    self.theme.wire,
+                    &self.charset.idle().to_string().repeat(width - len),
+                ));
+                row.push_str(&self.theme.paint(
+                    self.theme.barrier,
+                    &self.charset.quantum_vertical().to_string(),
+                ));
+            }
+        }
+
+        for (q, row) in rows.iter().enumerate() {
+            let utilisation = if total_time > 0.0 {
+                busy[q] / total_time * 100.0
+            } else {
+                0.0
+            };
+            writeln!(f, "{} {:>3.0}%", row, utilisation)?;
+        }
+
+        let kernels: usize = layers.iter().map(|layer| layer.kernels.len()).sum();
+        let per_layer = if layers.is_empty() {
+            0.0
+        } else {
+            kernels as f64 / layers.len() as f64
+        };
+        let plural = |n: usize| if n == 1 { "" } else { "s" };
+        writeln!(
+            f,
+            "{} kernel{} in {} layer{}, {:.2} kernels per layer",
+            kernels,
+            plural(kernels),
+            layers.len(),
+            plural(layers.len()),
+            per_layer
+        )?;
+        if let Some(profile) = self.profile {
+            writeln!(f, "{}", profile)?;
+        }
+        Ok(())
+    }
+}
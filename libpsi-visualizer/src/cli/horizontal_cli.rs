@@ -1,14 +1,236 @@
+use super::amplitude_cli;
+use super::charset::Charset;
+use super::layout::{self, Cell, IdleQubits, Row, WireView};
+use super::terminal::{self, Terminal};
+use super::theme::Theme;
 use super::visualizer::Visualizer;
 use core::fmt;
-use libpsi_core::{GateOp, QuantumCircuit};
+use libpsi_core::{QuantumCircuit, QuantumState};
 
+/// Long diagrams are folded into stacked segments no wider than `max_width`
+/// characters, with `»`/`«` marking where a segment continues. The width
+/// defaults to the terminal width from `COLUMNS`; if that is unavailable the
+/// diagram is never folded. `with_terminal` sets width, charset and theme
+/// from a `Terminal` in one go.
+///
+/// Given a state with `with_probabilities`, each qubit wire ends with the
+/// probability of measuring 1 on it. `with_column_numbers` numbers the time
+/// steps along the top, and `with_state_summary` writes the circuit's
+/// computed state under the diagram.
 pub struct HorizontalRenderer<'a> {
     circuit: &'a QuantumCircuit,
+    max_width: Option<usize>,
+    theme: Theme,
+    charset: Charset,
+    qubit_labels: Vec<String>,
+    classical_labels: Vec<String>,
+    idle_qubits: IdleQubits,
+    state: Option<&'a QuantumState>,
+    column_numbers: bool,
+    summary: Option<f64>,
+}
+
+#[derive(Clone, Copy)]
+enum Wire {
+    Quantum,
+    Elided,
+    Classical,
+}
+
+struct Column {
+    q_cells: Vec<String>,
+    c_cells: Vec<String>,
+    gap: String,
+    width: usize,
+    title: Option<String>,
 }
 
 impl<'a> HorizontalRenderer<'a> {
     pub fn new(circuit: &'a QuantumCircuit) -> Self {
-        HorizontalRenderer { circuit }
+        HorizontalRenderer {
+            circuit,
+            max_width: terminal::detect_width(),
+            theme: Theme::plain(),
+            charset: Charset::Unicode,
+            qubit_labels: Vec::new(),
+            classical_labels: Vec::new(),
+            idle_qubits: IdleQubits::Show,
+            state: None,
+            column_numbers: false,
+            summary: None,
+        }
+    }
+
+    pub fn with_max_width(mut self, max_width: usize) -> Self {
+        self.max_width = Some(max_width);
+        self
+    }
+
+    /// Takes the folding width, charset and theme from `terminal`.
+    pub fn with_terminal(mut self, terminal: Terminal) -> Self {
+        self.max_width = terminal.width;
+        self.charset = terminal.charset();
+        self.theme = terminal.theme();
+        self
+    }
+
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    pub fn with_charset(mut self, charset: Charset) -> Self {
+        self.charset = charset;
+        self
+    }
+
+    /// Overrides the circuit's qubit labels, in wire order.
+    pub fn with_qubit_labels(mut self, labels: &[&str]) -> Self {
+        self.qubit_labels = labels.iter().map(|label| label.to_string()).collect();
+        self
+    }
+
+    /// Overrides the circuit's classical bit labels, in wire order.
+    pub fn with_classical_labels(mut self, labels: &[&str]) -> Self {
+        self.classical_labels = labels.iter().map(|label| label.to_string()).collect();
+        self
+    }
+
+    pub fn with_idle_qubits(mut self, idle_qubits: IdleQubits) -> Self {
+        self.idle_qubits = idle_qubits;
+        self
+    }
+
+    pub fn with_probabilities(mut self, state: &'a QuantumState) -> Self {
+        self.state = Some(state);
+        self
+    }
+
+    pub fn with_column_numbers(mut self) -> Self {
+        self.column_numbers = true;
+        self
+    }
+
+    /// Writes the amplitudes of the circuit's computed state, in Dirac form
+    /// and leaving out basis states with probability below `cutoff`, under
+    /// the diagram.
+    pub fn with_state_summary(mut self, cutoff: f64) -> Self {
+        self.summary = Some(cutoff);
+        self
+    }
+
+    fn write_summary(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(cutoff) = self.summary else {
+            return Ok(());
+        };
+        match self.circuit.computed_state() {
+            Some(state) => writeln!(
+                f,
+                "State: {}",
+                amplitude_cli::format_state(state, self.circuit.num_qubits(), cutoff, self.charset)
+            ),
+            None => writeln!(f, "State: (not computed)"),
+        }
+    }
+
+    fn annotation(&self, view: &WireView, row: usize) -> String {
+        match (self.state, view.qubits[row]) {
+            (Some(state), Some(q)) => format!(
+                "  P(1)={:.2}",
+                layout::marginal_one(state, self.circuit.num_qubits(), q)
+            ),
+            _ => String::new(),
+        }
+    }
+
+    fn cell_text(&self, cell: Option<&(Cell, &str)>, width: usize, wire: Wire) -> String {
+        let (fill, wire_style) = match wire {
+            Wire::Quantum => (self.charset.quantum_wire(), self.theme.wire),
+            Wire::Elided => (' ', ""),
+            Wire::Classical => (self.charset.classical_wire(), self.theme.classical),
+        };
+        let Some((cell, style)) = cell else {
+            return self
+                .theme
+                .paint(wire_style, &fill.to_string().repeat(width));
+        };
+        let content = match cell {
+            Cell::Gate(label) | Cell::Block { text: label, .. } | Cell::Condition(label) => {
+                label.clone()
+            }
+            Cell::Control => self.charset.control().to_string(),
+            Cell::Target => self.charset.target().to_string(),
+            Cell::Swap => self.charset.swap().to_string(),
+            Cell::Cross => self.charset.quantum_vertical().to_string(),
+            Cell::Measure => self.charset.boxed("M"),
+            Cell::MeasureCross | Cell::ClassicalCross => {
+                self.charset.classical_vertical().to_string()
+            }
+            Cell::ClassicalTarget => self.charset.measure_down().to_string(),
+            Cell::Barrier => self.charset.barrier().to_string(),
+        };
+
+        let (left, right) = layout::padding(content.chars().count(), width);
+        format!(
+            "{}{}{}",
+            self.theme.paint(wire_style, &fill.to_string().repeat(left)),
+            self.theme.paint(style, &content),
+            self.theme
+                .paint(wire_style, &fill.to_string().repeat(right))
+        )
+    }
+
+    /// Section titles for a run of columns, each starting above its barrier
+    /// and cut short before the next title.
+    fn title_line(columns: &[Column]) -> Option<String> {
+        let mut starts: Vec<(usize, &str)> = Vec::new();
+        let mut offset = 0;
+        for col in columns {
+            if let Some(title) = &col.title {
+                starts.push((offset + (col.width - 1) / 2, title));
+            }
+            offset += col.width;
+        }
+        if starts.is_empty() {
+            return None;
+        }
+
+        let mut line: Vec<char> = vec![' '; offset];
+        for (k, &(start, title)) in starts.iter().enumerate() {
+            let limit = starts.get(k + 1).map_or(usize::MAX, |&(next, _)| next - 1);
+            for (j, ch) in title.chars().enumerate() {
+                let pos = start + j;
+                if pos >= limit {
+                    break;
+                }
+                if pos < line.len() {
+                    line[pos] = ch;
+                } else {
+                    line.push(ch);
+                }
+            }
+        }
+
+        let line: String = line.into_iter().collect();
+        Some(line.trim_end().to_string())
+    }
+
+    fn fold(columns: &[Column], budget: usize) -> Vec<std::ops::Range<usize>> {
+        let mut segments = Vec::new();
+        let mut start = 0;
+        let mut used = 0;
+
+        for (i, col) in columns.iter().enumerate() {
+            if i > start && used + col.width > budget {
+                segments.push(start..i);
+                start = i;
+                used = 0;
+            }
+            used += col.width;
+        }
+        segments.push(start..columns.len());
+
+        segments
     }
 }
 
@@ -20,444 +242,208 @@ impl<'a> Visualizer for HorizontalRenderer<'a> {
 
 impl<'a> fmt::Display for HorizontalRenderer<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let nq = self.circuit.num_qubits();
-        let nc = self.circuit.num_classical();
-        let ops = self.circuit.operations();
+        let view = layout::wire_view(
+            self.circuit,
+            &self.qubit_labels,
+            &self.classical_labels,
+            self.idle_qubits,
+        );
+        let nq = view.num_qubits();
+        let nc = view.num_classical();
+        let ops = &view.ops;
 
-        let mut q_lines: Vec<String> = (0..nq).map(|i| format!("q{}: ", i)).collect();
-        let mut c_lines: Vec<String> = (0..nc).map(|i| format!("c{}: ", i)).collect();
+        let q_lines: Vec<String> = view
+            .qubit_names
+            .iter()
+            .enumerate()
+            .map(|(row, name)| {
+                if view.is_elided(row) {
+                    format!("{}  ", self.charset.ellipsis_down())
+                } else {
+                    format!("{}: ", name)
+                }
+            })
+            .collect();
+        let c_lines: Vec<String> = view
+            .classical_names
+            .iter()
+            .map(|name| format!("{}: ", name))
+            .collect();
 
         let max_label = q_lines
             .iter()
             .chain(c_lines.iter())
-            .map(|s| s.len())
+            .map(|s| s.chars().count())
             .max()
             .unwrap_or(3);
 
-        for line in &mut q_lines {
-            while line.len() < max_label {
-                line.insert(0, ' ');
-            }
-        }
-        for line in &mut c_lines {
-            while line.len() < max_label {
-                line.insert(0, ' ');
-            }
-        }
-        let mut gap_line = " ".repeat(max_label);
+        let q_lines: Vec<String> = q_lines
+            .iter()
+            .map(|line| format!("{:>width$}", line, width = max_label))
+            .collect();
+        let c_lines: Vec<String> = c_lines
+            .iter()
+            .map(|line| format!("{:>width$}", line, width = max_label))
+            .collect();
+        let gap_line = " ".repeat(max_label);
+
+        let end = self.charset.end();
 
         if ops.is_empty() {
-            for line in &q_lines {
-                writeln!(f, "{}───░", line)?;
+            let c_wire = self.charset.classical_wire().to_string().repeat(3);
+            for (row, line) in q_lines.iter().enumerate() {
+                let wire = if view.is_elided(row) {
+                    Wire::Elided
+                } else {
+                    Wire::Quantum
+                };
+                let note = self.annotation(&view, row);
+                writeln!(
+                    f,
+                    "{}{}{}{}",
+                    line,
+                    self.cell_text(None, 3, wire),
+                    end,
+                    note
+                )?;
             }
             if nc > 0 {
-                writeln!(f, "{}   ░", gap_line)?;
+                writeln!(f, "{}   {}", gap_line, end)?;
                 for line in &c_lines {
-                    writeln!(f, "{}═══░", line)?;
+                    writeln!(f, "{}{}{}", line, c_wire, end)?;
                 }
             }
-            return Ok(());
+            return self.write_summary(f);
         }
 
-        for op in ops {
-            let q_targets = op.quantum_targets();
-
-            let min_q = q_targets.iter().min().copied().unwrap_or(0);
-            let max_q = q_targets.iter().max().copied().unwrap_or(0);
+        let mut columns: Vec<Column> = Vec::new();
+        for moment in layout::pack_moments(ops, nq, nc) {
+            let mut q_cells: Vec<Option<(Cell, &str)>> = vec![None; nq];
+            let mut c_cells: Vec<Option<(Cell, &str)>> = vec![None; nc];
+            let mut measure_style = None;
+            let mut title = None;
 
-            match op {
-                GateOp::H(t) => {
-                    for (i, line) in q_lines.iter_mut().enumerate() {
-                        if i == *t {
-                            line.push_str("─[H]─");
-                        } else {
-                            line.push_str("─────");
-                        }
-                    }
-                    for line in c_lines.iter_mut() {
-                        line.push_str("═════");
-                    }
-                    gap_line.push_str("     ");
-                }
-                GateOp::X(t) => {
-                    for (i, line) in q_lines.iter_mut().enumerate() {
-                        if i == *t {
-                            line.push_str("─[X]─");
-                        } else {
-                            line.push_str("─────");
-                        }
-                    }
-                    for line in c_lines.iter_mut() {
-                        line.push_str("═════");
-                    }
-                    gap_line.push_str("     ");
-                }
-                GateOp::Y(t) => {
-                    for (i, line) in q_lines.iter_mut().enumerate() {
-                        if i == *t {
-                            line.push_str("─[Y]─");
-                        } else {
-                            line.push_str("─────");
-                        }
-                    }
-                    for line in c_lines.iter_mut() {
-                        line.push_str("═════");
-                    }
-                    gap_line.push_str("     ");
+            for &idx in &moment {
+                let op = &ops[idx];
+                let style = self.theme.op_style(op);
+                if layout::classical_span(op).is_some() {
+                    measure_style = Some(style);
                 }
-                GateOp::Z(t) => {
-                    for (i, line) in q_lines.iter_mut().enumerate() {
-                        if i == *t {
-                            line.push_str("─[Z]─");
-                        } else {
-                            line.push_str("─────");
-                        }
-                    }
-                    for line in c_lines.iter_mut() {
-                        line.push_str("═════");
-                    }
-                    gap_line.push_str("     ");
+                if let Some(section) = layout::section_title(op) {
+                    title = Some(section.to_string());
                 }
-                GateOp::S(t) => {
-                    for (i, line) in q_lines.iter_mut().enumerate() {
-                        if i == *t {
-                            line.push_str("─[S]─");
-                        } else {
-                            line.push_str("─────");
-                        }
-                    }
-                    for line in c_lines.iter_mut() {
-                        line.push_str("═════");
+                for (row, cell) in layout::op_cells(op, nq, self.charset) {
+                    match row {
+                        Row::Quantum(q) => q_cells[q] = Some((cell, style)),
+                        Row::Classical(c) => c_cells[c] = Some((cell, style)),
                     }
-                    gap_line.push_str("     ");
                 }
-                GateOp::T(t) => {
-                    for (i, line) in q_lines.iter_mut().enumerate() {
-                        if i == *t {
-                            line.push_str("─[T]─");
-                        } else {
-                            line.push_str("─────");
-                        }
-                    }
-                    for line in c_lines.iter_mut() {
-                        line.push_str("═════");
-                    }
-                    gap_line.push_str("     ");
-                }
-                GateOp::Sdg(t) => {
-                    for (i, line) in q_lines.iter_mut().enumerate() {
-                        if i == *t {
-                            line.push_str("─[S†]─");
-                        } else {
-                            line.push_str("──────");
-                        }
-                    }
-                    for line in c_lines.iter_mut() {
-                        line.push_str("══════");
-                    }
-                    gap_line.push_str("      ");
-                }
-                GateOp::Tdg(t) => {
-                    for (i, line) in q_lines.iter_mut().enumerate() {
-                        if i == *t {
-                            line.push_str("─[T†]─");
-                        } else {
-                            line.push_str("──────");
-                        }
-                    }
-                    for line in c_lines.iter_mut() {
-                        line.push_str("══════");
-                    }
-                    gap_line.push_str("      ");
-                }
-                GateOp::Sx(t) => {
-                    for (i, line) in q_lines.iter_mut().enumerate() {
-                        if i == *t {
-                            line.push_str("─[√X]─");
-                        } else {
-                            line.push_str("──────");
-                        }
-                    }
-                    for line in c_lines.iter_mut() {
-                        line.push_str("══════");
-                    }
-                    gap_line.push_str("      ");
-                }
-                GateOp::Sxdg(t) => {
-                    for (i, line) in q_lines.iter_mut().enumerate() {
-                        if i == *t {
-                            line.push_str("─[√X†]─");
-                        } else {
-                            line.push_str("───────");
-                        }
-                    }
-                    for line in c_lines.iter_mut() {
-                        line.push_str("═══════");
-                    }
-                    gap_line.push_str("       ");
-                }
-                GateOp::Rx(t, theta) => {
-                    let label = format!("[Rx({:.2})]", theta);
-                    for (i, line) in q_lines.iter_mut().enumerate() {
-                        if i == *t {
-                            line.push_str(&format!("─{}─", label));
-                        } else {
-                            line.push_str(&format!("─{}─", "─".repeat(label.len())));
-                        }
-                    }
-                    for line in c_lines.iter_mut() {
-                        line.push_str(&format!("═{}═", "═".repeat(label.len())));
-                    }
-                    gap_line.push_str(&format!(" {} ", " ".repeat(label.len())));
-                }
-                GateOp::Ry(t, theta) => {
-                    let label = format!("[Ry({:.2})]", theta);
-                    for (i, line) in q_lines.iter_mut().enumerate() {
-                        if i == *t {
-                            line.push_str(&format!("─{}─", label));
-                        } else {
-                            line.push_str(&format!("─{}─", "─".repeat(label.len())));
-                        }
-                    }
-                    for line in c_lines.iter_mut() {
-                        line.push_str(&format!("═{}═", "═".repeat(label.len())));
-                    }
-                    gap_line.push_str(&format!(" {} ", " ".repeat(label.len())));
-                }
-                GateOp::Rz(t, theta) => {
-                    let label = format!("[Rz({:.2})]", theta);
-                    for (i, line) in q_lines.iter_mut().enumerate() {
-                        if i == *t {
-                            line.push_str(&format!("─{}─", label));
-                        } else {
-                            line.push_str(&format!("─{}─", "─".repeat(label.len())));
-                        }
-                    }
-                    for line in c_lines.iter_mut() {
-                        line.push_str(&format!("═{}═", "═".repeat(label.len())));
-                    }
-                    gap_line.push_str(&format!(" {} ", " ".repeat(label.len())));
-                }
-                GateOp::P(t, theta) => {
-                    let label = format!("[P({:.2})]", theta);
-                    for (i, line) in q_lines.iter_mut().enumerate() {
-                        if i == *t {
-                            line.push_str(&format!("─{}─", label));
-                        } else {
-                            line.push_str(&format!("─{}─", "─".repeat(label.len())));
-                        }
-                    }
-                    for line in c_lines.iter_mut() {
-                        line.push_str(&format!("═{}═", "═".repeat(label.len())));
-                    }
-                    gap_line.push_str(&format!(" {} ", " ".repeat(label.len())));
-                }
-                GateOp::U1(t, lambda) => {
-                    let label = format!("[U1({:.2})]", lambda);
-                    for (i, line) in q_lines.iter_mut().enumerate() {
-                        if i == *t {
-                            line.push_str(&format!("─{}─", label));
-                        } else {
-                            line.push_str(&format!("─{}─", "─".repeat(label.len())));
-                        }
-                    }
-                    for line in c_lines.iter_mut() {
-                        line.push_str(&format!("═{}═", "═".repeat(label.len())));
-                    }
-                    gap_line.push_str(&format!(" {} ", " ".repeat(label.len())));
-                }
-                GateOp::U2(t, _, _) => {
-                    let label = "[U2]";
-                    for (i, line) in q_lines.iter_mut().enumerate() {
-                        if i == *t {
-                            line.push_str(&format!("─{}─", label));
-                        } else {
-                            line.push_str(&format!("─{}─", "─".repeat(label.len())));
-                        }
-                    }
-                    for line in c_lines.iter_mut() {
-                        line.push_str(&format!("═{}═", "═".repeat(label.len())));
-                    }
-                    gap_line.push_str(&format!(" {} ", " ".repeat(label.len())));
-                }
-                GateOp::U3(t, _, _, _) => {
-                    let label = "[U3]";
-                    for (i, line) in q_lines.iter_mut().enumerate() {
-                        if i == *t {
-                            line.push_str(&format!("─{}─", label));
-                        } else {
-                            line.push_str(&format!("─{}─", "─".repeat(label.len())));
-                        }
-                    }
-                    for line in c_lines.iter_mut() {
-                        line.push_str(&format!("═{}═", "═".repeat(label.len())));
-                    }
-                    gap_line.push_str(&format!(" {} ", " ".repeat(label.len())));
-                }
-                GateOp::CRx(c, t, theta) | GateOp::CRy(c, t, theta) | GateOp::CRz(c, t, theta) | GateOp::CP(c, t, theta) => {
-                    let label = match op {
-                        GateOp::CRx(_, _, _) => format!("[CRx({:.2})]", theta),
-                        GateOp::CRy(_, _, _) => format!("[CRy({:.2})]", theta),
-                        GateOp::CRz(_, _, _) => format!("[CRz({:.2})]", theta),
-                        GateOp::CP(_, _, _) => format!("[CP({:.2})]", theta),
-                        _ => unreachable!(),
-                    };
-                    for (i, line) in q_lines.iter_mut().enumerate() {
-                        if i == *c {
-                            line.push_str(&format!("─{}─", "●".to_string() + &"─".repeat(label.len() - 1)));
-                        } else if i == *t {
-                            line.push_str(&format!("─{}─", label));
-                        } else if i > min_q && i < max_q {
-                            line.push_str(&format!("─{}─", "│".to_string() + &"─".repeat(label.len() - 1)));
-                        } else {
-                            line.push_str(&format!("─{}─", "─".repeat(label.len())));
-                        }
-                    }
-                    for line in c_lines.iter_mut() {
-                        line.push_str(&format!("═{}═", "═".repeat(label.len())));
-                    }
-                    gap_line.push_str(&format!(" {} ", " ".repeat(label.len())));
-                }
-                GateOp::CNOT(c, t) => {
-                    for (i, line) in q_lines.iter_mut().enumerate() {
-                        if i == *c {
-                            line.push_str("──●──");
-                        } else if i == *t {
-                            line.push_str("──⊕──");
-                        } else if i > min_q && i < max_q {
-                            line.push_str("──│──");
-                        } else {
-                            line.push_str("─────");
-                        }
-                    }
-                    for line in c_lines.iter_mut() {
-                        line.push_str("═════");
-                    }
-                    gap_line.push_str("     ");
-                }
-                GateOp::CZ(c, t) => {
-                    for (i, line) in q_lines.iter_mut().enumerate() {
-                        if i == *c || i == *t {
-                            line.push_str("──●──");
-                        } else if i > min_q && i < max_q {
-                            line.push_str("──│──");
-                        } else {
-                            line.push_str("─────");
-                        }
-                    }
-                    for line in c_lines.iter_mut() {
-                        line.push_str("═════");
-                    }
-                    gap_line.push_str("     ");
-                }
-                GateOp::SWAP(a, b) => {
-                    for (i, line) in q_lines.iter_mut().enumerate() {
-                        if i == *a || i == *b {
-                            line.push_str("──╳──");
-                        } else if i > min_q && i < max_q {
-                            line.push_str("──│──");
-                        } else {
-                            line.push_str("─────");
-                        }
-                    }
-                    for line in c_lines.iter_mut() {
-                        line.push_str("═════");
-                    }
-                    gap_line.push_str("     ");
-                }
-                GateOp::CCNOT(c1, c2, t) => {
-                    for (i, line) in q_lines.iter_mut().enumerate() {
-                        if i == *c1 || i == *c2 {
-                            line.push_str("──●──");
-                        } else if i == *t {
-                            line.push_str("──⊕──");
-                        } else if i > min_q && i < max_q {
-                            line.push_str("──│──");
-                        } else {
-                            line.push_str("─────");
-                        }
-                    }
-                    for line in c_lines.iter_mut() {
-                        line.push_str("═════");
-                    }
-                    gap_line.push_str("     ");
-                }
-                GateOp::CSWAP(c, t1, t2) => {
-                    for (i, line) in q_lines.iter_mut().enumerate() {
-                        if i == *c {
-                            line.push_str("──●──");
-                        } else if i == *t1 || i == *t2 {
-                            line.push_str("──╳──");
-                        } else if i > min_q && i < max_q {
-                            line.push_str("──│──");
-                        } else {
-                            line.push_str("─────");
-                        }
-                    }
-                    for line in c_lines.iter_mut() {
-                        line.push_str("═════");
-                    }
-                    gap_line.push_str("     ");
-                }
-                GateOp::Measure(q, c) => {
-                    for (i, line) in q_lines.iter_mut().enumerate() {
-                        if i == *q {
-                            line.push_str("─[M]─");
-                        } else if i > *q {
-                            line.push_str("──║──");
-                        } else {
-                            line.push_str("─────");
-                        }
-                    }
-                    for (i, line) in c_lines.iter_mut().enumerate() {
-                        if i == *c {
-                            line.push_str("══╩══");
-                        } else if i < *c {
-                            line.push_str("══║══");
-                        } else {
-                            line.push_str("═════");
-                        }
-                    }
-                    gap_line.push_str("  ║  ");
+            }
+
+            let width = q_cells
+                .iter()
+                .chain(c_cells.iter())
+                .flatten()
+                .map(|(cell, _)| layout::cell_width(cell))
+                .max()
+                .unwrap_or(1)
+                .max(3)
+                + 2;
+
+            let gap = match measure_style {
+                Some(style) => {
+                    let (left, right) = layout::padding(1, width);
+                    format!(
+                        "{}{}{}",
+                        " ".repeat(left),
+                        self.theme
+                            .paint(style, &self.charset.classical_vertical().to_string()),
+                        " ".repeat(right)
+                    )
                 }
-                GateOp::Custom(gate, targets) => {
-                    let name = &gate.name;
-                    let label = format!("[{}]", name);
-
-                    for (i, line) in q_lines.iter_mut().enumerate() {
-                        if targets.contains(&i) {
-                            if i == targets[0] {
-                                line.push_str(&format!("─{}─", label));
-                            } else {
-                                line.push_str(&format!("─{}─", "─".repeat(label.len())));
-                            }
-                        } else if i > min_q && i < max_q {
-                            line.push_str(&format!(
-                                "─{}─",
-                                "│".to_string() + &"─".repeat(label.len() - 1)
-                            ));
+                None => " ".repeat(width),
+            };
+            columns.push(Column {
+                q_cells: q_cells
+                    .iter()
+                    .enumerate()
+                    .map(|(row, cell)| {
+                        let wire = if view.is_elided(row) {
+                            Wire::Elided
                         } else {
-                            line.push_str(&format!("─{}─", "─".repeat(label.len())));
-                        }
-                    }
-                    for line in c_lines.iter_mut() {
-                        line.push_str(&format!("═{}═", "═".repeat(label.len())));
-                    }
-                    gap_line.push_str(&format!(" {} ", " ".repeat(label.len())));
-                }
-            }
+                            Wire::Quantum
+                        };
+                        self.cell_text(cell.as_ref(), width, wire)
+                    })
+                    .collect(),
+                c_cells: c_cells
+                    .iter()
+                    .map(|cell| self.cell_text(cell.as_ref(), width, Wire::Classical))
+                    .collect(),
+                gap,
+                width,
+                title,
+            });
         }
 
-        for line in &q_lines {
-            writeln!(f, "{}░", line)?;
-        }
-        if nc > 0 {
-            writeln!(f, "{}░", gap_line)?;
-            for line in &c_lines {
-                writeln!(f, "{}░", line)?;
+        let segments = match self.max_width {
+            Some(max_width) => Self::fold(&columns, max_width.saturating_sub(max_label + 2)),
+            None => std::iter::once(0..columns.len()).collect(),
+        };
+
+        for (k, segment) in segments.iter().enumerate() {
+            let first = k == 0;
+            let last = k + 1 == segments.len();
+            let (open, open_gap) = if first {
+                (String::new(), "")
+            } else {
+                (self.charset.continued().to_string(), " ")
+            };
+            let close = if last { end } else { self.charset.continues() };
+            let close_gap = if last { end } else { ' ' };
+
+            if !first {
+                writeln!(f)?;
+            }
+
+            let cols = &columns[segment.clone()];
+            if self.column_numbers {
+                let numbers: String = segment
+                    .clone()
+                    .zip(cols)
+                    .map(|(i, col)| format!("{:^width$}", i, width = col.width))
+                    .collect();
+                writeln!(
+                    f,
+                    "{}{}{}",
+                    " ".repeat(max_label),
+                    open_gap,
+                    numbers.trim_end()
+                )?;
+            }
+            if let Some(titles) = Self::title_line(cols) {
+                writeln!(f, "{}{}{}", " ".repeat(max_label), open_gap, titles)?;
+            }
+            for (q, label) in q_lines.iter().enumerate() {
+                let body: String = cols.iter().map(|col| col.q_cells[q].as_str()).collect();
+                let note = if last {
+                    self.annotation(&view, q)
+                } else {
+                    String::new()
+                };
+                writeln!(f, "{}{}{}{}{}", label, open, body, close, note)?;
+            }
+            if nc > 0 {
+                let body: String = cols.iter().map(|col| col.gap.as_str()).collect();
+                let line = format!("{}{}{}{}", gap_line, open_gap, body, close_gap);
+                writeln!(f, "{}", line.trim_end())?;
+                for (c, label) in c_lines.iter().enumerate() {
+                    let body: String = cols.iter().map(|col| col.c_cells[c].as_str()).collect();
+                    writeln!(f, "{}{}{}{}", label, open, body, close)?;
+                }
             }
         }
 
-        Ok(())
+        self.write_summary(f)
     }
 }
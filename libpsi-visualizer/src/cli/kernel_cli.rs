@@ -0,0 +1,192 @@
+use super::charset::Charset;
+use super::layout::{self, Cell, Row};
+use super::terminal::Terminal;
+use super::theme::Theme;
+use super::visualizer::Visualizer;
+use core::fmt;
+use libpsi_core::{Kernel, KernelBatch, StructureAwareKernelBatch};
+
+/// Draws the kernels a runtime will actually execute, after fusion and
+/// reordering, as a circuit-like diagram. Fused kernels are boxed under their
+/// combined name (e.g. `[H+T+S]`, or `[prep:H+T+S]` inside a section titled
+/// "prep") and multi-qubit kernels span their wires.
+///
+/// A `StructureAwareKernelBatch` that has been optimised is drawn one
+/// execution layer per column, headed `L0`, `L1`, ...; otherwise kernels are
+/// packed into columns in execution order.
+pub struct KernelRenderer<'a> {
+    num_qubits: usize,
+    columns: Vec<Vec<&'a Kernel>>,
+    layered: bool,
+    theme: Theme,
+    charset: Charset,
+}
+
+impl<'a> KernelRenderer<'a> {
+    pub fn from_batch(batch: &'a KernelBatch) -> Self {
+        Self::packed(batch.num_qubits(), batch.kernels())
+    }
+
+    pub fn from_structure_aware(batch: &'a StructureAwareKernelBatch) -> Self {
+        if batch.layers().is_empty() {
+            return Self::packed(batch.num_qubits(), batch.kernels());
+        }
+
+        KernelRenderer {
+            num_qubits: batch.num_qubits(),
+            columns: batch
+                .layers()
+                .iter()
+                .map(|layer| layer.kernels.iter().collect())
+                .collect(),
+            layered: true,
+            theme: Theme::plain(),
+            charset: Charset::Unicode,
+        }
+    }
+
+    fn packed(num_qubits: usize, kernels: &'a [Kernel]) -> Self {
+        let spans: Vec<Vec<usize>> = kernels
+            .iter()
+            .map(|kernel| {
+                let min_q = kernel.targets.iter().min().copied().unwrap_or(0);
+                let max_q = kernel.targets.iter().max().copied().unwrap_or(0);
+                (min_q..=max_q).collect()
+            })
+            .collect();
+
+        KernelRenderer {
+            num_qubits,
+            columns: layout::pack_rows(&spans, num_qubits)
+                .into_iter()
+                .map(|column| column.into_iter().map(|idx| &kernels[idx]).collect())
+                .collect(),
+            layered: false,
+            theme: Theme::plain(),
+            charset: Charset::Unicode,
+        }
+    }
+
+    /// Takes the charset and theme from `terminal`.
+    pub fn with_terminal(mut self, terminal: Terminal) -> Self {
+        self.charset = terminal.charset();
+        self.theme = terminal.theme();
+        self
+    }
+
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    pub fn with_charset(mut self, charset: Charset) -> Self {
+        self.charset = charset;
+        self
+    }
+
+    fn kernel_cells(&self, kernel: &Kernel) -> Vec<(Row, Cell)> {
+        if kernel.targets.len() == 1 {
+            vec![(
+                Row::Quantum(kernel.targets[0]),
+                Cell::Gate(self.charset.boxed(&kernel.label())),
+            )]
+        } else {
+            layout::block_cells(&kernel.label(), &kernel.targets, self.charset)
+        }
+    }
+
+    fn kernel_style(&self, kernel: &Kernel) -> &'static str {
+        if kernel.name.contains('+') {
+            self.theme.custom
+        } else {
+            self.theme.clifford
+        }
+    }
+
+    fn cell_text(&self, cell: Option<&(Cell, &str)>, width: usize) -> String {
+        let fill = self.charset.quantum_wire().to_string();
+        let Some((Cell::Gate(text) | Cell::Block { text, .. }, style)) = cell else {
+            return self.theme.paint(self.theme.wire, &fill.repeat(width));
+        };
+
+        let (left, right) = layout::padding(text.chars().count(), width);
+        format!(
+            "{}{}{}",
+            self.theme.paint(self.theme.wire, &fill.repeat(left)),
+            self.theme.paint(style, text),
+            self.theme.paint(self.theme.wire, &fill.repeat(right))
+        )
+    }
+}
+
+impl<'a> Visualizer for KernelRenderer<'a> {
+    fn export(&self) -> String {
+        format!("{}", self)
+    }
+}
+
+impl<'a> fmt::Display for KernelRenderer<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let nq = self.num_qubits;
+        let labels: Vec<String> = (0..nq).map(|q| format!("q{}: ", q)).collect();
+        let label_width = labels.iter().map(|l| l.len()).max().unwrap_or(0);
+
+        let mut header = " ".repeat(label_width);
+        let mut lines: Vec<String> = labels
+            .iter()
+            .map(|label| format!("{:>width$}", label, width = label_width))
+            .collect();
+
+        for (i, column) in self.columns.iter().enumerate() {
+            let mut cells: Vec<Option<(Cell, &str)>> = vec![None; nq];
+            for kernel in column {
+                let style = self.kernel_style(kernel);
+                for (row, cell) in self.kernel_cells(kernel) {
+                    if let Row::Quantum(q) = row {
+                        cells[q] = Some((cell, style));
+                    }
+                }
+            }
+
+            let width = cells
+                .iter()
+                .flatten()
+                .map(|(cell, _)| layout::cell_width(cell))
+                .max()
+                .unwrap_or(1)
+                .max(3)
+                + 2;
+
+            let index = format!("L{}", i);
+            let (left, right) = layout::padding(index.len(), width);
+            header.push_str(&format!(
+                "{}{}{}",
+                " ".repeat(left),
+                index,
+                " ".repeat(right)
+            ));
+
+            for (line, cell) in lines.iter_mut().zip(&cells) {
+                line.push_str(&self.cell_text(cell.as_ref(), width));
+            }
+        }
+
+        if self.layered {
+            writeln!(f, "{}", header.trim_end())?;
+        }
+        for line in &lines {
+            writeln!(f, "{}{}", line, self.charset.end())?;
+        }
+
+        let kernels: usize = self.columns.iter().map(|column| column.len()).sum();
+        let plural = |n: usize| if n == 1 { "" } else { "s" };
+        write!(f, "{} kernel{}", kernels, plural(kernels))?;
+        if self.layered {
+            let layers = self.columns.len();
+            write!(f, " in {} layer{}", layers, plural(layers))?;
+        }
+        writeln!(f)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,431 @@
+use super::charset::Charset;
+use libpsi_core::{GateOp, QuantumCircuit, QuantumState, Vector};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Row {
+    Quantum(usize),
+    Classical(usize),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Cell {
+    Gate(String),
+    /// One wire's slice of a box spanning several wires. `text` is the full
+    /// row as drawn by the horizontal renderer; `port` is the gate-local index
+    /// of the wire, or `None` for a wire the box merely covers.
+    Block {
+        text: String,
+        port: Option<usize>,
+    },
+    Control,
+    Target,
+    Swap,
+    Cross,
+    Measure,
+    MeasureCross,
+    ClassicalCross,
+    ClassicalTarget,
+    /// Where a classically conditioned gate reads its bit, labelled with
+    /// the condition.
+    Condition(String),
+    Barrier,
+}
+
+fn gate_label(op: &GateOp, charset: Charset) -> String {
+    charset.boxed(&gate_text(op))
+}
+
+fn gate_text(op: &GateOp) -> String {
+    match op {
+        GateOp::Rx(_, theta) | GateOp::CRx(_, _, theta) => format!("Rx({:.2})", theta),
+        GateOp::Ry(_, theta) | GateOp::CRy(_, _, theta) => format!("Ry({:.2})", theta),
+        GateOp::Rz(_, theta) | GateOp::CRz(_, _, theta) => format!("Rz({:.2})", theta),
+        GateOp::P(_, theta) | GateOp::CP(_, _, theta) => format!("P({:.2})", theta),
+        GateOp::U1(_, lambda) => format!("U1({:.2})", lambda),
+        GateOp::Rxx(_, _, theta) => format!("Rxx({:.2})", theta),
+        GateOp::Ryy(_, _, theta) => format!("Ryy({:.2})", theta),
+        GateOp::Rzz(_, _, theta) => format!("Rzz({:.2})", theta),
+        GateOp::CY(_, _) => "Y".to_string(),
+        GateOp::CH(_, _) => "H".to_string(),
+        GateOp::CSX(_, _) => "√X".to_string(),
+        GateOp::Reset(_) => "|0⟩".to_string(),
+        GateOp::Conditional { op, .. } => gate_text(op),
+        GateOp::Parameterized { op, symbols } => {
+            let [Some(symbol)] = symbols.as_slice() else {
+                return gate_text(op);
+            };
+            match **op {
+                GateOp::Rx(..) | GateOp::CRx(..) => format!("Rx({})", symbol),
+                GateOp::Ry(..) | GateOp::CRy(..) => format!("Ry({})", symbol),
+                GateOp::Rz(..) | GateOp::CRz(..) => format!("Rz({})", symbol),
+                GateOp::P(..) | GateOp::CP(..) => format!("P({})", symbol),
+                GateOp::U1(..) => format!("U1({})", symbol),
+                GateOp::Rxx(..) => format!("Rxx({})", symbol),
+                GateOp::Ryy(..) => format!("Ryy({})", symbol),
+                GateOp::Rzz(..) => format!("Rzz({})", symbol),
+                _ => gate_text(op),
+            }
+        }
+        _ => op.name().to_string(),
+    }
+}
+
+/// How the renderers treat qubits that no gate or measurement acts on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IdleQubits {
+    #[default]
+    Show,
+    Hide,
+    /// Replace each run of idle qubits with a single ellipsis wire.
+    Collapse,
+}
+
+/// The rows a renderer draws: operations remapped onto the visible wires
+/// with their indices in the circuit, the wire names, and the circuit qubit
+/// behind each quantum row (`None` for rows standing in for elided idle
+/// qubits).
+pub(crate) struct WireView {
+    pub(crate) ops: Vec<GateOp>,
+    pub(crate) op_indices: Vec<usize>,
+    pub(crate) qubit_names: Vec<String>,
+    pub(crate) classical_names: Vec<String>,
+    pub(crate) qubits: Vec<Option<usize>>,
+}
+
+impl WireView {
+    pub(crate) fn is_elided(&self, row: usize) -> bool {
+        self.qubits[row].is_none()
+    }
+
+    pub(crate) fn num_qubits(&self) -> usize {
+        self.qubit_names.len()
+    }
+
+    pub(crate) fn num_classical(&self) -> usize {
+        self.classical_names.len()
+    }
+}
+
+/// Builds the view of a circuit, naming wires from the renderer overrides
+/// first and then the circuit's own labels.
+pub(crate) fn wire_view(
+    circuit: &QuantumCircuit,
+    qubit_overrides: &[String],
+    classical_overrides: &[String],
+    idle: IdleQubits,
+) -> WireView {
+    let nq = circuit.num_qubits();
+
+    let mut used = vec![idle == IdleQubits::Show; nq];
+    for op in circuit
+        .operations()
+        .iter()
+        .filter(|op| !op.is_barrier() && !op.is_snapshot())
+    {
+        for q in op.quantum_targets() {
+            used[q] = true;
+        }
+    }
+
+    let mut row_of: Vec<Option<usize>> = vec![None; nq];
+    let mut qubit_names: Vec<String> = Vec::new();
+    let mut qubits: Vec<Option<usize>> = Vec::new();
+    for q in 0..nq {
+        if used[q] {
+            row_of[q] = Some(qubit_names.len());
+            qubit_names.push(
+                qubit_overrides
+                    .get(q)
+                    .cloned()
+                    .unwrap_or_else(|| circuit.qubit_label(q)),
+            );
+            qubits.push(Some(q));
+        } else if idle == IdleQubits::Collapse {
+            if qubits.last() != Some(&None) {
+                qubit_names.push(String::new());
+                qubits.push(None);
+            }
+            row_of[q] = Some(qubit_names.len() - 1);
+        }
+    }
+
+    let (op_indices, ops) = circuit
+        .operations()
+        .iter()
+        .enumerate()
+        .filter_map(|(index, op)| {
+            let op = match op {
+                GateOp::Barrier(qubits, title) => {
+                    let mut rows: Vec<usize> = qubits.iter().filter_map(|&q| row_of[q]).collect();
+                    rows.dedup();
+                    (!rows.is_empty()).then(|| GateOp::Barrier(rows, title.clone()))
+                }
+                // Snapshots cover the whole state, so they are drawn as a
+                // barrier across every wire titled with their label.
+                GateOp::Snapshot(label, _) => Some(GateOp::Barrier(
+                    (0..qubit_names.len()).collect(),
+                    Some(label.clone()),
+                )),
+                _ => Some(op.map_qubits(|q| row_of[q].expect("qubit with operations is visible"))),
+            };
+            op.map(|op| (index, op))
+        })
+        .unzip();
+
+    let classical_names = (0..circuit.num_classical())
+        .map(|c| {
+            classical_overrides
+                .get(c)
+                .cloned()
+                .unwrap_or_else(|| circuit.classical_label(c))
+        })
+        .collect();
+
+    WireView {
+        ops,
+        op_indices,
+        qubit_names,
+        classical_names,
+        qubits,
+    }
+}
+
+/// Probability of reading 1 from `qubit`, summed over the other qubits.
+pub(crate) fn marginal_one(state: &QuantumState, num_qubits: usize, qubit: usize) -> f64 {
+    let bit = num_qubits - 1 - qubit;
+    (0..state.size())
+        .filter(|i| (i >> bit) & 1 == 1)
+        .map(|i| state.get(i).norm2())
+        .sum()
+}
+
+/// Quantum rows spanned by an op, including the wires its connectors cross.
+/// Measurements and conditioned gates run down to the classical register,
+/// so they span every qubit below their own.
+pub(crate) fn quantum_span(op: &GateOp, num_qubits: usize) -> (usize, usize) {
+    let targets = op.quantum_targets();
+    let min_q = targets.iter().min().copied().unwrap_or(0);
+    let max_q = targets.iter().max().copied().unwrap_or(0);
+    if classical_span(op).is_some() {
+        return (min_q, num_qubits.saturating_sub(1));
+    }
+    (min_q, max_q)
+}
+
+pub(crate) fn classical_span(op: &GateOp) -> Option<(usize, usize)> {
+    match op {
+        GateOp::Measure(_, c) => Some((0, *c)),
+        GateOp::Conditional { classical_bit, .. } => Some((0, *classical_bit)),
+        _ => None,
+    }
+}
+
+/// The label on the classical wire a conditioned gate reads.
+pub(crate) fn condition_text(classical_bit: usize, value: bool) -> String {
+    format!("c{}=={}", classical_bit, value as u8)
+}
+
+/// Groups operations into moments: every op is placed in the earliest column
+/// after all previous ops touching any wire it spans, so gates on disjoint
+/// wires share a column.
+pub(crate) fn pack_moments(
+    ops: &[GateOp],
+    num_qubits: usize,
+    num_classical: usize,
+) -> Vec<Vec<usize>> {
+    let spans: Vec<Vec<usize>> = ops.iter().map(|op| spanned_rows(op, num_qubits)).collect();
+    pack_rows(&spans, num_qubits + num_classical)
+}
+
+/// Packs items, each spanning the given rows, into the earliest column after
+/// every earlier item sharing one of its rows.
+pub(crate) fn pack_rows(spans: &[Vec<usize>], num_rows: usize) -> Vec<Vec<usize>> {
+    let mut next_free = vec![0usize; num_rows];
+    let mut moments: Vec<Vec<usize>> = Vec::new();
+
+    for (idx, rows) in spans.iter().enumerate() {
+        let column = rows.iter().map(|&r| next_free[r]).max().unwrap_or(0);
+        for &r in rows {
+            next_free[r] = column + 1;
+        }
+
+        if column == moments.len() {
+            moments.push(Vec::new());
+        }
+        moments[column].push(idx);
+    }
+
+    moments
+}
+
+fn spanned_rows(op: &GateOp, num_qubits: usize) -> Vec<usize> {
+    let (min_q, max_q) = quantum_span(op, num_qubits);
+    let mut rows: Vec<usize> = (min_q..=max_q).collect();
+    if let Some((min_c, max_c)) = classical_span(op) {
+        rows.extend((min_c..=max_c).map(|c| num_qubits + c));
+    }
+    rows
+}
+
+/// Cells of a box spanning every wire between the outermost `targets`, with
+/// the gate-local port index on each target wire and `name` on the first row.
+pub(crate) fn block_cells(name: &str, targets: &[usize], charset: Charset) -> Vec<(Row, Cell)> {
+    let (open, close) = charset.brackets();
+    let name = charset.gate_name(name);
+    let min_q = targets.iter().min().copied().unwrap_or(0);
+    let max_q = targets.iter().max().copied().unwrap_or(0);
+    let port_width = targets.len().saturating_sub(1).to_string().len();
+    let name_width = name.chars().count();
+
+    (min_q..=max_q)
+        .map(|q| {
+            let port = targets.iter().position(|&t| t == q);
+            let port_text = port.map(|k| k.to_string()).unwrap_or_default();
+            let name_text = if q == min_q { name.as_str() } else { "" };
+            let text = format!(
+                "{}{:<pw$} {:<nw$}{}",
+                open,
+                port_text,
+                name_text,
+                close,
+                pw = port_width,
+                nw = name_width
+            );
+            (Row::Quantum(q), Cell::Block { text, port })
+        })
+        .collect()
+}
+
+pub(crate) fn op_cells(op: &GateOp, num_qubits: usize, charset: Charset) -> Vec<(Row, Cell)> {
+    let (min_q, max_q) = quantum_span(op, num_qubits);
+    let mut cells: Vec<(Row, Cell)> = Vec::new();
+
+    let mut place = |q: usize, cell: Cell| cells.push((Row::Quantum(q), cell));
+
+    match op {
+        GateOp::CNOT(c, t) => {
+            place(*c, Cell::Control);
+            place(*t, Cell::Target);
+        }
+        GateOp::CZ(c, t) => {
+            place(*c, Cell::Control);
+            place(*t, Cell::Control);
+        }
+        GateOp::SWAP(a, b) => {
+            place(*a, Cell::Swap);
+            place(*b, Cell::Swap);
+        }
+        GateOp::CRx(c, t, _)
+        | GateOp::CRy(c, t, _)
+        | GateOp::CRz(c, t, _)
+        | GateOp::CP(c, t, _) => {
+            place(*c, Cell::Control);
+            place(*t, Cell::Gate(gate_label(op, charset)));
+        }
+        GateOp::CY(c, t) | GateOp::CH(c, t) | GateOp::CSX(c, t) => {
+            place(*c, Cell::Control);
+            place(*t, Cell::Gate(gate_label(op, charset)));
+        }
+        GateOp::CCNOT(c1, c2, t) => {
+            place(*c1, Cell::Control);
+            place(*c2, Cell::Control);
+            place(*t, Cell::Target);
+        }
+        GateOp::MCX(controls, t) => {
+            for &c in controls {
+                place(c, Cell::Control);
+            }
+            place(*t, Cell::Target);
+        }
+        GateOp::MCU {
+            controls, target, ..
+        } => {
+            for &c in controls {
+                place(c, Cell::Control);
+            }
+            place(*target, Cell::Gate(gate_label(op, charset)));
+        }
+        GateOp::CSWAP(c, t1, t2) => {
+            place(*c, Cell::Control);
+            place(*t1, Cell::Swap);
+            place(*t2, Cell::Swap);
+        }
+        GateOp::Measure(q, c) => {
+            place(*q, Cell::Measure);
+            for below in (*q + 1)..num_qubits {
+                place(below, Cell::MeasureCross);
+            }
+            for above in 0..*c {
+                cells.push((Row::Classical(above), Cell::ClassicalCross));
+            }
+            cells.push((Row::Classical(*c), Cell::ClassicalTarget));
+            return cells;
+        }
+        GateOp::Barrier(qubits, _) => {
+            for &q in qubits {
+                place(q, Cell::Barrier);
+            }
+            return cells;
+        }
+        GateOp::Conditional {
+            op: gate,
+            classical_bit,
+            value,
+        } => {
+            let (_, max_q) = quantum_span(gate, num_qubits);
+            let mut cells = op_cells(gate, num_qubits, charset);
+            for below in (max_q + 1)..num_qubits {
+                cells.push((Row::Quantum(below), Cell::MeasureCross));
+            }
+            for above in 0..*classical_bit {
+                cells.push((Row::Classical(above), Cell::ClassicalCross));
+            }
+            cells.push((
+                Row::Classical(*classical_bit),
+                Cell::Condition(condition_text(*classical_bit, *value)),
+            ));
+            return cells;
+        }
+        GateOp::Custom(gate, targets) if targets.len() > 1 => {
+            return block_cells(&gate.name, targets, charset);
+        }
+        _ => {
+            for t in op.quantum_targets() {
+                place(t, Cell::Gate(gate_label(op, charset)));
+            }
+        }
+    }
+
+    let targets = op.quantum_targets();
+    for q in (min_q + 1)..max_q {
+        if !targets.contains(&q) {
+            cells.push((Row::Quantum(q), Cell::Cross));
+        }
+    }
+
+    cells
+}
+
+pub(crate) fn cell_width(cell: &Cell) -> usize {
+    match cell {
+        Cell::Gate(label) | Cell::Block { text: label, .. } | Cell::Condition(label) => {
+            label.chars().count()
+        }
+        Cell::Measure => 3,
+        _ => 1,
+    }
+}
+
+/// Left and right padding that centres `len` characters in `width`.
+pub(crate) fn padding(len: usize, width: usize) -> (usize, usize) {
+    let len = len.min(width);
+    let left = (width - len) / 2;
+    (left, width - len - left)
+}
+
+pub(crate) fn section_title(op: &GateOp) -> Option<&str> {
+    match op {
+        GateOp::Barrier(_, title) => title.as_deref(),
+        _ => None,
+    }
+}
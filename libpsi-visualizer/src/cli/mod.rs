@@ -1,7 +1,23 @@
+pub mod amplitude_cli;
+pub mod charset;
+pub mod diff_cli;
+pub mod gantt_cli;
 pub mod horizontal_cli;
+pub mod kernel_cli;
+pub mod layout;
+pub mod terminal;
+pub mod theme;
 pub mod vertical_cli;
 pub mod visualizer;
 
+pub use amplitude_cli::*;
+pub use charset::*;
+pub use diff_cli::*;
+pub use gantt_cli::*;
 pub use horizontal_cli::*;
+pub use kernel_cli::*;
+pub use layout::IdleQubits;
+pub use terminal::*;
+pub use theme::*;
 pub use vertical_cli::*;
 pub use visualizer::*;
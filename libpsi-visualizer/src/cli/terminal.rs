@@ -0,0 +1,119 @@
+use super::charset::Charset;
+use super::theme::Theme;
+use std::env;
+use std::io::IsTerminal;
+
+/// What the terminal a diagram is printed to can display: its width in
+/// columns, whether it renders Unicode and whether it understands ANSI
+/// colour. Handing one to a renderer with `with_terminal` picks the folding
+/// width, `Charset` and `Theme` together; any of them can still be
+/// overridden afterwards with the renderer's own `with_*` methods.
+///
+/// `Terminal::detect` reads the capabilities from the environment:
+///
+/// - the width from `COLUMNS`, leaving it unset (no folding) if absent;
+/// - Unicode from the locale in `LC_ALL`, `LC_CTYPE` or `LANG`, which must
+///   name a UTF-8 encoding;
+/// - colour only when stdout is a terminal other than `TERM=dumb`, honouring
+///   `NO_COLOR` and `CLICOLOR_FORCE`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Terminal {
+    pub width: Option<usize>,
+    pub unicode: bool,
+    pub color: bool,
+}
+
+impl Terminal {
+    pub fn detect() -> Self {
+        Terminal {
+            width: detect_width(),
+            unicode: detect_unicode(),
+            color: detect_color(),
+        }
+    }
+
+    /// A terminal of unknown width with Unicode and no colour, matching the
+    /// renderers' defaults.
+    pub fn plain() -> Self {
+        Terminal {
+            width: None,
+            unicode: true,
+            color: false,
+        }
+    }
+
+    pub fn with_width(mut self, width: usize) -> Self {
+        self.width = Some(width);
+        self
+    }
+
+    pub fn without_width(mut self) -> Self {
+        self.width = None;
+        self
+    }
+
+    pub fn with_unicode(mut self, unicode: bool) -> Self {
+        self.unicode = unicode;
+        self
+    }
+
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn charset(&self) -> Charset {
+        if self.unicode {
+            Charset::Unicode
+        } else {
+            Charset::Ascii
+        }
+    }
+
+    pub fn theme(&self) -> Theme {
+        if self.color {
+            Theme::ansi()
+        } else {
+            Theme::plain()
+        }
+    }
+}
+
+impl Default for Terminal {
+    fn default() -> Self {
+        Terminal::plain()
+    }
+}
+
+fn non_empty_var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+pub(crate) fn detect_width() -> Option<usize> {
+    non_empty_var("COLUMNS")
+        .and_then(|cols| cols.trim().parse::<usize>().ok())
+        .filter(|&cols| cols > 0)
+}
+
+fn detect_unicode() -> bool {
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .find_map(|name| non_empty_var(name));
+    match locale {
+        Some(locale) => {
+            let locale = locale.to_ascii_lowercase();
+            locale.contains("utf-8") || locale.contains("utf8")
+        }
+        None => cfg!(windows),
+    }
+}
+
+fn detect_color() -> bool {
+    if non_empty_var("NO_COLOR").is_some() {
+        return false;
+    }
+    if non_empty_var("CLICOLOR_FORCE").is_some_and(|force| force != "0") {
+        return true;
+    }
+    non_empty_var("TERM").as_deref() != Some("dumb") && std::io::stdout().is_terminal()
+}
@@ -0,0 +1,82 @@
+use libpsi_core::GateOp;
+
+/// ANSI styles used by the circuit renderers. Each field holds the SGR
+/// parameters (e.g. `"1;36"`) for one kind of element; an empty string leaves
+/// that element uncoloured, so `Theme::plain()` produces escape-free output.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Theme {
+    pub clifford: &'static str,
+    pub non_clifford: &'static str,
+    pub measurement: &'static str,
+    pub custom: &'static str,
+    pub barrier: &'static str,
+    pub wire: &'static str,
+    pub classical: &'static str,
+    pub inserted: &'static str,
+    pub removed: &'static str,
+    pub changed: &'static str,
+}
+
+impl Theme {
+    pub fn plain() -> Self {
+        Theme {
+            clifford: "",
+            non_clifford: "",
+            measurement: "",
+            custom: "",
+            barrier: "",
+            wire: "",
+            classical: "",
+            inserted: "",
+            removed: "",
+            changed: "",
+        }
+    }
+
+    pub fn ansi() -> Self {
+        Theme {
+            clifford: "1;36",
+            non_clifford: "1;35",
+            measurement: "1;33",
+            custom: "1;32",
+            barrier: "2;37",
+            wire: "2",
+            classical: "2;33",
+            inserted: "32",
+            removed: "31",
+            changed: "33",
+        }
+    }
+
+    pub fn is_plain(&self) -> bool {
+        *self == Theme::plain()
+    }
+
+    pub(crate) fn op_style(&self, op: &GateOp) -> &'static str {
+        if op.is_measurement() || op.is_reset() {
+            self.measurement
+        } else if op.is_barrier() {
+            self.barrier
+        } else if op.is_custom() {
+            self.custom
+        } else if op.is_non_clifford() {
+            self.non_clifford
+        } else {
+            self.clifford
+        }
+    }
+
+    pub(crate) fn paint(&self, style: &str, text: &str) -> String {
+        if style.is_empty() || text.is_empty() {
+            text.to_string()
+        } else {
+            format!("\x1b[{}m{}\x1b[0m", style, text)
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::plain()
+    }
+}
@@ -1,58 +1,151 @@
+use super::amplitude_cli;
+use super::charset::Charset;
+use super::layout::{self, Cell, IdleQubits, Row, WireView};
+use super::terminal::Terminal;
+use super::theme::Theme;
 use super::visualizer::Visualizer;
 use core::fmt;
-use libpsi_core::{GateOp, QuantumCircuit};
+use libpsi_core::{GateOp, QuantumCircuit, QuantumState};
 
+/// Given a state with `with_probabilities`, a footer row shows the
+/// probability of measuring 1 under each qubit. `with_op_indices` lists the
+/// circuit indices of the operations drawn on each row to its right, and
+/// `with_state_summary` writes the circuit's computed state under the
+/// diagram.
 pub struct VerticalRenderer<'a> {
     circuit: &'a QuantumCircuit,
+    theme: Theme,
+    charset: Charset,
+    qubit_labels: Vec<String>,
+    classical_labels: Vec<String>,
+    idle_qubits: IdleQubits,
+    state: Option<&'a QuantumState>,
+    op_indices: bool,
+    summary: Option<f64>,
 }
 
 impl<'a> VerticalRenderer<'a> {
     pub fn new(circuit: &'a QuantumCircuit) -> Self {
-        VerticalRenderer { circuit }
+        VerticalRenderer {
+            circuit,
+            theme: Theme::plain(),
+            charset: Charset::Unicode,
+            qubit_labels: Vec::new(),
+            classical_labels: Vec::new(),
+            idle_qubits: IdleQubits::Show,
+            state: None,
+            op_indices: false,
+            summary: None,
+        }
+    }
+
+    /// Takes the charset and theme from `terminal`.
+    pub fn with_terminal(mut self, terminal: Terminal) -> Self {
+        self.charset = terminal.charset();
+        self.theme = terminal.theme();
+        self
+    }
+
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    pub fn with_charset(mut self, charset: Charset) -> Self {
+        self.charset = charset;
+        self
+    }
+
+    /// Overrides the circuit's qubit labels, in wire order.
+    pub fn with_qubit_labels(mut self, labels: &[&str]) -> Self {
+        self.qubit_labels = labels.iter().map(|label| label.to_string()).collect();
+        self
+    }
+
+    /// Overrides the circuit's classical bit labels, in wire order.
+    pub fn with_classical_labels(mut self, labels: &[&str]) -> Self {
+        self.classical_labels = labels.iter().map(|label| label.to_string()).collect();
+        self
+    }
+
+    pub fn with_idle_qubits(mut self, idle_qubits: IdleQubits) -> Self {
+        self.idle_qubits = idle_qubits;
+        self
+    }
+
+    pub fn with_probabilities(mut self, state: &'a QuantumState) -> Self {
+        self.state = Some(state);
+        self
+    }
+
+    pub fn with_op_indices(mut self) -> Self {
+        self.op_indices = true;
+        self
+    }
+
+    /// Writes the amplitudes of the circuit's computed state, in Dirac form
+    /// and leaving out basis states with probability below `cutoff`, under
+    /// the diagram.
+    pub fn with_state_summary(mut self, cutoff: f64) -> Self {
+        self.summary = Some(cutoff);
+        self
     }
 
-    fn gate_label(op: &GateOp) -> String {
-        match op {
-            GateOp::H(_) => "[H]".to_string(),
-            GateOp::X(_) => "[X]".to_string(),
-            GateOp::Y(_) => "[Y]".to_string(),
-            GateOp::Z(_) => "[Z]".to_string(),
-            GateOp::S(_) => "[S]".to_string(),
-            GateOp::T(_) => "[T]".to_string(),
-            GateOp::Sdg(_) => "[S†]".to_string(),
-            GateOp::Tdg(_) => "[T†]".to_string(),
-            GateOp::Sx(_) => "[√X]".to_string(),
-            GateOp::Sxdg(_) => "[√X†]".to_string(),
-            GateOp::Rx(_, theta) => format!("[Rx({:.2})]", theta),
-            GateOp::Ry(_, theta) => format!("[Ry({:.2})]", theta),
-            GateOp::Rz(_, theta) => format!("[Rz({:.2})]", theta),
-            GateOp::P(_, theta) => format!("[P({:.2})]", theta),
-            GateOp::U1(_, lambda) => format!("[U1({:.2})]", lambda),
-            GateOp::U2(_, _, _) => "[U2]".to_string(),
-            GateOp::U3(_, _, _, _) => "[U3]".to_string(),
-            GateOp::CRx(_, _, _) => "[CRx]".to_string(),
-            GateOp::CRy(_, _, _) => "[CRy]".to_string(),
-            GateOp::CRz(_, _, _) => "[CRz]".to_string(),
-            GateOp::CP(_, _, _) => "[CP]".to_string(),
-            GateOp::CNOT(_, _) => "●".to_string(),
-            GateOp::CZ(_, _) => "●".to_string(),
-            GateOp::SWAP(_, _) => "╳".to_string(),
-            GateOp::CCNOT(_, _, _) => "●".to_string(),
-            GateOp::CSWAP(_, _, _) => "●".to_string(),
-            GateOp::Measure(_, _) => "[M]".to_string(),
-            GateOp::Custom(gate, _) => format!("[{}]", gate.name),
+    fn write_summary(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(cutoff) = self.summary else {
+            return Ok(());
+        };
+        match self.circuit.computed_state() {
+            Some(state) => writeln!(
+                f,
+                "State: {}",
+                amplitude_cli::format_state(state, self.circuit.num_qubits(), cutoff, self.charset)
+            ),
+            None => writeln!(f, "State: (not computed)"),
         }
     }
 
-    fn calculate_col_width(&self) -> usize {
-        let min_width = 3;
-        let mut max_label_len = min_width;
+    fn place_label<'s>(
+        line: &mut [(char, &'s str)],
+        center: usize,
+        col_width: usize,
+        label: &str,
+        style: &'s str,
+    ) {
+        let label_start = center - col_width / 2 + (col_width - label.chars().count()) / 2;
+        for (j, ch) in label.chars().enumerate() {
+            line[label_start + j] = (ch, style);
+        }
+    }
 
-        for op in self.circuit.operations() {
-            let label = Self::gate_label(op);
-            let char_count: usize = label.chars().count();
-            if char_count > max_label_len {
-                max_label_len = char_count;
+    fn paint_line(&self, line: &[(char, &str)]) -> String {
+        let mut out = String::new();
+        let mut run = String::new();
+        let mut run_style = "";
+
+        for &(ch, style) in line {
+            if style != run_style && !run.is_empty() {
+                out.push_str(&self.theme.paint(run_style, &run));
+                run.clear();
+            }
+            run_style = style;
+            run.push(ch);
+        }
+        out.push_str(&self.theme.paint(run_style, &run));
+
+        out
+    }
+
+    fn calculate_col_width(&self, view: &WireView, wire_labels: &[String]) -> usize {
+        let min_width = 3;
+        let mut max_label_len = wire_labels
+            .iter()
+            .map(|label| label.chars().count())
+            .fold(min_width, usize::max);
+
+        for op in &view.ops {
+            for (_, cell) in layout::op_cells(op, view.num_qubits(), self.charset) {
+                max_label_len = max_label_len.max(layout::cell_width(&cell));
             }
         }
 
@@ -73,20 +166,43 @@ impl<'a> Visualizer for VerticalRenderer<'a> {
 
 impl<'a> fmt::Display for VerticalRenderer<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let nq = self.circuit.num_qubits();
-        let nc = self.circuit.num_classical();
-        let ops = self.circuit.operations();
+        let view = layout::wire_view(
+            self.circuit,
+            &self.qubit_labels,
+            &self.classical_labels,
+            self.idle_qubits,
+        );
+        let nq = view.num_qubits();
+        let nc = view.num_classical();
+        let ops = &view.ops;
+
+        let q_names: Vec<String> = view
+            .qubit_names
+            .iter()
+            .enumerate()
+            .map(|(row, name)| {
+                if view.is_elided(row) {
+                    self.charset.ellipsis_across().to_string()
+                } else {
+                    name.clone()
+                }
+            })
+            .collect();
+        let c_names = &view.classical_names;
+        let all_names: Vec<String> = q_names.iter().chain(c_names.iter()).cloned().collect();
 
-        let col_width = self.calculate_col_width();
+        let col_width = self.calculate_col_width(&view, &all_names);
         let gap_width = 3;
 
-        let q_header: String = (0..nq)
-            .map(|i| format!("{:^width$}", format!("q{}", i), width = col_width))
+        let q_header: String = q_names
+            .iter()
+            .map(|name| format!("{:^width$}", name, width = col_width))
             .collect::<Vec<_>>()
             .join(" ");
 
-        let c_header: String = (0..nc)
-            .map(|i| format!("{:^width$}", format!("c{}", i), width = col_width))
+        let c_header: String = c_names
+            .iter()
+            .map(|name| format!("{:^width$}", name, width = col_width))
             .collect::<Vec<_>>()
             .join(" ");
 
@@ -96,272 +212,209 @@ impl<'a> fmt::Display for VerticalRenderer<'a> {
             writeln!(f, "{}", q_header)?;
         }
 
-        let q_wires: String = (0..nq)
-            .map(|_| format!("{:^width$}", "│", width = col_width))
-            .collect::<Vec<_>>()
-            .join(" ");
+        let q_total = nq * col_width + nq.saturating_sub(1);
+        let c_total = if nc > 0 { nc * col_width + (nc - 1) } else { 0 };
+        let total_width = q_total + gap_width + c_total;
 
-        let c_wires: String = (0..nc)
-            .map(|_| format!("{:^width$}", "║", width = col_width))
-            .collect::<Vec<_>>()
-            .join(" ");
+        let q_center = |q: usize| q * (col_width + 1) + col_width / 2;
+        let c_center = |c: usize| q_total + gap_width + c * (col_width + 1) + col_width / 2;
 
+        let mut wires: Vec<(char, &str)> = vec![(' ', ""); total_width];
+        for i in (0..nq).filter(|&i| !view.is_elided(i)) {
+            wires[q_center(i)] = (self.charset.quantum_vertical(), self.theme.wire);
+        }
+        for i in 0..nc {
+            wires[c_center(i)] = (self.charset.classical_vertical(), self.theme.classical);
+        }
         let full_wires = if nc > 0 {
-            format!("{}{}{}", q_wires, " ".repeat(gap_width), c_wires)
+            self.paint_line(&wires)
         } else {
-            q_wires.clone()
+            self.paint_line(&wires[..q_total])
         };
 
         if ops.is_empty() {
             writeln!(f, "{}", full_wires)?;
-            return Ok(());
+            return self.write_summary(f);
         }
 
-        let q_total = nq * col_width + (nq - 1);
-        let c_total = if nc > 0 { nc * col_width + (nc - 1) } else { 0 };
-        let total_width = q_total + gap_width + c_total;
-
-        for op in ops {
+        for moment in layout::pack_moments(ops, nq, nc) {
             writeln!(f, "{}", full_wires)?;
 
-            let q_targets = op.quantum_targets();
-            let min_q = q_targets.iter().min().copied().unwrap_or(0);
-            let max_q = q_targets.iter().max().copied().unwrap_or(0);
-
-            let label = Self::gate_label(op);
-
-            match op {
-                GateOp::H(t)
-                | GateOp::X(t)
-                | GateOp::Y(t)
-                | GateOp::Z(t)
-                | GateOp::S(t)
-                | GateOp::T(t)
-                | GateOp::Sdg(t)
-                | GateOp::Tdg(t)
-                | GateOp::Sx(t)
-                | GateOp::Sxdg(t)
-                | GateOp::Rx(t, _)
-                | GateOp::Ry(t, _)
-                | GateOp::Rz(t, _)
-                | GateOp::P(t, _)
-                | GateOp::U1(t, _)
-                | GateOp::U2(t, _, _)
-                | GateOp::U3(t, _, _, _) => {
-                    let mut line: Vec<char> = vec![' '; total_width];
-
-                    for i in 0..nq {
-                        let col_start = i * (col_width + 1);
-                        let center = col_start + col_width / 2;
-                        if i == *t {
-                            let label_start = col_start + (col_width - label.chars().count()) / 2;
-                            for (j, ch) in label.chars().enumerate() {
-                                line[label_start + j] = ch;
-                            }
-                        } else {
-                            line[center] = '│';
-                        }
-                    }
+            let mut line: Vec<(char, &str)> = vec![(' ', ""); total_width];
+            let mut q_covered = vec![false; nq];
+            let mut c_covered = vec![false; nc];
+            let mut title = None;
 
-                    for i in 0..nc {
-                        let center = q_total + gap_width + i * (col_width + 1) + col_width / 2;
-                        line[center] = '║';
-                    }
+            for &idx in &moment {
+                let op = &ops[idx];
+                let style = self.theme.op_style(op);
 
-                    let gate_line: String = line.into_iter().collect();
-                    writeln!(f, "{}", gate_line)?;
+                let (min_q, max_q) = layout::quantum_span(op, nq);
+                for covered in &mut q_covered[min_q..=max_q] {
+                    *covered = true;
+                }
+                if let Some((min_c, max_c)) = layout::classical_span(op) {
+                    for covered in &mut c_covered[min_c..=max_c] {
+                        *covered = true;
+                    }
                 }
-                GateOp::CNOT(c, t) | GateOp::CZ(c, t) | GateOp::SWAP(c, t)
-                | GateOp::CRx(c, t, _) | GateOp::CRy(c, t, _) | GateOp::CRz(c, t, _) | GateOp::CP(c, t, _) => {
-                    let (sym1, sym2) = match op {
-                        GateOp::CNOT(_, _) => ('●', '⊕'),
-                        GateOp::CZ(_, _) => ('●', '●'),
-                        GateOp::SWAP(_, _) => ('╳', '╳'),
-                        GateOp::CRx(_, _, _) | GateOp::CRy(_, _, _) | GateOp::CRz(_, _, _) | GateOp::CP(_, _, _) => ('●', '□'),
-                        _ => unreachable!(),
-                    };
 
-                    let mut line: Vec<char> = vec![' '; total_width];
-
-                    for i in 0..nq {
-                        let col_start = i * (col_width + 1);
-                        let center = col_start + col_width / 2;
-                        if i < min_q || i > max_q {
-                            line[center] = '│';
-                        } else if i == *c {
-                            line[center] = sym1;
-                        } else if i == *t {
-                            // For controlled parametric gates, show the gate label on target
-                            if matches!(op, GateOp::CRx(_, _, _) | GateOp::CRy(_, _, _) | GateOp::CRz(_, _, _) | GateOp::CP(_, _, _)) {
-                                let label_start = col_start + (col_width - label.chars().count()) / 2;
-                                for (j, ch) in label.chars().enumerate() {
-                                    if label_start + j < line.len() {
-                                        line[label_start + j] = ch;
-                                    }
+                let mut is_block = false;
+                for (row, cell) in layout::op_cells(op, nq, self.charset) {
+                    let center = match row {
+                        Row::Quantum(q) => q_center(q),
+                        Row::Classical(c) => c_center(c),
+                    };
+                    let symbol = match cell {
+                        Cell::Gate(label) | Cell::Condition(label) => {
+                            Self::place_label(&mut line, center, col_width, &label, style);
+                            continue;
+                        }
+                        Cell::Measure => {
+                            Self::place_label(
+                                &mut line,
+                                center,
+                                col_width,
+                                &self.charset.boxed("M"),
+                                style,
+                            );
+                            continue;
+                        }
+                        Cell::Block { port, .. } => {
+                            is_block = true;
+                            if let Some(k) = port {
+                                for (j, ch) in k.to_string().chars().enumerate() {
+                                    line[center + j] = (ch, style);
                                 }
-                            } else {
-                                line[center] = sym2;
                             }
+                            continue;
                         }
-                    }
-
-                    let min_center = min_q * (col_width + 1) + col_width / 2;
-                    let max_center = max_q * (col_width + 1) + col_width / 2;
-                    for pos in (min_center + 1)..max_center {
-                        if line[pos] == ' ' {
-                            line[pos] = '─';
+                        Cell::Control => self.charset.control(),
+                        Cell::Target => self.charset.target(),
+                        Cell::Swap => self.charset.swap(),
+                        Cell::ClassicalTarget => self.charset.measure_across(),
+                        Cell::Cross | Cell::MeasureCross | Cell::ClassicalCross | Cell::Barrier => {
+                            continue
                         }
-                    }
-
-                    for i in 0..nc {
-                        let center = q_total + gap_width + i * (col_width + 1) + col_width / 2;
-                        line[center] = '║';
-                    }
-
-                    let gate_line: String = line.into_iter().collect();
-                    writeln!(f, "{}", gate_line)?;
-                }
-                GateOp::CCNOT(c1, c2, t) | GateOp::CSWAP(c1, c2, t) => {
-                    let (sym_c, sym_t) = match op {
-                        GateOp::CCNOT(_, _, _) => ('●', '⊕'),
-                        GateOp::CSWAP(_, _, _) => ('●', '╳'),
-                        _ => unreachable!(),
                     };
-                    let is_cswap = matches!(op, GateOp::CSWAP(_, _, _));
-
-                    let mut line: Vec<char> = vec![' '; total_width];
-
-                    for i in 0..nq {
-                        let center = i * (col_width + 1) + col_width / 2;
-                        if i < min_q || i > max_q {
-                            line[center] = '│';
-                        } else if i == *c1 {
-                            line[center] = sym_c;
-                        } else if i == *c2 {
-                            line[center] = if is_cswap { sym_t } else { sym_c };
-                        } else if i == *t {
-                            line[center] = sym_t;
-                        }
-                    }
+                    line[center] = (symbol, style);
+                }
 
-                    let min_center = min_q * (col_width + 1) + col_width / 2;
-                    let max_center = max_q * (col_width + 1) + col_width / 2;
-                    for pos in (min_center + 1)..max_center {
-                        if line[pos] == ' ' {
-                            line[pos] = '─';
-                        }
+                if op.is_barrier() {
+                    let from = q_center(min_q) - col_width / 2;
+                    let to = q_center(max_q) + col_width / 2;
+                    for cell in &mut line[from..=to] {
+                        *cell = (self.charset.barrier_across(), style);
                     }
-
-                    for i in 0..nc {
-                        let center = q_total + gap_width + i * (col_width + 1) + col_width / 2;
-                        line[center] = '║';
+                    if let Some(section) = layout::section_title(op) {
+                        title = Some(section.to_string());
                     }
-
-                    let gate_line: String = line.into_iter().collect();
-                    writeln!(f, "{}", gate_line)?;
+                    continue;
                 }
-                GateOp::Measure(mq, mc) => {
-                    let mut line: Vec<char> = vec![' '; total_width];
-
-                    for i in 0..nq {
-                        let col_start = i * (col_width + 1);
-                        let center = col_start + col_width / 2;
-                        if i < *mq {
-                            line[center] = '│';
-                        } else if i == *mq {
-                            let label_start = col_start + (col_width - label.chars().count()) / 2;
-                            for (j, ch) in label.chars().enumerate() {
-                                line[label_start + j] = ch;
-                            }
-                        }
-                    }
-
-                    let mq_col_start = *mq * (col_width + 1);
-                    let mq_center = mq_col_start + col_width / 2;
-                    let mc_start = q_total + gap_width;
-                    let mc_center = mc_start + *mc * (col_width + 1) + col_width / 2;
 
-                    for pos in (mq_center + 2)..=mc_center {
-                        if line[pos] == ' ' {
-                            line[pos] = '═';
-                        }
+                // A conditioned gate's span runs on to the classical
+                // register; its box covers only the gate's own qubits.
+                let gate_max = op.quantum_targets().into_iter().max().unwrap_or(0);
+                if is_block {
+                    let (open, close) = self.charset.brackets();
+                    line[q_center(min_q) - col_width / 2] = (open, style);
+                    line[q_center(gate_max) + col_width / 2] = (close, style);
+
+                    let name_start = q_center(min_q) + 2;
+                    for (j, ch) in self.charset.gate_name(op.name()).chars().enumerate() {
+                        line[name_start + j] = (ch, style);
                     }
-                    line[mc_center] = '╣';
-
-                    for i in 0..nc {
-                        let center = mc_start + i * (col_width + 1) + col_width / 2;
-                        if i > *mc {
-                            line[center] = '║';
-                        }
+                    if op.condition().is_none() {
+                        continue;
                     }
-
-                    let measure_line: String = line.into_iter().collect();
-                    writeln!(f, "{}", measure_line)?;
                 }
-                GateOp::Custom(_, targets) => {
-                    let mut line: Vec<char> = vec![' '; total_width];
-
-                    if targets.len() == 1 {
-                        for i in 0..nq {
-                            let col_start = i * (col_width + 1);
-                            let center = col_start + col_width / 2;
-                            if i == targets[0] {
-                                let label_start =
-                                    col_start + (col_width - label.chars().count()) / 2;
-                                for (j, ch) in label.chars().enumerate() {
-                                    line[label_start + j] = ch;
-                                }
-                            } else {
-                                line[center] = '│';
-                            }
-                        }
 
-                        for i in 0..nc {
-                            let center = q_total + gap_width + i * (col_width + 1) + col_width / 2;
-                            line[center] = '║';
-                        }
+                let (from, to, connector) = if let GateOp::Measure(mq, mc) = op {
+                    (
+                        q_center(*mq) + 2,
+                        c_center(*mc),
+                        self.charset.classical_wire(),
+                    )
+                } else if let Some((bit, _)) = op.condition() {
+                    if is_block {
+                        let from = q_center(gate_max) + col_width / 2 + 1;
+                        (from, c_center(bit), self.charset.classical_wire())
                     } else {
-                        for i in 0..nq {
-                            let col_start = i * (col_width + 1);
-                            let center = col_start + col_width / 2;
-                            if i < min_q || i > max_q {
-                                line[center] = '│';
-                            } else if i == targets[0] {
-                                let label_start =
-                                    col_start + (col_width - label.chars().count()) / 2;
-                                for (j, ch) in label.chars().enumerate() {
-                                    line[label_start + j] = ch;
-                                }
-                            } else if targets.contains(&i) {
-                                line[center] = '□';
-                            }
-                        }
-
-                        let min_center = min_q * (col_width + 1) + col_width / 2;
-                        let max_center = max_q * (col_width + 1) + col_width / 2;
-                        for pos in (min_center + 1)..max_center {
-                            if line[pos] == ' ' {
-                                line[pos] = '─';
+                        let quantum = q_center(min_q) + 1..q_center(gate_max);
+                        for cell in line.iter_mut().take(quantum.end).skip(quantum.start) {
+                            if cell.0 == ' ' {
+                                *cell = (self.charset.quantum_wire(), style);
                             }
                         }
-
-                        for i in 0..nc {
-                            let center = q_total + gap_width + i * (col_width + 1) + col_width / 2;
-                            line[center] = '║';
+                        (
+                            q_center(gate_max) + 1,
+                            c_center(bit),
+                            self.charset.classical_wire(),
+                        )
+                    }
+                } else {
+                    (
+                        q_center(min_q) + 1,
+                        q_center(max_q),
+                        self.charset.quantum_wire(),
+                    )
+                };
+                if from < to {
+                    for cell in &mut line[from..to] {
+                        if cell.0 == ' ' {
+                            *cell = (connector, style);
                         }
                     }
+                }
+            }
 
-                    let gate_line: String = line.into_iter().collect();
-                    writeln!(f, "{}", gate_line)?;
+            for (i, covered) in q_covered.iter().enumerate() {
+                if !covered && !view.is_elided(i) {
+                    line[q_center(i)] = (self.charset.quantum_vertical(), self.theme.wire);
+                }
+            }
+            for (i, covered) in c_covered.iter().enumerate() {
+                if !covered {
+                    line[c_center(i)] = (self.charset.classical_vertical(), self.theme.classical);
                 }
             }
+
+            let mut notes: Vec<String> = Vec::new();
+            if self.op_indices {
+                notes.extend(
+                    moment
+                        .iter()
+                        .map(|&idx| format!("#{}", view.op_indices[idx])),
+                );
+            }
+            notes.extend(title);
+            if notes.is_empty() {
+                writeln!(f, "{}", self.paint_line(&line))?;
+            } else {
+                writeln!(f, "{}  {}", self.paint_line(&line), notes.join(" "))?;
+            }
         }
 
         writeln!(f, "{}", full_wires)?;
 
-        let end_line: String = "░".repeat(total_width);
+        let end_line: String = self.charset.end().to_string().repeat(total_width);
         writeln!(f, "{}", end_line)?;
 
-        Ok(())
+        if let Some(state) = self.state {
+            let probabilities: Vec<String> = view
+                .qubits
+                .iter()
+                .map(|qubit| {
+                    let p = qubit
+                        .map(|q| layout::marginal_one(state, self.circuit.num_qubits(), q))
+                        .map(|p| format!("{:.2}", p))
+                        .unwrap_or_default();
+                    format!("{:^width$}", p, width = col_width)
+                })
+                .collect();
+            writeln!(f, "{}  P(1)", probabilities.join(" "))?;
+        }
+
+        self.write_summary(f)
     }
 }
@@ -1,2 +1,9 @@
 pub mod cli;
+#[cfg(feature = "notebook")]
+pub mod notebook;
+pub mod svg;
+
 pub use cli::*;
+#[cfg(feature = "notebook")]
+pub use notebook::*;
+pub use svg::*;
@@ -0,0 +1,154 @@
+//! HTML for notebooks: Jupyter picks it up through `_repr_html_` in the
+//! Python bindings, evcxr through `RichDisplay::evcxr_display`.
+
+use super::cli::{HorizontalRenderer, Terminal};
+use core::fmt::Write;
+use libpsi_core::{format_amplitude, Counts, QuantumCircuit, QuantumState, Vector};
+use std::f64::consts::PI;
+
+const EPSILON: f64 = 1e-10;
+/// Rows listed before the rest of a state or histogram is summarised.
+const MAX_ROWS: usize = 64;
+const BAR_WIDTH: usize = 200;
+
+/// Values that render as HTML in a notebook.
+///
+/// evcxr shows a cell's value through an `evcxr_display` method, which it
+/// finds once the trait is in scope: `use libpsi_visualizer::RichDisplay;`.
+pub trait RichDisplay {
+    fn to_html(&self) -> String;
+
+    fn evcxr_display(&self) {
+        println!(
+            "EVCXR_BEGIN_CONTENT text/html\n{}\nEVCXR_END_CONTENT",
+            self.to_html()
+        );
+    }
+}
+
+/// The horizontal diagram, followed by the state if it has been computed.
+impl RichDisplay for QuantumCircuit {
+    fn to_html(&self) -> String {
+        let diagram = HorizontalRenderer::new(self).with_terminal(Terminal::plain());
+        let mut html = format!(
+            "<pre style=\"line-height: 1.2\">{}</pre>",
+            escape(&diagram.to_string())
+        );
+        if let Some(state) = self.computed_state() {
+            html.push_str(&state.to_html());
+        }
+        html
+    }
+}
+
+/// Non-zero amplitudes with their probability as a bar coloured by phase.
+impl RichDisplay for QuantumState {
+    fn to_html(&self) -> String {
+        let num_qubits = self.size().max(1).trailing_zeros() as usize;
+        let rows: Vec<(String, String, f64, f64)> = (0..self.size())
+            .filter_map(|i| {
+                let amp = self.get(i);
+                let probability = amp.norm2();
+                (probability >= EPSILON).then(|| {
+                    let phase = amp.imaginary.atan2(amp.real);
+                    let label = format!("|{}⟩", bits(i, num_qubits));
+                    (label, format_amplitude(&amp), probability, phase)
+                })
+            })
+            .collect();
+
+        let mut html = table_start(&["Basis", "Amplitude", "Probability", ""]);
+        for (label, amplitude, probability, phase) in rows.iter().take(MAX_ROWS) {
+            let hue = (phase.rem_euclid(2.0 * PI) / (2.0 * PI) * 360.0).round();
+            let _ = write!(
+                html,
+                "<tr><td><code>{}</code></td><td><code>{}</code></td><td>{:.4}</td><td>{}</td></tr>",
+                label,
+                escape(amplitude),
+                probability,
+                bar(*probability, &format!("hsl({}, 65%, 50%)", hue))
+            );
+        }
+        table_end(&mut html, rows.len(), 4);
+        html
+    }
+}
+
+/// Outcomes as a horizontal histogram.
+impl RichDisplay for Counts {
+    fn to_html(&self) -> String {
+        let max = self.iter().map(|(_, n)| n).max().unwrap_or(0).max(1);
+        let mut html = table_start(&["Outcome", "Count", "", ""]);
+        for (outcome, n) in self.iter().take(MAX_ROWS) {
+            let _ = write!(
+                html,
+                "<tr><td><code>{}</code></td><td>{}</td><td>{:.2}%</td><td>{}</td></tr>",
+                escape(outcome),
+                n,
+                self.probability(outcome) * 100.0,
+                bar(n as f64 / max as f64, "steelblue")
+            );
+        }
+        table_end(&mut html, self.len(), 4);
+        let _ = write!(html, "<p>{} shots</p>", self.shots());
+        html
+    }
+}
+
+fn bits(index: usize, num_qubits: usize) -> String {
+    (0..num_qubits)
+        .map(|q| {
+            if (index >> (num_qubits - 1 - q)) & 1 == 1 {
+                '1'
+            } else {
+                '0'
+            }
+        })
+        .collect()
+}
+
+fn table_start(headers: &[&str]) -> String {
+    let mut html = String::from("<table style=\"border-collapse: collapse\"><tr>");
+    for header in headers {
+        let _ = write!(html, "<th style=\"text-align: left\">{}</th>", header);
+    }
+    html.push_str("</tr>");
+    html
+}
+
+fn table_end(html: &mut String, rows: usize, columns: usize) {
+    if rows > MAX_ROWS {
+        let _ = write!(
+            html,
+            "<tr><td colspan=\"{}\">… {} more</td></tr>",
+            columns,
+            rows - MAX_ROWS
+        );
+    }
+    html.push_str("</table>");
+}
+
+/// A bar `fraction` (0 to 1) of the full width.
+fn bar(fraction: f64, color: &str) -> String {
+    let width = (fraction.clamp(0.0, 1.0) * BAR_WIDTH as f64).round() as usize;
+    format!(
+        "<div style=\"width: {}px; height: 1em; background: {}\"></div>",
+        width.max(1),
+        color
+    )
+}
+
+/// Escapes text for use in HTML element content.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,368 @@
+//! Circuit diagrams as standalone SVG, for notebooks, documentation and
+//! anything else that shows images rather than text.
+
+use super::cli::charset::Charset;
+use super::cli::layout::{self, Cell, IdleQubits, Row};
+use core::fmt::{self, Write};
+use libpsi_core::{GateOp, QuantumCircuit};
+
+const ROW_HEIGHT: f64 = 40.0;
+const MARGIN: f64 = 20.0;
+const CHAR_WIDTH: f64 = 8.0;
+const MIN_COLUMN: f64 = 40.0;
+const BOX_HEIGHT: f64 = 28.0;
+const STROKE: &str = "#222";
+
+/// The horizontal diagram drawn as SVG: the same moments and wires as
+/// `HorizontalRenderer`, with gates as boxes filled by kind (Clifford,
+/// non-Clifford, measurement and reset, custom) and classical wires doubled.
+/// Never folded; the image is as wide as the circuit.
+pub struct SvgRenderer<'a> {
+    circuit: &'a QuantumCircuit,
+    qubit_labels: Vec<String>,
+    classical_labels: Vec<String>,
+    idle_qubits: IdleQubits,
+}
+
+impl<'a> SvgRenderer<'a> {
+    pub fn new(circuit: &'a QuantumCircuit) -> Self {
+        SvgRenderer {
+            circuit,
+            qubit_labels: Vec::new(),
+            classical_labels: Vec::new(),
+            idle_qubits: IdleQubits::Show,
+        }
+    }
+
+    /// Overrides the circuit's qubit labels, in wire order.
+    pub fn with_qubit_labels(mut self, labels: &[&str]) -> Self {
+        self.qubit_labels = labels.iter().map(|label| label.to_string()).collect();
+        self
+    }
+
+    /// Overrides the circuit's classical bit labels, in wire order.
+    pub fn with_classical_labels(mut self, labels: &[&str]) -> Self {
+        self.classical_labels = labels.iter().map(|label| label.to_string()).collect();
+        self
+    }
+
+    pub fn with_idle_qubits(mut self, idle_qubits: IdleQubits) -> Self {
+        self.idle_qubits = idle_qubits;
+        self
+    }
+}
+
+impl fmt::Display for SvgRenderer<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let view = layout::wire_view(
+            self.circuit,
+            &self.qubit_labels,
+            &self.classical_labels,
+            self.idle_qubits,
+        );
+        let nq = view.num_qubits();
+        let nc = view.num_classical();
+        let moments = layout::pack_moments(&view.ops, nq, nc);
+
+        let label_width = view
+            .qubit_names
+            .iter()
+            .chain(&view.classical_names)
+            .map(|name| text_width(name))
+            .fold(0.0, f64::max)
+            + MARGIN;
+        let widths: Vec<f64> = moments
+            .iter()
+            .map(|moment| {
+                moment
+                    .iter()
+                    .map(|&i| op_width(&view.ops[i], nq))
+                    .fold(MIN_COLUMN, f64::max)
+            })
+            .collect();
+        let width = label_width + widths.iter().sum::<f64>() + 2.0 * MARGIN;
+        let height = (nq + nc) as f64 * ROW_HEIGHT + 2.0 * MARGIN;
+        let y = |row: Row| match row {
+            Row::Quantum(q) => MARGIN + (q as f64 + 0.5) * ROW_HEIGHT,
+            Row::Classical(c) => MARGIN + ((nq + c) as f64 + 0.5) * ROW_HEIGHT,
+        };
+
+        let mut svg = String::new();
+        let _ = write!(
+            svg,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" \
+             viewBox=\"0 0 {w} {h}\" font-family=\"monospace\" font-size=\"13\">\n\
+             <rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n",
+            w = width,
+            h = height
+        );
+
+        let (start, end) = (label_width, width - MARGIN);
+        for (row, name) in view.qubit_names.iter().enumerate() {
+            let wy = y(Row::Quantum(row));
+            if view.is_elided(row) {
+                line(&mut svg, start, wy, end, wy, "stroke-dasharray=\"2 4\"");
+                text(&mut svg, label_width - 8.0, wy, "⋮", "end");
+            } else {
+                line(&mut svg, start, wy, end, wy, "");
+                text(&mut svg, label_width - 8.0, wy, name, "end");
+            }
+        }
+        for (c, name) in view.classical_names.iter().enumerate() {
+            let wy = y(Row::Classical(c));
+            double_line(&mut svg, start, wy, end, wy);
+            text(&mut svg, label_width - 8.0, wy, name, "end");
+        }
+
+        let mut left = label_width + MARGIN;
+        for (moment, column) in moments.iter().zip(&widths) {
+            let x = left + column / 2.0;
+            for &i in moment {
+                draw_op(&mut svg, &view.ops[i], nq, x, &y);
+            }
+            left += column;
+        }
+
+        svg.push_str("</svg>\n");
+        f.write_str(&svg)
+    }
+}
+
+fn draw_op(svg: &mut String, op: &GateOp, nq: usize, x: f64, y: &impl Fn(Row) -> f64) {
+    if let GateOp::Barrier(rows, title) = op {
+        for &row in rows {
+            let wy = y(Row::Quantum(row));
+            line(
+                svg,
+                x,
+                wy - ROW_HEIGHT / 2.0,
+                x,
+                wy + ROW_HEIGHT / 2.0,
+                "stroke-dasharray=\"4 3\" stroke-opacity=\"0.6\"",
+            );
+        }
+        if let (Some(title), Some(&first)) = (title, rows.iter().min()) {
+            text(
+                svg,
+                x,
+                y(Row::Quantum(first)) - ROW_HEIGHT / 2.0 + 6.0,
+                title,
+                "middle",
+            );
+        }
+        return;
+    }
+
+    // Connectors first, so the boxes sit on top of them.
+    let (gate, condition) = match op {
+        GateOp::Conditional {
+            op, classical_bit, ..
+        } => (&**op, Some(*classical_bit)),
+        GateOp::Measure(_, c) => (op, Some(*c)),
+        _ => (op, None),
+    };
+    let targets = gate.quantum_targets();
+    let min_q = targets.iter().min().copied().unwrap_or(0);
+    let max_q = targets.iter().max().copied().unwrap_or(0);
+    if max_q > min_q {
+        line(
+            svg,
+            x,
+            y(Row::Quantum(min_q)),
+            x,
+            y(Row::Quantum(max_q)),
+            "",
+        );
+    }
+    if let Some(c) = condition {
+        double_line(svg, x, y(Row::Quantum(max_q)), x, y(Row::Classical(c)));
+    }
+
+    if let GateOp::Custom(custom, targets) = gate {
+        if targets.len() > 1 {
+            let top = y(Row::Quantum(min_q)) - BOX_HEIGHT / 2.0;
+            let bottom = y(Row::Quantum(max_q)) + BOX_HEIGHT / 2.0;
+            let half = op_width(gate, nq) / 2.0 - 4.0;
+            rect(svg, x - half, top, 2.0 * half, bottom - top, fill(gate));
+            text(svg, x, (top + bottom) / 2.0, &custom.name, "middle");
+            for (port, &q) in targets.iter().enumerate() {
+                let label = port.to_string();
+                text(svg, x - half + 6.0, y(Row::Quantum(q)), &label, "start");
+            }
+            if let Some(c) = condition {
+                draw_cell(
+                    svg,
+                    op,
+                    x,
+                    y(Row::Classical(c)),
+                    &Cell::Condition(condition_label(op, c)),
+                );
+            }
+            return;
+        }
+    }
+
+    for (row, cell) in layout::op_cells(op, nq, Charset::Unicode) {
+        draw_cell(svg, op, x, y(row), &cell);
+    }
+}
+
+fn draw_cell(svg: &mut String, op: &GateOp, x: f64, y: f64, cell: &Cell) {
+    match cell {
+        Cell::Gate(label) => {
+            let label = label.trim_start_matches('[').trim_end_matches(']');
+            let half = (text_width(label) + 16.0).max(BOX_HEIGHT) / 2.0;
+            rect(
+                svg,
+                x - half,
+                y - BOX_HEIGHT / 2.0,
+                2.0 * half,
+                BOX_HEIGHT,
+                fill(op),
+            );
+            text(svg, x, y, label, "middle");
+        }
+        Cell::Control => {
+            let _ = writeln!(
+                svg,
+                "<circle cx=\"{}\" cy=\"{}\" r=\"4\" fill=\"{}\"/>",
+                x, y, STROKE
+            );
+        }
+        Cell::Target => {
+            let _ = writeln!(
+                svg,
+                "<circle cx=\"{}\" cy=\"{}\" r=\"10\" fill=\"white\" stroke=\"{}\"/>",
+                x, y, STROKE
+            );
+            line(svg, x - 10.0, y, x + 10.0, y, "");
+            line(svg, x, y - 10.0, x, y + 10.0, "");
+        }
+        Cell::Swap => {
+            line(svg, x - 6.0, y - 6.0, x + 6.0, y + 6.0, "");
+            line(svg, x - 6.0, y + 6.0, x + 6.0, y - 6.0, "");
+        }
+        Cell::Measure => {
+            let half = BOX_HEIGHT / 2.0;
+            rect(svg, x - half, y - half, BOX_HEIGHT, BOX_HEIGHT, fill(op));
+            let _ = writeln!(
+                svg,
+                "<path d=\"M {} {} A 9 9 0 0 1 {} {}\" fill=\"none\" stroke=\"{}\"/>",
+                x - 9.0,
+                y + 5.0,
+                x + 9.0,
+                y + 5.0,
+                STROKE
+            );
+            line(svg, x, y + 5.0, x + 7.0, y - 8.0, "");
+        }
+        Cell::ClassicalTarget => {
+            let _ = writeln!(
+                svg,
+                "<path d=\"M {} {} L {} {} L {} {} Z\" fill=\"{}\"/>",
+                x - 5.0,
+                y - 8.0,
+                x + 5.0,
+                y - 8.0,
+                x,
+                y,
+                STROKE
+            );
+        }
+        Cell::Condition(label) => {
+            let _ = writeln!(
+                svg,
+                "<circle cx=\"{}\" cy=\"{}\" r=\"4\" fill=\"{}\"/>",
+                x, y, STROKE
+            );
+            text(svg, x + 6.0, y - 10.0, label, "start");
+        }
+        Cell::Block { .. }
+        | Cell::Cross
+        | Cell::MeasureCross
+        | Cell::ClassicalCross
+        | Cell::Barrier => {}
+    }
+}
+
+fn condition_label(op: &GateOp, classical_bit: usize) -> String {
+    match op {
+        GateOp::Conditional { value, .. } => layout::condition_text(classical_bit, *value),
+        _ => String::new(),
+    }
+}
+
+/// The horizontal room `op` needs: its widest box or condition label.
+fn op_width(op: &GateOp, nq: usize) -> f64 {
+    let widest = layout::op_cells(op, nq, Charset::Unicode)
+        .iter()
+        .map(|(_, cell)| match cell {
+            Cell::Gate(label) => text_width(label) + 16.0,
+            Cell::Block { text, .. } => text_width(text) + 16.0,
+            Cell::Condition(label) => 2.0 * (text_width(label) + 6.0),
+            _ => 0.0,
+        })
+        .fold(0.0, f64::max);
+    (widest + 12.0).max(MIN_COLUMN)
+}
+
+/// Fill colours by kind, in the order `Theme::op_style` picks its styles.
+fn fill(op: &GateOp) -> &'static str {
+    let op = match op {
+        GateOp::Conditional { op, .. } => op,
+        op => op,
+    };
+    if op.is_measurement() || op.is_reset() {
+        "#fde68a"
+    } else if op.is_custom() {
+        "#bbf7d0"
+    } else if op.is_non_clifford() {
+        "#e9d5ff"
+    } else {
+        "#bae6fd"
+    }
+}
+
+fn text_width(text: &str) -> f64 {
+    text.chars().count() as f64 * CHAR_WIDTH
+}
+
+fn line(svg: &mut String, x1: f64, y1: f64, x2: f64, y2: f64, extra: &str) {
+    let _ = writeln!(
+        svg,
+        "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"{}\" {}/>",
+        x1, y1, x2, y2, STROKE, extra
+    );
+}
+
+/// A classical wire: two thin lines either side of the path.
+fn double_line(svg: &mut String, x1: f64, y1: f64, x2: f64, y2: f64) {
+    let (dx, dy) = if y1 == y2 { (0.0, 1.5) } else { (1.5, 0.0) };
+    line(svg, x1 - dx, y1 - dy, x2 - dx, y2 - dy, "");
+    line(svg, x1 + dx, y1 + dy, x2 + dx, y2 + dy, "");
+}
+
+fn rect(svg: &mut String, x: f64, y: f64, width: f64, height: f64, fill: &str) {
+    let _ = writeln!(
+        svg,
+        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"3\" fill=\"{}\" stroke=\"{}\"/>",
+        x, y, width, height, fill, STROKE
+    );
+}
+
+fn text(svg: &mut String, x: f64, y: f64, content: &str, anchor: &str) {
+    let _ = writeln!(
+        svg,
+        "<text x=\"{}\" y=\"{}\" text-anchor=\"{}\" dominant-baseline=\"central\">{}</text>",
+        x,
+        y,
+        anchor,
+        escape(content)
+    );
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
@@ -0,0 +1,468 @@
+use libpsi_core::{GateOp, QuantumCircuit, Runtime, Vector};
+use libpsi_visualizer::{
+    AmplitudeRenderer, Charset, HorizontalRenderer, RichDisplay, SvgRenderer, Terminal,
+    VerticalRenderer,
+};
+use numpy::{Complex64, PyArray1};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use std::collections::BTreeMap;
+
+const DEFAULT_RUNTIME: &str = "optimal";
+
+fn runtime(name: &str) -> PyResult<Runtime> {
+    name.parse().map_err(PyValueError::new_err)
+}
+
+/// Imports numpy first, so that a missing install is an `ImportError`
+/// rather than a panic inside the array constructor.
+fn require_numpy(py: Python<'_>) -> PyResult<()> {
+    py.import("numpy").map(|_| ())
+}
+
+fn terminal(ascii: bool, width: Option<usize>) -> Terminal {
+    let terminal = Terminal::plain().with_unicode(!ascii);
+    match width {
+        Some(width) => terminal.with_width(width),
+        None => terminal,
+    }
+}
+
+/// A quantum circuit. Gate methods return the circuit, so calls chain:
+/// `QuantumCircuit(2).h(0).cx(0, 1)`.
+#[pyclass(name = "QuantumCircuit", module = "psi")]
+struct PyCircuit {
+    circuit: QuantumCircuit,
+}
+
+impl PyCircuit {
+    /// Appends `op` after checking that its qubits exist and are distinct.
+    fn push(mut slf: PyRefMut<'_, Self>, op: GateOp) -> PyResult<PyRefMut<'_, Self>> {
+        let num_qubits = slf.circuit.num_qubits();
+        let targets = op.quantum_targets();
+        for (i, &q) in targets.iter().enumerate() {
+            if q >= num_qubits {
+                return Err(PyValueError::new_err(format!(
+                    "qubit {} out of range for {} qubits",
+                    q, num_qubits
+                )));
+            }
+            if targets[..i].contains(&q) {
+                return Err(PyValueError::new_err(format!(
+                    "{} uses qubit {} twice",
+                    op.name(),
+                    q
+                )));
+            }
+        }
+        slf.circuit.push(op);
+        Ok(slf)
+    }
+}
+
+#[pymethods]
+impl PyCircuit {
+    #[new]
+    #[pyo3(signature = (num_qubits, num_classical = 0))]
+    fn new(num_qubits: usize, num_classical: usize) -> Self {
+        PyCircuit {
+            circuit: QuantumCircuit::with_classical(num_qubits, num_classical),
+        }
+    }
+
+    /// Parses an OpenQASM 2.0 program.
+    #[staticmethod]
+    fn from_qasm(source: &str) -> PyResult<Self> {
+        let circuit =
+            libpsi_qasm::parse(source).map_err(|err| PyValueError::new_err(err.to_string()))?;
+        Ok(PyCircuit { circuit })
+    }
+
+    fn to_qasm(&self) -> PyResult<String> {
+        libpsi_qasm::to_qasm(&self.circuit).map_err(PyValueError::new_err)
+    }
+
+    #[getter]
+    fn num_qubits(&self) -> usize {
+        self.circuit.num_qubits()
+    }
+
+    #[getter]
+    fn num_classical(&self) -> usize {
+        self.circuit.num_classical()
+    }
+
+    fn __len__(&self) -> usize {
+        self.circuit.operations().len()
+    }
+
+    fn h(slf: PyRefMut<'_, Self>, q: usize) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, GateOp::H(q))
+    }
+
+    fn x(slf: PyRefMut<'_, Self>, q: usize) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, GateOp::X(q))
+    }
+
+    fn y(slf: PyRefMut<'_, Self>, q: usize) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, GateOp::Y(q))
+    }
+
+    fn z(slf: PyRefMut<'_, Self>, q: usize) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, GateOp::Z(q))
+    }
+
+    fn s(slf: PyRefMut<'_, Self>, q: usize) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, GateOp::S(q))
+    }
+
+    fn t(slf: PyRefMut<'_, Self>, q: usize) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, GateOp::T(q))
+    }
+
+    fn sdg(slf: PyRefMut<'_, Self>, q: usize) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, GateOp::Sdg(q))
+    }
+
+    fn tdg(slf: PyRefMut<'_, Self>, q: usize) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, GateOp::Tdg(q))
+    }
+
+    fn sx(slf: PyRefMut<'_, Self>, q: usize) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, GateOp::Sx(q))
+    }
+
+    fn sxdg(slf: PyRefMut<'_, Self>, q: usize) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, GateOp::Sxdg(q))
+    }
+
+    fn rx(slf: PyRefMut<'_, Self>, q: usize, theta: f64) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, GateOp::Rx(q, theta))
+    }
+
+    fn ry(slf: PyRefMut<'_, Self>, q: usize, theta: f64) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, GateOp::Ry(q, theta))
+    }
+
+    fn rz(slf: PyRefMut<'_, Self>, q: usize, theta: f64) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, GateOp::Rz(q, theta))
+    }
+
+    fn p(slf: PyRefMut<'_, Self>, q: usize, theta: f64) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, GateOp::P(q, theta))
+    }
+
+    fn u3(
+        slf: PyRefMut<'_, Self>,
+        q: usize,
+        theta: f64,
+        phi: f64,
+        lambda: f64,
+    ) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, GateOp::U3(q, theta, phi, lambda))
+    }
+
+    #[pyo3(name = "cx")]
+    fn cnot(
+        slf: PyRefMut<'_, Self>,
+        control: usize,
+        target: usize,
+    ) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, GateOp::CNOT(control, target))
+    }
+
+    fn cz(slf: PyRefMut<'_, Self>, control: usize, target: usize) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, GateOp::CZ(control, target))
+    }
+
+    fn cy(slf: PyRefMut<'_, Self>, control: usize, target: usize) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, GateOp::CY(control, target))
+    }
+
+    fn ch(slf: PyRefMut<'_, Self>, control: usize, target: usize) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, GateOp::CH(control, target))
+    }
+
+    fn csx(slf: PyRefMut<'_, Self>, control: usize, target: usize) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, GateOp::CSX(control, target))
+    }
+
+    fn ecr(slf: PyRefMut<'_, Self>, a: usize, b: usize) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, GateOp::ECR(a, b))
+    }
+
+    fn dcx(slf: PyRefMut<'_, Self>, a: usize, b: usize) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, GateOp::DCX(a, b))
+    }
+
+    fn swap(slf: PyRefMut<'_, Self>, a: usize, b: usize) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, GateOp::SWAP(a, b))
+    }
+
+    fn iswap(slf: PyRefMut<'_, Self>, a: usize, b: usize) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, GateOp::ISwap(a, b))
+    }
+
+    fn sqrt_swap(slf: PyRefMut<'_, Self>, a: usize, b: usize) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, GateOp::SqrtSwap(a, b))
+    }
+
+    fn crx(
+        slf: PyRefMut<'_, Self>,
+        control: usize,
+        target: usize,
+        theta: f64,
+    ) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, GateOp::CRx(control, target, theta))
+    }
+
+    fn cry(
+        slf: PyRefMut<'_, Self>,
+        control: usize,
+        target: usize,
+        theta: f64,
+    ) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, GateOp::CRy(control, target, theta))
+    }
+
+    fn crz(
+        slf: PyRefMut<'_, Self>,
+        control: usize,
+        target: usize,
+        theta: f64,
+    ) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, GateOp::CRz(control, target, theta))
+    }
+
+    fn cp(
+        slf: PyRefMut<'_, Self>,
+        control: usize,
+        target: usize,
+        theta: f64,
+    ) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, GateOp::CP(control, target, theta))
+    }
+
+    fn rxx(
+        slf: PyRefMut<'_, Self>,
+        a: usize,
+        b: usize,
+        theta: f64,
+    ) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, GateOp::Rxx(a, b, theta))
+    }
+
+    fn ryy(
+        slf: PyRefMut<'_, Self>,
+        a: usize,
+        b: usize,
+        theta: f64,
+    ) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, GateOp::Ryy(a, b, theta))
+    }
+
+    fn rzz(
+        slf: PyRefMut<'_, Self>,
+        a: usize,
+        b: usize,
+        theta: f64,
+    ) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, GateOp::Rzz(a, b, theta))
+    }
+
+    #[pyo3(name = "ccx")]
+    fn ccnot(
+        slf: PyRefMut<'_, Self>,
+        c1: usize,
+        c2: usize,
+        target: usize,
+    ) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, GateOp::CCNOT(c1, c2, target))
+    }
+
+    fn mcx(
+        slf: PyRefMut<'_, Self>,
+        controls: Vec<usize>,
+        target: usize,
+    ) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, GateOp::MCX(controls, target))
+    }
+
+    fn cswap(
+        slf: PyRefMut<'_, Self>,
+        control: usize,
+        t1: usize,
+        t2: usize,
+    ) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, GateOp::CSWAP(control, t1, t2))
+    }
+
+    /// Appends a gate by name, OpenQASM (`"crz"`) or builder style
+    /// (`"toffoli"`), for drivers that receive gates as strings.
+    #[pyo3(signature = (name, qubits, params = Vec::new()))]
+    fn append_by_name<'py>(
+        mut slf: PyRefMut<'py, Self>,
+        name: &str,
+        qubits: Vec<usize>,
+        params: Vec<f64>,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        slf.circuit
+            .append_by_name(name, &qubits, &params)
+            .map_err(PyValueError::new_err)?;
+        Ok(slf)
+    }
+
+    /// Measures `qubit` into classical bit `clbit` (default: the same index).
+    #[pyo3(signature = (qubit, clbit = None))]
+    fn measure(
+        slf: PyRefMut<'_, Self>,
+        qubit: usize,
+        clbit: Option<usize>,
+    ) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, GateOp::Measure(qubit, clbit.unwrap_or(qubit)))
+    }
+
+    /// Barrier across the given qubits, or all of them when none are given.
+    #[pyo3(signature = (*qubits))]
+    fn barrier(slf: PyRefMut<'_, Self>, qubits: Vec<usize>) -> PyResult<PyRefMut<'_, Self>> {
+        let qubits = if qubits.is_empty() {
+            (0..slf.circuit.num_qubits()).collect()
+        } else {
+            qubits
+        };
+        Self::push(slf, GateOp::Barrier(qubits, None))
+    }
+
+    fn section<'py>(mut slf: PyRefMut<'py, Self>, title: &str) -> PyRefMut<'py, Self> {
+        slf.circuit.section(title);
+        slf
+    }
+
+    /// Final state vector as a complex128 numpy array, qubit 0 being the
+    /// most significant bit of the index.
+    #[pyo3(signature = (runtime = DEFAULT_RUNTIME))]
+    fn state<'py>(
+        &mut self,
+        py: Python<'py>,
+        runtime: &str,
+    ) -> PyResult<Bound<'py, PyArray1<Complex64>>> {
+        require_numpy(py)?;
+        let runtime = self::runtime(runtime)?;
+        let state = self.circuit.compute_with(runtime);
+        let amplitudes: Vec<Complex64> = (0..state.size())
+            .map(|i| {
+                let amp = state.get(i);
+                Complex64::new(amp.real, amp.imaginary)
+            })
+            .collect();
+        Ok(PyArray1::from_vec(py, amplitudes))
+    }
+
+    /// Probability of every basis state as a float64 numpy array.
+    #[pyo3(signature = (runtime = DEFAULT_RUNTIME))]
+    fn probabilities<'py>(
+        &mut self,
+        py: Python<'py>,
+        runtime: &str,
+    ) -> PyResult<Bound<'py, PyArray1<f64>>> {
+        require_numpy(py)?;
+        let runtime = self::runtime(runtime)?;
+        let state = self.circuit.compute_with(runtime);
+        let probabilities: Vec<f64> = (0..state.size()).map(|i| state.get(i).norm2()).collect();
+        Ok(PyArray1::from_vec(py, probabilities))
+    }
+
+    /// Samples the measured classical bits (or all qubits, without
+    /// measurements) `shots` times; returns a dict of bitstring to count.
+    #[pyo3(signature = (shots, seed = None, runtime = DEFAULT_RUNTIME))]
+    fn sample(
+        &mut self,
+        shots: usize,
+        seed: Option<u64>,
+        runtime: &str,
+    ) -> PyResult<BTreeMap<String, usize>> {
+        let runtime = self::runtime(runtime)?;
+        self.circuit.compute_with(runtime);
+        let counts = self.circuit.sample(shots, seed);
+        Ok(counts
+            .iter()
+            .map(|(bits, n)| (bits.to_string(), n))
+            .collect())
+    }
+
+    /// Diagram of the circuit: text in the `"horizontal"` or `"vertical"`
+    /// style, or an SVG image with `"svg"`, which ignores `ascii` and
+    /// `width`.
+    #[pyo3(signature = (style = "horizontal", ascii = false, width = None))]
+    fn draw(&self, style: &str, ascii: bool, width: Option<usize>) -> PyResult<String> {
+        let terminal = terminal(ascii, width);
+        match style {
+            "svg" => Ok(SvgRenderer::new(&self.circuit).to_string()),
+            "horizontal" => Ok(HorizontalRenderer::new(&self.circuit)
+                .with_terminal(terminal)
+                .to_string()),
+            "vertical" => Ok(VerticalRenderer::new(&self.circuit)
+                .with_terminal(terminal)
+                .to_string()),
+            _ => Err(PyValueError::new_err(format!("unknown style '{}'", style))),
+        }
+    }
+
+    /// Bar chart of the final amplitudes.
+    #[pyo3(signature = (runtime = DEFAULT_RUNTIME, ascii = false))]
+    fn amplitudes(&mut self, runtime: &str, ascii: bool) -> PyResult<String> {
+        let runtime = self::runtime(runtime)?;
+        let state = self.circuit.compute_with(runtime);
+        let charset = if ascii {
+            Charset::Ascii
+        } else {
+            Charset::Unicode
+        };
+        Ok(AmplitudeRenderer::new(state)
+            .with_charset(charset)
+            .to_string())
+    }
+
+    fn __str__(&self) -> String {
+        HorizontalRenderer::new(&self.circuit)
+            .with_terminal(Terminal::plain().with_unicode(true))
+            .to_string()
+    }
+
+    /// Rich display in Jupyter: the diagram, plus the state once computed.
+    fn _repr_html_(&self) -> String {
+        self.circuit.to_html()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "QuantumCircuit(num_qubits={}, num_classical={}, operations={})",
+            self.circuit.num_qubits(),
+            self.circuit.num_classical(),
+            self.circuit.operations().len()
+        )
+    }
+}
+
+/// Python bindings for the psi quantum circuit simulator.
+#[pymodule]
+fn psi(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyCircuit>()?;
+    m.add(
+        "RUNTIMES",
+        vec![
+            "basic",
+            "basic-mt",
+            "batched",
+            "batched-mt",
+            "simd",
+            "simd-mt",
+            "structure-aware",
+            "structure-aware-mt",
+            "stabilizer",
+            "gpu",
+            "optimal",
+        ],
+    )?;
+    Ok(())
+}
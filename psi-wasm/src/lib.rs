@@ -0,0 +1,141 @@
+use js_sys::{Float64Array, Math, Object, Reflect};
+use libpsi_core::{GateOp, QuantumCircuit, Runtime, Vector};
+use libpsi_visualizer::{HorizontalRenderer, Terminal, VerticalRenderer};
+use wasm_bindgen::prelude::*;
+
+/// A circuit for JavaScript. Gates are added by their OpenQASM name, so a
+/// playground can pass user input straight through:
+/// `circuit.apply("cx", [0, 1], [])`.
+#[wasm_bindgen]
+pub struct Circuit {
+    circuit: QuantumCircuit,
+    runtime: Runtime,
+}
+
+impl Circuit {
+    fn check_qubits(&self, qubits: &[usize]) -> Result<(), JsError> {
+        let num_qubits = self.circuit.num_qubits();
+        for (i, &q) in qubits.iter().enumerate() {
+            if q >= num_qubits {
+                return Err(JsError::new(&format!(
+                    "qubit {} out of range for {} qubits",
+                    q, num_qubits
+                )));
+            }
+            if qubits[..i].contains(&q) {
+                return Err(JsError::new(&format!("qubit {} used twice", q)));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[wasm_bindgen]
+impl Circuit {
+    #[wasm_bindgen(constructor)]
+    pub fn new(num_qubits: usize) -> Circuit {
+        Circuit {
+            circuit: QuantumCircuit::new(num_qubits),
+            runtime: Runtime::SimdRT,
+        }
+    }
+
+    /// Parses an OpenQASM 2.0 program.
+    #[wasm_bindgen(js_name = fromQasm)]
+    pub fn from_qasm(source: &str) -> Result<Circuit, JsError> {
+        let circuit = libpsi_qasm::parse(source).map_err(|err| JsError::new(&err.to_string()))?;
+        Ok(Circuit {
+            circuit,
+            runtime: Runtime::SimdRT,
+        })
+    }
+
+    #[wasm_bindgen(js_name = toQasm)]
+    pub fn to_qasm(&self) -> Result<String, JsError> {
+        libpsi_qasm::to_qasm(&self.circuit).map_err(|err| JsError::new(&err))
+    }
+
+    #[wasm_bindgen(getter, js_name = numQubits)]
+    pub fn num_qubits(&self) -> usize {
+        self.circuit.num_qubits()
+    }
+
+    /// Selects the runtime by name (`basic`, `simd`, `structure-aware`, ...).
+    /// Multi-threaded names run single-threaded in the browser.
+    #[wasm_bindgen(js_name = setRuntime)]
+    pub fn set_runtime(&mut self, name: &str) -> Result<(), JsError> {
+        self.runtime = name.parse().map_err(|err: String| JsError::new(&err))?;
+        Ok(())
+    }
+
+    /// Appends the gate `name` on `qubits` with `params`, named as in
+    /// `qelib1.inc` or after the builder methods.
+    pub fn apply(&mut self, name: &str, qubits: Vec<u32>, params: Vec<f64>) -> Result<(), JsError> {
+        let qubits: Vec<usize> = qubits.into_iter().map(|q| q as usize).collect();
+        if name == "id" && qubits.len() == 1 && params.is_empty() {
+            // No operation of its own.
+            return self.check_qubits(&qubits);
+        }
+        self.circuit
+            .append_by_name(name, &qubits, &params)
+            .map_err(|err| JsError::new(&err))?;
+        Ok(())
+    }
+
+    pub fn measure(&mut self, qubit: usize, clbit: usize) -> Result<(), JsError> {
+        self.check_qubits(&[qubit])?;
+        self.circuit.push(GateOp::Measure(qubit, clbit));
+        Ok(())
+    }
+
+    /// Removes the last operation; returns `false` if there was none.
+    pub fn undo(&mut self) -> bool {
+        self.circuit.pop().is_some()
+    }
+
+    /// Final state as interleaved `[re0, im0, re1, im1, ...]`, qubit 0 being
+    /// the most significant bit of the index.
+    pub fn state(&mut self) -> Float64Array {
+        let state = self.circuit.compute_with(self.runtime);
+        let amplitudes: Vec<f64> = (0..state.size())
+            .flat_map(|i| {
+                let amp = state.get(i);
+                [amp.real, amp.imaginary]
+            })
+            .collect();
+        Float64Array::from(amplitudes.as_slice())
+    }
+
+    pub fn probabilities(&mut self) -> Float64Array {
+        let state = self.circuit.compute_with(self.runtime);
+        let probabilities: Vec<f64> = (0..state.size()).map(|i| state.get(i).norm2()).collect();
+        Float64Array::from(probabilities.as_slice())
+    }
+
+    /// Samples `shots` outcomes into an object of bitstring to count. Without
+    /// a seed one is drawn from `Math.random`.
+    pub fn sample(&mut self, shots: usize, seed: Option<u32>) -> Result<Object, JsValue> {
+        let seed = seed.map_or_else(|| (Math::random() * u32::MAX as f64) as u64, u64::from);
+        self.circuit.compute_with(self.runtime);
+        let counts = self.circuit.sample(shots, Some(seed));
+        let object = Object::new();
+        for (bits, n) in counts.iter() {
+            Reflect::set(&object, &bits.into(), &(n as f64).into())?;
+        }
+        Ok(object)
+    }
+
+    /// Text diagram of the circuit, horizontal unless `vertical`.
+    pub fn draw(&self, vertical: bool, ascii: bool) -> String {
+        let terminal = Terminal::plain().with_unicode(!ascii);
+        if vertical {
+            VerticalRenderer::new(&self.circuit)
+                .with_terminal(terminal)
+                .to_string()
+        } else {
+            HorizontalRenderer::new(&self.circuit)
+                .with_terminal(terminal)
+                .to_string()
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use libpsi_core::{GateOp, QuantumCircuit};
+use serde_json::Value;
+
+/// Reads a circuit from JSON of the form
+///
+/// ```json
+/// {
+///   "num_qubits": 2,
+///   "num_classical": 2,
+///   "operations": [
+///     { "gate": "h", "qubits": [0] },
+///     { "gate": "rx", "qubits": [1], "params": [0.5] },
+///     { "gate": "cx", "qubits": [0, 1] },
+///     { "gate": "barrier", "qubits": [0, 1], "title": "readout" },
+///     { "gate": "measure", "qubits": [0], "clbits": [0] }
+///   ]
+/// }
+/// ```
+///
+/// Gate names and parameters are those of OpenQASM's `qelib1.inc`, or
+/// the builder method names `GateOp::from_name` also accepts;
+/// `num_classical` is optional and grows to fit the measurements.
+pub fn parse(source: &str) -> Result<QuantumCircuit, String> {
+    let root: Value = serde_json::from_str(source).map_err(|err| err.to_string())?;
+    let num_qubits = field_usize(&root, "num_qubits")?;
+    let num_classical = match root.get("num_classical") {
+        Some(_) => field_usize(&root, "num_classical")?,
+        None => 0,
+    };
+    let operations = root
+        .get("operations")
+        .and_then(Value::as_array)
+        .ok_or("missing 'operations' array")?;
+
+    let mut circuit = QuantumCircuit::with_classical(num_qubits, num_classical);
+    for (i, entry) in operations.iter().enumerate() {
+        let op = operation(entry, num_qubits).map_err(|err| format!("operation {}: {}", i, err))?;
+        if let Some(op) = op {
+            circuit.push(op);
+        }
+    }
+    Ok(circuit)
+}
+
+fn operation(entry: &Value, num_qubits: usize) -> Result<Option<GateOp>, String> {
+    let gate = entry
+        .get("gate")
+        .and_then(Value::as_str)
+        .ok_or("missing 'gate' name")?;
+    let qubits = usize_list(entry, "qubits")?;
+    if let Some(&q) = qubits.iter().find(|&&q| q >= num_qubits) {
+        return Err(format!(
+            "qubit {} out of range for {} qubits",
+            q, num_qubits
+        ));
+    }
+    let params: Vec<f64> = match entry.get("params") {
+        Some(Value::Array(values)) => values
+            .iter()
+            .map(|v| v.as_f64().ok_or("'params' must be numbers"))
+            .collect::<Result<_, _>>()?,
+        Some(_) => return Err("'params' must be an array".to_string()),
+        None => Vec::new(),
+    };
+
+    match gate {
+        "measure" => {
+            let clbits = usize_list(entry, "clbits")?;
+            match (qubits.as_slice(), clbits.as_slice()) {
+                ([q], [c]) => Ok(Some(GateOp::Measure(*q, *c))),
+                _ => Err("'measure' takes one qubit and one clbit".to_string()),
+            }
+        }
+        "barrier" => {
+            let title = entry
+                .get("title")
+                .and_then(Value::as_str)
+                .map(str::to_string);
+            Ok(Some(GateOp::Barrier(qubits, title)))
+        }
+        "id" => Ok(None),
+        _ => GateOp::from_name(gate, &qubits, &params).map(Some),
+    }
+}
+
+fn field_usize(value: &Value, key: &str) -> Result<usize, String> {
+    value
+        .get(key)
+        .and_then(Value::as_u64)
+        .map(|n| n as usize)
+        .ok_or_else(|| format!("'{}' must be a non-negative integer", key))
+}
+
+fn usize_list(value: &Value, key: &str) -> Result<Vec<usize>, String> {
+    match value.get(key) {
+        Some(Value::Array(values)) => values
+            .iter()
+            .map(|v| {
+                v.as_u64()
+                    .map(|n| n as usize)
+                    .ok_or_else(|| format!("'{}' must hold non-negative integers", key))
+            })
+            .collect(),
+        Some(_) => Err(format!("'{}' must be an array", key)),
+        None => Ok(Vec::new()),
+    }
+}
@@ -0,0 +1,247 @@
+mod json;
+mod repl;
+
+use libpsi_core::{QuantumCircuit, Runtime};
+use libpsi_visualizer::{
+    format_state, AmplitudeRenderer, HorizontalRenderer, Terminal, VerticalRenderer,
+};
+use std::io::Read;
+use std::process::ExitCode;
+use std::{env, fs, io};
+
+const DEFAULT_SHOTS: usize = 1024;
+const DEFAULT_CUTOFF: f64 = 1e-6;
+
+fn print_usage() {
+    println!("Usage: psi <FILE> [OPTIONS]");
+    println!("       psi repl [QUBITS] [OPTIONS]");
+    println!();
+    println!("Runs a circuit read from an OpenQASM 2.0 (.qasm) or JSON (.json) file;");
+    println!("'-' reads OpenQASM from standard input. 'repl' instead starts an");
+    println!("interactive session on QUBITS qubits (default 2) where gates are applied");
+    println!("one at a time.");
+    println!();
+    println!("Options:");
+    println!("  --runtime <NAME>  basic (default), basic-mt, batched, batched-mt, simd,");
+    println!("                    simd-mt, structure-aware, structure-aware-mt, stabilizer,");
+    println!("                    gpu, optimal");
+    println!("  --shots <N>       Sample N measurement shots and print the counts");
+    println!("  --seed <N>        Seed for sampling");
+    println!("  --show <WHAT>     state, counts, diagram, vertical or amplitudes; may be");
+    println!("                    repeated or comma-separated (default: state, or counts");
+    println!("                    with --shots)");
+    println!("  --cutoff <P>      Hide basis states with probability below P (default 1e-6)");
+    println!("  --width <N>       Fold diagrams to N columns");
+    println!("  --ascii           Draw with ASCII characters only");
+    println!("  --color           Force ANSI colours");
+    println!("  --no-color        Disable ANSI colours");
+    println!("  -h, --help        Show this help message");
+    println!();
+    println!("Examples:");
+    println!("  psi bell.qasm --show diagram,state");
+    println!("  psi ghz.json --runtime simd-mt --shots 1000 --seed 42");
+    println!("  psi repl 3");
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Show {
+    State,
+    Counts,
+    Diagram,
+    Vertical,
+    Amplitudes,
+}
+
+struct Options {
+    /// The circuit file, or the qubit count in `repl` mode.
+    input: Option<String>,
+    runtime: Runtime,
+    shots: Option<usize>,
+    seed: Option<u64>,
+    show: Vec<Show>,
+    cutoff: f64,
+    terminal: Terminal,
+}
+
+fn parse_show(names: &str) -> Result<Vec<Show>, String> {
+    names
+        .split(',')
+        .map(|name| match name.trim() {
+            "state" => Ok(Show::State),
+            "counts" => Ok(Show::Counts),
+            "diagram" => Ok(Show::Diagram),
+            "vertical" => Ok(Show::Vertical),
+            "amplitudes" => Ok(Show::Amplitudes),
+            other => Err(format!("unknown --show value '{}'", other)),
+        })
+        .collect()
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut input = None;
+    let mut runtime = Runtime::default();
+    let mut shots = None;
+    let mut seed = None;
+    let mut show = Vec::new();
+    let mut cutoff = DEFAULT_CUTOFF;
+    let mut terminal = Terminal::detect();
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = |flag: &str| {
+            iter.next()
+                .cloned()
+                .ok_or_else(|| format!("{} expects a value", flag))
+        };
+        match arg.as_str() {
+            "--runtime" => runtime = value(arg)?.parse()?,
+            "--shots" => {
+                let n = value(arg)?;
+                shots = Some(
+                    n.parse()
+                        .map_err(|_| format!("invalid shot count '{}'", n))?,
+                );
+            }
+            "--seed" => {
+                let n = value(arg)?;
+                seed = Some(n.parse().map_err(|_| format!("invalid seed '{}'", n))?);
+            }
+            "--show" => show.extend(parse_show(&value(arg)?)?),
+            "--cutoff" => {
+                let p = value(arg)?;
+                cutoff = p.parse().map_err(|_| format!("invalid cutoff '{}'", p))?;
+            }
+            "--width" => {
+                let n = value(arg)?;
+                terminal =
+                    terminal.with_width(n.parse().map_err(|_| format!("invalid width '{}'", n))?);
+            }
+            "--ascii" => terminal = terminal.with_unicode(false),
+            "--color" => terminal = terminal.with_color(true),
+            "--no-color" => terminal = terminal.with_color(false),
+            _ if arg.starts_with("--") => return Err(format!("unknown option '{}'", arg)),
+            _ if input.is_none() => input = Some(arg.clone()),
+            _ => return Err(format!("unexpected argument '{}'", arg)),
+        }
+    }
+
+    if show.is_empty() {
+        show.push(if shots.is_some() {
+            Show::Counts
+        } else {
+            Show::State
+        });
+    }
+
+    Ok(Options {
+        input,
+        runtime,
+        shots,
+        seed,
+        show,
+        cutoff,
+        terminal,
+    })
+}
+
+fn load_circuit(path: &str) -> Result<QuantumCircuit, String> {
+    let source = if path == "-" {
+        let mut source = String::new();
+        io::stdin()
+            .read_to_string(&mut source)
+            .map_err(|err| format!("cannot read standard input: {}", err))?;
+        source
+    } else {
+        fs::read_to_string(path).map_err(|err| format!("cannot read {}: {}", path, err))?
+    };
+
+    if path.ends_with(".json") {
+        json::parse(&source).map_err(|err| format!("{}: {}", path, err))
+    } else {
+        libpsi_qasm::parse(&source).map_err(|err| format!("{}: {}", path, err))
+    }
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    let path = options.input.as_deref().ok_or("missing circuit file")?;
+    let mut circuit = load_circuit(path)?;
+    circuit.compute_with(options.runtime);
+
+    for (i, show) in options.show.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        match show {
+            Show::State => {
+                let num_qubits = circuit.num_qubits();
+                let charset = options.terminal.charset();
+                println!(
+                    "{}",
+                    format_state(circuit.state(), num_qubits, options.cutoff, charset)
+                );
+            }
+            Show::Counts => {
+                let shots = options.shots.unwrap_or(DEFAULT_SHOTS);
+                println!("{}", circuit.sample(shots, options.seed));
+            }
+            Show::Diagram => print!(
+                "{}",
+                HorizontalRenderer::new(&circuit).with_terminal(options.terminal)
+            ),
+            Show::Vertical => print!(
+                "{}",
+                VerticalRenderer::new(&circuit).with_terminal(options.terminal)
+            ),
+            Show::Amplitudes => {
+                let state = circuit.state();
+                print!(
+                    "{}",
+                    AmplitudeRenderer::new(state).with_terminal(options.terminal)
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.is_empty() || args.iter().any(|a| a == "--help" || a == "-h") {
+        print_usage();
+        return ExitCode::SUCCESS;
+    }
+
+    let repl = args[0] == "repl";
+    let options = match parse_args(&args[usize::from(repl)..]) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("psi: {}", err);
+            eprintln!("Run 'psi --help' for usage.");
+            return ExitCode::from(2);
+        }
+    };
+
+    if repl {
+        let num_qubits = match options.input.as_deref().map(str::parse::<usize>) {
+            None => 2,
+            Some(Ok(n)) => n,
+            Some(Err(_)) => {
+                eprintln!(
+                    "psi: invalid qubit count '{}'",
+                    options.input.unwrap_or_default()
+                );
+                return ExitCode::from(2);
+            }
+        };
+        repl::run(num_qubits, options.runtime, options.terminal);
+        return ExitCode::SUCCESS;
+    }
+
+    match run(&options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("psi: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use libpsi_core::{GateOp, QuantumCircuit, Runtime};
+use libpsi_qasm::expr;
+use libpsi_visualizer::{
+    format_state, AmplitudeRenderer, HorizontalRenderer, Terminal, VerticalRenderer,
+};
+use std::io::{self, BufRead, Write};
+
+const CUTOFF: f64 = 1e-6;
+
+fn print_help() {
+    println!("Gates (OpenQASM names, plus cnot, toffoli and fredkin):");
+    println!("  h 0            apply H to qubit 0");
+    println!("  cnot 0 1       apply CNOT with control 0 and target 1");
+    println!("  rx(pi/2) 0     parameters in parentheses, or after the qubits: rx 0 pi/2");
+    println!("  measure 0 [c]  measure qubit 0 into classical bit c (default: same index)");
+    println!();
+    println!("Commands:");
+    println!("  state          print the current state");
+    println!("  probs          print the amplitudes as a bar chart");
+    println!("  draw [vertical] draw the circuit so far");
+    println!("  counts [N]     sample N shots (default 1024)");
+    println!("  undo           remove the last operation");
+    println!("  clear          remove all operations");
+    println!("  qubits N       start over with N qubits");
+    println!("  runtime NAME   switch runtime (see psi --help)");
+    println!("  help           show this message");
+    println!("  quit           leave the REPL");
+}
+
+struct Session {
+    circuit: QuantumCircuit,
+    runtime: Runtime,
+    terminal: Terminal,
+}
+
+impl Session {
+    fn state(&mut self) -> String {
+        let num_qubits = self.circuit.num_qubits();
+        let state = self.circuit.compute_with(self.runtime);
+        format_state(state, num_qubits, CUTOFF, self.terminal.charset())
+    }
+
+    /// Runs one line; returns `Ok(false)` when the session should end.
+    fn execute(&mut self, line: &str) -> Result<bool, String> {
+        let mut words = line.split_whitespace();
+        let Some(command) = words.next() else {
+            return Ok(true);
+        };
+        let args: Vec<&str> = words.collect();
+
+        match command {
+            "quit" | "exit" => return Ok(false),
+            "help" | "?" => print_help(),
+            "state" => println!("{}", self.state()),
+            "probs" => {
+                let state = self.circuit.compute_with(self.runtime);
+                print!(
+                    "{}",
+                    AmplitudeRenderer::new(state).with_terminal(self.terminal)
+                );
+            }
+            "draw" => match args.first() {
+                Some(&"vertical") => print!(
+                    "{}",
+                    VerticalRenderer::new(&self.circuit).with_terminal(self.terminal)
+                ),
+                _ => print!(
+                    "{}",
+                    HorizontalRenderer::new(&self.circuit).with_terminal(self.terminal)
+                ),
+            },
+            "counts" => {
+                let shots = match args.first() {
+                    Some(n) => n
+                        .parse()
+                        .map_err(|_| format!("invalid shot count '{}'", n))?,
+                    None => 1024,
+                };
+                self.circuit.compute_with(self.runtime);
+                println!("{}", self.circuit.sample(shots, None));
+            }
+            "undo" => match self.circuit.pop() {
+                Some(op) => {
+                    println!("removed {} on {:?}", op.name(), op.quantum_targets());
+                    println!("{}", self.state());
+                }
+                None => println!("nothing to undo"),
+            },
+            "clear" => {
+                self.circuit.reset();
+                println!("{}", self.state());
+            }
+            "qubits" => {
+                let n = args.first().ok_or("usage: qubits N")?;
+                let n: usize = n
+                    .parse()
+                    .map_err(|_| format!("invalid qubit count '{}'", n))?;
+                self.circuit = QuantumCircuit::new(n);
+                println!("{}", self.state());
+            }
+            "runtime" => {
+                let name = args.first().ok_or("usage: runtime NAME")?;
+                self.runtime = name.parse()?;
+                let ops = self.circuit.operations().to_vec();
+                let mut circuit = QuantumCircuit::with_classical(
+                    self.circuit.num_qubits(),
+                    self.circuit.num_classical(),
+                );
+                for op in ops {
+                    circuit.push(op);
+                }
+                self.circuit = circuit;
+                println!("{}", self.state());
+            }
+            _ => {
+                let op = self.parse_op(command, &args)?;
+                self.circuit.push(op);
+                println!("{}", self.state());
+            }
+        }
+        Ok(true)
+    }
+
+    fn parse_op(&self, command: &str, args: &[&str]) -> Result<GateOp, String> {
+        let (name, mut params) = match command.split_once('(') {
+            Some((name, rest)) => {
+                let inner = rest
+                    .strip_suffix(')')
+                    .ok_or_else(|| format!("unclosed '(' in '{}'", command))?;
+                let params = inner
+                    .split(',')
+                    .map(expr::evaluate)
+                    .collect::<Result<Vec<f64>, String>>()?;
+                (name, params)
+            }
+            None => (command, Vec::new()),
+        };
+
+        let num_qubits = self.circuit.num_qubits();
+        let qubit = |text: &str| -> Result<usize, String> {
+            match text.parse::<usize>() {
+                Ok(q) if q < num_qubits => Ok(q),
+                Ok(q) => Err(format!(
+                    "qubit {} out of range for {} qubits",
+                    q, num_qubits
+                )),
+                Err(_) => Err(format!("invalid qubit '{}'", text)),
+            }
+        };
+
+        if name == "measure" {
+            let q = qubit(args.first().ok_or("usage: measure Q [C]")?)?;
+            let c = match args.get(1) {
+                Some(c) => c
+                    .parse()
+                    .map_err(|_| format!("invalid classical bit '{}'", c))?,
+                None => q,
+            };
+            return Ok(GateOp::Measure(q, c));
+        }
+
+        let (arity, num_params) =
+            GateOp::signature(name).ok_or_else(|| format!("unknown command or gate '{}'", name))?;
+        if args.len() < arity {
+            return Err(format!("'{}' acts on {} qubits", name, arity));
+        }
+        let qubits = args[..arity]
+            .iter()
+            .map(|arg| qubit(arg))
+            .collect::<Result<Vec<usize>, String>>()?;
+        for arg in &args[arity..] {
+            params.push(expr::evaluate(arg)?);
+        }
+        if params.len() != num_params {
+            return Err(format!("'{}' takes {} parameters", name, num_params));
+        }
+        GateOp::from_name(name, &qubits, &params)
+    }
+}
+
+/// Reads commands from standard input until `quit` or end of input,
+/// printing the state after every gate.
+pub fn run(num_qubits: usize, runtime: Runtime, terminal: Terminal) {
+    let mut session = Session {
+        circuit: QuantumCircuit::new(num_qubits),
+        runtime,
+        terminal,
+    };
+    println!("psi REPL, {} qubits. Type 'help' for commands.", num_qubits);
+    println!("{}", session.state());
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("psi> ");
+        let _ = io::stdout().flush();
+        let Some(Ok(line)) = lines.next() else {
+            println!();
+            break;
+        };
+        match session.execute(line.trim()) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(err) => println!("error: {}", err),
+        }
+    }
+}
@@ -1,90 +1,170 @@
-use crate::common::{benchmark_circuit, print_section, BenchmarkResult};
-use libpsi_core::QuantumCircuit;
+use crate::common::{
+    benchmark_circuit, format_duration, print_section, states_equal, BenchmarkResult,
+};
+use crate::memory;
+use libpsi_core::library::{self, GateSet};
+use libpsi_core::{QuantumCircuit, QuantumState, Runtime};
 use libpsi_visualizer::HorizontalRenderer;
+use std::time::{Duration, Instant};
 
-pub fn run_all(results: &mut Vec<BenchmarkResult>) {
-    println!("═══════════════════════════════════════════════════════════════");
-    println!("                    BENCHMARK CIRCUITS");
-    println!("═══════════════════════════════════════════════════════════════\n");
+const DEFAULT_QUBITS: [usize; 4] = [8, 10, 12, 14];
+const DEFAULT_DEPTH: usize = 20;
+/// Layers of the fixed benchmark circuits, few enough for the BasicRT
+/// baseline at 14 qubits.
+const FIXED_DEPTH: usize = 3;
 
-    test_8_qubit(results);
-    test_10_qubit(results);
-    test_12_qubit(results);
-    test_14_qubit(results);
+/// Benchmark parameters from the command line. With none of them set the
+/// fixed benchmark circuits run; otherwise a sweep of random circuits over
+/// the requested sizes and runtimes does.
+#[derive(Default)]
+pub struct BenchOptions {
+    pub qubits: Option<Vec<usize>>,
+    pub depth: Option<usize>,
+    pub runtimes: Option<Vec<(String, Runtime)>>,
+    pub repeat: Option<usize>,
 }
 
-pub fn test_8_qubit(results: &mut Vec<BenchmarkResult>) {
-    print_section("8-qubit Entangled Circuit");
+impl BenchOptions {
+    pub fn is_sweep(&self) -> bool {
+        self.qubits.is_some()
+            || self.depth.is_some()
+            || self.runtimes.is_some()
+            || self.repeat.is_some()
+    }
+}
 
-    let builder = || {
-        let mut circuit = QuantumCircuit::new(8);
-        for i in 0..8 {
-            circuit.h(i);
-        }
-        for i in 0..7 {
-            circuit.cnot(i, i + 1);
+/// Parses `N`, `A..B` (inclusive) or a comma-separated list of either.
+pub fn parse_qubits(text: &str) -> Result<Vec<usize>, String> {
+    let mut qubits = Vec::new();
+    for part in text.split(',') {
+        let number = |s: &str| {
+            s.trim()
+                .parse::<usize>()
+                .map_err(|_| format!("invalid qubit count '{}'", s))
+        };
+        match part.split_once("..") {
+            Some((from, to)) => {
+                let (from, to) = (number(from)?, number(to.trim_start_matches('='))?);
+                if from > to {
+                    return Err(format!("empty qubit range '{}'", part));
+                }
+                qubits.extend(from..=to);
+            }
+            None => qubits.push(number(part)?),
         }
-        circuit
-    };
+    }
+    if qubits.contains(&0) {
+        return Err("qubit counts must be at least 1".to_string());
+    }
+    Ok(qubits)
+}
 
-    println!("{}", HorizontalRenderer::new(&builder()));
-    results.push(benchmark_circuit("8-qubit entangled", builder));
+/// Parses a comma-separated list of runtime names; the first one is the
+/// baseline the others are compared against.
+pub fn parse_runtimes(text: &str) -> Result<Vec<(String, Runtime)>, String> {
+    text.split(',')
+        .map(|name| {
+            let name = name.trim();
+            Ok((name.to_string(), name.parse()?))
+        })
+        .collect()
 }
 
-pub fn test_10_qubit(results: &mut Vec<BenchmarkResult>) {
-    print_section("10-qubit Entangled Circuit");
+pub fn run_all(results: &mut Vec<BenchmarkResult>, options: &BenchOptions) {
+    println!("═══════════════════════════════════════════════════════════════");
+    println!("                    BENCHMARK CIRCUITS");
+    println!("═══════════════════════════════════════════════════════════════\n");
 
-    let builder = || {
-        let mut circuit = QuantumCircuit::new(10);
-        for i in 0..10 {
-            circuit.h(i);
-        }
-        for i in 0..9 {
-            circuit.cnot(i, i + 1);
-        }
-        circuit.cz(0, 9);
-        circuit
-    };
+    if options.is_sweep() {
+        run_sweep(results, options);
+        return;
+    }
 
-    println!("{}", HorizontalRenderer::new(&builder()));
-    results.push(benchmark_circuit("10-qubit entangled", builder));
+    for n in DEFAULT_QUBITS {
+        test_random_circuit(results, n);
+    }
 }
 
-pub fn test_12_qubit(results: &mut Vec<BenchmarkResult>) {
-    print_section("12-qubit Entangled Circuit");
+pub fn test_random_circuit(results: &mut Vec<BenchmarkResult>, num_qubits: usize) {
+    print_section(&format!("{}-qubit Random Circuit", num_qubits));
 
     let builder = || {
-        let mut circuit = QuantumCircuit::new(12);
-        for i in 0..12 {
-            circuit.h(i);
-        }
-        for i in 0..11 {
-            circuit.cnot(i, i + 1);
-        }
-        circuit.cz(0, 11);
-        circuit.swap(5, 6);
-        circuit
+        library::random_circuit(
+            num_qubits,
+            FIXED_DEPTH,
+            num_qubits as u64,
+            GateSet::Universal,
+        )
     };
 
     println!("{}", HorizontalRenderer::new(&builder()));
-    results.push(benchmark_circuit("12-qubit entangled", builder));
+    results.push(benchmark_circuit(
+        &format!("{}-qubit random", num_qubits),
+        builder,
+    ));
 }
 
-pub fn test_14_qubit(results: &mut Vec<BenchmarkResult>) {
-    print_section("14-qubit Entangled Circuit");
+/// Mean time of `repeat` runs and the state of the last one.
+fn time_runtime(
+    circuit: &QuantumCircuit,
+    runtime: Runtime,
+    repeat: usize,
+) -> (Duration, QuantumState) {
+    let mut total = Duration::ZERO;
+    let mut state = None;
+    for _ in 0..repeat {
+        let start = Instant::now();
+        let result = runtime.compute(circuit.num_qubits(), circuit.operations());
+        total += start.elapsed();
+        state = Some(result);
+    }
+    (total / repeat as u32, state.expect("repeat is at least 1"))
+}
 
-    let builder = || {
-        let mut circuit = QuantumCircuit::new(14);
-        for i in 0..14 {
-            circuit.h(i);
-        }
-        for i in 0..13 {
-            circuit.cnot(i, i + 1);
-        }
-        circuit
-    };
+fn run_sweep(results: &mut Vec<BenchmarkResult>, options: &BenchOptions) {
+    let qubits = options
+        .qubits
+        .clone()
+        .unwrap_or_else(|| DEFAULT_QUBITS.to_vec());
+    let depth = options.depth.unwrap_or(DEFAULT_DEPTH);
+    let repeat = options.repeat.unwrap_or(1).max(1);
+    let runtimes = options.runtimes.clone().unwrap_or_else(|| {
+        vec![
+            ("basic".to_string(), Runtime::BasicRT),
+            ("basic-mt".to_string(), Runtime::BasicRTMT),
+        ]
+    });
+    let (baseline_name, baseline) = runtimes[0].clone();
 
-    println!("{}", HorizontalRenderer::new(&builder()));
-    results.push(benchmark_circuit("14-qubit entangled", builder));
-}
+    for n in qubits {
+        print_section(&format!("{} qubits, depth {}, {} run(s)", n, depth, repeat));
+        let circuit = library::random_circuit(n, depth, n as u64, GateSet::Universal);
+        let (baseline_time, reference) = time_runtime(&circuit, baseline, repeat);
+        println!(
+            "  {:20} {:>12}",
+            baseline_name,
+            format_duration(baseline_time)
+        );
 
+        for (name, runtime) in &runtimes[1..] {
+            let (time, state) = time_runtime(&circuit, *runtime, repeat);
+            let results_match = states_equal(&reference, &state);
+            println!(
+                "  {:20} {:>12}  {:.2}x {}",
+                name,
+                format_duration(time),
+                baseline_time.as_secs_f64() / time.as_secs_f64(),
+                if results_match { "✓" } else { "✗" }
+            );
+            results.push(BenchmarkResult {
+                name: format!("{}q d{}: {} vs {}", n, depth, name, baseline_name),
+                num_qubits: n,
+                basic_time: baseline_time,
+                mt_time: time,
+                results_match,
+                peak_bytes: memory::take_peak(),
+            });
+        }
+        println!();
+    }
+}
@@ -1,5 +1,16 @@
-use crate::common::{benchmark_circuit, print_circuit, print_section, BenchmarkResult};
-use libpsi_core::QuantumCircuit;
+use crate::common::{
+    benchmark_circuit, print_circuit, print_section, states_equal, BenchmarkResult,
+};
+use crate::memory;
+use libpsi_core::{
+    circuit, complex, fidelity, CircuitDebugger, GateOp, Pauli, QuantumCircuit, QuantumState,
+    Runtime, SnapshotKind, SnapshotValue, StabilizerState, StabilizerTableau, Vector,
+};
+use libpsi_visualizer::{HorizontalRenderer, IdleQubits, Terminal, VerticalRenderer};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::f64::consts::{FRAC_PI_2, PI};
+use std::time::Instant;
 
 pub fn run_all(results: &mut Vec<BenchmarkResult>) {
     println!("═══════════════════════════════════════════════════════════════");
@@ -7,11 +18,23 @@ pub fn run_all(results: &mut Vec<BenchmarkResult>) {
     println!("═══════════════════════════════════════════════════════════════\n");
 
     test_bell_state(results);
+    test_teleportation(results);
+    test_superdense_coding(results);
     test_ghz_state(results);
+    test_w_state(results);
     test_swap_via_cnots(results);
+    test_iswap(results);
     test_toffoli(results);
     test_hadamard_measure(results);
     test_complex_circuit(results);
+    test_sparse_register(results);
+    test_stabilizer_tableau(results);
+    test_graph_state(results);
+    test_stabilizer_state(results);
+    test_circuit_macro(results);
+    test_append_by_name(results);
+    test_append(results);
+    test_classical_conditions(results);
 }
 
 pub fn test_bell_state(results: &mut Vec<BenchmarkResult>) {
@@ -19,43 +42,202 @@ pub fn test_bell_state(results: &mut Vec<BenchmarkResult>) {
 
     let builder = || {
         let mut circuit = QuantumCircuit::with_classical(2, 2);
-        circuit.h(0).cnot(0, 1).measure(0, 0).measure(1, 1);
+        circuit.bell_pair(0, 1).measure(0, 0).measure(1, 1);
         circuit
     };
 
     print_circuit(&builder());
+    let ascii = Terminal::plain().with_unicode(false);
+    println!(
+        "ASCII terminal:\n{}",
+        HorizontalRenderer::new(&builder()).with_terminal(ascii)
+    );
     results.push(benchmark_circuit("Bell State (2 qubits)", builder));
 
     let mut display = builder();
     display.compute();
     println!("{}\n", display);
+
+    let qasm = "OPENQASM 2.0;\ninclude \"qelib1.inc\";\nqreg q[2];\ncreg c[2];\n\
+                h q[0];\ncx q[0], q[1];\nmeasure q -> c;\n";
+    let mut parsed = libpsi_qasm::parse(qasm).expect("Bell state QASM should parse");
+    let matches = states_equal(parsed.state(), display.state());
+    println!(
+        "From QASM: {}",
+        if matches {
+            "✓ matches builder"
+        } else {
+            "✗ differs"
+        }
+    );
+    println!("Counts (seed 42):\n{}\n", display.sample(1000, Some(42)));
+}
+
+pub fn test_teleportation(results: &mut Vec<BenchmarkResult>) {
+    print_section("Quantum Teleportation");
+
+    let messages = [
+        (0.0, 0.0, 0.0),
+        (PI, 0.0, 0.0),
+        (FRAC_PI_2, 0.0, 0.0),
+        (1.1, 0.4, -2.3),
+    ];
+    print_circuit(&QuantumCircuit::teleportation(1.1, 0.4, -2.3));
+
+    let start = Instant::now();
+    let mut teleported = true;
+    for &(theta, phi, lambda) in &messages {
+        let mut circuit = QuantumCircuit::teleportation(theta, phi, lambda);
+        // The measured qubits end up in |+⟩|+⟩, leaving the message on qubit 2.
+        let mut expected = QuantumCircuit::new(3);
+        expected.h(0).h(1).u3(2, theta, phi, lambda);
+        let fidelity = fidelity(circuit.state(), expected.state());
+        println!(
+            "U3({:.2}, {:.2}, {:.2})|0⟩ arrives with fidelity {:.6}",
+            theta, phi, lambda, fidelity
+        );
+        teleported &= fidelity > 1.0 - 1e-9;
+    }
+    let elapsed = start.elapsed();
+
+    let counts = QuantumCircuit::teleportation(1.1, 0.4, -2.3).sample(4000, Some(7));
+    let uniform = counts.len() == 4
+        && counts
+            .iter()
+            .all(|(_, n)| (n as f64 / 4000.0 - 0.25).abs() < 0.03);
+    println!("Bell measurement outcomes (seed 7):\n{}", counts);
+
+    // Sampled shot by shot, the corrections read the measured bits, so a
+    // teleported |1⟩ reads 1 on the target whatever the Bell outcome.
+    let mut one = QuantumCircuit::teleportation(PI, 0.0, 0.0);
+    one.measure(2, 2);
+    let counts = one.sample(1000, Some(7));
+    let corrected = counts.len() == 4 && counts.iter().all(|(bits, _)| bits.ends_with('1'));
+    println!(
+        "Message teleported for every outcome: {}, |1⟩ read on bob in every shot: {}\n",
+        if teleported && uniform { "✓" } else { "✗" },
+        if corrected { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: "Teleportation (3 qubits)".to_string(),
+        num_qubits: 3,
+        basic_time: elapsed,
+        mt_time: elapsed,
+        results_match: teleported && uniform && corrected,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+pub fn test_superdense_coding(results: &mut Vec<BenchmarkResult>) {
+    print_section("Superdense Coding");
+
+    print_circuit(&QuantumCircuit::superdense_coding((true, true)));
+
+    let start = Instant::now();
+    let mut decoded = true;
+    for message in [(false, false), (false, true), (true, false), (true, true)] {
+        let sent = format!("{}{}", message.0 as u8, message.1 as u8);
+        let counts = QuantumCircuit::superdense_coding(message).sample(256, Some(3));
+        let received = counts
+            .most_frequent()
+            .map(|(outcome, _)| outcome.to_string());
+        println!(
+            "Sent {} → received {}",
+            sent,
+            received.as_deref().unwrap_or("-")
+        );
+        decoded &= counts.get(&sent) == 256;
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "Every message decoded on every shot: {}\n",
+        if decoded { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: "Superdense coding (2 qubits)".to_string(),
+        num_qubits: 2,
+        basic_time: elapsed,
+        mt_time: elapsed,
+        results_match: decoded,
+        peak_bytes: memory::take_peak(),
+    });
 }
 
 pub fn test_ghz_state(results: &mut Vec<BenchmarkResult>) {
     print_section("GHZ State");
 
-    let builder = || {
-        let mut circuit = QuantumCircuit::new(3);
-        circuit.h(0).cnot(0, 1).cnot(0, 2);
-        circuit
-    };
-
-    print_circuit(&builder());
-    results.push(benchmark_circuit("GHZ State (3 qubits)", builder));
+    let builder = || QuantumCircuit::ghz(3);
 
     let mut display = builder();
     display.compute();
-    println!("{}\n", display);
+    println!(
+        "Horizontal:\n{}",
+        HorizontalRenderer::new(&display).with_state_summary(1e-6)
+    );
+    println!(
+        "Vertical:\n{}",
+        VerticalRenderer::new(&display).with_state_summary(1e-6)
+    );
+    results.push(benchmark_circuit("GHZ State (3 qubits)", builder));
+
+    let mut snapshotted = QuantumCircuit::new(3);
+    snapshotted
+        .h(0)
+        .snapshot("superposition", SnapshotKind::Probabilities)
+        .cnot(0, 1)
+        .cnot(0, 2)
+        .snapshot(
+            "ZZ",
+            SnapshotKind::Expectation(vec![(0, Pauli::Z), (1, Pauli::Z)]),
+        )
+        .snapshot(
+            "XXX",
+            SnapshotKind::Expectation(vec![(0, Pauli::X), (1, Pauli::X), (2, Pauli::X)]),
+        );
+    print_circuit(&snapshotted);
+    snapshotted.compute_with_config(Runtime::optimal());
+    for label in ["superposition", "ZZ", "XXX"] {
+        if let Some(value) = snapshotted.snapshot_value(label) {
+            print!("Snapshot \"{}\":\n{}", label, value);
+        }
+    }
+    println!();
+}
+
+pub fn test_w_state(results: &mut Vec<BenchmarkResult>) {
+    print_section("W State");
+
+    let builder = || QuantumCircuit::w_state(4);
+    print_circuit(&builder());
+    results.push(benchmark_circuit("W State (4 qubits)", builder));
+
+    let mut matches = true;
+    for n in 1..=6 {
+        let mut circuit = QuantumCircuit::w_state(n);
+        let state = circuit.state();
+        let amplitude = 1.0 / (n as f64).sqrt();
+        let expected = QuantumState::new(
+            (0..1usize << n)
+                .map(|i| {
+                    let weight = if i.count_ones() == 1 { amplitude } else { 0.0 };
+                    complex!(weight, 0.0)
+                })
+                .collect(),
+        );
+        matches &= fidelity(state, &expected) > 1.0 - 1e-9;
+    }
+    println!(
+        "W states on 1 to 6 qubits match (|0…01⟩ + … + |10…0⟩)/√n: {}\n",
+        if matches { "✓" } else { "✗" }
+    );
 }
 
 pub fn test_swap_via_cnots(results: &mut Vec<BenchmarkResult>) {
     print_section("SWAP via 3 CNOTs");
 
-    let builder = || {
-        let mut circuit = QuantumCircuit::new(2);
-        circuit.x(0).cnot(0, 1).cnot(1, 0).cnot(0, 1);
-        circuit
-    };
+    let builder = || circuit!(2; x 0; cnot 0, 1; cnot 1, 0; cnot 0, 1);
 
     print_circuit(&builder());
     results.push(benchmark_circuit("SWAP via CNOTs (2 qubits)", builder));
@@ -65,14 +247,65 @@ pub fn test_swap_via_cnots(results: &mut Vec<BenchmarkResult>) {
     println!("{}\n", display);
 }
 
-pub fn test_toffoli(results: &mut Vec<BenchmarkResult>) {
-    print_section("Toffoli Gate");
+pub fn test_iswap(results: &mut Vec<BenchmarkResult>) {
+    print_section("iSWAP and √SWAP");
 
-    let builder = || {
-        let mut circuit = QuantumCircuit::new(3);
-        circuit.x(0).x(1).toffoli(0, 1, 2);
+    let builder = || circuit!(3; x 0; h 1; iswap 0, 1; sqrt_swap 1, 2; iswap 2, 0; sqrt_swap 0, 1);
+
+    print_circuit(&builder());
+    let mut result = benchmark_circuit("iSWAP and √SWAP (3 qubits)", builder);
+
+    // From a state with every amplitude nonzero.
+    let prepared = || {
+        let mut circuit = QuantumCircuit::new(2);
+        circuit.ry(0, 0.7).ry(1, 1.9).rz(0, 0.4).cnot(1, 0);
         circuit
     };
+    let (mut iswap, mut decomposed) = (prepared(), prepared());
+    iswap.iswap(0, 1);
+    decomposed.s(0).s(1).cz(0, 1).swap(0, 1);
+    let iswap_ok = states_equal(iswap.compute(), decomposed.compute());
+    println!(
+        "iSWAP = SWAP·CZ·(S⊗S): {}",
+        if iswap_ok { "✓" } else { "✗" }
+    );
+
+    let (mut halves, mut swapped) = (prepared(), prepared());
+    halves.sqrt_swap(1, 0).sqrt_swap(0, 1);
+    swapped.swap(0, 1);
+    let halves_ok = states_equal(halves.compute(), swapped.compute());
+    println!("√SWAP·√SWAP = SWAP: {}", if halves_ok { "✓" } else { "✗" });
+
+    let mut undone = builder();
+    undone.append(&builder().inverse(), &[0, 1, 2]);
+    let inverse_ok = states_equal(undone.compute(), QuantumCircuit::new(3).compute());
+    println!(
+        "Followed by its inverse, the circuit is the identity: {}",
+        if inverse_ok { "✓" } else { "✗" }
+    );
+
+    // iSWAP and its inverse are Clifford; √SWAP is not.
+    let clifford = circuit!(4; h 0; cnot 0, 1; iswap 1, 2; s 3; iswap 3, 0; h 2);
+    let mut inverted = clifford.clone();
+    inverted.append(&circuit!(4; iswap 1, 3).inverse(), &[0, 1, 2, 3]);
+    let stabilizer_ok = [clifford, inverted].iter().all(|circuit| {
+        let mut reference = circuit.clone();
+        let stabilizer = Runtime::Stabilizer.compute(4, circuit.operations());
+        (fidelity(reference.compute_with(Runtime::BasicRT), &stabilizer) - 1.0).abs() < 1e-9
+    }) && StabilizerState::from_circuit(&circuit!(2; sqrt_swap 0, 1)).is_err();
+    println!(
+        "Stabilizer runs iSWAP and iSWAP†, and rejects √SWAP: {}\n",
+        if stabilizer_ok { "✓" } else { "✗" }
+    );
+
+    result.results_match &= iswap_ok && halves_ok && inverse_ok && stabilizer_ok;
+    results.push(result);
+}
+
+pub fn test_toffoli(results: &mut Vec<BenchmarkResult>) {
+    print_section("Toffoli Gate");
+
+    let builder = || circuit!(3; x 0; x 1; toffoli 0, 1, 2);
 
     print_circuit(&builder());
     results.push(benchmark_circuit("Toffoli (3 qubits)", builder));
@@ -85,11 +318,7 @@ pub fn test_toffoli(results: &mut Vec<BenchmarkResult>) {
 pub fn test_hadamard_measure(results: &mut Vec<BenchmarkResult>) {
     print_section("Full Circuit with Measurements");
 
-    let builder = || {
-        let mut circuit = QuantumCircuit::with_classical(3, 3);
-        circuit.h(0).h(1).h(2).measure_all();
-        circuit
-    };
+    let builder = || circuit!(3, 3; h 0; h 1; h 2; measure_all);
 
     print_circuit(&builder());
     results.push(benchmark_circuit("3-qubit Hadamard + Measure", builder));
@@ -105,14 +334,20 @@ pub fn test_complex_circuit(results: &mut Vec<BenchmarkResult>) {
     let builder = || {
         let mut circuit = QuantumCircuit::with_classical(4, 2);
         circuit
+            .section("prep")
             .h(0)
             .h(1)
+            .section("entangle")
             .cnot(0, 2)
             .cnot(1, 3)
             .cz(2, 3)
             .swap(0, 1)
+            .section("readout")
             .measure(0, 0)
-            .measure(1, 1);
+            .measure(1, 1)
+            .label_qubits("data", &[0, 1])
+            .label_qubits("anc", &[2, 3])
+            .label_classical_bits("out", &[0, 1]);
         circuit
     };
 
@@ -122,5 +357,656 @@ pub fn test_complex_circuit(results: &mut Vec<BenchmarkResult>) {
     let mut display = builder();
     display.compute();
     println!("{}\n", display);
+
+    let circuit = builder();
+    println!(
+        "Indexed:\n{}",
+        HorizontalRenderer::new(&circuit).with_column_numbers()
+    );
+    println!("{}", VerticalRenderer::new(&circuit).with_op_indices());
+
+    let mut debugger = CircuitDebugger::new(&circuit);
+    debugger.run_to_label("entangle");
+    println!("Debugger at \"entangle\":\n{}", debugger);
+    debugger.add_label_breakpoint("readout");
+    debugger.run();
+    println!("Debugger at \"readout\":\n{}", debugger);
+    debugger.run();
+    let match_result = states_equal(&debugger.state(), display.state());
+    println!(
+        "Stepped state vs runtime: {}\n",
+        if match_result {
+            "✓ Match"
+        } else {
+            "✗ MISMATCH"
+        }
+    );
+}
+
+pub fn test_sparse_register(results: &mut Vec<BenchmarkResult>) {
+    print_section("Sparse Register (idle qubits collapsed)");
+
+    let builder = || {
+        let mut circuit = QuantumCircuit::new(8);
+        circuit.h(0).cnot(0, 3).cnot(3, 7);
+        circuit
+    };
+
+    let circuit = builder();
+    println!(
+        "Horizontal:\n{}",
+        HorizontalRenderer::new(&circuit).with_idle_qubits(IdleQubits::Collapse)
+    );
+    println!(
+        "Vertical:\n{}",
+        VerticalRenderer::new(&circuit).with_idle_qubits(IdleQubits::Hide)
+    );
+    results.push(benchmark_circuit("Sparse GHZ (8 qubits)", builder));
+
+    let mut display = builder();
+    display.compute();
+    println!("{}\n", display);
+}
+
+pub(crate) fn random_clifford(
+    num_qubits: usize,
+    num_gates: usize,
+    rng: &mut StdRng,
+) -> QuantumCircuit {
+    let mut circuit = QuantumCircuit::new(num_qubits);
+    for _ in 0..num_gates {
+        let a = rng.random_range(0..num_qubits);
+        let b = (a + rng.random_range(1..num_qubits)) % num_qubits;
+        let quarters = rng.random_range(0..4) as f64 * FRAC_PI_2;
+        circuit.push(match rng.random_range(0..12) {
+            0 => GateOp::H(a),
+            1 => GateOp::S(a),
+            2 => GateOp::Sdg(a),
+            3 => GateOp::X(a),
+            4 => GateOp::Y(a),
+            5 => GateOp::Z(a),
+            6 => GateOp::Sx(a),
+            7 => GateOp::CNOT(a, b),
+            8 => GateOp::CZ(a, b),
+            9 => GateOp::SWAP(a, b),
+            10 => GateOp::Rx(a, quarters),
+            _ => GateOp::Ry(a, quarters),
+        });
+    }
+    circuit
+}
+
+/// |⟨a|b⟩|, which is 1 when the states agree up to global phase.
+/// Runs `circuit` after a fixed generic (non-stabiliser) preparation, so
+/// that comparing outputs checks more than the first column.
+fn on_generic_input(circuit: &QuantumCircuit) -> QuantumState {
+    let n = circuit.num_qubits();
+    let mut run = QuantumCircuit::new(n);
+    for q in 0..n {
+        run.ry(q, 0.3 + 0.4 * q as f64).rz(q, 0.7 * q as f64 + 0.1);
+    }
+    for q in 0..n - 1 {
+        run.cnot(q, q + 1);
+    }
+    for op in circuit.operations() {
+        run.push(op.clone());
+    }
+    run.compute_with(Runtime::BasicRT).clone()
 }
 
+pub fn test_stabilizer_tableau(results: &mut Vec<BenchmarkResult>) {
+    print_section("Stabilizer Tableau");
+
+    let mut bell = QuantumCircuit::new(2);
+    bell.h(0).cnot(0, 1);
+    let tableau = StabilizerTableau::from_circuit(&bell).unwrap();
+    println!("Bell circuit tableau:\n{}", tableau);
+
+    let mut rng = StdRng::seed_from_u64(3481);
+    let (mut synthesis_ok, mut compose_ok, mut inverse_ok) = (true, true, true);
+    let mut synthesis_time = std::time::Duration::ZERO;
+    let mut state_time = std::time::Duration::ZERO;
+    for _ in 0..50 {
+        let first = random_clifford(5, 40, &mut rng);
+        let second = random_clifford(5, 40, &mut rng);
+
+        let start = Instant::now();
+        let a = StabilizerTableau::from_circuit(&first).unwrap();
+        let b = StabilizerTableau::from_circuit(&second).unwrap();
+        let synthesised = a.to_circuit();
+        synthesis_ok &= StabilizerTableau::from_circuit(&synthesised).as_ref() == Ok(&a);
+        synthesis_time += start.elapsed();
+
+        let start = Instant::now();
+        let fidelity = fidelity(&on_generic_input(&first), &on_generic_input(&synthesised));
+        state_time += start.elapsed();
+        synthesis_ok &= (fidelity - 1.0).abs() < 1e-9;
+        synthesis_ok &= synthesised.operations().iter().all(|op| {
+            matches!(
+                op,
+                GateOp::H(_)
+                    | GateOp::S(_)
+                    | GateOp::Sdg(_)
+                    | GateOp::CNOT(_, _)
+                    | GateOp::X(_)
+                    | GateOp::Z(_)
+            )
+        });
+
+        let mut both = first.clone();
+        for op in second.operations() {
+            both.push(op.clone());
+        }
+        compose_ok &= StabilizerTableau::from_circuit(&both).as_ref() == Ok(&a.compose(&b));
+        inverse_ok &= a.compose(&a.inverse()) == StabilizerTableau::new(5);
+    }
+    println!(
+        "Synthesis round trip (50 random 5-qubit circuits): {}",
+        if synthesis_ok { "✓" } else { "✗" }
+    );
+    println!(
+        "Compose matches concatenation: {}",
+        if compose_ok { "✓" } else { "✗" }
+    );
+    println!(
+        "Tableau times inverse is identity: {}",
+        if inverse_ok { "✓" } else { "✗" }
+    );
+
+    let mut cz = QuantumCircuit::new(2);
+    cz.cz(0, 1);
+    let mut h_cnot_h = QuantumCircuit::new(2);
+    h_cnot_h.h(1).cnot(0, 1).h(1);
+    let equivalent =
+        StabilizerTableau::from_circuit(&cz) == StabilizerTableau::from_circuit(&h_cnot_h);
+    println!("CZ ≡ H·CNOT·H: {}", if equivalent { "✓" } else { "✗" });
+
+    let mut t = QuantumCircuit::new(1);
+    t.t(0);
+    let rejected = StabilizerTableau::from_circuit(&t).is_err();
+    println!("T rejected: {}\n", if rejected { "✓" } else { "✗" });
+
+    results.push(BenchmarkResult {
+        name: "Stabilizer tableau (5q x50)".to_string(),
+        num_qubits: 5,
+        basic_time: state_time,
+        mt_time: synthesis_time,
+        results_match: synthesis_ok && compose_ok && inverse_ok && equivalent && rejected,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+pub fn test_graph_state(results: &mut Vec<BenchmarkResult>) {
+    print_section("Graph State");
+
+    // A 5-ring with one chord.
+    let edges = [(0, 1), (1, 2), (2, 3), (3, 4), (4, 0), (0, 2)];
+    let mut circuit = QuantumCircuit::graph_state(5, &edges);
+    print_circuit(&circuit);
+
+    let direct = StabilizerTableau::graph_state(5, &edges);
+    let matches_circuit = StabilizerTableau::from_circuit(&circuit).as_ref() == Ok(&direct);
+    println!("Stabilisers:");
+    for q in 0..5 {
+        println!("  K{} = {}", q, direct.stabiliser(q));
+    }
+    println!(
+        "Tableau matches circuit: {}",
+        if matches_circuit { "✓" } else { "✗" }
+    );
+
+    // Every stabiliser X_a Z_N(a) has expectation +1 on the state.
+    for a in 0..5 {
+        let mut factors = vec![(a, Pauli::X)];
+        for &(u, v) in &edges {
+            if u == a {
+                factors.push((v, Pauli::Z));
+            } else if v == a {
+                factors.push((u, Pauli::Z));
+            }
+        }
+        circuit.snapshot(&format!("K{}", a), SnapshotKind::Expectation(factors));
+    }
+    let start = Instant::now();
+    circuit.compute_with(Runtime::BasicRT);
+    let elapsed = start.elapsed();
+    let stabilised = (0..5).all(|a| {
+        circuit.snapshot_value(&format!("K{}", a)).is_some_and(
+            |value| matches!(value, SnapshotValue::Expectation(e) if (e - 1.0).abs() < 1e-9),
+        )
+    });
+    println!(
+        "⟨K_a⟩ = 1 for every qubit: {}\n",
+        if stabilised { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: "Graph state (5 qubits)".to_string(),
+        num_qubits: 5,
+        basic_time: elapsed,
+        mt_time: elapsed,
+        results_match: matches_circuit && stabilised,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+pub fn test_stabilizer_state(results: &mut Vec<BenchmarkResult>) {
+    print_section("Stabilizer State (CHP)");
+
+    let mut rng = StdRng::seed_from_u64(3506);
+    let n = 6;
+    let (mut states_ok, mut probabilities_ok) = (true, true);
+    for _ in 0..30 {
+        let circuit = random_clifford(n, 60, &mut rng);
+        let mut reference = circuit.clone();
+        let reference = reference.compute_with(Runtime::BasicRT).clone();
+        let state = StabilizerState::from_circuit(&circuit).unwrap();
+        states_ok &= (fidelity(&reference, &state.to_state()) - 1.0).abs() < 1e-9;
+        states_ok &= (fidelity(
+            &reference,
+            &Runtime::Stabilizer.compute(n, circuit.operations()),
+        ) - 1.0)
+            .abs()
+            < 1e-9;
+        probabilities_ok &= (0..1usize << n).all(|index| {
+            let bits: Vec<bool> = (0..n).map(|q| (index >> (n - 1 - q)) & 1 == 1).collect();
+            (state.probability(&bits) - reference.get(index).norm2()).abs() < 1e-9
+        });
+    }
+    println!(
+        "State vector and probabilities match BasicRT on 30 random circuits: {}",
+        if states_ok && probabilities_ok {
+            "✓"
+        } else {
+            "✗"
+        }
+    );
+
+    let mut bell = QuantumCircuit::new(2);
+    bell.h(0).cnot(0, 1);
+    let mut state = StabilizerState::from_circuit(&bell).unwrap();
+    let undetermined = state.certain_outcome(1).is_none();
+    let outcome = state.measure(0, &mut rng);
+    let collapsed = undetermined && state.certain_outcome(1) == Some(outcome);
+    println!(
+        "Measuring one half of a Bell pair fixes the other: {}",
+        if collapsed { "✓" } else { "✗" }
+    );
+
+    let width = 300;
+    let mut ghz = QuantumCircuit::with_classical(width, 2);
+    ghz.h(0);
+    for q in 0..width - 1 {
+        ghz.cnot(q, q + 1).s(q);
+    }
+    ghz.measure(0, 0).measure(width - 1, 1);
+    let start = Instant::now();
+    let counts = StabilizerState::sample(&ghz, 1000, Some(7)).unwrap();
+    let elapsed = start.elapsed();
+    let correlated = counts.get("00") + counts.get("11") == 1000 && counts.get("00") > 400;
+    println!(
+        "{}-qubit GHZ, 1000 shots in {:.2?}: {} × 00, {} × 11 {}",
+        width,
+        elapsed,
+        counts.get("00"),
+        counts.get("11"),
+        if correlated { "✓" } else { "✗" }
+    );
+
+    let mut t = QuantumCircuit::new(2);
+    t.h(0).t(0);
+    let rejected = StabilizerState::from_circuit(&t).is_err();
+    println!("T rejected: {}\n", if rejected { "✓" } else { "✗" });
+
+    results.push(BenchmarkResult {
+        name: format!("Stabilizer state ({}q GHZ)", width),
+        num_qubits: width,
+        basic_time: elapsed,
+        mt_time: elapsed,
+        results_match: states_ok && probabilities_ok && collapsed && correlated && rejected,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+pub fn test_circuit_macro(results: &mut Vec<BenchmarkResult>) {
+    print_section("circuit! Macro");
+
+    let n = 3;
+    let from_macro = circuit!(n, 2;
+        h 0;
+        cnot 0, 1;
+        rz 2, PI / 4.0;
+        u3 (n - 1), 0.1, 0.2, 0.3;
+        barrier 0, 1, 2;
+        section "readout";
+        measure 0 -> 0;
+        measure (n - 1) -> 1;
+    );
+    let mut expected = QuantumCircuit::with_classical(3, 2);
+    expected
+        .h(0)
+        .cnot(0, 1)
+        .rz(2, PI / 4.0)
+        .u3(2, 0.1, 0.2, 0.3)
+        .barrier(&[0, 1, 2])
+        .section("readout")
+        .measure(0, 0)
+        .measure(2, 1);
+    print_circuit(&from_macro);
+
+    let same = format!("{:?}", from_macro.operations()) == format!("{:?}", expected.operations())
+        && from_macro.num_classical() == expected.num_classical();
+    println!(
+        "Macro expands to the same builder calls: {}\n",
+        if same { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: "circuit! macro".to_string(),
+        num_qubits: 3,
+        basic_time: std::time::Duration::from_micros(0),
+        mt_time: std::time::Duration::from_micros(0),
+        results_match: same,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+pub fn test_append_by_name(results: &mut Vec<BenchmarkResult>) {
+    print_section("Appending Gates by Name");
+
+    let gates: [(&str, &[usize], &[f64]); 5] = [
+        ("h", &[0], &[]),
+        ("cx", &[0, 1], &[]),
+        ("crz", &[1, 2], &[FRAC_PI_2]),
+        ("u3", &[2], &[0.1, 0.2, 0.3]),
+        ("toffoli", &[0, 1, 2], &[]),
+    ];
+    let mut by_name = QuantumCircuit::new(3);
+    for (name, qubits, params) in gates {
+        by_name.append_by_name(name, qubits, params).unwrap();
+    }
+    let mut expected = QuantumCircuit::new(3);
+    expected
+        .h(0)
+        .cnot(0, 1)
+        .crz(1, 2, FRAC_PI_2)
+        .u3(2, 0.1, 0.2, 0.3)
+        .toffoli(0, 1, 2);
+    let same = format!("{:?}", by_name.operations()) == format!("{:?}", expected.operations());
+    println!("{:?}", by_name.operations());
+
+    let rejected: [(&str, &[usize], &[f64]); 4] = [
+        ("foo", &[0], &[]),
+        ("crz", &[0, 1], &[]),
+        ("cx", &[0, 3], &[]),
+        ("cswap", &[0, 1, 1], &[]),
+    ];
+    let mut all_rejected = true;
+    for (name, qubits, params) in rejected {
+        match by_name.append_by_name(name, qubits, params) {
+            Ok(_) => all_rejected = false,
+            Err(err) => println!("  {} {:?} {:?}: {}", name, qubits, params, err),
+        }
+    }
+    let unchanged = by_name.operations().len() == gates.len();
+    println!(
+        "Named gates match the builder and bad requests are rejected: {}\n",
+        if same && all_rejected && unchanged {
+            "✓"
+        } else {
+            "✗"
+        }
+    );
+
+    results.push(BenchmarkResult {
+        name: "append_by_name".to_string(),
+        num_qubits: 3,
+        basic_time: std::time::Duration::from_micros(0),
+        mt_time: std::time::Duration::from_micros(0),
+        results_match: same && all_rejected && unchanged,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+pub fn test_append(results: &mut Vec<BenchmarkResult>) {
+    print_section("Appending Subcircuits");
+
+    let mut oracle = QuantumCircuit::new(3);
+    oracle
+        .x(2)
+        .h(2)
+        .ccnot(0, 1, 2)
+        .h(2)
+        .x(2)
+        .rz(0, FRAC_PI_2)
+        .measure(2, 1);
+    let mut ghz = QuantumCircuit::ghz(2);
+    ghz.snapshot(
+        "zz",
+        SnapshotKind::Expectation(vec![(0, Pauli::Z), (1, Pauli::Z)]),
+    );
+
+    let mut stitched = QuantumCircuit::new(5);
+    stitched.h(0).h(3);
+    stitched.append(&oracle, &[3, 0, 4]).append(&ghz, &[1, 2]);
+    print_circuit(&stitched);
+
+    let mut by_hand = QuantumCircuit::new(5);
+    by_hand
+        .h(0)
+        .h(3)
+        .x(4)
+        .h(4)
+        .ccnot(3, 0, 4)
+        .h(4)
+        .x(4)
+        .rz(3, FRAC_PI_2)
+        .measure(4, 1)
+        .h(1)
+        .cnot(1, 2)
+        .snapshot(
+            "zz",
+            SnapshotKind::Expectation(vec![(1, Pauli::Z), (2, Pauli::Z)]),
+        );
+    let same_ops = format!("{:?}", stitched.operations()) == format!("{:?}", by_hand.operations())
+        && stitched.num_classical() == 2;
+    let same_state = states_equal(stitched.state(), by_hand.state());
+
+    let mut identity = QuantumCircuit::new(3);
+    identity.append(&oracle, &[0, 1, 2]);
+    let same_as_original = states_equal(identity.state(), oracle.clone().state());
+    println!(
+        "Remapped subcircuits match the hand-written circuit: {}, identity map reproduces it: {}\n",
+        if same_ops && same_state { "✓" } else { "✗" },
+        if same_as_original { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: "append".to_string(),
+        num_qubits: 5,
+        basic_time: std::time::Duration::from_micros(0),
+        mt_time: std::time::Duration::from_micros(0),
+        results_match: same_ops && same_state && same_as_original,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+/// Teleportation and bit-flip correction with their corrections
+/// conditioned on measured bits, and active reset, which reuses the
+/// measured qubit and so runs shot by shot.
+pub fn test_classical_conditions(results: &mut Vec<BenchmarkResult>) {
+    print_section("Classically Conditioned Gates");
+
+    let teleport = |message: GateOp| {
+        let mut circuit = QuantumCircuit::with_classical(3, 2);
+        circuit
+            .push(message)
+            .bell_pair(1, 2)
+            .cnot(0, 1)
+            .h(0)
+            .measure(0, 0)
+            .measure(1, 1);
+        circuit.x(2).c_if(1, 1).z(2).c_if(0, 1);
+        circuit
+    };
+    print_circuit(&teleport(GateOp::U3(0, 1.1, 0.4, -2.3)));
+    println!("{}", teleport(GateOp::U3(0, 1.1, 0.4, -2.3)));
+
+    let start = Instant::now();
+    let mut teleported = true;
+    for &(theta, phi, lambda) in &[(0.0, 0.0, 0.0), (PI, 0.0, 0.0), (1.1, 0.4, -2.3)] {
+        let mut expected = QuantumCircuit::new(3);
+        expected.h(0).h(1).u3(2, theta, phi, lambda);
+        for runtime in [Runtime::BasicRT, Runtime::StructureAwareMT] {
+            let mut circuit = teleport(GateOp::U3(0, theta, phi, lambda));
+            teleported &= fidelity(circuit.state_with(runtime), expected.state()) > 1.0 - 1e-9;
+        }
+    }
+    let mut one = teleport(GateOp::X(0));
+    let mut expected = QuantumCircuit::new(3);
+    expected.h(0).h(1).x(2);
+    teleported &= fidelity(one.state_with(Runtime::Stabilizer), expected.state()) > 1.0 - 1e-9;
+    let elapsed = start.elapsed();
+    println!(
+        "Teleported with X^c1 Z^c0 corrections on BasicRT, StructureAwareMT and Stabilizer: {}",
+        if teleported { "✓" } else { "✗" }
+    );
+
+    // Three-qubit bit-flip code: ancillas 3 and 4 hold the parities q0⊕q1
+    // and q1⊕q2, ancilla 5 their AND, so every correction is an XOR of
+    // single measured bits.
+    let mut corrected = true;
+    for error in [None, Some(0), Some(1), Some(2)] {
+        let mut code = QuantumCircuit::with_classical(6, 3);
+        code.ry(0, 0.7).cnot(0, 1).cnot(0, 2);
+        if let Some(q) = error {
+            code.x(q);
+        }
+        code.cnot(0, 3)
+            .cnot(1, 3)
+            .cnot(1, 4)
+            .cnot(2, 4)
+            .ccnot(3, 4, 5);
+        code.measure(3, 0).measure(4, 1).measure(5, 2);
+        code.x(0).c_if(0, 1).x(0).c_if(2, 1);
+        code.x(1).c_if(2, 1);
+        code.x(2).c_if(1, 1).x(2).c_if(2, 1);
+
+        let mut expected = QuantumCircuit::new(6);
+        expected.ry(0, 0.7).cnot(0, 1).cnot(0, 2);
+        let syndrome = match error {
+            None => [false, false, false],
+            Some(0) => [true, false, false],
+            Some(1) => [true, true, true],
+            _ => [false, true, false],
+        };
+        for (i, &bit) in syndrome.iter().enumerate() {
+            if bit {
+                expected.x(3 + i);
+            }
+        }
+        let fidelity = fidelity(code.state(), expected.state());
+        println!(
+            "Bit flip on {:?} corrected with fidelity {:.6}",
+            error, fidelity
+        );
+        corrected &= fidelity > 1.0 - 1e-9;
+    }
+
+    // A bit never measured reads 0, and a condition on 0 fires on |0⟩.
+    let mut unset = QuantumCircuit::with_classical(2, 2);
+    unset.x(0).c_if(1, 1).x(1).c_if(1, 0);
+    let mut flipped = QuantumCircuit::new(2);
+    flipped.x(1);
+    let unset_ok = fidelity(unset.state(), flipped.state()) > 1.0 - 1e-9;
+
+    let mut anti = QuantumCircuit::with_classical(2, 2);
+    anti.h(0).measure(0, 0).x(1).c_if(0, 0).measure(1, 1);
+    let counts = anti.sample(1000, Some(3513));
+    let anticorrelated = counts.get("01") + counts.get("10") == 1000 && counts.get("01") > 400;
+    println!(
+        "Unmeasured bits read 0 and c_if(c, 0) fires on 0 ({} × 01, {} × 10): {}\n",
+        counts.get("01"),
+        counts.get("10"),
+        if unset_ok && anticorrelated {
+            "✓"
+        } else {
+            "✗"
+        }
+    );
+
+    // Active reset: the X acts on the qubit it is conditioned on, so every
+    // shot collapses at the measurement and the qubit always ends in |0⟩.
+    let mut reset = QuantumCircuit::with_classical(2, 3);
+    reset.h(0).cnot(0, 1).measure(0, 0).x(0).c_if(0, 1);
+    reset.measure(0, 1).measure(1, 2);
+    let counts = reset.sample(1000, Some(3513));
+    let stabilizer = StabilizerState::sample(&reset, 1000, Some(3513)).unwrap();
+    let reset_ok = [&counts, &stabilizer]
+        .iter()
+        .all(|counts| counts.get("000") + counts.get("101") == 1000 && counts.get("101") > 400);
+    let mut zero = QuantumCircuit::new(2);
+    let mut ready = QuantumCircuit::with_classical(2, 1);
+    ready.h(0).measure(0, 0).x(0).c_if(0, 1);
+    let reset_state = fidelity(ready.state(), zero.state()) > 1.0 - 1e-9;
+
+    // Reading a qubit again after acting on it sees the new value.
+    let mut reuse = circuit!(1, 2; x 0; measure 0 -> 0; x 0; measure 0 -> 1);
+    let reused = reuse.sample(100, Some(3513)).get("10") == 100;
+    println!(
+        "Active reset ({} × 000, {} × 101; tableau {} × 101), |0⟩ after reset: {}, measured qubit reused: {}\n",
+        counts.get("000"),
+        counts.get("101"),
+        stabilizer.get("101"),
+        if reset_ok && reset_state { "✓" } else { "✗" },
+        if reused { "✓" } else { "✗" }
+    );
+
+    // A reset runs the circuit shot by shot; snapshots are still taken
+    // along that shot, traced or not.
+    let mut snapshots_ok = true;
+    let expect = |circuit: &mut QuantumCircuit, label: &str, expected: [f64; 4]| match circuit
+        .snapshot_value(label)
+    {
+        Some(SnapshotValue::Probabilities(p)) => {
+            p.iter().zip(expected).all(|(a, b)| (a - b).abs() < 1e-9)
+        }
+        _ => false,
+    };
+    for traced in [false, true] {
+        let mut circuit = QuantumCircuit::new(2);
+        circuit
+            .h(0)
+            .x(1)
+            .snapshot("before", SnapshotKind::Probabilities)
+            .reset_qubit(1)
+            .snapshot("after", SnapshotKind::Probabilities);
+        if traced {
+            circuit.compute_with_config(Runtime::optimal().traced(1e-6));
+        } else {
+            circuit.compute_with(Runtime::BasicRT);
+        }
+        snapshots_ok &= expect(&mut circuit, "before", [0.0, 0.5, 0.0, 0.5])
+            && expect(&mut circuit, "after", [0.5, 0.0, 0.5, 0.0]);
+    }
+    println!(
+        "Snapshots around a reset, traced and untraced: {}\n",
+        if snapshots_ok { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: "Classically conditioned gates".to_string(),
+        num_qubits: 6,
+        basic_time: elapsed,
+        mt_time: elapsed,
+        results_match: teleported
+            && corrected
+            && unset_ok
+            && anticorrelated
+            && reset_ok
+            && reset_state
+            && reused
+            && snapshots_ok,
+        peak_bytes: memory::take_peak(),
+    });
+}
@@ -1,12 +1,18 @@
-use libpsi_core::{QuantumCircuit, QuantumState, Runtime, Vector};
+use crate::memory;
+use libpsi_core::{QuantumCircuit, QuantumState, Runtime, SimdCapability, Vector};
 use libpsi_visualizer::{HorizontalRenderer, VerticalRenderer};
 use std::time::{Duration, Instant};
 
+pub type CircuitBuilder = Box<dyn Fn() -> QuantumCircuit>;
+
 pub struct BenchmarkResult {
     pub name: String,
+    pub num_qubits: usize,
     pub basic_time: Duration,
     pub mt_time: Duration,
     pub results_match: bool,
+    /// Peak heap bytes while producing the result, with `alloc-stats`.
+    pub peak_bytes: Option<usize>,
 }
 
 pub fn benchmark_circuit<F>(name: &str, circuit_builder: F) -> BenchmarkResult
@@ -31,9 +37,11 @@ where
 
     BenchmarkResult {
         name: name.to_string(),
+        num_qubits: circuit_st.num_qubits(),
         basic_time,
         mt_time,
         results_match,
+        peak_bytes: memory::take_peak(),
     }
 }
 
@@ -81,113 +89,164 @@ pub fn print_benchmark_table(results: &[BenchmarkResult]) {
         return;
     }
 
-    let headers = ["Circuit", "BasicRT", "BasicRTMT", "Speedup", "Match"];
+    let with_memory = results.iter().any(|r| r.peak_bytes.is_some());
+    let mut headers = vec!["Circuit", "Baseline", "Compared", "Speedup", "Match"];
+    if with_memory {
+        headers.push("Peak heap");
+    }
 
-    let formatted: Vec<(String, String, String, String, String)> = results
+    let rows: Vec<Vec<String>> = results
         .iter()
         .map(|r| {
-            let speedup = r.basic_time.as_secs_f64() / r.mt_time.as_secs_f64();
-            (
+            let mut row = vec![
                 r.name.clone(),
                 format_duration(r.basic_time),
                 format_duration(r.mt_time),
-                if speedup.is_finite() {
-                    format!("{:.2}x", speedup)
-                } else {
-                    "N/A".to_string()
+                match r.speedup() {
+                    Some(speedup) => format!("{:.2}x", speedup),
+                    None => "N/A".to_string(),
                 },
                 if r.results_match { "✓" } else { "✗" }.to_string(),
-            )
+            ];
+            if with_memory {
+                row.push(r.peak_bytes.map_or("N/A".to_string(), memory::format_bytes));
+            }
+            row
         })
         .collect();
 
-    let c1 = formatted
-        .iter()
-        .map(|r| r.0.len())
-        .max()
-        .unwrap()
-        .max(headers[0].len());
-    let c2 = formatted
-        .iter()
-        .map(|r| r.1.len())
-        .max()
-        .unwrap()
-        .max(headers[1].len());
-    let c3 = formatted
+    let widths: Vec<usize> = headers
         .iter()
-        .map(|r| r.2.len())
-        .max()
-        .unwrap()
-        .max(headers[2].len());
-    let c4 = formatted
-        .iter()
-        .map(|r| r.3.len())
-        .max()
-        .unwrap()
-        .max(headers[3].len());
-    let c5 = formatted
-        .iter()
-        .map(|r| r.4.chars().count())
-        .max()
-        .unwrap()
-        .max(headers[4].len());
-
-    let top = format!(
-        "╔{}═{}═{}═{}═{}╗",
-        "═".repeat(c1 + 2),
-        "═".repeat(c2 + 2),
-        "═".repeat(c3 + 2),
-        "═".repeat(c4 + 2),
-        "═".repeat(c5 + 2)
-    );
-    let title_sep = format!(
-        "╠{}╤{}╤{}╤{}╤{}╣",
-        "═".repeat(c1 + 2),
-        "═".repeat(c2 + 2),
-        "═".repeat(c3 + 2),
-        "═".repeat(c4 + 2),
-        "═".repeat(c5 + 2)
-    );
-    let header_sep = format!(
-        "╠{}╪{}╪{}╪{}╪{}╣",
-        "═".repeat(c1 + 2),
-        "═".repeat(c2 + 2),
-        "═".repeat(c3 + 2),
-        "═".repeat(c4 + 2),
-        "═".repeat(c5 + 2)
-    );
-    let bottom = format!(
-        "╚{}╧{}╧{}╧{}╧{}╝",
-        "═".repeat(c1 + 2),
-        "═".repeat(c2 + 2),
-        "═".repeat(c3 + 2),
-        "═".repeat(c4 + 2),
-        "═".repeat(c5 + 2)
-    );
+        .enumerate()
+        .map(|(i, header)| {
+            rows.iter()
+                .map(|row| row[i].chars().count())
+                .max()
+                .unwrap()
+                .max(header.len())
+        })
+        .collect();
 
-    let total_width = c1 + c2 + c3 + c4 + c5 + 14;
+    let rule = |left: &str, join: &str, right: &str| {
+        let cells: Vec<String> = widths.iter().map(|w| "═".repeat(w + 2)).collect();
+        format!("{}{}{}", left, cells.join(join), right)
+    };
+    let total_width = widths.iter().map(|w| w + 3).sum::<usize>() - 1;
 
-    println!("\n{}", top);
+    println!("\n{}", rule("╔", "═", "╗"));
     println!(
         "║{:^width$}║",
         "RUNTIME BENCHMARK RESULTS",
         width = total_width
     );
-    println!("{}", title_sep);
-    println!(
-        "║ {:<c1$} │ {:^c2$} │ {:^c3$} │ {:^c4$} │ {:^c5$} ║",
-        headers[0], headers[1], headers[2], headers[3], headers[4],
-    );
-    println!("{}", header_sep);
+    println!("{}", rule("╠", "╤", "╣"));
+    let header_cells: Vec<String> = headers
+        .iter()
+        .zip(&widths)
+        .enumerate()
+        .map(|(i, (header, &w))| {
+            if i == 0 {
+                format!(" {:<w$} ", header)
+            } else {
+                format!(" {:^w$} ", header)
+            }
+        })
+        .collect();
+    println!("║{}║", header_cells.join("│"));
+    println!("{}", rule("╠", "╪", "╣"));
+
+    for row in &rows {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .enumerate()
+            .map(|(i, (cell, &w))| match i {
+                0 => format!(" {:<w$} ", cell),
+                4 => format!(" {:^w$} ", cell),
+                _ => format!(" {:>w$} ", cell),
+            })
+            .collect();
+        println!("║{}║", cells.join("│"));
+    }
 
-    for (name, basic, mt, speedup, matched) in &formatted {
-        println!(
-            "║ {:<c1$} │ {:>c2$} │ {:>c3$} │ {:>c4$} │ {:^c5$} ║",
-            name, basic, mt, speedup, matched,
-        );
+    println!("{}", rule("╚", "╧", "╝"));
+}
+
+impl BenchmarkResult {
+    /// `basic_time / mt_time`, or `None` when either was too fast to time.
+    pub fn speedup(&self) -> Option<f64> {
+        let speedup = self.basic_time.as_secs_f64() / self.mt_time.as_secs_f64();
+        speedup.is_finite().then_some(speedup)
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut out = String::from("\"");
+    for ch in text.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
     }
+}
+
+/// Benchmark results as a JSON document, times in microseconds. `speedup`
+/// is `null` where it could not be measured.
+pub fn benchmark_json(results: &[BenchmarkResult]) -> String {
+    let entries: Vec<String> = results
+        .iter()
+        .map(|r| {
+            format!(
+                "    {{\"name\": {}, \"num_qubits\": {}, \"basic_time_us\": {:.3}, \"mt_time_us\": {:.3}, \"speedup\": {}, \"match\": {}, \"peak_bytes\": {}}}",
+                json_string(&r.name),
+                r.num_qubits,
+                r.basic_time.as_secs_f64() * 1_000_000.0,
+                r.mt_time.as_secs_f64() * 1_000_000.0,
+                r.speedup().map_or("null".to_string(), |s| format!("{:.4}", s)),
+                r.results_match,
+                r.peak_bytes.map_or("null".to_string(), |b| b.to_string())
+            )
+        })
+        .collect();
+    format!(
+        "{{\n  \"simd\": {},\n  \"results\": [\n{}\n  ]\n}}\n",
+        json_string(SimdCapability::detect().name()),
+        entries.join(",\n")
+    )
+}
 
-    println!("{}", bottom);
+/// Benchmark results as CSV with a header row, times in microseconds.
+pub fn benchmark_csv(results: &[BenchmarkResult]) -> String {
+    let simd = SimdCapability::detect().name();
+    let mut out =
+        String::from("name,num_qubits,basic_time_us,mt_time_us,speedup,match,peak_bytes,simd\n");
+    for r in results {
+        out.push_str(&format!(
+            "{},{},{:.3},{:.3},{},{},{},{}\n",
+            csv_field(&r.name),
+            r.num_qubits,
+            r.basic_time.as_secs_f64() * 1_000_000.0,
+            r.mt_time.as_secs_f64() * 1_000_000.0,
+            r.speedup().map_or(String::new(), |s| format!("{:.4}", s)),
+            r.results_match,
+            r.peak_bytes.map_or(String::new(), |b| b.to_string()),
+            simd
+        ));
+    }
+    out
 }
 
 pub fn print_summary(results: &[BenchmarkResult]) {
@@ -204,9 +263,12 @@ pub fn print_summary(results: &[BenchmarkResult]) {
     let overall_speedup = total_basic.as_secs_f64() / total_mt.as_secs_f64();
 
     println!(
-        "\nTotal time - BasicRT: {} | BasicRTMT: {} | Overall speedup: {:.2}x",
+        "\nTotal time - Baseline: {} | Compared: {} | Overall speedup: {:.2}x",
         format_duration(total_basic),
         format_duration(total_mt),
         overall_speedup
     );
+    if let Some(rss) = memory::peak_rss() {
+        println!("Peak resident memory: {}", memory::format_bytes(rss));
+    }
 }
@@ -0,0 +1,268 @@
+use crate::common::{print_section, states_equal, BenchmarkResult};
+use crate::memory;
+use libpsi_core::{
+    CustomGate, CustomGateBuilder, GateOp, QuantumCircuit, QuantumState, Runtime, Vector,
+};
+use libpsi_qasm::{parse, to_qasm};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::time::Duration;
+
+const SEED: u64 = 3467;
+const CIRCUITS: usize = 20;
+/// Largest difference allowed between psi's and the reference probabilities.
+const TOLERANCE: f64 = 1e-6;
+
+/// Reads an OpenQASM 2.0 program on stdin and prints one `bits probability`
+/// line per basis state, qubit 0 first (psi's order; Qiskit's keys are
+/// reversed).
+const QISKIT_SCRIPT: &str = r#"
+import sys
+from qiskit import QuantumCircuit
+from qiskit.quantum_info import Statevector
+qc = QuantumCircuit.from_qasm_str(sys.stdin.read())
+qc.remove_final_measurements()
+for bits, p in Statevector(qc).probabilities_dict().items():
+    print(bits[::-1], repr(float(p)))
+"#;
+
+enum Reference {
+    Shell(String),
+    Qiskit,
+}
+
+impl Reference {
+    /// The given command, or Qiskit through `python3` if it is installed.
+    fn find(command: Option<&str>) -> Option<Reference> {
+        if let Some(command) = command {
+            return Some(Reference::Shell(command.to_string()));
+        }
+        let qiskit = Command::new("python3")
+            .args(["-c", "import qiskit"])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+        matches!(qiskit, Ok(status) if status.success()).then_some(Reference::Qiskit)
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Reference::Shell(command) => format!("'{}'", command),
+            Reference::Qiskit => "Qiskit (python3)".to_string(),
+        }
+    }
+
+    fn probabilities(&self, qasm: &str) -> Result<BTreeMap<String, f64>, String> {
+        let mut command = match self {
+            Reference::Shell(command) => {
+                let mut sh = Command::new("sh");
+                sh.args(["-c", command]);
+                sh
+            }
+            Reference::Qiskit => {
+                let mut python = Command::new("python3");
+                python.args(["-c", QISKIT_SCRIPT]);
+                python
+            }
+        };
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|err| format!("could not start reference: {}", err))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(qasm.as_bytes())
+                .map_err(|err| format!("could not write to reference: {}", err))?;
+        }
+        let output = child
+            .wait_with_output()
+            .map_err(|err| format!("reference failed: {}", err))?;
+        if !output.status.success() {
+            return Err(format!(
+                "reference exited with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        let mut probabilities = BTreeMap::new();
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            let mut fields = line.split_whitespace();
+            let (Some(bits), Some(p)) = (fields.next(), fields.next()) else {
+                continue;
+            };
+            let p: f64 = p
+                .parse()
+                .map_err(|_| format!("unreadable reference line '{}'", line))?;
+            *probabilities.entry(bits.to_string()).or_insert(0.0) += p;
+        }
+        Ok(probabilities)
+    }
+}
+
+fn random_op(rng: &mut StdRng, n: usize, pair: &Arc<CustomGate>) -> GateOp {
+    let mut qubits: Vec<usize> = (0..n).collect();
+    for i in 0..3 {
+        let j = rng.random_range(i..n);
+        qubits.swap(i, j);
+    }
+    let (a, b, c) = (qubits[0], qubits[1], qubits[2]);
+    let mut angle = || rng.random_range(-PI..PI);
+    let (x, y, z) = (angle(), angle(), angle());
+    match rng.random_range(0..27) {
+        0 => GateOp::H(a),
+        1 => GateOp::X(a),
+        2 => GateOp::Y(a),
+        3 => GateOp::Z(a),
+        4 => GateOp::S(a),
+        5 => GateOp::T(a),
+        6 => GateOp::Sdg(a),
+        7 => GateOp::Tdg(a),
+        8 => GateOp::Sx(a),
+        9 => GateOp::Sxdg(a),
+        10 => GateOp::Rx(a, x),
+        11 => GateOp::Ry(a, x),
+        12 => GateOp::Rz(a, x),
+        13 => GateOp::P(a, x),
+        14 => GateOp::U1(a, x),
+        15 => GateOp::U2(a, x, y),
+        16 => GateOp::U3(a, x, y, z),
+        17 => GateOp::CNOT(a, b),
+        18 => GateOp::CZ(a, b),
+        19 => GateOp::SWAP(a, b),
+        20 => GateOp::CRx(a, b, x),
+        21 => GateOp::CRy(a, b, x),
+        22 => GateOp::CRz(a, b, x),
+        23 => GateOp::CP(a, b, x),
+        24 => GateOp::CCNOT(a, b, c),
+        25 => GateOp::CSWAP(a, b, c),
+        _ => GateOp::Custom(Arc::clone(pair), vec![a, b]),
+    }
+}
+
+fn random_circuit(rng: &mut StdRng) -> QuantumCircuit {
+    let pair = Arc::new(
+        CustomGateBuilder::new("PAIR", 2)
+            .h(0)
+            .cnot(0, 1)
+            .s(1)
+            .build(),
+    );
+    let n = rng.random_range(3..=5);
+    let mut circuit = QuantumCircuit::new(n);
+    for _ in 0..rng.random_range(5..30) {
+        circuit.push(random_op(rng, n, &pair));
+    }
+    circuit
+}
+
+fn probabilities(state: &QuantumState, num_qubits: usize) -> BTreeMap<String, f64> {
+    (0..state.size())
+        .map(|i| {
+            (
+                format!("{:0width$b}", i, width = num_qubits),
+                state.get(i).norm2(),
+            )
+        })
+        .collect()
+}
+
+/// Largest difference over the union of both distributions' outcomes.
+fn distribution_distance(a: &BTreeMap<String, f64>, b: &BTreeMap<String, f64>) -> f64 {
+    a.keys()
+        .chain(b.keys())
+        .map(|bits| (a.get(bits).unwrap_or(&0.0) - b.get(bits).unwrap_or(&0.0)).abs())
+        .fold(0.0, f64::max)
+}
+
+/// Exports seeded random circuits to OpenQASM and checks that reading them
+/// back gives the same state, then compares psi's distributions with an
+/// external simulator: `reference` (a shell command reading QASM on stdin
+/// and printing `bits probability` lines, qubit 0 first) or Qiskit when it
+/// is installed. Returns `false` if anything disagreed.
+pub fn run_all(results: &mut Vec<BenchmarkResult>, reference: Option<&str>) -> bool {
+    println!("═══════════════════════════════════════════════════════════════");
+    println!("                    EXTERNAL CROSS-VALIDATION");
+    println!("═══════════════════════════════════════════════════════════════\n");
+
+    let reference = Reference::find(reference);
+    match &reference {
+        Some(reference) => println!("Reference simulator: {}\n", reference.describe()),
+        None => println!("No reference simulator found (install qiskit or pass --reference CMD); checking QASM round trips only\n"),
+    }
+
+    let mut rng = StdRng::seed_from_u64(SEED);
+    let mut all_passed = true;
+    for index in 0..CIRCUITS {
+        let mut circuit = random_circuit(&mut rng);
+        let num_qubits = circuit.num_qubits();
+        let name = format!("Cross-validation #{}", index);
+        print_section(&name);
+
+        let qasm = match to_qasm(&circuit) {
+            Ok(qasm) => qasm,
+            Err(err) => {
+                println!("  ✗ Export failed: {}\n", err);
+                all_passed = false;
+                continue;
+            }
+        };
+        let state = circuit.compute_with(Runtime::BasicRT).clone();
+
+        let mut passed = match parse(&qasm) {
+            Ok(mut parsed) => {
+                let ok = states_equal(&state, parsed.compute_with(Runtime::BasicRT));
+                println!("  {:18} {}", "QASM round trip", if ok { "✓" } else { "✗" });
+                ok
+            }
+            Err(err) => {
+                println!("  {:18} ✗ {}", "QASM round trip", err);
+                false
+            }
+        };
+
+        if let Some(reference) = &reference {
+            match reference.probabilities(&qasm) {
+                Ok(expected) => {
+                    let distance =
+                        distribution_distance(&expected, &probabilities(&state, num_qubits));
+                    let ok = distance <= TOLERANCE;
+                    println!(
+                        "  {:18} distance {:.2e} {}",
+                        "Reference",
+                        distance,
+                        if ok { "✓" } else { "✗" }
+                    );
+                    passed &= ok;
+                }
+                Err(err) => {
+                    println!("  {:18} ✗ {}", "Reference", err);
+                    passed = false;
+                }
+            }
+        }
+
+        if !passed {
+            println!("Circuit:\n{}", qasm);
+        }
+        println!();
+
+        results.push(BenchmarkResult {
+            name,
+            num_qubits,
+            basic_time: Duration::from_micros(0),
+            mt_time: Duration::from_micros(0),
+            results_match: passed,
+            peak_bytes: memory::take_peak(),
+        });
+        all_passed &= passed;
+    }
+    all_passed
+}
@@ -1,5 +1,14 @@
-use crate::common::{benchmark_circuit, print_circuit, print_section, BenchmarkResult};
-use libpsi_core::{complex, matrix, CustomGate, CustomGateBuilder, QuantumCircuit};
+use crate::common::{
+    benchmark_circuit, print_circuit, print_section, states_equal, BenchmarkResult,
+};
+use crate::memory;
+use libpsi_core::{
+    complex, gates, matrix, CustomGate, CustomGateBuilder, GateOp, QuantumCircuit, QuantumState,
+    Runtime, RuntimeBackend, SimulatorBackend,
+};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Instant;
 
 pub fn run_all(results: &mut Vec<BenchmarkResult>) {
     println!("═══════════════════════════════════════════════════════════════");
@@ -9,6 +18,42 @@ pub fn run_all(results: &mut Vec<BenchmarkResult>) {
     test_bell_gate(results);
     test_swap_gate(results);
     test_sqrt_x_gate(results);
+    test_multi_controlled_gate(results);
+    test_custom_backend(results);
+}
+
+/// A backend from outside the core: counts what it is asked to do and
+/// hands the gates on to a built-in runtime.
+struct CountingBackend {
+    inner: RuntimeBackend,
+    gates: BTreeMap<String, usize>,
+    measurements: usize,
+}
+
+impl SimulatorBackend for CountingBackend {
+    fn name(&self) -> String {
+        format!("Counting({})", self.inner.name())
+    }
+
+    fn init(&mut self, num_qubits: usize) {
+        self.gates.clear();
+        self.measurements = 0;
+        self.inner.init(num_qubits);
+    }
+
+    fn apply(&mut self, op: &GateOp) {
+        *self.gates.entry(op.name().to_string()).or_insert(0) += 1;
+        self.inner.apply(op);
+    }
+
+    fn measure(&mut self, qubit: usize, clbit: usize) {
+        self.measurements += 1;
+        self.inner.measure(qubit, clbit);
+    }
+
+    fn finalize(&mut self) -> QuantumState {
+        self.inner.finalize()
+    }
 }
 
 pub fn test_bell_gate(results: &mut Vec<BenchmarkResult>) {
@@ -119,3 +164,163 @@ pub fn test_sqrt_x_gate(results: &mut Vec<BenchmarkResult>) {
     println!("(Two √X gates should equal X, so |0⟩ becomes |1⟩)\n");
 }
 
+pub fn test_multi_controlled_gate(results: &mut Vec<BenchmarkResult>) {
+    print_section("Custom Gate: Multi-Controlled Unitary");
+
+    let ry = Arc::new(CustomGate::from_matrix("Ry", gates::ry_matrix(0.7)));
+    let ht = Arc::new(CustomGateBuilder::new("HT", 1).h(0).t(0).build());
+    let mut display = QuantumCircuit::new(4);
+    display.mcu(&ry, &[0, 2, 3], 1).mcu(&ht, &[1], 3);
+    print_circuit(&display);
+
+    // Against the same gate as one enlarged matrix, after an input that
+    // is neither a basis state nor uniform.
+    let n = 9;
+    let mut input = QuantumCircuit::new(n);
+    for q in 0..n {
+        input.ry(q, 0.3 + 0.2 * q as f64);
+    }
+    for q in 0..n - 1 {
+        input.cnot(q, q + 1);
+    }
+    let runtimes = [
+        Runtime::BasicRT,
+        Runtime::BasicRTMT,
+        Runtime::BatchedRTMT,
+        Runtime::StructureAwareRT,
+        Runtime::StructureAwareMT,
+    ];
+    let mut all_match = true;
+    for (gate, controls, target) in [
+        (&ry, vec![], 4),
+        (&ry, vec![8], 0),
+        (&ht, vec![0, 5, 2], 7),
+        (&ht, (1..n).collect::<Vec<_>>(), 0),
+    ] {
+        let block = gate.to_quantum_gate().matrix;
+        let enlarged = Arc::new(CustomGate::from_matrix(
+            "enlarged",
+            gates::mcu_matrix(&block, controls.len()),
+        ));
+        let mut qubits = controls.clone();
+        qubits.push(target);
+        let mut reference = input.clone();
+        reference.custom(&enlarged, &qubits);
+        let expected = reference.compute_with(Runtime::BasicRT).clone();
+
+        let mut circuit = input.clone();
+        circuit.mcu(gate, &controls, target);
+        let matches = runtimes
+            .iter()
+            .all(|&runtime| states_equal(circuit.clone().compute_with(runtime), &expected));
+        // An MCU and its adjoint cancel.
+        let mut undone = circuit.clone();
+        undone.push(GateOp::MCU {
+            gate: Arc::new(gate.adjoint()),
+            controls: controls.clone(),
+            target,
+        });
+        let inverse_matches = states_equal(
+            undone.compute_with(Runtime::StructureAwareRT),
+            input.clone().compute_with(Runtime::StructureAwareRT),
+        );
+        println!(
+            "mcu({}, {:?}, {}) matches the enlarged matrix on every runtime {}, adjoint undoes it {}",
+            gate.name,
+            controls,
+            target,
+            if matches { "✓" } else { "✗" },
+            if inverse_matches { "✓" } else { "✗" }
+        );
+        all_match &= matches && inverse_matches;
+    }
+
+    // BasicRT builds the full operator of every other gate, so the
+    // enlarged matrix costs as much as any gate while the MCU only visits
+    // the pairs it changes.
+    let n = 10;
+    let controls: Vec<usize> = (0..n - 1).collect();
+    let total = ((1usize << n) * ((1 << n) - 1) / 2) as f64;
+    let start_state: Vec<_> = (0..1usize << n)
+        .map(|i| complex!((i as f64 / total).sqrt(), 0.0))
+        .collect();
+    let block = ry.to_quantum_gate().matrix;
+    let enlarged = Arc::new(CustomGate::from_matrix(
+        "enlarged",
+        gates::mcu_matrix(&block, controls.len()),
+    ));
+    let mut dense = QuantumCircuit::new(n);
+    dense.with_initial_state(&start_state);
+    let mut subspace = dense.clone();
+    dense.custom(&enlarged, &(0..n).collect::<Vec<_>>());
+    subspace.mcu(&ry, &controls, n - 1);
+    let start = Instant::now();
+    let dense_state = dense.compute_with(Runtime::BasicRT).clone();
+    let dense_time = start.elapsed();
+    let start = Instant::now();
+    let subspace_state = subspace.compute_with(Runtime::BasicRT).clone();
+    let subspace_time = start.elapsed();
+    let timing_matches = states_equal(&dense_state, &subspace_state);
+    println!(
+        "{}-qubit BasicRT: enlarged matrix {:.3}ms, subspace {:.3}ms {}\n",
+        n,
+        dense_time.as_secs_f64() * 1000.0,
+        subspace_time.as_secs_f64() * 1000.0,
+        if timing_matches { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: format!("Multi-controlled unitary ({} qubits)", n),
+        num_qubits: n,
+        basic_time: dense_time,
+        mt_time: subspace_time,
+        results_match: all_match && timing_matches,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+pub fn test_custom_backend(results: &mut Vec<BenchmarkResult>) {
+    print_section("Custom Backend: SimulatorBackend");
+
+    let build = || {
+        let mut circuit = QuantumCircuit::new(3);
+        circuit
+            .h(0)
+            .cnot(0, 1)
+            .cnot(1, 2)
+            .barrier_all()
+            .t(2)
+            .measure_all();
+        circuit
+    };
+
+    let mut backend = CountingBackend {
+        inner: RuntimeBackend::new(Runtime::SimdRT),
+        gates: BTreeMap::new(),
+        measurements: 0,
+    };
+    let mut reference = build();
+    let start = Instant::now();
+    reference.compute_with(Runtime::BasicRT);
+    let basic_time = start.elapsed();
+
+    let mut circuit = build();
+    let start = Instant::now();
+    circuit.compute_with_backend(&mut backend);
+    let backend_time = start.elapsed();
+
+    println!("Backend: {}", backend.name());
+    println!("Gates applied: {:?}", backend.gates);
+    println!("Measurements: {}", backend.measurements);
+    let matched = states_equal(reference.state(), circuit.computed_state().unwrap());
+    println!("Matches BasicRT: {}\n", if matched { "✓" } else { "✗" });
+
+    results.push(BenchmarkResult {
+        name: "Custom backend (Counting)".to_string(),
+        num_qubits: circuit.num_qubits(),
+        basic_time,
+        mt_time: backend_time,
+        results_match: matched,
+        peak_bytes: memory::take_peak(),
+    });
+}
@@ -0,0 +1,341 @@
+use crate::common::{print_section, BenchmarkResult};
+use crate::memory;
+use libpsi_core::{CustomGateBuilder, QuantumCircuit, QuantumState, Runtime, Vector};
+use libpsi_visualizer::{
+    Charset, HorizontalRenderer, RichDisplay, SvgRenderer, Terminal, VerticalRenderer,
+};
+use std::f64::consts::PI;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Largest difference allowed between a stored and a computed amplitude.
+const TOLERANCE: f64 = 1e-9;
+
+fn golden_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("golden")
+}
+
+fn runtimes() -> Vec<(&'static str, Runtime)> {
+    vec![
+        ("BasicRT", Runtime::BasicRT),
+        ("BasicRTMT", Runtime::BasicRTMT),
+        ("BatchedRT", Runtime::BatchedRT),
+        ("BatchedRTMT", Runtime::BatchedRTMT),
+        ("SimdRT", Runtime::SimdRT),
+        ("SimdRTMT", Runtime::SimdRTMT),
+        ("StructureAwareRT", Runtime::StructureAwareRT),
+        ("StructureAwareMT", Runtime::StructureAwareMT),
+        ("Optimal", Runtime::Custom(Runtime::optimal())),
+    ]
+}
+
+fn catalog() -> Vec<(&'static str, QuantumCircuit)> {
+    let mut bell = QuantumCircuit::with_classical(2, 2);
+    bell.h(0).cnot(0, 1).measure(0, 0).measure(1, 1);
+
+    let mut ghz = QuantumCircuit::new(4);
+    ghz.section("prepare").h(0).cnot(0, 1).cnot(1, 2).cnot(2, 3);
+
+    let mut clifford_t = QuantumCircuit::new(3);
+    clifford_t
+        .h(0)
+        .s(1)
+        .t(2)
+        .cnot(0, 2)
+        .sdg(0)
+        .tdg(1)
+        .sx(2)
+        .cz(1, 2)
+        .swap(0, 1)
+        .sxdg(0);
+
+    let mut rotations = QuantumCircuit::new(3);
+    rotations
+        .rx(0, PI / 3.0)
+        .ry(1, PI / 5.0)
+        .rz(2, -PI / 7.0)
+        .u3(0, PI / 2.0, PI / 4.0, PI / 8.0)
+        .crx(0, 1, PI / 3.0)
+        .cry(1, 2, PI / 6.0)
+        .crz(2, 0, PI / 9.0)
+        .cp(0, 2, PI / 4.0);
+
+    let mut qft = QuantumCircuit::new(3);
+    qft.x(0).x(2);
+    for target in 0..3 {
+        qft.h(target);
+        for control in target + 1..3 {
+            qft.cp(control, target, PI / (1 << (control - target)) as f64);
+        }
+    }
+    qft.swap(0, 2);
+
+    let mut three_qubit = QuantumCircuit::new(3);
+    three_qubit
+        .h(0)
+        .h(1)
+        .ccnot(0, 1, 2)
+        .x(0)
+        .cswap(0, 1, 2)
+        .barrier_all()
+        .p(2, PI / 3.0);
+
+    let bell_gate = CustomGateBuilder::new("BELL", 2).h(0).cnot(0, 1).build();
+    let mut custom = QuantumCircuit::new(3);
+    custom.apply_custom(bell_gate, &[0, 2]).t(1).h(1);
+
+    let teleport = QuantumCircuit::teleportation(PI / 3.0, PI / 4.0, PI / 8.0);
+
+    vec![
+        ("bell", bell),
+        ("ghz", ghz),
+        ("clifford_t", clifford_t),
+        ("rotations", rotations),
+        ("qft", qft),
+        ("three_qubit", three_qubit),
+        ("custom", custom),
+        ("teleport", teleport),
+    ]
+}
+
+fn format_state(state: &QuantumState) -> String {
+    (0..state.size())
+        .map(|i| {
+            let amp = state.get(i);
+            format!("{} {:.12} {:.12}\n", i, amp.real, amp.imaginary)
+        })
+        .collect()
+}
+
+fn parse_state(text: &str) -> Option<Vec<(f64, f64)>> {
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let mut fields = line.split_whitespace().skip(1);
+            let real = fields.next()?.parse().ok()?;
+            let imaginary = fields.next()?.parse().ok()?;
+            Some((real, imaginary))
+        })
+        .collect()
+}
+
+/// Largest amplitude difference, or `None` if the sizes differ.
+fn state_drift(expected: &[(f64, f64)], state: &QuantumState) -> Option<f64> {
+    if expected.len() != state.size() {
+        return None;
+    }
+    let drift = expected
+        .iter()
+        .enumerate()
+        .map(|(i, &(real, imaginary))| {
+            let amp = state.get(i);
+            (amp.real - real)
+                .abs()
+                .max((amp.imaginary - imaginary).abs())
+        })
+        .fold(0.0, f64::max);
+    Some(drift)
+}
+
+fn render_diagrams(circuit: &QuantumCircuit) -> String {
+    let plain = Terminal::plain();
+    format!(
+        "Horizontal:\n{}\nVertical:\n{}\nASCII:\n{}",
+        HorizontalRenderer::new(circuit).with_terminal(plain),
+        VerticalRenderer::new(circuit).with_terminal(plain),
+        HorizontalRenderer::new(circuit)
+            .with_terminal(plain)
+            .with_charset(Charset::Ascii)
+    )
+}
+
+/// Checks every circuit of the catalog against the stored reference state
+/// (under every runtime) and diagrams in `tester/golden`. With `update` the
+/// references are rewritten from the BasicRT results instead. Returns
+/// `false` if anything drifted.
+pub fn run_all(results: &mut Vec<BenchmarkResult>, update: bool) -> bool {
+    println!("═══════════════════════════════════════════════════════════════");
+    println!("                    GOLDEN-FILE REGRESSION");
+    println!("═══════════════════════════════════════════════════════════════\n");
+
+    let dir = golden_dir();
+    if update {
+        if let Err(err) = fs::create_dir_all(&dir) {
+            println!("✗ Could not create {}: {}", dir.display(), err);
+            return false;
+        }
+    }
+
+    let mut all_passed = true;
+    for (name, circuit) in catalog() {
+        print_section(&format!("Golden: {}", name));
+        let state_path = dir.join(format!("{}.state", name));
+        let diagram_path = dir.join(format!("{}.diagram", name));
+        let diagrams = render_diagrams(&circuit);
+
+        if update {
+            let mut reference = circuit.clone();
+            let state = format_state(reference.compute_with(Runtime::BasicRT));
+            let written =
+                fs::write(&state_path, state).and_then(|_| fs::write(&diagram_path, &diagrams));
+            match written {
+                Ok(()) => println!(
+                    "Updated {} and {}\n",
+                    state_path.display(),
+                    diagram_path.display()
+                ),
+                Err(err) => {
+                    println!("✗ Could not write references: {}\n", err);
+                    all_passed = false;
+                }
+            }
+            continue;
+        }
+
+        let mut passed = true;
+        let expected = fs::read_to_string(&state_path)
+            .ok()
+            .and_then(|text| parse_state(&text));
+        match expected {
+            Some(expected) => {
+                for (runtime_name, runtime) in runtimes() {
+                    let mut run = circuit.clone();
+                    let drift = state_drift(&expected, run.compute_with(runtime));
+                    let ok = drift.is_some_and(|d| d <= TOLERANCE);
+                    match drift {
+                        Some(d) => println!(
+                            "  {:18} drift {:.2e} {}",
+                            runtime_name,
+                            d,
+                            if ok { "✓" } else { "✗" }
+                        ),
+                        None => println!("  {:18} state size differs ✗", runtime_name),
+                    }
+                    passed &= ok;
+                }
+            }
+            None => {
+                println!("  ✗ Missing or unreadable {}", state_path.display());
+                passed = false;
+            }
+        }
+
+        match fs::read_to_string(&diagram_path) {
+            Ok(stored) if stored == diagrams => println!("  {:18} ✓", "Diagrams"),
+            Ok(stored) => {
+                println!(
+                    "  {:18} ✗ differ from {}",
+                    "Diagrams",
+                    diagram_path.display()
+                );
+                println!("Expected:\n{}\nGot:\n{}", stored, diagrams);
+                passed = false;
+            }
+            Err(_) => {
+                println!("  ✗ Missing {}", diagram_path.display());
+                passed = false;
+            }
+        }
+        println!();
+
+        results.push(BenchmarkResult {
+            name: format!("Golden: {}", name),
+            num_qubits: circuit.num_qubits(),
+            basic_time: Duration::from_micros(0),
+            mt_time: Duration::from_micros(0),
+            results_match: passed,
+            peak_bytes: memory::take_peak(),
+        });
+        all_passed &= passed;
+    }
+
+    all_passed &= check_html(&dir, update, results);
+    all_passed &= check_svg(&dir, update, results);
+
+    if !update {
+        if all_passed {
+            println!("✓ All golden files match");
+        } else {
+            println!(
+                "✗ Golden files differ; run 'tester golden --update' if the change is intended"
+            );
+        }
+    }
+    all_passed
+}
+
+/// Notebook HTML of the computed Bell circuit and its counts, against
+/// `bell.html`.
+fn check_html(dir: &std::path::Path, update: bool, results: &mut Vec<BenchmarkResult>) -> bool {
+    print_section("Golden: notebook HTML");
+
+    let mut bell = QuantumCircuit::with_classical(2, 2);
+    bell.h(0).cnot(0, 1).measure(0, 0).measure(1, 1);
+    bell.compute_with(Runtime::BasicRT);
+    let html = format!(
+        "{}\n{}\n",
+        bell.to_html(),
+        bell.sample(1000, Some(42)).to_html()
+    );
+    let path = dir.join("bell.html");
+    let passed = check_rendered(&path, "HTML", &html, update);
+    push_result("notebook HTML", bell.num_qubits(), passed, results);
+    passed
+}
+
+/// The SVG diagram of the teleportation circuit, with its measurements and
+/// conditioned gates, against `teleport.svg`.
+fn check_svg(dir: &std::path::Path, update: bool, results: &mut Vec<BenchmarkResult>) -> bool {
+    print_section("Golden: SVG diagram");
+
+    let teleport = QuantumCircuit::teleportation(PI / 3.0, PI / 4.0, PI / 8.0);
+    let svg = SvgRenderer::new(&teleport).to_string();
+    let passed = check_rendered(&dir.join("teleport.svg"), "SVG", &svg, update);
+    push_result("SVG diagram", teleport.num_qubits(), passed, results);
+    passed
+}
+
+/// Compares `rendered` with the file at `path`, or rewrites the file with
+/// `update`.
+fn check_rendered(path: &std::path::Path, label: &str, rendered: &str, update: bool) -> bool {
+    if update {
+        return match fs::write(path, rendered) {
+            Ok(()) => {
+                println!("Updated {}\n", path.display());
+                true
+            }
+            Err(err) => {
+                println!("✗ Could not write {}: {}\n", path.display(), err);
+                false
+            }
+        };
+    }
+
+    match fs::read_to_string(path) {
+        Ok(stored) if stored == rendered => {
+            println!("  {:18} ✓\n", label);
+            true
+        }
+        Ok(stored) => {
+            println!("  {:18} ✗ differs from {}", label, path.display());
+            println!("Expected:\n{}\nGot:\n{}\n", stored, rendered);
+            false
+        }
+        Err(_) => {
+            println!("  ✗ Missing {}\n", path.display());
+            false
+        }
+    }
+}
+
+fn push_result(name: &str, num_qubits: usize, passed: bool, results: &mut Vec<BenchmarkResult>) {
+    results.push(BenchmarkResult {
+        name: format!("Golden: {}", name),
+        num_qubits,
+        basic_time: Duration::from_micros(0),
+        mt_time: Duration::from_micros(0),
+        results_match: passed,
+        peak_bytes: memory::take_peak(),
+    });
+}
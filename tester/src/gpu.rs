@@ -0,0 +1,194 @@
+use crate::common::{print_section, BenchmarkResult};
+use crate::memory;
+use libpsi_core::{fidelity, library, QuantumCircuit, Runtime, SnapshotKind, SnapshotValue};
+use std::time::Instant;
+
+pub fn run_all(results: &mut Vec<BenchmarkResult>) {
+    println!("═══════════════════════════════════════════════════════════════");
+    println!("                    GPU RUNTIME TESTS");
+    println!("═══════════════════════════════════════════════════════════════\n");
+
+    #[cfg(feature = "gpu")]
+    match libpsi_core::GpuSimulator::shared() {
+        Some(gpu) => println!(
+            "Adapter: {} (up to {} qubits)\n",
+            gpu.adapter(),
+            gpu.max_qubits()
+        ),
+        None => println!("Adapter: none, GPUAccelerated runs on the CPU\n"),
+    }
+    #[cfg(not(feature = "gpu"))]
+    println!("Built without the gpu feature, GPUAccelerated runs on the CPU\n");
+
+    test_gpu_matches_cpu(results);
+    test_gpu_snapshots(results);
+    #[cfg(feature = "gpu")]
+    test_gpu_batch(results);
+}
+
+/// The GPU runs in single precision, so states agree with the CPU up to
+/// about 1e-7 per gate.
+pub fn test_gpu_matches_cpu(results: &mut Vec<BenchmarkResult>) {
+    print_section("GPU vs CPU");
+
+    for n in [4, 12, 20] {
+        let mut circuit =
+            library::random_circuit(n, 12, 3502 + n as u64, library::GateSet::Universal);
+        // Wider gates run on the CPU between the shaders.
+        circuit.ccnot(0, n / 2, n - 1).mcx(&[1, 2, 3], 0).h(n - 1);
+
+        let start = Instant::now();
+        let expected = circuit
+            .clone()
+            .compute_with(Runtime::optimal().into())
+            .clone();
+        let cpu_time = start.elapsed();
+        let start = Instant::now();
+        let state = circuit
+            .clone()
+            .compute_with(Runtime::GPUAccelerated)
+            .clone();
+        let gpu_time = start.elapsed();
+
+        let infidelity = (1.0 - fidelity(&state, &expected)).abs();
+        let matches = infidelity < 1e-5;
+        println!(
+            "{}-qubit: CPU={:.3}ms, GPU={:.3}ms, 1 - F={:.1e}, Match={}",
+            n,
+            cpu_time.as_secs_f64() * 1000.0,
+            gpu_time.as_secs_f64() * 1000.0,
+            infidelity,
+            if matches { "✓" } else { "✗" }
+        );
+
+        results.push(BenchmarkResult {
+            name: format!("{}-qubit GPU", n),
+            num_qubits: n,
+            basic_time: cpu_time,
+            mt_time: gpu_time,
+            results_match: matches,
+            peak_bytes: memory::take_peak(),
+        });
+    }
+    println!();
+}
+
+pub fn test_gpu_snapshots(results: &mut Vec<BenchmarkResult>) {
+    print_section("GPU Snapshots");
+
+    let mut circuit = QuantumCircuit::new(3);
+    circuit
+        .h(0)
+        .snapshot("superposed", SnapshotKind::Probabilities)
+        .cnot(0, 1)
+        .cnot(1, 2)
+        .snapshot("ghz", SnapshotKind::Probabilities);
+    let start = Instant::now();
+    circuit.compute_with(Runtime::GPUAccelerated);
+    let elapsed = start.elapsed();
+
+    let mut close = |label: &str, expected: [f64; 8]| match circuit.snapshots().get(label) {
+        Some(SnapshotValue::Probabilities(p)) => {
+            p.iter().zip(expected).all(|(a, b)| (a - b).abs() < 1e-6)
+        }
+        _ => false,
+    };
+    let superposed = close("superposed", [0.5, 0.0, 0.0, 0.0, 0.5, 0.0, 0.0, 0.0]);
+    let ghz = close("ghz", [0.5, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.5]);
+    println!(
+        "Snapshots after H and after the GHZ chain: {}\n",
+        if superposed && ghz { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: "GPU snapshots".to_string(),
+        num_qubits: 3,
+        basic_time: elapsed,
+        mt_time: elapsed,
+        results_match: superposed && ghz,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+/// Parameter bindings of an ansatz run as one batch, against each binding
+/// computed on the CPU, and against running them one by one on the GPU.
+#[cfg(feature = "gpu")]
+pub fn test_gpu_batch(results: &mut Vec<BenchmarkResult>) {
+    use libpsi_core::{GateOp, GpuSimulator, QuantumState};
+
+    print_section("GPU Batched Bindings");
+
+    let Some(gpu) = GpuSimulator::shared() else {
+        println!("No adapter, skipped\n");
+        return;
+    };
+    let n = 10;
+    let mut ansatz = library::efficient_su2(n, 2);
+    // A wider gate, applied per instance on the CPU.
+    ansatz.ccnot(0, 1, 2);
+    let names = ansatz.parameters();
+    let values: Vec<Vec<(&str, f64)>> = (0..64)
+        .map(|instance| {
+            names
+                .iter()
+                .enumerate()
+                .map(|(k, name)| (name.as_str(), 0.1 * instance as f64 + 0.37 * k as f64))
+                .collect()
+        })
+        .collect();
+    let bindings: Vec<&[(&str, f64)]> = values.iter().map(Vec::as_slice).collect();
+
+    let start = Instant::now();
+    let one_by_one: Vec<QuantumState> = bindings
+        .iter()
+        .map(|binding| {
+            ansatz
+                .bind_parameters(binding)
+                .compute_with(Runtime::GPUAccelerated)
+                .clone()
+        })
+        .collect();
+    let single_time = start.elapsed();
+    let start = Instant::now();
+    let batched = gpu.run_bindings(&ansatz, &bindings).unwrap();
+    let batch_time = start.elapsed();
+
+    let worst = bindings
+        .iter()
+        .zip(batched.iter().zip(&one_by_one))
+        .map(|(binding, (batched, single))| {
+            let expected = ansatz
+                .bind_parameters(binding)
+                .compute_with(Runtime::optimal().into())
+                .clone();
+            let batched = (1.0 - fidelity(batched, &expected)).abs();
+            batched.max((1.0 - fidelity(single, &expected)).abs())
+        })
+        .fold(0.0, f64::max);
+    let rejected = gpu
+        .run_batch(2, &[vec![GateOp::H(0)], vec![GateOp::H(1)]], None)
+        .is_err();
+    let matches = batched.len() == bindings.len() && worst < 1e-5 && rejected;
+    println!(
+        "{} bindings of {} qubits: one by one={:.3}ms, batched={:.3}ms, worst 1 - F={:.1e}, Match={}",
+        bindings.len(),
+        n,
+        single_time.as_secs_f64() * 1000.0,
+        batch_time.as_secs_f64() * 1000.0,
+        worst,
+        if matches { "✓" } else { "✗" }
+    );
+    println!(
+        "Rejects circuits that differ in more than angles: {}\n",
+        if rejected { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: "GPU batched bindings".to_string(),
+        num_qubits: n,
+        basic_time: single_time,
+        mt_time: batch_time,
+        results_match: matches,
+        peak_bytes: memory::take_peak(),
+    });
+}
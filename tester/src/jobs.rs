@@ -0,0 +1,268 @@
+use crate::common::{print_section, states_equal, BenchmarkResult};
+use crate::memory;
+use libpsi_core::{JobError, QuantumCircuit, Runtime, SnapshotKind};
+use std::future::Future;
+use std::panic;
+use std::pin::pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake};
+use std::thread::{self, Thread};
+use std::time::{Duration, Instant};
+
+pub fn run_all(results: &mut Vec<BenchmarkResult>) {
+    println!("═══════════════════════════════════════════════════════════════");
+    println!("              ASYNC JOB & PARALLEL SHOT TESTS");
+    println!("═══════════════════════════════════════════════════════════════\n");
+
+    test_async_matches_sync(results);
+    test_cancellation(results);
+    test_await(results);
+    test_parallel_shots(results);
+}
+
+fn layered_circuit(num_qubits: usize, depth: usize) -> QuantumCircuit {
+    let mut circuit = QuantumCircuit::new(num_qubits);
+    for layer in 0..depth {
+        for q in 0..num_qubits {
+            circuit.ry(q, 0.1 * (layer + q + 1) as f64);
+        }
+        for q in (layer % 2..num_qubits - 1).step_by(2) {
+            circuit.cnot(q, q + 1);
+        }
+    }
+    circuit
+}
+
+pub fn test_async_matches_sync(results: &mut Vec<BenchmarkResult>) {
+    print_section("Background Computation");
+
+    let mut circuit = layered_circuit(12, 40);
+    let start = Instant::now();
+    circuit.compute_with(Runtime::SimdRT);
+    let sync_time = start.elapsed();
+
+    let start = Instant::now();
+    let mut job = circuit.compute_async(Runtime::SimdRT);
+    let mut polls = 0;
+    let output = loop {
+        if let Some(output) = job.try_take() {
+            break output;
+        }
+        polls += 1;
+        thread::sleep(Duration::from_micros(200));
+    };
+    let async_time = start.elapsed();
+
+    let progress = job.progress();
+    let matched = match &output {
+        Ok(output) => states_equal(&output.state, circuit.state()),
+        Err(_) => false,
+    };
+    println!("Polls before completion: {}", polls);
+    println!("Final progress: {:.0}%", progress * 100.0);
+    println!("Matches compute_with: {}", if matched { "✓" } else { "✗" });
+
+    // The worker's panic is expected; keep it out of the output.
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+    let mut non_clifford = QuantumCircuit::new(1);
+    non_clifford.t(0);
+    let failed = non_clifford.compute_async(Runtime::Stabilizer).wait();
+    panic::set_hook(hook);
+    let reported = matches!(failed, Err(JobError::Failed(_)));
+    println!(
+        "Failing runtime: {}\n",
+        match &failed {
+            Err(err) => err.to_string(),
+            Ok(_) => "no error".to_string(),
+        }
+    );
+
+    results.push(BenchmarkResult {
+        name: "Async: matches sync".to_string(),
+        num_qubits: circuit.num_qubits(),
+        basic_time: sync_time,
+        mt_time: async_time,
+        results_match: matched && progress == 1.0 && reported,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+pub fn test_cancellation(results: &mut Vec<BenchmarkResult>) {
+    print_section("Cancellation");
+
+    let circuit = layered_circuit(16, 200);
+    let job = circuit.compute_async(Runtime::BasicRTMT);
+    let start = Instant::now();
+    while job.progress() < 0.02 {
+        thread::sleep(Duration::from_millis(1));
+    }
+    let at_cancel = job.progress();
+    job.cancel();
+    let output = job.wait();
+    let elapsed = start.elapsed();
+
+    let cancelled = matches!(output, Err(JobError::Cancelled));
+    println!(
+        "Cancelled at {:.1}% after {:.1?}",
+        at_cancel * 100.0,
+        elapsed
+    );
+    println!(
+        "Reported as cancelled: {}\n",
+        if cancelled { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: "Async: cancellation".to_string(),
+        num_qubits: circuit.num_qubits(),
+        basic_time: elapsed,
+        mt_time: elapsed,
+        results_match: cancelled && at_cancel < 1.0,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// A minimal executor, standing in for tokio or any other async runtime.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let waker = Arc::new(ThreadWaker(thread::current())).into();
+    let mut cx = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        thread::park();
+    }
+}
+
+pub fn test_await(results: &mut Vec<BenchmarkResult>) {
+    print_section("Awaiting a Job");
+
+    let mut circuit = QuantumCircuit::new(3);
+    circuit
+        .h(0)
+        .cnot(0, 1)
+        .cnot(1, 2)
+        .snapshot("ghz", SnapshotKind::Probabilities);
+
+    let start = Instant::now();
+    let output = block_on(circuit.compute_async(Runtime::StructureAwareRT));
+    let elapsed = start.elapsed();
+
+    let matched = match output {
+        Ok(output) => {
+            let has_snapshot = output.snapshots.contains_key("ghz");
+            let mut reference = circuit.clone();
+            circuit.set_computed(output);
+            has_snapshot
+                && states_equal(
+                    circuit.computed_state().unwrap(),
+                    reference.compute_with(Runtime::BasicRT),
+                )
+        }
+        Err(_) => false,
+    };
+    println!(
+        "GHZ state and snapshot via .await: {}\n",
+        if matched { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: "Async: await".to_string(),
+        num_qubits: 3,
+        basic_time: elapsed,
+        mt_time: elapsed,
+        results_match: matched,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+pub fn test_parallel_shots(results: &mut Vec<BenchmarkResult>) {
+    print_section("Parallel Shots");
+
+    let shots = 2_000_000;
+    let mut circuit = QuantumCircuit::new(10);
+    circuit.h(0);
+    for q in 1..10 {
+        circuit.cnot(0, q);
+    }
+    circuit.compute();
+
+    let start = Instant::now();
+    circuit.sample(shots, Some(42));
+    let sequential_time = start.elapsed();
+
+    let start = Instant::now();
+    let counts = circuit.run_shots_parallel(shots, Some(42));
+    let parallel_time = start.elapsed();
+
+    let reproducible = counts == circuit.run_shots_parallel(shots, Some(42));
+    let zeros = counts.probability("0000000000");
+    let ones = counts.probability("1111111111");
+    let plausible = counts.shots() == shots
+        && counts.len() == 2
+        && (zeros - 0.5).abs() < 0.005
+        && (ones - 0.5).abs() < 0.005;
+    println!("{}", counts);
+    println!(
+        "Reproducible with a seed: {}",
+        if reproducible { "✓" } else { "✗" }
+    );
+    println!("GHZ statistics: {}\n", if plausible { "✓" } else { "✗" });
+
+    results.push(BenchmarkResult {
+        name: "Parallel shots (2M)".to_string(),
+        num_qubits: circuit.num_qubits(),
+        basic_time: sequential_time,
+        mt_time: parallel_time,
+        results_match: reproducible && plausible,
+        peak_bytes: memory::take_peak(),
+    });
+
+    // Active reset runs shot by shot; chunks of shots go to different
+    // threads.
+    let shots = 200_000;
+    let mut reset = QuantumCircuit::with_classical(2, 3);
+    reset.h(0).cnot(0, 1).measure(0, 0).x(0).c_if(0, 1);
+    reset.measure(0, 1).measure(1, 2);
+
+    let start = Instant::now();
+    reset.sample(shots, Some(42));
+    let sequential_time = start.elapsed();
+    let start = Instant::now();
+    let counts = reset.run_shots_parallel(shots, Some(42));
+    let parallel_time = start.elapsed();
+
+    let reproducible = counts == reset.run_shots_parallel(shots, Some(42));
+    let plausible = counts.shots() == shots
+        && counts.get("000") + counts.get("101") == shots
+        && (counts.probability("101") - 0.5).abs() < 0.01;
+    println!(
+        "Active reset, {} shots: sample={:.3}ms, parallel={:.3}ms ({} × 000, {} × 101), reproducible: {}, statistics: {}\n",
+        shots,
+        sequential_time.as_secs_f64() * 1000.0,
+        parallel_time.as_secs_f64() * 1000.0,
+        counts.get("000"),
+        counts.get("101"),
+        if reproducible { "✓" } else { "✗" },
+        if plausible { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: "Parallel shots, mid-circuit".to_string(),
+        num_qubits: reset.num_qubits(),
+        basic_time: sequential_time,
+        mt_time: parallel_time,
+        results_match: reproducible && plausible,
+        peak_bytes: memory::take_peak(),
+    });
+}
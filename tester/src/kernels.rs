@@ -1,5 +1,13 @@
-use crate::common::{print_section, states_equal, BenchmarkResult};
-use libpsi_core::{QuantumCircuit, Runtime, RuntimeConfig};
+use crate::common::{print_section, states_equal, BenchmarkResult, CircuitBuilder};
+use crate::memory;
+use libpsi_core::{
+    fidelity, gates, library, Complex, CustomGate, GateDurations, GateOp, GateType, Kernel, Matrix,
+    Precision, QuantumCircuit, QuantumState, Runtime, RuntimeConfig, SchedulePolicy, Vector,
+    MAX_FUSED_QUBITS,
+};
+use libpsi_visualizer::{GanttRenderer, KernelRenderer};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use std::f64::consts::PI;
 use std::time::Instant;
 
@@ -9,9 +17,20 @@ pub fn run_all(results: &mut Vec<BenchmarkResult>) {
     println!("═══════════════════════════════════════════════════════════════\n");
 
     test_kernel_fusion(results);
+    test_gate_classification(results);
     test_batched_vs_basic(results);
     test_batched_large_circuits(results);
+    test_in_place_kernels(results);
+    test_diagonal_kernels(results);
+    test_controlled_kernels(results);
+    test_permutation_kernels(results);
+    test_cache_blocking(results);
+    test_chunked_parallel_gates(results);
+    test_parametric_kernel_cache(results);
     test_structure_aware(results);
+    test_block_fusion(results);
+    test_barrier_fence(results);
+    test_commutation(results);
     test_composable_runtime(results);
 }
 
@@ -40,6 +59,8 @@ pub fn test_kernel_fusion(results: &mut Vec<BenchmarkResult>) {
         println!("  {}: {} on {:?}", i, k.name, k.targets);
     }
 
+    println!("\n{}", KernelRenderer::from_batch(&optimized_batch));
+
     let reduction = ((original_count - optimized_count) as f64 / original_count as f64) * 100.0;
     println!(
         "\nKernel reduction: {} → {} ({:.0}% fewer)",
@@ -61,9 +82,11 @@ pub fn test_kernel_fusion(results: &mut Vec<BenchmarkResult>) {
 
     results.push(BenchmarkResult {
         name: format!("Fusion ({}→{} kernels)", original_count, optimized_count),
+        num_qubits: basic.num_qubits(),
         basic_time,
         mt_time: batched_time,
         results_match: match_result,
+        peak_bytes: memory::take_peak(),
     });
 
     let fusion_heavy = || {
@@ -93,16 +116,18 @@ pub fn test_kernel_fusion(results: &mut Vec<BenchmarkResult>) {
 
     results.push(BenchmarkResult {
         name: format!("Heavy fusion ({}→{} kernels)", orig2, opt2),
+        num_qubits: basic2.num_qubits(),
         basic_time: basic_time2,
         mt_time: batched_time2,
         results_match: match2,
+        peak_bytes: memory::take_peak(),
     });
 }
 
 pub fn test_batched_vs_basic(results: &mut Vec<BenchmarkResult>) {
     print_section("Batched vs Basic Runtime Comparison");
 
-    let test_cases: Vec<(&str, Box<dyn Fn() -> QuantumCircuit>)> = vec![
+    let test_cases: Vec<(&str, CircuitBuilder)> = vec![
         (
             "Bell State",
             Box::new(|| {
@@ -164,9 +189,11 @@ pub fn test_batched_vs_basic(results: &mut Vec<BenchmarkResult>) {
 
         results.push(BenchmarkResult {
             name: format!("Batched: {}", name),
+            num_qubits: basic.num_qubits(),
             basic_time,
             mt_time: batched_time,
             results_match: match_result,
+            peak_bytes: memory::take_peak(),
         });
     }
     println!();
@@ -211,14 +238,609 @@ pub fn test_batched_large_circuits(results: &mut Vec<BenchmarkResult>) {
 
         results.push(BenchmarkResult {
             name: format!("{}-qubit batched", n),
+            num_qubits: n,
             basic_time: basic_mt_time,
             mt_time: batched_mt_time,
             results_match: match_result,
+            peak_bytes: memory::take_peak(),
         });
     }
     println!();
 }
 
+pub fn test_in_place_kernels(results: &mut Vec<BenchmarkResult>) {
+    print_section("In-Place Kernel Application");
+
+    // A 3-qubit unitary on scattered, unordered targets checks that each
+    // group is gathered and scattered in the order of the targets.
+    let mut block = QuantumCircuit::new(3);
+    block
+        .h(0)
+        .cry(0, 1, 0.7)
+        .t(2)
+        .ccnot(1, 2, 0)
+        .rx(2, 1.3)
+        .cz(0, 2);
+    let scrambler = CustomGate::from_matrix("U", block.unitary());
+    let mut small = QuantumCircuit::new(8);
+    small
+        .h(0)
+        .h(3)
+        .h(6)
+        .apply_custom(scrambler.clone(), &[5, 1, 3]);
+    small.apply_custom(scrambler, &[7, 0, 4]).cnot(4, 2);
+    let reference = small.clone().compute_with(Runtime::BasicRT).clone();
+    let threaded = RuntimeConfig::new().batched().parallel().with_threshold(1);
+    let scattered_matches =
+        states_equal(small.clone().compute_with(Runtime::BatchedRT), &reference)
+            && states_equal(small.clone().compute_with_config(threaded), &reference)
+            && states_equal(small.clone().compute_with(Runtime::BasicRTMT), &reference);
+    println!(
+        "Scattered 3-qubit custom gates, in place and ping-pong: {}",
+        if scattered_matches { "✓" } else { "✗" }
+    );
+
+    // Deep circuits of two-qubit gates apply one kernel after another.
+    let n = 18;
+    let mut deep = QuantumCircuit::new(n);
+    for layer in 0..40 {
+        for q in 0..n {
+            deep.ry(q, 0.1 * (layer + q) as f64);
+        }
+        for q in (layer % 2..n - 1).step_by(2) {
+            deep.cp(q, q + 1, 0.3).swap(q, (q + 7) % n);
+        }
+    }
+    let start = Instant::now();
+    let sequential = deep.clone().compute_with(Runtime::BatchedRT).clone();
+    let sequential_time = start.elapsed();
+    let start = Instant::now();
+    let parallel = deep.clone().compute_with(Runtime::BatchedRTMT).clone();
+    let parallel_time = start.elapsed();
+    let deep_matches = states_equal(&sequential, &parallel);
+    println!(
+        "{}-qubit, {} gates: BatchedRT={:.3}ms, BatchedRTMT={:.3}ms, Match={}\n",
+        n,
+        deep.operations().len(),
+        sequential_time.as_secs_f64() * 1000.0,
+        parallel_time.as_secs_f64() * 1000.0,
+        if deep_matches { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: "In-place kernels".to_string(),
+        num_qubits: n,
+        basic_time: sequential_time,
+        mt_time: parallel_time,
+        results_match: scattered_matches && deep_matches,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+pub fn test_diagonal_kernels(results: &mut Vec<BenchmarkResult>) {
+    print_section("Diagonal Kernels");
+
+    // Every diagonal gate, and a 3-qubit diagonal on scattered, unordered
+    // targets, after a layer that leaves no amplitude zero.
+    let phases: Vec<Complex<f64>> = (0..8)
+        .map(|k| Complex::new((0.4 * k as f64).cos(), (0.4 * k as f64).sin()))
+        .collect();
+    let mut diagonal = vec![Complex::new(0.0, 0.0); 64];
+    for (k, &phase) in phases.iter().enumerate() {
+        diagonal[k * 8 + k] = phase;
+    }
+    let phase_gate = CustomGate::from_matrix("D", Matrix::new(8, 8, diagonal));
+    let mut small = QuantumCircuit::new(8);
+    for q in 0..8 {
+        small.h(q).ry(q, 0.2 * q as f64);
+    }
+    small.barrier(&(0..8).collect::<Vec<_>>());
+    small
+        .z(0)
+        .s(7)
+        .t(3)
+        .rz(5, 0.9)
+        .p(1, 1.7)
+        .cz(2, 6)
+        .cp(7, 4, 0.6)
+        .apply_custom(phase_gate, &[6, 1, 4]);
+    let reference = small.clone().compute_with(Runtime::BasicRT).clone();
+    let single = RuntimeConfig::new().batched().precision(Precision::F32);
+    let small_matches = states_equal(small.clone().compute_with(Runtime::BatchedRT), &reference)
+        && states_equal(small.clone().compute_with(Runtime::SimdRT), &reference)
+        && states_equal(
+            small
+                .clone()
+                .compute_with_config(RuntimeConfig::new().batched().parallel().with_threshold(1)),
+            &reference,
+        )
+        && (1.0 - fidelity(small.compute_with_config(single), &reference)).abs() < 1e-5;
+    println!(
+        "Z/S/T/Rz/P/CZ/CP and a scattered 3-qubit diagonal: {}",
+        if small_matches { "✓" } else { "✗" }
+    );
+
+    // The same number of gates, diagonal or not, on a large state.
+    let n = 20;
+    let mut diagonal_layers = QuantumCircuit::new(n);
+    let mut dense_layers = QuantumCircuit::new(n);
+    for q in 0..n {
+        diagonal_layers.h(q);
+        dense_layers.h(q);
+    }
+    for layer in 0..20 {
+        for q in (layer % 2..n - 1).step_by(2) {
+            diagonal_layers.rz(q, 0.1 * layer as f64).cp(q, q + 1, 0.3);
+            dense_layers.rx(q, 0.1 * layer as f64).cry(q, q + 1, 0.3);
+        }
+    }
+    let start = Instant::now();
+    let diagonal_state = diagonal_layers
+        .clone()
+        .compute_with(Runtime::SimdRT)
+        .clone();
+    let diagonal_time = start.elapsed();
+    let start = Instant::now();
+    dense_layers.compute_with(Runtime::SimdRT);
+    let dense_time = start.elapsed();
+    let uniform = 1.0 / (1u64 << n) as f64;
+    let large_matches =
+        (0..diagonal_state.size()).all(|i| (diagonal_state.get(i).norm2() - uniform).abs() < 1e-12);
+    println!(
+        "{}-qubit, {} gates each: Rx/CRy={:.3}ms, Rz/CP={:.3}ms, Speedup={:.2}x, Match={}\n",
+        n,
+        diagonal_layers.operations().len(),
+        dense_time.as_secs_f64() * 1000.0,
+        diagonal_time.as_secs_f64() * 1000.0,
+        dense_time.as_secs_f64() / diagonal_time.as_secs_f64(),
+        if large_matches { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: "Diagonal kernels".to_string(),
+        num_qubits: n,
+        basic_time: dense_time,
+        mt_time: diagonal_time,
+        results_match: small_matches && large_matches,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+pub fn test_controlled_kernels(results: &mut Vec<BenchmarkResult>) {
+    print_section("Controlled Kernels");
+
+    // Controls above, below and between the targets, on the highest and
+    // lowest bits; the custom gate is controlled on its middle qubit.
+    let mut block = QuantumCircuit::new(3);
+    block.cry(1, 0, 0.8).crx(1, 2, 1.9).cswap(1, 0, 2);
+    let controlled = CustomGate::from_matrix("CU", block.unitary());
+    let n = 8;
+    let mut small = QuantumCircuit::new(n);
+    for q in 0..n {
+        small.h(q).rz(q, 0.3 * q as f64);
+    }
+    small.barrier(&(0..n).collect::<Vec<_>>());
+    small
+        .cnot(0, 7)
+        .cnot(7, 0)
+        .cnot(3, 4)
+        .ccnot(0, 7, 3)
+        .ccnot(2, 5, 1)
+        .cswap(6, 0, 4)
+        .crx(1, 6, 0.7)
+        .cry(7, 2, 1.2)
+        .apply_custom(controlled, &[5, 0, 6]);
+    let reference = small.clone().compute_with(Runtime::BasicRT).clone();
+    let threaded = RuntimeConfig::new().batched().parallel().with_threshold(1);
+    let single = RuntimeConfig::new().batched().precision(Precision::F32);
+    let small_matches = states_equal(small.clone().compute_with(Runtime::BatchedRT), &reference)
+        && states_equal(small.clone().compute_with(Runtime::SimdRT), &reference)
+        && states_equal(small.clone().compute_with_config(threaded), &reference)
+        && (1.0 - fidelity(small.compute_with_config(single), &reference)).abs() < 1e-5;
+    println!(
+        "CNOT/Toffoli/Fredkin/CRx/CRy and a controlled custom gate: {}",
+        if small_matches { "✓" } else { "✗" }
+    );
+
+    // CNOT and SWAP have the same sparsity, but only CNOT has a control.
+    let n = 20;
+    let mut cnots = QuantumCircuit::new(n);
+    let mut swaps = QuantumCircuit::new(n);
+    for q in 0..n {
+        cnots.h(q);
+        swaps.h(q);
+    }
+    for layer in 0..20 {
+        for q in (layer % 2..n - 1).step_by(2) {
+            cnots.cnot(q, q + 1);
+            swaps.swap(q, q + 1);
+        }
+    }
+    let start = Instant::now();
+    let cnot_state = cnots.clone().compute_with(Runtime::BatchedRT).clone();
+    let cnot_time = start.elapsed();
+    let start = Instant::now();
+    swaps.compute_with(Runtime::BatchedRT);
+    let swap_time = start.elapsed();
+    let threaded = cnots.clone().compute_with(Runtime::BatchedRTMT).clone();
+    let large_matches = states_equal(&cnot_state, &threaded);
+    println!(
+        "{}-qubit, {} gates each: SWAP={:.3}ms, CNOT={:.3}ms, Speedup={:.2}x, Match={}\n",
+        n,
+        cnots.operations().len(),
+        swap_time.as_secs_f64() * 1000.0,
+        cnot_time.as_secs_f64() * 1000.0,
+        swap_time.as_secs_f64() / cnot_time.as_secs_f64(),
+        if large_matches { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: "Controlled kernels".to_string(),
+        num_qubits: n,
+        basic_time: swap_time,
+        mt_time: cnot_time,
+        results_match: small_matches && large_matches,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+pub fn test_permutation_kernels(results: &mut Vec<BenchmarkResult>) {
+    print_section("Permutation Kernels");
+
+    // A 3-cycle and a transposition on scattered targets, X on the highest
+    // and lowest bits, and Y, whose phases keep it off the swapping path.
+    let zero = Complex::new(0.0, 0.0);
+    let mut shift = vec![zero; 64];
+    for (column, row) in [1, 2, 0, 3, 4, 5, 7, 6].into_iter().enumerate() {
+        shift[row * 8 + column] = Complex::new(1.0, 0.0);
+    }
+    let shift = CustomGate::from_matrix("P", Matrix::new(8, 8, shift));
+    let n = 8;
+    let mut small = QuantumCircuit::new(n);
+    for q in 0..n {
+        small.ry(q, 0.4 + 0.2 * q as f64).rz(q, 0.3 * q as f64);
+    }
+    small.barrier(&(0..n).collect::<Vec<_>>());
+    small
+        .x(0)
+        .x(7)
+        .swap(0, 7)
+        .swap(4, 3)
+        .cnot(6, 1)
+        .ccnot(4, 0, 5)
+        .cswap(2, 7, 0)
+        .y(3)
+        .apply_custom(shift, &[6, 2, 4]);
+    let reference = small.clone().compute_with(Runtime::BasicRT).clone();
+    let threaded = RuntimeConfig::new().batched().parallel().with_threshold(1);
+    let single = RuntimeConfig::new().batched().precision(Precision::F32);
+    let small_matches = states_equal(small.clone().compute_with(Runtime::BatchedRT), &reference)
+        && states_equal(small.clone().compute_with(Runtime::SimdRT), &reference)
+        && states_equal(small.clone().compute_with_config(threaded), &reference)
+        && (1.0 - fidelity(small.compute_with_config(single), &reference)).abs() < 1e-5;
+    println!(
+        "X/SWAP/CNOT/Toffoli/Fredkin/Y and a 3-qubit permutation: {}",
+        if small_matches { "✓" } else { "✗" }
+    );
+
+    // −SWAP has the structure of SWAP but must be multiplied through.
+    let mut negated = gates::SWAP.matrix.clone();
+    for entry in &mut negated.data {
+        *entry = Complex::new(-entry.real, -entry.imaginary);
+    }
+    let negated = CustomGate::from_matrix("-SWAP", negated);
+    let n = 20;
+    let mut swaps = QuantumCircuit::new(n);
+    let mut negated_swaps = QuantumCircuit::new(n);
+    for q in 0..n {
+        swaps.ry(q, 0.1 * q as f64);
+        negated_swaps.ry(q, 0.1 * q as f64);
+    }
+    for layer in 0..20 {
+        for q in (layer % 2..n - 1).step_by(2) {
+            let partner = (q + 1 + layer % (n - 1)) % n;
+            swaps.swap(q, partner);
+            negated_swaps.apply_custom(negated.clone(), &[q, partner]);
+        }
+    }
+    let start = Instant::now();
+    let swapped = swaps.clone().compute_with(Runtime::BatchedRT).clone();
+    let swap_time = start.elapsed();
+    let start = Instant::now();
+    let multiplied = negated_swaps.compute_with(Runtime::BatchedRT).clone();
+    let multiply_time = start.elapsed();
+    let large_matches = (1.0 - fidelity(&swapped, &multiplied)).abs() < 1e-10;
+    println!(
+        "{}-qubit, {} gates each: -SWAP={:.3}ms, SWAP={:.3}ms, Speedup={:.2}x, Match={}\n",
+        n,
+        swaps.operations().len(),
+        multiply_time.as_secs_f64() * 1000.0,
+        swap_time.as_secs_f64() * 1000.0,
+        multiply_time.as_secs_f64() / swap_time.as_secs_f64(),
+        if large_matches { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: "Permutation kernels".to_string(),
+        num_qubits: n,
+        basic_time: multiply_time,
+        mt_time: swap_time,
+        results_match: small_matches && large_matches,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+pub fn test_cache_blocking(results: &mut Vec<BenchmarkResult>) {
+    print_section("Cache-Blocked Kernel Runs");
+
+    // Layers on the last qubits run a block at a time; the gates on the
+    // first qubits in between end each run.
+    let layered = |n: usize| {
+        let mut c = QuantumCircuit::new(n);
+        for layer in 0..10 {
+            for q in 0..n {
+                c.ry(q, 0.1 * (q + layer) as f64);
+            }
+            for q in (layer % 2..n - 1).step_by(2) {
+                c.cnot(q, q + 1).cp(q + 1, q, 0.2);
+            }
+            c.ccnot(n - 1, n - 3, n - 2).cswap(n - 4, n - 1, n - 5);
+        }
+        c
+    };
+    let mut small_matches = true;
+    for n in [15, 16] {
+        let circuit = layered(n);
+        // One kernel per gate, so no run is ever blocked.
+        let reference = circuit.clone().compute_with(Runtime::BasicRTMT).clone();
+        let threaded = RuntimeConfig::new()
+            .batched()
+            .simd()
+            .parallel()
+            .with_threshold(1);
+        let single = RuntimeConfig::new()
+            .batched()
+            .simd()
+            .precision(Precision::F32);
+        let matches = states_equal(circuit.clone().compute_with(Runtime::BatchedRT), &reference)
+            && states_equal(circuit.clone().compute_with(Runtime::SimdRT), &reference)
+            && states_equal(circuit.clone().compute_with_config(threaded), &reference)
+            && states_equal(
+                circuit.clone().compute_with(Runtime::StructureAwareRT),
+                &reference,
+            )
+            && (1.0 - fidelity(circuit.clone().compute_with_config(single), &reference)).abs()
+                < 1e-5;
+        println!(
+            "{}-qubit layers against per-gate runs: {}",
+            n,
+            if matches { "✓" } else { "✗" }
+        );
+        small_matches &= matches;
+    }
+
+    let n = 20;
+    let circuit = layered(n);
+    let start = Instant::now();
+    let expected = circuit.clone().compute_with(Runtime::BasicRTMT).clone();
+    let per_gate_time = start.elapsed();
+    let start = Instant::now();
+    let state = circuit.clone().compute_with(Runtime::BatchedRT).clone();
+    let blocked_time = start.elapsed();
+    let large_matches = states_equal(&state, &expected);
+    println!(
+        "{}-qubit, {} gates: BasicRTMT={:.3}ms, BatchedRT={:.3}ms, Speedup={:.2}x, Match={}",
+        n,
+        circuit.operations().len(),
+        per_gate_time.as_secs_f64() * 1000.0,
+        blocked_time.as_secs_f64() * 1000.0,
+        per_gate_time.as_secs_f64() / blocked_time.as_secs_f64(),
+        if large_matches { "✓" } else { "✗" }
+    );
+
+    // Long runs on the first qubits, the largest strides: swapped into a
+    // block, run there, and swapped back.
+    let high = |n: usize| {
+        let mut c = QuantumCircuit::new(n);
+        for layer in 0..20 {
+            for q in 0..4 {
+                c.ry(q, 0.1 * (q + layer) as f64).rz(q, 0.3);
+            }
+            c.cnot(0, 1).cnot(2, 3).cz(1, 2).cnot(3, n - 1);
+        }
+        c
+    };
+    let mut high_matches = true;
+    let mut high_times = (Default::default(), Default::default());
+    for n in [16, 20] {
+        let circuit = high(n);
+        let start = Instant::now();
+        let expected = circuit.clone().compute_with(Runtime::BasicRTMT).clone();
+        let per_gate_time = start.elapsed();
+        let start = Instant::now();
+        let state = circuit.clone().compute_with(Runtime::BatchedRT).clone();
+        let blocked_time = start.elapsed();
+        let threaded = RuntimeConfig::new().batched().parallel().with_threshold(1);
+        let matches = states_equal(&state, &expected)
+            && states_equal(circuit.clone().compute_with_config(threaded), &expected);
+        println!(
+            "{}-qubit runs on qubits 0-3: BasicRTMT={:.3}ms, BatchedRT={:.3}ms, Speedup={:.2}x, Match={}",
+            n,
+            per_gate_time.as_secs_f64() * 1000.0,
+            blocked_time.as_secs_f64() * 1000.0,
+            per_gate_time.as_secs_f64() / blocked_time.as_secs_f64(),
+            if matches { "✓" } else { "✗" }
+        );
+        high_matches &= matches;
+        high_times = (per_gate_time, blocked_time);
+    }
+    println!();
+
+    results.push(BenchmarkResult {
+        name: "Cache-blocked runs".to_string(),
+        num_qubits: n,
+        basic_time: per_gate_time,
+        mt_time: blocked_time,
+        results_match: small_matches && large_matches,
+        peak_bytes: memory::take_peak(),
+    });
+    results.push(BenchmarkResult {
+        name: "Cache-blocked runs, high qubits".to_string(),
+        num_qubits: 20,
+        basic_time: high_times.0,
+        mt_time: high_times.1,
+        results_match: high_matches,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+pub fn test_chunked_parallel_gates(results: &mut Vec<BenchmarkResult>) {
+    print_section("Chunked Parallel Gate Application");
+
+    // Gates whose targets straddle the chunk size, so that some target
+    // bits are fixed per chunk and others vary within it.
+    let straddling = |n: usize| {
+        let mut c = QuantumCircuit::new(n);
+        for layer in 0..4 {
+            for q in 0..n {
+                c.ry(q, 0.1 * (q + layer) as f64);
+            }
+            c.ccnot(0, n - 1, n / 2)
+                .cswap(n - 1, 1, n - 2)
+                .cry(n - 1, 0, 0.3)
+                .crx(0, n - 1, 0.4)
+                .ccnot(n / 2, 1, n - 3)
+                .swap(0, n - 1);
+        }
+        c
+    };
+
+    let mut all_match = true;
+    let mut last = None;
+    for n in [10, 14, 16, 18] {
+        let circuit = straddling(n);
+        let start = Instant::now();
+        let reference = circuit.clone().compute_with(Runtime::BatchedRT).clone();
+        let in_place_time = start.elapsed();
+        let start = Instant::now();
+        let state = circuit.clone().compute_with(Runtime::BasicRTMT).clone();
+        let chunked_time = start.elapsed();
+        let matches = states_equal(&state, &reference);
+        println!(
+            "{}-qubit: BatchedRT={:.3}ms, BasicRTMT={:.3}ms, Match={}",
+            n,
+            in_place_time.as_secs_f64() * 1000.0,
+            chunked_time.as_secs_f64() * 1000.0,
+            if matches { "✓" } else { "✗" }
+        );
+        all_match &= matches;
+        last = Some((n, in_place_time, chunked_time));
+    }
+    println!();
+
+    let (n, in_place_time, chunked_time) = last.unwrap();
+    results.push(BenchmarkResult {
+        name: "Chunked parallel gates".to_string(),
+        num_qubits: n,
+        basic_time: in_place_time,
+        mt_time: chunked_time,
+        results_match: all_match,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+/// The matrix of `op` built from scratch, for checking cached kernels.
+fn fresh_matrix(op: &GateOp) -> Matrix<Complex<f64>> {
+    match op {
+        GateOp::H(_) => gates::HADAMARD.matrix.clone(),
+        GateOp::CNOT(_, _) => gates::CNOT.matrix.clone(),
+        GateOp::Rx(_, theta) => gates::rx_matrix(*theta),
+        GateOp::Rz(_, theta) => gates::rz_matrix(*theta),
+        GateOp::CRy(_, _, theta) => gates::cry_matrix(*theta),
+        GateOp::U3(_, theta, phi, lambda) => gates::u3_matrix(*theta, *phi, *lambda),
+        _ => unreachable!("not used by the cache test"),
+    }
+}
+
+pub fn test_parametric_kernel_cache(results: &mut Vec<BenchmarkResult>) {
+    print_section("Parametric Kernel Cache");
+
+    // A layered ansatz reusing a few angles, as in a parameter sweep.
+    let n = 10;
+    let layers = 200;
+    let angles = [0.3, -1.2, PI / 5.0, 2.9];
+    let builder = || {
+        let mut circuit = QuantumCircuit::new(n);
+        for q in 0..n {
+            circuit.h(q);
+        }
+        for layer in 0..layers {
+            let theta = angles[layer % angles.len()];
+            for q in 0..n {
+                circuit.rx(q, theta).rz(q, -theta);
+            }
+            for q in 0..n - 1 {
+                circuit.cry(q, q + 1, theta);
+            }
+            circuit.u3(layer % n, theta, 0.5 * theta, PI - theta);
+            circuit.cnot(layer % n, (layer + 3) % n);
+        }
+        circuit
+    };
+    let circuit = builder();
+
+    let start = Instant::now();
+    let batch = Runtime::build_kernel_batch(n, circuit.operations());
+    let build_time = start.elapsed();
+
+    let kernels_match = batch.kernels().len() == circuit.operations().len()
+        && batch
+            .kernels()
+            .iter()
+            .zip(circuit.operations())
+            .all(|(kernel, op)| {
+                let fresh = fresh_matrix(op);
+                kernel.targets == op.quantum_targets()
+                    && kernel.matrix.data.iter().zip(&fresh.data).all(|(a, b)| {
+                        (a.real - b.real).abs() < 1e-12 && (a.imaginary - b.imaginary).abs() < 1e-12
+                    })
+            });
+
+    let mut basic = builder();
+    let start = Instant::now();
+    basic.compute_with(Runtime::SimdRT);
+    let simd_time = start.elapsed();
+    let mut batched = builder();
+    let start = Instant::now();
+    batched.compute_with(Runtime::BatchedRT);
+    let batched_time = start.elapsed();
+    let states_match = states_equal(basic.state(), batched.state());
+
+    println!(
+        "{} operations, {} distinct angles: batch built in {:.3}ms",
+        circuit.operations().len(),
+        angles.len(),
+        build_time.as_secs_f64() * 1000.0
+    );
+    println!(
+        "Cached kernels match freshly built matrices: {}",
+        if kernels_match { "✓" } else { "✗" }
+    );
+    println!(
+        "BatchedRT matches SimdRT: {}\n",
+        if states_match { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: format!("{}-qubit cached ansatz", n),
+        num_qubits: n,
+        basic_time: simd_time,
+        mt_time: batched_time,
+        results_match: kernels_match && states_match,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
 pub fn test_structure_aware(results: &mut Vec<BenchmarkResult>) {
     print_section("Structure-Aware Kernel Optimisation");
 
@@ -245,9 +867,33 @@ pub fn test_structure_aware(results: &mut Vec<BenchmarkResult>) {
 
     println!("\nExecution layers: {}", batch.num_layers());
     for (i, layer) in batch.layers().iter().enumerate() {
-        let names: Vec<_> = layer.kernels.iter().map(|k| k.name.as_str()).collect();
+        let names: Vec<_> = layer.kernels.iter().map(|k| k.label()).collect();
         println!("  Layer {}: {:?}", i, names);
     }
+    println!("\n{}", KernelRenderer::from_structure_aware(&batch));
+
+    let mut layered = QuantumCircuit::new(6);
+    layered.section("prep").h(0).h(1).h(2).h(3).h(4).h(5);
+    layered
+        .section("mix")
+        .cnot(0, 1)
+        .cnot(2, 3)
+        .t(4)
+        .ccnot(1, 2, 5);
+    layered
+        .section("phase")
+        .rz(0, PI / 3.0)
+        .cz(3, 4)
+        .cswap(5, 0, 1);
+    let mut layered_batch = Runtime::build_structure_aware_batch(6, layered.operations());
+    layered_batch.optimise();
+    let mut state = vec![Complex::new(0.0, 0.0); 1 << 6];
+    state[0] = Complex::new(1.0, 0.0);
+    let profile = layered_batch.profile(&mut state);
+    println!(
+        "Layer chart (6 qubits, profiled):\n{}",
+        GanttRenderer::new(&layered_batch).with_profile(&profile)
+    );
 
     let stats = batch.stats();
     println!("\nStats: {}", stats);
@@ -272,15 +918,17 @@ pub fn test_structure_aware(results: &mut Vec<BenchmarkResult>) {
 
     results.push(BenchmarkResult {
         name: format!("SA: Commuting ({}→{})", original, optimised),
+        num_qubits: sa.num_qubits(),
         basic_time,
         mt_time: sa_time,
         results_match: match_result,
+        peak_bytes: memory::take_peak(),
     });
 
     println!();
     print_section("Structure-Aware vs Other Runtimes");
 
-    let test_cases: Vec<(&str, Box<dyn Fn() -> QuantumCircuit>)> = vec![
+    let test_cases: Vec<(&str, CircuitBuilder)> = vec![
         (
             "Diagonal-heavy (5q)",
             Box::new(|| {
@@ -341,9 +989,11 @@ pub fn test_structure_aware(results: &mut Vec<BenchmarkResult>) {
 
         results.push(BenchmarkResult {
             name: format!("SA: {}", name),
+            num_qubits: sa.num_qubits(),
             basic_time: batched_time,
             mt_time: sa_time,
             results_match: match_result,
+            peak_bytes: memory::take_peak(),
         });
     }
     println!();
@@ -402,9 +1052,11 @@ pub fn test_composable_runtime(results: &mut Vec<BenchmarkResult>) {
 
         results.push(BenchmarkResult {
             name: format!("Config: {}", name),
+            num_qubits: circuit.num_qubits(),
             basic_time: time,
             mt_time: time,
             results_match: match_result,
+            peak_bytes: memory::take_peak(),
         });
     }
 
@@ -418,3 +1070,390 @@ pub fn test_composable_runtime(results: &mut Vec<BenchmarkResult>) {
     println!("  {}", Runtime::optimal());
     println!();
 }
+
+pub fn test_gate_classification(results: &mut Vec<BenchmarkResult>) {
+    print_section("Gate Classification by Matrix");
+
+    // Names are deliberately misleading: only the matrix decides the class.
+    let cases = [
+        (
+            "Sx",
+            gates::SX_GATE.matrix.clone(),
+            1,
+            GateType::NonDiagonal,
+        ),
+        ("S", gates::S_GATE.matrix.clone(), 1, GateType::Diagonal),
+        (
+            "X",
+            gates::PAULI_X.matrix.clone(),
+            1,
+            GateType::AntiDiagonal,
+        ),
+        (
+            "Y",
+            gates::PAULI_Y.matrix.clone(),
+            1,
+            GateType::AntiDiagonal,
+        ),
+        ("Rz", gates::rz_matrix(0.3), 1, GateType::Diagonal),
+        (
+            "CZ_like",
+            gates::HADAMARD.matrix.clone(),
+            1,
+            GateType::NonDiagonal,
+        ),
+        ("CZ", gates::CZ.matrix.clone(), 2, GateType::Diagonal),
+        ("CNOT", gates::CNOT.matrix.clone(), 2, GateType::Permutation),
+        ("SWAP", gates::SWAP.matrix.clone(), 2, GateType::Permutation),
+        (
+            "ISWAP",
+            gates::ISWAP.matrix.clone(),
+            2,
+            GateType::Permutation,
+        ),
+        (
+            "SQRT_SWAP",
+            gates::SQRT_SWAP.matrix.clone(),
+            2,
+            GateType::NonDiagonal,
+        ),
+        ("CRx", gates::crx_matrix(0.7), 2, GateType::Controlled),
+        ("CRy", gates::cry_matrix(0.7), 2, GateType::Controlled),
+        ("CP", gates::cp_matrix(0.7), 2, GateType::Diagonal),
+        (
+            "CCNOT",
+            gates::TOFFOLI.matrix.clone(),
+            3,
+            GateType::Permutation,
+        ),
+        (
+            "CSWAP",
+            gates::FREDKIN.matrix.clone(),
+            3,
+            GateType::Permutation,
+        ),
+    ];
+
+    let mut all_correct = true;
+    for (name, matrix, width, expected) in cases {
+        let kernel = Kernel::new(name, matrix, (0..width).collect());
+        let correct = kernel.gate_type == expected;
+        all_correct &= correct;
+        println!(
+            "  {:10} {:?}{}",
+            name,
+            kernel.gate_type,
+            if correct {
+                ""
+            } else {
+                "  ✗ expected different class"
+            }
+        );
+    }
+
+    // Fusing keeps the class in step with the fused matrix.
+    let x = Kernel::new("X", gates::PAULI_X.matrix.clone(), vec![0]);
+    let fused = x.fuse(&x).unwrap();
+    let fused_correct = fused.gate_type == GateType::Diagonal;
+    println!("  X+X fused  {:?}", fused.gate_type);
+    println!(
+        "Classes follow the matrices: {}\n",
+        if all_correct && fused_correct {
+            "✓"
+        } else {
+            "✗"
+        }
+    );
+
+    results.push(BenchmarkResult {
+        name: "Gate classification".to_string(),
+        num_qubits: 3,
+        basic_time: std::time::Duration::from_micros(0),
+        mt_time: std::time::Duration::from_micros(0),
+        results_match: all_correct && fused_correct,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+pub fn test_commutation(results: &mut Vec<BenchmarkResult>) {
+    print_section("Matrix-Level Commutation");
+
+    let kernel = |name: &str, matrix: Matrix<Complex<f64>>, targets: &[usize]| {
+        Kernel::new(name, matrix, targets.to_vec())
+    };
+    let z0 = kernel("Z", gates::PAULI_Z.matrix.clone(), &[0]);
+    let x0 = kernel("X", gates::PAULI_X.matrix.clone(), &[0]);
+    let x1 = kernel("X", gates::PAULI_X.matrix.clone(), &[1]);
+    let rx1 = kernel("Rx", gates::rx_matrix(0.4), &[1]);
+    let cnot01 = kernel("CNOT", gates::CNOT.matrix.clone(), &[0, 1]);
+    let cnot02 = kernel("CNOT", gates::CNOT.matrix.clone(), &[0, 2]);
+    let cnot12 = kernel("CNOT", gates::CNOT.matrix.clone(), &[1, 2]);
+    let cz01 = kernel("CZ", gates::CZ.matrix.clone(), &[0, 1]);
+    let cz12 = kernel("CZ", gates::CZ.matrix.clone(), &[1, 2]);
+    let crx01 = kernel("CRx", gates::crx_matrix(0.9), &[0, 1]);
+    let pairs = [
+        ("Z on a CNOT control", &z0, &cnot01, true),
+        ("X on a CNOT target", &x1, &cnot01, true),
+        ("X on a CNOT control", &x0, &cnot01, false),
+        ("CZs sharing a qubit", &cz01, &cz12, true),
+        ("CNOTs sharing a control", &cnot01, &cnot02, true),
+        ("CNOT target into control", &cnot01, &cnot12, false),
+        ("Rx on a CRx target", &rx1, &crx01, true),
+        ("CZ across a CNOT target", &cz12, &cnot01, false),
+    ];
+    let mut pairs_correct = true;
+    for (description, a, b, expected) in pairs {
+        let commutes = a.commutes_with(b);
+        pairs_correct &= commutes == expected && b.commutes_with(a) == expected;
+        println!(
+            "  {:26} {}{}",
+            description,
+            if commutes {
+                "commute"
+            } else {
+                "do not commute"
+            },
+            if commutes == expected { "" } else { "  ✗" }
+        );
+    }
+
+    // Diagonal-heavy circuits with CNOT fan-outs leave many gates free to
+    // move past the ones they share qubits with.
+    let num_qubits = 8;
+    let mut rng = StdRng::seed_from_u64(3500);
+    let mut circuit = QuantumCircuit::new(num_qubits);
+    for q in 0..num_qubits {
+        circuit.h(q);
+    }
+    for _ in 0..120 {
+        let a = rng.random_range(0..num_qubits);
+        let b = (a + rng.random_range(1..num_qubits)) % num_qubits;
+        match rng.random_range(0..5) {
+            0 => circuit.cz(a, b),
+            1 => circuit.cp(a, b, rng.random::<f64>()),
+            2 => circuit.rz(a, rng.random::<f64>()),
+            3 => circuit.t(a),
+            _ => circuit.cnot(a, b),
+        };
+    }
+    let depth = circuit
+        .schedule(&GateDurations::unit(), SchedulePolicy::Asap)
+        .total_time();
+
+    let start = Instant::now();
+    let mut batch = Runtime::build_structure_aware_batch(num_qubits, circuit.operations());
+    batch.optimise();
+    let elapsed = start.elapsed();
+
+    let mut amplitudes = vec![Complex::new(0.0, 0.0); 1 << num_qubits];
+    amplitudes[0] = Complex::new(1.0, 0.0);
+    batch.execute_layered(&mut amplitudes);
+    let layered_matches = states_equal(
+        &QuantumState::new(amplitudes),
+        circuit.compute_with(Runtime::BasicRT),
+    );
+    println!(
+        "{} gates on {} qubits: depth {}, {} execution layers (optimised in {:.2}ms)",
+        circuit.operations().len(),
+        num_qubits,
+        depth,
+        batch.num_layers(),
+        elapsed.as_secs_f64() * 1000.0
+    );
+    println!(
+        "Layered execution matches the circuit: {}",
+        if layered_matches { "✓" } else { "✗" }
+    );
+    let fewer_layers = (batch.num_layers() as f64) < depth;
+    println!(
+        "Commutation analysis and known pairs: {}\n",
+        if pairs_correct && fewer_layers {
+            "✓"
+        } else {
+            "✗"
+        }
+    );
+
+    results.push(BenchmarkResult {
+        name: "SA: Matrix commutation".to_string(),
+        num_qubits,
+        basic_time: elapsed,
+        mt_time: elapsed,
+        results_match: pairs_correct && fewer_layers && layered_matches,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+pub fn test_block_fusion(results: &mut Vec<BenchmarkResult>) {
+    print_section("Two- and Three-Qubit Block Fusion");
+
+    let mut small_matches = true;
+    for seed in 0..4 {
+        let circuit = library::random_circuit(8, 10, 3549 + seed, library::GateSet::Universal);
+        let mut batch = Runtime::build_structure_aware_batch(8, circuit.operations());
+        batch.optimise();
+        let widest = batch
+            .kernels()
+            .iter()
+            .map(|k| k.targets.len())
+            .max()
+            .unwrap_or(0);
+        let reference = circuit.clone().compute_with(Runtime::BasicRT).clone();
+        let threaded = RuntimeConfig::new()
+            .structure_aware()
+            .parallel()
+            .with_threshold(1);
+        let single = RuntimeConfig::optimal().precision(Precision::F32);
+        let matches = widest <= MAX_FUSED_QUBITS
+            && states_equal(
+                circuit.clone().compute_with(Runtime::StructureAwareRT),
+                &reference,
+            )
+            && states_equal(circuit.clone().compute_with_config(threaded), &reference)
+            && (1.0 - fidelity(circuit.clone().compute_with_config(single), &reference)).abs()
+                < 1e-5;
+        println!(
+            "8-qubit random circuit {}: {} gates -> {} kernels, widest {}: {}",
+            seed,
+            circuit.operations().len(),
+            batch.len(),
+            widest,
+            if matches { "✓" } else { "✗" }
+        );
+        small_matches &= matches;
+    }
+
+    let n = 20;
+    let circuit = library::random_circuit(n, 20, 3549, library::GateSet::Universal);
+    let mut plain = Runtime::build_kernel_batch(n, circuit.operations());
+    plain.optimize();
+    let mut blocks = Runtime::build_structure_aware_batch(n, circuit.operations());
+    blocks.optimise();
+    let start = Instant::now();
+    let expected = circuit.clone().compute_with(Runtime::SimdRT).clone();
+    let batched_time = start.elapsed();
+    let start = Instant::now();
+    let state = circuit
+        .clone()
+        .compute_with(Runtime::StructureAwareRT)
+        .clone();
+    let fused_time = start.elapsed();
+    let large_matches = states_equal(&state, &expected);
+    println!(
+        "{}-qubit random circuit: Batched {} kernels={:.3}ms, fused {} kernels={:.3}ms, Speedup={:.2}x, Match={}\n",
+        n,
+        plain.len(),
+        batched_time.as_secs_f64() * 1000.0,
+        blocks.len(),
+        fused_time.as_secs_f64() * 1000.0,
+        batched_time.as_secs_f64() / fused_time.as_secs_f64(),
+        if large_matches { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: "Block fusion".to_string(),
+        num_qubits: n,
+        basic_time: batched_time,
+        mt_time: fused_time,
+        results_match: small_matches && large_matches,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+pub fn test_barrier_fence(results: &mut Vec<BenchmarkResult>) {
+    print_section("Barriers as Optimisation Fences");
+
+    let chain = |fenced: bool| {
+        let mut c = QuantumCircuit::new(2);
+        c.h(1).h(0).t(0).s(0);
+        if fenced {
+            c.barrier_all();
+        }
+        c.x(0).z(0).h(0).t(1);
+        c
+    };
+    let counts = |circuit: &QuantumCircuit| {
+        let mut batch = Runtime::build_kernel_batch(2, circuit.operations());
+        batch.optimize();
+        let mut structured = Runtime::build_structure_aware_batch(2, circuit.operations());
+        structured.optimise();
+        (batch.len(), structured.len())
+    };
+    let (open, fenced) = (counts(&chain(false)), counts(&chain(true)));
+    println!(
+        "Kernels after fusion without / with a barrier: batched {} / {}, structure-aware {} / {}",
+        open.0, fenced.0, open.1, fenced.1
+    );
+    let fewer_fusions = fenced.0 > open.0 && fenced.1 > open.1;
+
+    // Fusing across a section boundary would join the two titles with `+`.
+    let mut sections = QuantumCircuit::new(3);
+    sections.section("prep").h(0).t(0).h(1).h(2);
+    sections.section("mix").t(0).s(1).cnot(1, 2).rz(0, PI / 3.0);
+    let mut structured = Runtime::build_structure_aware_batch(3, sections.operations());
+    structured.optimise();
+    let mut batch = Runtime::build_kernel_batch(3, sections.operations());
+    batch.optimize();
+    let unmixed = structured
+        .kernels()
+        .iter()
+        .chain(batch.kernels())
+        .all(|k| !k.tag.as_deref().unwrap_or("").contains('+'));
+    println!("{}", KernelRenderer::from_structure_aware(&structured));
+
+    // Commuting gates on disjoint qubits share a layer unless fenced apart.
+    let mut split = QuantumCircuit::new(2);
+    split.t(0).h(0).barrier(&[0, 1]).t(1).h(1);
+    let mut layered = Runtime::build_structure_aware_batch(2, split.operations());
+    layered.optimise();
+    let layers_kept = layered.num_layers() == 2;
+    println!(
+        "Section tags never merged: {}, fenced layers kept apart: {}",
+        if unmixed { "✓" } else { "✗" },
+        if layers_kept { "✓" } else { "✗" }
+    );
+
+    let mut rng = StdRng::seed_from_u64(3515);
+    let n = 6;
+    let mut random = QuantumCircuit::new(n);
+    for _ in 0..120 {
+        let q = rng.random_range(0..n);
+        match rng.random_range(0..5) {
+            0 => random.h(q),
+            1 => random.rz(q, rng.random_range(0.0..PI)),
+            2 => random.cnot(q, (q + 1) % n),
+            3 => random.t(q),
+            _ => random.barrier(&[q, (q + 2) % n]),
+        };
+    }
+    let mut basic = random.clone();
+    let basic_time = {
+        let start = Instant::now();
+        basic.compute_with(Runtime::BasicRT);
+        start.elapsed()
+    };
+    let start = Instant::now();
+    let mut agree = true;
+    for runtime in [
+        Runtime::BatchedRT,
+        Runtime::SimdRTMT,
+        Runtime::StructureAwareRT,
+    ] {
+        let mut circuit = random.clone();
+        agree &= states_equal(basic.state(), circuit.compute_with(runtime));
+    }
+    let fenced_time = start.elapsed() / 3;
+    println!(
+        "Random circuit with barriers agrees across runtimes: {}\n",
+        if agree { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: "Barrier fences".to_string(),
+        num_qubits: n,
+        basic_time,
+        mt_time: fenced_time,
+        results_match: fewer_fusions && unmixed && layers_kept && agree,
+        peak_bytes: memory::take_peak(),
+    });
+}
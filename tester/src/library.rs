@@ -0,0 +1,875 @@
+use crate::common::{print_circuit, print_section, states_equal, BenchmarkResult};
+use crate::memory;
+use libpsi_core::{
+    complex, fidelity, library, library::Entanglement, library::Entangler, library::GateSet,
+    library::Rotation, Complex, CustomGate, CustomGateBuilder, GateOp, Matrix, Pauli,
+    QuantumCircuit, QuantumState, Runtime, RuntimeConfig, SparsePauliOp, TimeEvolution, Vector,
+};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::f64::consts::PI;
+use std::sync::Arc;
+use std::time::Instant;
+
+pub fn run_all(results: &mut Vec<BenchmarkResult>) {
+    println!("═══════════════════════════════════════════════════════════════");
+    println!("                 ALGORITHM LIBRARY TESTS");
+    println!("═══════════════════════════════════════════════════════════════\n");
+
+    test_qft(results);
+    test_grover(results);
+    test_mcx(results);
+    test_phase_estimation(results);
+    test_qaoa(results);
+    test_ansatz_templates(results);
+    test_random_circuit(results);
+    test_prepare_state(results);
+}
+
+/// A fixed entangled, non-basis input on `n` qubits, so transforms are
+/// compared on more than one column.
+fn generic_input(n: usize) -> QuantumCircuit {
+    let mut circuit = QuantumCircuit::new(n);
+    for q in 0..n {
+        circuit.ry(q, 0.4 + 0.3 * q as f64).rz(q, 0.2 * q as f64);
+    }
+    for q in 0..n - 1 {
+        circuit.cnot(q, q + 1);
+    }
+    circuit
+}
+
+/// `input` followed by `transform` on all qubits.
+fn run_after(input: &QuantumCircuit, transform: &QuantumCircuit, runtime: Runtime) -> QuantumState {
+    let n = input.num_qubits();
+    let mut circuit = input.clone();
+    circuit.append(transform, &(0..n).collect::<Vec<_>>());
+    circuit.compute_with(runtime).clone()
+}
+
+/// `Σₖ e^{2πi jk/2ⁿ} |k⟩ / √2ⁿ`.
+fn fourier_state(n: usize, j: usize) -> QuantumState {
+    let dim = 1 << n;
+    let scale = 1.0 / (dim as f64).sqrt();
+    QuantumState::new(
+        (0..dim)
+            .map(|k| {
+                let angle = 2.0 * PI * ((j * k) % dim) as f64 / dim as f64;
+                complex!(scale * angle.cos(), scale * angle.sin())
+            })
+            .collect(),
+    )
+}
+
+pub fn test_qft(results: &mut Vec<BenchmarkResult>) {
+    print_section("Quantum Fourier Transform");
+
+    print_circuit(&library::qft(3));
+
+    let mut basis_match = true;
+    for n in 1..=5 {
+        for j in 0..1 << n {
+            let mut input = QuantumCircuit::new(n);
+            for q in 0..n {
+                if j >> (n - 1 - q) & 1 == 1 {
+                    input.x(q);
+                }
+            }
+            let state = run_after(&input, &library::qft(n), Runtime::BasicRT);
+            basis_match &= states_equal(&state, &fourier_state(n, j));
+        }
+    }
+    println!(
+        "qft(n)|j⟩ matches Σₖ e^(2πi jk/2ⁿ)|k⟩/√2ⁿ for every j, n = 1..5: {}",
+        if basis_match { "✓" } else { "✗" }
+    );
+
+    let n = 6;
+    let input = generic_input(n);
+    let qft = library::qft(n);
+    let mut as_gate = QuantumCircuit::new(n);
+    as_gate.custom(&Arc::new(library::qft_gate(n)), &(0..n).collect::<Vec<_>>());
+    let mut inverse_gate = QuantumCircuit::new(n);
+    inverse_gate.custom(
+        &Arc::new(library::iqft_gate(n)),
+        &(0..n).collect::<Vec<_>>(),
+    );
+    let mut roundtrip = qft.clone();
+    roundtrip.append(&library::iqft(n), &(0..n).collect::<Vec<_>>());
+    let mut gate_roundtrip = as_gate.clone();
+    gate_roundtrip.append(&inverse_gate, &(0..n).collect::<Vec<_>>());
+
+    let expected = run_after(&input, &qft, Runtime::BasicRT);
+    let gate_matches = states_equal(&run_after(&input, &as_gate, Runtime::BasicRT), &expected);
+    let untouched = input.clone().compute_with(Runtime::BasicRT).clone();
+    let inverse_matches =
+        states_equal(&run_after(&input, &roundtrip, Runtime::BasicRT), &untouched)
+            && states_equal(
+                &run_after(&input, &gate_roundtrip, Runtime::BasicRT),
+                &untouched,
+            );
+    println!(
+        "qft_gate({0}) matches qft({0}) {1}, iqft undoes qft as circuits and as gates {2}",
+        n,
+        if gate_matches { "✓" } else { "✗" },
+        if inverse_matches { "✓" } else { "✗" }
+    );
+
+    let n = 10;
+    let input = generic_input(n);
+    let exact = run_after(&input, &library::qft(n), Runtime::StructureAwareRT);
+    let mut previous = 0.0;
+    let mut fidelity_grows = true;
+    for degree in [1, 2, 3, 4, n - 1] {
+        let approximate = library::approximate_qft(n, degree);
+        let fidelity = fidelity(
+            &run_after(&input, &approximate, Runtime::StructureAwareRT),
+            &exact,
+        );
+        println!(
+            "  approximate_qft({}, {}): {:>3} gates, fidelity {:.6}",
+            n,
+            degree,
+            approximate.operations().len(),
+            fidelity
+        );
+        fidelity_grows &= fidelity >= previous - 1e-12;
+        previous = fidelity;
+    }
+    let exact_at_full_degree = previous > 1.0 - 1e-12;
+    println!(
+        "Fidelity grows with the degree {}, degree n - 1 is exact {}",
+        if fidelity_grows { "✓" } else { "✗" },
+        if exact_at_full_degree { "✓" } else { "✗" }
+    );
+
+    let qft = library::qft(n);
+    let start = Instant::now();
+    let basic = run_after(&input, &qft, Runtime::BasicRT);
+    let basic_time = start.elapsed();
+    let start = Instant::now();
+    let mt = run_after(&input, &qft, Runtime::StructureAwareMT);
+    let mt_time = start.elapsed();
+    let runtimes_match = states_equal(&basic, &mt);
+    println!(
+        "qft({}) on BasicRT and StructureAwareMT agree: {}\n",
+        n,
+        if runtimes_match { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: format!("QFT ({} qubits)", n),
+        num_qubits: n,
+        basic_time,
+        mt_time,
+        results_match: basis_match
+            && gate_matches
+            && inverse_matches
+            && fidelity_grows
+            && exact_at_full_degree
+            && runtimes_match,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+/// Probability of measuring one of `states`.
+fn probability_of(state: &QuantumState, states: &[usize]) -> f64 {
+    states.iter().map(|&i| state.get(i).norm2()).sum()
+}
+
+pub fn test_grover(results: &mut Vec<BenchmarkResult>) {
+    print_section("Grover Search");
+
+    let n = 4;
+    let marked = [5, 10];
+    let oracle = library::phase_oracle(n, &marked);
+    print_circuit(&oracle);
+    let input = generic_input(n);
+    let before = input.clone().compute_with(Runtime::BasicRT).clone();
+    let after = run_after(&input, &oracle, Runtime::BasicRT);
+    let oracle_matches = (0..1 << n).all(|i| {
+        let sign = if marked.contains(&i) { -1.0 } else { 1.0 };
+        (after.get(i) - before.get(i) * complex!(sign, 0.0)).abs() < 1e-10
+    });
+
+    // On |s⟩ itself the diffusion is the identity up to a global phase.
+    let mut superposition = QuantumCircuit::new(n);
+    for q in 0..n {
+        superposition.h(q);
+    }
+    let plus = superposition.clone().compute_with(Runtime::BasicRT).clone();
+    let reflected = run_after(&superposition, &library::diffusion(n), Runtime::BasicRT);
+    let diffusion_matches = fidelity(&reflected, &plus) > 1.0 - 1e-9;
+    println!(
+        "phase_oracle({}, {:?}) flips exactly the marked amplitudes {}, diffusion fixes |s⟩ {}",
+        n,
+        marked,
+        if oracle_matches { "✓" } else { "✗" },
+        if diffusion_matches { "✓" } else { "✗" }
+    );
+
+    // After k rounds the marked states hold sin²((2k + 1)θ), sin²θ = M/N.
+    let n = 8;
+    let marked = [3, 77, 200];
+    let oracle = library::phase_oracle(n, &marked);
+    let theta = (marked.len() as f64 / (1 << n) as f64).sqrt().asin();
+    let mut amplitudes_match = true;
+    for k in 0..=library::grover_iterations(n, marked.len()) {
+        let mut search = library::grover_search(&oracle, k);
+        let found = probability_of(search.compute_with(Runtime::StructureAwareRT), &marked);
+        amplitudes_match &= (found - ((2 * k + 1) as f64 * theta).sin().powi(2)).abs() < 1e-9;
+    }
+    let iterations = library::grover_iterations(n, marked.len());
+    let search = library::grover_search(&oracle, iterations);
+
+    let start = Instant::now();
+    let basic = search.clone().compute_with(Runtime::BasicRT).clone();
+    let basic_time = start.elapsed();
+    let start = Instant::now();
+    let mt = search
+        .clone()
+        .compute_with(Runtime::StructureAwareMT)
+        .clone();
+    let mt_time = start.elapsed();
+    let found = probability_of(&mt, &marked);
+    let runtimes_match = states_equal(&basic, &mt);
+    println!(
+        "{} of {} states marked: probability sin²((2k + 1)θ) after every k ≤ {} {}, {:.4} after {} rounds {}\n",
+        marked.len(),
+        1 << n,
+        iterations,
+        if amplitudes_match { "✓" } else { "✗" },
+        found,
+        iterations,
+        if found > 0.9 && runtimes_match {
+            "✓"
+        } else {
+            "✗"
+        }
+    );
+
+    results.push(BenchmarkResult {
+        name: format!("Grover search ({} qubits)", n),
+        num_qubits: n,
+        basic_time,
+        mt_time,
+        results_match: oracle_matches
+            && diffusion_matches
+            && amplitudes_match
+            && found > 0.9
+            && runtimes_match,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+pub fn test_mcx(results: &mut Vec<BenchmarkResult>) {
+    print_section("Multi-Controlled X");
+
+    // Against the amplitudes of the input permuted by hand, on enough
+    // qubits that the multi-threaded runtimes apply it in place.
+    let n = 9;
+    let input = generic_input(n);
+    let before = input.clone().compute_with(Runtime::BasicRT).clone();
+    let mut flips_match = true;
+    for (controls, target) in [
+        (vec![], 4),
+        (vec![8], 0),
+        (vec![0, 5], 3),
+        (vec![7, 1, 4], 2),
+        (vec![0, 2, 3, 5, 6, 8], 7),
+        ((1..n).collect(), 0),
+    ] {
+        let mut gate = QuantumCircuit::new(n);
+        gate.mcx(&controls, target);
+        let set = controls.iter().fold(0, |set, &c| set | 1 << (n - 1 - c));
+        let expected = QuantumState::new(
+            (0..1usize << n)
+                .map(|i| match i & set == set {
+                    true => before.get(i ^ 1 << (n - 1 - target)),
+                    false => before.get(i),
+                })
+                .collect(),
+        );
+        let runtimes = [
+            Runtime::BasicRT,
+            Runtime::BasicRTMT,
+            Runtime::BatchedRTMT,
+            Runtime::StructureAwareRT,
+            Runtime::StructureAwareMT,
+        ];
+        let matches = runtimes
+            .iter()
+            .all(|&runtime| states_equal(&run_after(&input, &gate, runtime), &expected));
+        println!(
+            "mcx({:?}, {}) flips the target where every control is set {}",
+            controls,
+            target,
+            if matches { "✓" } else { "✗" }
+        );
+        flips_match &= matches;
+    }
+
+    // A 20-qubit oracle, whose controlled Z no dense matrix could hold.
+    let n = 20;
+    let marked = [12345, 1 << 19];
+    let oracle = library::phase_oracle(n, &marked);
+    let theta = (marked.len() as f64 / (1 << n) as f64).sqrt().asin();
+    let search = library::grover_search(&oracle, 3);
+    let start = Instant::now();
+    let basic = search.clone().compute_with(Runtime::BasicRTMT).clone();
+    let basic_time = start.elapsed();
+    let start = Instant::now();
+    let mt = search
+        .clone()
+        .compute_with(Runtime::StructureAwareMT)
+        .clone();
+    let mt_time = start.elapsed();
+    let found = probability_of(&mt, &marked);
+    let search_matches =
+        (found - (7.0 * theta).sin().powi(2)).abs() < 1e-9 && states_equal(&basic, &mt);
+    println!(
+        "{}-qubit Grover search: probability {:.6} after 3 rounds, sin²(7θ) = {:.6} {}\n",
+        n,
+        found,
+        (7.0 * theta).sin().powi(2),
+        if search_matches { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: format!("Multi-controlled X ({} qubits)", n),
+        num_qubits: n,
+        basic_time,
+        mt_time,
+        results_match: flips_match && search_matches,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+/// `V · diag(e^{2πiφ₀}, e^{2πiφ₁}, ...) · V†`, whose eigenvectors are the
+/// columns of `V`.
+fn with_eigenphases(v: &Matrix<Complex<f64>>, phases: &[f64]) -> CustomGate {
+    let dim = phases.len();
+    let mut diagonal = Matrix::new(dim, dim, vec![complex!(0.0, 0.0); dim * dim]);
+    for (i, phase) in phases.iter().enumerate() {
+        let angle = 2.0 * PI * phase;
+        diagonal.set(i, i, complex!(angle.cos(), angle.sin()));
+    }
+    let v_dagger = Matrix::new(
+        dim,
+        dim,
+        (0..dim * dim)
+            .map(|i| v.get(i % dim, i / dim).get_conjugate())
+            .collect(),
+    );
+    let matrix = v.dot(&diagonal).unwrap().dot(&v_dagger).unwrap();
+    CustomGate::from_matrix("U", matrix)
+}
+
+pub fn test_phase_estimation(results: &mut Vec<BenchmarkResult>) {
+    print_section("Quantum Phase Estimation");
+
+    // Eigenvectors of U are the Bell states V|j⟩, V = CNOT·(H ⊗ I).
+    let bell = CustomGateBuilder::new("V", 2).h(0).cnot(0, 1).build();
+    let v = bell.to_quantum_gate().matrix;
+    let exact_phases = [0.0, 0.375, 0.625, 0.125];
+    let unitary = with_eigenphases(&v, &exact_phases);
+    let m = 3;
+    let estimation = library::phase_estimation(&unitary, m);
+    print_circuit(&estimation);
+
+    let mut exact_match = true;
+    for (j, &phase) in exact_phases.iter().enumerate() {
+        let mut circuit = QuantumCircuit::new(m + 2);
+        if j & 2 != 0 {
+            circuit.x(m);
+        }
+        if j & 1 != 0 {
+            circuit.x(m + 1);
+        }
+        circuit.h(m).cnot(m, m + 1);
+        circuit.append(&estimation, &(0..m + 2).collect::<Vec<_>>());
+        let counts = circuit.sample(200, Some(3528));
+        exact_match &= counts.len() == 1 && library::estimated_phase(&counts) == Some(phase);
+    }
+    println!(
+        "{}-bit phases {:?} of the Bell eigenstates read exactly in every shot: {}",
+        m,
+        exact_phases,
+        if exact_match { "✓" } else { "✗" }
+    );
+
+    // A phase with no finite binary expansion lands on a nearest m-bit value
+    // with probability at least 4/π².
+    let m = 6;
+    let phase = 0.3;
+    let unitary = with_eigenphases(&v, &[phase, 0.0, 0.0, 0.0]);
+    let mut circuit = QuantumCircuit::new(m + 2);
+    circuit.h(m).cnot(m, m + 1);
+    circuit.append(
+        &library::phase_estimation(&unitary, m),
+        &(0..m + 2).collect::<Vec<_>>(),
+    );
+
+    let start = Instant::now();
+    let basic = circuit.clone().compute_with(Runtime::BasicRT).clone();
+    let basic_time = start.elapsed();
+    let start = Instant::now();
+    let mt = circuit.compute_with(Runtime::StructureAwareMT).clone();
+    let mt_time = start.elapsed();
+    let runtimes_match = states_equal(&basic, &mt);
+    let counts = circuit.sample(2000, Some(3528));
+    let estimate = library::estimated_phase(&counts).unwrap();
+    let close = (estimate - phase).abs() <= 1.0 / (1 << m) as f64;
+    let (best, hits) = counts.most_frequent().unwrap();
+    println!(
+        "φ = {} with {} bits: most frequent outcome {} ({:.1}% of shots) gives {} {}\n",
+        phase,
+        m,
+        best,
+        100.0 * hits as f64 / counts.shots() as f64,
+        estimate,
+        if close && hits as f64 >= 0.405 * counts.shots() as f64 {
+            "✓"
+        } else {
+            "✗"
+        }
+    );
+
+    results.push(BenchmarkResult {
+        name: format!("Phase estimation ({} qubits)", m + 2),
+        num_qubits: m + 2,
+        basic_time,
+        mt_time,
+        results_match: exact_match && close && runtimes_match,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+pub fn test_qaoa(results: &mut Vec<BenchmarkResult>) {
+    print_section("QAOA");
+
+    // A single cost term makes the cost layer exact, so it can be checked
+    // against integrating exp(-iγC) directly.
+    let gamma = 0.4;
+    let mut layers_match = true;
+    for label in ["XYZ", "YIX", "ZIZ"] {
+        let cost = SparsePauliOp::from_labels(&[(label, 0.7)]).unwrap();
+        let mut circuit =
+            library::qaoa(&cost, 1).bind_parameters(&[("gamma_0", gamma), ("beta_0", 0.0)]);
+        let mut plus = QuantumCircuit::new(3);
+        plus.h(0).h(1).h(2);
+        let expected = TimeEvolution::new(1e-3).evolve(
+            &cost.to_hamiltonian().unwrap(),
+            plus.compute_with(Runtime::BasicRT),
+            gamma,
+        );
+        layers_match &= fidelity(circuit.compute_with(Runtime::BasicRT), &expected) > 1.0 - 1e-9;
+    }
+    println!(
+        "Cost layers for XYZ, YIX and ZIZ match integrating exp(-iγC): {}",
+        if layers_match { "✓" } else { "✗" }
+    );
+
+    // MaxCut on a ring: C = Σ (1 - ZᵢZⱼ)/2 over the edges, maximum n.
+    let n = 8;
+    let mut cost = SparsePauliOp::new(n);
+    for i in 0..n {
+        let j = (i + 1) % n;
+        cost.add_term(0.5, &[]);
+        cost.add_term(-0.5, &[(i, Pauli::Z), (j, Pauli::Z)]);
+    }
+    let p = 2;
+    let ansatz = library::qaoa(&cost, p);
+    let names = ansatz.parameters();
+    let names_match = names == ["beta_0", "beta_1", "gamma_0", "gamma_1"];
+    print_circuit(&library::qaoa(
+        &SparsePauliOp::from_labels(&[("ZZI", 1.0), ("IZZ", 1.0)]).unwrap(),
+        1,
+    ));
+
+    // Gradient ascent on ⟨C⟩ with adjoint gradients; on a ring the best p = 2
+    // angles reach 5/6 of the maximum cut.
+    let observable = cost.to_hamiltonian().unwrap();
+    let config = RuntimeConfig::new();
+    let mut values = vec![0.1; names.len()];
+    let mut expectation = 0.0;
+    for _ in 0..100 {
+        let bindings: Vec<(&str, f64)> = names
+            .iter()
+            .map(String::as_str)
+            .zip(values.iter().copied())
+            .collect();
+        let gradient = config.adjoint_gradients(&ansatz, &observable, &bindings);
+        expectation = gradient.expectation;
+        for (value, (_, partial)) in values.iter_mut().zip(&gradient.partials) {
+            *value += 0.03 * partial;
+        }
+    }
+    let bindings: Vec<(&str, f64)> = names
+        .iter()
+        .map(String::as_str)
+        .zip(values.iter().copied())
+        .collect();
+    let mut circuit = ansatz.bind_parameters(&bindings);
+
+    let start = Instant::now();
+    let basic = circuit.clone().compute_with(Runtime::BasicRT).clone();
+    let basic_time = start.elapsed();
+    let start = Instant::now();
+    let mt = circuit.compute_with(Runtime::StructureAwareMT).clone();
+    let mt_time = start.elapsed();
+    let runtimes_match = states_equal(&basic, &mt);
+    let counts = circuit.sample(2000, Some(3529));
+    let (best, _) = counts.most_frequent().unwrap();
+    let best_cut = (0..n)
+        .filter(|&i| best[i..=i] != best[(i + 1) % n..=(i + 1) % n])
+        .count();
+    let ratio = expectation / n as f64;
+    println!(
+        "MaxCut on a {}-ring, p = {}: parameters {:?} {}, ⟨C⟩/max after gradient ascent {:.4}, most frequent cut {} cuts {} edges {}\n",
+        n,
+        p,
+        names,
+        if names_match { "✓" } else { "✗" },
+        ratio,
+        best,
+        best_cut,
+        if ratio > 0.83 && best_cut == n { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: format!("QAOA MaxCut ({} qubits)", n),
+        num_qubits: n,
+        basic_time,
+        mt_time,
+        results_match: layers_match
+            && names_match
+            && ratio > 0.83
+            && best_cut == n
+            && runtimes_match,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+pub fn test_ansatz_templates(results: &mut Vec<BenchmarkResult>) {
+    print_section("Variational Ansatz Templates");
+
+    print_circuit(&library::efficient_su2(3, 1));
+
+    let (n, reps) = (5, 2);
+    let templates = [
+        ("ry_cnot_linear", library::ry_cnot_linear(n, reps), 1, n - 1),
+        ("efficient_su2", library::efficient_su2(n, reps), 2, n - 1),
+        (
+            "two_local(Rx Rz, CZ, circular)",
+            library::two_local(
+                n,
+                &[Rotation::Rx, Rotation::Rz],
+                Entangler::Cz,
+                Entanglement::Circular,
+                reps,
+            ),
+            2,
+            n,
+        ),
+        (
+            "two_local(Ry, CNOT, full)",
+            library::two_local(
+                n,
+                &[Rotation::Ry],
+                Entangler::Cnot,
+                Entanglement::Full,
+                reps,
+            ),
+            1,
+            n * (n - 1) / 2,
+        ),
+    ];
+    let mut shapes_match = true;
+    for (name, circuit, rotations, pairs) in &templates {
+        let parameters = circuit.parameters();
+        let in_order: Vec<String> = circuit
+            .operations()
+            .iter()
+            .flat_map(|op| op.symbols())
+            .flatten()
+            .map(|symbol| symbol.name().to_string())
+            .collect();
+        let entanglers = circuit.operations().len() - in_order.len();
+        let shape_matches = parameters.len() == n * rotations * (reps + 1)
+            && parameters == in_order
+            && entanglers == pairs * reps;
+        println!(
+            "  {:<32} {:>2} parameters, {:>2} entanglers, listed in gate order {}",
+            name,
+            parameters.len(),
+            entanglers,
+            if shape_matches { "✓" } else { "✗" }
+        );
+        shapes_match &= shape_matches;
+    }
+
+    // VQE on an open transverse-field Ising chain, ground energy ≈ -7.296.
+    let n = 6;
+    let ansatz = library::efficient_su2(n, 2);
+    let mut cost = SparsePauliOp::new(n);
+    for q in 0..n {
+        if q + 1 < n {
+            cost.add_term(-1.0, &[(q, Pauli::Z), (q + 1, Pauli::Z)]);
+        }
+        cost.add_term(-1.0, &[(q, Pauli::X)]);
+    }
+    let observable = cost.to_hamiltonian().unwrap();
+    let names = ansatz.parameters();
+    let mut values: Vec<f64> = (0..names.len()).map(|i| 0.1 * (i % 7) as f64).collect();
+    let config = RuntimeConfig::new();
+    let mut energies = Vec::new();
+    for _ in 0..200 {
+        let bindings: Vec<(&str, f64)> = names
+            .iter()
+            .map(String::as_str)
+            .zip(values.iter().copied())
+            .collect();
+        let gradient = config.adjoint_gradients(&ansatz, &observable, &bindings);
+        energies.push(gradient.expectation);
+        for (value, (_, partial)) in values.iter_mut().zip(&gradient.partials) {
+            *value -= 0.1 * partial;
+        }
+    }
+    let (first, last) = (energies[0], energies[energies.len() - 1]);
+    let converged = last < -7.2;
+    println!(
+        "VQE with efficient_su2({}, 2) on a transverse-field Ising chain: energy {:.4} → {:.4} {}",
+        n,
+        first,
+        last,
+        if converged { "✓" } else { "✗" }
+    );
+
+    let n = 8;
+    let ansatz = library::efficient_su2(n, 3);
+    let bindings: Vec<(String, f64)> = ansatz
+        .parameters()
+        .into_iter()
+        .enumerate()
+        .map(|(i, name)| (name, 0.3 + 0.17 * i as f64))
+        .collect();
+    let bindings: Vec<(&str, f64)> = bindings.iter().map(|(n, v)| (n.as_str(), *v)).collect();
+    let circuit = ansatz.bind_parameters(&bindings);
+    let start = Instant::now();
+    let basic = circuit.clone().compute_with(Runtime::BasicRT).clone();
+    let basic_time = start.elapsed();
+    let start = Instant::now();
+    let mt = circuit
+        .clone()
+        .compute_with(Runtime::StructureAwareMT)
+        .clone();
+    let mt_time = start.elapsed();
+    let runtimes_match = states_equal(&basic, &mt);
+    println!(
+        "efficient_su2({}, 3) bound: BasicRT and StructureAwareMT agree {}\n",
+        n,
+        if runtimes_match { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: format!("efficient_su2 ({} qubits)", n),
+        num_qubits: n,
+        basic_time,
+        mt_time,
+        results_match: shapes_match && converged && runtimes_match,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+pub fn test_random_circuit(results: &mut Vec<BenchmarkResult>) {
+    print_section("Random Circuits");
+
+    print_circuit(&library::random_circuit(4, 3, 1, GateSet::Universal));
+
+    let listing = |circuit: &QuantumCircuit| format!("{:?}", circuit.operations());
+    let (n, depth) = (8, 40);
+    let reproducible = GateSet::Universal;
+    let same_seed = listing(&library::random_circuit(n, depth, 7, reproducible))
+        == listing(&library::random_circuit(n, depth, 7, reproducible));
+    let other_seed = listing(&library::random_circuit(n, depth, 7, reproducible))
+        != listing(&library::random_circuit(n, depth, 8, reproducible));
+    println!(
+        "Same seed gives the same circuit {}, another seed a different one {}",
+        if same_seed { "✓" } else { "✗" },
+        if other_seed { "✓" } else { "✗" }
+    );
+
+    // Every layer touches each qubit exactly once.
+    let mut layers_full = true;
+    for gate_set in [
+        GateSet::Clifford,
+        GateSet::CliffordT { t_fraction: 0.3 },
+        GateSet::Universal,
+    ] {
+        let circuit = library::random_circuit(n, depth, 11, gate_set);
+        let touches: usize = circuit
+            .operations()
+            .iter()
+            .map(|op| op.quantum_targets().len())
+            .sum();
+        layers_full &= touches == n * depth;
+    }
+
+    let mut clifford = library::random_circuit(n, depth, 12, GateSet::Clifford);
+    let stabilizer_matches = fidelity(
+        &Runtime::Stabilizer.compute(n, clifford.operations()),
+        clifford.compute_with(Runtime::StructureAwareRT),
+    ) > 1.0 - 1e-9;
+
+    let t_fraction = 0.3;
+    let clifford_t = library::random_circuit(16, 500, 13, GateSet::CliffordT { t_fraction });
+    let t_gates = clifford_t
+        .operations()
+        .iter()
+        .filter(|op| matches!(op, GateOp::T(_) | GateOp::Tdg(_)))
+        .count();
+    let measured_fraction = t_gates as f64 / (16 * 500) as f64;
+    let fraction_matches = (measured_fraction - t_fraction).abs() < 0.02;
+    println!(
+        "Layers cover every qubit once {}, Clifford set runs on the stabilizer runtime {}, T on {:.3} of qubit-layers for a fraction of {} {}",
+        if layers_full { "✓" } else { "✗" },
+        if stabilizer_matches { "✓" } else { "✗" },
+        measured_fraction,
+        t_fraction,
+        if fraction_matches { "✓" } else { "✗" }
+    );
+
+    let circuit = library::random_circuit(n, depth, 14, GateSet::Universal);
+    let start = Instant::now();
+    let basic = circuit.clone().compute_with(Runtime::BasicRT).clone();
+    let basic_time = start.elapsed();
+    let start = Instant::now();
+    let mt = circuit
+        .clone()
+        .compute_with(Runtime::StructureAwareMT)
+        .clone();
+    let mt_time = start.elapsed();
+    let runtimes_match = states_equal(&basic, &mt);
+    println!(
+        "{}-gate universal circuit on {} qubits: BasicRT and StructureAwareMT agree {}\n",
+        circuit.operations().len(),
+        n,
+        if runtimes_match { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: format!("Random circuit ({} qubits)", n),
+        num_qubits: n,
+        basic_time,
+        mt_time,
+        results_match: same_seed
+            && other_seed
+            && layers_full
+            && stabilizer_matches
+            && fraction_matches
+            && runtimes_match,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+/// `prepare_state` reaches the normalised target up to a global phase,
+/// with `2ⁿ − 1` rotations and `2ⁿ − 2` CNOTs per angle kind at most.
+pub fn test_prepare_state(results: &mut Vec<BenchmarkResult>) {
+    print_section("State Preparation");
+
+    let mut rng = StdRng::seed_from_u64(3540);
+    let target_of = |amplitudes: &[Complex<f64>]| {
+        let norm = amplitudes.iter().map(Complex::norm2).sum::<f64>().sqrt();
+        QuantumState::new(
+            amplitudes
+                .iter()
+                .map(|a| *a * complex!(1.0 / norm, 0.0))
+                .collect(),
+        )
+    };
+    let count = |circuit: &QuantumCircuit, name: &str| {
+        circuit
+            .operations()
+            .iter()
+            .filter(|op| op.name() == name)
+            .count()
+    };
+
+    let n = 10;
+    let dim = 1 << n;
+    let complex_data: Vec<Complex<f64>> = (0..dim)
+        .map(|_| complex!(rng.random_range(-1.0..1.0), rng.random_range(-1.0..1.0)))
+        .collect();
+    let start = Instant::now();
+    let circuit = library::prepare_state(&complex_data);
+    let synthesis_time = start.elapsed();
+    let start = Instant::now();
+    let single = circuit
+        .clone()
+        .compute_with(Runtime::StructureAwareRT)
+        .clone();
+    let basic_time = start.elapsed();
+    let start = Instant::now();
+    let prepared = circuit
+        .clone()
+        .compute_with(Runtime::StructureAwareMT)
+        .clone();
+    let mt_time = start.elapsed();
+    let target = target_of(&complex_data);
+    let general = fidelity(&single, &target) > 1.0 - 1e-9
+        && fidelity(&prepared, &target) > 1.0 - 1e-9
+        && count(&circuit, "Ry") < dim
+        && count(&circuit, "Rz") < dim
+        && count(&circuit, "CNOT") <= 2 * (dim - 2);
+    println!(
+        "Random complex {}-qubit vector: {} Ry, {} Rz, {} CNOT in {:?}: {}",
+        n,
+        count(&circuit, "Ry"),
+        count(&circuit, "Rz"),
+        count(&circuit, "CNOT"),
+        synthesis_time,
+        if general { "✓" } else { "✗" }
+    );
+
+    let real_data: Vec<Complex<f64>> = (0..64)
+        .map(|i| complex!(((i * 7) % 11) as f64, 0.0))
+        .collect();
+    let mut real = library::prepare_state(&real_data);
+    let real_ok = fidelity(real.compute_with(Runtime::BasicRT), &target_of(&real_data))
+        > 1.0 - 1e-9
+        && count(&real, "Rz") == 0;
+
+    let mut sparse_data = vec![complex!(0.0, 0.0); 32];
+    sparse_data[0] = complex!(1.0, 0.0);
+    sparse_data[31] = complex!(0.0, -1.0);
+    sparse_data[6] = complex!(-0.5, 0.5);
+    let mut sparse = library::prepare_state(&sparse_data);
+    let sparse_ok = fidelity(
+        sparse.compute_with(Runtime::BasicRT),
+        &target_of(&sparse_data),
+    ) > 1.0 - 1e-9;
+
+    let mut basis_data = vec![complex!(0.0, 0.0); 16];
+    basis_data[0b1011] = complex!(1.0, 0.0);
+    let mut basis = library::prepare_state(&basis_data);
+    let basis_ok = fidelity(
+        basis.compute_with(Runtime::BasicRT),
+        &target_of(&basis_data),
+    ) > 1.0 - 1e-9;
+    println!(
+        "Real data without Rz {}, sparse complex data {}, a basis state {}\n",
+        if real_ok { "✓" } else { "✗" },
+        if sparse_ok { "✓" } else { "✗" },
+        if basis_ok { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: format!("State preparation ({} qubits)", n),
+        num_qubits: n,
+        basic_time,
+        mt_time,
+        results_match: general && real_ok && sparse_ok && basis_ok,
+        peak_bytes: memory::take_peak(),
+    });
+}
@@ -1,14 +1,25 @@
 mod benchmarks;
 mod clifford;
 mod common;
+mod crossval;
 mod custom_gates;
+mod golden;
+mod gpu;
+mod jobs;
 mod kernels;
+mod library;
+mod memory;
 mod noise;
 mod non_clifford;
+mod qec;
+mod remote;
 mod simd;
 
-use common::{print_benchmark_table, print_summary, BenchmarkResult};
-use std::env;
+use benchmarks::{parse_qubits, parse_runtimes, BenchOptions};
+use common::{
+    benchmark_csv, benchmark_json, print_benchmark_table, print_summary, BenchmarkResult,
+};
+use std::{env, fs};
 
 fn print_header() {
     println!("═══════════════════════════════════════════════════════════════");
@@ -27,8 +38,25 @@ fn print_usage() {
     println!("  kernels      Run kernel batching tests only");
     println!("  simd         Run SIMD acceleration tests only");
     println!("  noise        Run noise channel tests only");
+    println!("  remote       Run remote execution client tests only");
+    println!("  jobs         Run background job tests only");
+    println!("  gpu          Run GPU runtime tests only (build with --features gpu)");
+    println!("  qec          Run error-correction code tests only");
+    println!("  library      Run algorithm library tests only");
     println!("  bench        Run benchmark tests only");
+    println!("  golden       Check states and diagrams against tester/golden");
+    println!("  --update     With golden, rewrite the reference files instead");
+    println!("  crossval     Compare random circuits with an external simulator (not in all)");
+    println!(
+        "  --reference CMD  With crossval, the simulator to run (default: Qiskit if installed)"
+    );
     println!("  help         Show this help message");
+    println!("  --qubits N|A..B   With bench, sweep these sizes (e.g. 4..20 or 8,12,16)");
+    println!("  --depth N         With bench, layers per sweep circuit (default 20)");
+    println!("  --runtimes A,B,.. With bench, runtimes to sweep; the first is the baseline");
+    println!("  --repeat N        With bench, average each timing over N runs");
+    println!("  --json FILE  Also write the benchmark results to FILE as JSON");
+    println!("  --csv FILE   Also write the benchmark results to FILE as CSV");
     println!();
     println!("Examples:");
     println!("  tester                   # Run all tests");
@@ -38,10 +66,75 @@ fn print_usage() {
     println!("  tester simd              # Run only SIMD tests");
     println!("  tester noise             # Run only noise channel tests");
     println!("  tester custom bench      # Run custom gates and benchmarks");
+    println!("  tester bench --json out.json");
+    println!(
+        "  tester bench --qubits 4..20 --depth 100 --runtimes basic,simd-mt,sa-mt --repeat 10"
+    );
+    println!("  tester crossval --reference './my_sim --probabilities'");
+}
+
+fn parse_count(option: &str, value: &str) -> Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(format!("{} expects a positive number", option)),
+    }
+}
+
+fn write_report(path: &str, contents: &str) {
+    match fs::write(path, contents) {
+        Ok(()) => println!("Wrote benchmark results to {}", path),
+        Err(err) => eprintln!("Could not write {}: {}", path, err),
+    }
 }
 
 fn main() {
-    let args: Vec<String> = env::args().skip(1).collect();
+    let mut args: Vec<String> = Vec::new();
+    let mut json_path = None;
+    let mut csv_path = None;
+    let mut update_golden = false;
+    let mut reference = None;
+    let mut bench = BenchOptions::default();
+    let mut raw = env::args().skip(1);
+    while let Some(arg) = raw.next() {
+        match arg.as_str() {
+            "--json" | "--csv" => {
+                let Some(path) = raw.next() else {
+                    eprintln!("{} expects a file name", arg);
+                    std::process::exit(2);
+                };
+                if arg == "--json" {
+                    json_path = Some(path);
+                } else {
+                    csv_path = Some(path);
+                }
+            }
+            "--update" => update_golden = true,
+            "--qubits" | "--depth" | "--runtimes" | "--repeat" => {
+                let Some(value) = raw.next() else {
+                    eprintln!("{} expects a value", arg);
+                    std::process::exit(2);
+                };
+                let parsed = match arg.as_str() {
+                    "--qubits" => parse_qubits(&value).map(|q| bench.qubits = Some(q)),
+                    "--runtimes" => parse_runtimes(&value).map(|r| bench.runtimes = Some(r)),
+                    "--depth" => parse_count(&arg, &value).map(|n| bench.depth = Some(n)),
+                    _ => parse_count(&arg, &value).map(|n| bench.repeat = Some(n)),
+                };
+                if let Err(err) = parsed {
+                    eprintln!("{}", err);
+                    std::process::exit(2);
+                }
+            }
+            "--reference" => {
+                let Some(command) = raw.next() else {
+                    eprintln!("--reference expects a command");
+                    std::process::exit(2);
+                };
+                reference = Some(command);
+            }
+            _ => args.push(arg),
+        }
+    }
 
     if args
         .iter()
@@ -55,14 +148,22 @@ fn main() {
 
     let mut results: Vec<BenchmarkResult> = Vec::new();
 
-    let run_all = args.is_empty() || args.iter().any(|a| a == "all");
+    // Sweep options on their own mean "just benchmark".
+    let run_all = (args.is_empty() && !bench.is_sweep()) || args.iter().any(|a| a == "all");
     let run_clifford = run_all || args.iter().any(|a| a == "clifford");
     let run_non_clifford = run_all || args.iter().any(|a| a == "non-clifford");
     let run_custom = run_all || args.iter().any(|a| a == "custom");
     let run_kernels = run_all || args.iter().any(|a| a == "kernels");
     let run_simd = run_all || args.iter().any(|a| a == "simd");
     let run_noise = run_all || args.iter().any(|a| a == "noise");
-    let run_bench = run_all || args.iter().any(|a| a == "bench");
+    let run_remote = run_all || args.iter().any(|a| a == "remote");
+    let run_jobs = run_all || args.iter().any(|a| a == "jobs");
+    let run_gpu = run_all || args.iter().any(|a| a == "gpu");
+    let run_qec = run_all || args.iter().any(|a| a == "qec");
+    let run_library = run_all || args.iter().any(|a| a == "library");
+    let run_bench = run_all || args.is_empty() || args.iter().any(|a| a == "bench");
+    let run_golden = run_all || args.iter().any(|a| a == "golden");
+    let run_crossval = args.iter().any(|a| a == "crossval");
 
     if run_clifford {
         clifford::run_all(&mut results);
@@ -88,12 +189,46 @@ fn main() {
         noise::run_all(&mut results);
     }
 
+    if run_remote {
+        remote::run_all(&mut results);
+    }
+
+    if run_jobs {
+        jobs::run_all(&mut results);
+    }
+
+    if run_gpu {
+        gpu::run_all(&mut results);
+    }
+
+    if run_qec {
+        qec::run_all(&mut results);
+    }
+
+    if run_library {
+        library::run_all(&mut results);
+    }
+
     if run_bench {
-        benchmarks::run_all(&mut results);
+        benchmarks::run_all(&mut results, &bench);
     }
 
+    let golden_passed = !run_golden || golden::run_all(&mut results, update_golden);
+    let crossval_passed = !run_crossval || crossval::run_all(&mut results, reference.as_deref());
+
     if !results.is_empty() {
         print_benchmark_table(&results);
         print_summary(&results);
     }
+
+    if let Some(path) = json_path {
+        write_report(&path, &benchmark_json(&results));
+    }
+    if let Some(path) = csv_path {
+        write_report(&path, &benchmark_csv(&results));
+    }
+
+    if !golden_passed || !crossval_passed {
+        std::process::exit(1);
+    }
 }
@@ -1,7 +1,11 @@
 use crate::common::{print_section, BenchmarkResult};
+use crate::memory;
 use libpsi_core::{
-    complex, DensityMatrix, NoiseChannel, QuantumCircuit, Runtime, Vector,
+    complex, fidelity, gates, library, Counts, DensityMatrix, GateDurations, Mitigation,
+    NoiseChannel, NoiseModel, QuantumCircuit, ReadoutCalibration, ReadoutError, Runtime,
+    RuntimeConfig, SchedulePolicy, TrajectorySimulator, Vector,
 };
+use std::collections::BTreeMap;
 use std::time::Instant;
 
 pub fn run_all(results: &mut Vec<BenchmarkResult>) {
@@ -10,8 +14,16 @@ pub fn run_all(results: &mut Vec<BenchmarkResult>) {
     println!("═══════════════════════════════════════════════════════════════\n");
 
     test_density_matrix_basics(results);
+    test_partial_trace(results);
+    test_entanglement_entropy(results);
+    test_fidelity(results);
+    test_bloch_vector(results);
     test_noise_channels(results);
     test_noisy_circuit(results);
+    test_readout_mitigation(results);
+    test_scheduling(results);
+    test_trajectories(results);
+    test_noise_model(results);
 }
 
 pub fn test_density_matrix_basics(results: &mut Vec<BenchmarkResult>) {
@@ -26,9 +38,7 @@ pub fn test_density_matrix_basics(results: &mut Vec<BenchmarkResult>) {
     circuit.compute_with(Runtime::BasicRT);
     let state = circuit.state();
 
-    let state_vec: Vec<_> = (0..state.size())
-        .map(|i| state.get(i))
-        .collect();
+    let state_vec: Vec<_> = (0..state.size()).map(|i| state.get(i)).collect();
 
     let dm_bell = DensityMatrix::from_state_vector(&state_vec);
     println!("Bell state |Φ+⟩:");
@@ -37,13 +47,337 @@ pub fn test_density_matrix_basics(results: &mut Vec<BenchmarkResult>) {
     println!("{:?}", dm_bell);
 
     let is_pure = dm_bell.is_pure(1e-10);
-    println!("Purity check: {}\n", if is_pure { "✓ Pure" } else { "✗ Mixed" });
+    println!(
+        "Purity check: {}\n",
+        if is_pure { "✓ Pure" } else { "✗ Mixed" }
+    );
 
     results.push(BenchmarkResult {
         name: "DM: Bell state".to_string(),
+        num_qubits: 2,
         basic_time: std::time::Duration::from_micros(0),
         mt_time: std::time::Duration::from_micros(0),
         results_match: is_pure,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+pub fn test_partial_trace(results: &mut Vec<BenchmarkResult>) {
+    print_section("Partial Trace");
+
+    let close = |a: &DensityMatrix, b: &DensityMatrix| {
+        a.dim == b.dim
+            && a.data
+                .iter()
+                .zip(&b.data)
+                .all(|(x, y)| (*x - *y).norm2() < 1e-24)
+    };
+    let mut mixed = DensityMatrix::new(1);
+    mixed.set(0, 0, complex!(0.5, 0.0));
+    mixed.set(1, 1, complex!(0.5, 0.0));
+
+    let mut bell = QuantumCircuit::new(2);
+    bell.h(0).cnot(0, 1);
+    let state = bell.compute_with(Runtime::BasicRT);
+    let amplitudes: Vec<_> = (0..state.size()).map(|i| state.get(i)).collect();
+    let traced = DensityMatrix::from_state_vector(&amplitudes).partial_trace(&[1]);
+    let reduced = state.reduced_density_matrix(&[0]);
+    let bell_mixed = close(&traced, &mixed) && close(&reduced, &mixed);
+    println!(
+        "Either half of a Bell pair is I/2 (purity {:.4}): {}",
+        reduced.purity(),
+        if bell_mixed { "✓" } else { "✗" }
+    );
+
+    let mut product = QuantumCircuit::new(3);
+    product.h(0).x(1).ry(2, 0.7).cnot(1, 2);
+    let state = product.compute_with(Runtime::BasicRT);
+    let pure = (0..3).all(|q| state.reduced_density_matrix(&[q]).is_pure(1e-10))
+        && state.reduced_density_matrix(&[1, 2]).is_pure(1e-10);
+    println!(
+        "Unentangled qubits stay pure: {}",
+        if pure { "✓" } else { "✗" }
+    );
+
+    let n = 10;
+    let mut circuit = library::random_circuit(n, 6, 3534, library::GateSet::Universal);
+    let state = circuit.compute_with(Runtime::StructureAwareRT);
+    let amplitudes: Vec<_> = (0..state.size()).map(|i| state.get(i)).collect();
+    let traced: Vec<usize> = (0..n).filter(|q| ![2, 5, 7].contains(q)).collect();
+
+    let start = Instant::now();
+    let full = DensityMatrix::from_state_vector(&amplitudes).partial_trace(&traced);
+    let full_time = start.elapsed();
+
+    let start = Instant::now();
+    let direct = state.reduced_density_matrix(&[2, 5, 7]);
+    let direct_time = start.elapsed();
+
+    let mut reordered = state.reduced_density_matrix(&[7, 5, 2]);
+    reordered.apply_unitary(&gates::SWAP.matrix, &[0, 2]);
+    let agree = close(&full, &direct)
+        && close(&reordered, &direct)
+        && (direct.trace().real - 1.0).abs() < 1e-10;
+    println!(
+        "{} qubits, 3 kept: density matrix route {:?}, from amplitudes {:?}, agree in any order: {}\n",
+        n,
+        full_time,
+        direct_time,
+        if agree { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: "DM: partial trace".to_string(),
+        num_qubits: n,
+        basic_time: full_time,
+        mt_time: direct_time,
+        results_match: bell_mixed && pure && agree,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+pub fn test_entanglement_entropy(results: &mut Vec<BenchmarkResult>) {
+    print_section("Entanglement Entropy");
+
+    let n = 8;
+    let mut ghz = QuantumCircuit::new(n);
+    ghz.h(0);
+    for q in 1..n {
+        ghz.cnot(q - 1, q);
+    }
+    let state = ghz.compute_with(Runtime::BasicRT);
+    let cuts: Vec<Vec<usize>> = vec![vec![0], vec![3, 5], vec![0, 1, 2, 3], vec![1, 2, 4, 6, 7]];
+    let ghz_bit = cuts
+        .iter()
+        .all(|cut| (state.entanglement_entropy(cut) - 1.0).abs() < 1e-10);
+    println!(
+        "GHZ-{} carries one bit across every cut: {}",
+        n,
+        if ghz_bit { "✓" } else { "✗" }
+    );
+
+    let mut pairs = QuantumCircuit::new(4);
+    pairs.h(0).cnot(0, 1).h(2).cnot(2, 3).ry(1, 0.4);
+    let state = pairs.compute_with(Runtime::BasicRT);
+    let counted = state.entanglement_entropy(&[0, 1]).abs() < 1e-10
+        && (state.entanglement_entropy(&[0, 2]) - 2.0).abs() < 1e-10
+        && (state.entanglement_entropy(&[1]) - 1.0).abs() < 1e-10;
+    println!(
+        "Bell pairs: 0 bits within a pair, 2 bits across both: {}",
+        if counted { "✓" } else { "✗" }
+    );
+
+    // A single qubit has purity (1 + r²)/2 for Bloch radius r and
+    // eigenvalues (1 ± r)/2, which fix its entropy.
+    let n = 12;
+    let mut circuit = library::random_circuit(n, 8, 3535, library::GateSet::Universal);
+    let state = circuit.compute_with(Runtime::StructureAwareRT);
+    let single = state.reduced_density_matrix(&[4]);
+    let radius = (2.0 * single.purity() - 1.0).max(0.0).sqrt();
+    let binary = |p: f64| {
+        [p, 1.0 - p]
+            .iter()
+            .filter(|&&x| x > 0.0)
+            .map(|x| -x * x.log2())
+            .sum::<f64>()
+    };
+    let one_qubit = (state.entanglement_entropy(&[4]) - binary((1.0 + radius) / 2.0)).abs() < 1e-9;
+
+    let half: Vec<usize> = (0..n / 2).collect();
+    let rest: Vec<usize> = (n / 2..n).collect();
+    let start = Instant::now();
+    let entropy = state.entanglement_entropy(&half);
+    let elapsed = start.elapsed();
+    let symmetric = (entropy - state.entanglement_entropy(&rest)).abs() < 1e-9
+        && entropy > 0.0
+        && entropy <= (n / 2) as f64 + 1e-9;
+    println!(
+        "Random {}-qubit state: S(qubit 4) matches its Bloch radius: {}, S(half) = S(other half) = {:.4} bits in {:?}: {}\n",
+        n,
+        if one_qubit { "✓" } else { "✗" },
+        entropy,
+        elapsed,
+        if symmetric { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: "Entanglement entropy".to_string(),
+        num_qubits: n,
+        basic_time: elapsed,
+        mt_time: elapsed,
+        results_match: ghz_bit && counted && one_qubit && symmetric,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+pub fn test_fidelity(results: &mut Vec<BenchmarkResult>) {
+    print_section("State Fidelity");
+
+    let mut bell = QuantumCircuit::new(2);
+    bell.h(0).cnot(0, 1);
+    let ideal = bell.compute_with(Runtime::BasicRT).clone();
+    let mut phased = bell.clone();
+    phased.x(0).z(0).x(0).z(0);
+    let mut flipped = bell.clone();
+    flipped.z(0);
+    let mut half = bell.clone();
+    half.ry(1, std::f64::consts::FRAC_PI_2);
+    let pure = (fidelity(&ideal, phased.compute_with(Runtime::BasicRT)) - 1.0).abs() < 1e-12
+        && fidelity(&ideal, flipped.compute_with(Runtime::BasicRT)) < 1e-12
+        && (fidelity(&ideal, half.compute_with(Runtime::BasicRT)) - 0.5).abs() < 1e-12;
+    println!(
+        "|⟨a|b⟩|²: 1 up to a global phase, 0 for |Φ-⟩, ½ halfway: {}",
+        if pure { "✓" } else { "✗" }
+    );
+
+    let n = 6;
+    let mut ghz = QuantumCircuit::new(n);
+    ghz.h(0);
+    for q in 1..n {
+        ghz.cnot(q - 1, q);
+    }
+    let amplitudes: Vec<_> = {
+        let state = ghz.clone().compute_with(Runtime::BasicRT).clone();
+        (0..state.size()).map(|i| state.get(i)).collect()
+    };
+    let noisy = |p: f64| {
+        let mut noise = NoiseModel::new();
+        noise.add_gate_noise(NoiseChannel::depolarising(p));
+        DensityMatrix::from_circuit(&ghz, &noise)
+    };
+    let (light, heavy) = (noisy(0.01), noisy(0.05));
+    let pure_dm = DensityMatrix::from_state_vector(&amplitudes);
+
+    let start = Instant::now();
+    let mixed = light.fidelity(&heavy);
+    let elapsed = start.elapsed();
+    let uhlmann = (light.fidelity(&pure_dm) - light.fidelity_with_pure_state(&amplitudes)).abs()
+        < 1e-9
+        && (pure_dm.fidelity(&heavy) - heavy.fidelity_with_pure_state(&amplitudes)).abs() < 1e-9
+        && (mixed - heavy.fidelity(&light)).abs() < 1e-9
+        && (light.fidelity(&light) - 1.0).abs() < 1e-9
+        && mixed < 1.0;
+    println!(
+        "GHZ-{} at 1% vs 5% depolarising: F = {:.6} in {:?}, against the ideal {:.6} / {:.6}",
+        n,
+        mixed,
+        elapsed,
+        light.fidelity_with_pure_state(&amplitudes),
+        heavy.fidelity_with_pure_state(&amplitudes)
+    );
+
+    // Diagonal states commute, leaving the classical (Σ √(pᵢ qᵢ))².
+    let (p, q): ([f64; 4], [f64; 4]) = ([0.5, 0.25, 0.125, 0.125], [0.1, 0.2, 0.3, 0.4]);
+    let diagonal = |weights: &[f64]| {
+        let mut dm = DensityMatrix::new(2);
+        for (i, &w) in weights.iter().enumerate() {
+            dm.set(i, i, complex!(w, 0.0));
+        }
+        dm
+    };
+    let bhattacharyya: f64 = p.iter().zip(&q).map(|(a, b)| (a * b).sqrt()).sum();
+    let classical =
+        (diagonal(&p).fidelity(&diagonal(&q)) - bhattacharyya * bhattacharyya).abs() < 1e-12;
+    println!(
+        "Pure limits, symmetry, F(ρ, ρ) = 1 and the classical limit: {}\n",
+        if uhlmann && classical { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: "DM: fidelity".to_string(),
+        num_qubits: n,
+        basic_time: elapsed,
+        mt_time: elapsed,
+        results_match: pure && uhlmann && classical,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+pub fn test_bloch_vector(results: &mut Vec<BenchmarkResult>) {
+    print_section("Bloch Vectors");
+
+    let near = |(x, y, z): (f64, f64, f64), (a, b, c): (f64, f64, f64)| {
+        (x - a).abs() < 1e-12 && (y - b).abs() < 1e-12 && (z - c).abs() < 1e-12
+    };
+    let (theta, phi) = (1.1, 0.4);
+    let mut circuit = QuantumCircuit::new(5);
+    circuit.h(1).h(2).s(2).rx(3, theta).u3(4, theta, phi, 0.3);
+    let state = circuit.compute_with(Runtime::BasicRT);
+    let expected = [
+        (0.0, 0.0, 1.0),
+        (1.0, 0.0, 0.0),
+        (0.0, 1.0, 0.0),
+        (0.0, -theta.sin(), theta.cos()),
+        (
+            theta.sin() * phi.cos(),
+            theta.sin() * phi.sin(),
+            theta.cos(),
+        ),
+    ];
+    let poles = (0..5).all(|q| near(state.bloch_vector(q), expected[q]));
+    for q in 0..5 {
+        let (x, y, z) = state.bloch_vector(q);
+        println!("  qubit {}: ({:+.4}, {:+.4}, {:+.4})", q, x, y, z);
+    }
+    println!(
+        "|0⟩, |+⟩, |+i⟩, Rx(θ)|0⟩ and U3(θ, φ, λ)|0⟩ land where expected: {}",
+        if poles { "✓" } else { "✗" }
+    );
+
+    let mut bell = QuantumCircuit::new(2);
+    bell.h(0).cnot(0, 1);
+    let centre = near(
+        bell.compute_with(Runtime::BasicRT).bloch_vector(1),
+        (0.0, 0.0, 0.0),
+    );
+
+    let mut plus = DensityMatrix::new(1);
+    plus.apply_unitary(&gates::HADAMARD.matrix, &[0]);
+    plus.apply_noise_channel(&NoiseChannel::depolarising(0.1), 0);
+    let shrunk = near(plus.bloch_vector(0), (1.0 - 0.4 / 3.0, 0.0, 0.0));
+    println!(
+        "Bell pair qubit at the centre: {}, 10% depolarising shrinks |+⟩ by 4p/3: {}",
+        if centre { "✓" } else { "✗" },
+        if shrunk { "✓" } else { "✗" }
+    );
+
+    let n = 8;
+    let mut circuit = library::random_circuit(n, 6, 3538, library::GateSet::Universal);
+    let state = circuit.compute_with(Runtime::StructureAwareRT);
+    let amplitudes: Vec<_> = (0..state.size()).map(|i| state.get(i)).collect();
+    let dm = DensityMatrix::from_state_vector(&amplitudes);
+
+    let start = Instant::now();
+    let from_dm: Vec<_> = (0..n).map(|q| dm.bloch_vector(q)).collect();
+    let dm_time = start.elapsed();
+    let start = Instant::now();
+    let from_state: Vec<_> = (0..n).map(|q| state.bloch_vector(q)).collect();
+    let state_time = start.elapsed();
+    let agree = from_dm
+        .iter()
+        .zip(&from_state)
+        .enumerate()
+        .all(|(q, (&a, &b))| {
+            let (x, y, z) = b;
+            let radius = x * x + y * y + z * z;
+            let purity = state.reduced_density_matrix(&[q]).purity();
+            near(a, b) && (radius - (2.0 * purity - 1.0)).abs() < 1e-12
+        });
+    println!(
+        "{} qubits: density matrix {:?}, state vector {:?}, agree with r² = 2·purity − 1: {}\n",
+        n,
+        dm_time,
+        state_time,
+        if agree { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: "Bloch vectors".to_string(),
+        num_qubits: n,
+        basic_time: dm_time,
+        mt_time: state_time,
+        results_match: poles && centre && shrunk && agree,
+        peak_bytes: memory::take_peak(),
     });
 }
 
@@ -52,7 +386,10 @@ pub fn test_noise_channels(results: &mut Vec<BenchmarkResult>) {
 
     let channels: Vec<(&str, NoiseChannel)> = vec![
         ("Depolarising (p=0.1)", NoiseChannel::depolarising(0.1)),
-        ("Amplitude Damping (γ=0.2)", NoiseChannel::amplitude_damping(0.2)),
+        (
+            "Amplitude Damping (γ=0.2)",
+            NoiseChannel::amplitude_damping(0.2),
+        ),
         ("Phase Damping (γ=0.2)", NoiseChannel::phase_damping(0.2)),
         ("Bit Flip (p=0.1)", NoiseChannel::bit_flip(0.1)),
         ("Phase Flip (p=0.1)", NoiseChannel::phase_flip(0.1)),
@@ -87,9 +424,11 @@ pub fn test_noise_channels(results: &mut Vec<BenchmarkResult>) {
 
         results.push(BenchmarkResult {
             name: format!("Noise: {}", name),
+            num_qubits: 1,
             basic_time: elapsed,
             mt_time: elapsed,
             results_match: purity_decreased,
+            peak_bytes: memory::take_peak(),
         });
     }
 }
@@ -129,13 +468,18 @@ pub fn test_noisy_circuit(results: &mut Vec<BenchmarkResult>) {
 
     println!("Bell state after 10% amplitude damping on both qubits:");
     println!("{}", dm2);
-    println!("Probabilities show decay towards |00⟩: {:?}", dm2.probabilities());
+    println!(
+        "Probabilities show decay towards |00⟩: {:?}",
+        dm2.probabilities()
+    );
 
     results.push(BenchmarkResult {
         name: "Noisy Bell circuit".to_string(),
+        num_qubits: 2,
         basic_time: elapsed,
         mt_time: elapsed,
         results_match: fidelity > 0.8 && fidelity < 1.0,
+        peak_bytes: memory::take_peak(),
     });
 
     println!();
@@ -145,7 +489,11 @@ pub fn test_noisy_circuit(results: &mut Vec<BenchmarkResult>) {
     let mut dm_t1 = DensityMatrix::from_state_vector(&one_state);
 
     println!("Simulating T1 decay of |1⟩ state:");
-    println!("  Initial: P(0)={:.4}, P(1)={:.4}", dm_t1.probabilities()[0], dm_t1.probabilities()[1]);
+    println!(
+        "  Initial: P(0)={:.4}, P(1)={:.4}",
+        dm_t1.probabilities()[0],
+        dm_t1.probabilities()[1]
+    );
 
     let t1_channel = NoiseChannel::amplitude_damping(0.3);
     for step in 1..=5 {
@@ -164,9 +512,309 @@ pub fn test_noisy_circuit(results: &mut Vec<BenchmarkResult>) {
 
     results.push(BenchmarkResult {
         name: "T1 decay simulation".to_string(),
+        num_qubits: 1,
         basic_time: std::time::Duration::from_micros(0),
         mt_time: std::time::Duration::from_micros(0),
         results_match: decayed,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+/// Total variation distance between `estimate` and the ideal GHZ outcomes.
+fn distance_from_ghz(estimate: &BTreeMap<String, f64>) -> f64 {
+    let ideal = |outcome: &str| match outcome {
+        "000" | "111" => 0.5,
+        _ => 0.0,
+    };
+    (0..8)
+        .map(|i| format!("{:03b}", i))
+        .map(|key| (estimate.get(&key).copied().unwrap_or(0.0) - ideal(&key)).abs())
+        .sum::<f64>()
+        / 2.0
+}
+
+fn frequencies(counts: &Counts) -> BTreeMap<String, f64> {
+    counts
+        .iter()
+        .map(|(outcome, _)| (outcome.to_string(), counts.probability(outcome)))
+        .collect()
+}
+
+pub fn test_readout_mitigation(results: &mut Vec<BenchmarkResult>) {
+    print_section("Readout Error Mitigation");
+
+    let error = ReadoutError::new(&[(0.02, 0.08), (0.05, 0.10), (0.03, 0.06)]);
+    let shots = 20_000;
+    let mut seed = 0;
+    let mut run = |mut circuit: QuantumCircuit| {
+        seed += 1;
+        error.apply(&circuit.sample(shots, Some(seed)), Some(seed + 1000))
+    };
+
+    let start = Instant::now();
+    let calibration = ReadoutCalibration::measure(3, &mut run);
+    let mut ghz = QuantumCircuit::ghz(3);
+    ghz.measure_all();
+    let noisy = run(ghz);
+    let inverse = calibration.mitigate(&noisy, Mitigation::Inverse);
+    let least_squares = calibration.mitigate(&noisy, Mitigation::LeastSquares);
+    let elapsed = start.elapsed();
+
+    let raw_distance = distance_from_ghz(&frequencies(&noisy));
+    let inverse_distance = distance_from_ghz(&inverse);
+    let ls_distance = distance_from_ghz(&least_squares);
+    let ls_is_distribution = least_squares.values().all(|&p| p >= 0.0)
+        && (least_squares.values().sum::<f64>() - 1.0).abs() < 1e-9;
+
+    println!(
+        "Assignment fidelity: {:.4}",
+        calibration.assignment_fidelity()
+    );
+    println!("Distance from ideal GHZ outcomes:");
+    println!("  noisy counts    {:.4}", raw_distance);
+    println!("  inverse         {:.4}", inverse_distance);
+    println!("  least squares   {:.4}", ls_distance);
+    let improved = inverse_distance < raw_distance / 4.0 && ls_distance < raw_distance / 4.0;
+    println!(
+        "Mitigation recovers the ideal distribution: {}",
+        if improved { "✓" } else { "✗" }
+    );
+    println!(
+        "Least squares returns a probability distribution: {}\n",
+        if ls_is_distribution { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: "Readout mitigation (GHZ-3)".to_string(),
+        num_qubits: 3,
+        basic_time: elapsed,
+        mt_time: elapsed,
+        results_match: improved && ls_is_distribution,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+pub fn test_scheduling(results: &mut Vec<BenchmarkResult>) {
+    print_section("Gate Scheduling");
+
+    let ghz = QuantumCircuit::ghz(4);
+    let depth = ghz
+        .schedule(&GateDurations::unit(), SchedulePolicy::Asap)
+        .total_time();
+    println!("GHZ-4 depth with unit durations: {}", depth);
+
+    // Durations in nanoseconds, with a faster CNOT than the two-qubit default.
+    let mut durations = GateDurations::new(35.0, 300.0, 600.0, 1000.0);
+    durations.set("CNOT", 250.0);
+    let mut circuit = QuantumCircuit::new(3);
+    circuit.h(0).x(2).cnot(0, 1).measure_all();
+
+    let start = Instant::now();
+    let asap = circuit.schedule(&durations, SchedulePolicy::Asap);
+    let alap = circuit.schedule(&durations, SchedulePolicy::Alap);
+    let elapsed = start.elapsed();
+
+    for (name, schedule) in [("ASAP", &asap), ("ALAP", &alap)] {
+        println!("{} schedule, total {} ns:", name, schedule.total_time());
+        for ((op, start), duration) in schedule
+            .operations()
+            .iter()
+            .zip(schedule.start_times())
+            .zip(schedule.durations())
+        {
+            println!("  {:>6} – {:>6}  {:?}", start, start + duration, op);
+        }
+        println!("  idle per qubit: {:?}", schedule.idle_times());
+    }
+
+    let mut fenced = QuantumCircuit::new(2);
+    fenced.h(0).barrier_all().x(1);
+    let fenced = fenced.schedule(&durations, SchedulePolicy::Asap);
+    println!("X after a barrier starts at {} ns", fenced.start_times()[2]);
+
+    let correct = depth == 4.0
+        && asap.total_time() == 1285.0
+        && alap.total_time() == 1285.0
+        && asap.start_times()[1] == 0.0
+        && alap.start_times()[1] == 250.0
+        && asap.idle_times() == vec![0.0, 35.0, 250.0]
+        && alap.idle_periods(2) == vec![(0.0, 250.0)]
+        && fenced.start_times()[2] == 35.0;
+    println!(
+        "Start times, wall time and idle times: {}\n",
+        if correct { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: "Gate scheduling".to_string(),
+        num_qubits: 3,
+        basic_time: elapsed,
+        mt_time: elapsed,
+        results_match: correct,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+pub fn test_trajectories(results: &mut Vec<BenchmarkResult>) {
+    print_section("Monte Carlo Quantum Trajectories");
+
+    // GHZ-3 with depolarising noise after every gate and amplitude damping
+    // after each CNOT, against the same channels on a density matrix.
+    let depolarising = NoiseChannel::depolarising(0.05);
+    let damping = NoiseChannel::amplitude_damping(0.1);
+    let mut noise = NoiseModel::new();
+    noise
+        .add_gate_noise(depolarising.clone())
+        .add_gate_type_noise("CNOT", damping.clone());
+    let simulator = TrajectorySimulator::new(noise).parallel();
+
+    let mut circuit = QuantumCircuit::new(3);
+    circuit.h(0).cnot(0, 1).cnot(1, 2);
+
+    let mut dm = DensityMatrix::new(3);
+    dm.apply_unitary(&gates::HADAMARD.matrix, &[0]);
+    dm.apply_noise_channel(&depolarising, 0);
+    for (c, t) in [(0, 1), (1, 2)] {
+        dm.apply_unitary(&gates::CNOT.matrix, &[c, t]);
+        for channel in [&depolarising, &damping] {
+            dm.apply_noise_channel(channel, c);
+            dm.apply_noise_channel(channel, t);
+        }
+    }
+
+    let shots = 20000;
+    let start = Instant::now();
+    let counts = simulator.sample(&circuit, shots, Some(3508));
+    let elapsed = start.elapsed();
+    let distance = dm
+        .probabilities()
+        .iter()
+        .enumerate()
+        .map(|(i, p)| (counts.probability(&format!("{:03b}", i)) - p).abs())
+        .sum::<f64>()
+        / 2.0;
+    let agrees = distance < 0.02;
+    println!(
+        "{} trajectories vs density matrix: total variation distance {:.4} {}",
+        shots,
+        distance,
+        if agrees { "✓" } else { "✗" }
+    );
+
+    // 16 qubits: a density matrix would take 64 GiB.
+    let width = 16;
+    let mut ghz = QuantumCircuit::new(width);
+    ghz.h(0);
+    for q in 0..width - 1 {
+        ghz.cnot(q, q + 1);
+    }
+    let mut flips = NoiseModel::new();
+    flips.add_gate_noise(NoiseChannel::bit_flip(0.01));
+    let flips = TrajectorySimulator::new(flips).parallel();
+    let start = Instant::now();
+    let counts = flips.sample(&ghz, 100, Some(16));
+    let wide_time = start.elapsed();
+    let ideal = (counts.get(&"0".repeat(width)) + counts.get(&"1".repeat(width))) as f64 / 100.0;
+    let wide_ok = ideal > 0.5 && ideal < 0.95;
+    println!(
+        "{}-qubit GHZ with 1% bit flips, 100 trajectories in {:.2?}: {:.1}% ideal outcomes {}\n",
+        width,
+        wide_time,
+        100.0 * ideal,
+        if wide_ok { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: "Quantum trajectories".to_string(),
+        num_qubits: width,
+        basic_time: elapsed,
+        mt_time: wide_time,
+        results_match: agrees && wide_ok,
+        peak_bytes: memory::take_peak(),
     });
 }
 
+pub fn test_noise_model(results: &mut Vec<BenchmarkResult>) {
+    print_section("Noise Models");
+
+    let depolarising = NoiseChannel::depolarising(0.03);
+    let damping = NoiseChannel::amplitude_damping(0.05);
+    let idle = NoiseChannel::bit_flip(0.02);
+    let mut noise = NoiseModel::new();
+    noise
+        .add_gate_noise(depolarising.clone())
+        .add_qubit_noise(2, damping.clone())
+        .add_idle_noise(idle.clone());
+
+    let mut circuit = QuantumCircuit::new(3);
+    circuit.h(0).cnot(0, 1).cnot(1, 2);
+    circuit.with_noise(noise.clone());
+
+    // Layers: H(0) | CNOT(0, 1) | CNOT(1, 2); qubit 1 idles in the first,
+    // qubit 2 in the first two and qubit 0 in the last.
+    let mut expected = DensityMatrix::new(3);
+    expected.apply_noise_channel(&idle, 1);
+    expected.apply_noise_channel(&idle, 2);
+    expected.apply_unitary(&gates::HADAMARD.matrix, &[0]);
+    expected.apply_noise_channel(&depolarising, 0);
+    expected.apply_noise_channel(&idle, 2);
+    expected.apply_unitary(&gates::CNOT.matrix, &[0, 1]);
+    expected.apply_noise_channel(&depolarising, 0);
+    expected.apply_noise_channel(&depolarising, 1);
+    expected.apply_noise_channel(&idle, 0);
+    expected.apply_unitary(&gates::CNOT.matrix, &[1, 2]);
+    for q in [1, 2] {
+        expected.apply_noise_channel(&depolarising, q);
+    }
+    expected.apply_noise_channel(&damping, 2);
+
+    let start = Instant::now();
+    let dm = circuit.density_matrix();
+    let elapsed = start.elapsed();
+    let matches = dm
+        .data
+        .iter()
+        .zip(&expected.data)
+        .all(|(a, b)| (*a - *b).norm2() < 1e-24);
+    println!(
+        "Density matrix places gate, qubit and idle noise: purity {:.4} {}",
+        dm.purity(),
+        if matches { "✓" } else { "✗" }
+    );
+
+    let shots = 20000;
+    let counts = circuit.run_shots_parallel(shots, Some(3509));
+    let distance = dm
+        .probabilities()
+        .iter()
+        .enumerate()
+        .map(|(i, p)| (counts.probability(&format!("{:03b}", i)) - p).abs())
+        .sum::<f64>()
+        / 2.0;
+    let runtime = RuntimeConfig::new()
+        .parallel()
+        .noisy(noise)
+        .sample(&circuit, shots, Some(3509));
+    let sampled = distance < 0.02 && runtime.iter().eq(counts.iter());
+    println!(
+        "Noisy sampling follows the density matrix (distance {:.4}), same via RuntimeConfig::noisy: {}",
+        distance,
+        if sampled { "✓" } else { "✗" }
+    );
+
+    let ideal = circuit.compute_with(Runtime::BasicRT).get(7).norm2();
+    let noiseless = (ideal - 0.5).abs() < 1e-12;
+    println!(
+        "compute stays noiseless: {}\n",
+        if noiseless { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: "Noise model".to_string(),
+        num_qubits: 3,
+        basic_time: elapsed,
+        mt_time: elapsed,
+        results_match: matches && sampled && noiseless,
+        peak_bytes: memory::take_peak(),
+    });
+}
@@ -1,6 +1,23 @@
-use crate::common::{benchmark_circuit, print_circuit, print_section, BenchmarkResult};
-use libpsi_core::QuantumCircuit;
+use crate::clifford::random_clifford;
+use crate::common::{
+    benchmark_circuit, print_circuit, print_section, states_equal, BenchmarkResult,
+};
+use crate::memory;
+use libpsi_core::{
+    complex, fidelity, gates, library, Angle, Complex, CustomGate, CustomGateBuilder,
+    CustomGateDefinition, ExtendedStabilizer, GateOp, Hamiltonian, Matrix, NoiseChannel,
+    NoiseModel, Parameter, Pauli, ProductState, QuantumCircuit, QuantumState, Runtime,
+    RuntimeConfig, SnapshotKind, SparsePauliOp, TimeEvolution, TruncatedState, Vector,
+};
+use libpsi_visualizer::{
+    AmplitudeRenderer, CircuitDiff, DiffRenderer, HorizontalRenderer, VerticalRenderer,
+};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use std::f64::consts::PI;
+use std::fs;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 pub fn run_all(results: &mut Vec<BenchmarkResult>) {
     println!("═══════════════════════════════════════════════════════════════");
@@ -12,7 +29,26 @@ pub fn run_all(results: &mut Vec<BenchmarkResult>) {
     test_phase_gates(results);
     test_general_unitaries(results);
     test_controlled_rotations(results);
+    test_two_qubit_rotations(results);
+    test_ibm_two_qubit_gates(results);
     test_variational_circuit(results);
+    test_extended_stabilizer(results);
+    test_extended_stabilizer_sampling(results);
+    test_product_state(results);
+    test_truncated_state(results);
+    test_qubit_reuse(results);
+    test_time_evolution(results);
+    test_inverse(results);
+    test_controlled(results);
+    test_power(results);
+    test_parameters(results);
+    test_adjoint_gradients(results);
+    test_sparse_pauli_op(results);
+    test_equivalence(results);
+    test_unitary(results);
+    test_initial_state(results);
+    test_serialization(results);
+    test_state_files(results);
 }
 
 pub fn test_fixed_gates(results: &mut Vec<BenchmarkResult>) {
@@ -27,6 +63,10 @@ pub fn test_fixed_gates(results: &mut Vec<BenchmarkResult>) {
     print_circuit(&builder());
     results.push(benchmark_circuit("Non-Clifford fixed gates", builder));
 
+    println!("Trace:");
+    builder().compute_with_config(RuntimeConfig::new().traced(1e-6));
+    println!();
+
     let mut display = builder();
     display.compute();
     println!("{}\n", display);
@@ -51,6 +91,22 @@ pub fn test_rotation_gates(results: &mut Vec<BenchmarkResult>) {
     let mut display = builder();
     display.compute();
     println!("{}\n", display);
+
+    let mut measured = QuantumCircuit::with_classical(2, 2);
+    measured
+        .rx(0, PI / 3.0)
+        .ry(1, PI / 2.0)
+        .cry(0, 1, PI / 2.0)
+        .measure_all();
+    let state = measured.state().clone();
+    println!(
+        "Measurement probabilities:\n{}",
+        HorizontalRenderer::new(&measured).with_probabilities(&state)
+    );
+    println!(
+        "{}",
+        VerticalRenderer::new(&measured).with_probabilities(&state)
+    );
 }
 
 pub fn test_phase_gates(results: &mut Vec<BenchmarkResult>) {
@@ -67,7 +123,8 @@ pub fn test_phase_gates(results: &mut Vec<BenchmarkResult>) {
 
     let mut display = builder();
     display.compute();
-    println!("{}\n", display);
+    println!("{}", display);
+    println!("Amplitudes:\n{}", AmplitudeRenderer::new(display.state()));
 }
 
 pub fn test_general_unitaries(results: &mut Vec<BenchmarkResult>) {
@@ -116,6 +173,187 @@ pub fn test_controlled_rotations(results: &mut Vec<BenchmarkResult>) {
     println!("{}\n", display);
 }
 
+pub fn test_two_qubit_rotations(results: &mut Vec<BenchmarkResult>) {
+    print_section("Two-Qubit Rotations: Rxx, Ryy, Rzz");
+
+    // One Trotter step of a Heisenberg chain.
+    let builder = || {
+        let mut circuit = QuantumCircuit::new(4);
+        circuit.h(0).x(2);
+        for q in 0..3 {
+            circuit
+                .rxx(q, q + 1, 0.3)
+                .ryy(q, q + 1, 0.5)
+                .rzz(q, q + 1, 0.7);
+        }
+        circuit
+    };
+
+    print_circuit(&builder());
+    let mut result = benchmark_circuit("Two-qubit rotations (4 qubits)", builder);
+
+    // Each rotation against its textbook decomposition, from a state with
+    // every amplitude nonzero.
+    let prepared = || {
+        let mut circuit = QuantumCircuit::new(3);
+        circuit
+            .ry(0, 0.4)
+            .ry(1, 1.1)
+            .ry(2, 2.3)
+            .rz(1, 0.6)
+            .cnot(0, 2);
+        circuit
+    };
+    let theta = 0.9;
+    let (mut rzz, mut rzz_decomposed) = (prepared(), prepared());
+    rzz.rzz(0, 2, theta);
+    rzz_decomposed.cnot(0, 2).rz(2, theta).cnot(0, 2);
+    let (mut rxx, mut rxx_decomposed) = (prepared(), prepared());
+    rxx.rxx(2, 1, theta);
+    rxx_decomposed.h(2).h(1).rzz(2, 1, theta).h(2).h(1);
+    let (mut ryy, mut ryy_decomposed) = (prepared(), prepared());
+    ryy.ryy(1, 0, theta);
+    ryy_decomposed.sdg(1).sdg(0).rxx(1, 0, theta).s(1).s(0);
+    let mut all_match = true;
+    for (label, mut gate, mut decomposed) in [
+        ("Rzz = CNOT·Rz·CNOT", rzz, rzz_decomposed),
+        ("Rxx = (H⊗H)·Rzz·(H⊗H)", rxx, rxx_decomposed),
+        ("Ryy = (S⊗S)·Rxx·(S†⊗S†)", ryy, ryy_decomposed),
+    ] {
+        let matches = states_equal(gate.compute(), decomposed.compute());
+        println!("{}: {}", label, if matches { "✓" } else { "✗" });
+        all_match &= matches;
+    }
+
+    let mut undone = builder();
+    undone.append(&builder().inverse(), &[0, 1, 2, 3]);
+    let inverse_ok = states_equal(undone.compute(), QuantumCircuit::new(4).compute());
+    println!(
+        "Step followed by its inverse is the identity: {}",
+        if inverse_ok { "✓" } else { "✗" }
+    );
+
+    let mut extended = builder();
+    extended.compute_with_backend(&mut ExtendedStabilizer::new(0));
+    let extended_ok =
+        (fidelity(extended.computed_state().unwrap(), builder().compute()) - 1.0).abs() < 1e-9;
+    println!(
+        "Extended stabilizer matches the state vector: {}\n",
+        if extended_ok { "✓" } else { "✗" }
+    );
+
+    result.results_match &= all_match && inverse_ok && extended_ok;
+    results.push(result);
+}
+
+pub fn test_ibm_two_qubit_gates(results: &mut Vec<BenchmarkResult>) {
+    print_section("Two-Qubit Gates: CY, CH, CSX, ECR, DCX");
+
+    let builder = || {
+        let mut circuit = QuantumCircuit::new(4);
+        circuit.h(0).ry(1, 0.8).h(3);
+        circuit
+            .cy(0, 1)
+            .ch(1, 2)
+            .csx(2, 3)
+            .ecr(3, 0)
+            .dcx(0, 2)
+            .t(1)
+            .ecr(1, 2);
+        circuit
+    };
+
+    print_circuit(&builder());
+    let mut result = benchmark_circuit("CY, CH, CSX, ECR, DCX (4 qubits)", builder);
+
+    // Each gate against its definition, from a state with every amplitude
+    // nonzero.
+    let prepared = || {
+        let mut circuit = QuantumCircuit::new(3);
+        circuit
+            .ry(0, 0.4)
+            .ry(1, 1.1)
+            .ry(2, 2.3)
+            .rz(1, 0.6)
+            .cnot(0, 2);
+        circuit
+    };
+    let single = |name: &str, matrix: &Matrix<Complex<f64>>| {
+        Arc::new(CustomGate::from_matrix(name, matrix.clone()))
+    };
+    let (mut cy, mut cy_reference) = (prepared(), prepared());
+    cy.cy(2, 0);
+    cy_reference.mcu(&single("Y", &gates::PAULI_Y.matrix), &[2], 0);
+    let (mut ch, mut ch_reference) = (prepared(), prepared());
+    ch.ch(0, 1);
+    ch_reference.mcu(&single("H", &gates::HADAMARD.matrix), &[0], 1);
+    let (mut csx, mut csx_reference) = (prepared(), prepared());
+    csx.csx(1, 2);
+    csx_reference.mcu(&single("√X", &gates::SX_GATE.matrix), &[1], 2);
+    let (mut ecr, mut ecr_reference) = (prepared(), prepared());
+    ecr.ecr(2, 1);
+    ecr_reference.x(2).h(1).rzz(2, 1, -PI / 2.0).h(1);
+    let (mut dcx, mut dcx_reference) = (prepared(), prepared());
+    dcx.dcx(0, 2);
+    dcx_reference.cnot(0, 2).cnot(2, 0);
+    let mut all_match = true;
+    for (label, mut gate, mut reference) in [
+        ("CY = Y controlled", cy, cy_reference),
+        ("CH = H controlled", ch, ch_reference),
+        ("C√X = √X controlled", csx, csx_reference),
+        ("ECR = exp(iπ/4·Z⊗X)·(X⊗I)", ecr, ecr_reference),
+        ("DCX = CNOT one way, then the other", dcx, dcx_reference),
+    ] {
+        let matches = states_equal(gate.compute(), reference.compute());
+        println!("{}: {}", label, if matches { "✓" } else { "✗" });
+        all_match &= matches;
+    }
+
+    let mut undone = builder();
+    undone.append(&builder().inverse(), &[0, 1, 2, 3]);
+    let inverse_ok = states_equal(undone.compute(), QuantumCircuit::new(4).compute());
+    println!(
+        "Circuit followed by its inverse is the identity: {}",
+        if inverse_ok { "✓" } else { "✗" }
+    );
+
+    let mut extended = builder();
+    extended.compute_with_backend(&mut ExtendedStabilizer::new(0));
+    let extended_ok =
+        (fidelity(extended.computed_state().unwrap(), builder().compute()) - 1.0).abs() < 1e-9;
+    // CY, ECR and DCX are Clifford and run on the tableau as well.
+    let clifford = || {
+        let mut circuit = QuantumCircuit::new(3);
+        circuit.h(0).h(1).s(1);
+        circuit.cy(0, 2).ecr(1, 0).dcx(2, 1).ecr(0, 2);
+        circuit
+    };
+    let tableau_ok = (fidelity(
+        clifford().compute_with(Runtime::Stabilizer),
+        clifford().compute_with(Runtime::BasicRT),
+    ) - 1.0)
+        .abs()
+        < 1e-9;
+    println!(
+        "Extended stabilizer matches the state vector: {}, tableau too for CY, ECR, DCX: {}",
+        if extended_ok { "✓" } else { "✗" },
+        if tableau_ok { "✓" } else { "✗" }
+    );
+
+    // The names IBM backends export, read back from OpenQASM.
+    let qasm = libpsi_qasm::to_qasm(&builder()).expect("every gate has an OpenQASM name");
+    let qasm_ok = libpsi_qasm::parse(&qasm)
+        .map(|mut parsed| states_equal(parsed.compute(), builder().compute()))
+        .unwrap_or(false);
+    println!(
+        "OpenQASM round trip through cy, ch, csx, ecr, dcx: {}\n",
+        if qasm_ok { "✓" } else { "✗" }
+    );
+
+    result.results_match &= all_match && inverse_ok && extended_ok && tableau_ok && qasm_ok;
+    results.push(result);
+}
+
 pub fn test_variational_circuit(results: &mut Vec<BenchmarkResult>) {
     print_section("Variational Circuit (VQE-like)");
 
@@ -131,7 +369,1867 @@ pub fn test_variational_circuit(results: &mut Vec<BenchmarkResult>) {
     print_circuit(&builder());
     results.push(benchmark_circuit("Variational circuit (3 qubits)", builder));
 
+    let mut updated = QuantumCircuit::new(3);
+    updated.ry(0, 0.5).ry(1, 0.35).ry(2, 0.7);
+    updated.cnot(0, 1).cnot(1, 2);
+    updated.rx(0, 0.2).rx(2, 0.6);
+    updated.cz(0, 2).rz(1, 0.1);
+    let diff = CircuitDiff::new(&builder(), &updated);
+    println!("Diff after parameter update:\n{}", DiffRenderer::new(&diff));
+
     let mut display = builder();
     display.compute();
     println!("{}\n", display);
 }
+
+/// A random Clifford layer with `non_clifford` gates of every kind
+/// sprinkled through it.
+fn random_near_clifford(
+    num_qubits: usize,
+    non_clifford: usize,
+    rng: &mut StdRng,
+) -> QuantumCircuit {
+    let mut circuit = random_clifford(num_qubits, 10, rng);
+    let composite = Arc::new(CustomGateBuilder::new("ct", 2).h(1).t(1).cnot(0, 1).build());
+    for _ in 0..non_clifford {
+        let a = rng.random_range(0..num_qubits);
+        let b = (a + rng.random_range(1..num_qubits)) % num_qubits;
+        let theta = rng.random_range(-PI..PI);
+        match rng.random_range(0..8) {
+            0 => circuit.t(a),
+            1 => circuit.tdg(a),
+            2 => circuit.rx(a, theta),
+            3 => circuit.ry(a, theta),
+            4 => circuit.u3(a, theta, 0.3, -0.7),
+            5 => circuit.crz(a, b, theta),
+            6 => circuit.cp(a, b, theta),
+            _ => circuit.custom(&composite, &[a, b]),
+        };
+        for op in random_clifford(num_qubits, 6, rng).operations() {
+            circuit.push(op.clone());
+        }
+    }
+    circuit.push(GateOp::CCNOT(0, 1, 2));
+    circuit
+}
+
+/// The sum-over-Cliffords state matches the state vector on random
+/// near-Clifford circuits, and reaches amplitudes far beyond it.
+pub fn test_extended_stabilizer(results: &mut Vec<BenchmarkResult>) {
+    print_section("Extended Stabilizer Simulation");
+
+    let mut rng = StdRng::seed_from_u64(3485);
+    let start = Instant::now();
+    let mut all_match = true;
+    let mut max_rank = 0;
+    let mut fredkin = QuantumCircuit::new(3);
+    fredkin.h(0).ry(1, 0.4).cswap(0, 1, 2).h(2);
+    let circuits = (0..20)
+        .map(|_| random_near_clifford(5, 3, &mut rng))
+        .chain([fredkin]);
+    for circuit in circuits {
+        let mut reference = circuit.clone();
+        reference.compute_with(Runtime::BasicRT);
+        let mut backend = ExtendedStabilizer::new(0);
+        let mut extended = circuit.clone();
+        extended.compute_with_backend(&mut backend);
+        max_rank = max_rank.max(ExtendedStabilizer::from_circuit(&circuit).unwrap().rank());
+        let agree = (fidelity(
+            reference.computed_state().unwrap(),
+            extended.computed_state().unwrap(),
+        ) - 1.0)
+            .abs()
+            < 1e-9;
+        all_match &= agree;
+    }
+    println!(
+        "20 random Clifford+rotation circuits and a Fredkin (5 qubits, up to {} terms) match the state vector: {}",
+        max_rank,
+        if all_match { "✓" } else { "✗" }
+    );
+
+    // A 60-qubit GHZ state with a T on one qubit: (|0…0⟩ + e^{iπ/4}|1…1⟩)/√2.
+    let n = 60;
+    let mut ghz = QuantumCircuit::new(n);
+    ghz.h(0);
+    for q in 1..n {
+        ghz.cnot(q - 1, q);
+    }
+    ghz.t(n - 1);
+    let state = ExtendedStabilizer::from_circuit(&ghz).unwrap();
+    let zeros = state.amplitude(&vec![false; n]);
+    let ones = state.amplitude(&vec![true; n]);
+    let mut other = vec![false; n];
+    other[17] = true;
+    let relative = ones / zeros;
+    let ghz_ok = (zeros.norm2() - 0.5).abs() < 1e-9
+        && (ones.norm2() - 0.5).abs() < 1e-9
+        && (relative.phase() - PI / 4.0).abs() < 1e-9
+        && state.probability(&other) < 1e-12;
+    let elapsed = start.elapsed();
+    println!(
+        "60-qubit GHZ with T: ⟨1…1|ψ⟩/⟨0…0|ψ⟩ = {} with {} terms {}\n",
+        relative,
+        state.rank(),
+        if ghz_ok { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: "Extended stabilizer".to_string(),
+        num_qubits: n,
+        basic_time: elapsed,
+        mt_time: elapsed,
+        results_match: all_match && ghz_ok,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+/// Gate-by-gate sampling reproduces the exact distribution on a small
+/// circuit, and samples a 50-qubit one with 14 T gates.
+pub fn test_extended_stabilizer_sampling(results: &mut Vec<BenchmarkResult>) {
+    print_section("Extended Stabilizer Sampling");
+
+    let mut rng = StdRng::seed_from_u64(7);
+    let circuit = random_near_clifford(4, 3, &mut rng);
+    let shots = 2000;
+    let start = Instant::now();
+    let counts = ExtendedStabilizer::sample(&circuit, shots, Some(1)).unwrap();
+    let mut reference = circuit.clone();
+    let exact = reference.compute_with(Runtime::BasicRT).clone();
+    let distance: f64 = (0..16)
+        .map(|i| {
+            let key: String = (0..4)
+                .map(|q| if (i >> (3 - q)) & 1 == 1 { '1' } else { '0' })
+                .collect();
+            (counts.probability(&key) - exact.get(i).norm2()).abs()
+        })
+        .sum::<f64>()
+        / 2.0;
+    let close = distance < 0.05;
+    println!(
+        "Total variation distance from the exact distribution over {} shots: {:.4} {}",
+        shots,
+        distance,
+        if close { "✓" } else { "✗" }
+    );
+
+    // Two Toffolis copy ANDs of random bits onto the last qubits.
+    let n = 50;
+    let mut wide = QuantumCircuit::with_classical(n, 6);
+    for q in 0..4 {
+        wide.h(q);
+    }
+    wide.ccnot(0, 1, n - 1).ccnot(2, 3, n - 2);
+    for (c, q) in [0, 1, 2, 3, n - 2, n - 1].into_iter().enumerate() {
+        wide.measure(q, c);
+    }
+    let wide_counts = ExtendedStabilizer::sample(&wide, 200, Some(2)).unwrap();
+    let elapsed = start.elapsed();
+    let consistent = wide_counts.iter().all(|(outcome, _)| {
+        let b: Vec<bool> = outcome.chars().map(|c| c == '1').collect();
+        b[4] == (b[2] && b[3]) && b[5] == (b[0] && b[1])
+    });
+    println!(
+        "50 qubits, 14 T gates: {} distinct outcomes over 200 shots, all with c4 = c2·c3 and c5 = c0·c1 {}\n",
+        wide_counts.len(),
+        if consistent { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: "Extended stabilizer sampling".to_string(),
+        num_qubits: n,
+        basic_time: elapsed,
+        mt_time: elapsed,
+        results_match: close && consistent && wide_counts.len() == 16,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+/// The factorised simulator matches the state vector on random circuits,
+/// splits qubits back out once they disentangle, and runs a register with
+/// late entanglement on small groups until the last layer.
+pub fn test_product_state(results: &mut Vec<BenchmarkResult>) {
+    print_section("Product-State Simulation");
+
+    let mut rng = StdRng::seed_from_u64(3493);
+    let mut all_match = true;
+    for _ in 0..20 {
+        let circuit = random_near_clifford(6, 4, &mut rng);
+        let mut reference = circuit.clone();
+        reference.compute_with(Runtime::BasicRT);
+        let mut factorised = circuit.clone();
+        factorised.compute_with_backend(&mut ProductState::new(0));
+        all_match &= (fidelity(
+            reference.computed_state().unwrap(),
+            factorised.computed_state().unwrap(),
+        ) - 1.0)
+            .abs()
+            < 1e-9;
+    }
+    println!(
+        "20 random 6-qubit circuits match the state vector: {}",
+        if all_match { "✓" } else { "✗" }
+    );
+
+    // CNOT, rotation, CNOT: entangled in between, a product state after.
+    let mut undo = QuantumCircuit::new(3);
+    undo.h(0).cnot(0, 1).rz(1, 0.3).cnot(0, 1).ry(2, 1.1);
+    let split = ProductState::from_circuit(&undo).largest_group() == 1;
+    println!(
+        "Qubits split back out after disentangling: {}",
+        if split { "✓" } else { "✗" }
+    );
+
+    // 16 qubits of single-qubit layers, entangled only by a final CNOT chain.
+    let n = 16;
+    let mut late = QuantumCircuit::new(n);
+    for layer in 0..30 {
+        for q in 0..n {
+            late.ry(q, 0.1 * (layer + q) as f64)
+                .rz(q, 0.07 * (layer * q) as f64);
+        }
+    }
+    for q in 0..n - 1 {
+        late.cnot(q, q + 1);
+    }
+    let mut dense = late.clone();
+    let start = Instant::now();
+    dense.compute_with(Runtime::SimdRT);
+    let dense_time = start.elapsed();
+    let mut factorised = late.clone();
+    let start = Instant::now();
+    factorised.compute_with_backend(&mut ProductState::new(0));
+    let factorised_time = start.elapsed();
+    let late_match = (fidelity(
+        dense.computed_state().unwrap(),
+        factorised.computed_state().unwrap(),
+    ) - 1.0)
+        .abs()
+        < 1e-9;
+    println!(
+        "{}-qubit late entanglement: SimdRT {:.3}ms, ProductState {:.3}ms {}",
+        n,
+        dense_time.as_secs_f64() * 1000.0,
+        factorised_time.as_secs_f64() * 1000.0,
+        if late_match { "✓" } else { "✗" }
+    );
+
+    // 100 qubits as 50 rotated Bell pairs: far past a state vector.
+    let mut pairs = QuantumCircuit::new(100);
+    for q in (0..100).step_by(2) {
+        pairs.ry(q, 0.5 + 0.01 * q as f64).cnot(q, q + 1);
+    }
+    let state = ProductState::from_circuit(&pairs);
+    let counts = ProductState::sample(&pairs, 500, Some(5));
+    let correlated = counts.iter().all(|(outcome, _)| {
+        let bits = outcome.as_bytes();
+        bits.chunks(2).all(|pair| pair[0] == pair[1])
+    });
+    let pairs_ok = state.largest_group() == 2 && state.groups().len() == 50 && correlated;
+    println!(
+        "100 qubits in {} groups of at most {}, every sampled pair agrees: {}\n",
+        state.groups().len(),
+        state.largest_group(),
+        if pairs_ok { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: "Product state (late CNOTs)".to_string(),
+        num_qubits: n,
+        basic_time: dense_time,
+        mt_time: factorised_time,
+        results_match: all_match && split && late_match && pairs_ok,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+/// Weakly entangling layers: small rotations on every qubit and a CNOT
+/// ring, so the state stays concentrated near |0…0⟩.
+fn weakly_entangling(num_qubits: usize, depth: usize, angle: f64) -> QuantumCircuit {
+    let mut circuit = QuantumCircuit::new(num_qubits);
+    for layer in 0..depth {
+        for q in 0..num_qubits {
+            circuit.ry(q, angle * (1.0 + 0.1 * ((q + layer) % 5) as f64));
+        }
+        for q in 0..num_qubits {
+            circuit.cnot(q, (q + 1) % num_qubits);
+        }
+    }
+    circuit
+}
+
+/// Truncation at threshold 0 is exact; higher thresholds store fewer
+/// amplitudes while the fidelity estimate tracks the true overlap.
+pub fn test_truncated_state(results: &mut Vec<BenchmarkResult>) {
+    print_section("Amplitude-Truncation Simulation");
+
+    let mut rng = StdRng::seed_from_u64(3494);
+    let mut exact = true;
+    for _ in 0..10 {
+        let circuit = random_near_clifford(6, 4, &mut rng);
+        let mut reference = circuit.clone();
+        reference.compute_with(Runtime::BasicRT);
+        let state = TruncatedState::from_circuit(&circuit, 0.0);
+        exact &= (fidelity(reference.computed_state().unwrap(), &state.to_state()) - 1.0).abs()
+            < 1e-9
+            && state.fidelity() == 1.0;
+    }
+    println!(
+        "Threshold 0 matches the state vector on 10 random circuits: {}",
+        if exact { "✓" } else { "✗" }
+    );
+
+    let n = 12;
+    let circuit = weakly_entangling(n, 4, 0.3);
+    let mut reference = circuit.clone();
+    reference.compute_with(Runtime::SimdRT);
+    let start = Instant::now();
+    let mut tracked = true;
+    println!("{}-qubit weakly entangling circuit:", n);
+    println!("  threshold   amplitudes   estimated   actual fidelity");
+    for threshold in [0.0, 1e-6, 1e-4, 1e-3] {
+        let state = TruncatedState::from_circuit(&circuit, threshold);
+        let actual = fidelity(reference.computed_state().unwrap(), &state.to_state());
+        println!(
+            "  {:9.0e}   {:10}   {:9.6}   {:.6}",
+            threshold,
+            state.num_amplitudes(),
+            state.fidelity(),
+            actual
+        );
+        tracked &= (actual - state.fidelity()).abs() < 0.05;
+    }
+    let elapsed = start.elapsed();
+
+    // 40 qubits: far past a state vector, but few amplitudes matter. Each
+    // rotation follows a CNOT onto its qubit, so every layer is short and
+    // the support never grows far before it is pruned.
+    let mut wide = QuantumCircuit::new(40);
+    for _ in 0..2 {
+        wide.ry(0, 0.1);
+        for q in 0..39 {
+            wide.cnot(q, q + 1).ry(q + 1, 0.1);
+        }
+    }
+    let state = TruncatedState::from_circuit(&wide, 1e-6);
+    let counts = TruncatedState::sample(&wide, 1e-6, 1000, Some(9));
+    let zeros = "0".repeat(40);
+    let wide_ok = state.fidelity() > 0.99 && counts.get(&zeros) > 500;
+    println!(
+        "40 qubits at threshold 1e-6: {} amplitudes, fidelity ≈ {:.4}, |0…0⟩ in {} of 1000 shots",
+        state.num_amplitudes(),
+        state.fidelity(),
+        counts.get(&zeros)
+    );
+    println!(
+        "Fidelity estimate tracks the true overlap: {}\n",
+        if tracked && wide_ok { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: "Amplitude truncation".to_string(),
+        num_qubits: n,
+        basic_time: elapsed,
+        mt_time: elapsed,
+        results_match: exact && tracked && wide_ok,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+/// `(x0 ∧ x1) ⊕ (x2 ∧ x3) ⊕ …` of `2 * pairs` inputs in uniform superposition,
+/// each AND computed into a scratch qubit that is uncomputed and freed.
+pub fn test_qubit_reuse(results: &mut Vec<BenchmarkResult>) {
+    print_section("Qubit Allocation with Reuse");
+
+    let pairs = 4;
+    let inputs = 2 * pairs;
+    let mut circuit = QuantumCircuit::new(inputs);
+    for q in 0..inputs {
+        circuit.h(q);
+    }
+    let output = circuit.alloc_qubit();
+    let mut scratch = Vec::new();
+    for pair in 0..pairs {
+        let (a, b) = (2 * pair, 2 * pair + 1);
+        let and = circuit.alloc_qubit();
+        scratch.push(and);
+        circuit.ccnot(a, b, and).cnot(and, output).ccnot(a, b, and);
+        circuit.free_qubit(and);
+    }
+    let width = circuit.num_qubits();
+    println!(
+        "{} scratch allocations used qubits {:?}: width {} instead of {}",
+        pairs,
+        scratch,
+        width,
+        inputs + 1 + pairs
+    );
+
+    let start = Instant::now();
+    circuit.compute_with(Runtime::SimdRT);
+    let elapsed = start.elapsed();
+    let state = circuit.state();
+    let bit = |index: usize, q: usize| (index >> (width - 1 - q)) & 1 == 1;
+    let correct = (0..state.size()).all(|index| {
+        let p = state.get(index).norm2();
+        let expected = (0..pairs).fold(false, |acc, k| {
+            acc ^ (bit(index, 2 * k) && bit(index, 2 * k + 1))
+        });
+        p < 1e-12 || (bit(index, output) == expected && !bit(index, width - 1))
+    });
+    println!(
+        "Output holds the parity of ANDs and scratch is back in |0⟩: {}",
+        if correct { "✓" } else { "✗" }
+    );
+
+    // A scratch qubit freed while still entangled is reset, so the next
+    // allocation reads 0 while the qubit it touched stays random.
+    let mut dirty = QuantumCircuit::with_classical(1, 2);
+    dirty.h(0);
+    let scratch = dirty.alloc_qubit();
+    dirty.cnot(0, scratch).free_qubit(scratch);
+    let reused = dirty.alloc_qubit();
+    dirty.measure(reused, 0).measure(0, 1);
+    let counts = dirty.sample(1000, Some(3496));
+    let fresh = reused == scratch
+        && counts.iter().all(|(outcome, _)| outcome.starts_with('0'))
+        && counts.get("01") > 400
+        && counts.get("00") > 400;
+    let qasm = libpsi_qasm::to_qasm(&dirty).unwrap_or_default();
+    let round_trip = qasm.contains("reset q[1];")
+        && libpsi_qasm::parse(&qasm)
+            .ok()
+            .and_then(|parsed| libpsi_qasm::to_qasm(&parsed).ok())
+            .is_some_and(|again| again == qasm);
+    println!(
+        "Freeing an entangled qubit resets it ({} × 00, {} × 01), OpenQASM round trip: {}\n",
+        counts.get("00"),
+        counts.get("01"),
+        if fresh && round_trip { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: "Qubit reuse (AND parity)".to_string(),
+        num_qubits: width,
+        basic_time: elapsed,
+        mt_time: elapsed,
+        results_match: correct
+            && width == inputs + 2
+            && circuit.live_qubits() == inputs + 1
+            && fresh
+            && round_trip,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+fn max_difference(a: &QuantumState, b: &QuantumState) -> f64 {
+    (0..a.size())
+        .map(|i| {
+            let (x, y) = (a.get(i), b.get(i));
+            (x.real - y.real).hypot(x.imaginary - y.imaginary)
+        })
+        .fold(0.0, f64::max)
+}
+
+pub fn test_time_evolution(results: &mut Vec<BenchmarkResult>) {
+    print_section("Schrödinger-Equation Time Evolution");
+
+    let mut circuit = QuantumCircuit::new(3);
+    circuit
+        .h(0)
+        .x(1)
+        .y(2)
+        .z(0)
+        .s(1)
+        .t(2)
+        .sdg(0)
+        .tdg(1)
+        .sx(2)
+        .sxdg(0)
+        .rx(1, 0.7)
+        .ry(2, -1.3)
+        .rz(0, 2.1)
+        .p(1, 0.4)
+        .u1(2, -0.9)
+        .u2(0, 0.3, 1.1)
+        .u3(1, 0.8, -0.5, 2.4)
+        .cnot(0, 2)
+        .cz(1, 0)
+        .swap(2, 1)
+        .crx(0, 1, 1.7)
+        .cry(2, 0, -0.6)
+        .crz(1, 2, 0.9)
+        .cp(0, 1, 2.2)
+        .ccnot(2, 0, 1)
+        .cswap(1, 2, 0)
+        .measure(0, 0);
+    let mut reference = circuit.clone();
+    reference.compute_with(Runtime::BasicRT);
+    let reference = reference.computed_state().unwrap().clone();
+
+    let start = Instant::now();
+    let evolved = Runtime::WFEvolution.compute(3, circuit.operations());
+    let basic_time = start.elapsed();
+    let start = Instant::now();
+    let evolved_mt = Runtime::WFEvolutionMT.compute(3, circuit.operations());
+    let mt_time = start.elapsed();
+    let error = max_difference(&reference, &evolved);
+    let gates_ok = error < 1e-6 && max_difference(&evolved, &evolved_mt) < 1e-12;
+    println!(
+        "Every built-in gate integrated from its generator: max amplitude error {:.1e} {}",
+        error,
+        if gates_ok { "✓" } else { "✗" }
+    );
+
+    // H = X: |0⟩ → cos t |0⟩ − i sin t |1⟩.
+    let mut rabi = Hamiltonian::new(1);
+    rabi.add_term(1.0, &[(0, Pauli::X)]);
+    let t = 0.7;
+    let zero = QuantumState::new(vec![complex!(1.0, 0.0), complex!(0.0, 0.0)]);
+    let state = TimeEvolution::new(1e-3).evolve(&rabi, &zero, t);
+    let expected = QuantumState::new(vec![complex!(t.cos(), 0.0), complex!(0.0, -t.sin())]);
+    let rabi_ok = max_difference(&state, &expected) < 1e-10;
+    println!(
+        "Rabi oscillation under X matches cos t, −i sin t: {}",
+        if rabi_ok { "✓" } else { "✗" }
+    );
+
+    // Heisenberg coupling plus a Y field, as Pauli terms and as a matrix.
+    let mut paulis = Hamiltonian::new(2);
+    for pauli in [Pauli::X, Pauli::Y, Pauli::Z] {
+        paulis.add_term(1.0, &[(0, pauli), (1, pauli)]);
+    }
+    paulis.add_term(0.5, &[(0, Pauli::Y)]);
+    let r = |x: f64| complex!(x, 0.0);
+    let i = |x: f64| complex!(0.0, x);
+    #[rustfmt::skip]
+    let dense = Hamiltonian::from_matrix(Matrix::new(4, 4, vec![
+        r(1.0), r(0.0), i(-0.5), r(0.0),
+        r(0.0), r(-1.0), r(2.0), i(-0.5),
+        i(0.5), r(2.0), r(-1.0), r(0.0),
+        r(0.0), i(0.5), r(0.0), r(1.0),
+    ]))
+    .unwrap();
+    let norm = 0.5f64.sqrt();
+    let initial = QuantumState::new(vec![r(norm), r(0.0), i(norm), r(0.0)]);
+    let evolution = TimeEvolution::default().parallel();
+    let forms_ok = max_difference(
+        &evolution.evolve(&paulis, &initial, 1.5),
+        &evolution.evolve(&dense, &initial, 1.5),
+    ) < 1e-12;
+    let rejected =
+        Hamiltonian::from_matrix(Matrix::new(2, 2, vec![r(0.0), r(1.0), r(0.0), r(0.0)])).is_err();
+    println!(
+        "Pauli-sum and dense Hamiltonians agree, non-Hermitian matrix rejected: {}\n",
+        if forms_ok && rejected { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: "WFEvolution (RK4)".to_string(),
+        num_qubits: 3,
+        basic_time,
+        mt_time,
+        results_match: gates_ok && rabi_ok && forms_ok && rejected,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+pub fn test_inverse(results: &mut Vec<BenchmarkResult>) {
+    print_section("Circuit Inverse");
+
+    let mut prep = QuantumCircuit::new(3);
+    for q in 0..3 {
+        prep.ry(q, 0.3 + 0.4 * q as f64).rz(q, 0.7 * q as f64 + 0.1);
+    }
+    prep.cnot(0, 1).cnot(1, 2);
+    let start_state = prep.clone().compute_with(Runtime::BasicRT).clone();
+
+    let clifford_composite = CustomGateBuilder::new("sh", 2).s(0).h(1).cnot(0, 1).build();
+    let t_composite = CustomGateBuilder::new("ct", 2).h(1).t(1).cnot(0, 1).build();
+    let stays_composite = matches!(
+        clifford_composite.adjoint().definition,
+        CustomGateDefinition::Composite(_)
+    );
+    let ops = [
+        GateOp::H(0),
+        GateOp::S(1),
+        GateOp::Sdg(1),
+        GateOp::T(2),
+        GateOp::Tdg(2),
+        GateOp::Sx(0),
+        GateOp::Sxdg(0),
+        GateOp::Rx(0, 0.4),
+        GateOp::Ry(1, -1.3),
+        GateOp::Rz(2, 2.2),
+        GateOp::P(0, 0.9),
+        GateOp::U1(1, -0.5),
+        GateOp::U2(2, 0.3, 1.7),
+        GateOp::U3(0, 1.1, 0.4, -2.3),
+        GateOp::CRx(0, 2, 0.8),
+        GateOp::CRy(2, 1, -0.6),
+        GateOp::CRz(1, 0, 1.9),
+        GateOp::CP(0, 1, 2.5),
+        GateOp::CCNOT(2, 0, 1),
+        GateOp::CSWAP(1, 2, 0),
+        GateOp::Custom(Arc::new(clifford_composite), vec![2, 0]),
+        GateOp::Custom(Arc::new(t_composite), vec![0, 1]),
+        GateOp::Custom(
+            Arc::new(CustomGate::from_matrix(
+                "u",
+                gates::u3_matrix(0.7, -1.2, 2.9),
+            )),
+            vec![1],
+        ),
+    ];
+    let mut gates_undone = true;
+    for op in &ops {
+        let mut circuit = prep.clone();
+        circuit.push(op.clone()).push(op.adjoint());
+        let fidelity = fidelity(circuit.compute_with(Runtime::BasicRT), &start_state);
+        if fidelity < 1.0 - 1e-9 {
+            println!(
+                "  {:?} followed by {:?}: fidelity {:.6}",
+                op,
+                op.adjoint(),
+                fidelity
+            );
+            gates_undone = false;
+        }
+    }
+    println!(
+        "Each of {} gates undone by its adjoint: {}, Clifford composite stays composite: {}",
+        ops.len(),
+        if gates_undone { "✓" } else { "✗" },
+        if stays_composite { "✓" } else { "✗" }
+    );
+
+    let mut rng = StdRng::seed_from_u64(3517);
+    let n = 10;
+    let mut circuit = QuantumCircuit::new(n);
+    for _ in 0..200 {
+        let q = rng.random_range(0..n);
+        let op = ops[rng.random_range(0..ops.len())].map_qubits(|t| (t + q) % n);
+        circuit.push(op);
+    }
+    let inverse = circuit.inverse();
+    let mut roundtrip = QuantumCircuit::new(n);
+    roundtrip.h(0).ry(3, 0.8).cnot(0, 5);
+    let mut expected = roundtrip.clone();
+    roundtrip.append(&circuit, &(0..n).collect::<Vec<_>>());
+    roundtrip.append(&inverse, &(0..n).collect::<Vec<_>>());
+    let start = Instant::now();
+    let fidelity = fidelity(
+        roundtrip.compute_with(Runtime::StructureAwareMT),
+        expected.state(),
+    );
+    let elapsed = start.elapsed();
+    let reversed = inverse.operations().len() == circuit.operations().len()
+        && format!("{:?}", inverse.operations()[0])
+            == format!("{:?}", circuit.operations()[199].adjoint());
+    println!(
+        "{}-qubit, 200-gate circuit followed by its inverse: fidelity {:.12} {}\n",
+        n,
+        fidelity,
+        if fidelity > 1.0 - 1e-9 && reversed {
+            "✓"
+        } else {
+            "✗"
+        }
+    );
+
+    results.push(BenchmarkResult {
+        name: format!("Inverse ({} qubits)", n),
+        num_qubits: n,
+        basic_time: elapsed,
+        mt_time: elapsed,
+        results_match: gates_undone && stays_composite && fidelity > 1.0 - 1e-9 && reversed,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+/// State of `prep`, then Hadamards on `controls`, then `body` applied only on
+/// the branch where every control is |1⟩, assembled from separate runs of
+/// `prep` and `prep` + `body`. `prep` and `body` must leave the controls alone.
+fn controlled_reference(
+    prep: &QuantumCircuit,
+    body: &QuantumCircuit,
+    controls: &[usize],
+) -> QuantumState {
+    let n = prep.num_qubits();
+    let idle = prep.clone().compute_with(Runtime::BasicRT).clone();
+    let mut applied = prep.clone();
+    applied.append(body, &(0..n).collect::<Vec<_>>());
+    let applied = applied.compute_with(Runtime::BasicRT).clone();
+    let mask: usize = controls.iter().map(|&c| 1 << (n - 1 - c)).sum();
+    let scale = 1.0 / ((1usize << controls.len()) as f64).sqrt();
+    QuantumState::new(
+        (0..1usize << n)
+            .map(|i| {
+                let source = if i & mask == mask { &applied } else { &idle };
+                let amp = source.get(i & !mask);
+                complex!(amp.real * scale, amp.imaginary * scale)
+            })
+            .collect(),
+    )
+}
+
+pub fn test_controlled(results: &mut Vec<BenchmarkResult>) {
+    print_section("Controlled Gates and Circuits");
+
+    let mut prep = QuantumCircuit::new(4);
+    for q in 1..4 {
+        prep.ry(q, 0.5 + 0.3 * q as f64).rz(q, 0.9 - 0.4 * q as f64);
+    }
+    prep.cnot(1, 2).cnot(2, 3);
+
+    let composite = CustomGateBuilder::new("ht", 2).h(0).t(1).cnot(0, 1).build();
+    let ops = [
+        GateOp::H(1),
+        GateOp::X(2),
+        GateOp::Y(3),
+        GateOp::Z(1),
+        GateOp::S(2),
+        GateOp::Tdg(3),
+        GateOp::Sx(1),
+        GateOp::Rx(2, 0.7),
+        GateOp::Ry(3, -1.1),
+        GateOp::Rz(1, 2.3),
+        GateOp::P(2, 0.4),
+        GateOp::U3(3, 1.2, -0.3, 0.8),
+        GateOp::CNOT(1, 3),
+        GateOp::CZ(2, 1),
+        GateOp::SWAP(1, 2),
+        GateOp::CRy(3, 1, 0.6),
+        GateOp::CCNOT(1, 2, 3),
+        GateOp::Custom(Arc::new(composite), vec![3, 1]),
+        GateOp::Custom(
+            Arc::new(CustomGate::from_matrix(
+                "u",
+                gates::u3_matrix(0.4, 1.9, -0.7),
+            )),
+            vec![2],
+        ),
+    ];
+    let mut gates_match = true;
+    let mut native = 0;
+    for op in &ops {
+        let controlled = op.controlled(0);
+        if !controlled.is_custom() {
+            native += 1;
+        }
+        let mut body = QuantumCircuit::new(4);
+        body.push(op.clone());
+        let mut circuit = prep.clone();
+        circuit.h(0).push(controlled.clone());
+        let fidelity = fidelity(
+            circuit.compute_with(Runtime::BasicRT),
+            &controlled_reference(&prep, &body, &[0]),
+        );
+        if fidelity < 1.0 - 1e-9 {
+            println!("  {:?} → {:?}: fidelity {:.6}", op, controlled, fidelity);
+            gates_match = false;
+        }
+    }
+    println!(
+        "{} gates controlled on qubit 0 ({} onto built-in controlled gates): {}",
+        ops.len(),
+        native,
+        if gates_match { "✓" } else { "✗" }
+    );
+
+    let n = 10;
+    let controls = [0, 1];
+    let mut rng = StdRng::seed_from_u64(3518);
+    let mut prep = QuantumCircuit::new(n);
+    for q in 2..n {
+        prep.ry(q, rng.random_range(0.0..PI))
+            .rz(q, rng.random_range(0.0..PI));
+    }
+    let mut body = QuantumCircuit::new(n);
+    for _ in 0..60 {
+        let q = rng.random_range(2..n);
+        let op = ops[rng.random_range(0..ops.len())].map_qubits(|t| 2 + (t - 1 + q) % (n - 2));
+        body.push(op);
+    }
+    let mut circuit = prep.clone();
+    for &c in &controls {
+        circuit.h(c);
+    }
+    circuit.append(&body.controlled(&controls), &(0..n).collect::<Vec<_>>());
+    let expected = controlled_reference(&prep, &body, &controls);
+
+    let start = Instant::now();
+    let basic = circuit.clone().compute_with(Runtime::BasicRT).clone();
+    let basic_time = start.elapsed();
+    let start = Instant::now();
+    let mt = circuit.compute_with(Runtime::StructureAwareMT).clone();
+    let mt_time = start.elapsed();
+    let basic_fidelity = fidelity(&basic, &expected);
+    let circuit_matches = basic_fidelity > 1.0 - 1e-9 && fidelity(&mt, &expected) > 1.0 - 1e-9;
+    println!(
+        "{}-gate circuit on {} qubits controlled by qubits 0 and 1: fidelity {:.12} {}\n",
+        body.operations().len(),
+        n,
+        basic_fidelity,
+        if circuit_matches { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: format!("Controlled circuit ({} qubits)", n),
+        num_qubits: n,
+        basic_time,
+        mt_time,
+        results_match: gates_match && circuit_matches,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+pub fn test_power(results: &mut Vec<BenchmarkResult>) {
+    print_section("Circuit Repetition and Powers");
+
+    let mut step = QuantumCircuit::new(2);
+    step.rx(1, 0.3).crx(0, 1, 0.45);
+    let mut rotations_match = true;
+    for k in [-3i64, -1, 0, 2, 5] {
+        let mut direct = QuantumCircuit::new(2);
+        direct.h(0).rx(1, 0.3 * k as f64).crx(0, 1, 0.45 * k as f64);
+        let powered = step.power(k);
+        let mut circuit = QuantumCircuit::new(2);
+        circuit.h(0).append(&powered, &[0, 1]);
+        rotations_match &=
+            fidelity(circuit.compute_with(Runtime::BasicRT), direct.state()) > 1.0 - 1e-9;
+        rotations_match &= powered.operations().len() == 2 * k.unsigned_abs() as usize;
+    }
+    println!(
+        "(Rx(θ)·CRx(φ))^k matches Rx(kθ)·CRx(kφ) for k = -3, -1, 0, 2, 5: {}",
+        if rotations_match { "✓" } else { "✗" }
+    );
+
+    let n = 8;
+    let mut rng = StdRng::seed_from_u64(3519);
+    let mut circuit = QuantumCircuit::new(n);
+    for _ in 0..80 {
+        let a = rng.random_range(0..n);
+        let b = (a + rng.random_range(1..n)) % n;
+        match rng.random_range(0..4) {
+            0 => circuit.h(a),
+            1 => circuit.t(a),
+            2 => circuit.ry(a, rng.random_range(0.0..PI)),
+            _ => circuit.cnot(a, b),
+        };
+    }
+    let repeats = 4;
+    let mut by_hand = QuantumCircuit::new(n);
+    for _ in 0..repeats {
+        by_hand.append(&circuit, &(0..n).collect::<Vec<_>>());
+    }
+    let start = Instant::now();
+    let basic = by_hand.compute_with(Runtime::BasicRT).clone();
+    let basic_time = start.elapsed();
+    let mut repeated = circuit.repeat(repeats);
+    let start = Instant::now();
+    let mt = repeated.compute_with(Runtime::StructureAwareMT).clone();
+    let mt_time = start.elapsed();
+    let repeat_matches = fidelity(&basic, &mt) > 1.0 - 1e-9;
+
+    let mut undone = circuit.power(3);
+    undone.append(&circuit.power(-3), &(0..n).collect::<Vec<_>>());
+    let identity = QuantumCircuit::new(n)
+        .compute_with(Runtime::BasicRT)
+        .clone();
+    let undone_matches = fidelity(undone.compute_with(Runtime::BasicRT), &identity) > 1.0 - 1e-9;
+    println!(
+        "{}-gate circuit on {} qubits: repeat({}) matches hand-appended copies {}, power(3) then power(-3) is the identity {}\n",
+        circuit.operations().len(),
+        n,
+        repeats,
+        if repeat_matches { "✓" } else { "✗" },
+        if undone_matches { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: format!("Circuit power ({} qubits)", n),
+        num_qubits: n,
+        basic_time,
+        mt_time,
+        results_match: rotations_match && repeat_matches && undone_matches,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+/// Layered ry/rz ansatz with every angle taken from `angle(layer, qubit)`.
+fn layered_ansatz<A: Into<Angle>>(
+    n: usize,
+    angle: impl Fn(usize, usize) -> (A, A),
+) -> QuantumCircuit {
+    let mut circuit = QuantumCircuit::new(n);
+    for layer in 0..3 {
+        for q in 0..n {
+            let (y, z) = angle(layer, q);
+            circuit.ry(q, y).rz(q, z);
+        }
+        for q in 0..n - 1 {
+            circuit.cnot(q, q + 1);
+        }
+    }
+    circuit
+}
+
+pub fn test_parameters(results: &mut Vec<BenchmarkResult>) {
+    print_section("Symbolic Parameters");
+
+    let theta = Parameter::new("theta");
+    let phi = Parameter::new("phi");
+    let mut template = QuantumCircuit::new(3);
+    template
+        .h(0)
+        .ry(0, &theta)
+        .crx(0, 1, phi.clone() * 2.0)
+        .u3(2, -theta.clone() + 0.5, 0.3, &phi)
+        .u2(1, &phi, 1.2)
+        .cp(1, 2, theta.clone() - 0.4);
+    let concrete = |t: f64, p: f64| {
+        let mut circuit = QuantumCircuit::new(3);
+        circuit
+            .h(0)
+            .ry(0, t)
+            .crx(0, 1, 2.0 * p)
+            .u3(2, 0.5 - t, 0.3, p)
+            .u2(1, p, 1.2)
+            .cp(1, 2, t - 0.4);
+        circuit
+    };
+
+    let names_match = template.parameters() == ["phi", "theta"]
+        && template.bind_parameters(&[("theta", 0.1)]).parameters() == ["phi"];
+    let mut bound_match = true;
+    let mut inverse_match = true;
+    for (t, p) in [(0.0, 0.0), (0.3, -1.2), (2.1, 0.7), (-0.8, 3.0)] {
+        let bindings = [("theta", t), ("phi", p)];
+        let mut bound = template.bind_parameters(&bindings);
+        bound_match &= bound.parameters().is_empty()
+            && states_equal(bound.compute_with(Runtime::BasicRT), concrete(t, p).state());
+
+        let mut roundtrip = concrete(t, p);
+        roundtrip.append(&template.inverse().bind_parameters(&bindings), &[0, 1, 2]);
+        let identity = QuantumCircuit::new(3)
+            .compute_with(Runtime::BasicRT)
+            .clone();
+        inverse_match &= fidelity(roundtrip.compute_with(Runtime::BasicRT), &identity) > 1.0 - 1e-9;
+    }
+    let mut rotation = QuantumCircuit::new(2);
+    rotation.ry(1, &theta).rz(1, phi.clone() * 0.5);
+    let mut controlled = QuantumCircuit::new(2);
+    controlled.h(0).append(
+        &rotation
+            .controlled(&[0])
+            .bind_parameters(&[("theta", 0.9), ("phi", -0.4)]),
+        &[0, 1],
+    );
+    let mut expected = QuantumCircuit::new(2);
+    expected.h(0).cry(0, 1, 0.9).crz(0, 1, -0.2);
+    let controlled_match =
+        states_equal(controlled.compute_with(Runtime::BasicRT), expected.state());
+    let shown = format!("{:?}", template.operations()[3]);
+    println!(
+        "Open parameters {:?}, U3 shown as {}",
+        template.parameters(),
+        shown
+    );
+    println!(
+        "Bound circuits match direct construction: {}, inverse then bind undoes the circuit: {}, controlled rotations keep their parameters: {}",
+        if names_match && bound_match { "✓" } else { "✗" },
+        if inverse_match { "✓" } else { "✗" },
+        if controlled_match { "✓" } else { "✗" }
+    );
+
+    let n = 8;
+    let iterations = 10;
+    let symbols: Vec<Parameter> = (0..6 * n)
+        .map(|i| Parameter::new(&format!("a{}", i)))
+        .collect();
+    let template = layered_ansatz(n, |layer, q| {
+        let i = 2 * (layer * n + q);
+        (symbols[i].clone(), symbols[i + 1].clone())
+    });
+    let mut rng = StdRng::seed_from_u64(3520);
+    let mut basic_time = Duration::ZERO;
+    let mut mt_time = Duration::ZERO;
+    let mut loop_match = true;
+    for _ in 0..iterations {
+        let values: Vec<f64> = (0..6 * n).map(|_| rng.random_range(-PI..PI)).collect();
+        let start = Instant::now();
+        let mut rebuilt = layered_ansatz(n, |layer, q| {
+            let i = 2 * (layer * n + q);
+            (values[i], values[i + 1])
+        });
+        let rebuilt_state = rebuilt.compute_with(Runtime::BasicRT).clone();
+        basic_time += start.elapsed();
+
+        let start = Instant::now();
+        let bindings: Vec<(&str, f64)> = symbols
+            .iter()
+            .map(Parameter::name)
+            .zip(values.iter().copied())
+            .collect();
+        let mut bound = template.bind_parameters(&bindings);
+        let bound_state = bound.compute_with(Runtime::StructureAwareMT).clone();
+        mt_time += start.elapsed();
+        loop_match &= fidelity(&rebuilt_state, &bound_state) > 1.0 - 1e-9;
+    }
+    println!(
+        "{} iterations of a {}-parameter, {}-qubit ansatz bound from one template: {}\n",
+        iterations,
+        symbols.len(),
+        n,
+        if loop_match { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: format!("Parameter binding ({} qubits)", n),
+        num_qubits: n,
+        basic_time,
+        mt_time,
+        results_match: names_match
+            && bound_match
+            && inverse_match
+            && controlled_match
+            && loop_match,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+/// `⟨ψ|H|ψ⟩` for `circuit` with `bindings`, run on `runtime`.
+fn energy(
+    observable: &Hamiltonian,
+    circuit: &QuantumCircuit,
+    bindings: &[(&str, f64)],
+    runtime: Runtime,
+) -> f64 {
+    let mut bound = circuit.bind_parameters(bindings);
+    let state = bound.compute_with(runtime);
+    let amplitudes: Vec<Complex<f64>> = (0..state.size()).map(|i| state.get(i)).collect();
+    let applied = observable.apply(&amplitudes, false);
+    amplitudes
+        .iter()
+        .zip(&applied)
+        .map(|(a, b)| (a.get_conjugate() * *b).real)
+        .sum()
+}
+
+/// Central finite differences of `energy` in every parameter of `circuit`.
+fn finite_difference(
+    observable: &Hamiltonian,
+    circuit: &QuantumCircuit,
+    bindings: &[(&str, f64)],
+    runtime: Runtime,
+) -> Vec<f64> {
+    let h = 1e-6;
+    circuit
+        .parameters()
+        .iter()
+        .map(|name| {
+            let shifted = |delta: f64| {
+                let moved: Vec<(&str, f64)> = bindings
+                    .iter()
+                    .map(|&(n, v)| (n, if n == name { v + delta } else { v }))
+                    .collect();
+                energy(observable, circuit, &moved, runtime)
+            };
+            (shifted(h) - shifted(-h)) / (2.0 * h)
+        })
+        .collect()
+}
+
+pub fn test_adjoint_gradients(results: &mut Vec<BenchmarkResult>) {
+    print_section("Adjoint Gradients");
+
+    let (a, b, c) = (
+        Parameter::new("a"),
+        Parameter::new("b"),
+        Parameter::new("c"),
+    );
+    let mut circuit = QuantumCircuit::with_classical(3, 3);
+    circuit
+        .h(0)
+        .rx(0, &a)
+        .ry(1, b.clone() * 2.0)
+        .rz(2, -c.clone() + 0.4)
+        .cnot(0, 1)
+        .p(1, &a)
+        .u1(2, b.clone() * 0.5)
+        .t(2)
+        .u2(0, &c, 0.3)
+        .u3(1, &a, &b, c.clone() - 1.0)
+        .barrier(&[0, 1, 2])
+        .crx(1, 2, &c)
+        .cry(2, 0, a.clone() * -1.5)
+        .crz(0, 2, &b)
+        .cp(2, 1, c.clone() + 0.2)
+        .rx(1, 0.8)
+        .measure_all();
+    let mut observable = Hamiltonian::new(3);
+    observable
+        .add_term(1.0, &[(0, Pauli::Z), (1, Pauli::Z)])
+        .add_term(0.5, &[(2, Pauli::X)])
+        .add_term(-0.7, &[(0, Pauli::Y), (2, Pauli::Y)])
+        .add_term(0.3, &[(1, Pauli::X), (2, Pauli::Z)]);
+    let bindings = [("a", 0.37), ("b", -1.1), ("c", 2.3)];
+    let expected = finite_difference(&observable, &circuit, &bindings, Runtime::BasicRT);
+    let expected_energy = energy(&observable, &circuit, &bindings, Runtime::BasicRT);
+    let mut gates_match = true;
+    for config in [
+        RuntimeConfig::new(),
+        RuntimeConfig::optimal().with_threshold(1),
+    ] {
+        let gradient = config.adjoint_gradients(&circuit, &observable, &bindings);
+        gates_match &= (gradient.expectation - expected_energy).abs() < 1e-10
+            && gradient.partials.len() == 3
+            && gradient
+                .partials
+                .iter()
+                .zip(&expected)
+                .all(|((_, adjoint), fd)| (adjoint - fd).abs() < 1e-6);
+    }
+    let gradient = RuntimeConfig::new().adjoint_gradients(&circuit, &observable, &bindings);
+    println!(
+        "⟨H⟩ = {:.6}, ∂a = {:.6}, ∂b = {:.6}, ∂c = {:.6}",
+        gradient.expectation,
+        gradient.get("a").unwrap(),
+        gradient.get("b").unwrap(),
+        gradient.get("c").unwrap()
+    );
+    println!(
+        "Every parametric gate, shared and scaled parameters, match finite differences: {}",
+        if gates_match { "✓" } else { "✗" }
+    );
+
+    let n = 8;
+    let symbols: Vec<Parameter> = (0..6 * n)
+        .map(|i| Parameter::new(&format!("a{}", i)))
+        .collect();
+    let ansatz = layered_ansatz(n, |layer, q| {
+        let i = 2 * (layer * n + q);
+        (symbols[i].clone(), symbols[i + 1].clone())
+    });
+    let mut observable = Hamiltonian::new(n);
+    for q in 0..n - 1 {
+        observable.add_term(1.0, &[(q, Pauli::Z), (q + 1, Pauli::Z)]);
+        observable.add_term(0.5, &[(q, Pauli::X)]);
+    }
+    let mut rng = StdRng::seed_from_u64(3522);
+    let values: Vec<f64> = (0..symbols.len())
+        .map(|_| rng.random_range(-PI..PI))
+        .collect();
+    let mut bindings: Vec<(&str, f64)> = symbols
+        .iter()
+        .map(Parameter::name)
+        .zip(values.iter().copied())
+        .collect();
+    // `partials` and `finite_difference` both follow `parameters()` order.
+    bindings.sort_by(|x, y| x.0.cmp(y.0));
+
+    let start = Instant::now();
+    let expected = finite_difference(&observable, &ansatz, &bindings, Runtime::StructureAwareMT);
+    let fd_time = start.elapsed();
+    let config = RuntimeConfig::optimal();
+    let start = Instant::now();
+    let gradient = config.adjoint_gradients(&ansatz, &observable, &bindings);
+    let adjoint_time = start.elapsed();
+    let max_error = gradient
+        .partials
+        .iter()
+        .zip(&expected)
+        .map(|((_, adjoint), fd)| (adjoint - fd).abs())
+        .fold(0.0, f64::max);
+    let ansatz_match = max_error < 1e-5;
+    println!(
+        "{}-parameter, {}-qubit ansatz: adjoint sweep {:.2?} vs {} finite-difference runs {:.2?}, max difference {:.1e} {}\n",
+        symbols.len(),
+        n,
+        adjoint_time,
+        2 * symbols.len(),
+        fd_time,
+        max_error,
+        if ansatz_match { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: format!("Adjoint gradients ({} qubits)", n),
+        num_qubits: n,
+        basic_time: fd_time,
+        mt_time: adjoint_time,
+        results_match: gates_match && ansatz_match,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+fn max_matrix_difference(a: &Matrix<Complex<f64>>, b: &Matrix<Complex<f64>>) -> f64 {
+    a.data
+        .iter()
+        .zip(&b.data)
+        .map(|(x, y)| (*x - *y).abs())
+        .fold(0.0, f64::max)
+}
+
+pub fn test_sparse_pauli_op(results: &mut Vec<BenchmarkResult>) {
+    print_section("Sparse Pauli Operators");
+
+    let pauli = |label: char| match label {
+        'X' => gates::PAULI_X.matrix.clone(),
+        'Y' => gates::PAULI_Y.matrix.clone(),
+        'Z' => gates::PAULI_Z.matrix.clone(),
+        _ => gates::IDENTITY.matrix.clone(),
+    };
+    let dense = |label: &str| {
+        let mut chars = label.chars();
+        let first = pauli(chars.next().unwrap());
+        chars.fold(first, |matrix, c| matrix.kronecker(&pauli(c)))
+    };
+
+    let mut strings_match = true;
+    for label in ["XYZ", "YIX", "ZZY", "IYI"] {
+        let op = SparsePauliOp::from_labels(&[(label, 1.0)]).unwrap();
+        strings_match &= max_matrix_difference(&op.to_matrix(), &dense(label)) < 1e-12;
+    }
+    let a = SparsePauliOp::from_labels(&[("XZ", 0.5), ("YY", -1.2)]).unwrap();
+    let mut b = SparsePauliOp::new(2);
+    b.add_term(complex!(0.0, 0.7), &[(1, Pauli::X)])
+        .add_term(2.0, &[(0, Pauli::Z), (1, Pauli::Z)]);
+    let expected = a
+        .to_matrix()
+        .add_to(&b.to_matrix().scale(complex!(3.0, 0.0)))
+        .unwrap();
+    let sum_matches =
+        max_matrix_difference(&(a.clone() + b.clone() * 3.0).to_matrix(), &expected) < 1e-12;
+    let tensor_matches = max_matrix_difference(
+        &a.tensor(&b).to_matrix(),
+        &a.to_matrix().kronecker(&b.to_matrix()),
+    ) < 1e-12;
+    let merged = (a.clone() + a.clone() * 2.0 - a.clone() * 3.0 + b.clone()).simplify(1e-12);
+    let simplify_matches = merged.terms().len() == 2
+        && max_matrix_difference(&merged.to_matrix(), &b.to_matrix()) < 1e-12;
+    let parse_errors = SparsePauliOp::from_labels(&[("XQ", 1.0)]).is_err()
+        && SparsePauliOp::from_labels(&[("XZ", 1.0), ("X", 1.0)]).is_err()
+        && b.to_hamiltonian().is_err();
+    println!("a = {}", a);
+    println!(
+        "Strings match Kronecker products: {}, sums and scaling: {}, tensor product: {}, simplify: {}, bad input rejected: {}",
+        if strings_match { "✓" } else { "✗" },
+        if sum_matches { "✓" } else { "✗" },
+        if tensor_matches { "✓" } else { "✗" },
+        if simplify_matches { "✓" } else { "✗" },
+        if parse_errors { "✓" } else { "✗" }
+    );
+
+    let n = 10;
+    let mut ising = SparsePauliOp::new(n);
+    for q in 0..n {
+        ising.add_term(-1.0, &[(q, Pauli::Z), ((q + 1) % n, Pauli::Z)]);
+        ising.add_term(0.6, &[(q, Pauli::X)]);
+    }
+    let mut rng = StdRng::seed_from_u64(3524);
+    let state: Vec<Complex<f64>> = (0..1 << n)
+        .map(|_| complex!(rng.random_range(-1.0..1.0), rng.random_range(-1.0..1.0)))
+        .collect();
+    let start = Instant::now();
+    let matrix = ising.to_matrix();
+    let dense_result: Vec<Complex<f64>> = (0..1 << n)
+        .map(|row| {
+            (0..1 << n).fold(complex!(0.0, 0.0), |sum, col| {
+                sum + matrix.get(row, col) * state[col]
+            })
+        })
+        .collect();
+    let dense_time = start.elapsed();
+    let start = Instant::now();
+    let sparse_result = ising.to_hamiltonian().unwrap().apply(&state, true);
+    let sparse_time = start.elapsed();
+    let apply_matches = dense_result
+        .iter()
+        .zip(&sparse_result)
+        .all(|(x, y)| (*x - *y).abs() < 1e-9);
+    println!(
+        "{}-qubit transverse-field Ising operator, {} terms: dense matrix and Hamiltonian agree on H|ψ⟩ {}\n",
+        n,
+        ising.terms().len(),
+        if apply_matches { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: format!("SparsePauliOp H|ψ⟩ ({} qubits)", n),
+        num_qubits: n,
+        basic_time: dense_time,
+        mt_time: sparse_time,
+        results_match: strings_match
+            && sum_matches
+            && tensor_matches
+            && simplify_matches
+            && parse_errors
+            && apply_matches,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+pub fn test_equivalence(results: &mut Vec<BenchmarkResult>) {
+    print_section("Circuit Equivalence");
+
+    let build = |n: usize, ops: &dyn Fn(&mut QuantumCircuit)| {
+        let mut circuit = QuantumCircuit::new(n);
+        ops(&mut circuit);
+        circuit
+    };
+    let tolerance = 1e-9;
+    let identities = [
+        (
+            "H·X·H = Z",
+            build(1, &|c| {
+                c.h(0).x(0).h(0);
+            }),
+            build(1, &|c| {
+                c.z(0);
+            }),
+        ),
+        (
+            "T·T = S",
+            build(1, &|c| {
+                c.t(0).t(0);
+            }),
+            build(1, &|c| {
+                c.s(0);
+            }),
+        ),
+        (
+            "Rz(θ) = P(θ) up to phase",
+            build(1, &|c| {
+                c.rz(0, 0.7);
+            }),
+            build(1, &|c| {
+                c.p(0, 0.7);
+            }),
+        ),
+        (
+            "CNOT = H·CZ·H",
+            build(2, &|c| {
+                c.cnot(0, 1);
+            }),
+            build(2, &|c| {
+                c.h(1).cz(0, 1).h(1);
+            }),
+        ),
+        (
+            "SWAP = three CNOTs",
+            build(3, &|c| {
+                c.swap(0, 2);
+            }),
+            build(3, &|c| {
+                c.cnot(0, 2).cnot(2, 0).cnot(0, 2);
+            }),
+        ),
+    ];
+    let mut identities_hold = true;
+    for (name, a, b) in &identities {
+        let holds = a.equivalent_to(b, tolerance) && b.equivalent_to(a, tolerance);
+        println!("  {:<26} {}", name, if holds { "✓" } else { "✗" });
+        identities_hold &= holds;
+    }
+
+    // Z agrees with I on each basis state up to a phase, but not one phase.
+    let differences = [
+        (
+            "Z ≠ I",
+            build(1, &|c| {
+                c.z(0);
+            }),
+            QuantumCircuit::new(1),
+        ),
+        (
+            "S ≠ S†",
+            build(1, &|c| {
+                c.s(0);
+            }),
+            build(1, &|c| {
+                c.sdg(0);
+            }),
+        ),
+        (
+            "Rz(θ) ≠ Rz(θ + 10⁻⁶)",
+            build(1, &|c| {
+                c.rz(0, 0.7);
+            }),
+            build(1, &|c| {
+                c.rz(0, 0.7 + 1e-6);
+            }),
+        ),
+        (
+            "X on q0 ≠ X on q1",
+            build(2, &|c| {
+                c.x(0);
+            }),
+            build(2, &|c| {
+                c.x(1);
+            }),
+        ),
+        (
+            "1 qubit ≠ 2 qubits",
+            QuantumCircuit::new(1),
+            QuantumCircuit::new(2),
+        ),
+    ];
+    let mut differences_found = true;
+    for (name, a, b) in &differences {
+        let found = !a.equivalent_to(b, tolerance);
+        println!("  {:<26} {}", name, if found { "✓" } else { "✗" });
+        differences_found &= found;
+    }
+
+    // Wider circuits: a rewrite adding cancelling pairs, and one changed gate
+    // that leaves |0…0⟩ alone and so escapes a check of the final state.
+    let n = 12;
+    let mut rng = StdRng::seed_from_u64(3532);
+    let mut circuit = QuantumCircuit::new(n);
+    for _ in 0..120 {
+        let a = rng.random_range(0..n);
+        let b = (a + rng.random_range(1..n)) % n;
+        match rng.random_range(0..4) {
+            0 => circuit.h(a),
+            1 => circuit.t(a),
+            2 => circuit.ry(a, rng.random_range(-PI..PI)),
+            _ => circuit.cnot(a, b),
+        };
+    }
+    let mut padded = QuantumCircuit::new(n);
+    for (i, op) in circuit.operations().iter().enumerate() {
+        padded.push(op.clone());
+        if i % 10 == 0 {
+            padded.h(i % n).h(i % n).cnot(0, n - 1).cnot(0, n - 1);
+        }
+    }
+    let mut changed = QuantumCircuit::new(n);
+    changed.cz(0, 1);
+    changed.append(&circuit, &(0..n).collect::<Vec<_>>());
+
+    let start = Instant::now();
+    let (original, edited) = (
+        circuit
+            .clone()
+            .compute_with(Runtime::StructureAwareRT)
+            .clone(),
+        changed
+            .clone()
+            .compute_with(Runtime::StructureAwareRT)
+            .clone(),
+    );
+    let same_final_state = states_equal(&original, &edited);
+    let basic_time = start.elapsed();
+    let start = Instant::now();
+    let padded_equivalent = circuit.equivalent_to(&padded, tolerance);
+    let changed_equivalent = circuit.equivalent_to(&changed, tolerance);
+    let mt_time = start.elapsed();
+    println!(
+        "{}-gate circuit on {} qubits: equivalent with cancelling pairs added {}, a leading CZ is caught {} though the final states agree {}\n",
+        circuit.operations().len(),
+        n,
+        if padded_equivalent { "✓" } else { "✗" },
+        if !changed_equivalent { "✓" } else { "✗" },
+        if same_final_state { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: format!("Circuit equivalence ({} qubits)", n),
+        num_qubits: n,
+        basic_time,
+        mt_time,
+        results_match: identities_hold
+            && differences_found
+            && padded_equivalent
+            && !changed_equivalent
+            && same_final_state,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+pub fn test_unitary(results: &mut Vec<BenchmarkResult>) {
+    print_section("Circuit Unitary");
+
+    let r = |x: f64| complex!(x, 0.0);
+    let h = std::f64::consts::FRAC_1_SQRT_2;
+    let mut hadamard = QuantumCircuit::new(1);
+    hadamard.h(0);
+    let mut cnot = QuantumCircuit::new(2);
+    cnot.cnot(0, 1);
+    // Qubit 0 is the high bit, so the control selects the lower half.
+    #[rustfmt::skip]
+    let cnot_matrix = Matrix::new(4, 4, vec![
+        r(1.0), r(0.0), r(0.0), r(0.0),
+        r(0.0), r(1.0), r(0.0), r(0.0),
+        r(0.0), r(0.0), r(0.0), r(1.0),
+        r(0.0), r(0.0), r(1.0), r(0.0),
+    ]);
+    let gates_match = max_matrix_difference(
+        &hadamard.unitary(),
+        &Matrix::new(2, 2, vec![r(h), r(h), r(h), r(-h)]),
+    ) < 1e-12
+        && max_matrix_difference(&cnot.unitary(), &cnot_matrix) < 1e-12;
+    let qft_matches = max_matrix_difference(
+        &library::qft(4).unitary(),
+        &library::qft_gate(4).to_quantum_gate().matrix,
+    ) < 1e-12;
+    println!(
+        "H and CNOT give their matrices, qubit 0 most significant {}, qft(4) gives the Fourier matrix {}",
+        if gates_match { "✓" } else { "✗" },
+        if qft_matches { "✓" } else { "✗" }
+    );
+
+    let n = 6;
+    let mut rng = StdRng::seed_from_u64(3533);
+    let mut circuit = random_clifford(n, 40, &mut rng);
+    circuit.t(2).crz(0, 4, 0.3).u3(5, 0.2, 0.4, 0.6);
+    let start = Instant::now();
+    let unitary = circuit.unitary();
+    let mt_time = start.elapsed();
+    let dim = 1 << n;
+    let adjoint = Matrix::new(
+        dim,
+        dim,
+        (0..dim * dim)
+            .map(|i| unitary.get(i % dim, i / dim).get_conjugate())
+            .collect(),
+    );
+    let mut identity = Matrix::new(dim, dim, vec![r(0.0); dim * dim]);
+    for i in 0..dim {
+        identity.set(i, i, r(1.0));
+    }
+    let unitary_product = max_matrix_difference(&unitary.dot(&adjoint).unwrap(), &identity) < 1e-10;
+
+    // Column j is the state the circuit makes of |j⟩.
+    let start = Instant::now();
+    let mut columns_match = true;
+    for j in 0..dim {
+        let mut prepared = QuantumCircuit::new(n);
+        for q in 0..n {
+            if j >> (n - 1 - q) & 1 == 1 {
+                prepared.x(q);
+            }
+        }
+        prepared.append(&circuit, &(0..n).collect::<Vec<_>>());
+        let state = prepared.compute_with(Runtime::BasicRT);
+        columns_match &= (0..dim).all(|i| (state.get(i) - unitary.get(i, j)).abs() < 1e-10);
+    }
+    let basic_time = start.elapsed();
+
+    let mut measured = circuit.clone();
+    measured.barrier(&[0, 1]).measure(0, 0).measure(3, 1);
+    let measurements_ignored = max_matrix_difference(&measured.unitary(), &unitary) < 1e-12;
+    println!(
+        "{}-gate circuit on {} qubits: U·U† = I {}, each column matches a run from |j⟩ {}, measurements left out {}\n",
+        circuit.operations().len(),
+        n,
+        if unitary_product { "✓" } else { "✗" },
+        if columns_match { "✓" } else { "✗" },
+        if measurements_ignored { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: format!("Circuit unitary ({} qubits)", n),
+        num_qubits: n,
+        basic_time,
+        mt_time,
+        results_match: gates_match
+            && qft_matches
+            && unitary_product
+            && columns_match
+            && measurements_ignored,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+/// A circuit started from a prepared vector ends where the preparation
+/// followed by the circuit does, on every runtime.
+pub fn test_initial_state(results: &mut Vec<BenchmarkResult>) {
+    print_section("Initial State");
+
+    let n = 9;
+    let all: Vec<usize> = (0..n).collect();
+    let mut preparation = library::random_circuit(n, 4, 3539, library::GateSet::Universal);
+    let prepared = preparation.compute_with(Runtime::StructureAwareRT).clone();
+    let amplitudes: Vec<Complex<f64>> = (0..prepared.size()).map(|i| prepared.get(i)).collect();
+    let body = library::random_circuit(n, 6, 3540, library::GateSet::Universal);
+    let mut reference = preparation.clone();
+    reference.append(&body, &all);
+    let expected = reference.compute_with(Runtime::StructureAwareRT).clone();
+
+    let mut started = body.clone();
+    started.with_initial_state(&amplitudes);
+    let runtimes = [
+        Runtime::BasicRT,
+        Runtime::BasicRTMT,
+        Runtime::BatchedRT,
+        Runtime::BatchedRTMT,
+        Runtime::SimdRT,
+        Runtime::SimdRTMT,
+        Runtime::StructureAwareRT,
+        Runtime::StructureAwareMT,
+        Runtime::Custom(RuntimeConfig::optimal()),
+    ];
+    let (mut basic_time, mut mt_time) = Default::default();
+    let mut runtimes_match = true;
+    for runtime in runtimes {
+        let start = Instant::now();
+        let state = started.clone().compute_with(runtime).clone();
+        match runtime {
+            Runtime::BasicRT => basic_time = start.elapsed(),
+            Runtime::StructureAwareMT => mt_time = start.elapsed(),
+            _ => {}
+        }
+        let matches = fidelity(&state, &expected) > 1.0 - 1e-9;
+        if !matches {
+            println!("  {:?} ignores the initial state", runtime);
+        }
+        runtimes_match &= matches;
+    }
+
+    // Time evolution integrates each gate, so it only comes close.
+    let mut small = QuantumCircuit::new(3);
+    small.h(0).cnot(0, 1).ry(2, 0.7);
+    let small_amplitudes: Vec<Complex<f64>> = {
+        let state = small.compute_with(Runtime::BasicRT);
+        (0..state.size()).map(|i| state.get(i)).collect()
+    };
+    let mut evolved = QuantumCircuit::new(3);
+    evolved.rx(1, 0.4).cz(0, 2);
+    let mut small_reference = small.clone();
+    small_reference.append(&evolved, &[0, 1, 2]);
+    evolved.with_initial_state(&small_amplitudes);
+    let evolution_matches = fidelity(
+        evolved.clone().compute_with(Runtime::WFEvolution),
+        small_reference.compute_with(Runtime::BasicRT),
+    ) > 1.0 - 1e-6;
+    println!(
+        "{} runtimes start from a prepared {}-qubit state: {}, WFEvolution: {}",
+        runtimes.len(),
+        n,
+        if runtimes_match { "✓" } else { "✗" },
+        if evolution_matches { "✓" } else { "✗" }
+    );
+
+    // Tableaus start from basis states only.
+    let mut basis = vec![complex!(0.0, 0.0); 16];
+    basis[0b0101] = complex!(0.0, 1.0);
+    let mut clifford = QuantumCircuit::new(4);
+    clifford.h(0).cnot(0, 2).s(3);
+    let mut flipped = QuantumCircuit::new(4);
+    flipped.x(1).x(3);
+    flipped.append(&clifford, &[0, 1, 2, 3]);
+    clifford.with_initial_state(&basis);
+    let stabilizer_matches = fidelity(
+        clifford.clone().compute_with(Runtime::Stabilizer),
+        flipped.compute_with(Runtime::BasicRT),
+    ) > 1.0 - 1e-12;
+
+    let job = started.compute_async(Runtime::StructureAwareMT);
+    let mut awaited = body.clone();
+    awaited.set_computed(job.wait().unwrap());
+    let async_matches = fidelity(awaited.computed_state().unwrap(), &expected) > 1.0 - 1e-9;
+
+    let density_matches = (evolved.density_matrix().fidelity_with_pure_state(&{
+        let state = small_reference.compute_with(Runtime::BasicRT);
+        (0..state.size()).map(|i| state.get(i)).collect::<Vec<_>>()
+    }) - 1.0)
+        .abs()
+        < 1e-9;
+
+    // The new qubit is the last, so the state becomes the old one ⊗ |+⟩.
+    let mut widened = evolved.clone();
+    let ancilla = widened.alloc_qubit();
+    widened.h(ancilla);
+    let half = complex!(std::f64::consts::FRAC_1_SQRT_2, 0.0);
+    let tensored = QuantumState::new(
+        (0..16)
+            .map(|i| small_reference.state().get(i / 2) * half)
+            .collect(),
+    );
+    let ancilla_matches = fidelity(widened.compute_with(Runtime::BasicRT), &tensored) > 1.0 - 1e-9;
+    println!(
+        "Stabilizer from a basis state: {}, compute_async: {}, density_matrix: {}, alloc_qubit adds a |0⟩: {}\n",
+        if stabilizer_matches { "✓" } else { "✗" },
+        if async_matches { "✓" } else { "✗" },
+        if density_matches { "✓" } else { "✗" },
+        if ancilla_matches { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: format!("Initial state ({} qubits)", n),
+        num_qubits: n,
+        basic_time,
+        mt_time,
+        results_match: runtimes_match
+            && evolution_matches
+            && stabilizer_matches
+            && async_matches
+            && density_matches
+            && ancilla_matches,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+pub fn test_serialization(results: &mut Vec<BenchmarkResult>) {
+    print_section("Circuit Serialization");
+
+    let theta = Parameter::new("theta");
+    let bell = Arc::new(CustomGateBuilder::new("BELL", 2).h(0).cnot(0, 1).build());
+    let iswap = Arc::new(CustomGate::from_matrix(
+        "ISWAP",
+        gates::ISWAP.matrix.clone(),
+    ));
+    let mut noise = NoiseModel::new();
+    noise
+        .add_gate_noise(NoiseChannel::depolarising(0.01))
+        .add_gate_type_noise("cnot", NoiseChannel::bit_flip(0.02));
+    let mut circuit = QuantumCircuit::with_classical(4, 2);
+    circuit
+        .label_qubits("q", &[0, 1, 2, 3])
+        .custom(&bell, &[0, 2])
+        .custom(&iswap, &[1, 3])
+        .ry(1, Angle::Symbol(theta.clone() * 2.0))
+        .u3(3, 0.3, theta.clone(), -0.4)
+        .measure(0, 0)
+        .x(3)
+        .c_if(0, 1)
+        .section("end")
+        .snapshot(
+            "zx",
+            SnapshotKind::Expectation(vec![(0, Pauli::Z), (1, Pauli::X)]),
+        );
+    let started = {
+        let mut prepared = QuantumCircuit::new(4);
+        prepared.h(0).t(0).cnot(0, 1).ry(3, 0.9);
+        let state = prepared.compute_with(Runtime::BasicRT).clone();
+        (0..state.size()).map(|i| state.get(i)).collect::<Vec<_>>()
+    };
+    circuit.with_initial_state(&started).with_noise(noise);
+
+    let start = Instant::now();
+    let json = serde_json::to_string(&circuit).unwrap();
+    let restored: QuantumCircuit = serde_json::from_str(&json).unwrap();
+    let elapsed = start.elapsed();
+
+    let same_circuit = format!("{:?}", restored.operations())
+        == format!("{:?}", circuit.operations())
+        && restored.to_string() == circuit.to_string()
+        && serde_json::to_string(&restored).unwrap() == json
+        && restored.initial_state() == circuit.initial_state()
+        && format!("{:?}", restored.noise()) == format!("{:?}", circuit.noise())
+        && restored.parameters() == circuit.parameters();
+    let bindings = [("theta", 0.7)];
+    let same_state = states_equal(
+        circuit
+            .bind_parameters(&bindings)
+            .compute_with(Runtime::StructureAwareRT),
+        restored
+            .bind_parameters(&bindings)
+            .compute_with(Runtime::StructureAwareRT),
+    );
+    println!(
+        "{} operations round-trip through {} bytes of JSON: {}, same state: {}",
+        circuit.operations().len(),
+        json.len(),
+        if same_circuit { "✓" } else { "✗" },
+        if same_state { "✓" } else { "✗" }
+    );
+
+    let mut small = QuantumCircuit::with_classical(2, 1);
+    small.h(1).measure(1, 0);
+    let valid = serde_json::to_string(&small).unwrap();
+    let rejected = [
+        valid.replace("\"num_qubits\":2", "\"num_qubits\":1"),
+        valid.replace("\"num_classical\":1", "\"num_classical\":0"),
+    ]
+    .iter()
+    .all(|source| serde_json::from_str::<QuantumCircuit>(source).is_err());
+    println!(
+        "Out-of-range qubits and classical bits are rejected: {}\n",
+        if rejected { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: "Circuit serialization".to_string(),
+        num_qubits: 4,
+        basic_time: elapsed,
+        mt_time: elapsed,
+        results_match: same_circuit && same_state && rejected,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+pub fn test_state_files(results: &mut Vec<BenchmarkResult>) {
+    print_section("State Files");
+
+    let dir = std::env::temp_dir().join(format!("psi-states-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let bitwise = |a: &QuantumState, b: &QuantumState| {
+        a.size() == b.size()
+            && (0..a.size()).all(|i| {
+                let (x, y) = (a.get(i), b.get(i));
+                x.real.to_bits() == y.real.to_bits()
+                    && x.imaginary.to_bits() == y.imaginary.to_bits()
+            })
+    };
+
+    let n = 14;
+    let state = library::random_circuit(n, 6, 3542, library::GateSet::Universal)
+        .compute_with(Runtime::StructureAwareMT)
+        .clone();
+    let path = dir.join("random.psis");
+    let start = Instant::now();
+    state.save(&path).unwrap();
+    let loaded = QuantumState::load(&path).unwrap();
+    let elapsed = start.elapsed();
+    let size = fs::metadata(&path).unwrap().len();
+    let dense_matches = bitwise(&state, &loaded) && size == 10 + (16 << n);
+
+    let ghz = QuantumCircuit::ghz(n)
+        .compute_with(Runtime::StructureAwareRT)
+        .clone();
+    let compressed_path = dir.join("ghz.psis.z");
+    ghz.save_compressed(&compressed_path).unwrap();
+    let compressed_size = fs::metadata(&compressed_path).unwrap().len();
+    let compressed_matches =
+        bitwise(&ghz, &QuantumState::load(&compressed_path).unwrap()) && compressed_size < 1024;
+    println!(
+        "{}-qubit state: {} bytes, loads bit for bit: {}; compressed GHZ: {} bytes, loads bit for bit: {}",
+        n,
+        size,
+        if dense_matches { "✓" } else { "✗" },
+        compressed_size,
+        if compressed_matches { "✓" } else { "✗" }
+    );
+
+    let bytes = fs::read(&path).unwrap();
+    let corrupt = [
+        b"not a state file".to_vec(),
+        bytes[..bytes.len() - 1].to_vec(),
+        [&bytes[..], &[0]].concat(),
+        [&bytes[..6], &64u32.to_le_bytes(), &bytes[10..]].concat(),
+    ];
+    let rejected = corrupt.iter().enumerate().all(|(i, contents)| {
+        let corrupt_path = dir.join(format!("corrupt-{}.psis", i));
+        fs::write(&corrupt_path, contents).unwrap();
+        QuantumState::load(&corrupt_path)
+            .is_err_and(|err| err.kind() == std::io::ErrorKind::InvalidData)
+    });
+    println!(
+        "Foreign, truncated, padded and oversized files are rejected: {}\n",
+        if rejected { "✓" } else { "✗" }
+    );
+    fs::remove_dir_all(&dir).unwrap();
+
+    results.push(BenchmarkResult {
+        name: "State files".to_string(),
+        num_qubits: n,
+        basic_time: elapsed,
+        mt_time: elapsed,
+        results_match: dense_matches && compressed_matches && rejected,
+        peak_bytes: memory::take_peak(),
+    });
+}
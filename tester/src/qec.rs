@@ -0,0 +1,478 @@
+use crate::common::{print_circuit, print_section, BenchmarkResult};
+use crate::memory;
+use libpsi_core::{
+    CssCode, Decoder, LookupDecoder, MatchingDecoder, Pauli, QuantumCircuit, Runtime, SnapshotKind,
+    SnapshotValue, MAX_DEFECTS,
+};
+use std::time::Instant;
+
+pub fn run_all(results: &mut Vec<BenchmarkResult>) {
+    println!("═══════════════════════════════════════════════════════════════");
+    println!("                  ERROR CORRECTION TESTS");
+    println!("═══════════════════════════════════════════════════════════════\n");
+
+    for code in [
+        CssCode::repetition(3),
+        CssCode::steane(),
+        CssCode::surface(3),
+    ] {
+        test_encoding(results, &code);
+    }
+    test_memory_circuit(results);
+    test_syndrome_of_error(results);
+    test_single_errors_corrected(results);
+    test_matching_agrees_with_lookup(results);
+    test_detection_events(results);
+    test_logical_error_rate(results);
+}
+
+/// Expectations of `observables` after encoding the input prepared by
+/// `prepare` and then running `after` on the data.
+fn encoded_expectations(
+    code: &CssCode,
+    prepare: impl Fn(&mut QuantumCircuit, usize),
+    after: impl Fn(&mut QuantumCircuit, &[usize]),
+    observables: &[Vec<(usize, Pauli)>],
+) -> Vec<f64> {
+    let data: Vec<usize> = (0..code.num_data_qubits()).collect();
+    let mut circuit = QuantumCircuit::new(data.len());
+    prepare(&mut circuit, code.input_qubit());
+    code.encode(&mut circuit, &data);
+    after(&mut circuit, &data);
+    for (i, factors) in observables.iter().enumerate() {
+        circuit.snapshot(&i.to_string(), SnapshotKind::Expectation(factors.clone()));
+    }
+    circuit.compute_with(Runtime::SimdRT);
+    (0..observables.len())
+        .map(|i| match circuit.snapshot_value(&i.to_string()) {
+            Some(SnapshotValue::Expectation(e)) => *e,
+            _ => f64::NAN,
+        })
+        .collect()
+}
+
+fn flip(circuit: &mut QuantumCircuit, qubit: usize) {
+    circuit.x(qubit);
+}
+
+fn plus(circuit: &mut QuantumCircuit, qubit: usize) {
+    circuit.h(qubit);
+}
+
+fn all_near(values: &[f64], expected: f64) -> bool {
+    values.iter().all(|v| (v - expected).abs() < 1e-9)
+}
+
+pub fn test_encoding(results: &mut Vec<BenchmarkResult>, code: &CssCode) {
+    print_section(&format!("Encoding ({})", code.name()));
+
+    let n = code.num_data_qubits();
+    let data: Vec<usize> = (0..n).collect();
+    let mut encoder = QuantumCircuit::new(n);
+    code.encode(&mut encoder, &data);
+    print_circuit(&encoder);
+
+    let stabilizers = code.stabilizers(&data);
+    let logical_x = code.logical_x_paulis(&data);
+    let logical_z = code.logical_z_paulis(&data);
+    let nothing = |_: &mut QuantumCircuit, _: &[usize]| {};
+
+    let start = Instant::now();
+    let zero = encoded_expectations(code, |_, _| {}, nothing, &stabilizers);
+    let zero_z = encoded_expectations(code, |_, _| {}, nothing, std::slice::from_ref(&logical_z));
+    let one_z = encoded_expectations(code, flip, nothing, std::slice::from_ref(&logical_z));
+    let plus_x = encoded_expectations(code, plus, nothing, std::slice::from_ref(&logical_x));
+    let flipped_z = encoded_expectations(
+        code,
+        |_, _| {},
+        |c, d| code.apply_logical_x(c, d),
+        std::slice::from_ref(&logical_z),
+    );
+    let phased_x = encoded_expectations(
+        code,
+        plus,
+        |c, d| code.apply_logical_z(c, d),
+        std::slice::from_ref(&logical_x),
+    );
+    let elapsed = start.elapsed();
+
+    let checks = [
+        ("⟨S_i⟩ = +1 for every stabilizer", all_near(&zero, 1.0)),
+        ("|0⟩ encodes to ⟨Z_L⟩ = +1", all_near(&zero_z, 1.0)),
+        ("|1⟩ encodes to ⟨Z_L⟩ = -1", all_near(&one_z, -1.0)),
+        ("|+⟩ encodes to ⟨X_L⟩ = +1", all_near(&plus_x, 1.0)),
+        ("X_L flips ⟨Z_L⟩", all_near(&flipped_z, -1.0)),
+        ("Z_L flips ⟨X_L⟩", all_near(&phased_x, -1.0)),
+    ];
+    println!("{} stabilizers, {} data qubits", stabilizers.len(), n);
+    for (label, ok) in &checks {
+        println!("{}: {}", label, if *ok { "✓" } else { "✗" });
+    }
+    println!();
+
+    results.push(BenchmarkResult {
+        name: format!("QEC encoding ({})", code.name()),
+        num_qubits: n,
+        basic_time: elapsed,
+        mt_time: elapsed,
+        results_match: checks.iter().all(|(_, ok)| *ok),
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+/// Without noise every syndrome bit reads 0 and the data decodes to |0_L⟩.
+pub fn test_memory_circuit(results: &mut Vec<BenchmarkResult>) {
+    print_section("Memory Experiment (steane, 1 round)");
+
+    let code = CssCode::steane();
+    let m = code.num_stabilizers();
+    let mut circuit = code.memory_circuit(1);
+    print_circuit(&circuit);
+
+    let start = Instant::now();
+    circuit.compute_with(Runtime::SimdRT);
+    let counts = circuit.sample(200, Some(7));
+    let elapsed = start.elapsed();
+
+    let clean = counts.iter().all(|(outcome, _)| {
+        let bits = outcome.as_bytes();
+        let syndrome_zero = bits[..m].iter().all(|&b| b == b'0');
+        let parity = code
+            .logical_z()
+            .iter()
+            .filter(|&&q| bits[m + q] == b'1')
+            .count();
+        syndrome_zero && parity % 2 == 0
+    });
+    println!("Distinct outcomes: {}", counts.len());
+    println!(
+        "Trivial syndrome and even Z_L parity on every shot: {}\n",
+        if clean { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: "QEC memory (steane)".to_string(),
+        num_qubits: circuit.num_qubits(),
+        basic_time: elapsed,
+        mt_time: elapsed,
+        results_match: clean,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+/// A single X error between encoding and the syndrome round trips exactly
+/// the Z checks containing that qubit.
+pub fn test_syndrome_of_error(results: &mut Vec<BenchmarkResult>) {
+    print_section("Syndrome of a Bit Flip (surface-3)");
+
+    let code = CssCode::surface(3);
+    let n = code.num_data_qubits();
+    let m = code.num_stabilizers();
+    let x_count = code.x_stabilizers().len();
+    let data: Vec<usize> = (0..n).collect();
+    let ancillas: Vec<usize> = (n..n + m).collect();
+    let classical: Vec<usize> = (0..m).collect();
+
+    let start = Instant::now();
+    let mut all_match = true;
+    for flipped in 0..n {
+        let mut circuit = QuantumCircuit::with_classical(n + m, m);
+        code.encode(&mut circuit, &data);
+        circuit.x(flipped);
+        code.measure_syndrome(&mut circuit, &data, &ancillas, &classical);
+
+        circuit.compute_with(Runtime::SimdRT);
+        let counts = circuit.sample(20, Some(flipped as u64));
+        let expected: String = (0..m)
+            .map(|i| {
+                let fires = i >= x_count && code.z_stabilizers()[i - x_count].contains(&flipped);
+                if fires {
+                    '1'
+                } else {
+                    '0'
+                }
+            })
+            .collect();
+        let matches = counts.len() == 1 && counts.get(&expected) == 20;
+        println!(
+            "X on d{}: syndrome {} {}",
+            flipped,
+            expected,
+            if matches { "✓" } else { "✗" }
+        );
+        all_match &= matches;
+    }
+    let elapsed = start.elapsed();
+    println!();
+
+    results.push(BenchmarkResult {
+        name: "QEC syndrome (surface-3)".to_string(),
+        num_qubits: n + m,
+        basic_time: elapsed,
+        mt_time: elapsed,
+        results_match: all_match,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+/// Every single bit flip before the syndrome round is corrected, for the
+/// lookup decoder on all three codes and matching where it applies.
+pub fn test_single_errors_corrected(results: &mut Vec<BenchmarkResult>) {
+    print_section("Decoding Single Bit Flips");
+
+    let start = Instant::now();
+    let mut all_corrected = true;
+    for code in [
+        CssCode::repetition(3),
+        CssCode::steane(),
+        CssCode::surface(3),
+    ] {
+        let lookup = LookupDecoder::bit_flips(&code);
+        let matching = MatchingDecoder::bit_flips(&code).ok();
+        let mut decoders: Vec<(&str, &dyn Decoder)> = vec![("lookup", &lookup)];
+        if let Some(matching) = &matching {
+            decoders.push(("matching", matching));
+        }
+
+        for (name, decoder) in decoders {
+            let mut raw_errors = 0;
+            let mut logical_errors = 0;
+            for flipped in 0..code.num_data_qubits() {
+                let mut circuit = code.memory_circuit_with(1, |c, data, round| {
+                    if round == 0 {
+                        c.x(data[flipped]);
+                    }
+                });
+                circuit.compute_with(Runtime::SimdRT);
+                let counts = circuit.sample(50, Some(flipped as u64));
+                let stats = code
+                    .decode_memory(&counts, 1, decoder)
+                    .expect("single flips are decodable");
+                raw_errors += stats.raw_errors;
+                logical_errors += stats.logical_errors;
+            }
+            let corrected = logical_errors == 0 && raw_errors > 0;
+            println!(
+                "{:<13} {:<9} raw logical flips {:>4}, after decoding {} {}",
+                code.name(),
+                name,
+                raw_errors,
+                logical_errors,
+                if corrected { "✓" } else { "✗" }
+            );
+            all_corrected &= corrected;
+        }
+    }
+    let matching_rejects_steane = MatchingDecoder::bit_flips(&CssCode::steane()).is_err();
+    println!(
+        "Matching rejects the Steane code: {}\n",
+        if matching_rejects_steane {
+            "✓"
+        } else {
+            "✗"
+        }
+    );
+    let elapsed = start.elapsed();
+
+    results.push(BenchmarkResult {
+        name: "QEC single-flip decoding".to_string(),
+        num_qubits: 17,
+        basic_time: elapsed,
+        mt_time: elapsed,
+        results_match: all_corrected && matching_rejects_steane,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+/// Syndrome of a set of flipped qubits under `checks`.
+fn syndrome(checks: &[Vec<usize>], flips: &[usize]) -> Vec<bool> {
+    checks
+        .iter()
+        .map(|check| flips.iter().filter(|q| check.contains(q)).count() % 2 == 1)
+        .collect()
+}
+
+/// On graph-like codes both decoders find minimum-weight corrections, so
+/// for every syndrome they must explain it with equally many flips.
+pub fn test_matching_agrees_with_lookup(results: &mut Vec<BenchmarkResult>) {
+    print_section("Matching vs Lookup Decoder");
+
+    let start = Instant::now();
+    let mut all_agree = true;
+    for code in [
+        CssCode::repetition(7),
+        CssCode::surface(3),
+        CssCode::surface(5),
+    ] {
+        for (checks, lookup, matching) in [
+            (
+                code.z_stabilizers(),
+                LookupDecoder::bit_flips(&code),
+                MatchingDecoder::bit_flips(&code),
+            ),
+            (
+                code.x_stabilizers(),
+                LookupDecoder::phase_flips(&code),
+                MatchingDecoder::phase_flips(&code),
+            ),
+        ] {
+            let Ok(matching) = matching else {
+                all_agree = false;
+                continue;
+            };
+            let m = checks.len();
+            // Every syndrome for small codes, a sweep through them otherwise.
+            let step = (1usize << m).div_ceil(4096).max(1);
+            let agree = (0..1usize << m).step_by(step).all(|bits| {
+                let s: Vec<bool> = (0..m).map(|i| (bits >> i) & 1 == 1).collect();
+                let (Ok(a), Ok(b)) = (lookup.decode(&s), matching.decode(&s)) else {
+                    return false;
+                };
+                syndrome(checks, &a) == s && syndrome(checks, &b) == s && a.len() == b.len()
+            });
+            println!(
+                "{:<13} {} checks: {}",
+                code.name(),
+                m,
+                if agree { "✓" } else { "✗" }
+            );
+            all_agree &= agree;
+        }
+    }
+    let elapsed = start.elapsed();
+    println!();
+
+    results.push(BenchmarkResult {
+        name: "QEC matching vs lookup".to_string(),
+        num_qubits: 25,
+        basic_time: elapsed,
+        mt_time: elapsed,
+        results_match: all_agree,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+/// One flip before each of two rounds of the distance-3 repetition code:
+/// their final syndrome alone points at the middle qubit, a logical error,
+/// while the detection events of each round locate each flip. Also checks
+/// that matching refuses syndromes it cannot pair rather than guessing.
+pub fn test_detection_events(results: &mut Vec<BenchmarkResult>) {
+    print_section("Decoding Detection Events");
+
+    let start = Instant::now();
+    let code = CssCode::repetition(3);
+    let mut circuit = code.memory_circuit_with(2, |c, data, round| match round {
+        0 => {
+            c.x(data[0]);
+        }
+        1 => {
+            c.x(data[2]);
+        }
+        _ => {}
+    });
+    circuit.compute_with(Runtime::SimdRT);
+    let counts = circuit.sample(50, Some(3484));
+    let lookup = LookupDecoder::bit_flips(&code);
+    let matching = MatchingDecoder::bit_flips(&code).unwrap();
+    let mut corrected = true;
+    for (name, decoder) in [("lookup", &lookup as &dyn Decoder), ("matching", &matching)] {
+        let ok = code
+            .decode_memory(&counts, 2, decoder)
+            .is_ok_and(|stats| stats.raw_errors == 50 && stats.logical_errors == 0);
+        println!(
+            "{:<9} corrects flips in separate rounds: {}",
+            name,
+            if ok { "✓" } else { "✗" }
+        );
+        corrected &= ok;
+    }
+
+    let wide = MatchingDecoder::bit_flips(&CssCode::repetition(MAX_DEFECTS + 3)).unwrap();
+    let too_many = wide.decode(&[true; MAX_DEFECTS + 2]).is_err();
+    // A triangle of checks with no boundary: one flagged check cannot be
+    // paired with anything.
+    let triangle = MatchingDecoder::new(&[vec![0, 1], vec![1, 2], vec![0, 2]], 3).unwrap();
+    let unpaired = triangle.decode(&[true, false, false]).is_err();
+    let paired = triangle
+        .decode(&[true, true, false])
+        .is_ok_and(|q| q == [1]);
+    println!(
+        "Matching refuses {} defects and unpairable ones: {}\n",
+        MAX_DEFECTS + 2,
+        if too_many && unpaired && paired {
+            "✓"
+        } else {
+            "✗"
+        }
+    );
+    let elapsed = start.elapsed();
+
+    results.push(BenchmarkResult {
+        name: "QEC detection events".to_string(),
+        num_qubits: circuit.num_qubits(),
+        basic_time: elapsed,
+        mt_time: elapsed,
+        results_match: corrected && too_many && unpaired && paired,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+/// Rx(θ) on each data qubit flips it with probability sin²(θ/2); decoding
+/// the distance-5 repetition code leaves only the ≥3-flip events.
+pub fn test_logical_error_rate(results: &mut Vec<BenchmarkResult>) {
+    print_section("Logical Error Rate (repetition-5)");
+
+    let code = CssCode::repetition(5);
+    let theta = 0.6;
+    let p = (theta / 2.0_f64).sin().powi(2);
+    let mut circuit = code.memory_circuit_with(1, |c, data, round| {
+        if round == 0 {
+            for &q in data {
+                c.rx(q, theta);
+            }
+        }
+    });
+
+    let start = Instant::now();
+    circuit.compute_with(Runtime::SimdRT);
+    let counts = circuit.sample(4000, Some(11));
+    let stats = code
+        .decode_memory(&counts, 1, &MatchingDecoder::bit_flips(&code).unwrap())
+        .unwrap();
+    let elapsed = start.elapsed();
+
+    // P(at least 3 of 5 flip).
+    let expected: f64 = (3..=5)
+        .map(|k| {
+            let choose = [1.0, 5.0, 10.0, 10.0, 5.0, 1.0][k];
+            choose * p.powi(k as i32) * (1.0 - p).powi(5 - k as i32)
+        })
+        .sum();
+    println!("Physical flip probability: {:.4}", p);
+    println!(
+        "Flagged shots: {:.4}",
+        stats.flagged as f64 / stats.shots as f64
+    );
+    println!("Raw logical error rate:     {:.4}", stats.raw_error_rate());
+    println!(
+        "Decoded logical error rate: {:.4} (expected {:.4})",
+        stats.logical_error_rate(),
+        expected
+    );
+    let suppressed = stats.logical_error_rate() < stats.raw_error_rate()
+        && (stats.logical_error_rate() - expected).abs() < 0.01
+        && (stats.raw_error_rate() - p).abs() < 0.03;
+    println!(
+        "Decoding suppresses errors as predicted: {}\n",
+        if suppressed { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: "QEC logical error rate".to_string(),
+        num_qubits: circuit.num_qubits(),
+        basic_time: elapsed,
+        mt_time: elapsed,
+        results_match: suppressed,
+        peak_bytes: memory::take_peak(),
+    });
+}
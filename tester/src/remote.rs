@@ -0,0 +1,214 @@
+use crate::common::{print_section, BenchmarkResult};
+use crate::memory;
+use libpsi_core::{Counts, QuantumCircuit};
+use libpsi_remote::{HttpProvider, JobRequest, JobStatus, Provider, RemoteClient, RemoteError};
+use std::cell::RefCell;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const SEED: u64 = 7;
+
+pub fn run_all(results: &mut Vec<BenchmarkResult>) {
+    println!("═══════════════════════════════════════════════════════════════");
+    println!("                    REMOTE EXECUTION TESTS");
+    println!("═══════════════════════════════════════════════════════════════\n");
+
+    test_local_provider(results);
+    test_http_provider(results);
+}
+
+/// Stands in for hardware: parses the submitted QASM back and samples it
+/// locally, reporting each job as queued on its first poll.
+struct LocalProvider {
+    jobs: RefCell<Vec<(Counts, bool)>>,
+}
+
+impl Provider for LocalProvider {
+    fn name(&self) -> &str {
+        "local"
+    }
+
+    fn submit(&self, job: &JobRequest) -> Result<String, RemoteError> {
+        let mut circuit =
+            libpsi_qasm::parse(&job.qasm).map_err(|err| RemoteError::JobFailed(err.to_string()))?;
+        let counts = circuit.sample(job.shots, Some(SEED));
+        let mut jobs = self.jobs.borrow_mut();
+        jobs.push((counts, false));
+        Ok((jobs.len() - 1).to_string())
+    }
+
+    fn status(&self, job_id: &str) -> Result<JobStatus, RemoteError> {
+        let mut jobs = self.jobs.borrow_mut();
+        let index: usize = job_id
+            .parse()
+            .map_err(|_| RemoteError::Response(format!("bad job id '{}'", job_id)))?;
+        let (counts, polled) = jobs
+            .get_mut(index)
+            .ok_or_else(|| RemoteError::Response(format!("no job {}", job_id)))?;
+        if !*polled {
+            *polled = true;
+            return Ok(JobStatus::Queued);
+        }
+        Ok(JobStatus::Done(counts.clone()))
+    }
+}
+
+pub fn test_local_provider(results: &mut Vec<BenchmarkResult>) {
+    print_section("Remote Client (Local Provider)");
+
+    let client = RemoteClient::new(LocalProvider {
+        jobs: RefCell::new(Vec::new()),
+    })
+    .with_poll_interval(Duration::from_millis(1));
+
+    let mut bell = QuantumCircuit::new(2);
+    bell.h(0).cnot(0, 1);
+
+    let mut measured = QuantumCircuit::with_classical(3, 2);
+    measured
+        .h(0)
+        .cnot(0, 2)
+        .ry(1, 0.7)
+        .measure(2, 0)
+        .measure(1, 1);
+
+    let mut matched = true;
+    let mut local_time = Duration::ZERO;
+    let mut remote_time = Duration::ZERO;
+    for (name, circuit) in [
+        ("Bell (unmeasured)", bell),
+        ("Partial measurement", measured),
+    ] {
+        let mut circuit = circuit;
+        let start = Instant::now();
+        let local = circuit.sample(1000, Some(SEED));
+        local_time += start.elapsed();
+
+        let start = Instant::now();
+        let remote = client.run(&circuit, 1000);
+        remote_time += start.elapsed();
+
+        let same = remote.as_ref() == Ok(&local);
+        println!("{}: {}", name, if same { "✓" } else { "✗" });
+        if !same {
+            println!("  local:  {:?}", local.iter().collect::<Vec<_>>());
+            println!(
+                "  remote: {:?}",
+                remote.map(|c| c
+                    .iter()
+                    .map(|(b, n)| (b.to_string(), n))
+                    .collect::<Vec<_>>())
+            );
+        }
+        matched &= same;
+    }
+    println!();
+
+    results.push(BenchmarkResult {
+        name: "Remote: local provider".to_string(),
+        num_qubits: 3,
+        basic_time: local_time,
+        mt_time: remote_time,
+        results_match: matched,
+        peak_bytes: memory::take_peak(),
+    });
+}
+
+/// Answers one request per connection from `responses` in order, returning
+/// the request lines it saw.
+fn serve(listener: TcpListener, responses: Vec<String>) -> thread::JoinHandle<Vec<String>> {
+    thread::spawn(move || {
+        let mut seen = Vec::new();
+        for body in responses {
+            let Ok((stream, _)) = listener.accept() else {
+                break;
+            };
+            let mut reader = BufReader::new(stream);
+            let mut request_line = String::new();
+            let _ = reader.read_line(&mut request_line);
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n" {
+                    break;
+                }
+                let lower = line.to_ascii_lowercase();
+                if let Some(value) = lower.strip_prefix("content-length:") {
+                    content_length = value.trim().parse().unwrap_or(0);
+                }
+            }
+            let mut request_body = vec![0; content_length];
+            let _ = reader.read_exact(&mut request_body);
+            seen.push(request_line.trim_end().to_string());
+
+            let mut stream = reader.into_inner();
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+        }
+        seen
+    })
+}
+
+pub fn test_http_provider(results: &mut Vec<BenchmarkResult>) {
+    print_section("Remote Client (HTTP Provider)");
+
+    let listener = match TcpListener::bind("127.0.0.1:0") {
+        Ok(listener) => listener,
+        Err(err) => {
+            println!("Skipped: cannot listen on localhost ({})\n", err);
+            return;
+        }
+    };
+    let base_url = format!("http://{}/v1", listener.local_addr().unwrap());
+    let server = serve(
+        listener,
+        vec![
+            r#"{"id": "job-1"}"#.to_string(),
+            r#"{"status": "running"}"#.to_string(),
+            r#"{"status": "done", "counts": {"01": 700, "00": 300}}"#.to_string(),
+        ],
+    );
+
+    let client = RemoteClient::new(HttpProvider::new(base_url).with_reversed_bits())
+        .with_poll_interval(Duration::from_millis(1));
+    let mut circuit = QuantumCircuit::new(2);
+    circuit.ry(0, 1.98);
+
+    let start = Instant::now();
+    let counts = client.run(&circuit, 1000);
+    let elapsed = start.elapsed();
+    let requests = server.join().unwrap_or_default();
+
+    println!("Requests: {:?}", requests);
+    let expected = Counts::from_outcomes([("10", 700), ("00", 300)]);
+    let matched = counts.as_ref() == Ok(&expected)
+        && requests
+            == [
+                "POST /v1/jobs HTTP/1.1",
+                "GET /v1/jobs/job-1 HTTP/1.1",
+                "GET /v1/jobs/job-1 HTTP/1.1",
+            ];
+    match &counts {
+        Ok(counts) => println!("{}", counts),
+        Err(err) => println!("Error: {}", err),
+    }
+    println!(
+        "Counts in psi bit order: {}\n",
+        if matched { "✓" } else { "✗" }
+    );
+
+    results.push(BenchmarkResult {
+        name: "Remote: HTTP provider".to_string(),
+        num_qubits: 2,
+        basic_time: elapsed,
+        mt_time: elapsed,
+        results_match: matched,
+        peak_bytes: memory::take_peak(),
+    });
+}
@@ -1,7 +1,10 @@
-use crate::common::{print_section, states_equal, BenchmarkResult};
-use libpsi_core::{get_simd_info, QuantumCircuit, Runtime};
+use crate::common::{print_section, states_equal, BenchmarkResult, CircuitBuilder};
+use crate::memory;
+use libpsi_core::{
+    fidelity, get_simd_info, library, Precision, QuantumCircuit, Runtime, RuntimeConfig,
+};
 use std::f64::consts::PI;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 pub fn run_all(results: &mut Vec<BenchmarkResult>) {
     println!("═══════════════════════════════════════════════════════════════");
@@ -13,12 +16,14 @@ pub fn run_all(results: &mut Vec<BenchmarkResult>) {
     test_simd_correctness(results);
     test_simd_vs_batched(results);
     test_simd_large_circuits(results);
+    test_single_precision(results);
+    test_two_qubit_kernels(results);
 }
 
 pub fn test_simd_correctness(results: &mut Vec<BenchmarkResult>) {
     print_section("SIMD Correctness Verification");
 
-    let test_cases: Vec<(&str, Box<dyn Fn() -> QuantumCircuit>)> = vec![
+    let test_cases: Vec<(&str, CircuitBuilder)> = vec![
         (
             "Bell State",
             Box::new(|| {
@@ -78,9 +83,11 @@ pub fn test_simd_correctness(results: &mut Vec<BenchmarkResult>) {
 
         results.push(BenchmarkResult {
             name: format!("SIMD verify: {}", name),
+            num_qubits: basic.num_qubits(),
             basic_time: std::time::Duration::from_micros(0),
             mt_time: std::time::Duration::from_micros(0),
             results_match: match_result,
+            peak_bytes: memory::take_peak(),
         });
     }
     println!();
@@ -89,7 +96,7 @@ pub fn test_simd_correctness(results: &mut Vec<BenchmarkResult>) {
 pub fn test_simd_vs_batched(results: &mut Vec<BenchmarkResult>) {
     print_section("SIMD vs Batched Runtime Comparison");
 
-    let test_cases: Vec<(&str, Box<dyn Fn() -> QuantumCircuit>)> = vec![
+    let test_cases: Vec<(&str, CircuitBuilder)> = vec![
         (
             "Single-Qubit Heavy (6q)",
             Box::new(|| {
@@ -149,9 +156,11 @@ pub fn test_simd_vs_batched(results: &mut Vec<BenchmarkResult>) {
 
         results.push(BenchmarkResult {
             name: format!("SIMD: {}", name),
+            num_qubits: simd.num_qubits(),
             basic_time: batched_time,
             mt_time: simd_time,
             results_match: match_result,
+            peak_bytes: memory::take_peak(),
         });
     }
     println!();
@@ -201,9 +210,173 @@ pub fn test_simd_large_circuits(results: &mut Vec<BenchmarkResult>) {
 
         results.push(BenchmarkResult {
             name: format!("{}-qubit SIMD", n),
+            num_qubits: n,
             basic_time: batched_time,
             mt_time: simd_time,
             results_match: match_result,
+            peak_bytes: memory::take_peak(),
+        });
+    }
+    println!();
+}
+
+pub fn test_single_precision(results: &mut Vec<BenchmarkResult>) {
+    print_section("Single Precision (f32)");
+
+    for n in [8, 14, 20] {
+        let circuit = library::random_circuit(n, 12, 3543 + n as u64, library::GateSet::Universal);
+        let double = RuntimeConfig::optimal();
+        let single = RuntimeConfig::optimal().precision(Precision::F32);
+
+        let start = Instant::now();
+        let expected = circuit.clone().compute_with_config(double).clone();
+        let double_time = start.elapsed();
+        let start = Instant::now();
+        let state = circuit.clone().compute_with_config(single).clone();
+        let single_time = start.elapsed();
+        // The sequential and per-gate paths round the same way.
+        let sequential = circuit
+            .clone()
+            .compute_with_config(RuntimeConfig::new().simd().precision(Precision::F32))
+            .clone();
+
+        let infidelity = (1.0 - fidelity(&state, &expected)).abs();
+        let sequential_infidelity = (1.0 - fidelity(&sequential, &expected)).abs();
+        let matches = infidelity < 1e-5 && sequential_infidelity < 1e-5;
+        println!(
+            "{}-qubit: f64={:.3}ms, f32={:.3}ms, Speedup={:.2}x, 1 - F={:.1e}, Match={}",
+            n,
+            double_time.as_secs_f64() * 1000.0,
+            single_time.as_secs_f64() * 1000.0,
+            double_time.as_secs_f64() / single_time.as_secs_f64(),
+            infidelity.max(sequential_infidelity),
+            if matches { "✓" } else { "✗" }
+        );
+
+        results.push(BenchmarkResult {
+            name: format!("{}-qubit f32", n),
+            num_qubits: n,
+            basic_time: double_time,
+            mt_time: single_time,
+            results_match: matches,
+            peak_bytes: memory::take_peak(),
+        });
+    }
+    println!();
+}
+
+pub fn test_two_qubit_kernels(results: &mut Vec<BenchmarkResult>) {
+    print_section("Two-Qubit SIMD Kernels");
+
+    // Qubits 0 and 1 give runs longer than a parallel task, 12 and 13 runs
+    // of one or two amplitudes; every order of every pair is tried.
+    let n = 14;
+    let qubits = [0, 1, 7, 12, 13];
+    let all: Vec<usize> = (0..n).collect();
+    for name in ["CNOT", "CZ", "SWAP", "CP", "fused"] {
+        let mut matches = true;
+        for &a in &qubits {
+            for &b in qubits.iter().filter(|&&b| b != a) {
+                let mut circuit = QuantumCircuit::new(n);
+                for q in 0..n {
+                    circuit.ry(q, 0.2 + 0.1 * q as f64).rz(q, 0.5 * q as f64);
+                }
+                // Keep the preparation out of the gate's kernel.
+                circuit.barrier(&all);
+                match name {
+                    "CNOT" => circuit.cnot(a, b),
+                    "CZ" => circuit.cz(a, b),
+                    "SWAP" => circuit.swap(a, b),
+                    "CP" => circuit.cp(a, b, 0.7),
+                    _ => circuit
+                        .ry(a, 0.3)
+                        .rx(b, 1.1)
+                        .cnot(a, b)
+                        .rz(b, 0.4)
+                        .cp(b, a, 2.1)
+                        .h(a),
+                };
+                let expected = circuit.clone().compute_with(Runtime::BatchedRT).clone();
+                for config in [
+                    RuntimeConfig::new().batched().simd(),
+                    RuntimeConfig::new()
+                        .batched()
+                        .simd()
+                        .parallel()
+                        .with_threshold(0),
+                    RuntimeConfig::new()
+                        .batched()
+                        .simd()
+                        .precision(Precision::F32),
+                ] {
+                    let f32 = config.precision == Precision::F32;
+                    let label = config.to_string();
+                    let state = circuit.clone().compute_with_config(config).clone();
+                    let ok = if f32 {
+                        (1.0 - fidelity(&state, &expected)).abs() < 1e-5
+                    } else {
+                        states_equal(&state, &expected)
+                    };
+                    if !ok {
+                        println!("  {} on ({}, {}) differs under {}", name, a, b, label);
+                    }
+                    matches &= ok;
+                }
+            }
+        }
+        println!(
+            "{} on {} target pairs: Match={}",
+            name,
+            qubits.len() * (qubits.len() - 1),
+            if matches { "✓" } else { "✗" }
+        );
+        results.push(BenchmarkResult {
+            name: format!("2q SIMD {}", name),
+            num_qubits: n,
+            basic_time: Duration::ZERO,
+            mt_time: Duration::ZERO,
+            results_match: matches,
+            peak_bytes: memory::take_peak(),
+        });
+    }
+
+    // Entangling layers only, so every kernel is a two-qubit one.
+    for n in [16, 20] {
+        let mut circuit = QuantumCircuit::new(n);
+        for q in 0..n {
+            circuit.h(q);
+        }
+        for layer in 0..12 {
+            for q in (layer % 2..n - 1).step_by(2) {
+                match layer % 3 {
+                    0 => circuit.cnot(q, q + 1),
+                    1 => circuit.cp(q + 1, q, 0.1 * layer as f64),
+                    _ => circuit.cz(q, q + 1),
+                };
+            }
+        }
+        let start = Instant::now();
+        let expected = circuit.clone().compute_with(Runtime::BatchedRT).clone();
+        let batched_time = start.elapsed();
+        let start = Instant::now();
+        let state = circuit.clone().compute_with(Runtime::SimdRT).clone();
+        let simd_time = start.elapsed();
+        let matches = states_equal(&state, &expected);
+        println!(
+            "{}-qubit entangling layers: Batched={:.3}ms, SIMD={:.3}ms, Speedup={:.2}x, Match={}",
+            n,
+            batched_time.as_secs_f64() * 1000.0,
+            simd_time.as_secs_f64() * 1000.0,
+            batched_time.as_secs_f64() / simd_time.as_secs_f64(),
+            if matches { "✓" } else { "✗" }
+        );
+        results.push(BenchmarkResult {
+            name: format!("{}-qubit 2q SIMD", n),
+            num_qubits: n,
+            basic_time: batched_time,
+            mt_time: simd_time,
+            results_match: matches,
+            peak_bytes: memory::take_peak(),
         });
     }
     println!();